@@ -14,8 +14,14 @@ warnings.filterwarnings('ignore')
 
 def validate_csv_columns(df):
     """Validate that required columns exist in the CSV file"""
+    # Files written before grids could be rectangular have one grid_size column
+    if 'grid_size' in df.columns:
+        for col in ['grid_width', 'grid_height']:
+            if col not in df.columns:
+                df[col] = df['grid_size']
+
     required_columns = [
-        'algorithm', 'grid_size', 'num_walls', 'num_obstacles', 'success',
+        'algorithm', 'grid_width', 'grid_height', 'num_walls', 'num_obstacles', 'success',
         'total_moves', 'optimal_path_length', 'route_efficiency',
         'execution_time_ms', 'average_find_path_time_ns', 'total_pathfinding_calls'
     ]
@@ -40,7 +46,9 @@ def calculate_metrics(df):
     df = df[df['algorithm'].isin(['a_star', 'd_star_lite'])]
 
     # Calculate density metrics
-    df['grid_area'] = df['grid_size'] ** 2
+    df['grid_area'] = df['grid_width'] * df['grid_height']
+    # Side of the square grid with the same area, so rectangular grids group by size too
+    df['grid_size'] = np.sqrt(df['grid_area']).round().astype(int)
     df['obstacle_density'] = df['num_obstacles'] / df['grid_area']
     df['wall_density'] = df['num_walls'] / df['grid_area']
     df['total_density'] = (df['num_obstacles'] + df['num_walls']) / df['grid_area']
@@ -0,0 +1,99 @@
+//! Wall checks and copies of the flat cell buffer and wall bitset of `Grid`, against the cells
+//! indexed as `cells[z][x][y]` in nested vectors that the grid kept before. Run with
+//! `cargo bench --bench grid_storage`.
+
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use dynamic_pathfinding::grid::{Cell, Grid, Position};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::collections::VecDeque;
+
+/// Side of the square grid the searches run on
+const SIZE: usize = 500;
+
+/// A grid with a fifth of its cells walls, drawn from a fixed seed
+fn walled_grid() -> Grid {
+    let mut grid = Grid::empty(SIZE, SIZE, 1);
+    let mut rng = StdRng::seed_from_u64(7);
+    for _ in 0..SIZE * SIZE / 5 {
+        let pos = Position {
+            x: rng.gen_range(0..SIZE),
+            y: rng.gen_range(0..SIZE),
+            z: 0,
+        };
+        grid.set_cell(pos, Cell::Wall);
+    }
+    grid.set_cell(grid.start, Cell::Empty);
+    grid
+}
+
+/// The cells of `grid` in the nested layout, `cells[z][x][y]`
+fn nested_cells(grid: &Grid) -> Vec<Vec<Vec<Cell>>> {
+    (0..grid.floors)
+        .map(|z| {
+            (0..grid.width)
+                .map(|x| {
+                    (0..grid.height)
+                        .map(|y| grid.cell(Position { x, y, z }))
+                        .collect()
+                })
+                .collect()
+        })
+        .collect()
+}
+
+/// Breadth-first search over every cell reachable from the start, the way planners
+/// expand cells: four wall checks per cell reached. Returns the cells reached.
+fn flood(grid: &Grid, is_wall: impl Fn(Position) -> bool) -> usize {
+    let mut reached = vec![false; grid.cell_count()];
+    let mut queue = VecDeque::from([grid.start]);
+    reached[grid.index(grid.start)] = true;
+    let mut count = 0;
+    while let Some(pos) = queue.pop_front() {
+        count += 1;
+        for (dx, dy) in [(0, 1), (0, -1), (1, 0), (-1, 0)] {
+            let Some(next) = grid.offset(pos, dx, dy) else {
+                continue;
+            };
+            let index = grid.index(next);
+            if !reached[index] && !is_wall(next) {
+                reached[index] = true;
+                queue.push_back(next);
+            }
+        }
+    }
+    count
+}
+
+fn wall_checks(c: &mut Criterion) {
+    let grid = walled_grid();
+    let nested = nested_cells(&grid);
+    let mut group = c.benchmark_group("flood 500x500");
+    group.bench_function("flat wall bitset", |b| {
+        b.iter(|| flood(black_box(&grid), |pos| grid.is_wall(pos)))
+    });
+    group.bench_function("flat cells", |b| {
+        b.iter(|| flood(black_box(&grid), |pos| grid.cell(pos) == Cell::Wall))
+    });
+    group.bench_function("nested vectors", |b| {
+        b.iter(|| {
+            flood(black_box(&grid), |pos| {
+                nested[pos.z][pos.x][pos.y] == Cell::Wall
+            })
+        })
+    });
+    group.finish();
+}
+
+/// Copies of the grid, which simulations make of the grid they start from
+fn copies(c: &mut Criterion) {
+    let grid = walled_grid();
+    let nested = nested_cells(&grid);
+    let mut group = c.benchmark_group("copy 500x500");
+    group.bench_function("flat grid", |b| b.iter(|| black_box(&grid).clone()));
+    group.bench_function("nested vectors", |b| b.iter(|| black_box(&nested).clone()));
+    group.finish();
+}
+
+criterion_group!(benches, wall_checks, copies);
+criterion_main!(benches);
@@ -1,11 +1,35 @@
+use crate::algorithms::common::ObstacleChanges;
 use crate::grid::{Cell, Grid, Position};
-use std::collections::HashSet;
+use crate::knowledge::{Knowledge, Memory};
+use crate::vision::{SensorNoise, Vision};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::collections::{HashMap, HashSet};
 
 pub struct Agent {
     pub position: Position,
     pub known_obstacles: HashSet<Position>,
     pub current_path: Option<Vec<Position>>,
     pub path_index: usize,
+    /// The map as the agent knows it in `explore` mode, with the walls it has not
+    /// seen yet left open; in the other modes it plans on the real grid
+    pub belief: Option<Grid>,
+    /// Cells of `known_obstacles` that only hold moving obstacles in view (or where
+    /// they are expected next), forgotten at the next observation
+    mover_cells: HashSet<Position>,
+    /// How far the agent sees; without it, only the cells it can step to next
+    vision: Option<Vision>,
+    knowledge: Knowledge,
+    memory: Memory,
+    /// How firmly each remembered obstacle is held with a `ttl` or `decay` memory:
+    /// the steps left, or the confidence; forgotten below 0.5
+    recall: HashMap<Position, f64>,
+    /// Wrong readings the agent's sensor makes, drawn from its own generator
+    noise: Option<(SensorNoise, StdRng)>,
+    /// Cells the agent's methods added to and removed from `known_obstacles` since
+    /// `take_obstacle_changes`
+    added_obstacles: HashSet<Position>,
+    removed_obstacles: HashSet<Position>,
 }
 
 impl Agent {
@@ -15,16 +39,289 @@ impl Agent {
             known_obstacles: HashSet::new(),
             current_path: None,
             path_index: 0,
+            belief: None,
+            mover_cells: HashSet::new(),
+            vision: None,
+            knowledge: Knowledge::Map,
+            memory: Memory::Forever,
+            recall: HashMap::new(),
+            noise: None,
+            added_obstacles: HashSet::new(),
+            removed_obstacles: HashSet::new(),
         }
     }
 
-    pub fn observe(&mut self, grid: &Grid) {
-        // Observe obstacles around agent (within observation range)
-        for neighbor in grid.get_neighbors(&self.position) {
-            if grid.cells[neighbor.x][neighbor.y] == Cell::Obstacle {
-                self.known_obstacles.insert(neighbor);
+    /// Make the agent's readings noisy, with wrong readings drawn from `seed`
+    pub fn with_noise(mut self, noise: Option<SensorNoise>, seed: u64) -> Self {
+        self.noise = noise.map(|noise| (noise, StdRng::seed_from_u64(seed)));
+        self
+    }
+
+    /// Set how the agent forgets the obstacles it has seen
+    pub fn with_memory(mut self, memory: Memory) -> Self {
+        self.memory = memory;
+        self
+    }
+
+    /// Set what the agent knows of `grid` from the start; exploring agents begin
+    /// with a belief grid without walls
+    pub fn with_knowledge(mut self, knowledge: Knowledge, grid: &Grid) -> Self {
+        self.knowledge = knowledge;
+        self.belief = (knowledge == Knowledge::Explore).then(|| {
+            let mut belief = grid.clone();
+            belief.replace_cells(Cell::Wall, Cell::Empty);
+            belief
+        });
+        self
+    }
+
+    /// The grid to plan on: the belief grid when exploring, otherwise `grid` itself
+    pub fn planning_grid<'a>(&'a self, grid: &'a Grid) -> &'a Grid {
+        self.belief.as_ref().unwrap_or(grid)
+    }
+
+    /// Set how far the agent sees (`None`: only the cells it can step to next)
+    pub fn with_vision(mut self, vision: Option<Vision>) -> Self {
+        self.vision = vision;
+        self
+    }
+
+    /// Cells the agent can currently see obstacles on
+    pub fn visible_cells(&self, grid: &Grid) -> Vec<Position> {
+        if self.knowledge == Knowledge::Full {
+            return (0..grid.floors)
+                .flat_map(|z| {
+                    (0..grid.width)
+                        .flat_map(move |x| (0..grid.height).map(move |y| Position { x, y, z }))
+                })
+                .collect();
+        }
+        match self.vision {
+            Some(vision) => vision.visible_cells(grid, self.position),
+            None => grid.get_neighbors(&self.position),
+        }
+    }
+
+    /// Observe the obstacles in view and forget the ones the memory model lets go.
+    /// Returns whether any known obstacle was forgotten, which may open a shorter path.
+    pub fn observe(&mut self, grid: &Grid) -> bool {
+        let mut forgotten = false;
+        // With full knowledge, obstacles are forgotten as soon as they are gone
+        if self.knowledge == Knowledge::Full {
+            let gone: Vec<Position> = self
+                .known_obstacles
+                .iter()
+                .filter(|&&pos| {
+                    !self.mover_cells.contains(&pos) && grid.cell(pos) != Cell::Obstacle
+                })
+                .copied()
+                .collect();
+            for pos in gone {
+                forgotten |= self.forget(pos);
             }
         }
+
+        // Observe obstacles around agent (within observation range), in a fixed order
+        // so a noisy sensor draws the same numbers for the same cells every run
+        let mut visible = self.visible_cells(grid);
+        visible.sort();
+        visible.dedup();
+        let mut sighted = HashSet::new();
+        for pos in visible {
+            if self.mover_cells.contains(&pos) {
+                continue;
+            }
+            if self.sense(grid, pos) {
+                self.remember(pos);
+                sighted.insert(pos);
+            } else if self.memory == Memory::Reobserve {
+                forgotten |= self.forget(pos);
+            }
+        }
+
+        // Obstacles out of view (or seen gone) fade from a `ttl` or `decay` memory
+        let memory = self.memory;
+        let mut faded = Vec::new();
+        for (pos, strength) in &mut self.recall {
+            if sighted.contains(pos) {
+                continue;
+            }
+            match memory {
+                Memory::Ttl(_) => *strength -= 1.0,
+                Memory::Decay(rate) => *strength *= 1.0 - rate,
+                Memory::Forever | Memory::Reobserve => {}
+            }
+            if *strength < 0.5 {
+                faded.push(*pos);
+            }
+        }
+        for pos in faded {
+            self.recall.remove(&pos);
+            forgotten |= self.forget(pos);
+        }
+        forgotten
+    }
+
+    /// Add `pos` to the known obstacles, returning whether it is new to them
+    fn learn(&mut self, pos: Position) -> bool {
+        let learned = self.known_obstacles.insert(pos);
+        if learned && !self.removed_obstacles.remove(&pos) {
+            self.added_obstacles.insert(pos);
+        }
+        learned
+    }
+
+    /// Take `pos` off the known obstacles, returning whether it was one of them
+    fn forget(&mut self, pos: Position) -> bool {
+        let forgotten = self.known_obstacles.remove(&pos);
+        if forgotten && !self.added_obstacles.remove(&pos) {
+            self.removed_obstacles.insert(pos);
+        }
+        forgotten
+    }
+
+    /// The cells that became known obstacles and the ones that stopped being known
+    /// since the last call, for planners that update their plans incrementally.
+    /// Changes made to `known_obstacles` directly are not included.
+    pub fn take_obstacle_changes(&mut self) -> ObstacleChanges {
+        let mut added: Vec<Position> = self.added_obstacles.drain().collect();
+        let mut removed: Vec<Position> = self.removed_obstacles.drain().collect();
+        added.sort_unstable();
+        removed.sort_unstable();
+        ObstacleChanges { added, removed }
+    }
+
+    /// Whether the agent sees an obstacle on a cell in view, which with sensor noise
+    /// is sometimes wrong
+    fn sense(&mut self, grid: &Grid, pos: Position) -> bool {
+        let obstacle = grid.cell(pos) == Cell::Obstacle;
+        let Some((noise, rng)) = &mut self.noise else {
+            return obstacle;
+        };
+        if obstacle {
+            !rng.gen_bool(noise.miss_rate)
+        } else {
+            // Never on a target, or the agent could never reach it
+            !grid.is_wall(pos)
+                && !grid.is_goal(pos)
+                && !grid.waypoints.contains(&pos)
+                && rng.gen_bool(noise.false_alarm_rate)
+        }
+    }
+
+    /// Add an obstacle to the known ones, refreshing it in a `ttl` or `decay` memory
+    fn remember(&mut self, pos: Position) {
+        self.learn(pos);
+        match self.memory {
+            Memory::Ttl(steps) => {
+                self.recall.insert(pos, steps as f64);
+            }
+            Memory::Decay(_) => *self.recall.entry(pos).or_insert(0.0) += 1.0,
+            Memory::Forever | Memory::Reobserve => {}
+        }
+    }
+
+    /// Check the next step for obstacles the agent failed to notice: bumping into one
+    /// stops the agent and teaches it the obstacle is there. Returns whether the
+    /// agent was stopped.
+    pub fn bump(&mut self, grid: &Grid) -> bool {
+        let Some(next) = self.get_next_step() else {
+            return false;
+        };
+        let is_obstacle = |pos: Position| grid.cell(pos) == Cell::Obstacle;
+        if grid.can_move(self.position, next, is_obstacle) {
+            return false;
+        }
+        // The obstacle is on the next cell or, for a diagonal step, a corner it squeezes past
+        let from = self.position;
+        let mut stopped = false;
+        for pos in [
+            next,
+            Position { x: next.x, ..from },
+            Position { y: next.y, ..from },
+        ] {
+            if is_obstacle(pos) && !grid.can_move(from, next, |blocked| blocked == pos) {
+                self.remember(pos);
+                stopped = true;
+            }
+        }
+        stopped
+    }
+
+    /// Known obstacles that are no longer there; moving obstacles the agent is
+    /// tracking do not count
+    pub fn stale_beliefs(&self, grid: &Grid) -> usize {
+        self.known_obstacles
+            .iter()
+            .filter(|&&pos| !self.mover_cells.contains(&pos) && grid.cell(pos) != Cell::Obstacle)
+            .count()
+    }
+
+    /// Observe the moving obstacles around the agent, given as (current, previous)
+    /// cell pairs for every moving obstacle. Unlike other obstacles they are forgotten
+    /// once they move on. With `predict_steps`, the cells each one will reach if it
+    /// keeps going the same way are avoided too. Returns whether the known obstacles
+    /// changed; call this before `observe`.
+    pub fn observe_movers(
+        &mut self,
+        grid: &Grid,
+        movers: &[(Position, Position)],
+        predict_steps: usize,
+    ) -> bool {
+        let in_view = self.visible_cells(grid);
+        let mut cells = HashSet::new();
+        for &(current, previous) in movers {
+            if !in_view.contains(&current) {
+                continue;
+            }
+            cells.insert(current);
+            let (dx, dy) = grid.metric().offset(previous, current);
+            if (dx, dy) == (0, 0) || current.z != previous.z {
+                continue;
+            }
+            let mut ahead = current;
+            for _ in 0..predict_steps {
+                match grid.offset(ahead, dx, dy) {
+                    Some(next) if !grid.is_wall(next) => ahead = next,
+                    _ => break,
+                }
+                // The agent's own cell and the goals stay open, or no path could be found
+                if ahead != self.position && !grid.is_goal(ahead) {
+                    cells.insert(ahead);
+                }
+            }
+        }
+
+        let previous = std::mem::take(&mut self.mover_cells);
+        for &pos in &previous {
+            self.forget(pos);
+        }
+        for pos in cells {
+            if self.learn(pos) {
+                self.mover_cells.insert(pos);
+            }
+        }
+        self.mover_cells != previous
+    }
+
+    /// Update the belief grid with the walls in view that have appeared or
+    /// disappeared since the agent last looked, returning the cells that changed, or
+    /// `None` when the agent is not exploring. The cells next to the agent are always
+    /// checked, so it never walks into an unseen wall.
+    pub fn observe_walls(&mut self, grid: &Grid) -> Option<Vec<Position>> {
+        let mut seen = self.visible_cells(grid);
+        let belief = self.belief.as_mut()?;
+        seen.extend(belief.get_neighbors(&self.position));
+
+        let mut changed = Vec::new();
+        for pos in seen {
+            let wall = grid.is_wall(pos);
+            if wall != (belief.is_wall(pos)) && !changed.contains(&pos) {
+                belief.set_cell(pos, if wall { Cell::Wall } else { Cell::Empty });
+                changed.push(pos);
+            }
+        }
+        Some(changed)
     }
 
     /// Move along the current path (more efficient than recalculating every step)
@@ -69,9 +366,10 @@ impl Agent {
 
     pub fn is_path_blocked(&self, grid: &Grid) -> bool {
         if let Some(next_pos) = self.get_next_step() {
-            // Check if next step is blocked
-            grid.cells[next_pos.x][next_pos.y] == Cell::Obstacle
-                || grid.cells[next_pos.x][next_pos.y] == Cell::Wall
+            // Check if next step is blocked (for a diagonal step, also its corners)
+            !grid.can_move(self.position, next_pos, |pos| {
+                grid.cell(pos) == Cell::Obstacle
+            })
         } else {
             false
         }
@@ -84,11 +382,15 @@ impl Agent {
             let check_ahead = 3.min(path.len().saturating_sub(self.path_index + 1));
             for i in 1..=check_ahead {
                 if self.path_index + i < path.len() {
-                    let pos = path[self.path_index + i];
-                    if grid.cells[pos.x][pos.y] == Cell::Obstacle
-                        || grid.cells[pos.x][pos.y] == Cell::Wall
-                        || self.known_obstacles.contains(&pos)
-                    {
+                    // Diagonal steps are also blocked by the corners they squeeze past
+                    let (from, pos) = (path[self.path_index + i - 1], path[self.path_index + i]);
+                    // Past a planned wait, the path is timed around obstacles going away
+                    if from == pos {
+                        break;
+                    }
+                    if !self.planning_grid(grid).can_move(from, pos, |p| {
+                        grid.cell(p) == Cell::Obstacle || self.known_obstacles.contains(&p)
+                    }) {
                         return true;
                     }
                 }
@@ -1,16 +1,153 @@
-use crate::algorithms::common::PathfindingAlgorithm;
-use crate::grid::{Grid, Position, Cell};
+use crate::algorithms::common::{
+    AbortHandle, EnvironmentChanges, ObstacleLifetimes, PathfindingAlgorithm, SearchMetrics,
+    SearchTrace,
+};
+use crate::algorithms::landmarks::LandmarkHeuristic;
+use crate::algorithms::search_space::{GridSpace, ObstacleCost, SearchSpace};
+use crate::grid::{Grid, Position};
 use pathfinding::prelude::astar;
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 
 /// Implements the A* pathfinding algorithm using the `pathfinding` crate.
 #[derive(Default)]
-pub struct AStar;
+pub struct AStar {
+    landmarks: Option<LandmarkHeuristic>,
+    /// Whether paths may wait in place for an obstacle to expire
+    wait: bool,
+    /// Steps each known obstacle that is going to expire still stays
+    lifetimes: ObstacleLifetimes,
+    /// How obstacles that are going to expire are priced
+    obstacle_cost: ObstacleCost,
+    metrics: SearchMetrics,
+    /// What the latest call's searches did, once tracing
+    trace: Option<SearchTrace>,
+    abort: AbortHandle,
+}
 
 impl AStar {
     /// Creates a new instance of the A* algorithm provider.
     pub fn new() -> Self {
-        AStar
+        AStar::default()
+    }
+
+    /// Creates an A* provider that uses the ALT landmark heuristic instead of plain Manhattan distance.
+    pub fn with_landmarks(landmarks: LandmarkHeuristic) -> Self {
+        AStar {
+            landmarks: Some(landmarks),
+            ..AStar::default()
+        }
+    }
+
+    /// Lets paths wait in place in front of an obstacle that is about to expire when
+    /// that is cheaper than the detour around it.
+    pub fn with_waiting(mut self, wait: bool) -> Self {
+        self.wait = wait;
+        self
+    }
+
+    /// Prices obstacles that are going to expire by `obstacle_cost`. Time-aware
+    /// pricing makes one search weigh every such obstacle against its detour, and
+    /// takes the place of the two searches of `with_waiting`.
+    pub fn with_obstacle_cost(mut self, obstacle_cost: ObstacleCost) -> Self {
+        self.obstacle_cost = obstacle_cost;
+        self
+    }
+
+    /// `search_counted` on the grid, also recording the search in `trace`, if tracing
+    fn search_traced(
+        space: &GridSpace,
+        start: Position,
+        goals: &[Position],
+        metrics: &mut SearchMetrics,
+        trace: Option<&mut SearchTrace>,
+        abort: &AbortHandle,
+    ) -> Option<Vec<Position>> {
+        let Some(trace) = trace else {
+            return Self::search_counted(space, start, goals, metrics, abort);
+        };
+        let observe = |node, successors: &[(Position, u32)]| {
+            trace.expanded.push(node);
+            trace
+                .open
+                .extend(successors.iter().map(|&(successor, _)| successor));
+        };
+        let path = Self::search_observed(space, start, goals, metrics, abort, observe);
+        let expanded: HashSet<Position> = trace.expanded.iter().copied().collect();
+        trace.open.retain(|pos| !expanded.contains(pos));
+        path
+    }
+
+    /// Finds a cheapest path from `start` to the closest of `goals` in any search space.
+    ///
+    /// The heuristic is the smallest estimate over all goals, which stays admissible
+    /// because the true cost to the nearest goal is at least that minimum.
+    pub fn search<S: SearchSpace>(
+        space: &S,
+        start: S::Node,
+        goals: &[S::Node],
+    ) -> Option<Vec<S::Node>> {
+        Self::search_counted(
+            space,
+            start,
+            goals,
+            &mut SearchMetrics::default(),
+            &AbortHandle::default(),
+        )
+    }
+
+    /// `search`, counting its expansions in `metrics` and giving up (with no path)
+    /// once `abort` is aborted
+    pub fn search_counted<S: SearchSpace>(
+        space: &S,
+        start: S::Node,
+        goals: &[S::Node],
+        metrics: &mut SearchMetrics,
+        abort: &AbortHandle,
+    ) -> Option<Vec<S::Node>> {
+        Self::search_observed(space, start, goals, metrics, abort, |_, _| {})
+    }
+
+    /// `search_counted`, showing `observe` each node expanded and its successors
+    pub fn search_observed<S: SearchSpace>(
+        space: &S,
+        start: S::Node,
+        goals: &[S::Node],
+        metrics: &mut SearchMetrics,
+        abort: &AbortHandle,
+        mut observe: impl FnMut(S::Node, &[(S::Node, u32)]),
+    ) -> Option<Vec<S::Node>> {
+        let result = astar(
+            &start,
+            |&node| {
+                // An aborted search expands nothing more, so the open list runs dry
+                if abort.is_aborted_after(metrics.nodes_expanded) {
+                    return Vec::new();
+                }
+                // Successors are neighbors whose edge can currently be traversed.
+                let successors = metrics.expanded(
+                    space
+                        .neighbors(node)
+                        .into_iter()
+                        .filter_map(|successor| {
+                            space.cost(node, successor).map(|cost| (successor, cost))
+                        })
+                        .collect::<Vec<_>>(),
+                );
+                observe(node, &successors);
+                successors
+            },
+            |&node| {
+                goals
+                    .iter()
+                    .map(|&goal| space.heuristic(node, goal))
+                    .min()
+                    .unwrap_or(0)
+            },
+            |node| goals.contains(node), // Success condition: we've reached any goal.
+        );
+
+        // The result from `astar` is a tuple `(path, cost)`. We only need the path.
+        result.map(|(path, _)| path)
     }
 }
 
@@ -34,28 +171,102 @@ impl PathfindingAlgorithm for AStar {
         goal: Position,
         obstacles: &HashSet<Position>,
     ) -> Option<Vec<Position>> {
-        let result = astar(
-            &start,
-            |p| {
-                // Successors are valid neighbors that are not known obstacles.
-                grid.get_neighbors(p)
-                    .into_iter()
-                    .filter(|neighbor| {
-                        // The agent can't move through walls or known dynamic obstacles.
-                        grid.cells[neighbor.x][neighbor.y] != Cell::Wall && !obstacles.contains(neighbor)
-                    })
-                    .map(|successor| (successor, 1)) // Cost of moving to a neighbor is 1.
-                    .collect::<Vec<_>>()
-            },
-            |p| {
-                // Heuristic: Manhattan distance to the goal.
-                ((p.x as i32 - goal.x as i32).abs() + (p.y as i32 - goal.y as i32).abs()) as u32
-            },
-            |p| *p == goal, // Success condition: we've reached the goal.
+        self.find_path_to_any(grid, start, &[goal], obstacles)
+    }
+
+    /// Finds a path to the closest of several goals.
+    fn find_path_to_any(
+        &mut self,
+        grid: &Grid,
+        start: Position,
+        goals: &[Position],
+        obstacles: &HashSet<Position>,
+    ) -> Option<Vec<Position>> {
+        if let Some(landmarks) = &mut self.landmarks {
+            landmarks.prepare(grid);
+        }
+
+        if let Some(trace) = &mut self.trace {
+            *trace = SearchTrace::default();
+        }
+
+        // Walls and known dynamic obstacles can't be entered (unless they are priced
+        // by how long they stay); each move costs 1.
+        let space = GridSpace::new(grid, obstacles)
+            .with_landmarks(self.landmarks.as_ref())
+            .with_lifetimes(self.obstacle_cost.lifetimes(&self.lifetimes));
+        let detour = Self::search_traced(
+            &space,
+            start,
+            goals,
+            &mut self.metrics,
+            self.trace.as_mut(),
+            &self.abort,
         );
+        if self.obstacle_cost == ObstacleCost::TimeAware {
+            return detour.map(|path| self.lifetimes.wait_for(grid, &path));
+        }
+        if !self.wait || self.lifetimes.is_empty() {
+            return detour;
+        }
 
-        // The result from `astar` is a tuple `(path, cost)`. We only need the path.
-        result.map(|(path, _)| path)
+        // Also plan straight through the obstacles that are going to expire, waiting
+        // for each one to go, and keep whichever path is cheaper (the detour on a tie)
+        let lasting: HashSet<Position> = obstacles
+            .iter()
+            .filter(|&&pos| !self.lifetimes.contains(pos))
+            .copied()
+            .collect();
+        let space = GridSpace::new(grid, &lasting).with_landmarks(self.landmarks.as_ref());
+        let waiting = Self::search_traced(
+            &space,
+            start,
+            goals,
+            &mut self.metrics,
+            self.trace.as_mut(),
+            &self.abort,
+        )
+        .map(|path| self.lifetimes.wait_for(grid, &path));
+        match (detour, waiting) {
+            (Some(detour), Some(waiting)) if grid.path_cost(&waiting) < grid.path_cost(&detour) => {
+                Some(waiting)
+            }
+            (detour, waiting) => detour.or(waiting),
+        }
+    }
+
+    fn search_metrics(&self) -> SearchMetrics {
+        self.metrics
+    }
+
+    fn trace_searches(&mut self) {
+        self.trace.get_or_insert_with(SearchTrace::default);
+    }
+
+    fn search_trace(&self, _grid: &Grid) -> Option<SearchTrace> {
+        self.trace.clone()
+    }
+
+    fn set_abort_handle(&mut self, abort: AbortHandle) {
+        self.abort = abort;
+    }
+
+    fn update_environment(
+        &mut self,
+        _grid: &Grid,
+        _obstacles: &HashSet<Position>,
+        changes: &EnvironmentChanges,
+    ) {
+        // Landmark distances are computed around walls
+        if let Some(landmarks) = &mut self.landmarks {
+            if !changes.walls.is_empty() {
+                landmarks.invalidate();
+            }
+        }
+    }
+
+    fn update_obstacle_lifetimes(&mut self, lifetimes: &HashMap<Position, usize>) {
+        self.lifetimes.update(lifetimes);
     }
 
     fn as_any_mut(&mut self) -> &mut dyn std::any::Any {
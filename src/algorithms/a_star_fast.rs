@@ -0,0 +1,188 @@
+use crate::algorithms::common::{AbortHandle, PathfindingAlgorithm, SearchMetrics};
+use crate::grid::{Grid, Position};
+use std::any::Any;
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashSet};
+
+/// A* on the simulation grid that keeps its search buffers between calls.
+///
+/// `AStar` hands each search to the `pathfinding` crate, which allocates a new open
+/// list and table of visited cells on every call. Replanning calls it over and over
+/// on the same grid, so this planner keeps flat vectors sized to the grid instead,
+/// indexed by `Grid::index`, along with the open list and a neighbor buffer. A cell
+/// counts as reached only if its mark is the current search's, so starting a search
+/// never clears the vectors; they are only resized when the grid's size changes.
+///
+/// Paths cost the same as `AStar`'s, though on ties the two may pick different ones:
+/// of the open cells with the lowest f, the one closest to a goal is expanded first,
+/// and of those the one that has been open longest. Breaking the last ties by the
+/// cells' indices (or by the newest cell) widens the search over plateaus of equal f
+/// on grids with walls, expanding several times as many cells as `AStar`.
+#[derive(Default)]
+pub struct AStarFast {
+    /// Cost of the cheapest way from the start found to each cell
+    g_scores: Vec<u32>,
+    /// Index of the cell each cell was reached from
+    parents: Vec<usize>,
+    /// The search that last reached each cell; any other mark means unreached
+    marks: Vec<u32>,
+    /// Mark of the current search
+    search: u32,
+    /// (f, h, push, index) of the open cells, lowest f first; entries whose g has
+    /// since been lowered are stale and skipped
+    open: BinaryHeap<Reverse<(u32, u32, usize, usize)>>,
+    /// Entries pushed onto the open list in this search
+    pushes: usize,
+    /// Neighbors of the cell being expanded
+    neighbors: Vec<Position>,
+    metrics: SearchMetrics,
+    abort: AbortHandle,
+}
+
+impl AStarFast {
+    /// Creates an A* planner whose buffers are sized on its first search.
+    pub fn new() -> Self {
+        AStarFast::default()
+    }
+
+    /// Size the buffers for `cell_count` cells, forgetting every earlier search
+    fn resize(&mut self, cell_count: usize) {
+        self.g_scores = vec![u32::MAX; cell_count];
+        self.parents = vec![usize::MAX; cell_count];
+        self.marks = vec![0; cell_count];
+        self.search = 0;
+    }
+
+    /// Start a new search: a new mark, so every cell reads as unreached
+    fn begin(&mut self, grid: &Grid) {
+        if self.marks.len() != grid.cell_count() {
+            self.resize(grid.cell_count());
+        }
+        if self.search == u32::MAX {
+            // Old marks could be taken for the new search's once they wrap around
+            self.marks.fill(0);
+            self.search = 0;
+        }
+        self.search += 1;
+        self.open.clear();
+        self.pushes = 0;
+    }
+
+    /// Lowest cost reaching `index` has been found to take so far in this search
+    fn g(&self, index: usize) -> u32 {
+        match self.marks[index] == self.search {
+            true => self.g_scores[index],
+            false => u32::MAX,
+        }
+    }
+
+    /// Record that `index` is reached for `g` from `parent` and put it on the open list
+    fn reach(&mut self, index: usize, g: u32, h: u32, parent: usize) {
+        self.marks[index] = self.search;
+        self.g_scores[index] = g;
+        self.parents[index] = parent;
+        self.pushes += 1;
+        self.open
+            .push(Reverse((g.saturating_add(h), h, self.pushes, index)));
+        self.metrics.pushed(self.open.len());
+    }
+
+    /// Cheapest path from `start` to the closest of `goals` around the walls and
+    /// `obstacles`, or `None` when there is none or the search was aborted
+    fn search(
+        &mut self,
+        grid: &Grid,
+        start: Position,
+        goals: &[Position],
+        obstacles: &HashSet<Position>,
+    ) -> Option<Vec<Position>> {
+        self.begin(grid);
+        let heuristic = |pos: Position| {
+            goals
+                .iter()
+                .map(|&goal| grid.heuristic(pos, goal))
+                .min()
+                .unwrap_or(0)
+        };
+        let start_index = grid.index(start);
+        self.reach(start_index, 0, heuristic(start), usize::MAX);
+
+        let mut neighbors = std::mem::take(&mut self.neighbors);
+        let mut found = None;
+        while let Some(Reverse((f, h, _, index))) = self.open.pop() {
+            self.metrics.popped();
+            if f - h > self.g(index) {
+                continue; // Reached more cheaply since this entry was pushed
+            }
+            let pos = grid.position(index);
+            if goals.contains(&pos) {
+                found = Some(index);
+                break;
+            }
+            // An aborted search stops expanding and returns no path
+            if self.abort.is_aborted_after(self.metrics.nodes_expanded) {
+                break;
+            }
+            self.metrics.nodes_expanded += 1;
+
+            let g = f - h;
+            grid.neighbors_into(&pos, &mut neighbors);
+            for &next in &neighbors {
+                if !grid.can_move(pos, next, |cell| obstacles.contains(&cell)) {
+                    continue;
+                }
+                self.metrics.nodes_generated += 1;
+                let next_g = g.saturating_add(grid.move_cost(pos, next));
+                let next_index = grid.index(next);
+                if next_g < self.g(next_index) {
+                    self.reach(next_index, next_g, heuristic(next), index);
+                }
+            }
+        }
+        self.neighbors = neighbors;
+
+        let mut index = found?;
+        let mut path = vec![grid.position(index)];
+        while index != start_index {
+            index = self.parents[index];
+            path.push(grid.position(index));
+        }
+        path.reverse();
+        Some(path)
+    }
+}
+
+impl PathfindingAlgorithm for AStarFast {
+    fn find_path(
+        &mut self,
+        grid: &Grid,
+        start: Position,
+        goal: Position,
+        obstacles: &HashSet<Position>,
+    ) -> Option<Vec<Position>> {
+        self.search(grid, start, &[goal], obstacles)
+    }
+
+    /// Finds a path to the closest of several goals, in one search.
+    fn find_path_to_any(
+        &mut self,
+        grid: &Grid,
+        start: Position,
+        goals: &[Position],
+        obstacles: &HashSet<Position>,
+    ) -> Option<Vec<Position>> {
+        self.search(grid, start, goals, obstacles)
+    }
+
+    fn search_metrics(&self) -> SearchMetrics {
+        self.metrics
+    }
+
+    fn set_abort_handle(&mut self, abort: AbortHandle) {
+        self.abort = abort;
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
@@ -0,0 +1,341 @@
+use crate::algorithms::common::{
+    AbortHandle, EnvironmentChanges, KnownObstacles, PathfindingAlgorithm, SearchMetrics,
+};
+use crate::grid::{Grid, Metric, Position};
+use std::any::Any;
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap, HashSet};
+use std::time::Duration;
+use web_time::Instant;
+
+/// Heuristic inflation used for the first, quick search after the environment changes.
+const INITIAL_EPSILON: f64 = 3.0;
+/// Amount epsilon is reduced by after each completed improvement pass.
+const EPSILON_STEP: f64 = 0.5;
+/// Fixed-point scale for priority keys so fractional epsilons order correctly.
+const KEY_SCALE: f64 = 1000.0;
+
+/// Implements Anytime Repairing A* (ARA*) from Likhachev, Gordon and Thrun (2003).
+///
+/// The search runs backwards from the goal so that g-values are costs-to-goal and
+/// stay valid while the agent walks along the path. The first search uses a large
+/// heuristic inflation to return a path quickly; every later pass lowers epsilon and
+/// reuses previous work to tighten the path until it is optimal (epsilon = 1).
+/// Improvement passes are bounded by the per-step planning budget and resume where
+/// they left off on the next call.
+pub struct AraStar {
+    plan_budget: Duration,
+    epsilon: f64,
+    /// Epsilon of the last completed pass, i.e. the bound on the published path.
+    path_epsilon: f64,
+    improving: bool,
+    initialized: bool,
+    search_start: Position,
+    goal: Position,
+    metric: Metric,
+    /// Portals of the grid, which the heuristic takes into account
+    portals: HashMap<Position, Position>,
+    obstacles: KnownObstacles,
+    g_scores: HashMap<Position, u32>,
+    next_step: HashMap<Position, Position>,
+    open: HashSet<Position>,
+    closed: HashSet<Position>,
+    incons: HashSet<Position>,
+    queue: BinaryHeap<Reverse<(u64, Position, u32)>>,
+    metrics: SearchMetrics,
+    abort: AbortHandle,
+}
+
+impl AraStar {
+    /// Creates a new ARA* planner that spends at most `plan_budget_ms` improving per call.
+    pub fn new(plan_budget_ms: u64) -> Self {
+        let origin = Position { x: 0, y: 0, z: 0 };
+        AraStar {
+            plan_budget: Duration::from_millis(plan_budget_ms),
+            epsilon: INITIAL_EPSILON,
+            path_epsilon: INITIAL_EPSILON,
+            improving: false,
+            initialized: false,
+            search_start: origin,
+            goal: origin,
+            metric: Metric::MANHATTAN,
+            portals: HashMap::new(),
+            obstacles: KnownObstacles::default(),
+            g_scores: HashMap::new(),
+            next_step: HashMap::new(),
+            open: HashSet::new(),
+            closed: HashSet::new(),
+            incons: HashSet::new(),
+            queue: BinaryHeap::new(),
+            metrics: SearchMetrics::default(),
+            abort: AbortHandle::default(),
+        }
+    }
+
+    /// Suboptimality bound of the most recently published path.
+    pub fn current_epsilon(&self) -> f64 {
+        self.path_epsilon
+    }
+
+    /// Grid distance to the search start (Manhattan, octile or hex, through portals
+    /// where shorter)
+    fn h(&self, pos: Position) -> u32 {
+        self.metric
+            .distance_via(pos, self.search_start, &self.portals)
+    }
+
+    fn g(&self, pos: Position) -> u32 {
+        self.g_scores.get(&pos).copied().unwrap_or(u32::MAX)
+    }
+
+    /// fvalue(s) = g(s) + epsilon * h(s), in fixed-point units.
+    fn key(&self, pos: Position) -> u64 {
+        (self.g(pos) as f64 * KEY_SCALE + self.epsilon * KEY_SCALE * self.h(pos) as f64) as u64
+    }
+
+    fn push_open(&mut self, pos: Position) {
+        self.open.insert(pos);
+        self.queue.push(Reverse((self.key(pos), pos, self.g(pos))));
+        self.metrics.pushed(self.queue.len());
+    }
+
+    /// Rebuild the priority queue after epsilon or the search start has changed.
+    fn rebuild_queue(&mut self) {
+        self.queue.clear();
+        let open: Vec<Position> = self.open.iter().copied().collect();
+        for pos in open {
+            self.queue.push(Reverse((self.key(pos), pos, self.g(pos))));
+            self.metrics.pushed(self.queue.len());
+        }
+    }
+
+    /// Start a fresh search rooted at the goal, discarding all previous work.
+    fn reset(
+        &mut self,
+        grid: &Grid,
+        start: Position,
+        goal: Position,
+        obstacles: &HashSet<Position>,
+    ) {
+        self.metric = grid.metric();
+        self.portals = grid.portals.clone();
+        self.epsilon = INITIAL_EPSILON;
+        self.path_epsilon = INITIAL_EPSILON;
+        self.search_start = start;
+        self.goal = goal;
+        self.obstacles.reset(obstacles);
+        self.g_scores.clear();
+        self.next_step.clear();
+        self.open.clear();
+        self.closed.clear();
+        self.incons.clear();
+        self.queue.clear();
+
+        self.g_scores.insert(goal, 0);
+        self.push_open(goal);
+        self.improving = true;
+        self.initialized = true;
+    }
+
+    /// Move the heuristic target to the agent's new position, keeping all g-values.
+    fn move_start(&mut self, start: Position) {
+        if self.search_start != start {
+            self.search_start = start;
+            self.rebuild_queue();
+            // The new start may not have been reached yet; make sure the pass continues.
+            if self.g(start) == u32::MAX {
+                self.improving = true;
+            }
+        }
+    }
+
+    fn is_blocked(&self, grid: &Grid, pos: Position) -> bool {
+        grid.is_wall(pos) || self.obstacles.contains(&pos)
+    }
+
+    /// procedure ImprovePath() - expand until the start is epsilon-consistent, the deadline
+    /// passes or the search is aborted. Returns true when the pass completed.
+    fn improve_path(&mut self, grid: &Grid, deadline: Option<Instant>) -> bool {
+        while let Some(&Reverse((key, pos, g_at_push))) = self.queue.peek() {
+            // Skip entries that were superseded by a cheaper g-value or already expanded
+            if g_at_push != self.g(pos) || self.closed.contains(&pos) || !self.open.contains(&pos) {
+                self.queue.pop();
+                self.metrics.popped();
+                continue;
+            }
+
+            if self.key(self.search_start) <= key && self.g(self.search_start) != u32::MAX {
+                return true;
+            }
+
+            if let Some(deadline) = deadline {
+                if Instant::now() >= deadline {
+                    return false;
+                }
+            }
+            if self.abort.is_aborted_after(self.metrics.nodes_expanded) {
+                return false;
+            }
+
+            self.queue.pop();
+            self.metrics.popped();
+            self.open.remove(&pos);
+            self.closed.insert(pos);
+            self.metrics.nodes_expanded += 1;
+
+            let g_pos = self.g(pos);
+            for neighbor in grid.get_neighbors(&pos) {
+                // Backward search: the agent would move from `neighbor` into `pos`
+                if self.is_blocked(grid, neighbor)
+                    || !grid.can_move(neighbor, pos, |p| self.obstacles.contains(&p))
+                {
+                    continue;
+                }
+                self.metrics.nodes_generated += 1;
+                let candidate = g_pos.saturating_add(grid.move_cost(neighbor, pos));
+                if candidate < self.g(neighbor) {
+                    self.g_scores.insert(neighbor, candidate);
+                    self.next_step.insert(neighbor, pos);
+                    if self.closed.contains(&neighbor) {
+                        self.incons.insert(neighbor);
+                    } else {
+                        self.push_open(neighbor);
+                    }
+                }
+            }
+        }
+
+        // OPEN exhausted: either the start was reached or no path exists
+        true
+    }
+
+    /// Run improvement passes until epsilon reaches 1 or the deadline passes.
+    /// Returns true if at least one pass completed.
+    fn improve(&mut self, grid: &Grid, deadline: Option<Instant>) -> bool {
+        let mut completed_any = false;
+        loop {
+            if !self.improving {
+                if self.epsilon <= 1.0 {
+                    return completed_any;
+                }
+                // Decrease epsilon, move INCONS into OPEN and clear CLOSED
+                self.epsilon = (self.epsilon - EPSILON_STEP).max(1.0);
+                let incons: Vec<Position> = self.incons.drain().collect();
+                self.open.extend(incons);
+                self.closed.clear();
+                self.rebuild_queue();
+                self.improving = true;
+            }
+
+            if !self.improve_path(grid, deadline) {
+                return completed_any;
+            }
+            self.improving = false;
+            self.path_epsilon = self.epsilon;
+            completed_any = true;
+        }
+    }
+
+    /// Follow next-step pointers from the start to the goal.
+    fn extract_path(&self, grid: &Grid) -> Option<Vec<Position>> {
+        if self.g(self.search_start) == u32::MAX {
+            return None;
+        }
+
+        let mut path = vec![self.search_start];
+        let mut current = self.search_start;
+        while current != self.goal {
+            current = *self.next_step.get(&current)?;
+            path.push(current);
+
+            // Safety check for infinite loops
+            if path.len() > grid.cell_count() {
+                return None;
+            }
+        }
+        Some(path)
+    }
+}
+
+impl PathfindingAlgorithm for AraStar {
+    fn find_path(
+        &mut self,
+        grid: &Grid,
+        start: Position,
+        goal: Position,
+        obstacles: &HashSet<Position>,
+    ) -> Option<Vec<Position>> {
+        let obstacles_changed = !self.obstacles.catch_up(obstacles).is_empty();
+        if !self.initialized || self.goal != goal || obstacles_changed {
+            self.reset(grid, start, goal, obstacles);
+        } else {
+            self.move_start(start);
+        }
+
+        // The first pass is unbounded so that a path is always returned if one exists,
+        // unless it is aborted; the next call carries on with it
+        if self.improving {
+            if !self.improve_path(grid, None) {
+                return None;
+            }
+            self.improving = false;
+            self.path_epsilon = self.epsilon;
+        }
+
+        // Spend the rest of the budget tightening the bound
+        let deadline = Instant::now() + self.plan_budget;
+        self.improve(grid, Some(deadline));
+
+        self.extract_path(grid)
+    }
+
+    fn refine_path(
+        &mut self,
+        grid: &Grid,
+        start: Position,
+        goal: Position,
+        obstacles: &HashSet<Position>,
+    ) -> Option<Vec<Position>> {
+        // Environment changes are handled by a full find_path call
+        if !self.initialized || self.goal != goal || self.obstacles.cells() != obstacles {
+            return None;
+        }
+
+        self.move_start(start);
+        if !self.improving && self.epsilon <= 1.0 {
+            return None;
+        }
+
+        let deadline = Instant::now() + self.plan_budget;
+        if self.improve(grid, Some(deadline)) {
+            self.extract_path(grid)
+        } else {
+            None
+        }
+    }
+
+    fn update_environment(
+        &mut self,
+        _grid: &Grid,
+        obstacles: &HashSet<Position>,
+        changes: &EnvironmentChanges,
+    ) {
+        // The search tree may run through (or around) the changed cells; start over
+        self.obstacles.update(changes);
+        if !self.obstacles.catch_up(obstacles).is_empty() || !changes.walls.is_empty() {
+            self.initialized = false;
+        }
+    }
+
+    fn search_metrics(&self) -> SearchMetrics {
+        self.metrics
+    }
+
+    fn set_abort_handle(&mut self, abort: AbortHandle) {
+        self.abort = abort;
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
@@ -1,6 +1,398 @@
 use crate::grid::{Grid, Position};
-use std::collections::HashSet;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
 use std::any::Any;
+use std::collections::{HashMap, HashSet};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use web_time::Instant;
+
+/// Expansions a search makes between two looks at its abort handle's deadline
+const DEADLINE_CHECK_INTERVAL: usize = 64;
+
+/// What a planner's searches did, summed over every call since it was created
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct SearchMetrics {
+    /// Nodes taken off the open list and expanded
+    pub nodes_expanded: usize,
+    /// Successors generated while expanding them
+    pub nodes_generated: usize,
+    /// Most entries the open list held at once
+    pub max_open_list: usize,
+    /// Pushes onto and pops off the open list
+    pub heap_operations: usize,
+}
+
+impl SearchMetrics {
+    /// Count the expansion of a node into `successors`. Searches the `pathfinding`
+    /// crate runs are counted this way only: their open list is out of sight, so
+    /// they leave `max_open_list` and `heap_operations` at 0
+    pub fn expanded<T>(&mut self, successors: Vec<T>) -> Vec<T> {
+        self.nodes_expanded += 1;
+        self.nodes_generated += successors.len();
+        successors
+    }
+
+    /// Count a push onto an open list that now holds `open` entries
+    pub fn pushed(&mut self, open: usize) {
+        self.heap_operations += 1;
+        self.max_open_list = self.max_open_list.max(open);
+    }
+
+    /// Count a pop off the open list
+    pub fn popped(&mut self) {
+        self.heap_operations += 1;
+    }
+
+    /// Add up the metrics of two planners (or searches), keeping the larger open list
+    pub fn merge(self, other: SearchMetrics) -> SearchMetrics {
+        SearchMetrics {
+            nodes_expanded: self.nodes_expanded + other.nodes_expanded,
+            nodes_generated: self.nodes_generated + other.nodes_generated,
+            max_open_list: self.max_open_list.max(other.max_open_list),
+            heap_operations: self.heap_operations + other.heap_operations,
+        }
+    }
+}
+
+/// What a planner's latest search did on the grid, for `--debug-planner` to draw
+#[derive(Debug, Clone, Default)]
+pub struct SearchTrace {
+    /// Cells the search expanded, in the order it expanded them
+    pub expanded: Vec<Position>,
+    /// Cells it generated but never expanded: A*'s open list when it stopped
+    pub open: HashSet<Position>,
+    /// g and rhs of every cell with either finite, for planners that keep them
+    /// between searches (D* Lite); `i32::MAX` is infinite
+    pub costs: HashMap<Position, (i32, i32)>,
+}
+
+/// Asks planners to give up the search they are running, returning no path: a
+/// search checks the handle every few expansions. Clones share the handle, so one
+/// kept by the simulation (or a server, or a batch) aborts the planner holding the
+/// other. An aborted handle stays aborted, and aborts every later search, until it
+/// is `reset`.
+#[derive(Debug, Clone, Default)]
+pub struct AbortHandle {
+    state: Arc<AbortState>,
+    /// Handle whose abort aborts this one too
+    parent: Option<Arc<AbortHandle>>,
+}
+
+#[derive(Debug, Default)]
+struct AbortState {
+    aborted: AtomicBool,
+    deadline: Mutex<Option<Instant>>,
+}
+
+impl AbortHandle {
+    pub fn new() -> Self {
+        AbortHandle::default()
+    }
+
+    /// A handle of its own that is also aborted when this one is, such as one run's
+    /// handle under the handle of the whole batch
+    pub fn child(&self) -> Self {
+        AbortHandle {
+            state: Arc::default(),
+            parent: Some(Arc::new(self.clone())),
+        }
+    }
+
+    /// Abort the searches now
+    pub fn abort(&self) {
+        self.state.aborted.store(true, Ordering::Relaxed);
+    }
+
+    /// Abort the searches once `deadline` passes, or at the handle's earlier
+    /// deadline
+    pub fn abort_at(&self, deadline: Instant) {
+        if let Ok(mut current) = self.state.deadline.lock() {
+            *current = Some(current.map_or(deadline, |current| current.min(deadline)));
+        }
+    }
+
+    /// Let searches run again, with no deadline (a parent's abort still counts)
+    pub fn reset(&self) {
+        self.state.aborted.store(false, Ordering::Relaxed);
+        if let Ok(mut deadline) = self.state.deadline.lock() {
+            *deadline = None;
+        }
+    }
+
+    /// Let searches run again for `budget` from now, such as one search's share of
+    /// a real-time step
+    pub fn renew(&self, budget: Duration) {
+        self.reset();
+        self.abort_at(Instant::now() + budget);
+    }
+
+    /// Whether searches should give up: the handle (or its parent) was aborted, or
+    /// its deadline has passed
+    pub fn is_aborted(&self) -> bool {
+        if self.state.aborted.load(Ordering::Relaxed) {
+            return true;
+        }
+        let passed = self
+            .state
+            .deadline
+            .lock()
+            .is_ok_and(|deadline| deadline.is_some_and(|deadline| Instant::now() >= deadline));
+        if passed {
+            self.abort();
+        }
+        passed
+            || self
+                .parent
+                .as_ref()
+                .is_some_and(|parent| parent.is_aborted())
+    }
+
+    /// `is_aborted` for a search that has made `expansions` expansions: the clock is
+    /// read only every few expansions, so checking costs next to nothing
+    pub fn is_aborted_after(&self, expansions: usize) -> bool {
+        self.state.aborted.load(Ordering::Relaxed)
+            || (expansions.is_multiple_of(DEADLINE_CHECK_INTERVAL) && self.is_aborted())
+    }
+}
+
+/// What changed in the environment since a planner's last `update_environment`
+#[derive(Debug, Clone, Default)]
+pub struct EnvironmentChanges {
+    /// Cells that became walls or stopped being walls; the grid already reflects
+    /// the change
+    pub walls: Vec<Position>,
+    /// The obstacles that appeared and went away, for callers that keep track of
+    /// them. `None` leaves planners to compare the obstacle set with the last one
+    /// they saw.
+    pub obstacles: Option<ObstacleChanges>,
+}
+
+impl EnvironmentChanges {
+    /// Changes to the walls only, from a caller that does not keep track of its
+    /// obstacles
+    pub fn walls(walls: &[Position]) -> Self {
+        EnvironmentChanges {
+            walls: walls.to_vec(),
+            obstacles: None,
+        }
+    }
+}
+
+/// Cells that became obstacles and cells that stopped being ones
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ObstacleChanges {
+    pub added: Vec<Position>,
+    pub removed: Vec<Position>,
+}
+
+impl ObstacleChanges {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty()
+    }
+}
+
+/// The obstacles a planner last planned around, kept up to date from the
+/// `ObstacleChanges` it is given, so that a replan costs as much as the changes
+/// rather than a copy of the whole obstacle set. Until a caller gives changes (and
+/// whenever the number of obstacles shows some were missed) it falls back to
+/// comparing the sets.
+#[derive(Debug, Clone, Default)]
+pub struct KnownObstacles {
+    cells: HashSet<Position>,
+    /// Cells that changed since the last `catch_up`
+    changed: Vec<Position>,
+    /// Whether the changes of the last `update_environment` were given
+    tracked: bool,
+}
+
+impl KnownObstacles {
+    pub fn contains(&self, pos: &Position) -> bool {
+        self.cells.contains(pos)
+    }
+
+    pub fn cells(&self) -> &HashSet<Position> {
+        &self.cells
+    }
+
+    /// Plan around `obstacles` from now on, forgetting any changes not caught up on
+    pub fn reset(&mut self, obstacles: &HashSet<Position>) {
+        self.cells.clone_from(obstacles);
+        self.changed.clear();
+    }
+
+    /// Take in the obstacle changes of an `update_environment` call
+    pub fn update(&mut self, changes: &EnvironmentChanges) {
+        let Some(obstacles) = &changes.obstacles else {
+            self.tracked = false;
+            return;
+        };
+        self.tracked = true;
+        for &pos in &obstacles.added {
+            if self.cells.insert(pos) {
+                self.changed.push(pos);
+            }
+        }
+        for &pos in &obstacles.removed {
+            if self.cells.remove(&pos) {
+                self.changed.push(pos);
+            }
+        }
+    }
+
+    /// Bring the known obstacles up to `obstacles`, returning the cells that
+    /// became or stopped being obstacles since the last call, in order
+    pub fn catch_up(&mut self, obstacles: &HashSet<Position>) -> Vec<Position> {
+        if !self.tracked || self.cells.len() != obstacles.len() {
+            self.changed
+                .extend(obstacles.symmetric_difference(&self.cells).copied());
+            if !self.changed.is_empty() {
+                self.cells.clone_from(obstacles);
+            }
+        }
+        let mut changed = std::mem::take(&mut self.changed);
+        changed.sort_unstable();
+        changed.dedup();
+        changed
+    }
+}
+
+/// Steps each known obstacle that is going to expire stays, as last given to
+/// `update_obstacle_lifetimes`; obstacles that are not listed stay as far as anyone
+/// knows. Planners that wait for obstacles to go and planners that price them by
+/// how long they stay read them the same way: an obstacle with `n` steps left is
+/// still there for the next `n` moves.
+#[derive(Debug, Clone, Default)]
+pub struct ObstacleLifetimes {
+    steps: HashMap<Position, usize>,
+}
+
+impl ObstacleLifetimes {
+    /// Steps the obstacle on `pos` stays, if it is going to expire
+    pub fn get(&self, pos: Position) -> Option<usize> {
+        self.steps.get(&pos).copied()
+    }
+
+    pub fn contains(&self, pos: Position) -> bool {
+        self.steps.contains_key(&pos)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.steps.is_empty()
+    }
+
+    /// Take in the lifetimes of an `update_obstacle_lifetimes` call, returning the
+    /// cells whose lifetime changed (or that gained or lost one), in order
+    pub fn update(&mut self, lifetimes: &HashMap<Position, usize>) -> Vec<Position> {
+        let mut changed: Vec<Position> = lifetimes
+            .iter()
+            .filter(|&(pos, steps)| self.steps.get(pos) != Some(steps))
+            .map(|(&pos, _)| pos)
+            .chain(
+                self.steps
+                    .keys()
+                    .filter(|pos| !lifetimes.contains_key(pos))
+                    .copied(),
+            )
+            .collect();
+        changed.sort_unstable();
+        self.steps.clone_from(lifetimes);
+        changed
+    }
+
+    /// Steps a move from `from` to `to` made now waits for the obstacles in its way
+    /// (on the cell it enters or, diagonally, the cells it squeezes past) to expire
+    pub fn wait_before(&self, grid: &Grid, from: Position, to: Position) -> usize {
+        let wait = std::cell::Cell::new(0);
+        grid.can_move(from, to, |pos| {
+            wait.set(wait.get().max(self.get(pos).unwrap_or(0)));
+            false
+        });
+        wait.get()
+    }
+
+    /// Times `path` around the obstacles that are going to expire: before each move
+    /// into (or squeezing past) one of them, waits in place until it is gone. An
+    /// empty path stays empty.
+    pub fn wait_for(&self, grid: &Grid, path: &[Position]) -> Vec<Position> {
+        let Some(&first) = path.first() else {
+            return Vec::new();
+        };
+        let mut timed = vec![first];
+        for step in path.windows(2) {
+            // The next move is move number `timed.len()`, and an obstacle with a
+            // lifetime of `n` steps is still there for the next `n` moves
+            while !grid.can_move(step[0], step[1], |pos| {
+                self.get(pos).is_some_and(|left| left >= timed.len())
+            }) {
+                timed.push(step[0]);
+            }
+            timed.push(step[1]);
+        }
+        timed
+    }
+}
+
+/// Every obstacle the timeline is going to place, and the steps each stays for, as
+/// given to `update_obstacle_schedule`. A cell counts as blocked for the whole stay
+/// of each group that lists it, even where an earlier obstacle already stands and
+/// the group's would not be placed, so a plan that keeps clear of the schedule
+/// never runs into a timeline obstacle.
+#[derive(Debug, Clone, Default)]
+pub struct ObstacleSchedule {
+    /// Steps each cell is blocked at, as `from..until` ranges; a stay that starts
+    /// before the cell's last one ends is merged into it
+    stays: HashMap<Position, Vec<(usize, usize)>>,
+    /// First step no scheduled obstacle is left at
+    end: usize,
+}
+
+impl ObstacleSchedule {
+    pub fn new() -> Self {
+        ObstacleSchedule::default()
+    }
+
+    /// Schedule obstacles on `cells` that appear at `step` and stay for `steps`
+    /// steps (an obstacle is there for at least the step it appears at)
+    pub fn add(&mut self, cells: impl IntoIterator<Item = Position>, step: usize, steps: usize) {
+        let until = step + steps.max(1);
+        for pos in cells {
+            let stays = self.stays.entry(pos).or_default();
+            // Long stays of groups placed close together overlap on busy cells
+            match stays.last_mut() {
+                Some((from, last)) if (*from..=*last).contains(&step) => *last = (*last).max(until),
+                _ => stays.push((step, until)),
+            }
+        }
+        self.end = self.end.max(until);
+    }
+
+    /// Whether a scheduled obstacle stands on `pos` during `step`
+    pub fn is_blocked(&self, pos: Position, step: usize) -> bool {
+        self.stays.get(&pos).is_some_and(|stays| {
+            stays
+                .iter()
+                .any(|&(from, until)| (from..until).contains(&step))
+        })
+    }
+
+    /// Whether any obstacle is scheduled on `pos`
+    pub fn contains(&self, pos: Position) -> bool {
+        self.stays.contains_key(&pos)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.stays.is_empty()
+    }
+
+    /// First step no scheduled obstacle is left at; from then on every step looks
+    /// the same
+    pub fn end(&self) -> usize {
+        self.end
+    }
+}
 
 pub trait PathfindingAlgorithm {
     fn find_path(
@@ -10,16 +402,85 @@ pub trait PathfindingAlgorithm {
         goal: Position,
         obstacles: &HashSet<Position>,
     ) -> Option<Vec<Position>>;
-    
+
+    /// Find a path to whichever goal is cheapest to reach; reaching any of them counts as success
+    /// Default: plan to each goal in turn and keep the shortest path
+    fn find_path_to_any(
+        &mut self,
+        grid: &Grid,
+        start: Position,
+        goals: &[Position],
+        obstacles: &HashSet<Position>,
+    ) -> Option<Vec<Position>> {
+        goals
+            .iter()
+            .filter_map(|&goal| self.find_path(grid, start, goal, obstacles))
+            .min_by_key(|path| path.len())
+    }
+
     /// Get algorithm usage statistics (for hybrid algorithms)
     fn get_usage_stats(&self) -> (usize, usize) {
-        (0, 0)  // Default: no breakdown available
+        (0, 0) // Default: no breakdown available
+    }
+
+    /// Nodes expanded and generated and open list work over every search so far
+    fn search_metrics(&self) -> SearchMetrics {
+        SearchMetrics::default() // Default: the planner does not count its work
     }
-    
+
+    /// Start recording what each search does, for `search_trace`; off by default, as
+    /// recording slows searches down
+    fn trace_searches(&mut self) {}
+
+    /// Give up searches once `abort` is aborted, returning no path. Planners whose
+    /// searches are bounded anyway (the real-time ones, potential fields) finish
+    /// every search.
+    fn set_abort_handle(&mut self, _abort: AbortHandle) {}
+
+    /// What the latest search did on `grid`, once `trace_searches` has been called
+    fn search_trace(&self, _grid: &Grid) -> Option<SearchTrace> {
+        None // Default: the planner's searches cannot be shown
+    }
+
     /// Update environment (for incremental algorithms like D* Lite)
-    fn update_environment(&mut self, _grid: &Grid, _obstacles: &HashSet<Position>) {
+    /// `changes` lists what changed since the last call. Callers that list obstacle
+    /// changes list every one, so incremental planners can trust them over
+    /// comparing `obstacles` with the set they saw last (see `KnownObstacles`).
+    fn update_environment(
+        &mut self,
+        _grid: &Grid,
+        _obstacles: &HashSet<Position>,
+        _changes: &EnvironmentChanges,
+    ) {
         // Default: do nothing (most algorithms don't need this)
     }
 
+    /// Tell the planner how many more steps each known obstacle that is going to
+    /// expire stays; obstacles that are not listed stay as far as anyone knows
+    /// Planners that can wait in place use this to wait for an obstacle to go away
+    fn update_obstacle_lifetimes(&mut self, _lifetimes: &HashMap<Position, usize>) {
+        // Default: do nothing (most algorithms never wait)
+    }
+
+    /// Give the planner the whole obstacle timeline ahead of a plan, and the step
+    /// the plan starts at: the first move of the path it returns is made during
+    /// step `step + 1`. Only planners that look into the future use this; they keep
+    /// a clone of `schedule`, which is the same for every call of a run.
+    fn update_obstacle_schedule(&mut self, _schedule: &Arc<ObstacleSchedule>, _step: usize) {
+        // Default: do nothing (most algorithms only plan around what they know now)
+    }
+
+    /// Keep improving the current plan between moves (for anytime algorithms like ARA*)
+    /// Returns a better path when one was found
+    fn refine_path(
+        &mut self,
+        _grid: &Grid,
+        _start: Position,
+        _goal: Position,
+        _obstacles: &HashSet<Position>,
+    ) -> Option<Vec<Position>> {
+        None // Default: plans are final once returned
+    }
+
     fn as_any_mut(&mut self) -> &mut dyn Any;
 }
@@ -1,8 +1,13 @@
-use crate::algorithms::common::PathfindingAlgorithm;
-use crate::grid::{Grid, Position, Cell};
-use std::cmp::Ordering;
-use std::collections::{BinaryHeap, HashMap, HashSet};
+use crate::algorithms::common::{
+    AbortHandle, EnvironmentChanges, KnownObstacles, ObstacleLifetimes, PathfindingAlgorithm,
+    SearchMetrics, SearchTrace,
+};
+use crate::algorithms::landmarks::LandmarkHeuristic;
+use crate::algorithms::priority_queue::{PriorityQueue, QueueKey, QueueKind};
+use crate::algorithms::search_space::{GridSpace, ObstacleCost, SearchSpace};
+use crate::grid::{Grid, Position};
 use std::any::Any;
+use std::collections::{HashMap, HashSet};
 
 /// Represents the priority key for a node in the D* Lite priority queue.
 #[derive(Clone, Copy, PartialEq, Debug)]
@@ -11,121 +16,322 @@ struct Key {
     k2: i32,
 }
 
-impl Eq for Key {}
-
-impl PartialOrd for Key {
-    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
-        Some(self.cmp(other))
+impl From<Key> for QueueKey {
+    fn from(key: Key) -> Self {
+        (key.k1, key.k2)
     }
 }
 
-impl Ord for Key {
-    fn cmp(&self, other: &Self) -> Ordering {
-        // For min-heap behavior with BinaryHeap (which is max-heap)
-        // We reverse the comparison
-        match other.k1.cmp(&self.k1) {
-            Ordering::Equal => other.k2.cmp(&self.k2),
-            other => other,
-        }
+impl From<QueueKey> for Key {
+    fn from((k1, k2): QueueKey) -> Self {
+        Key { k1, k2 }
     }
 }
 
 /// Implements the D* Lite pathfinding algorithm based on the 2002 paper by S. Koenig and M. Likhachev.
-pub struct DStarLite {
-    pub g_scores: Vec<i32>,      // Make public for hybrid access
-    pub rhs_scores: Vec<i32>,    // Make public for hybrid access
-    queue: BinaryHeap<(Key, Position, u64)>, // Priority queue U with generation counter
-    vertex_generations: Vec<u64>, // Track current generation for each vertex
-    current_generation: u64,               // Current generation counter
-    k_m: i32,                              // Key modifier
-    pub s_start: Position,                     // Make public for hybrid access
-    pub s_goal: Position,                      // Goal position
-    s_last: Position,                      // Last start position
-    edge_costs: HashMap<(Position, Position), i32>, // c(u,v) edge costs
-    pub initialized: bool,                     // Track if algorithm has been initialized
-    pub last_known_obstacles: HashSet<Position>,  // Track what obstacles we've seen
-    pub last_start: Position,                     // Track last start position
-    grid_size: usize,  // Add this field at the end
+///
+/// The search itself works on any `SearchSpace` (see `plan` and `update_nodes`); the
+/// `PathfindingAlgorithm` implementation runs it on the simulation grid.
+pub struct DStarLite<N = Position> {
+    g_scores: Vec<i32>,                      // Cost-to-goal estimate of each node
+    rhs_scores: Vec<i32>,                    // One-step lookahead cost of each node
+    queue: Box<dyn PriorityQueue<(N, u64)>>, // Priority queue U with generation counter
+    queue_kind: QueueKind,                   // Which priority queue backend is in use
+    vertex_generations: Vec<u64>,            // Track current generation for each vertex
+    current_generation: u64,                 // Current generation counter
+    k_m: i32,                                // Key modifier
+    s_start: N,                              // Current start position
+    s_goal: N,                               // Goal position
+    goals: Vec<N>,                           // All goals; they hang off a virtual super-goal
+    s_last: N,                               // Last start position
+    initialized: bool,                       // Track if algorithm has been initialized
+    known_obstacles: KnownObstacles,         // Track what obstacles we've seen (grid adapter)
+    node_count: usize,                       // Size of the per-node vectors
+    grid_shape: (usize, usize, usize),       // Width, height and floors of the grid searched last
+    grid_walls: u64,                         // Wall generation of the grid searched last
+    landmarks: Option<LandmarkHeuristic>,    // Optional ALT heuristic (grid adapter)
+    lifetimes: ObstacleLifetimes,            // Steps known obstacles stay (grid adapter)
+    obstacle_cost: ObstacleCost,             // How expiring obstacles are priced (grid adapter)
+    lifetime_changes: Vec<Position>,         // Cells whose lifetime changed since the last search
+    metrics: SearchMetrics,                  // Work done over every search
+    trace: bool,                             // Whether to record the nodes each search expands
+    expanded: Vec<N>,                        // Nodes the latest search expanded, in order
+    abort: AbortHandle,                      // Gives up a search when aborted
 }
 
 impl DStarLite {
-    /// Creates a new instance of the D* Lite algorithm with the specified grid size.
-    pub fn new(start: Position, goal: Position, grid_size: usize) -> Self {
-        let total_cells = grid_size * grid_size;
-        
+    /// Creates a new instance of the D* Lite algorithm for a `width` x `height` grid.
+    /// The size only saves a resize on the first search: the planner fits itself to
+    /// whatever grid it is given (see `fit_grid`).
+    pub fn new(start: Position, goal: Position, width: usize, height: usize) -> Self {
+        Self::with_node_count(start, goal, width * height)
+    }
+
+    /// Creates a D* Lite instance that uses the ALT landmark heuristic instead of plain Manhattan distance.
+    pub fn with_landmarks(
+        start: Position,
+        goal: Position,
+        width: usize,
+        height: usize,
+        landmarks: LandmarkHeuristic,
+    ) -> Self {
         DStarLite {
-            g_scores: vec![i32::MAX; total_cells],
-            rhs_scores: vec![i32::MAX; total_cells],
-            queue: BinaryHeap::new(),
-            vertex_generations: vec![0; total_cells],
+            landmarks: Some(landmarks),
+            ..Self::new(start, goal, width, height)
+        }
+    }
+
+    /// Prices obstacles that are going to expire by `obstacle_cost`. A time-aware
+    /// planner repairs its search around every obstacle whose lifetime changed, as
+    /// it does around obstacles that come and go.
+    pub fn with_obstacle_cost(mut self, obstacle_cost: ObstacleCost) -> Self {
+        self.obstacle_cost = obstacle_cost;
+        self
+    }
+
+    /// Ensure vectors are sized correctly for a `width` x `height` grid
+    pub fn ensure_grid_size(&mut self, width: usize, height: usize) {
+        self.ensure_node_count(width * height);
+    }
+
+    /// Fit the per-node vectors to `grid`, starting the search over when the grid
+    /// is not the shape of the last one searched or is of another wall generation
+    /// (see `Grid::wall_generation`) than the walls it knows of. Grids of the same
+    /// cell count but another width would index the old search's cells by different
+    /// positions, and the old search's costs are wrong around walls it was never told
+    /// moved. Called before every search on the grid, so a planner can be moved
+    /// between grids of any size and layout.
+    pub fn fit_grid(&mut self, grid: &Grid) {
+        let shape = (grid.width, grid.height, grid.floors);
+        let walls = grid.wall_generation();
+        if self.grid_shape != shape || self.grid_walls != walls {
+            self.grid_shape = shape;
+            self.grid_walls = walls;
+            self.initialized = false;
+        }
+        self.ensure_node_count(grid.cell_count());
+    }
+
+    /// Take the walls of `grid` as those the search knows of, for a grid whose walls
+    /// changed in cells that are reported to `update_nodes` or `plan`: the search is
+    /// then repaired around them rather than started over
+    pub fn track_walls(&mut self, grid: &Grid) {
+        self.grid_walls = grid.wall_generation();
+    }
+
+    /// EFFICIENT: Update only edges that actually changed
+    pub fn update_edge_costs_incremental(
+        &mut self,
+        grid: &Grid,
+        new_obstacles: &HashSet<Position>,
+    ) {
+        // Edges into an obstacle that appeared or disappeared changed cost
+        let changed = self.known_obstacles.catch_up(new_obstacles);
+        if changed.is_empty() {
+            return;
+        }
+        self.on_grid(grid, new_obstacles, |d_star, space| {
+            d_star.update_nodes(space, &changed)
+        });
+    }
+
+    /// Run `f` with the grid as a search space, using the landmark heuristic if enabled
+    fn on_grid<R>(
+        &mut self,
+        grid: &Grid,
+        obstacles: &HashSet<Position>,
+        f: impl FnOnce(&mut Self, &GridSpace) -> R,
+    ) -> R {
+        self.fit_grid(grid);
+        if let Some(landmarks) = &mut self.landmarks {
+            landmarks.prepare(grid);
+        }
+        let landmarks = self.landmarks.take();
+        let lifetimes = std::mem::take(&mut self.lifetimes);
+        let result = f(
+            self,
+            &GridSpace::new(grid, obstacles)
+                .with_landmarks(landmarks.as_ref())
+                .with_lifetimes(self.obstacle_cost.lifetimes(&lifetimes)),
+        );
+        self.landmarks = landmarks;
+        self.lifetimes = lifetimes;
+        result
+    }
+}
+
+impl<N: Copy + Eq + std::hash::Hash + Ord + 'static> DStarLite<N> {
+    /// Creates a D* Lite instance for a search space with `node_count` nodes.
+    pub fn with_node_count(start: N, goal: N, node_count: usize) -> Self {
+        DStarLite {
+            g_scores: vec![i32::MAX; node_count],
+            rhs_scores: vec![i32::MAX; node_count],
+            queue: QueueKind::BinaryHeap.create(),
+            queue_kind: QueueKind::BinaryHeap,
+            vertex_generations: vec![0; node_count],
             current_generation: 0,
             k_m: 0,
             s_start: start,
             s_goal: goal,
+            goals: vec![goal],
             s_last: start,
-            edge_costs: HashMap::new(),
             initialized: false,
-            last_known_obstacles: HashSet::new(),
-            last_start: start,
-            grid_size, // Use the actual grid size passed in
+            known_obstacles: KnownObstacles::default(),
+            node_count,
+            grid_shape: (0, 0, 0),
+            grid_walls: 0,
+            landmarks: None,
+            lifetimes: ObstacleLifetimes::default(),
+            obstacle_cost: ObstacleCost::default(),
+            lifetime_changes: Vec::new(),
+            metrics: SearchMetrics::default(),
+            trace: false,
+            expanded: Vec::new(),
+            abort: AbortHandle::default(),
         }
     }
-    
 
-    /// procedure CalculateKey(s) - line 01'
-    fn calculate_key(&self, s: Position) -> Key {
-        let index = self.pos_to_index(s);
-        let g_s = self.g_scores[index];
-        let rhs_s = self.rhs_scores[index];
-        let min_val = g_s.min(rhs_s);
-        
-        if min_val == i32::MAX {
-            Key { k1: i32::MAX, k2: i32::MAX }
+    /// Swaps the priority queue backend (binary heap by default).
+    pub fn with_queue(mut self, queue_kind: QueueKind) -> Self {
+        self.queue = queue_kind.create();
+        self.queue_kind = queue_kind;
+        self
+    }
+
+    /// Priority queue backend in use.
+    pub fn queue_kind(&self) -> QueueKind {
+        self.queue_kind
+    }
+
+    /// Give up searches once `abort` is aborted. An aborted search leaves the queue
+    /// as it was between two expansions, so the next `plan` carries on from there.
+    pub fn set_abort_handle(&mut self, abort: AbortHandle) {
+        self.abort = abort;
+    }
+
+    /// Ensure the per-node vectors hold `node_count` entries, discarding the search if they didn't
+    pub fn ensure_node_count(&mut self, node_count: usize) {
+        if self.node_count != node_count {
+            self.node_count = node_count;
+
+            // Resize vectors to match the search space
+            self.g_scores.resize(node_count, i32::MAX);
+            self.rhs_scores.resize(node_count, i32::MAX);
+            self.vertex_generations.resize(node_count, 0);
+
+            // Clear any existing data since the search space changed
+            self.g_scores.fill(i32::MAX);
+            self.rhs_scores.fill(i32::MAX);
+            self.vertex_generations.fill(0);
+            self.initialized = false;
+        }
+    }
+
+    /// Plans from `start` to the closest of `goals`, reusing the previous search when
+    /// only the start moved. Edge cost changes must be reported with `update_nodes`
+    /// first (or passed here as `changed`).
+    pub fn plan<S: SearchSpace<Node = N>>(
+        &mut self,
+        space: &S,
+        start: N,
+        goals: &[N],
+        changed: &[N],
+    ) -> Option<Vec<N>> {
+        let goal = *goals.first()?;
+        self.expanded.clear();
+        self.ensure_node_count(space.node_count());
+
+        // Only reinitialize if the goals changed
+        if !self.initialized || self.goals != goals {
+            self.s_goal = goal;
+            self.goals = goals.to_vec();
+            self.s_start = start;
+            self.s_last = start;
+            self.initialize(space);
         } else {
-            Key {
-                k1: min_val.saturating_add(self.h(s, self.s_start)).saturating_add(self.k_m),
-                k2: min_val,
+            if self.s_start != start {
+                self.s_last = self.s_start;
+                self.s_start = start;
+                self.k_m = self
+                    .k_m
+                    .saturating_add(self.h(space, self.s_last, self.s_start));
+            }
+            if !changed.is_empty() {
+                self.update_nodes(space, changed);
             }
         }
-    }
+        // Cheap when nothing is inconsistent: the top key is already above the start's
+        if !self.compute_shortest_path(space) {
+            return None;
+        }
+
+        // Check if path exists
+        let g_start = self.g_scores[space.index(self.s_start)];
+        if g_start == i32::MAX {
+            return None;
+        }
 
-    /// Heuristic function h(s1, s2) - Manhattan distance
-    fn h(&self, s1: Position, s2: Position) -> i32 {
-        (s1.x as i32 - s2.x as i32).abs() + (s1.y as i32 - s2.y as i32).abs()
+        self.reconstruct_path(space)
     }
 
-    /// Get edge cost c(u, v)
-    fn c(&self, u: Position, v: Position, grid: &Grid, obstacles: &HashSet<Position>) -> i32 {
-        // Check if edge exists in our stored costs first
-        if let Some(&cost) = self.edge_costs.get(&(u, v)) {
-            return cost;
+    /// Reports that the costs of edges into `changed` nodes changed (e.g. an obstacle
+    /// appeared on or left them). Their rhs values, and those of their predecessors,
+    /// are recomputed; the next `plan` call repairs the search from there.
+    pub fn update_nodes<S: SearchSpace<Node = N>>(&mut self, space: &S, changed: &[N]) {
+        self.ensure_node_count(space.node_count());
+        if !self.initialized {
+            return;
+        }
+        let mut changed_vertices = HashSet::new();
+        for &node in changed {
+            changed_vertices.extend(space.predecessors(node));
+            changed_vertices.insert(node);
         }
 
-        // Check bounds
-        if v.x >= grid.size || v.y >= grid.size {
-            return i32::MAX;
+        // Only update vertices that were actually affected
+        for vertex in changed_vertices {
+            self.update_vertex(space, vertex);
         }
+    }
+
+    /// procedure CalculateKey(s) - line 01'
+    fn calculate_key<S: SearchSpace<Node = N>>(&self, space: &S, s: N) -> Key {
+        let index = space.index(s);
+        let g_s = self.g_scores[index];
+        let rhs_s = self.rhs_scores[index];
+        let min_val = g_s.min(rhs_s);
 
-        // Check if destination is blocked
-        if obstacles.contains(&v) || grid.cells[v.x][v.y] == Cell::Wall {
-            i32::MAX
+        if min_val == i32::MAX {
+            Key {
+                k1: i32::MAX,
+                k2: i32::MAX,
+            }
         } else {
-            1 // Standard movement cost
+            Key {
+                k1: min_val
+                    .saturating_add(self.h(space, self.s_start, s))
+                    .saturating_add(self.k_m),
+                k2: min_val,
+            }
         }
     }
 
-    /// Get successors of position s
-    fn succ(&self, s: Position, grid: &Grid) -> Vec<Position> {
-        grid.get_neighbors(&s)
+    /// Heuristic function h(s1, s2) from the search space
+    fn h<S: SearchSpace<Node = N>>(&self, space: &S, s1: N, s2: N) -> i32 {
+        space.heuristic(s1, s2).min(i32::MAX as u32) as i32
     }
 
-    /// Get predecessors of position s  
-    fn pred(&self, s: Position, grid: &Grid) -> Vec<Position> {
-        grid.get_neighbors(&s) // In grid world, predecessors = successors
+    /// Get edge cost c(u, v)
+    fn c<S: SearchSpace<Node = N>>(&self, space: &S, u: N, v: N) -> i32 {
+        space
+            .cost(u, v)
+            .map_or(i32::MAX, |cost| cost.min(i32::MAX as u32) as i32)
     }
 
     /// procedure Initialize() - lines 02'-06'
-    fn initialize(&mut self) {
+    fn initialize<S: SearchSpace<Node = N>>(&mut self, space: &S) {
+        self.ensure_node_count(space.node_count());
+
         // Clear all data structures
         self.queue.clear();
         self.vertex_generations.fill(0);
@@ -133,247 +339,186 @@ impl DStarLite {
         self.k_m = 0;
         self.g_scores.fill(i32::MAX);
         self.rhs_scores.fill(i32::MAX);
-        
-        // line 05': rhs(s_goal) = 0
-        let goal_index = self.pos_to_index(self.s_goal);
-        self.rhs_scores[goal_index] = 0;
-        
-        // line 06': U.Insert(s_goal, CalculateKey(s_goal))
-        let key = self.calculate_key(self.s_goal);
-        self.current_generation += 1;
-        self.vertex_generations[goal_index] = self.current_generation;
-        self.queue.push((key, self.s_goal, self.current_generation));
-        
+
+        // Every goal is joined to a virtual super-goal by a zero-cost edge, so
+        // lines 05'-06' run for each of them: rhs(goal) = 0 and U.Insert(goal)
+        for goal in self.goals.clone() {
+            // line 05': rhs(s_goal) = 0
+            let goal_index = space.index(goal);
+            self.rhs_scores[goal_index] = 0;
+
+            // line 06': U.Insert(s_goal, CalculateKey(s_goal))
+            let key = self.calculate_key(space, goal);
+            self.current_generation += 1;
+            self.vertex_generations[goal_index] = self.current_generation;
+            self.enqueue(key, goal);
+        }
+
         self.initialized = true;
     }
 
     /// procedure UpdateVertex(u) - lines 07'-09' with lazy deletion
-    fn update_vertex(&mut self, u: Position, grid: &Grid, obstacles: &HashSet<Position>) {
-        let u_index = self.pos_to_index(u);
+    fn update_vertex<S: SearchSpace<Node = N>>(&mut self, space: &S, u: N) {
+        let u_index = space.index(u);
         let g_u = self.g_scores[u_index];
-        
-        // Calculate new rhs(u) if u != s_goal
-        if u != self.s_goal {
+
+        // Calculate new rhs(u) unless u is a goal
+        if !self.goals.contains(&u) {
             let mut min_rhs = i32::MAX;
-            let successors = self.succ(u, grid);
-            
+            let successors = space.neighbors(u);
+
             for s_prime in successors {
-                let cost = self.c(u, s_prime, grid, obstacles);
-                let s_prime_index = self.pos_to_index(s_prime);
+                let cost = self.c(space, u, s_prime);
+                let s_prime_index = space.index(s_prime);
                 let g_s_prime = self.g_scores[s_prime_index];
-                
+
                 if cost != i32::MAX && g_s_prime != i32::MAX {
                     let total_cost = cost.saturating_add(g_s_prime);
                     min_rhs = min_rhs.min(total_cost);
                 }
             }
-            
+
             self.rhs_scores[u_index] = min_rhs;
         }
-        
+
         let rhs_u = self.rhs_scores[u_index];
-        
+
         // Invalidate old entries by incrementing generation
         self.current_generation += 1;
         self.vertex_generations[u_index] = self.current_generation;
-        
+
         // Insert u if it's inconsistent
         if g_u != rhs_u {
-            let key = self.calculate_key(u);
-            self.queue.push((key, u, self.current_generation));
+            let key = self.calculate_key(space, u);
+            self.enqueue(key, u);
         }
     }
 
+    /// U.Insert(node, key), as the node's current generation
+    fn enqueue(&mut self, key: Key, node: N) {
+        self.queue.push(key.into(), (node, self.current_generation));
+        self.metrics.pushed(self.queue.len());
+    }
+
+    /// Nodes expanded and generated and queue work over every search so far
+    pub fn metrics(&self) -> SearchMetrics {
+        self.metrics
+    }
+
     /// procedure ComputeShortestPath() - lines 10'-20' with lazy deletion
-    fn compute_shortest_path(&mut self, grid: &Grid, obstacles: &HashSet<Position>) {
+    /// Returns false when the search was aborted before it finished
+    fn compute_shortest_path<S: SearchSpace<Node = N>>(&mut self, space: &S) -> bool {
         while !self.queue.is_empty() {
+            if self.abort.is_aborted_after(self.metrics.nodes_expanded) {
+                return false;
+            }
             // Skip invalid entries using lazy deletion
             let (k_old, u) = loop {
-                if let Some((k, pos, gen)) = self.queue.pop() {
+                if let Some((k, (pos, gen))) = self.queue.pop() {
+                    self.metrics.popped();
                     // Check if this entry is still valid
-                    let pos_index = self.pos_to_index(pos);
+                    let pos_index = space.index(pos);
                     if self.vertex_generations[pos_index] == gen {
-                        break (k, pos);
+                        break (Key::from(k), pos);
                     }
                     // Skip this entry - it's been invalidated
                 } else {
-                    return; // Queue is empty
+                    return true; // Queue is empty
                 }
             };
-            
+
             // Check termination condition
-            let start_key = self.calculate_key(self.s_start);
-            let start_index = self.pos_to_index(self.s_start);
+            let start_key = self.calculate_key(space, self.s_start);
+            let start_index = space.index(self.s_start);
             let rhs_start = self.rhs_scores[start_index];
             let g_start = self.g_scores[start_index];
 
             let top_less_than_start = self.key_less_than(k_old, start_key);
             let start_inconsistent = rhs_start != g_start;
-            
+
             if !top_less_than_start && !start_inconsistent {
                 // Put the item back and break
                 self.current_generation += 1;
-                let u_index = self.pos_to_index(u);
+                let u_index = space.index(u);
                 self.vertex_generations[u_index] = self.current_generation;
-                self.queue.push((k_old, u, self.current_generation));
+                self.enqueue(k_old, u);
                 break;
             }
 
             // Check if key has changed
-            let k_new = self.calculate_key(u);
+            let k_new = self.calculate_key(space, u);
             if self.key_less_than(k_old, k_new) {
                 self.current_generation += 1;
-                let u_index = self.pos_to_index(u);
+                let u_index = space.index(u);
                 self.vertex_generations[u_index] = self.current_generation;
-                self.queue.push((k_new, u, self.current_generation));
+                self.enqueue(k_new, u);
                 continue;
             }
 
-            let u_index = self.pos_to_index(u);
+            let u_index = space.index(u);
             let g_u = self.g_scores[u_index];
             let rhs_u = self.rhs_scores[u_index];
+            self.metrics.nodes_expanded += 1;
+            if self.trace {
+                self.expanded.push(u);
+            }
 
             if g_u > rhs_u {
                 // Make vertex consistent
                 self.g_scores[u_index] = rhs_u;
-                
+
                 // Update all predecessors
-                let predecessors = self.pred(u, grid);
+                let predecessors = space.predecessors(u);
+                self.metrics.nodes_generated += predecessors.len();
                 for s in predecessors {
-                    self.update_vertex(s, grid, obstacles);
+                    self.update_vertex(space, s);
                 }
             } else {
                 // Set g(u) to infinity
                 self.g_scores[u_index] = i32::MAX;
-                
+
                 // Update all predecessors and u itself
-                let mut vertices_to_update = self.pred(u, grid);
+                let mut vertices_to_update = space.predecessors(u);
                 vertices_to_update.push(u);
-                
-                for s in vertices_to_update {
-                    self.update_vertex(s, grid, obstacles);
-                }
-            }
-        }
-    }
+                self.metrics.nodes_generated += vertices_to_update.len() - 1;
 
-    /// Update edge costs when obstacles change
-    pub fn update_edge_costs(&mut self, grid: &Grid, obstacles: &HashSet<Position>) {
-        self.edge_costs.clear();
-        
-        for x in 0..grid.size {
-            for y in 0..grid.size {
-                let pos = Position { x, y };
-                for neighbor in grid.get_neighbors(&pos) {
-                    let cost = if obstacles.contains(&neighbor) || 
-                                  grid.cells[neighbor.x][neighbor.y] == Cell::Wall {
-                        i32::MAX
-                    } else {
-                        1
-                    };
-                    self.edge_costs.insert((pos, neighbor), cost);
-                }
-            }
-        }
-    }
-}
-
-impl PathfindingAlgorithm for DStarLite {
-    fn find_path(
-        &mut self,
-        grid: &Grid,
-        start: Position,
-        goal: Position,
-        obstacles: &HashSet<Position>,
-    ) -> Option<Vec<Position>> {
-        // Ensure our vectors are sized correctly for this grid
-        // self.ensure_grid_size(grid.size);
-        
-        // Only reinitialize if goal changed
-        if !self.initialized || self.s_goal != goal {
-            self.s_goal = goal;
-            self.s_start = start;
-            self.s_last = start;
-            self.initialize();
-            self.update_edge_costs(grid, obstacles);
-            self.compute_shortest_path(grid, obstacles);
-            self.last_known_obstacles = obstacles.clone();
-        } else {
-            // For incremental updates, only update what changed
-            let obstacles_changed = obstacles != &self.last_known_obstacles;
-            let start_changed = self.s_start != start;
-            
-            // Only update if something actually changed
-            if start_changed || obstacles_changed {
-                if start_changed {
-                    self.s_last = self.s_start;
-                    self.s_start = start;
-                    self.k_m = self.k_m.saturating_add(self.h(self.s_last, self.s_start));
-                }
-                
-                if obstacles_changed {
-                    // Use incremental update instead of full rebuild
-                    self.update_edge_costs_incremental(grid, obstacles);
+                for s in vertices_to_update {
+                    self.update_vertex(space, s);
                 }
-                
-                self.compute_shortest_path(grid, obstacles);
-                self.last_known_obstacles = obstacles.clone();
             }
         }
-        
-        // Check if path exists
-        let g_start = self.g_scores[self.pos_to_index(self.s_start)];
-        if g_start == i32::MAX {
-            return None;
-        }
-
-        self.reconstruct_path(grid, obstacles)
-    }
-    
-    fn update_environment(&mut self, grid: &Grid, obstacles: &HashSet<Position>) {
-        // Only update if obstacles actually changed
-        if obstacles != &self.last_known_obstacles {
-            self.update_edge_costs_incremental(grid, obstacles);
-            self.last_known_obstacles = obstacles.clone();
-        }
-    }
-
-    fn as_any_mut(&mut self) -> &mut dyn Any {
-        self
+        true
     }
-}
-
 
-impl DStarLite {
     /// Reconstruct path from start to goal
-    fn reconstruct_path(&self, grid: &Grid, obstacles: &HashSet<Position>) -> Option<Vec<Position>> {
+    fn reconstruct_path<S: SearchSpace<Node = N>>(&self, space: &S) -> Option<Vec<N>> {
         let mut path = Vec::new();
         let mut current = self.s_start;
-        
+
         // Safety check
-        let start_index = self.pos_to_index(self.s_start);
+        let start_index = space.index(self.s_start);
         if self.g_scores[start_index] == i32::MAX {
             return None; // No path exists
         }
-        
+
         path.push(current);
-        
-        while current != self.s_goal {
-            let current_index = self.pos_to_index(current);
+
+        while !self.goals.contains(&current) {
+            let current_index = space.index(current);
             let current_g = self.g_scores[current_index];
-            
+
             if current_g == i32::MAX {
                 return None; // Path broken
             }
-            
+
             let mut best_next = None;
             let mut best_cost = i32::MAX;
-            
-            for next in self.succ(current, grid) {
-                let cost = self.c(current, next, grid, obstacles);
-                let next_index = self.pos_to_index(next);
+
+            for next in space.neighbors(current) {
+                let cost = self.c(space, current, next);
+                let next_index = space.index(next);
                 let next_g = self.g_scores[next_index];
-                
+
                 if cost != i32::MAX && next_g != i32::MAX {
                     let total_cost = cost.saturating_add(next_g);
                     if total_cost < best_cost {
@@ -382,25 +527,23 @@ impl DStarLite {
                     }
                 }
             }
-            
+
             if let Some(next) = best_next {
                 current = next;
                 path.push(current);
             } else {
                 return None; // No valid next step
             }
-            
+
             // Safety check for infinite loops
-            if path.len() > grid.size * grid.size {
+            if path.len() > self.node_count {
                 return None;
             }
         }
-        
+
         Some(path)
     }
-}
 
-impl DStarLite {
     /// Helper function to compare keys (k1 < k2)
     fn key_less_than(&self, k1: Key, k2: Key) -> bool {
         if k1.k1 != k2.k1 {
@@ -409,61 +552,113 @@ impl DStarLite {
             k1.k2 < k2.k2
         }
     }
-    /// EFFICIENT: Update only edges that actually changed
-    pub fn update_edge_costs_incremental(&mut self, grid: &Grid, new_obstacles: &HashSet<Position>) {
-        let mut changed_vertices = HashSet::new();
-        
-        // Handle new obstacles
-        for &obs_pos in new_obstacles.difference(&self.last_known_obstacles) {
-            // Update edges TO this position (now blocked)
-            for neighbor in grid.get_neighbors(&obs_pos) {
-                self.edge_costs.insert((neighbor, obs_pos), i32::MAX);
-                changed_vertices.insert(neighbor);
-            }
-            changed_vertices.insert(obs_pos);
+}
+
+impl PathfindingAlgorithm for DStarLite {
+    fn find_path(
+        &mut self,
+        grid: &Grid,
+        start: Position,
+        goal: Position,
+        obstacles: &HashSet<Position>,
+    ) -> Option<Vec<Position>> {
+        self.find_path_to_any(grid, start, &[goal], obstacles)
+    }
+
+    /// Plans to the closest of several goals at once by searching backwards from a virtual super-goal.
+    fn find_path_to_any(
+        &mut self,
+        grid: &Grid,
+        start: Position,
+        goals: &[Position],
+        obstacles: &HashSet<Position>,
+    ) -> Option<Vec<Position>> {
+        // Cells whose obstacle state (or time-aware cost) changed since the last call
+        let mut changed = self.known_obstacles.catch_up(obstacles);
+        if !self.lifetime_changes.is_empty() {
+            changed.append(&mut self.lifetime_changes);
+            changed.sort_unstable();
+            changed.dedup();
         }
-        
-        // Handle removed obstacles
-        for &obs_pos in self.last_known_obstacles.difference(new_obstacles) {
-            // Update edges TO this position (now passable)
-            for neighbor in grid.get_neighbors(&obs_pos) {
-                self.edge_costs.insert((neighbor, obs_pos), 1);
-                changed_vertices.insert(neighbor);
-            }
-            changed_vertices.insert(obs_pos);
+
+        let path = self.on_grid(grid, obstacles, |d_star, space| {
+            d_star.plan(space, start, goals, &changed)
+        })?;
+        Some(match self.obstacle_cost {
+            ObstacleCost::TimeAware => self.lifetimes.wait_for(grid, &path),
+            ObstacleCost::Blocked => path,
+        })
+    }
+
+    fn update_environment(
+        &mut self,
+        grid: &Grid,
+        obstacles: &HashSet<Position>,
+        changes: &EnvironmentChanges,
+    ) {
+        let changed_walls = &changes.walls;
+        if !changed_walls.is_empty() {
+            // The same grid with walls moved, which are repaired below
+            self.track_walls(grid);
         }
-        
-        // Only update vertices that were actually affected
-        for &vertex in &changed_vertices {
-            self.update_vertex(vertex, grid, new_obstacles);
+        self.known_obstacles.update(changes);
+        self.update_edge_costs_incremental(grid, obstacles);
+
+        if changed_walls.is_empty() {
+            return;
+        }
+        if let Some(landmarks) = &mut self.landmarks {
+            // New landmark distances change every key in the queue, so start over
+            landmarks.invalidate();
+            self.initialized = false;
+        } else {
+            self.on_grid(grid, obstacles, |d_star, space| {
+                d_star.update_nodes(space, changed_walls)
+            });
         }
     }
-}
 
-impl DStarLite {
-    /// Convert 2D position to 1D vector index
-    #[inline(always)]
-    fn pos_to_index(&self, pos: Position) -> usize {
-        pos.y * self.grid_size + pos.x
+    fn update_obstacle_lifetimes(&mut self, lifetimes: &HashMap<Position, usize>) {
+        let changed = self.lifetimes.update(lifetimes);
+        if self.obstacle_cost == ObstacleCost::TimeAware {
+            self.lifetime_changes.extend(changed);
+        }
     }
-}
 
-impl DStarLite {
-    /// Ensure vectors are sized correctly for the grid
-    pub fn ensure_grid_size(&mut self, grid_size: usize) {
-        if self.grid_size != grid_size {
-            let total_cells = grid_size * grid_size;
-            self.grid_size = grid_size;
-            
-            // Resize vectors to match grid size
-            self.g_scores.resize(total_cells, i32::MAX);
-            self.rhs_scores.resize(total_cells, i32::MAX);
-            self.vertex_generations.resize(total_cells, 0);
-            
-            // Clear any existing data since grid size changed
-            self.g_scores.fill(i32::MAX);
-            self.rhs_scores.fill(i32::MAX);
-            self.vertex_generations.fill(0);
+    fn search_metrics(&self) -> SearchMetrics {
+        self.metrics
+    }
+
+    fn trace_searches(&mut self) {
+        self.trace = true;
+    }
+
+    fn set_abort_handle(&mut self, abort: AbortHandle) {
+        DStarLite::set_abort_handle(self, abort);
+    }
+
+    fn search_trace(&self, grid: &Grid) -> Option<SearchTrace> {
+        if !self.trace {
+            return None;
         }
+        let costs: HashMap<Position, (i32, i32)> = self
+            .g_scores
+            .iter()
+            .zip(&self.rhs_scores)
+            .enumerate()
+            .filter(|&(index, (&g, &rhs))| {
+                index < grid.cell_count() && (g != i32::MAX || rhs != i32::MAX)
+            })
+            .map(|(index, (&g, &rhs))| (grid.position(index), (g, rhs)))
+            .collect();
+        Some(SearchTrace {
+            expanded: self.expanded.clone(),
+            open: HashSet::new(),
+            costs,
+        })
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
     }
 }
@@ -0,0 +1,90 @@
+use crate::algorithms::common::{AbortHandle, PathfindingAlgorithm, SearchMetrics};
+use crate::grid::{Grid, Position};
+use pathfinding::prelude::dijkstra;
+use std::any::Any;
+use std::collections::HashSet;
+
+/// Implements Dijkstra's algorithm using the `pathfinding` crate.
+///
+/// Uninformed baseline: same successor function and move costs as A*, but no
+/// heuristic, so it expands every cell closer to the start than the goal.
+#[derive(Default)]
+pub struct Dijkstra {
+    metrics: SearchMetrics,
+    abort: AbortHandle,
+}
+
+impl Dijkstra {
+    /// Creates a new instance of the Dijkstra algorithm provider.
+    pub fn new() -> Self {
+        Dijkstra::default()
+    }
+}
+
+impl PathfindingAlgorithm for Dijkstra {
+    /// Finds a shortest path from start to goal using Dijkstra's algorithm.
+    ///
+    /// # Arguments
+    ///
+    /// * `grid` - The simulation grid.
+    /// * `start` - The starting position.
+    /// * `goal` - The goal position.
+    /// * `obstacles` - A set of known obstacle positions to avoid.
+    ///
+    /// # Returns
+    ///
+    /// An `Option` containing a `Vec<Position>` representing the path, or `None` if no path is found.
+    fn find_path(
+        &mut self,
+        grid: &Grid,
+        start: Position,
+        goal: Position,
+        obstacles: &HashSet<Position>,
+    ) -> Option<Vec<Position>> {
+        self.find_path_to_any(grid, start, &[goal], obstacles)
+    }
+
+    /// Finds a shortest path to the closest of several goals; the search stops at the first goal reached.
+    fn find_path_to_any(
+        &mut self,
+        grid: &Grid,
+        start: Position,
+        goals: &[Position],
+        obstacles: &HashSet<Position>,
+    ) -> Option<Vec<Position>> {
+        let (metrics, abort) = (&mut self.metrics, &self.abort);
+        let result = dijkstra(
+            &start,
+            |p| {
+                // An aborted search expands nothing more, so the open list runs dry
+                if abort.is_aborted_after(metrics.nodes_expanded) {
+                    return Vec::new();
+                }
+                metrics.expanded(
+                    grid.get_neighbors(p)
+                        .into_iter()
+                        .filter(|&neighbor| {
+                            grid.can_move(*p, neighbor, |pos| obstacles.contains(&pos))
+                        })
+                        .map(|successor| (successor, grid.move_cost(*p, successor)))
+                        .collect::<Vec<_>>(),
+                )
+            },
+            |p| goals.contains(p),
+        );
+
+        result.map(|(path, _)| path)
+    }
+
+    fn search_metrics(&self) -> SearchMetrics {
+        self.metrics
+    }
+
+    fn set_abort_handle(&mut self, abort: AbortHandle) {
+        self.abort = abort;
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
@@ -0,0 +1,618 @@
+use crate::algorithms::common::{
+    AbortHandle, EnvironmentChanges, KnownObstacles, PathfindingAlgorithm, SearchMetrics,
+};
+use crate::grid::{Grid, Metric, MetricKind, Position, HEX_DIRECTIONS};
+use std::any::Any;
+use std::cmp::Ordering;
+use std::collections::{BinaryHeap, HashMap, HashSet};
+
+/// Tolerance used when comparing interpolated (floating point) path costs.
+const EPSILON: f64 = 1e-9;
+
+/// Offsets of two neighbors bounding the edge a path may cross.
+type NeighborPair = ((i32, i32), (i32, i32));
+
+/// Each pair is a cardinal neighbor followed by the diagonal neighbor next to it.
+/// Together they bound one of the eight edges the interpolated path may cross.
+const NEIGHBOR_PAIRS: [NeighborPair; 8] = [
+    ((1, 0), (1, 1)),
+    ((0, 1), (1, 1)),
+    ((0, 1), (-1, 1)),
+    ((-1, 0), (-1, 1)),
+    ((-1, 0), (-1, -1)),
+    ((0, -1), (-1, -1)),
+    ((0, -1), (1, -1)),
+    ((1, 0), (1, -1)),
+];
+
+/// On hex grids each pair is two neighbors next to each other; together with the
+/// cell itself they form an equilateral triangle, and the path crosses the edge
+/// between them.
+const HEX_NEIGHBOR_PAIRS: [NeighborPair; 6] = [
+    (HEX_DIRECTIONS[0], HEX_DIRECTIONS[1]),
+    (HEX_DIRECTIONS[1], HEX_DIRECTIONS[2]),
+    (HEX_DIRECTIONS[2], HEX_DIRECTIONS[3]),
+    (HEX_DIRECTIONS[3], HEX_DIRECTIONS[4]),
+    (HEX_DIRECTIONS[4], HEX_DIRECTIONS[5]),
+    (HEX_DIRECTIONS[5], HEX_DIRECTIONS[0]),
+];
+
+/// Represents the priority key for a node in the Field D* priority queue.
+#[derive(Clone, Copy, PartialEq, Debug)]
+struct Key {
+    k1: f64,
+    k2: f64,
+}
+
+impl Eq for Key {}
+
+impl PartialOrd for Key {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Key {
+    fn cmp(&self, other: &Self) -> Ordering {
+        // Reversed so BinaryHeap (a max-heap) pops the smallest key first
+        match other.k1.total_cmp(&self.k1) {
+            Ordering::Equal => other.k2.total_cmp(&self.k2),
+            other => other,
+        }
+    }
+}
+
+/// Where the interpolated path leaves a cell: the edge between a cardinal and a
+/// diagonal neighbor, and how far along that edge (0 = cardinal, 1 = diagonal).
+/// On hex grids the two ends are simply the first and second neighbor of the pair.
+#[derive(Clone, Copy)]
+struct EdgeCrossing {
+    cardinal: Position,
+    diagonal: Position,
+    fraction: f64,
+    cost: f64,
+}
+
+/// Implements Field D* (Ferguson and Stentz, 2005) on cell centers.
+///
+/// Field D* is D* Lite with an interpolation-based cost function: instead of only
+/// moving to one of the neighboring cells, the path may leave a cell through any
+/// point on the edge between a cardinal and a diagonal neighbor, with the cost-to-goal
+/// of that point linearly interpolated. This produces smooth, any-angle paths.
+///
+/// The agent still moves from cell to cell, so the interpolated path is mapped back
+/// to grid moves by snapping each edge crossing to the nearer cell. On a 4-connected
+/// grid diagonal steps are routed through the (always free) cardinal neighbor; with
+/// diagonal movement they are taken directly unless that would cut a corner.
+///
+/// On weighted grids each stretch of the path costs its length times the terrain
+/// cost of the cell it enters, the dearer of the two cells for edge crossings.
+///
+/// On hex grids the path crosses the edge between two adjacent neighbors instead,
+/// and distances are measured between hex centers. Stairs are plain moves to the
+/// floor above or below, with floors one cell apart, and portals plain moves to
+/// their other end. On toroidal grids neighbors and distances wrap around the edges.
+pub struct FieldDStar {
+    g_scores: Vec<f64>,
+    rhs_scores: Vec<f64>,
+    queue: BinaryHeap<(Key, Position, u64)>, // Priority queue with generation counter
+    vertex_generations: Vec<u64>,
+    current_generation: u64,
+    k_m: f64,
+    s_start: Position,
+    s_goal: Position,
+    known_obstacles: KnownObstacles,
+    initialized: bool,
+    width: usize,
+    height: usize,
+    floors: usize,
+    /// Topology of the grid: hex or square cells, and whether the edges wrap
+    metric: Metric,
+    portals: HashMap<Position, Position>,
+    last_interpolated_cost: f64,
+    metrics: SearchMetrics,
+    abort: AbortHandle,
+}
+
+impl FieldDStar {
+    /// Creates a new instance of Field D* for a `width` x `height` grid.
+    pub fn new(start: Position, goal: Position, width: usize, height: usize) -> Self {
+        let total_cells = width * height;
+
+        FieldDStar {
+            g_scores: vec![f64::INFINITY; total_cells],
+            rhs_scores: vec![f64::INFINITY; total_cells],
+            queue: BinaryHeap::new(),
+            vertex_generations: vec![0; total_cells],
+            current_generation: 0,
+            k_m: 0.0,
+            s_start: start,
+            s_goal: goal,
+            known_obstacles: KnownObstacles::default(),
+            initialized: false,
+            width,
+            height,
+            floors: 1,
+            metric: Metric::MANHATTAN,
+            portals: HashMap::new(),
+            last_interpolated_cost: f64::INFINITY,
+            metrics: SearchMetrics::default(),
+            abort: AbortHandle::default(),
+        }
+    }
+
+    /// Interpolated cost-to-goal from the start of the most recent plan.
+    pub fn last_interpolated_cost(&self) -> f64 {
+        self.last_interpolated_cost
+    }
+
+    /// Convert a position to its index in the per-cell vectors (same as `Grid::index`)
+    #[inline(always)]
+    fn pos_to_index(&self, pos: Position) -> usize {
+        (pos.z * self.height + pos.y) * self.width + pos.x
+    }
+
+    /// Heuristic function h(s1, s2) - Euclidean distance between cell centers, through
+    /// portals where shorter
+    fn h(&self, s1: Position, s2: Position) -> f64 {
+        self.metric.euclidean_via(s1, s2, &self.portals)
+    }
+
+    fn g(&self, pos: Position) -> f64 {
+        self.g_scores[self.pos_to_index(pos)]
+    }
+
+    fn hex(&self) -> bool {
+        self.metric.kind == MetricKind::Hex
+    }
+
+    /// The cell at an offset from `pos`, wrapping around the edges of a toroidal grid
+    fn offset(&self, pos: Position, (dx, dy): (i32, i32)) -> Option<Position> {
+        let mut nx = pos.x as i32 + dx;
+        let mut ny = pos.y as i32 + dy;
+        if self.metric.wrap.is_some() {
+            nx = nx.rem_euclid(self.width as i32);
+            ny = ny.rem_euclid(self.height as i32);
+        }
+        if nx < 0 || ny < 0 || nx >= self.width as i32 || ny >= self.height as i32 {
+            None
+        } else {
+            Some(Position {
+                x: nx as usize,
+                y: ny as usize,
+                z: pos.z,
+            })
+        }
+    }
+
+    fn is_free(&self, pos: Position, grid: &Grid) -> bool {
+        !grid.is_wall(pos) && !self.known_obstacles.contains(&pos)
+    }
+
+    /// All eight (on hex grids six) neighbors within the grid plus any stairs up or
+    /// down and the other end of a portal, successors and predecessors alike
+    fn neighbors(&self, pos: Position, grid: &Grid) -> Vec<Position> {
+        if self.hex() {
+            return HEX_DIRECTIONS
+                .iter()
+                .filter_map(|&offset| self.offset(pos, offset))
+                .chain(grid.links(&pos))
+                .collect();
+        }
+        let mut result = grid.links(&pos);
+        for dx in -1..=1 {
+            for dy in -1..=1 {
+                if dx == 0 && dy == 0 {
+                    continue;
+                }
+                if let Some(neighbor) = self.offset(pos, (dx, dy)) {
+                    result.push(neighbor);
+                }
+            }
+        }
+        result
+    }
+
+    /// procedure ComputeCost(s, sa, sb) - cheapest way to leave `s` through one edge
+    fn compute_cost(&self, s: Position, pair: NeighborPair, grid: &Grid) -> Option<EdgeCrossing> {
+        let cardinal = self.offset(s, pair.0)?;
+        let diagonal = self.offset(s, pair.1)?;
+        if self.hex() {
+            return self.compute_cost_hex(cardinal, diagonal, grid);
+        }
+
+        // Both the straight move and any crossing of the edge pass through the cardinal cell
+        if !self.is_free(cardinal, grid) {
+            return None;
+        }
+
+        let g1 = self.g(cardinal);
+        let g2 = if self.is_free(diagonal, grid) {
+            self.g(diagonal)
+        } else {
+            f64::INFINITY
+        };
+
+        let mut best: Option<EdgeCrossing> = None;
+        let mut consider = |fraction: f64, cost: f64| {
+            if cost.is_finite() && best.is_none_or(|b| cost < b.cost) {
+                best = Some(EdgeCrossing {
+                    cardinal,
+                    diagonal,
+                    fraction,
+                    cost,
+                });
+            }
+        };
+
+        // Distance is weighted by the terrain cost of the cell entered, and by the
+        // dearer of the two when crossing the edge between them
+        let card_weight = grid.terrain_cost(cardinal) as f64;
+        let diag_weight = grid.terrain_cost(diagonal) as f64;
+        let edge_weight = card_weight.max(diag_weight);
+
+        // Straight to the cardinal neighbor, or straight to the diagonal neighbor
+        consider(0.0, card_weight + g1);
+        consider(1.0, diag_weight * std::f64::consts::SQRT_2 + g2);
+
+        // Somewhere in between, with the cost-to-goal interpolated along the edge
+        let f = g1 - g2;
+        if g1.is_finite() && g2.is_finite() && f > 0.0 && f < edge_weight {
+            let y = f / (edge_weight * edge_weight - f * f).sqrt();
+            if y < 1.0 {
+                consider(y, edge_weight * (1.0 + y * y).sqrt() + g1 - f * y);
+            }
+        }
+
+        best
+    }
+
+    /// ComputeCost on a hex grid, where both ends of the edge are one cell away.
+    /// A point a fraction y along the edge is sqrt(1 - y + y^2) away.
+    fn compute_cost_hex(
+        &self,
+        first: Position,
+        second: Position,
+        grid: &Grid,
+    ) -> Option<EdgeCrossing> {
+        let g_of = |pos: Position| {
+            if self.is_free(pos, grid) {
+                self.g(pos)
+            } else {
+                f64::INFINITY
+            }
+        };
+        let (g1, g2) = (g_of(first), g_of(second));
+
+        let mut best: Option<EdgeCrossing> = None;
+        let mut consider = |fraction: f64, cost: f64| {
+            if cost.is_finite() && best.is_none_or(|b| cost < b.cost) {
+                best = Some(EdgeCrossing {
+                    cardinal: first,
+                    diagonal: second,
+                    fraction,
+                    cost,
+                });
+            }
+        };
+
+        let first_weight = grid.terrain_cost(first) as f64;
+        let second_weight = grid.terrain_cost(second) as f64;
+        let edge_weight = first_weight.max(second_weight);
+
+        consider(0.0, first_weight + g1);
+        consider(1.0, second_weight + g2);
+
+        // Setting the derivative of w * sqrt(1 - y + y^2) + g1 - f * y to zero
+        // gives 2y - 1 = f * sqrt(3) / sqrt(w^2 - f^2)
+        let f = g1 - g2;
+        if g1.is_finite() && g2.is_finite() && f.abs() < edge_weight {
+            let y = 0.5 * (1.0 + f * 3f64.sqrt() / (edge_weight * edge_weight - f * f).sqrt());
+            if y > 0.0 && y < 1.0 {
+                consider(y, edge_weight * (1.0 - y + y * y).sqrt() + g1 - f * y);
+            }
+        }
+
+        best
+    }
+
+    /// Best edge crossing out of `s`, or the climb up or down a flight of stairs or
+    /// the step through a portal
+    fn best_crossing(&self, s: Position, grid: &Grid) -> Option<EdgeCrossing> {
+        let pairs: &[NeighborPair] = if self.hex() {
+            &HEX_NEIGHBOR_PAIRS
+        } else {
+            &NEIGHBOR_PAIRS
+        };
+        let climbs = grid
+            .links(&s)
+            .into_iter()
+            .filter(|&other| self.is_free(other, grid) && self.g(other).is_finite())
+            .map(|other| EdgeCrossing {
+                cardinal: other,
+                diagonal: other,
+                fraction: 0.0,
+                cost: grid.terrain_cost(other) as f64 + self.g(other),
+            });
+        pairs
+            .iter()
+            .filter_map(|&pair| self.compute_cost(s, pair, grid))
+            .chain(climbs)
+            .min_by(|a, b| a.cost.total_cmp(&b.cost))
+    }
+
+    /// procedure CalculateKey(s)
+    fn calculate_key(&self, s: Position) -> Key {
+        let index = self.pos_to_index(s);
+        let min_val = self.g_scores[index].min(self.rhs_scores[index]);
+        Key {
+            k1: min_val + self.h(s, self.s_start) + self.k_m,
+            k2: min_val,
+        }
+    }
+
+    fn key_less_than(&self, k1: Key, k2: Key) -> bool {
+        if (k1.k1 - k2.k1).abs() > EPSILON {
+            k1.k1 < k2.k1
+        } else {
+            k1.k2 < k2.k2 - EPSILON
+        }
+    }
+
+    fn push(&mut self, u: Position) {
+        let key = self.calculate_key(u);
+        let index = self.pos_to_index(u);
+        self.current_generation += 1;
+        self.vertex_generations[index] = self.current_generation;
+        self.queue.push((key, u, self.current_generation));
+        self.metrics.pushed(self.queue.len());
+    }
+
+    fn is_consistent(&self, index: usize) -> bool {
+        let (g, rhs) = (self.g_scores[index], self.rhs_scores[index]);
+        g == rhs || (g - rhs).abs() < EPSILON
+    }
+
+    /// procedure Initialize()
+    fn initialize(&mut self) {
+        self.queue.clear();
+        self.vertex_generations.fill(0);
+        self.current_generation = 0;
+        self.k_m = 0.0;
+        self.g_scores.fill(f64::INFINITY);
+        self.rhs_scores.fill(f64::INFINITY);
+
+        let goal_index = self.pos_to_index(self.s_goal);
+        self.rhs_scores[goal_index] = 0.0;
+        self.push(self.s_goal);
+
+        self.initialized = true;
+    }
+
+    /// procedure UpdateState(s) - rhs(s) is the cheapest interpolated edge crossing
+    fn update_vertex(&mut self, u: Position, grid: &Grid) {
+        let u_index = self.pos_to_index(u);
+
+        if u != self.s_goal {
+            self.rhs_scores[u_index] = if self.is_free(u, grid) {
+                self.best_crossing(u, grid)
+                    .map(|crossing| crossing.cost)
+                    .unwrap_or(f64::INFINITY)
+            } else {
+                f64::INFINITY
+            };
+        }
+
+        if self.is_consistent(u_index) {
+            // Consistent: drop any queued entry
+            self.current_generation += 1;
+            self.vertex_generations[u_index] = self.current_generation;
+        } else {
+            self.push(u);
+        }
+    }
+
+    /// procedure ComputeShortestPath()
+    /// Returns false when the search was aborted before it finished
+    fn compute_shortest_path(&mut self, grid: &Grid) -> bool {
+        loop {
+            if self.abort.is_aborted_after(self.metrics.nodes_expanded) {
+                return false;
+            }
+            // Skip invalid entries using lazy deletion
+            let (k_old, u) = loop {
+                match self.queue.peek() {
+                    Some(&(k, pos, gen)) => {
+                        if self.vertex_generations[self.pos_to_index(pos)] == gen {
+                            break (k, pos);
+                        }
+                        self.queue.pop();
+                        self.metrics.popped();
+                    }
+                    None => return true,
+                }
+            };
+
+            let start_key = self.calculate_key(self.s_start);
+            let start_index = self.pos_to_index(self.s_start);
+            if !self.key_less_than(k_old, start_key) && self.is_consistent(start_index) {
+                return true;
+            }
+
+            self.queue.pop();
+            self.metrics.popped();
+            let k_new = self.calculate_key(u);
+            if self.key_less_than(k_old, k_new) {
+                self.push(u);
+                continue;
+            }
+
+            let u_index = self.pos_to_index(u);
+            self.current_generation += 1;
+            self.vertex_generations[u_index] = self.current_generation;
+            self.metrics.nodes_expanded += 1;
+
+            if self.g_scores[u_index] > self.rhs_scores[u_index] {
+                self.g_scores[u_index] = self.rhs_scores[u_index];
+                let neighbors = self.neighbors(u, grid);
+                self.metrics.nodes_generated += neighbors.len();
+                for s in neighbors {
+                    self.update_vertex(s, grid);
+                }
+            } else {
+                self.g_scores[u_index] = f64::INFINITY;
+                let mut vertices_to_update = self.neighbors(u, grid);
+                self.metrics.nodes_generated += vertices_to_update.len();
+                vertices_to_update.push(u);
+                for s in vertices_to_update {
+                    self.update_vertex(s, grid);
+                }
+            }
+        }
+    }
+
+    /// Repair vertices around cells whose obstacle status changed
+    fn update_obstacles(&mut self, grid: &Grid, obstacles: &HashSet<Position>) {
+        let changed = self.known_obstacles.catch_up(obstacles);
+        self.update_cells(grid, &changed);
+    }
+
+    /// Recompute the cells whose blocked state changed, and their neighbors
+    fn update_cells(&mut self, grid: &Grid, changed: &[Position]) {
+        for &pos in changed {
+            self.update_vertex(pos, grid);
+            for neighbor in self.neighbors(pos, grid) {
+                self.update_vertex(neighbor, grid);
+            }
+        }
+    }
+
+    /// Move the search start, keeping queued keys valid lower bounds
+    fn move_start(&mut self, start: Position) {
+        if self.s_start != start {
+            self.k_m += self.h(self.s_start, start);
+            self.s_start = start;
+        }
+    }
+
+    /// Follow the interpolated path and map it back onto grid moves
+    fn reconstruct_path(&mut self, grid: &Grid) -> Option<Vec<Position>> {
+        let agent_start = self.s_start;
+        let path = self.extract_path(grid);
+        self.move_start(agent_start);
+        path
+    }
+
+    fn extract_path(&mut self, grid: &Grid) -> Option<Vec<Position>> {
+        if !self.g(self.s_start).is_finite() {
+            return None;
+        }
+
+        let mut path = vec![self.s_start];
+        let mut current = self.s_start;
+        while current != self.s_goal {
+            // Snapped cells are off the interpolated path, so the search may not have
+            // settled them yet; continue the search from there before following g-values.
+            // This returns immediately when the cell is already settled.
+            self.move_start(current);
+            if !self.compute_shortest_path(grid) {
+                return None;
+            }
+
+            let crossing = self.best_crossing(current, grid)?;
+
+            // Snap the edge crossing to the nearer cell. Every crossing costs at least
+            // 1 (sqrt(3)/2 on hex grids) + min(g(cardinal), g(diagonal)), so one of the
+            // two is always strictly closer to the goal, which rules out cycles.
+            let (near, far) = if crossing.fraction < 0.5 {
+                (crossing.cardinal, crossing.diagonal)
+            } else {
+                (crossing.diagonal, crossing.cardinal)
+            };
+            let g_current = self.g(current);
+            let next = [near, far]
+                .into_iter()
+                .find(|&p| self.is_free(p, grid) && self.g(p) < g_current - EPSILON)?;
+
+            // Diagonal steps become two moves through the free cardinal neighbor,
+            // unless the grid allows the diagonal move itself
+            if next == crossing.diagonal
+                && !grid.can_move(current, next, |pos| self.known_obstacles.contains(&pos))
+            {
+                path.push(crossing.cardinal);
+            }
+            path.push(next);
+            current = next;
+
+            // Safety check for infinite loops
+            if path.len() > 2 * grid.cell_count() {
+                return None;
+            }
+        }
+
+        Some(path)
+    }
+}
+
+impl PathfindingAlgorithm for FieldDStar {
+    fn find_path(
+        &mut self,
+        grid: &Grid,
+        start: Position,
+        goal: Position,
+        obstacles: &HashSet<Position>,
+    ) -> Option<Vec<Position>> {
+        if !self.initialized
+            || self.s_goal != goal
+            || (self.width, self.height, self.floors) != (grid.width, grid.height, grid.floors)
+            || self.metric != grid.metric()
+            || self.portals != grid.portals
+        {
+            let total_cells = grid.cell_count();
+            (self.width, self.height, self.floors) = (grid.width, grid.height, grid.floors);
+            self.metric = grid.metric();
+            self.portals = grid.portals.clone();
+            self.g_scores.resize(total_cells, f64::INFINITY);
+            self.rhs_scores.resize(total_cells, f64::INFINITY);
+            self.vertex_generations.resize(total_cells, 0);
+            self.s_start = start;
+            self.s_goal = goal;
+            self.known_obstacles.reset(obstacles);
+            self.initialize();
+        } else {
+            self.move_start(start);
+            self.update_obstacles(grid, obstacles);
+        }
+
+        if !self.compute_shortest_path(grid) {
+            return None;
+        }
+        self.last_interpolated_cost = self.g(self.s_start);
+        self.reconstruct_path(grid)
+    }
+
+    fn update_environment(
+        &mut self,
+        grid: &Grid,
+        obstacles: &HashSet<Position>,
+        changes: &EnvironmentChanges,
+    ) {
+        self.known_obstacles.update(changes);
+        if self.initialized {
+            self.update_obstacles(grid, obstacles);
+            self.update_cells(grid, &changes.walls);
+        }
+    }
+
+    fn search_metrics(&self) -> SearchMetrics {
+        self.metrics
+    }
+
+    fn set_abort_handle(&mut self, abort: AbortHandle) {
+        self.abort = abort;
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
@@ -0,0 +1,356 @@
+use crate::algorithms::common::{
+    AbortHandle, EnvironmentChanges, KnownObstacles, PathfindingAlgorithm, SearchMetrics,
+};
+use crate::grid::{Grid, Position};
+use std::any::Any;
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashSet};
+
+/// Marks cells that cannot reach the goal (or have not been reached yet).
+const UNREACHABLE: u32 = u32::MAX;
+
+/// Implements a flow field (a.k.a. Dijkstra map): the distance to the goal is
+/// stored for every cell of the grid, and the agent follows the field downhill.
+///
+/// The field is built once per goal with a Dijkstra search over move costs. When
+/// obstacles change it is repaired locally instead of rebuilt: cells that lost their
+/// only downhill neighbor are invalidated and refilled from the surrounding valid
+/// cells, and freed cells pull their neighborhood's distances down. Small obstacle
+/// changes therefore only touch a small part of the field, and the same field can
+/// be shared by any number of agents heading to the same goal.
+pub struct FlowField {
+    distances: Vec<u32>,
+    goal: Option<Position>,
+    width: usize,
+    height: usize,
+    floors: usize,
+    known_obstacles: KnownObstacles,
+    field_builds: usize,
+    cells_repaired: usize,
+    metrics: SearchMetrics,
+    abort: AbortHandle,
+}
+
+impl Default for FlowField {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl FlowField {
+    /// Creates a new flow field planner; the field is built on the first call.
+    pub fn new() -> Self {
+        FlowField {
+            distances: Vec::new(),
+            goal: None,
+            width: 0,
+            height: 0,
+            floors: 0,
+            known_obstacles: KnownObstacles::default(),
+            field_builds: 0,
+            cells_repaired: 0,
+            metrics: SearchMetrics::default(),
+            abort: AbortHandle::default(),
+        }
+    }
+
+    /// Number of times the whole field was built from scratch.
+    pub fn field_builds(&self) -> usize {
+        self.field_builds
+    }
+
+    /// Total number of cell updates (invalidations and refills) during incremental repairs.
+    pub fn cells_repaired(&self) -> usize {
+        self.cells_repaired
+    }
+
+    /// Distance to the goal for a cell, or `None` if the goal cannot be reached from it.
+    pub fn distance(&self, pos: Position) -> Option<u32> {
+        self.distances
+            .get(self.pos_to_index(pos))
+            .copied()
+            .filter(|&d| d != UNREACHABLE)
+    }
+
+    /// Convert a position to its index in the per-cell vectors (same as `Grid::index`)
+    #[inline(always)]
+    fn pos_to_index(&self, pos: Position) -> usize {
+        (pos.z * self.height + pos.y) * self.width + pos.x
+    }
+
+    fn is_free(&self, grid: &Grid, pos: Position) -> bool {
+        !grid.is_wall(pos) && !self.known_obstacles.contains(&pos)
+    }
+
+    /// Cost of stepping from `from` onto the adjacent cell `to`, if that move is possible
+    fn step_cost(&self, grid: &Grid, from: Position, to: Position) -> Option<u32> {
+        grid.can_move(from, to, |pos| self.known_obstacles.contains(&pos))
+            .then(|| grid.move_cost(from, to))
+    }
+
+    /// Cheapest-first search outwards from the goal over the whole grid. Returns
+    /// false when the search was aborted, leaving no field to repair.
+    fn build(&mut self, grid: &Grid, goal: Position) -> bool {
+        (self.width, self.height, self.floors) = (grid.width, grid.height, grid.floors);
+        self.goal = Some(goal);
+        self.distances = vec![UNREACHABLE; grid.cell_count()];
+
+        let goal_index = self.pos_to_index(goal);
+        self.distances[goal_index] = 0;
+        let mut heap = BinaryHeap::from([Reverse((0, goal))]);
+        self.metrics.pushed(heap.len());
+        while let Some(Reverse((distance, pos))) = heap.pop() {
+            self.metrics.popped();
+            if self.distances[self.pos_to_index(pos)] != distance {
+                continue; // Stale entry
+            }
+            if self.abort.is_aborted_after(self.metrics.nodes_expanded) {
+                self.goal = None;
+                return false;
+            }
+            self.relax_neighbors(grid, pos, distance, &mut heap);
+        }
+
+        self.field_builds += 1;
+        true
+    }
+
+    /// Lower the distances of free neighbors that can step onto `pos`, queueing the ones that improved
+    fn relax_neighbors(
+        &mut self,
+        grid: &Grid,
+        pos: Position,
+        distance: u32,
+        heap: &mut BinaryHeap<Reverse<(u32, Position)>>,
+    ) {
+        let neighbors = grid.get_neighbors(&pos);
+        self.metrics.nodes_expanded += 1;
+        self.metrics.nodes_generated += neighbors.len();
+        for neighbor in neighbors {
+            if !self.is_free(grid, neighbor) {
+                continue;
+            }
+            let Some(cost) = self.step_cost(grid, neighbor, pos) else {
+                continue;
+            };
+            let index = self.pos_to_index(neighbor);
+            if distance + cost < self.distances[index] {
+                self.distances[index] = distance + cost;
+                heap.push(Reverse((distance + cost, neighbor)));
+                self.metrics.pushed(heap.len());
+            }
+        }
+    }
+
+    /// Cheapest neighbor distance plus the step onto it, the value a free cell should hold
+    fn best_from_neighbors(&self, grid: &Grid, pos: Position) -> u32 {
+        grid.get_neighbors(&pos)
+            .into_iter()
+            .filter_map(|neighbor| {
+                let distance = self.distances[self.pos_to_index(neighbor)];
+                if distance == UNREACHABLE {
+                    return None;
+                }
+                Some(distance + self.step_cost(grid, pos, neighbor)?)
+            })
+            .min()
+            .unwrap_or(UNREACHABLE)
+    }
+
+    /// Queue the neighbors of an invalidated cell that may have depended on it
+    fn push_children(
+        &mut self,
+        grid: &Grid,
+        pos: Position,
+        distance: u32,
+        goal: Option<Position>,
+        candidates: &mut BinaryHeap<Reverse<(u32, Position)>>,
+    ) {
+        for child in grid.get_neighbors(&pos) {
+            let child_distance = distance + grid.move_cost(child, pos);
+            if Some(child) != goal && self.distances[self.pos_to_index(child)] == child_distance {
+                candidates.push(Reverse((child_distance, child)));
+                self.metrics.pushed(candidates.len());
+            }
+        }
+    }
+
+    /// Repair the field after obstacles appeared or disappeared
+    fn update_obstacles(&mut self, grid: &Grid, obstacles: &HashSet<Position>) {
+        let changed = self.known_obstacles.catch_up(obstacles);
+        if changed.is_empty() {
+            return;
+        }
+        let (added, removed): (Vec<Position>, Vec<Position>) = changed
+            .into_iter()
+            .partition(|pos| self.known_obstacles.contains(pos));
+        self.repair(grid, added, removed);
+    }
+
+    /// Repair the field after the `added` cells were blocked and the `removed` ones
+    /// freed, whether by obstacles or by walls
+    fn repair(&mut self, grid: &Grid, added: Vec<Position>, removed: Vec<Position>) {
+        let goal = self.goal;
+
+        // Invalidate every cell whose downhill neighbors were all invalidated. Candidates
+        // are checked in order of distance, so all of their parents have been settled.
+        let mut invalidated = Vec::new();
+        let mut candidates = BinaryHeap::new();
+        let seeds: Vec<(Position, u32)> = added
+            .iter()
+            .copied()
+            .filter(|&pos| Some(pos) != goal)
+            .map(|pos| (pos, self.distances[self.pos_to_index(pos)]))
+            .filter(|&(_, distance)| distance != UNREACHABLE)
+            .collect();
+        for &(pos, _) in &seeds {
+            let index = self.pos_to_index(pos);
+            self.distances[index] = UNREACHABLE;
+        }
+        for (pos, distance) in seeds {
+            self.cells_repaired += 1;
+            self.push_children(grid, pos, distance, goal, &mut candidates);
+        }
+        if grid.diagonal {
+            // A new obstacle also blocks the diagonal moves squeezing past it
+            for &pos in &added {
+                for neighbor in grid.get_neighbors(&pos) {
+                    let distance = self.distances[self.pos_to_index(neighbor)];
+                    if Some(neighbor) != goal && distance != UNREACHABLE {
+                        candidates.push(Reverse((distance, neighbor)));
+                        self.metrics.pushed(candidates.len());
+                    }
+                }
+            }
+        }
+        while let Some(Reverse((distance, pos))) = candidates.pop() {
+            self.metrics.popped();
+            let index = self.pos_to_index(pos);
+            if self.distances[index] != distance {
+                continue; // Already invalidated
+            }
+            let still_supported = grid.get_neighbors(&pos).into_iter().any(|parent| {
+                let parent_distance = self.distances[self.pos_to_index(parent)];
+                parent_distance != UNREACHABLE
+                    && self
+                        .step_cost(grid, pos, parent)
+                        .is_some_and(|cost| parent_distance + cost == distance)
+            });
+            if still_supported {
+                continue;
+            }
+            self.distances[index] = UNREACHABLE;
+            self.cells_repaired += 1;
+            invalidated.push(pos);
+            self.push_children(grid, pos, distance, goal, &mut candidates);
+        }
+
+        // Refill invalidated and freed cells from their valid neighbors, cheapest first.
+        // With diagonal movement a freed cell also reopens the diagonals squeezing past it.
+        let reopened: Vec<Position> = if grid.diagonal {
+            removed
+                .iter()
+                .flat_map(|pos| grid.get_neighbors(pos))
+                .collect()
+        } else {
+            Vec::new()
+        };
+        let mut heap = BinaryHeap::new();
+        for pos in invalidated.into_iter().chain(removed).chain(reopened) {
+            if !self.is_free(grid, pos) || Some(pos) == goal {
+                continue;
+            }
+            let candidate = self.best_from_neighbors(grid, pos);
+            let index = self.pos_to_index(pos);
+            if candidate < self.distances[index] {
+                self.distances[index] = candidate;
+                heap.push(Reverse((candidate, pos)));
+                self.metrics.pushed(heap.len());
+            }
+        }
+        while let Some(Reverse((distance, pos))) = heap.pop() {
+            self.metrics.popped();
+            if self.distances[self.pos_to_index(pos)] != distance {
+                continue; // Stale entry
+            }
+            self.cells_repaired += 1;
+            self.relax_neighbors(grid, pos, distance, &mut heap);
+        }
+    }
+
+    /// Follow the field downhill from `start` to the goal
+    fn descend(&self, grid: &Grid, start: Position, goal: Position) -> Option<Vec<Position>> {
+        let mut current = start;
+        let mut distance = self.distance(start)?;
+        let mut path = vec![start];
+        while current != goal {
+            // Cheapest step plus remaining distance; ties go to the lower field value
+            let (_, next_distance, next) = grid
+                .get_neighbors(&current)
+                .into_iter()
+                .filter_map(|neighbor| {
+                    let cost = self.step_cost(grid, current, neighbor)?;
+                    let d = self.distance(neighbor)?;
+                    Some((d + cost, d, neighbor))
+                })
+                .filter(|&(_, d, _)| d < distance)
+                .min()?;
+            (distance, current) = (next_distance, next);
+            path.push(current);
+        }
+        Some(path)
+    }
+}
+
+impl PathfindingAlgorithm for FlowField {
+    fn find_path(
+        &mut self,
+        grid: &Grid,
+        start: Position,
+        goal: Position,
+        obstacles: &HashSet<Position>,
+    ) -> Option<Vec<Position>> {
+        if self.goal != Some(goal)
+            || (self.width, self.height, self.floors) != (grid.width, grid.height, grid.floors)
+        {
+            self.known_obstacles.reset(obstacles);
+            if !self.build(grid, goal) {
+                return None;
+            }
+        } else {
+            self.update_obstacles(grid, obstacles);
+        }
+
+        self.descend(grid, start, goal)
+    }
+
+    fn update_environment(
+        &mut self,
+        grid: &Grid,
+        obstacles: &HashSet<Position>,
+        changes: &EnvironmentChanges,
+    ) {
+        self.known_obstacles.update(changes);
+        if self.goal.is_none() {
+            return;
+        }
+        self.update_obstacles(grid, obstacles);
+        if !changes.walls.is_empty() {
+            let (added, removed): (Vec<Position>, Vec<Position>) =
+                changes.walls.iter().partition(|&&pos| grid.is_wall(pos));
+            self.repair(grid, added, removed);
+        }
+    }
+
+    fn search_metrics(&self) -> SearchMetrics {
+        self.metrics
+    }
+
+    fn set_abort_handle(&mut self, abort: AbortHandle) {
+        self.abort = abort;
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
@@ -0,0 +1,683 @@
+use crate::algorithms::common::{
+    AbortHandle, EnvironmentChanges, KnownObstacles, PathfindingAlgorithm, SearchMetrics,
+};
+use crate::grid::{Grid, Position};
+use pathfinding::prelude::astar;
+use std::any::Any;
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap, HashSet};
+use std::time::Duration;
+use web_time::Instant;
+
+/// Entrances shorter than this get a single transition in the middle; longer
+/// ones get a transition at each end (Botea, Müller and Schaeffer, 2004).
+const MAX_SINGLE_TRANSITION_LENGTH: usize = 6;
+
+/// Implements Hierarchical Pathfinding A* (HPA*) from Botea, Müller and Schaeffer (2004).
+///
+/// The grid is partitioned into square clusters. Free stretches along each cluster
+/// border become entrances with one or two transition cells, and the shortest
+/// distances between transitions inside a cluster form an abstract graph. A query
+/// connects the start and goal to the transitions of their clusters, runs A* on the
+/// small abstract graph and then refines each abstract edge into grid moves.
+///
+/// Cluster borders are scanned up front, but the intra-cluster edges of a cluster
+/// are only computed the first time the abstract search reaches it, so the first
+/// query on a large grid does not pay for clusters far away from the path.
+///
+/// When obstacles change only the clusters containing the changed cells (and the
+/// borders they share with their neighbors) are rebuilt. Build and rebuild times
+/// are recorded so the cost of keeping the abstraction up to date can be compared
+/// with the time saved during planning. Paths are near-optimal, not optimal; with
+/// diagonal movement, borders are still only crossed with straight moves, and
+/// transitions are placed without regard to terrain costs.
+///
+/// Each floor of a multi-floor grid has its own layer of clusters; every flight of
+/// stairs is a transition between the clusters above and below each other. On a
+/// toroidal grid the clusters along opposite edges border each other too, and a
+/// portal between two clusters is a transition between them wherever they are.
+pub struct HpaStar {
+    cluster_size: usize,
+    width: usize,
+    height: usize,
+    floors: usize,
+    wrap: bool,
+    portals: HashMap<Position, Position>,
+    /// Other clusters reachable through a portal, for every cluster with one
+    portal_links: HashMap<usize, Vec<usize>>,
+    /// Number of clusters per row and per column of each floor
+    clusters_x: usize,
+    clusters_y: usize,
+    known_obstacles: KnownObstacles,
+    initialized: bool,
+    /// Transition cell pairs across each border, keyed by (cluster, neighbor cluster)
+    transitions: HashMap<(usize, usize), Vec<(Position, Position)>>,
+    /// Transition cells on the other side of a border, for every transition cell
+    crossings: HashMap<Position, Vec<Position>>,
+    /// Transition cells inside each cluster, indexed by cluster id
+    entrances: Vec<Vec<Position>>,
+    /// Whether the intra-cluster edges of each cluster are up to date
+    cluster_built: Vec<bool>,
+    /// Intra-cluster edges from each transition cell of a built cluster
+    intra_edges: HashMap<Position, Vec<(Position, u32)>>,
+    build_time: Duration,
+    rebuilds: usize,
+    rebuild_time: Duration,
+    /// Work of the abstract and refinement searches; building the abstraction is
+    /// covered by the build and rebuild times instead
+    metrics: SearchMetrics,
+    abort: AbortHandle,
+}
+
+impl HpaStar {
+    /// Creates a new HPA* planner with square clusters of `cluster_size` cells per side.
+    pub fn new(cluster_size: usize) -> Self {
+        HpaStar {
+            cluster_size: cluster_size.max(2),
+            width: 0,
+            height: 0,
+            floors: 0,
+            wrap: false,
+            portals: HashMap::new(),
+            portal_links: HashMap::new(),
+            clusters_x: 0,
+            clusters_y: 0,
+            known_obstacles: KnownObstacles::default(),
+            initialized: false,
+            transitions: HashMap::new(),
+            crossings: HashMap::new(),
+            entrances: Vec::new(),
+            cluster_built: Vec::new(),
+            intra_edges: HashMap::new(),
+            build_time: Duration::ZERO,
+            rebuilds: 0,
+            rebuild_time: Duration::ZERO,
+            metrics: SearchMetrics::default(),
+            abort: AbortHandle::default(),
+        }
+    }
+
+    /// Side length of a cluster in cells.
+    pub fn cluster_size(&self) -> usize {
+        self.cluster_size
+    }
+
+    /// Time spent building the abstraction (borders, plus clusters as searches reach them).
+    pub fn build_time(&self) -> Duration {
+        self.build_time
+    }
+
+    /// Number of partial rebuilds triggered by obstacle changes.
+    pub fn rebuilds(&self) -> usize {
+        self.rebuilds
+    }
+
+    /// Total time spent on partial rebuilds.
+    pub fn rebuild_time(&self) -> Duration {
+        self.rebuild_time
+    }
+
+    /// Number of clusters on each floor
+    fn clusters_per_floor(&self) -> usize {
+        self.clusters_x * self.clusters_y
+    }
+
+    fn cluster_of(&self, pos: Position) -> usize {
+        pos.z * self.clusters_per_floor()
+            + (pos.y / self.cluster_size) * self.clusters_x
+            + pos.x / self.cluster_size
+    }
+
+    /// Floor a cluster lies on
+    fn cluster_floor(&self, cluster: usize) -> usize {
+        cluster / self.clusters_per_floor()
+    }
+
+    /// Column and row of a cluster within its floor
+    fn cluster_coordinates(&self, cluster: usize) -> (usize, usize) {
+        let planar = cluster % self.clusters_per_floor();
+        (planar % self.clusters_x, planar / self.clusters_x)
+    }
+
+    /// Cell bounds of a cluster as (min_x, max_x, min_y, max_y), inclusive
+    fn cluster_bounds(&self, cluster: usize) -> (usize, usize, usize, usize) {
+        let (cx, cy) = self.cluster_coordinates(cluster);
+        let min_x = cx * self.cluster_size;
+        let min_y = cy * self.cluster_size;
+        (
+            min_x,
+            (min_x + self.cluster_size).min(self.width) - 1,
+            min_y,
+            (min_y + self.cluster_size).min(self.height) - 1,
+        )
+    }
+
+    fn in_cluster(&self, pos: Position, cluster: usize) -> bool {
+        let (min_x, max_x, min_y, max_y) = self.cluster_bounds(cluster);
+        pos.z == self.cluster_floor(cluster)
+            && pos.x >= min_x
+            && pos.x <= max_x
+            && pos.y >= min_y
+            && pos.y <= max_y
+    }
+
+    fn is_free(&self, grid: &Grid, pos: Position) -> bool {
+        !grid.is_wall(pos) && !self.known_obstacles.contains(&pos)
+    }
+
+    /// Cluster ids to the right of, below and on the floor above `cluster`, if any.
+    /// On a toroidal grid the last cluster of a row or column borders the first.
+    fn forward_neighbors(&self, cluster: usize) -> Vec<usize> {
+        let (cx, cy) = self.cluster_coordinates(cluster);
+        let mut result = Vec::with_capacity(3);
+        if cx + 1 < self.clusters_x {
+            result.push(cluster + 1);
+        } else if self.wrap && self.clusters_x > 1 {
+            result.push(cluster - cx);
+        }
+        if cy + 1 < self.clusters_y {
+            result.push(cluster + self.clusters_x);
+        } else if self.wrap && self.clusters_y > 1 {
+            result.push(cluster - cy * self.clusters_x);
+        }
+        if self.cluster_floor(cluster) + 1 < self.floors {
+            result.push(cluster + self.clusters_per_floor());
+        }
+        result
+    }
+
+    /// Cluster ids that have `cluster` among their forward neighbors
+    fn backward_neighbors(&self, cluster: usize) -> Vec<usize> {
+        let (cx, cy) = self.cluster_coordinates(cluster);
+        let mut result = Vec::with_capacity(3);
+        if cx > 0 {
+            result.push(cluster - 1);
+        } else if self.wrap && self.clusters_x > 1 {
+            result.push(cluster + self.clusters_x - 1);
+        }
+        if cy > 0 {
+            result.push(cluster - self.clusters_x);
+        } else if self.wrap && self.clusters_y > 1 {
+            result.push(cluster + (self.clusters_y - 1) * self.clusters_x);
+        }
+        if self.cluster_floor(cluster) > 0 {
+            result.push(cluster - self.clusters_per_floor());
+        }
+        result
+    }
+
+    /// Every border of a cluster, keyed like `transitions`: with its forward and
+    /// backward neighbors, and with the clusters it has portals to (the lower id first)
+    fn borders(&self, cluster: usize) -> Vec<(usize, usize)> {
+        let mut borders: Vec<(usize, usize)> = self
+            .forward_neighbors(cluster)
+            .into_iter()
+            .map(|neighbor| (cluster, neighbor))
+            .chain(
+                self.backward_neighbors(cluster)
+                    .into_iter()
+                    .map(|neighbor| (neighbor, cluster)),
+            )
+            .collect();
+        for &other in self.portal_links.get(&cluster).into_iter().flatten() {
+            let border = (cluster.min(other), cluster.max(other));
+            if !borders.contains(&border) {
+                borders.push(border);
+            }
+        }
+        borders
+    }
+
+    /// Transitions across one border: the entrances shared with an adjacent cluster,
+    /// plus the open portals leading from `cluster` to `neighbor` (recorded under the
+    /// lower cluster id only)
+    fn build_border(
+        &self,
+        grid: &Grid,
+        cluster: usize,
+        neighbor: usize,
+    ) -> Vec<(Position, Position)> {
+        let mut transitions = Vec::new();
+        if cluster < neighbor {
+            transitions.extend(
+                self.portals
+                    .iter()
+                    .map(|(&a, &b)| (a, b))
+                    .filter(|&(a, b)| {
+                        self.cluster_of(a) == cluster
+                            && self.cluster_of(b) == neighbor
+                            && self.is_free(grid, a)
+                            && self.is_free(grid, b)
+                    }),
+            );
+            transitions.sort();
+        }
+        if self.forward_neighbors(cluster).contains(&neighbor) {
+            transitions.extend(self.build_adjacent_border(grid, cluster, neighbor));
+        }
+        transitions
+    }
+
+    /// Find the entrances on the border between `cluster` and the cluster right of
+    /// or below it, and place transition cells on each. Between floors every
+    /// open flight of stairs is a transition of its own.
+    fn build_adjacent_border(
+        &self,
+        grid: &Grid,
+        cluster: usize,
+        neighbor: usize,
+    ) -> Vec<(Position, Position)> {
+        let (min_x, max_x, min_y, max_y) = self.cluster_bounds(cluster);
+        let z = self.cluster_floor(cluster);
+        if self.cluster_floor(neighbor) != z {
+            return (min_x..=max_x)
+                .flat_map(|x| (min_y..=max_y).map(move |y| Position { x, y, z }))
+                .filter(|pos| grid.stair_links(pos).iter().any(|above| above.z > z))
+                .map(|pos| (pos, Position { z: z + 1, ..pos }))
+                .filter(|&(below, above)| self.is_free(grid, below) && self.is_free(grid, above))
+                .collect();
+        }
+
+        // Clusters in the same row share a vertical border
+        let horizontal =
+            self.cluster_coordinates(neighbor).1 == self.cluster_coordinates(cluster).1;
+
+        // Cell pairs straddling the border, in order along it; the cells across the
+        // edge of a toroidal grid are in the first column or row
+        let (next_x, next_y) = ((max_x + 1) % self.width, (max_y + 1) % self.height);
+        let pairs: Vec<(Position, Position)> = if horizontal {
+            (min_y..=max_y)
+                .map(|y| (Position { x: max_x, y, z }, Position { x: next_x, y, z }))
+                .collect()
+        } else {
+            (min_x..=max_x)
+                .map(|x| (Position { x, y: max_y, z }, Position { x, y: next_y, z }))
+                .collect()
+        };
+
+        let mut transitions = Vec::new();
+        let mut run: Vec<(Position, Position)> = Vec::new();
+        for pair in pairs.into_iter().map(Some).chain(std::iter::once(None)) {
+            match pair {
+                Some((a, b)) if self.is_free(grid, a) && self.is_free(grid, b) => run.push((a, b)),
+                _ => {
+                    // End of an entrance
+                    if run.len() >= MAX_SINGLE_TRANSITION_LENGTH {
+                        transitions.push(run[0]);
+                        transitions.push(run[run.len() - 1]);
+                    } else if !run.is_empty() {
+                        transitions.push(run[run.len() / 2]);
+                    }
+                    run.clear();
+                }
+            }
+        }
+        transitions
+    }
+
+    /// Free/blocked state of every cell in a cluster, in cluster-local row-major order
+    fn free_mask(&self, grid: &Grid, cluster: usize) -> Vec<bool> {
+        let (min_x, max_x, min_y, max_y) = self.cluster_bounds(cluster);
+        let z = self.cluster_floor(cluster);
+        (min_y..=max_y)
+            .flat_map(|y| (min_x..=max_x).map(move |x| Position { x, y, z }))
+            .map(|pos| self.is_free(grid, pos))
+            .collect()
+    }
+
+    /// Shortest move-cost distances from `from` to each of `targets`, moving only inside
+    /// `cluster`, or from each of `targets` to `from` when `reverse` is set (terrain
+    /// makes the two differ)
+    fn cluster_distances(
+        &self,
+        grid: &Grid,
+        free: &[bool],
+        from: Position,
+        cluster: usize,
+        targets: &[Position],
+        reverse: bool,
+    ) -> Vec<Option<u32>> {
+        let (min_x, max_x, min_y, max_y) = self.cluster_bounds(cluster);
+        let width = max_x - min_x + 1;
+        let local_index = |pos: Position| (pos.y - min_y) * width + (pos.x - min_x);
+        let inside = |pos: Position| self.in_cluster(pos, cluster);
+
+        let mut distances = vec![u32::MAX; width * (max_y - min_y + 1)];
+        distances[local_index(from)] = 0;
+        let mut heap = BinaryHeap::from([Reverse((0, from))]);
+        while let Some(Reverse((distance, pos))) = heap.pop() {
+            if distance > distances[local_index(pos)] {
+                continue;
+            }
+            for neighbor in grid.get_neighbors(&pos) {
+                let (step_from, step_to) = if reverse {
+                    (neighbor, pos)
+                } else {
+                    (pos, neighbor)
+                };
+                // Both cells a diagonal move squeezes past lie inside the cluster too
+                if !inside(neighbor)
+                    || !free[local_index(neighbor)]
+                    || !grid.can_move(step_from, step_to, |cell| !free[local_index(cell)])
+                {
+                    continue;
+                }
+                let index = local_index(neighbor);
+                let next = distance + grid.move_cost(step_from, step_to);
+                if next < distances[index] {
+                    distances[index] = next;
+                    heap.push(Reverse((next, neighbor)));
+                }
+            }
+        }
+
+        targets
+            .iter()
+            .map(|&target| Some(distances[local_index(target)]).filter(|&d| d != u32::MAX))
+            .collect()
+    }
+
+    /// Recompute the transition cells and intra-cluster edges of one cluster
+    fn build_cluster(&mut self, grid: &Grid, cluster: usize) {
+        let borders = self.borders(cluster);
+
+        let mut entrances = Vec::new();
+        for border in borders {
+            for &(a, b) in self.transitions.get(&border).into_iter().flatten() {
+                let inside = if self.in_cluster(a, cluster) { a } else { b };
+                if !entrances.contains(&inside) {
+                    entrances.push(inside);
+                }
+            }
+        }
+
+        for old in std::mem::take(&mut self.entrances[cluster]) {
+            self.intra_edges.remove(&old);
+        }
+
+        let free = self.free_mask(grid, cluster);
+        for &from in &entrances {
+            let distances = self.cluster_distances(grid, &free, from, cluster, &entrances, false);
+            let edges = entrances
+                .iter()
+                .zip(distances)
+                .filter(|(&to, _)| to != from)
+                .filter_map(|(&to, distance)| distance.map(|d| (to, d)))
+                .collect();
+            self.intra_edges.insert(from, edges);
+        }
+
+        self.entrances[cluster] = entrances;
+        self.cluster_built[cluster] = true;
+    }
+
+    /// Build a cluster's intra-cluster edges the first time a search needs them
+    fn ensure_cluster(&mut self, grid: &Grid, cluster: usize) {
+        if !self.cluster_built[cluster] {
+            let started = Instant::now();
+            self.build_cluster(grid, cluster);
+            self.build_time += started.elapsed();
+        }
+    }
+
+    /// Replace the transitions across one border, keeping `crossings` in sync
+    fn set_border(&mut self, border: (usize, usize), transitions: Vec<(Position, Position)>) {
+        for (a, b) in self.transitions.remove(&border).unwrap_or_default() {
+            for (from, to) in [(a, b), (b, a)] {
+                if let Some(targets) = self.crossings.get_mut(&from) {
+                    targets.retain(|&t| t != to);
+                }
+            }
+        }
+        for &(a, b) in &transitions {
+            self.crossings.entry(a).or_default().push(b);
+            self.crossings.entry(b).or_default().push(a);
+        }
+        self.transitions.insert(border, transitions);
+    }
+
+    /// Scan every cluster border; clusters themselves are built on demand
+    fn build(&mut self, grid: &Grid) {
+        let started = Instant::now();
+        (self.width, self.height, self.floors) = (grid.width, grid.height, grid.floors);
+        self.wrap = grid.wrap;
+        self.portals = grid.portals.clone();
+        self.clusters_x = grid.width.div_ceil(self.cluster_size);
+        self.clusters_y = grid.height.div_ceil(self.cluster_size);
+        let num_clusters = self.clusters_per_floor() * grid.floors;
+
+        // Portals within a cluster need no transition; the cluster's own search uses them
+        self.portal_links.clear();
+        for (&a, &b) in &grid.portals {
+            let (from, to) = (self.cluster_of(a), self.cluster_of(b));
+            let links = self.portal_links.entry(from).or_default();
+            if from != to && !links.contains(&to) {
+                links.push(to);
+            }
+        }
+        for links in self.portal_links.values_mut() {
+            links.sort();
+        }
+
+        self.transitions.clear();
+        self.crossings.clear();
+        self.intra_edges.clear();
+        for cluster in 0..num_clusters {
+            for (owner, neighbor) in self.borders(cluster) {
+                if owner == cluster {
+                    let transitions = self.build_border(grid, cluster, neighbor);
+                    self.set_border((cluster, neighbor), transitions);
+                }
+            }
+        }
+        self.entrances = vec![Vec::new(); num_clusters];
+        self.cluster_built = vec![false; num_clusters];
+
+        self.initialized = true;
+        self.build_time = started.elapsed();
+    }
+
+    /// Rebuild only the clusters touched by changed obstacle cells
+    fn update_obstacles(&mut self, grid: &Grid, obstacles: &HashSet<Position>) {
+        let changed = self.known_obstacles.catch_up(obstacles);
+        if changed.is_empty() {
+            return;
+        }
+        self.rebuild(grid, &changed);
+    }
+
+    /// Rebuild the clusters containing the changed cells, and the borders they share
+    fn rebuild(&mut self, grid: &Grid, changed: &[Position]) {
+        let started = Instant::now();
+        let dirty: HashSet<usize> = changed.iter().map(|&pos| self.cluster_of(pos)).collect();
+
+        // Borders of dirty clusters change, which changes the entrances on both sides
+        let mut affected = dirty.clone();
+        for &cluster in &dirty {
+            let borders: Vec<(usize, usize)> = self
+                .transitions
+                .keys()
+                .filter(|(a, b)| *a == cluster || *b == cluster)
+                .copied()
+                .collect();
+            for (a, b) in borders {
+                let transitions = self.build_border(grid, a, b);
+                self.set_border((a, b), transitions);
+                affected.insert(a);
+                affected.insert(b);
+            }
+        }
+
+        // Clusters no search has reached yet will pick up the changes when built
+        for cluster in affected {
+            if self.cluster_built[cluster] {
+                self.build_cluster(grid, cluster);
+            }
+        }
+
+        self.rebuilds += 1;
+        self.rebuild_time += started.elapsed();
+    }
+
+    /// Expand an abstract path into grid moves
+    fn refine(
+        &self,
+        grid: &Grid,
+        abstract_path: &[Position],
+        metrics: &mut SearchMetrics,
+    ) -> Option<Vec<Position>> {
+        let mut path = vec![abstract_path[0]];
+        for window in abstract_path.windows(2) {
+            let (from, to) = (window[0], window[1]);
+            let cluster = self.cluster_of(from);
+            if cluster != self.cluster_of(to) {
+                // Transition across a border: the cells are adjacent
+                path.push(to);
+                continue;
+            }
+
+            let (segment, _) = astar(
+                &from,
+                |p| {
+                    // An aborted search expands nothing more, so the open list runs dry
+                    if self.abort.is_aborted_after(metrics.nodes_expanded) {
+                        return Vec::new();
+                    }
+                    metrics.expanded(
+                        grid.get_neighbors(p)
+                            .into_iter()
+                            .filter(|&n| {
+                                self.in_cluster(n, cluster)
+                                    && grid.can_move(*p, n, |cell| {
+                                        self.known_obstacles.contains(&cell)
+                                    })
+                            })
+                            .map(|n| (n, grid.move_cost(*p, n)))
+                            .collect::<Vec<_>>(),
+                    )
+                },
+                |p| grid.heuristic(*p, to),
+                |p| *p == to,
+            )?;
+            path.extend(segment.into_iter().skip(1));
+        }
+        Some(path)
+    }
+}
+
+impl PathfindingAlgorithm for HpaStar {
+    fn find_path(
+        &mut self,
+        grid: &Grid,
+        start: Position,
+        goal: Position,
+        obstacles: &HashSet<Position>,
+    ) -> Option<Vec<Position>> {
+        if !self.initialized
+            || (self.width, self.height, self.floors) != (grid.width, grid.height, grid.floors)
+            || self.wrap != grid.wrap
+            || self.portals != grid.portals
+        {
+            self.known_obstacles.reset(obstacles);
+            self.build(grid);
+        } else {
+            self.update_obstacles(grid, obstacles);
+        }
+
+        if start == goal {
+            return Some(vec![start]);
+        }
+
+        // Connect the start and goal to the transitions of their clusters
+        let start_cluster = self.cluster_of(start);
+        let goal_cluster = self.cluster_of(goal);
+        self.ensure_cluster(grid, start_cluster);
+        self.ensure_cluster(grid, goal_cluster);
+        let start_targets: Vec<Position> = self.entrances[start_cluster]
+            .iter()
+            .copied()
+            .chain(std::iter::once(goal).filter(|_| start_cluster == goal_cluster))
+            .collect();
+        let start_edges: Vec<(Position, u32)> = start_targets
+            .iter()
+            .zip(self.cluster_distances(
+                grid,
+                &self.free_mask(grid, start_cluster),
+                start,
+                start_cluster,
+                &start_targets,
+                false,
+            ))
+            .filter_map(|(&target, distance)| distance.map(|d| (target, d)))
+            .collect();
+        let goal_entrances = &self.entrances[goal_cluster];
+        let goal_links: HashMap<Position, u32> = goal_entrances
+            .iter()
+            .zip(self.cluster_distances(
+                grid,
+                &self.free_mask(grid, goal_cluster),
+                goal,
+                goal_cluster,
+                goal_entrances,
+                true,
+            ))
+            .filter_map(|(&entrance, distance)| distance.map(|d| (entrance, d)))
+            .collect();
+
+        let (abstract_path, _) = astar(
+            &start,
+            |p| {
+                if self.abort.is_aborted_after(self.metrics.nodes_expanded) {
+                    return Vec::new();
+                }
+                let mut successors = Vec::new();
+                if *p == start {
+                    successors.extend(start_edges.iter().copied());
+                }
+                if let Some(&distance) = goal_links.get(p) {
+                    successors.push((goal, distance));
+                }
+                let targets = self.crossings.get(p).cloned().unwrap_or_default();
+                if !targets.is_empty() {
+                    // Reaching a transition cell means the search has entered its cluster
+                    self.ensure_cluster(grid, self.cluster_of(*p));
+                    successors.extend(self.intra_edges.get(p).into_iter().flatten().copied());
+                    successors.extend(targets.into_iter().map(|t| (t, grid.move_cost(*p, t))));
+                }
+                self.metrics.expanded(successors)
+            },
+            |p| grid.heuristic(*p, goal),
+            |p| *p == goal,
+        )?;
+
+        let mut metrics = self.metrics;
+        let path = self.refine(grid, &abstract_path, &mut metrics);
+        self.metrics = metrics;
+        path
+    }
+
+    fn update_environment(
+        &mut self,
+        grid: &Grid,
+        _obstacles: &HashSet<Position>,
+        changes: &EnvironmentChanges,
+    ) {
+        // Obstacle changes are picked up by the next query; walls must be caught now
+        self.known_obstacles.update(changes);
+        if self.initialized && !changes.walls.is_empty() {
+            self.rebuild(grid, &changes.walls);
+        }
+    }
+
+    fn search_metrics(&self) -> SearchMetrics {
+        self.metrics
+    }
+
+    fn set_abort_handle(&mut self, abort: AbortHandle) {
+        self.abort = abort;
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
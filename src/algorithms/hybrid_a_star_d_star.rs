@@ -1,41 +1,111 @@
-use crate::algorithms::common::PathfindingAlgorithm;
 use crate::algorithms::a_star::AStar;
+use crate::algorithms::common::{
+    AbortHandle, EnvironmentChanges, KnownObstacles, ObstacleLifetimes, PathfindingAlgorithm,
+    SearchMetrics,
+};
 use crate::algorithms::d_star_lite::DStarLite;
+use crate::algorithms::search_space::{GridSpace, ObstacleCost, SearchSpace};
+use crate::algorithms::switching_policy::{SwitchContext, SwitchingPolicy, SwitchingPolicyKind};
 use crate::grid::{Grid, Position};
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
+use std::hash::Hash;
+use web_time::Instant;
 
 /// Hybrid algorithm that uses A* for initial path finding and D* Lite Simple for updates
-pub struct HybridAStarDStar {
-    a_star: AStar,
-    d_star_lite: DStarLite,
+///
+/// Like D* Lite it searches any `SearchSpace` (see `search`); the `PathfindingAlgorithm`
+/// implementation runs it on the simulation grid.
+pub struct HybridAStarDStar<N = Position> {
+    d_star_lite: DStarLite<N>,
     initial_path_found: bool,
-    last_start: Position,
-    last_goal: Position,
-    last_obstacles: HashSet<Position>,
+    last_start: N,
+    last_goals: Vec<N>,
+    known_obstacles: KnownObstacles, // Obstacles seen on the last call (grid adapter)
+    last_obstacle_count: usize,      // How many there were on the last call
+    pending_changes: HashSet<N>,     // Changed nodes D* Lite hasn't been told about yet
+    lifetimes: ObstacleLifetimes,    // Steps known obstacles stay (grid adapter)
+    obstacle_cost: ObstacleCost,     // How expiring obstacles are priced (grid adapter)
     // Add usage tracking
     a_star_usage_count: usize,
     d_star_usage_count: usize,
+    a_star_metrics: SearchMetrics, // Work done by the A* searches; D* Lite counts its own
+    policy_kind: SwitchingPolicyKind,
+    policy: Box<dyn SwitchingPolicy>, // Decides between A* and D* Lite after the first path
+    abort: AbortHandle,               // Gives up either planner's search when aborted
 }
 
 impl HybridAStarDStar {
-    pub fn new(start: Position, goal: Position, grid_size: usize) -> Self {
+    pub fn new(start: Position, goal: Position, width: usize, height: usize) -> Self {
+        Self::with_policy(start, goal, width, height, SwitchingPolicyKind::DEFAULT)
+    }
+
+    /// Creates a hybrid planner that switches between A* and D* Lite according to `policy_kind`.
+    pub fn with_policy(
+        start: Position,
+        goal: Position,
+        width: usize,
+        height: usize,
+        policy_kind: SwitchingPolicyKind,
+    ) -> Self {
+        Self::with_node_count(start, goal, width * height, policy_kind)
+    }
+
+    /// Prices obstacles that are going to expire by `obstacle_cost`, in the searches
+    /// of both planners.
+    pub fn with_obstacle_cost(mut self, obstacle_cost: ObstacleCost) -> Self {
+        self.obstacle_cost = obstacle_cost;
+        self
+    }
+}
+
+impl<N: Copy + Eq + Hash + Ord + 'static> HybridAStarDStar<N> {
+    /// Creates a hybrid planner for a search space with `node_count` nodes.
+    pub fn with_node_count(
+        start: N,
+        goal: N,
+        node_count: usize,
+        policy_kind: SwitchingPolicyKind,
+    ) -> Self {
         HybridAStarDStar {
-            a_star: AStar::new(),
-            d_star_lite: DStarLite::new(start, goal, grid_size),
+            d_star_lite: DStarLite::with_node_count(start, goal, node_count),
             initial_path_found: false,
             last_start: start,
-            last_goal: goal,
-            last_obstacles: HashSet::new(),
+            last_goals: vec![goal],
+            known_obstacles: KnownObstacles::default(),
+            last_obstacle_count: 0,
+            pending_changes: HashSet::new(),
+            lifetimes: ObstacleLifetimes::default(),
+            obstacle_cost: ObstacleCost::default(),
             a_star_usage_count: 0,
             d_star_usage_count: 0,
+            a_star_metrics: SearchMetrics::default(),
+            policy_kind,
+            policy: policy_kind.create(),
+            abort: AbortHandle::default(),
         }
     }
 
+    /// Switching policy in use.
+    pub fn policy_kind(&self) -> SwitchingPolicyKind {
+        self.policy_kind
+    }
+
+    /// Give up the searches of both planners once `abort` is aborted
+    pub fn set_abort_handle(&mut self, abort: AbortHandle) {
+        self.d_star_lite.set_abort_handle(abort.clone());
+        self.abort = abort;
+    }
+
     /// Get usage statistics
     pub fn get_usage_stats(&self) -> (usize, usize) {
         (self.a_star_usage_count, self.d_star_usage_count)
     }
 
+    /// Nodes expanded and generated and open list work of both planners' searches
+    pub fn metrics(&self) -> SearchMetrics {
+        self.a_star_metrics.merge(self.d_star_lite.metrics())
+    }
+
     /// Print detailed usage statistics
     pub fn print_usage_stats(&self) {
         let total_calls = self.a_star_usage_count + self.d_star_usage_count;
@@ -52,15 +122,23 @@ impl HybridAStarDStar {
 
         println!("\n=== HYBRID ALGORITHM USAGE STATISTICS ===");
         println!("Total pathfinding calls: {}", total_calls);
-        println!("A* usage: {} calls ({:.1}%)", self.a_star_usage_count, a_star_percentage);
-        println!("D* Lite Simple usage: {} calls ({:.1}%)", self.d_star_usage_count, d_star_percentage);
+        println!(
+            "A* usage: {} calls ({:.1}%)",
+            self.a_star_usage_count, a_star_percentage
+        );
+        println!(
+            "D* Lite Simple usage: {} calls ({:.1}%)",
+            self.d_star_usage_count, d_star_percentage
+        );
         println!();
-        
+
         if total_calls > 0 {
             if self.a_star_usage_count == 1 && self.d_star_usage_count > 0 {
                 println!("✓ Optimal hybrid performance: A* used once for initial path, D* Lite Simple handled all updates");
             } else if self.a_star_usage_count > 1 {
-                println!("⚠ Multiple A* calls detected - may indicate significant environment changes");
+                println!(
+                    "⚠ Multiple A* calls detected - may indicate significant environment changes"
+                );
                 println!("  This could be due to goal changes or major start position jumps");
             } else if self.d_star_usage_count == 0 {
                 println!("⚠ Only A* was used - no incremental updates occurred");
@@ -68,27 +146,47 @@ impl HybridAStarDStar {
         }
     }
 
-    /// Check if we need to use A* (first run or major changes)
-    fn should_use_astar(&self, start: Position, goal: Position, obstacles: &HashSet<Position>) -> bool {
-        // Use A* if:
-        // 1. No initial path found yet
-        // 2. Goal changed
-        // 3. Start changed significantly (more than a few steps)
-        if !self.initial_path_found || self.last_goal != goal {
-            return true;
-        }
-        
-        // Check if start moved significantly
-        let start_distance = (start.x as i32 - self.last_start.x as i32).abs() + 
-                           (start.y as i32 - self.last_start.y as i32).abs();
-        if start_distance > 3 {
+    /// Plans from `start` to the closest of `goals` in any search space. `changed` lists
+    /// the nodes whose edge costs changed since the previous call.
+    pub fn search<S: SearchSpace<Node = N>>(
+        &mut self,
+        space: &S,
+        start: N,
+        goals: &[N],
+        changed: &[N],
+    ) -> Option<Vec<N>> {
+        let context = SwitchContext {
+            start_jump: space.heuristic(self.last_start, start) as usize,
+            obstacle_delta: changed.len(),
+            changed_nodes: changed.len(),
+        };
+        self.search_with_context(space, start, goals, changed, &context)
+    }
+
+    /// Time one call and report it to the switching policy
+    fn search_with_context<S: SearchSpace<Node = N>>(
+        &mut self,
+        space: &S,
+        start: N,
+        goals: &[N],
+        changed: &[N],
+        context: &SwitchContext,
+    ) -> Option<Vec<N>> {
+        let started = Instant::now();
+        self.pending_changes.extend(changed);
+        let use_astar = self.should_use_astar(goals, context);
+        let result = self.plan(space, start, goals, use_astar);
+        self.policy.record(use_astar, started.elapsed());
+        result
+    }
+
+    /// Check if we need to use A* (first run, goal change, or the switching policy says so)
+    fn should_use_astar(&mut self, goals: &[N], context: &SwitchContext) -> bool {
+        if !self.initial_path_found || self.last_goals != goals {
             return true;
         }
-        
-        // Check if obstacles changed significantly
-        let obstacles_changed = obstacles != &self.last_obstacles;
-        obstacles_changed && 
-            (obstacles.len() as i32 - self.last_obstacles.len() as i32).abs() > 5
+
+        self.policy.should_use_astar(context)
     }
 }
 
@@ -100,19 +198,102 @@ impl PathfindingAlgorithm for HybridAStarDStar {
         goal: Position,
         obstacles: &HashSet<Position>,
     ) -> Option<Vec<Position>> {
-        // Check if we should use A* for this computation
-        if self.should_use_astar(start, goal, obstacles) {
+        self.find_path_to_any(grid, start, &[goal], obstacles)
+    }
+
+    fn find_path_to_any(
+        &mut self,
+        grid: &Grid,
+        start: Position,
+        goals: &[Position],
+        obstacles: &HashSet<Position>,
+    ) -> Option<Vec<Position>> {
+        let changed = self.known_obstacles.catch_up(obstacles);
+        self.d_star_lite.fit_grid(grid);
+        let (dx, dy) = grid.metric().offset(self.last_start, start);
+        let context = SwitchContext {
+            start_jump: (dx.unsigned_abs() + dy.unsigned_abs()) as usize,
+            obstacle_delta: obstacles.len().abs_diff(self.last_obstacle_count),
+            changed_nodes: changed.len(),
+        };
+        self.last_obstacle_count = obstacles.len();
+        let lifetimes = std::mem::take(&mut self.lifetimes);
+        let path = self.search_with_context(
+            &GridSpace::new(grid, obstacles)
+                .with_lifetimes(self.obstacle_cost.lifetimes(&lifetimes)),
+            start,
+            goals,
+            &changed,
+            &context,
+        );
+        self.lifetimes = lifetimes;
+        match self.obstacle_cost {
+            ObstacleCost::TimeAware => path.map(|path| self.lifetimes.wait_for(grid, &path)),
+            ObstacleCost::Blocked => path,
+        }
+    }
+
+    fn get_usage_stats(&self) -> (usize, usize) {
+        (self.a_star_usage_count, self.d_star_usage_count)
+    }
+
+    fn search_metrics(&self) -> SearchMetrics {
+        self.metrics()
+    }
+
+    fn set_abort_handle(&mut self, abort: AbortHandle) {
+        HybridAStarDStar::set_abort_handle(self, abort);
+    }
+
+    fn update_environment(
+        &mut self,
+        grid: &Grid,
+        _obstacles: &HashSet<Position>,
+        changes: &EnvironmentChanges,
+    ) {
+        // Passed on to D* Lite the next time it answers a query, which repairs its
+        // search around the walls rather than starting over
+        self.known_obstacles.update(changes);
+        if !changes.walls.is_empty() {
+            self.d_star_lite.track_walls(grid);
+        }
+        self.pending_changes.extend(&changes.walls);
+    }
+
+    fn update_obstacle_lifetimes(&mut self, lifetimes: &HashMap<Position, usize>) {
+        let changed = self.lifetimes.update(lifetimes);
+        if self.obstacle_cost == ObstacleCost::TimeAware {
+            self.pending_changes.extend(changed);
+        }
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn std::any::Any {
+        self
+    }
+}
+
+impl<N: Copy + Eq + Hash + Ord + 'static> HybridAStarDStar<N> {
+    /// Answer a query with the planner chosen by `should_use_astar`
+    fn plan<S: SearchSpace<Node = N>>(
+        &mut self,
+        space: &S,
+        start: N,
+        goals: &[N],
+        use_astar: bool,
+    ) -> Option<Vec<N>> {
+        if use_astar {
             // Increment A* usage counter
             self.a_star_usage_count += 1;
-            
+
             // Use A* to find initial path
-            if let Some(path) = self.a_star.find_path(grid, start, goal, obstacles) {
+            if let Some(path) =
+                AStar::search_counted(space, start, goals, &mut self.a_star_metrics, &self.abort)
+            {
                 // Update tracking variables
                 self.last_start = start;
-                self.last_goal = goal;
-                self.last_obstacles = obstacles.clone();
+                self.last_goals = goals.to_vec();
                 self.initial_path_found = true;
-                
+
                 Some(path)
             } else {
                 None
@@ -120,29 +301,22 @@ impl PathfindingAlgorithm for HybridAStarDStar {
         } else {
             // Increment D* Lite Simple usage counter
             self.d_star_usage_count += 1;
-            
-            // Use D* Lite Simple for incremental updates
-            let result = self.d_star_lite.find_path(grid, start, goal, obstacles);
-            
+
+            // Use D* Lite Simple for incremental updates, passing on every change
+            // made while A* was answering
+            let changed: Vec<N> = self.pending_changes.drain().collect();
+            let result = self.d_star_lite.plan(space, start, goals, &changed);
+
             // Update tracking variables
             self.last_start = start;
-            self.last_obstacles = obstacles.clone();
-            
-            if let Some(ref _path) = result {
+
+            if result.is_some() || self.abort.is_aborted() {
                 result
             } else {
                 // Fallback to A* if D* Lite Simple fails
                 self.a_star_usage_count += 1;
-                self.a_star.find_path(grid, start, goal, obstacles)
+                AStar::search_counted(space, start, goals, &mut self.a_star_metrics, &self.abort)
             }
         }
     }
-    
-    fn get_usage_stats(&self) -> (usize, usize) {
-        (self.a_star_usage_count, self.d_star_usage_count)
-    }
-
-    fn as_any_mut(&mut self) -> &mut dyn std::any::Any {
-        self
-    }
-}
\ No newline at end of file
+}
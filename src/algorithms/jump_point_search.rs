@@ -0,0 +1,452 @@
+use crate::algorithms::common::{AbortHandle, PathfindingAlgorithm, SearchMetrics};
+use crate::grid::{Cell, Grid, Position};
+use pathfinding::prelude::astar;
+use std::any::Any;
+use std::collections::HashSet;
+
+/// Direction of travel into a jump point. `(0, 0)` marks the start node, which
+/// has no parent and therefore expands in every direction.
+type Direction = (i32, i32);
+
+/// Implements Jump Point Search for 4-connected, uniform-cost grids.
+///
+/// Paths are searched in canonical "vertical first" order: vertical runs may
+/// branch horizontally at any cell, while horizontal runs only turn when a
+/// forced neighbor appears. Only jump points are pushed onto the open list, so
+/// large open areas are crossed without expanding every cell.
+///
+/// With diagonal movement the classic 8-connected rules (Harabor and Grastien,
+/// 2011) are used instead, in the variant that never cuts corners: diagonal runs
+/// probe both straight directions at every cell, and straight runs stop where a
+/// cell beside them opens up after being blocked.
+///
+/// Jumping relies on uniform costs, so on weighted grids every cell next to (or
+/// on) terrain is treated like the goal: runs stop there, and it is expanded in
+/// every direction like the start. Open ground away from terrain is still jumped.
+/// Stairs and portals are treated the same way, and from there the search
+/// continues on the floor above or below or at the portal's other end.
+///
+/// There are no jump rules for hex grids, so there every neighbor is a successor
+/// and the search is plain A*. The same goes for toroidal grids, where a run across
+/// open ground could circle the grid forever.
+#[derive(Default)]
+pub struct JumpPointSearch {
+    metrics: SearchMetrics,
+    abort: AbortHandle,
+}
+
+impl JumpPointSearch {
+    /// Creates a new instance of the Jump Point Search algorithm provider.
+    pub fn new() -> Self {
+        JumpPointSearch::default()
+    }
+
+    /// Returns true if the cell is inside the grid and not blocked by a wall or known obstacle.
+    fn walkable(grid: &Grid, obstacles: &HashSet<Position>, z: usize, x: i32, y: i32) -> bool {
+        if !grid.in_bounds(x, y) {
+            return false;
+        }
+        let pos = Position {
+            x: x as usize,
+            y: y as usize,
+            z,
+        };
+        !grid.is_wall(pos) && !obstacles.contains(&pos)
+    }
+
+    /// Returns true if runs have to stop at a cell and expand it in every direction,
+    /// like the goal and the start: next to terrain, on stairs, which lead to
+    /// another floor, and on portals.
+    fn must_expand(grid: &Grid, pos: Position) -> bool {
+        grid.cell(pos) == Cell::Stairs
+            || grid.portals.contains_key(&pos)
+            || Self::near_terrain(grid, pos)
+    }
+
+    /// Returns true if the cell or any cell around it carries a terrain cost, so
+    /// costs around it are not uniform and jumping across it could miss a cheaper path.
+    fn near_terrain(grid: &Grid, pos: Position) -> bool {
+        let xs = pos.x.saturating_sub(1)..=(pos.x + 1).min(grid.width - 1);
+        xs.into_iter().any(|x| {
+            (pos.y.saturating_sub(1)..=(pos.y + 1).min(grid.height - 1))
+                .any(|y| grid.terrain_cost(Position { x, y, ..pos }) > 1)
+        })
+    }
+
+    /// Jump horizontally from `(x, y)` until a jump point, the goal, or a blocked cell.
+    fn jump_horizontal(
+        grid: &Grid,
+        obstacles: &HashSet<Position>,
+        goal: Position,
+        z: usize,
+        mut x: i32,
+        y: i32,
+        dx: i32,
+    ) -> Option<Position> {
+        loop {
+            if !Self::walkable(grid, obstacles, z, x, y) {
+                return None;
+            }
+            let pos = Position {
+                x: x as usize,
+                y: y as usize,
+                z,
+            };
+            if pos == goal || Self::must_expand(grid, pos) {
+                return Some(pos);
+            }
+
+            // A forced neighbor appears when the cell beside us opens up right
+            // after the cell beside our predecessor was blocked.
+            let forced_up = !Self::walkable(grid, obstacles, z, x - dx, y - 1)
+                && Self::walkable(grid, obstacles, z, x, y - 1);
+            let forced_down = !Self::walkable(grid, obstacles, z, x - dx, y + 1)
+                && Self::walkable(grid, obstacles, z, x, y + 1);
+            if forced_up || forced_down {
+                return Some(pos);
+            }
+
+            x += dx;
+        }
+    }
+
+    /// Jump vertically from `(x, y)`, probing horizontal runs at every cell.
+    fn jump_vertical(
+        grid: &Grid,
+        obstacles: &HashSet<Position>,
+        goal: Position,
+        z: usize,
+        x: i32,
+        mut y: i32,
+        dy: i32,
+    ) -> Option<Position> {
+        loop {
+            if !Self::walkable(grid, obstacles, z, x, y) {
+                return None;
+            }
+            let pos = Position {
+                x: x as usize,
+                y: y as usize,
+                z,
+            };
+            if pos == goal || Self::must_expand(grid, pos) {
+                return Some(pos);
+            }
+
+            let forced_left = !Self::walkable(grid, obstacles, z, x - 1, y - dy)
+                && Self::walkable(grid, obstacles, z, x - 1, y);
+            let forced_right = !Self::walkable(grid, obstacles, z, x + 1, y - dy)
+                && Self::walkable(grid, obstacles, z, x + 1, y);
+            if forced_left || forced_right {
+                return Some(pos);
+            }
+
+            // Any horizontal run that leads somewhere interesting makes this cell a jump point.
+            if Self::jump_horizontal(grid, obstacles, goal, z, x + 1, y, 1).is_some()
+                || Self::jump_horizontal(grid, obstacles, goal, z, x - 1, y, -1).is_some()
+            {
+                return Some(pos);
+            }
+
+            y += dy;
+        }
+    }
+
+    /// Jump from `(x, y)` in an 8-connected step direction `(dx, dy)`, never cutting corners.
+    fn jump_octile(
+        grid: &Grid,
+        obstacles: &HashSet<Position>,
+        goal: Position,
+        z: usize,
+        mut x: i32,
+        mut y: i32,
+        (dx, dy): Direction,
+    ) -> Option<Position> {
+        let walkable = |x, y| Self::walkable(grid, obstacles, z, x, y);
+        loop {
+            if !walkable(x, y) {
+                return None;
+            }
+            let pos = Position {
+                x: x as usize,
+                y: y as usize,
+                z,
+            };
+            if pos == goal || Self::must_expand(grid, pos) {
+                return Some(pos);
+            }
+
+            let jump_point = if dx != 0 && dy != 0 {
+                // A diagonal run stops wherever one of its straight runs finds something
+                Self::jump_octile(grid, obstacles, goal, z, x + dx, y, (dx, 0)).is_some()
+                    || Self::jump_octile(grid, obstacles, goal, z, x, y + dy, (0, dy)).is_some()
+            } else if dx != 0 {
+                (walkable(x, y - 1) && !walkable(x - dx, y - 1))
+                    || (walkable(x, y + 1) && !walkable(x - dx, y + 1))
+            } else {
+                (walkable(x - 1, y) && !walkable(x - 1, y - dy))
+                    || (walkable(x + 1, y) && !walkable(x + 1, y - dy))
+            };
+            if jump_point {
+                return Some(pos);
+            }
+
+            // Diagonal steps need both cells beside them open
+            if dx != 0 && dy != 0 && !(walkable(x + dx, y) && walkable(x, y + dy)) {
+                return None;
+            }
+            x += dx;
+            y += dy;
+        }
+    }
+
+    /// Directions worth exploring on an 8-connected grid from a node reached while travelling in `dir`.
+    fn pruned_directions_octile(
+        grid: &Grid,
+        obstacles: &HashSet<Position>,
+        pos: Position,
+        dir: Direction,
+    ) -> Vec<Direction> {
+        let (x, y, z) = (pos.x as i32, pos.y as i32, pos.z);
+        let walkable = |dx, dy| Self::walkable(grid, obstacles, z, x + dx, y + dy);
+        let mut dirs = Vec::new();
+        match dir {
+            (0, 0) => {
+                for dx in -1..=1 {
+                    for dy in -1..=1 {
+                        if (dx, dy) != (0, 0)
+                            && walkable(dx, dy)
+                            && walkable(dx, 0)
+                            && walkable(0, dy)
+                        {
+                            dirs.push((dx, dy));
+                        }
+                    }
+                }
+            }
+            (dx, 0) => {
+                if walkable(dx, 0) {
+                    dirs.push((dx, 0));
+                }
+                for dy in [-1, 1] {
+                    if walkable(0, dy) {
+                        dirs.push((0, dy));
+                        if walkable(dx, 0) {
+                            dirs.push((dx, dy));
+                        }
+                    }
+                }
+            }
+            (0, dy) => {
+                if walkable(0, dy) {
+                    dirs.push((0, dy));
+                }
+                for dx in [-1, 1] {
+                    if walkable(dx, 0) {
+                        dirs.push((dx, 0));
+                        if walkable(0, dy) {
+                            dirs.push((dx, dy));
+                        }
+                    }
+                }
+            }
+            (dx, dy) => {
+                if walkable(0, dy) {
+                    dirs.push((0, dy));
+                }
+                if walkable(dx, 0) {
+                    dirs.push((dx, 0));
+                }
+                if walkable(0, dy) && walkable(dx, 0) {
+                    dirs.push((dx, dy));
+                }
+            }
+        }
+        dirs
+    }
+
+    /// Directions worth exploring from a node reached while travelling in `dir`.
+    fn pruned_directions(
+        grid: &Grid,
+        obstacles: &HashSet<Position>,
+        pos: Position,
+        dir: Direction,
+    ) -> Vec<Direction> {
+        let (x, y, z) = (pos.x as i32, pos.y as i32, pos.z);
+        match dir {
+            (0, 0) => vec![(1, 0), (-1, 0), (0, 1), (0, -1)],
+            (0, dy) => vec![(0, dy), (1, 0), (-1, 0)],
+            (dx, _) => {
+                let mut dirs = vec![(dx, 0)];
+                if !Self::walkable(grid, obstacles, z, x - dx, y - 1) {
+                    dirs.push((0, -1));
+                }
+                if !Self::walkable(grid, obstacles, z, x - dx, y + 1) {
+                    dirs.push((0, 1));
+                }
+                dirs
+            }
+        }
+    }
+
+    /// Find the jump point successors of a node, paired with their travel cost.
+    fn successors(
+        grid: &Grid,
+        obstacles: &HashSet<Position>,
+        goal: Position,
+        node: &(Position, Direction),
+    ) -> Vec<((Position, Direction), u32)> {
+        let (pos, dir) = *node;
+        if grid.hex || grid.wrap {
+            return grid
+                .get_neighbors(&pos)
+                .into_iter()
+                .filter(|&n| grid.can_move(pos, n, |p| obstacles.contains(&p)))
+                .map(|n| {
+                    let dir = (n.x as i32 - pos.x as i32, n.y as i32 - pos.y as i32);
+                    ((n, dir), grid.move_cost(pos, n))
+                })
+                .collect();
+        }
+        let (x, y, z) = (pos.x as i32, pos.y as i32, pos.z);
+        // Next to terrain, on stairs and on portals nothing can be pruned
+        let dir = if Self::must_expand(grid, pos) {
+            (0, 0)
+        } else {
+            dir
+        };
+        // Stairs lead straight to the stairs above or below and portals to their
+        // other end, entered like a start
+        let climbs = grid
+            .links(&pos)
+            .into_iter()
+            .filter(|&other| grid.can_move(pos, other, |p| obstacles.contains(&p)))
+            .map(|other| ((other, (0, 0)), grid.move_cost(pos, other)));
+
+        if grid.diagonal {
+            return Self::pruned_directions_octile(grid, obstacles, pos, dir)
+                .into_iter()
+                .filter_map(|(dx, dy)| {
+                    let jump_point =
+                        Self::jump_octile(grid, obstacles, goal, z, x + dx, y + dy, (dx, dy))?;
+                    Some((
+                        (jump_point, (dx, dy)),
+                        Self::segment_cost(grid, pos, jump_point),
+                    ))
+                })
+                .chain(climbs)
+                .collect();
+        }
+
+        Self::pruned_directions(grid, obstacles, pos, dir)
+            .into_iter()
+            .filter_map(|(dx, dy)| {
+                let jump_point = if dx != 0 {
+                    Self::jump_horizontal(grid, obstacles, goal, z, x + dx, y, dx)
+                } else {
+                    Self::jump_vertical(grid, obstacles, goal, z, x, y + dy, dy)
+                }?;
+                let cost = Self::segment_cost(grid, pos, jump_point);
+                Some(((jump_point, (dx, dy)), cost))
+            })
+            .chain(climbs)
+            .collect()
+    }
+
+    /// Cells along the straight or diagonal run from `from` to `to`, excluding `from`
+    /// (on hex and toroidal grids, up or down stairs and through portals jump points
+    /// are always adjacent, so this is just `to`).
+    fn segment(grid: &Grid, from: Position, to: Position) -> Vec<Position> {
+        if grid.links(&from).contains(&to) {
+            return vec![to];
+        }
+        let (dx, dy) = grid.metric().offset(from, to);
+        let (dx, dy) = (dx.signum(), dy.signum());
+        std::iter::successors(Some(from), |current| {
+            (*current != to).then(|| {
+                grid.offset(*current, dx, dy)
+                    .expect("runs stay on the grid")
+            })
+        })
+        .skip(1)
+        .collect()
+    }
+
+    /// Travel cost of a run between two jump points.
+    fn segment_cost(grid: &Grid, from: Position, to: Position) -> u32 {
+        let mut cost = 0;
+        let mut previous = from;
+        for pos in Self::segment(grid, from, to) {
+            cost += grid.move_cost(previous, pos);
+            previous = pos;
+        }
+        cost
+    }
+
+    /// Expand a list of jump points into the full cell-by-cell path.
+    fn expand_path(grid: &Grid, jump_points: &[Position]) -> Vec<Position> {
+        let mut path = Vec::new();
+        if let Some(&first) = jump_points.first() {
+            path.push(first);
+        }
+
+        for segment in jump_points.windows(2) {
+            path.extend(Self::segment(grid, segment[0], segment[1]));
+        }
+
+        path
+    }
+}
+
+impl PathfindingAlgorithm for JumpPointSearch {
+    /// Finds a path from start to goal using Jump Point Search.
+    ///
+    /// # Arguments
+    ///
+    /// * `grid` - The simulation grid.
+    /// * `start` - The starting position.
+    /// * `goal` - The goal position.
+    /// * `obstacles` - A set of known obstacle positions to avoid.
+    ///
+    /// # Returns
+    ///
+    /// An `Option` containing a `Vec<Position>` representing the path, or `None` if no path is found.
+    fn find_path(
+        &mut self,
+        grid: &Grid,
+        start: Position,
+        goal: Position,
+        obstacles: &HashSet<Position>,
+    ) -> Option<Vec<Position>> {
+        let (metrics, abort) = (&mut self.metrics, &self.abort);
+        let result = astar(
+            &(start, (0, 0)),
+            |node| {
+                // An aborted search expands nothing more, so the open list runs dry
+                if abort.is_aborted_after(metrics.nodes_expanded) {
+                    return Vec::new();
+                }
+                metrics.expanded(Self::successors(grid, obstacles, goal, node))
+            },
+            |(p, _)| grid.heuristic(*p, goal),
+            |(p, _)| *p == goal,
+        );
+
+        result.map(|(nodes, _)| {
+            let jump_points: Vec<Position> = nodes.into_iter().map(|(p, _)| p).collect();
+            Self::expand_path(grid, &jump_points)
+        })
+    }
+
+    fn search_metrics(&self) -> SearchMetrics {
+        self.metrics
+    }
+
+    fn set_abort_handle(&mut self, abort: AbortHandle) {
+        self.abort = abort;
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
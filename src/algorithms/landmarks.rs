@@ -0,0 +1,267 @@
+use crate::grid::{Grid, Metric, Position};
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::SeedableRng;
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap};
+use std::time::Duration;
+use web_time::Instant;
+
+/// How landmark cells are chosen.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LandmarkStrategy {
+    /// Repeatedly pick the free cell farthest from all landmarks chosen so far.
+    Farthest,
+    /// Free cells closest to the grid corners, then to the edge midpoints.
+    Corners,
+    /// Uniformly random free cells (fixed seed, so runs are comparable).
+    Random,
+}
+
+impl LandmarkStrategy {
+    /// Parses a strategy name as given on the command line.
+    pub fn from_name(name: &str) -> Result<Self, String> {
+        match name {
+            "farthest" => Ok(LandmarkStrategy::Farthest),
+            "corners" => Ok(LandmarkStrategy::Corners),
+            "random" => Ok(LandmarkStrategy::Random),
+            _ => Err(format!(
+                "Unknown landmark strategy: '{}' (expected 'farthest', 'corners' or 'random')",
+                name
+            )),
+        }
+    }
+}
+
+/// ALT (A*, Landmarks, Triangle inequality) heuristic from Goldberg and Harrelson (2005).
+///
+/// Distances from a few landmark cells to every other cell are precomputed once.
+/// By the triangle inequality `|d(L, goal) - d(L, n)|` is a lower bound on the
+/// distance from `n` to the goal, and the maximum over all landmarks (and the
+/// Manhattan distance) is usually much tighter than Manhattan distance alone.
+/// With diagonal movement distances are octile move costs, and octile distance
+/// takes Manhattan's place; on hex grids hex distance does.
+///
+/// Terrain makes distances direction dependent, so on weighted grids distances
+/// to each landmark are computed as well, and the two one-sided bounds
+/// `d(L, to) - d(L, from)` and `d(from, L) - d(to, L)` replace the absolute difference.
+///
+/// Distances are computed around walls only. Dynamic obstacles can only make
+/// real paths longer, so the bound stays admissible (and consistent) as they come
+/// and go.
+#[derive(Debug, Clone)]
+pub struct LandmarkHeuristic {
+    strategy: LandmarkStrategy,
+    count: usize,
+    width: usize,
+    height: usize,
+    floors: usize,
+    metric: Metric,
+    portals: HashMap<Position, Position>,
+    landmarks: Vec<Position>,
+    /// Distance from each landmark to every cell, indexed by `Grid::index`
+    distances: Vec<Vec<u32>>,
+    /// Distance from every cell to each landmark; empty when costs are symmetric
+    distances_to: Vec<Vec<u32>>,
+    precompute_time: Duration,
+}
+
+impl LandmarkHeuristic {
+    /// Creates a landmark heuristic; distances are computed on the first `prepare` call.
+    pub fn new(strategy: LandmarkStrategy, count: usize) -> Self {
+        LandmarkHeuristic {
+            strategy,
+            count,
+            width: 0,
+            height: 0,
+            floors: 0,
+            metric: Metric::MANHATTAN,
+            portals: HashMap::new(),
+            landmarks: Vec::new(),
+            distances: Vec::new(),
+            distances_to: Vec::new(),
+            precompute_time: Duration::ZERO,
+        }
+    }
+
+    /// Selected landmark cells (empty until prepared).
+    pub fn landmarks(&self) -> &[Position] {
+        &self.landmarks
+    }
+
+    /// Time spent selecting landmarks and computing their distance tables.
+    pub fn precompute_time(&self) -> Duration {
+        self.precompute_time
+    }
+
+    /// Drop the distance tables, so the next `prepare` recomputes them. They are computed
+    /// around walls, so this is needed whenever walls appear or disappear.
+    pub fn invalidate(&mut self) {
+        self.distances.clear();
+    }
+
+    /// Select landmarks and compute distance tables, unless already done for this grid size,
+    /// topology and set of portals.
+    pub fn prepare(&mut self, grid: &Grid) {
+        if (self.width, self.height, self.floors) == (grid.width, grid.height, grid.floors)
+            && self.metric == grid.metric()
+            && self.portals == grid.portals
+            && !self.distances.is_empty()
+        {
+            return;
+        }
+
+        let started = Instant::now();
+        (self.width, self.height, self.floors) = (grid.width, grid.height, grid.floors);
+        self.metric = grid.metric();
+        self.portals = grid.portals.clone();
+        self.landmarks.clear();
+        self.distances.clear();
+        self.distances_to.clear();
+
+        let free_cells: Vec<Position> = (0..grid.floors)
+            .flat_map(|z| (0..grid.width).map(move |x| (x, z)))
+            .flat_map(|(x, z)| (0..grid.height).map(move |y| Position { x, y, z }))
+            .filter(|&pos| !grid.is_wall(pos))
+            .collect();
+        if free_cells.is_empty() {
+            return;
+        }
+
+        match self.strategy {
+            LandmarkStrategy::Farthest => self.select_farthest(grid, &free_cells),
+            LandmarkStrategy::Corners => self.select_corners(grid, &free_cells),
+            LandmarkStrategy::Random => self.select_random(grid, &free_cells),
+        }
+
+        self.precompute_time += started.elapsed();
+    }
+
+    fn add_landmark(&mut self, grid: &Grid, landmark: Position) {
+        let distances = Self::distances_from(grid, landmark, false);
+        if grid.has_terrain() {
+            self.distances_to
+                .push(Self::distances_from(grid, landmark, true));
+        }
+        self.landmarks.push(landmark);
+        self.distances.push(distances);
+    }
+
+    /// Farthest-point selection: each new landmark maximizes the distance to the closest existing one
+    fn select_farthest(&mut self, grid: &Grid, free_cells: &[Position]) {
+        // Seed with the cell farthest from an arbitrary free cell, which lies on the map's periphery
+        let seed_distances = Self::distances_from(grid, free_cells[0], false);
+        let mut min_distances = seed_distances;
+
+        while self.landmarks.len() < self.count {
+            let best = free_cells
+                .iter()
+                .copied()
+                .filter(|pos| min_distances[grid.index(*pos)] != u32::MAX)
+                .filter(|pos| !self.landmarks.contains(pos))
+                .max_by_key(|pos| min_distances[grid.index(*pos)]);
+            let Some(best) = best else {
+                break;
+            };
+
+            self.add_landmark(grid, best);
+            let latest = self.distances.last().expect("landmark was just added");
+            for (min_distance, &distance) in min_distances.iter_mut().zip(latest) {
+                *min_distance = (*min_distance).min(distance);
+            }
+        }
+    }
+
+    /// Free cells nearest to the corners, then to the midpoints of the edges,
+    /// alternating between the ground and the top floor
+    fn select_corners(&mut self, grid: &Grid, free_cells: &[Position]) {
+        let (last_x, last_y) = (grid.width - 1, grid.height - 1);
+        let (mid_x, mid_y) = (grid.width / 2, grid.height / 2);
+        let anchors = [
+            (0, 0),
+            (last_x, last_y),
+            (last_x, 0),
+            (0, last_y),
+            (mid_x, 0),
+            (mid_x, last_y),
+            (0, mid_y),
+            (last_x, mid_y),
+        ];
+
+        for (i, &(x, y)) in anchors.iter().take(self.count).enumerate() {
+            let z = if i % 2 == 0 { 0 } else { grid.floors - 1 };
+            let nearest = free_cells
+                .iter()
+                .copied()
+                .filter(|pos| !self.landmarks.contains(pos))
+                .min_by_key(|pos| pos.x.abs_diff(x) + pos.y.abs_diff(y) + pos.z.abs_diff(z));
+            if let Some(nearest) = nearest {
+                self.add_landmark(grid, nearest);
+            }
+        }
+    }
+
+    fn select_random(&mut self, grid: &Grid, free_cells: &[Position]) {
+        let mut rng = StdRng::seed_from_u64(grid.cell_count() as u64);
+        let chosen: Vec<Position> = free_cells
+            .choose_multiple(&mut rng, self.count)
+            .copied()
+            .collect();
+        for landmark in chosen {
+            self.add_landmark(grid, landmark);
+        }
+    }
+
+    /// Shortest move-cost distances from `source` to every cell, around walls, or
+    /// from every cell to `source` when `reverse` is set
+    fn distances_from(grid: &Grid, source: Position, reverse: bool) -> Vec<u32> {
+        let mut distances = vec![u32::MAX; grid.cell_count()];
+        distances[grid.index(source)] = 0;
+        let mut queue = BinaryHeap::from([Reverse((0, source))]);
+        while let Some(Reverse((distance, pos))) = queue.pop() {
+            if distance > distances[grid.index(pos)] {
+                continue;
+            }
+            for neighbor in grid.get_neighbors(&pos) {
+                let index = grid.index(neighbor);
+                let cost = if reverse {
+                    grid.move_cost(neighbor, pos)
+                } else {
+                    grid.move_cost(pos, neighbor)
+                };
+                let next = distance + cost;
+                if next < distances[index] {
+                    distances[index] = next;
+                    queue.push(Reverse((next, neighbor)));
+                }
+            }
+        }
+        distances
+    }
+
+    /// Lower bound on the distance between `from` and `to`: the larger of the
+    /// grid distance (Manhattan, octile or hex, through portals where shorter) and
+    /// the best landmark bound.
+    pub fn estimate(&self, from: Position, to: Position) -> u32 {
+        let base = self.metric.distance_via(from, to, &self.portals);
+
+        let index = |pos: Position| (pos.z * self.height + pos.y) * self.width + pos.x;
+        let (from_index, to_index) = (index(from), index(to));
+        // How much farther `table` puts `far` than `near`
+        let gap = |table: &Vec<u32>, near: usize, far: usize| {
+            let (d_near, d_far) = (*table.get(near)?, *table.get(far)?);
+            // Cells the landmark cannot reach give no information
+            (d_near != u32::MAX && d_far != u32::MAX).then(|| d_far.saturating_sub(d_near))
+        };
+        self.distances
+            .iter()
+            .enumerate()
+            .filter_map(|(i, from_landmark)| {
+                let to_landmark = self.distances_to.get(i).unwrap_or(from_landmark);
+                let forward = gap(from_landmark, from_index, to_index)?;
+                let backward = gap(to_landmark, to_index, from_index)?;
+                Some(forward.max(backward))
+            })
+            .fold(base, u32::max)
+    }
+}
@@ -1,4 +1,22 @@
 pub mod a_star;
+pub mod a_star_fast;
+pub mod ara_star;
+pub mod bfs;
 pub mod common;
 pub mod d_star_lite;
+pub mod dijkstra;
+pub mod field_d_star;
+pub mod flow_field;
+pub mod hpa_star;
 pub mod hybrid_a_star_d_star;
+pub mod jump_point_search;
+pub mod landmarks;
+pub mod mt_d_star_lite;
+pub mod potential_field;
+pub mod priority_queue;
+pub mod real_time_a_star;
+pub mod registry;
+pub mod search_space;
+pub mod space_time_a_star;
+pub mod switching_policy;
+pub mod weighted_a_star;
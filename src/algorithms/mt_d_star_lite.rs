@@ -0,0 +1,384 @@
+use crate::algorithms::common::{
+    AbortHandle, EnvironmentChanges, KnownObstacles, PathfindingAlgorithm, SearchMetrics,
+};
+use crate::grid::{Grid, Metric, Position};
+use std::any::Any;
+use std::cmp::Ordering;
+use std::collections::{BinaryHeap, HashMap, HashSet};
+
+/// Represents the priority key for a node in the MT-D* Lite priority queue.
+#[derive(Clone, Copy, PartialEq, Debug)]
+struct Key {
+    k1: i32,
+    k2: i32,
+}
+
+impl Eq for Key {}
+
+impl PartialOrd for Key {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Key {
+    fn cmp(&self, other: &Self) -> Ordering {
+        // Reversed so BinaryHeap (a max-heap) pops the smallest key first
+        match other.k1.cmp(&self.k1) {
+            Ordering::Equal => other.k2.cmp(&self.k2),
+            other => other,
+        }
+    }
+}
+
+/// Implements Moving Target D* Lite (basic variant) from Sun, Yeoh and Koenig (2010).
+///
+/// Unlike D* Lite, the search tree is rooted at the agent and grows towards the
+/// goal. When the goal moves only the heuristic changes, which is absorbed by the
+/// key modifier `k_m`; when the agent moves the root is transferred to the new
+/// position and only the affected part of the tree is repaired.
+pub struct MtDStarLite {
+    g_scores: Vec<i32>,
+    rhs_scores: Vec<i32>,
+    queue: BinaryHeap<(Key, Position, u64)>, // Priority queue with generation counter
+    vertex_generations: Vec<u64>,            // Current generation for each vertex
+    current_generation: u64,
+    k_m: i32,
+    s_start: Position,
+    s_goal: Position,
+    known_obstacles: KnownObstacles,
+    initialized: bool,
+    width: usize,
+    height: usize,
+    floors: usize,
+    metric: Metric,
+    /// Portals of the grid, which the heuristic takes into account
+    portals: HashMap<Position, Position>,
+    metrics: SearchMetrics,
+    abort: AbortHandle,
+}
+
+impl MtDStarLite {
+    /// Creates a new instance of MT-D* Lite for a `width` x `height` grid.
+    pub fn new(start: Position, goal: Position, width: usize, height: usize) -> Self {
+        let total_cells = width * height;
+
+        MtDStarLite {
+            g_scores: vec![i32::MAX; total_cells],
+            rhs_scores: vec![i32::MAX; total_cells],
+            queue: BinaryHeap::new(),
+            vertex_generations: vec![0; total_cells],
+            current_generation: 0,
+            k_m: 0,
+            s_start: start,
+            s_goal: goal,
+            known_obstacles: KnownObstacles::default(),
+            initialized: false,
+            width,
+            height,
+            floors: 1,
+            metric: Metric::MANHATTAN,
+            portals: HashMap::new(),
+            metrics: SearchMetrics::default(),
+            abort: AbortHandle::default(),
+        }
+    }
+
+    /// Convert a position to its index in the per-cell vectors (same as `Grid::index`)
+    #[inline(always)]
+    fn pos_to_index(&self, pos: Position) -> usize {
+        (pos.z * self.height + pos.y) * self.width + pos.x
+    }
+
+    /// Heuristic function h(s1, s2) - the grid's distance metric (Manhattan, octile or hex),
+    /// through portals where shorter
+    fn h(&self, s1: Position, s2: Position) -> i32 {
+        self.metric.distance_via(s1, s2, &self.portals) as i32
+    }
+
+    /// Edge cost c(u, v): moving into a wall or known obstacle (or cutting a corner) is impossible
+    fn c(&self, u: Position, v: Position, grid: &Grid) -> i32 {
+        if grid.can_move(u, v, |pos| self.known_obstacles.contains(&pos)) {
+            grid.move_cost(u, v) as i32
+        } else {
+            i32::MAX
+        }
+    }
+
+    /// procedure CalculateKey(s) - keys are goal-directed since the tree grows from the agent
+    fn calculate_key(&self, s: Position) -> Key {
+        let index = self.pos_to_index(s);
+        let min_val = self.g_scores[index].min(self.rhs_scores[index]);
+
+        if min_val == i32::MAX {
+            Key {
+                k1: i32::MAX,
+                k2: i32::MAX,
+            }
+        } else {
+            Key {
+                k1: min_val
+                    .saturating_add(self.h(s, self.s_goal))
+                    .saturating_add(self.k_m),
+                k2: min_val,
+            }
+        }
+    }
+
+    /// Helper function to compare keys (k1 < k2)
+    fn key_less_than(&self, k1: Key, k2: Key) -> bool {
+        if k1.k1 != k2.k1 {
+            k1.k1 < k2.k1
+        } else {
+            k1.k2 < k2.k2
+        }
+    }
+
+    /// procedure Initialize() - the root of the search tree is the agent
+    fn initialize(&mut self) {
+        self.queue.clear();
+        self.vertex_generations.fill(0);
+        self.current_generation = 0;
+        self.k_m = 0;
+        self.g_scores.fill(i32::MAX);
+        self.rhs_scores.fill(i32::MAX);
+
+        let start_index = self.pos_to_index(self.s_start);
+        self.rhs_scores[start_index] = 0;
+        self.push(self.s_start);
+
+        self.initialized = true;
+    }
+
+    /// Insert a vertex with a fresh key, invalidating any older queue entries
+    fn push(&mut self, u: Position) {
+        let key = self.calculate_key(u);
+        let index = self.pos_to_index(u);
+        self.current_generation += 1;
+        self.vertex_generations[index] = self.current_generation;
+        self.queue.push((key, u, self.current_generation));
+        self.metrics.pushed(self.queue.len());
+    }
+
+    /// procedure UpdateState(u) - recompute rhs(u) from predecessors and requeue if inconsistent
+    fn update_vertex(&mut self, u: Position, grid: &Grid) {
+        let u_index = self.pos_to_index(u);
+
+        if u != self.s_start {
+            let mut min_rhs = i32::MAX;
+            for s_prime in grid.get_neighbors(&u) {
+                let g_s_prime = self.g_scores[self.pos_to_index(s_prime)];
+                let cost = self.c(s_prime, u, grid);
+                if g_s_prime != i32::MAX && cost != i32::MAX {
+                    min_rhs = min_rhs.min(g_s_prime.saturating_add(cost));
+                }
+            }
+            self.rhs_scores[u_index] = min_rhs;
+        }
+
+        if self.g_scores[u_index] != self.rhs_scores[u_index] {
+            self.push(u);
+        } else {
+            // Consistent: drop any queued entry
+            self.current_generation += 1;
+            self.vertex_generations[u_index] = self.current_generation;
+        }
+    }
+
+    /// procedure ComputeCostMinimalPath()
+    /// Returns false when the search was aborted before it finished
+    fn compute_shortest_path(&mut self, grid: &Grid) -> bool {
+        loop {
+            if self.abort.is_aborted_after(self.metrics.nodes_expanded) {
+                return false;
+            }
+            // Skip invalid entries using lazy deletion
+            let (k_old, u) = loop {
+                match self.queue.peek() {
+                    Some(&(k, pos, gen)) => {
+                        if self.vertex_generations[self.pos_to_index(pos)] == gen {
+                            break (k, pos);
+                        }
+                        self.queue.pop();
+                        self.metrics.popped();
+                    }
+                    None => return true,
+                }
+            };
+
+            let goal_key = self.calculate_key(self.s_goal);
+            let goal_index = self.pos_to_index(self.s_goal);
+            let goal_inconsistent = self.rhs_scores[goal_index] != self.g_scores[goal_index];
+            if !self.key_less_than(k_old, goal_key) && !goal_inconsistent {
+                return true;
+            }
+
+            self.queue.pop();
+            self.metrics.popped();
+            let k_new = self.calculate_key(u);
+            if self.key_less_than(k_old, k_new) {
+                self.push(u);
+                continue;
+            }
+
+            let u_index = self.pos_to_index(u);
+            // Popped entry is consumed
+            self.current_generation += 1;
+            self.vertex_generations[u_index] = self.current_generation;
+            self.metrics.nodes_expanded += 1;
+
+            if self.g_scores[u_index] > self.rhs_scores[u_index] {
+                self.g_scores[u_index] = self.rhs_scores[u_index];
+                let neighbors = grid.get_neighbors(&u);
+                self.metrics.nodes_generated += neighbors.len();
+                for s in neighbors {
+                    self.update_vertex(s, grid);
+                }
+            } else {
+                self.g_scores[u_index] = i32::MAX;
+                let mut vertices_to_update = grid.get_neighbors(&u);
+                self.metrics.nodes_generated += vertices_to_update.len();
+                vertices_to_update.push(u);
+                for s in vertices_to_update {
+                    self.update_vertex(s, grid);
+                }
+            }
+        }
+    }
+
+    /// Transfer the root of the search tree to the agent's new position
+    fn move_start(&mut self, start: Position, grid: &Grid) {
+        let old_start = self.s_start;
+        self.s_start = start;
+
+        let start_index = self.pos_to_index(start);
+        self.rhs_scores[start_index] = 0;
+        self.update_vertex(start, grid);
+
+        // The old root is now an ordinary vertex whose rhs comes from its neighbors
+        self.update_vertex(old_start, grid);
+    }
+
+    /// Account for a goal move by bumping the key modifier
+    fn move_goal(&mut self, goal: Position) {
+        self.k_m = self.k_m.saturating_add(self.h(self.s_goal, goal));
+        self.s_goal = goal;
+    }
+
+    /// Repair vertices around cells whose obstacle status changed
+    fn update_obstacles(&mut self, grid: &Grid, obstacles: &HashSet<Position>) {
+        let changed = self.known_obstacles.catch_up(obstacles);
+        self.update_cells(grid, &changed);
+    }
+
+    /// Recompute the cells whose blocked state changed, and their neighbors
+    fn update_cells(&mut self, grid: &Grid, changed: &[Position]) {
+        for &pos in changed {
+            self.update_vertex(pos, grid);
+            for neighbor in grid.get_neighbors(&pos) {
+                self.update_vertex(neighbor, grid);
+            }
+        }
+    }
+
+    /// Walk back from the goal along the predecessors it was reached through, then reverse
+    fn reconstruct_path(&self, grid: &Grid) -> Option<Vec<Position>> {
+        if self.g_scores[self.pos_to_index(self.s_goal)] == i32::MAX {
+            return None;
+        }
+
+        let mut path = vec![self.s_goal];
+        let mut current = self.s_goal;
+        while current != self.s_start {
+            let best_prev = grid
+                .get_neighbors(&current)
+                .into_iter()
+                .filter(|p| self.g_scores[self.pos_to_index(*p)] != i32::MAX)
+                .filter(|p| self.c(*p, current, grid) != i32::MAX)
+                .min_by_key(|p| {
+                    self.g_scores[self.pos_to_index(*p)].saturating_add(self.c(*p, current, grid))
+                })?;
+
+            if self.g_scores[self.pos_to_index(best_prev)]
+                >= self.g_scores[self.pos_to_index(current)]
+            {
+                return None; // Path broken
+            }
+
+            current = best_prev;
+            path.push(current);
+
+            // Safety check for infinite loops
+            if path.len() > grid.cell_count() {
+                return None;
+            }
+        }
+
+        path.reverse();
+        Some(path)
+    }
+}
+
+impl PathfindingAlgorithm for MtDStarLite {
+    fn find_path(
+        &mut self,
+        grid: &Grid,
+        start: Position,
+        goal: Position,
+        obstacles: &HashSet<Position>,
+    ) -> Option<Vec<Position>> {
+        if !self.initialized
+            || (self.width, self.height, self.floors) != (grid.width, grid.height, grid.floors)
+        {
+            let total_cells = grid.cell_count();
+            (self.width, self.height, self.floors) = (grid.width, grid.height, grid.floors);
+            self.g_scores.resize(total_cells, i32::MAX);
+            self.rhs_scores.resize(total_cells, i32::MAX);
+            self.vertex_generations.resize(total_cells, 0);
+            self.s_start = start;
+            self.s_goal = goal;
+            self.metric = grid.metric();
+            self.portals = grid.portals.clone();
+            self.known_obstacles.reset(obstacles);
+            self.initialize();
+        } else {
+            if self.s_goal != goal {
+                self.move_goal(goal);
+            }
+            if self.s_start != start {
+                self.move_start(start, grid);
+            }
+            self.update_obstacles(grid, obstacles);
+        }
+
+        if !self.compute_shortest_path(grid) {
+            return None;
+        }
+        self.reconstruct_path(grid)
+    }
+
+    fn update_environment(
+        &mut self,
+        grid: &Grid,
+        obstacles: &HashSet<Position>,
+        changes: &EnvironmentChanges,
+    ) {
+        self.known_obstacles.update(changes);
+        if self.initialized {
+            self.update_obstacles(grid, obstacles);
+            self.update_cells(grid, &changes.walls);
+        }
+    }
+
+    fn search_metrics(&self) -> SearchMetrics {
+        self.metrics
+    }
+
+    fn set_abort_handle(&mut self, abort: AbortHandle) {
+        self.abort = abort;
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
@@ -0,0 +1,314 @@
+use crate::algorithms::common::{PathfindingAlgorithm, SearchMetrics};
+use crate::grid::{Grid, Position};
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::SeedableRng;
+use std::any::Any;
+use std::collections::HashSet;
+
+/// Weight of the attractive potential (per cell of Euclidean distance to the goal).
+const ATTRACTIVE_GAIN: f64 = 1.0;
+/// Weight of the repulsive potential around walls and known obstacles.
+const REPULSIVE_GAIN: f64 = 2.0;
+/// Extra potential per unit of terrain cost above 1 (mud and water).
+const TERRAIN_GAIN: f64 = 0.5;
+/// Blocked cells farther away than this (in cells) exert no repulsion.
+const INFLUENCE_DISTANCE: f64 = 2.5;
+/// Number of random moves made per random-walk escape.
+const RANDOM_WALK_STEPS: usize = 8;
+/// Recently visited cells; stepping back onto one counts as being trapped.
+const HISTORY_LENGTH: usize = 4;
+
+/// How the potential-field planner gets out of a local minimum.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EscapeStrategy {
+    /// Take a fixed number of random moves.
+    RandomWalk,
+    /// Follow the blocking wall (left-hand rule) until the potential drops below the minimum.
+    WallFollow,
+}
+
+impl EscapeStrategy {
+    /// Parses a strategy name as given on the command line.
+    pub fn from_name(name: &str) -> Result<Self, String> {
+        match name {
+            "random_walk" => Ok(EscapeStrategy::RandomWalk),
+            "wall_follow" => Ok(EscapeStrategy::WallFollow),
+            _ => Err(format!(
+                "Unknown escape strategy: '{}' (expected 'random_walk' or 'wall_follow')",
+                name
+            )),
+        }
+    }
+
+    /// Name as accepted by `from_name`.
+    pub fn name(&self) -> &'static str {
+        match self {
+            EscapeStrategy::RandomWalk => "random_walk",
+            EscapeStrategy::WallFollow => "wall_follow",
+        }
+    }
+}
+
+/// Implements artificial potential-field navigation (Khatib, 1986) as a reactive baseline.
+///
+/// Every cell has a potential made of an attractive term that grows with the
+/// distance to the goal and a repulsive term that grows near walls and known
+/// obstacles. Terrain raises the potential of its cells in proportion to its cost,
+/// so the agent skirts mud and water where a detour is short. Each call moves one
+/// cell downhill; no path to the goal is ever planned.
+///
+/// Potential fields get trapped in local minima (e.g. in front of a U-shaped wall).
+/// When no neighbor is downhill, or the agent would step back onto a cell it just
+/// left, an escape is started: either a short random walk or a left-hand wall
+/// follow that stops once the potential drops below the trapped cell's.
+///
+/// Floors count towards the distance to the goal, so stairs leading towards the
+/// goal's floor are downhill; nothing draws the agent to stairs it cannot see,
+/// though, so multi-floor goals are mostly reached by luck. Portals are part of the
+/// map, so the attraction is measured through them where that is shorter.
+pub struct PotentialField {
+    escape_strategy: EscapeStrategy,
+    rng: StdRng,
+    history: Vec<Position>,
+    escapes: usize,
+    successful_escapes: usize,
+    /// Each descent step expands the current cell into its free neighbors; there is
+    /// no open list
+    metrics: SearchMetrics,
+}
+
+impl PotentialField {
+    /// Creates a new potential-field planner with the given local-minimum escape.
+    pub fn new(escape_strategy: EscapeStrategy) -> Self {
+        PotentialField {
+            escape_strategy,
+            // Fixed seed so random-walk escapes are repeatable across runs
+            rng: StdRng::seed_from_u64(0),
+            history: Vec::new(),
+            escapes: 0,
+            successful_escapes: 0,
+            metrics: SearchMetrics::default(),
+        }
+    }
+
+    /// Escape strategy in use.
+    pub fn escape_strategy(&self) -> EscapeStrategy {
+        self.escape_strategy
+    }
+
+    /// Number of local minima the planner had to escape from.
+    pub fn escapes(&self) -> usize {
+        self.escapes
+    }
+
+    /// Escapes that ended somewhere with a lower potential than the trapped cell.
+    pub fn successful_escapes(&self) -> usize {
+        self.successful_escapes
+    }
+
+    fn is_blocked(grid: &Grid, pos: Position, obstacles: &HashSet<Position>) -> bool {
+        grid.is_wall(pos) || obstacles.contains(&pos)
+    }
+
+    fn free_neighbors(grid: &Grid, pos: Position, obstacles: &HashSet<Position>) -> Vec<Position> {
+        grid.get_neighbors(&pos)
+            .into_iter()
+            .filter(|&n| grid.can_move(pos, n, |p| obstacles.contains(&p)))
+            .collect()
+    }
+
+    /// U(p) = attractive(p) + repulsive(p) + terrain(p)
+    fn potential(
+        &self,
+        grid: &Grid,
+        pos: Position,
+        goal: Position,
+        obstacles: &HashSet<Position>,
+    ) -> f64 {
+        let metric = grid.metric();
+        let distance = |a: Position, b: Position| metric.euclidean(a, b);
+        // Portals that bring the goal closer pull the agent towards them
+        let attractive = ATTRACTIVE_GAIN * metric.euclidean_via(pos, goal, &grid.portals);
+
+        // Only blocked cells inside the influence radius contribute. A hex cell is at
+        // least sqrt(3)/2 per move away, so its axial offsets can be a bit larger.
+        let reach = if grid.hex {
+            (INFLUENCE_DISTANCE * 2.0 / 3f64.sqrt()).floor() as i32
+        } else {
+            INFLUENCE_DISTANCE.floor() as i32
+        };
+        let mut repulsive = 0.0;
+        for dx in -reach..=reach {
+            for dy in -reach..=reach {
+                let Some(other) = grid.offset(pos, dx, dy) else {
+                    continue;
+                };
+                if other == pos || !Self::is_blocked(grid, other, obstacles) {
+                    continue;
+                }
+                let d = distance(pos, other);
+                if d < INFLUENCE_DISTANCE {
+                    repulsive +=
+                        0.5 * REPULSIVE_GAIN * (1.0 / d - 1.0 / INFLUENCE_DISTANCE).powi(2);
+                }
+            }
+        }
+
+        let terrain = TERRAIN_GAIN * (grid.terrain_cost(pos) - 1) as f64;
+
+        attractive + repulsive + terrain
+    }
+
+    /// A few random moves, avoiding immediate backtracking where possible
+    fn random_walk(
+        &mut self,
+        grid: &Grid,
+        start: Position,
+        obstacles: &HashSet<Position>,
+    ) -> Vec<Position> {
+        let mut path = vec![start];
+        let mut current = start;
+        for _ in 0..RANDOM_WALK_STEPS {
+            let mut options = Self::free_neighbors(grid, current, obstacles);
+            if path.len() >= 2 && options.len() > 1 {
+                let previous = path[path.len() - 2];
+                options.retain(|&n| n != previous);
+            }
+            let Some(&next) = options.choose(&mut self.rng) else {
+                break;
+            };
+            path.push(next);
+            current = next;
+        }
+        path
+    }
+
+    /// Left-hand wall following until the potential drops below `threshold`
+    fn wall_follow(
+        &self,
+        grid: &Grid,
+        start: Position,
+        goal: Position,
+        obstacles: &HashSet<Position>,
+        threshold: f64,
+    ) -> Vec<Position> {
+        // Headings in clockwise order: up, right, down, left
+        const HEADINGS: [(i32, i32); 4] = [(0, -1), (1, 0), (0, 1), (-1, 0)];
+        let step = |pos: Position, heading: usize| -> Option<Position> {
+            let (dx, dy) = HEADINGS[heading];
+            let next = grid.offset(pos, dx, dy)?;
+            (!Self::is_blocked(grid, next, obstacles)).then_some(next)
+        };
+
+        // Head for the goal until something blocks the way, then keep that wall on the left
+        let (dx, dy) = grid.metric().offset(start, goal);
+        let mut heading = if dx.abs() >= dy.abs() {
+            if dx >= 0 {
+                1
+            } else {
+                3
+            }
+        } else if dy >= 0 {
+            2
+        } else {
+            0
+        };
+        let mut following = false;
+
+        let mut path = vec![start];
+        let mut current = start;
+        for _ in 0..2 * (grid.width + grid.height) {
+            // Left-hand rule: prefer left, then straight, right and finally back.
+            // Until a wall has been found there is nothing to keep on the left.
+            let turns: &[usize] = if following {
+                &[3, 0, 1, 2]
+            } else {
+                &[0, 1, 2, 3]
+            };
+            let Some((next_heading, next)) = turns
+                .iter()
+                .map(|turn| (heading + turn) % 4)
+                .find_map(|h| step(current, h).map(|next| (h, next)))
+            else {
+                break;
+            };
+            following |= next_heading != heading;
+            heading = next_heading;
+            current = next;
+            path.push(current);
+
+            if current == goal || self.potential(grid, current, goal, obstacles) < threshold {
+                break;
+            }
+        }
+        path
+    }
+
+    fn remember(&mut self, pos: Position) {
+        self.history.push(pos);
+        if self.history.len() > HISTORY_LENGTH {
+            self.history.remove(0);
+        }
+    }
+}
+
+impl PathfindingAlgorithm for PotentialField {
+    fn find_path(
+        &mut self,
+        grid: &Grid,
+        start: Position,
+        goal: Position,
+        obstacles: &HashSet<Position>,
+    ) -> Option<Vec<Position>> {
+        if start == goal {
+            return Some(vec![start]);
+        }
+
+        let neighbors = self
+            .metrics
+            .expanded(Self::free_neighbors(grid, start, obstacles));
+        if neighbors.is_empty() {
+            return None;
+        }
+        if neighbors.contains(&goal) {
+            return Some(vec![start, goal]);
+        }
+
+        let current_potential = self.potential(grid, start, goal, obstacles);
+        let (best, best_potential) = neighbors
+            .iter()
+            .map(|&n| (n, self.potential(grid, n, goal, obstacles)))
+            .min_by(|a, b| a.1.total_cmp(&b.1))?;
+
+        let trapped = best_potential >= current_potential || self.history.contains(&best);
+        self.remember(start);
+        if !trapped {
+            return Some(vec![start, best]);
+        }
+
+        // Local minimum: escape, then resume descending from wherever the escape ends
+        self.escapes += 1;
+        self.history.clear();
+        let path = match self.escape_strategy {
+            EscapeStrategy::RandomWalk => self.random_walk(grid, start, obstacles),
+            EscapeStrategy::WallFollow => {
+                self.wall_follow(grid, start, goal, obstacles, current_potential)
+            }
+        };
+
+        let end = *path.last()?;
+        if self.potential(grid, end, goal, obstacles) < current_potential {
+            self.successful_escapes += 1;
+        }
+        (path.len() > 1).then_some(path)
+    }
+
+    fn search_metrics(&self) -> SearchMetrics {
+        self.metrics
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
@@ -0,0 +1,292 @@
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+
+/// Lexicographic priority `(primary, secondary)`; smaller pops first.
+pub type QueueKey = (i32, i32);
+
+/// Min-priority queue used by D* Lite's open list.
+///
+/// D* Lite invalidates stale entries itself (lazy deletion), so backends only
+/// need insert and extract-min; no decrease-key or arbitrary removal.
+pub trait PriorityQueue<T> {
+    /// Insert an item with the given key.
+    fn push(&mut self, key: QueueKey, item: T);
+
+    /// Remove and return an item with the smallest key.
+    fn pop(&mut self) -> Option<(QueueKey, T)>;
+
+    fn len(&self) -> usize;
+
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn clear(&mut self);
+}
+
+/// Which priority queue backend to use.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum QueueKind {
+    /// `std::collections::BinaryHeap`
+    BinaryHeap,
+    /// One bucket per primary key, ties broken by the secondary key inside a bucket.
+    BucketQueue,
+    /// Pairing heap with O(1) insert and amortized O(log n) extract-min.
+    PairingHeap,
+}
+
+impl QueueKind {
+    /// Parses a queue name as given on the command line.
+    pub fn from_name(name: &str) -> Result<Self, String> {
+        match name {
+            "binary_heap" => Ok(QueueKind::BinaryHeap),
+            "bucket_queue" => Ok(QueueKind::BucketQueue),
+            "pairing_heap" => Ok(QueueKind::PairingHeap),
+            _ => Err(format!(
+                "Unknown priority queue: '{}' (expected 'binary_heap', 'bucket_queue' or 'pairing_heap')",
+                name
+            )),
+        }
+    }
+
+    /// Name as accepted by `from_name`.
+    pub fn name(&self) -> &'static str {
+        match self {
+            QueueKind::BinaryHeap => "binary_heap",
+            QueueKind::BucketQueue => "bucket_queue",
+            QueueKind::PairingHeap => "pairing_heap",
+        }
+    }
+
+    /// Creates an empty queue of this kind.
+    pub fn create<T: Ord + Copy + 'static>(&self) -> Box<dyn PriorityQueue<T>> {
+        match self {
+            QueueKind::BinaryHeap => Box::new(BinaryHeapQueue::new()),
+            QueueKind::BucketQueue => Box::new(BucketQueue::new()),
+            QueueKind::PairingHeap => Box::new(PairingHeap::new()),
+        }
+    }
+}
+
+/// Binary heap backend (the original D* Lite queue)
+pub struct BinaryHeapQueue<T> {
+    heap: BinaryHeap<Reverse<(QueueKey, T)>>,
+}
+
+impl<T: Ord> BinaryHeapQueue<T> {
+    pub fn new() -> Self {
+        BinaryHeapQueue {
+            heap: BinaryHeap::new(),
+        }
+    }
+}
+
+impl<T: Ord> Default for BinaryHeapQueue<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: Ord> PriorityQueue<T> for BinaryHeapQueue<T> {
+    fn push(&mut self, key: QueueKey, item: T) {
+        self.heap.push(Reverse((key, item)));
+    }
+
+    fn pop(&mut self) -> Option<(QueueKey, T)> {
+        self.heap.pop().map(|Reverse(entry)| entry)
+    }
+
+    fn len(&self) -> usize {
+        self.heap.len()
+    }
+
+    fn clear(&mut self) {
+        self.heap.clear();
+    }
+}
+
+/// Bucket queue indexed by the primary key.
+///
+/// On a unit-cost grid the primary keys of D* Lite are small integers, so finding
+/// the next non-empty bucket is a short forward scan from the last minimum. Each
+/// bucket is a small heap ordered by the secondary key. Keys must be non-negative.
+pub struct BucketQueue<T> {
+    buckets: Vec<BinaryHeap<Reverse<(i32, T)>>>,
+    /// No bucket below this index holds an item
+    min_bucket: usize,
+    len: usize,
+}
+
+impl<T: Ord> BucketQueue<T> {
+    pub fn new() -> Self {
+        BucketQueue {
+            buckets: Vec::new(),
+            min_bucket: 0,
+            len: 0,
+        }
+    }
+}
+
+impl<T: Ord> Default for BucketQueue<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: Ord> PriorityQueue<T> for BucketQueue<T> {
+    fn push(&mut self, key: QueueKey, item: T) {
+        let bucket = usize::try_from(key.0).expect("bucket queue keys must be non-negative");
+        if bucket >= self.buckets.len() {
+            self.buckets.resize_with(bucket + 1, BinaryHeap::new);
+        }
+        self.buckets[bucket].push(Reverse((key.1, item)));
+        self.min_bucket = self.min_bucket.min(bucket);
+        self.len += 1;
+    }
+
+    fn pop(&mut self) -> Option<(QueueKey, T)> {
+        if self.len == 0 {
+            return None;
+        }
+        while self.buckets[self.min_bucket].is_empty() {
+            self.min_bucket += 1;
+        }
+        let Reverse((secondary, item)) = self.buckets[self.min_bucket].pop()?;
+        self.len -= 1;
+        Some(((self.min_bucket as i32, secondary), item))
+    }
+
+    fn len(&self) -> usize {
+        self.len
+    }
+
+    fn clear(&mut self) {
+        // Keep the bucket allocations for the next search
+        for bucket in &mut self.buckets {
+            bucket.clear();
+        }
+        self.min_bucket = 0;
+        self.len = 0;
+    }
+}
+
+struct PairingNode<T> {
+    key: QueueKey,
+    item: T,
+    child: Option<usize>,
+    sibling: Option<usize>,
+}
+
+/// Pairing heap (Fredman, Sedgewick, Sleator and Tarjan, 1986) stored in an arena.
+///
+/// Insert links the new node with the root in O(1); extract-min merges the root's
+/// children pairwise left to right, then right to left. Freed nodes are reused.
+pub struct PairingHeap<T> {
+    nodes: Vec<PairingNode<T>>,
+    free: Vec<usize>,
+    root: Option<usize>,
+    len: usize,
+}
+
+impl<T: Ord + Copy> PairingHeap<T> {
+    pub fn new() -> Self {
+        PairingHeap {
+            nodes: Vec::new(),
+            free: Vec::new(),
+            root: None,
+            len: 0,
+        }
+    }
+
+    /// Make the root with the larger entry the leftmost child of the other root
+    fn link(&mut self, a: usize, b: usize) -> usize {
+        let (parent, child) =
+            if (self.nodes[a].key, self.nodes[a].item) <= (self.nodes[b].key, self.nodes[b].item) {
+                (a, b)
+            } else {
+                (b, a)
+            };
+        self.nodes[child].sibling = self.nodes[parent].child;
+        self.nodes[parent].child = Some(child);
+        parent
+    }
+
+    /// Two-pass merge of a sibling list into a single tree
+    fn merge_pairs(&mut self, first: Option<usize>) -> Option<usize> {
+        let mut pairs = Vec::new();
+        let mut current = first;
+        while let Some(a) = current {
+            let next = self.nodes[a].sibling;
+            self.nodes[a].sibling = None;
+            match next {
+                Some(b) => {
+                    current = self.nodes[b].sibling;
+                    self.nodes[b].sibling = None;
+                    pairs.push(self.link(a, b));
+                }
+                None => {
+                    current = None;
+                    pairs.push(a);
+                }
+            }
+        }
+
+        let mut merged = pairs.pop()?;
+        while let Some(tree) = pairs.pop() {
+            merged = self.link(tree, merged);
+        }
+        Some(merged)
+    }
+}
+
+impl<T: Ord + Copy> Default for PairingHeap<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: Ord + Copy> PriorityQueue<T> for PairingHeap<T> {
+    fn push(&mut self, key: QueueKey, item: T) {
+        let node = PairingNode {
+            key,
+            item,
+            child: None,
+            sibling: None,
+        };
+        let index = match self.free.pop() {
+            Some(index) => {
+                self.nodes[index] = node;
+                index
+            }
+            None => {
+                self.nodes.push(node);
+                self.nodes.len() - 1
+            }
+        };
+        self.root = Some(match self.root {
+            Some(root) => self.link(root, index),
+            None => index,
+        });
+        self.len += 1;
+    }
+
+    fn pop(&mut self) -> Option<(QueueKey, T)> {
+        let root = self.root?;
+        let (key, item) = (self.nodes[root].key, self.nodes[root].item);
+        self.root = self.merge_pairs(self.nodes[root].child);
+        self.free.push(root);
+        self.len -= 1;
+        Some((key, item))
+    }
+
+    fn len(&self) -> usize {
+        self.len
+    }
+
+    fn clear(&mut self) {
+        self.nodes.clear();
+        self.free.clear();
+        self.root = None;
+        self.len = 0;
+    }
+}
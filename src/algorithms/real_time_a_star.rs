@@ -0,0 +1,167 @@
+use crate::algorithms::common::{EnvironmentChanges, PathfindingAlgorithm, SearchMetrics};
+use crate::grid::{Grid, Position};
+use std::any::Any;
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap, HashSet};
+
+/// Implements Real-Time Adaptive A* (RTAA*) from Koenig and Likhachev (2006).
+///
+/// Every call runs an A* lookahead limited to `lookahead` node expansions and
+/// returns a partial path to the most promising frontier node. Afterwards the
+/// heuristic of every expanded node is raised to `f(best) - g(node)`, so repeated
+/// visits to the same area are steered away from dead ends over time.
+///
+/// With a lookahead of 1 the update reduces to the classic LRTA* rule
+/// `h(s) = min(c(s, s') + h(s'))`, which is how `lrta_star` is provided.
+pub struct RealTimeAStar {
+    lookahead: usize,
+    goals: Vec<Position>,
+    learned_h: HashMap<Position, u32>,
+    metrics: SearchMetrics,
+}
+
+impl RealTimeAStar {
+    /// Creates an RTAA* planner that expands at most `lookahead` nodes per call.
+    pub fn new(lookahead: usize) -> Self {
+        RealTimeAStar {
+            lookahead: lookahead.max(1),
+            goals: Vec::new(),
+            learned_h: HashMap::new(),
+            metrics: SearchMetrics::default(),
+        }
+    }
+
+    /// Creates an LRTA* planner (RTAA* with a single expansion per move).
+    pub fn lrta_star() -> Self {
+        Self::new(1)
+    }
+
+    /// Node expansions allowed per call.
+    pub fn lookahead(&self) -> usize {
+        self.lookahead
+    }
+
+    /// Learned heuristic, falling back to the grid heuristic (Manhattan or octile
+    /// distance) to the nearest goal for unvisited cells.
+    fn h(&self, grid: &Grid, pos: Position) -> u32 {
+        self.learned_h.get(&pos).copied().unwrap_or_else(|| {
+            self.goals
+                .iter()
+                .map(|&goal| grid.heuristic(pos, goal))
+                .min()
+                .unwrap_or(0)
+        })
+    }
+}
+
+impl PathfindingAlgorithm for RealTimeAStar {
+    fn find_path(
+        &mut self,
+        grid: &Grid,
+        start: Position,
+        goal: Position,
+        obstacles: &HashSet<Position>,
+    ) -> Option<Vec<Position>> {
+        self.find_path_to_any(grid, start, &[goal], obstacles)
+    }
+
+    /// Heads for the nearest of several goals; the learned values become distances to the goal set.
+    fn find_path_to_any(
+        &mut self,
+        grid: &Grid,
+        start: Position,
+        goals: &[Position],
+        obstacles: &HashSet<Position>,
+    ) -> Option<Vec<Position>> {
+        // Learned values are distances to a specific set of goals
+        if self.goals != goals {
+            self.learned_h.clear();
+            self.goals = goals.to_vec();
+        }
+
+        let mut g_scores: HashMap<Position, u32> = HashMap::new();
+        let mut parents: HashMap<Position, Position> = HashMap::new();
+        let mut closed: Vec<Position> = Vec::new();
+        let mut closed_set: HashSet<Position> = HashSet::new();
+        let mut open = BinaryHeap::new();
+
+        g_scores.insert(start, 0);
+        open.push(Reverse((self.h(grid, start), 0u32, start)));
+        self.metrics.pushed(open.len());
+
+        // Bounded A* lookahead
+        let frontier_best = loop {
+            let Some(Reverse((f, g, pos))) = open.pop() else {
+                return None; // Open list exhausted - goal unreachable
+            };
+            self.metrics.popped();
+            if closed_set.contains(&pos) || g_scores.get(&pos) != Some(&g) {
+                continue; // Stale entry
+            }
+            if goals.contains(&pos) || closed.len() >= self.lookahead {
+                break (pos, f);
+            }
+
+            closed.push(pos);
+            closed_set.insert(pos);
+            self.metrics.nodes_expanded += 1;
+
+            for neighbor in grid.get_neighbors(&pos) {
+                if !grid.can_move(pos, neighbor, |p| obstacles.contains(&p)) {
+                    continue;
+                }
+                self.metrics.nodes_generated += 1;
+                let tentative_g = g + grid.move_cost(pos, neighbor);
+                if tentative_g < g_scores.get(&neighbor).copied().unwrap_or(u32::MAX) {
+                    g_scores.insert(neighbor, tentative_g);
+                    parents.insert(neighbor, pos);
+                    open.push(Reverse((
+                        tentative_g + self.h(grid, neighbor),
+                        tentative_g,
+                        neighbor,
+                    )));
+                    self.metrics.pushed(open.len());
+                }
+            }
+        };
+
+        // Heuristic update: h(s) = f(best) - g(s) for every expanded state
+        let (best, best_f) = frontier_best;
+        for pos in closed {
+            // Never lower a value, which could happen after obstacles disappear
+            let learned = best_f.saturating_sub(g_scores[&pos]).max(self.h(grid, pos));
+            self.learned_h.insert(pos, learned);
+        }
+
+        // Partial path from the start to the best frontier node
+        let mut path = vec![best];
+        let mut current = best;
+        while let Some(&parent) = parents.get(&current) {
+            path.push(parent);
+            current = parent;
+        }
+        path.reverse();
+        Some(path)
+    }
+
+    fn update_environment(
+        &mut self,
+        grid: &Grid,
+        _obstacles: &HashSet<Position>,
+        changes: &EnvironmentChanges,
+    ) {
+        // A removed wall can open a shortcut the learned values overestimate, and
+        // walls do not come back the way obstacles do
+        if changes.walls.iter().any(|&pos| !grid.is_wall(pos)) {
+            self.learned_h.clear();
+        }
+    }
+
+    fn search_metrics(&self) -> SearchMetrics {
+        self.metrics
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
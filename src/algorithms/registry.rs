@@ -0,0 +1,375 @@
+use crate::algorithms::a_star::AStar;
+use crate::algorithms::a_star_fast::AStarFast;
+use crate::algorithms::ara_star::AraStar;
+use crate::algorithms::bfs::Bfs;
+use crate::algorithms::common::PathfindingAlgorithm;
+use crate::algorithms::d_star_lite::DStarLite;
+use crate::algorithms::dijkstra::Dijkstra;
+use crate::algorithms::field_d_star::FieldDStar;
+use crate::algorithms::flow_field::FlowField;
+use crate::algorithms::hpa_star::HpaStar;
+use crate::algorithms::hybrid_a_star_d_star::HybridAStarDStar;
+use crate::algorithms::jump_point_search::JumpPointSearch;
+use crate::algorithms::mt_d_star_lite::MtDStarLite;
+use crate::algorithms::potential_field::{EscapeStrategy, PotentialField};
+use crate::algorithms::priority_queue::QueueKind;
+use crate::algorithms::real_time_a_star::RealTimeAStar;
+use crate::algorithms::search_space::ObstacleCost;
+use crate::algorithms::space_time_a_star::SpaceTimeAStar;
+use crate::algorithms::switching_policy::SwitchingPolicyKind;
+use crate::algorithms::weighted_a_star::WeightedAStar;
+use crate::config::Config;
+use crate::error::ConfigError;
+use crate::grid::Position;
+use crate::simulation::Simulation;
+use crate::statistics::{MetricValue, PlannerMetrics};
+
+/// Everything a factory may use to build an algorithm for one run.
+pub struct AlgorithmContext<'a> {
+    pub config: &'a Config,
+    pub start: Position,
+    pub goal: Position,
+    pub width: usize,
+    pub height: usize,
+}
+
+/// Builds a fresh algorithm instance; errors are reported like invalid command-line options.
+pub type AlgorithmFactory =
+    Box<dyn Fn(&AlgorithmContext) -> Result<Box<dyn PathfindingAlgorithm>, String> + Send + Sync>;
+
+/// Turns a finished algorithm into its metrics. Also given the run's config and
+/// the number of path calculations the simulation counted; the search metrics are
+/// filled in afterwards from `PathfindingAlgorithm::search_metrics`.
+pub type StatsAdapter =
+    Box<dyn Fn(&mut dyn PathfindingAlgorithm, &Config, usize) -> PlannerMetrics + Send + Sync>;
+
+struct Registration {
+    name: String,
+    factory: AlgorithmFactory,
+    stats: StatsAdapter,
+}
+
+/// Named algorithms available to the simulation, `--algorithm all` and batch mode.
+///
+/// `with_builtins` registers every planner in this crate. Library users can add their
+/// own with `register`, then hand the registry to `Simulation::new_with_registry`,
+/// `Simulation::run_all_algorithms_with_registry` or `BatchSimulation::with_registry`.
+/// Algorithms run in registration order.
+pub struct AlgorithmRegistry {
+    registrations: Vec<Registration>,
+}
+
+impl Default for AlgorithmRegistry {
+    fn default() -> Self {
+        Self::with_builtins()
+    }
+}
+
+impl AlgorithmRegistry {
+    /// Creates a registry without any algorithms.
+    pub fn new() -> Self {
+        AlgorithmRegistry {
+            registrations: Vec::new(),
+        }
+    }
+
+    /// Creates a registry with all built-in algorithms.
+    pub fn with_builtins() -> Self {
+        let mut registry = Self::new();
+        registry
+            .register_with_stats(
+                "a_star",
+                |context| {
+                    let a_star = match Simulation::landmark_heuristic(context.config)? {
+                        Some(landmarks) => AStar::with_landmarks(landmarks),
+                        None => AStar::new(),
+                    };
+                    Ok(Box::new(
+                        a_star
+                            .with_waiting(context.config.wait_for_obstacles)
+                            .with_obstacle_cost(ObstacleCost::from_name(
+                                &context.config.obstacle_cost,
+                            )?),
+                    ))
+                },
+                |_, _, calls| PlannerMetrics::new("A*", calls),
+            )
+            .register_with_stats(
+                "a_star_fast",
+                |_| Ok(Box::new(AStarFast::new())),
+                |_, _, calls| PlannerMetrics::new("A* (reused buffers)", calls),
+            )
+            .register_with_stats(
+                "d_star_lite",
+                |context| {
+                    let queue_kind = QueueKind::from_name(&context.config.priority_queue)?;
+                    let (start, goal) = (context.start, context.goal);
+                    let (width, height) = (context.width, context.height);
+                    let d_star = match Simulation::landmark_heuristic(context.config)? {
+                        Some(landmarks) => {
+                            DStarLite::with_landmarks(start, goal, width, height, landmarks)
+                        }
+                        None => DStarLite::new(start, goal, width, height),
+                    }
+                    .with_queue(queue_kind)
+                    .with_obstacle_cost(ObstacleCost::from_name(&context.config.obstacle_cost)?);
+                    Ok(Box::new(d_star))
+                },
+                |_, _, calls| PlannerMetrics::new("D* Lite", calls),
+            )
+            .register_with_stats(
+                "hybrid",
+                |context| {
+                    Ok(Box::new(
+                        HybridAStarDStar::with_policy(
+                            context.start,
+                            context.goal,
+                            context.width,
+                            context.height,
+                            Simulation::switching_policy(context.config)?,
+                        )
+                        .with_obstacle_cost(ObstacleCost::from_name(
+                            &context.config.obstacle_cost,
+                        )?),
+                    ))
+                },
+                |algorithm, _, calls| {
+                    let (a_star_calls, d_star_calls) = algorithm.get_usage_stats();
+                    let policy = algorithm
+                        .as_any_mut()
+                        .downcast_mut::<HybridAStarDStar>()
+                        .map_or(SwitchingPolicyKind::DEFAULT.name(), |hybrid| {
+                            hybrid.policy_kind().name()
+                        });
+                    let metrics = PlannerMetrics::new("Hybrid A*/D*", calls)
+                        .with("switching_policy", MetricValue::Text(policy.to_string()))
+                        .with_delegated("A*", a_star_calls)
+                        .with_delegated("D* Lite", d_star_calls);
+                    // D* Lite repairs the path A* or its own earlier searches left
+                    match a_star_calls + d_star_calls {
+                        0 => metrics,
+                        searches => metrics.with_reuse_ratio(d_star_calls as f64 / searches as f64),
+                    }
+                },
+            )
+            .register_with_stats(
+                "jps",
+                |_| Ok(Box::new(JumpPointSearch::new())),
+                |_, _, calls| PlannerMetrics::new("Jump Point Search", calls),
+            )
+            .register_with_stats(
+                "weighted_a_star",
+                |context| {
+                    Ok(Box::new(WeightedAStar::new(
+                        context.config.heuristic_weight,
+                    )))
+                },
+                |_, config, calls| {
+                    PlannerMetrics::new("Weighted A*", calls).with(
+                        "heuristic_weight",
+                        MetricValue::Number(config.heuristic_weight.max(1.0)),
+                    )
+                },
+            )
+            .register_with_stats(
+                "ara_star",
+                |context| Ok(Box::new(AraStar::new(context.config.plan_budget_ms))),
+                |_, _, calls| PlannerMetrics::new("ARA*", calls),
+            )
+            .register_with_stats(
+                "mt_d_star_lite",
+                |context| {
+                    Ok(Box::new(MtDStarLite::new(
+                        context.start,
+                        context.goal,
+                        context.width,
+                        context.height,
+                    )))
+                },
+                |_, _, calls| PlannerMetrics::new("MT-D* Lite", calls),
+            )
+            .register_with_stats(
+                "lrta_star",
+                |_| Ok(Box::new(RealTimeAStar::lrta_star())),
+                |_, _, calls| {
+                    PlannerMetrics::new("LRTA*", calls).with("lookahead", MetricValue::Count(1))
+                },
+            )
+            .register_with_stats(
+                "rtaa_star",
+                |context| Ok(Box::new(RealTimeAStar::new(context.config.lookahead))),
+                |_, config, calls| {
+                    let lookahead = config.lookahead.max(1);
+                    // A lookahead of one expansion is LRTA*
+                    let planner = if lookahead == 1 { "LRTA*" } else { "RTAA*" };
+                    PlannerMetrics::new(planner, calls)
+                        .with("lookahead", MetricValue::Count(lookahead))
+                },
+            )
+            .register_with_stats(
+                "dijkstra",
+                |_| Ok(Box::new(Dijkstra::new())),
+                |_, _, calls| PlannerMetrics::new("Dijkstra", calls),
+            )
+            .register_with_stats(
+                "bfs",
+                |_| Ok(Box::new(Bfs::new())),
+                |_, _, calls| PlannerMetrics::new("BFS", calls),
+            )
+            .register_with_stats(
+                "field_d_star",
+                |context| {
+                    Ok(Box::new(FieldDStar::new(
+                        context.start,
+                        context.goal,
+                        context.width,
+                        context.height,
+                    )))
+                },
+                |_, _, calls| PlannerMetrics::new("Field D*", calls),
+            )
+            .register_with_stats(
+                "hpa_star",
+                |context| Ok(Box::new(HpaStar::new(context.config.cluster_size))),
+                |algorithm, _, calls| match algorithm.as_any_mut().downcast_mut::<HpaStar>() {
+                    Some(hpa) => PlannerMetrics::new("HPA*", calls)
+                        .with("cluster_size", MetricValue::Count(hpa.cluster_size()))
+                        .with("build_time", MetricValue::Duration(hpa.build_time()))
+                        .with("rebuilds", MetricValue::Count(hpa.rebuilds()))
+                        .with("rebuild_time", MetricValue::Duration(hpa.rebuild_time())),
+                    None => PlannerMetrics::new("HPA*", calls),
+                },
+            )
+            .register_with_stats(
+                "flow_field",
+                |_| Ok(Box::new(FlowField::new())),
+                |algorithm, _, calls| match algorithm.as_any_mut().downcast_mut::<FlowField>() {
+                    Some(flow_field) => {
+                        let builds = flow_field.field_builds();
+                        let metrics = PlannerMetrics::new("Flow Field", calls)
+                            .with("field_builds", MetricValue::Count(builds))
+                            .with(
+                                "cells_repaired",
+                                MetricValue::Count(flow_field.cells_repaired()),
+                            );
+                        // Every call but those that built the field repaired or reused it
+                        match calls {
+                            0 => metrics,
+                            calls => metrics.with_reuse_ratio(
+                                calls.saturating_sub(builds) as f64 / calls as f64,
+                            ),
+                        }
+                    }
+                    None => PlannerMetrics::new("Flow Field", calls),
+                },
+            )
+            .register_with_stats(
+                "potential_field",
+                |context| {
+                    Ok(Box::new(PotentialField::new(EscapeStrategy::from_name(
+                        &context.config.escape_strategy,
+                    )?)))
+                },
+                |algorithm, _, calls| match algorithm.as_any_mut().downcast_mut::<PotentialField>()
+                {
+                    Some(potential_field) => PlannerMetrics::new("Potential Field", calls)
+                        .with(
+                            "escape_strategy",
+                            MetricValue::Text(potential_field.escape_strategy().name().to_string()),
+                        )
+                        .with("escapes", MetricValue::Count(potential_field.escapes()))
+                        .with(
+                            "successful_escapes",
+                            MetricValue::Count(potential_field.successful_escapes()),
+                        ),
+                    None => PlannerMetrics::new("Potential Field", calls),
+                },
+            )
+            .register_with_stats(
+                "space_time_a_star",
+                |_| Ok(Box::new(SpaceTimeAStar::new())),
+                |_, _, calls| PlannerMetrics::new("Space-time A* (oracle)", calls),
+            );
+        registry
+    }
+
+    /// Registers an algorithm reported under its name with no metrics of its own.
+    /// Registering a name that already exists replaces that algorithm in place.
+    pub fn register<F>(&mut self, name: &str, factory: F) -> &mut Self
+    where
+        F: Fn(&AlgorithmContext) -> Result<Box<dyn PathfindingAlgorithm>, String>
+            + Send
+            + Sync
+            + 'static,
+    {
+        let stats_name = name.to_string();
+        self.register_with_stats(name, factory, move |_, _, calls| {
+            PlannerMetrics::new(stats_name.clone(), calls)
+        })
+    }
+
+    /// Registers an algorithm together with the adapter that reports its metrics.
+    pub fn register_with_stats<F, S>(&mut self, name: &str, factory: F, stats: S) -> &mut Self
+    where
+        F: Fn(&AlgorithmContext) -> Result<Box<dyn PathfindingAlgorithm>, String>
+            + Send
+            + Sync
+            + 'static,
+        S: Fn(&mut dyn PathfindingAlgorithm, &Config, usize) -> PlannerMetrics
+            + Send
+            + Sync
+            + 'static,
+    {
+        let registration = Registration {
+            name: name.to_string(),
+            factory: Box::new(factory),
+            stats: Box::new(stats),
+        };
+        match self.registrations.iter_mut().find(|r| r.name == name) {
+            Some(existing) => *existing = registration,
+            None => self.registrations.push(registration),
+        }
+        self
+    }
+
+    /// Names of the registered algorithms, in registration order.
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.registrations.iter().map(|r| r.name.as_str())
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.registrations.iter().any(|r| r.name == name)
+    }
+
+    /// Builds the named algorithm.
+    pub fn create(
+        &self,
+        name: &str,
+        context: &AlgorithmContext,
+    ) -> Result<Box<dyn PathfindingAlgorithm>, ConfigError> {
+        Ok((self.find(name)?.factory)(context)?)
+    }
+
+    /// Metrics of a finished run of the named algorithm.
+    pub fn stats(
+        &self,
+        name: &str,
+        algorithm: &mut dyn PathfindingAlgorithm,
+        config: &Config,
+        calls: usize,
+    ) -> PlannerMetrics {
+        let mut metrics = match self.find(name) {
+            Ok(registration) => (registration.stats)(algorithm, config, calls),
+            Err(_) => PlannerMetrics::new(name, calls),
+        };
+        metrics.search = algorithm.search_metrics();
+        metrics
+    }
+
+    fn find(&self, name: &str) -> Result<&Registration, ConfigError> {
+        self.registrations
+            .iter()
+            .find(|r| r.name == name)
+            .ok_or_else(|| ConfigError::UnknownAlgorithm(name.to_string()))
+    }
+}
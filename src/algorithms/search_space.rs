@@ -0,0 +1,164 @@
+use crate::algorithms::common::ObstacleLifetimes;
+use crate::algorithms::landmarks::LandmarkHeuristic;
+use crate::grid::{Grid, Position};
+use std::collections::HashSet;
+use std::hash::Hash;
+
+/// A graph the generic planners (A*, D* Lite and the hybrid) can search.
+///
+/// Nodes are small copyable handles such as grid positions or navmesh polygon ids.
+/// `neighbors` lists the nodes an edge leads to, and `cost` says whether that edge
+/// can currently be traversed and at what price. Keeping the two apart lets a
+/// node's edges be blocked temporarily (a dynamic obstacle) without changing the
+/// graph's shape, which is what D* Lite needs to repair its search incrementally.
+///
+/// D* Lite keeps its search state in flat vectors, so every node also needs a
+/// dense index below `node_count`.
+pub trait SearchSpace {
+    /// Ordered so planners can break ties deterministically.
+    type Node: Copy + Eq + Hash + Ord + 'static;
+
+    /// Nodes reachable from `node` over a single edge, blocked or not.
+    fn neighbors(&self, node: Self::Node) -> Vec<Self::Node>;
+
+    /// Nodes with an edge into `node`. Defaults to `neighbors` (undirected graphs).
+    fn predecessors(&self, node: Self::Node) -> Vec<Self::Node> {
+        self.neighbors(node)
+    }
+
+    /// Cost of the edge from `from` to `to`, or `None` while it cannot be traversed.
+    fn cost(&self, from: Self::Node, to: Self::Node) -> Option<u32>;
+
+    /// Estimated cost from `from` to `to`; must never overestimate for optimal paths.
+    fn heuristic(&self, from: Self::Node, to: Self::Node) -> u32;
+
+    /// Number of nodes; indices run from 0 to `node_count() - 1`.
+    fn node_count(&self) -> usize;
+
+    /// Dense index of a node.
+    fn index(&self, node: Self::Node) -> usize;
+}
+
+/// How the grid planners (`a_star`, `d_star_lite` and the hybrid) price a known
+/// obstacle that is going to expire.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ObstacleCost {
+    /// Like any other obstacle: it cannot be entered until it is gone.
+    #[default]
+    Blocked,
+    /// As a cell that costs a wait of a straight move for every step it stays, so
+    /// a path through an obstacle about to go can beat a long detour. The path is
+    /// then timed to wait in front of it until it is gone.
+    TimeAware,
+}
+
+impl ObstacleCost {
+    /// Parses an obstacle cost mode name as given on the command line.
+    pub fn from_name(name: &str) -> Result<Self, String> {
+        match name {
+            "blocked" => Ok(ObstacleCost::Blocked),
+            "time_aware" => Ok(ObstacleCost::TimeAware),
+            _ => Err(format!(
+                "Unknown obstacle cost: '{}' (expected 'blocked' or 'time_aware')",
+                name
+            )),
+        }
+    }
+
+    /// Name of the mode as given on the command line.
+    pub fn name(self) -> &'static str {
+        match self {
+            ObstacleCost::Blocked => "blocked",
+            ObstacleCost::TimeAware => "time_aware",
+        }
+    }
+
+    /// The lifetimes a `GridSpace` should price obstacles by in this mode
+    pub fn lifetimes(self, lifetimes: &ObstacleLifetimes) -> Option<&ObstacleLifetimes> {
+        (self == ObstacleCost::TimeAware).then_some(lifetimes)
+    }
+}
+
+/// The simulation grid as a search space: 4-connected cells with unit costs, or
+/// 8-connected cells with octile costs when the grid allows diagonal movement.
+///
+/// Walls are left out of the graph entirely, while known dynamic obstacles only
+/// block the edges leading into them (and diagonal edges squeezing past them). The
+/// heuristic is the grid's own (Manhattan or octile distance), or the ALT landmark
+/// bound when landmarks are given.
+pub struct GridSpace<'a> {
+    grid: &'a Grid,
+    obstacles: &'a HashSet<Position>,
+    landmarks: Option<&'a LandmarkHeuristic>,
+    /// Obstacles that only cost the wait for them to expire (`ObstacleCost::TimeAware`)
+    lifetimes: Option<&'a ObstacleLifetimes>,
+}
+
+impl<'a> GridSpace<'a> {
+    pub fn new(grid: &'a Grid, obstacles: &'a HashSet<Position>) -> Self {
+        GridSpace {
+            grid,
+            obstacles,
+            landmarks: None,
+            lifetimes: None,
+        }
+    }
+
+    /// Use the landmark heuristic (already prepared for this grid) when one is given.
+    pub fn with_landmarks(mut self, landmarks: Option<&'a LandmarkHeuristic>) -> Self {
+        self.landmarks = landmarks;
+        self
+    }
+
+    /// Let edges into (or squeezing past) obstacles that are going to expire be
+    /// traversed, at the cost of waiting for them, when lifetimes are given. Costs
+    /// only ever go up from an open grid, so the heuristics stay admissible.
+    pub fn with_lifetimes(mut self, lifetimes: Option<&'a ObstacleLifetimes>) -> Self {
+        self.lifetimes = lifetimes;
+        self
+    }
+}
+
+impl SearchSpace for GridSpace<'_> {
+    type Node = Position;
+
+    fn neighbors(&self, node: Position) -> Vec<Position> {
+        self.grid.get_neighbors(&node)
+    }
+
+    fn cost(&self, from: Position, to: Position) -> Option<u32> {
+        let Some(lifetimes) = self.lifetimes else {
+            return self
+                .grid
+                .can_move(from, to, |pos| self.obstacles.contains(&pos))
+                .then(|| self.grid.move_cost(from, to));
+        };
+        let lasting = |pos: Position| self.obstacles.contains(&pos) && !lifetimes.contains(pos);
+        if !self.grid.can_move(from, to, lasting) {
+            return None;
+        }
+        let wait = lifetimes.wait_before(self.grid, from, to) as u32;
+        Some(
+            self.grid
+                .move_cost(from, to)
+                .saturating_add(wait.saturating_mul(self.grid.straight_cost())),
+        )
+    }
+
+    fn heuristic(&self, from: Position, to: Position) -> u32 {
+        match self.landmarks {
+            // Landmark lower bound, which is never below the grid heuristic
+            Some(landmarks) => landmarks.estimate(from, to),
+            None => self.grid.heuristic(from, to),
+        }
+    }
+
+    fn node_count(&self) -> usize {
+        self.grid.cell_count()
+    }
+
+    #[inline(always)]
+    fn index(&self, node: Position) -> usize {
+        self.grid.index(node)
+    }
+}
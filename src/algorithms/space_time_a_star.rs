@@ -0,0 +1,199 @@
+use crate::algorithms::common::{
+    AbortHandle, ObstacleSchedule, PathfindingAlgorithm, SearchMetrics,
+};
+use crate::grid::{Grid, Position};
+use std::any::Any;
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap, HashSet};
+use std::sync::Arc;
+
+/// A state of the search: a cell's index and the step the agent is on it at
+type State = (usize, usize);
+
+/// States a search reaches before it gives up with no path unless told otherwise
+/// (`with_state_limit`). Each costs some tens of bytes, so a search stays within a
+/// few hundred megabytes however long the timeline is.
+pub const MAX_STATES: usize = 1 << 21;
+
+/// A* in space and time against the whole obstacle timeline: an oracle.
+///
+/// Every other planner only knows the obstacles the agent has seen, and the
+/// simulation keeps replanning as more turn up. This one is given the timeline the
+/// environment was generated with (`update_obstacle_schedule`), so it searches
+/// (cell, step) states, moving to a neighbor or waiting in place each step, and only
+/// ever enters a cell at a step no scheduled obstacle stands on it. Its first plan
+/// is the cheapest way to the goal there is, with waits costing a straight move, so
+/// its run is the baseline the other planners' runs in a dynamic environment can
+/// be measured against.
+///
+/// Once the last scheduled obstacle is gone every step looks the same, so states
+/// past `ObstacleSchedule::end` share one step and the search always ends. Known
+/// obstacles that are not in the schedule, such as moving obstacles, are taken to
+/// stay for good. Without a schedule (before it is given one, or outside the
+/// simulation) it is plain A* around the obstacles it is passed.
+///
+/// A long timeline on a large grid has more states than fit in memory, so a search
+/// that reaches `MAX_STATES` of them gives up and finds no path.
+pub struct SpaceTimeAStar {
+    schedule: Arc<ObstacleSchedule>,
+    /// Step the next plan starts at
+    step: usize,
+    /// Most states a search may reach
+    state_limit: usize,
+    metrics: SearchMetrics,
+    abort: AbortHandle,
+}
+
+impl Default for SpaceTimeAStar {
+    fn default() -> Self {
+        SpaceTimeAStar {
+            schedule: Arc::default(),
+            step: 0,
+            state_limit: MAX_STATES,
+            metrics: SearchMetrics::default(),
+            abort: AbortHandle::default(),
+        }
+    }
+}
+
+impl SpaceTimeAStar {
+    pub fn new() -> Self {
+        SpaceTimeAStar::default()
+    }
+
+    /// Gives up searches that reach more than `state_limit` states instead of
+    /// `MAX_STATES`
+    pub fn with_state_limit(mut self, state_limit: usize) -> Self {
+        self.state_limit = state_limit;
+        self
+    }
+
+    /// Cheapest timed path from `start` at the current step to the closest of
+    /// `goals`, one position per step (repeated while it waits), or `None` when
+    /// there is none, the search reached its state limit or it was aborted
+    fn search(
+        &mut self,
+        grid: &Grid,
+        start: Position,
+        goals: &[Position],
+        obstacles: &HashSet<Position>,
+    ) -> Option<Vec<Position>> {
+        let schedule = Arc::clone(&self.schedule);
+        // Steps from `end` on are all alike, so a state's step stops counting there
+        let end = schedule.end().max(self.step);
+        let blocked = |pos: Position, step: usize| {
+            schedule.is_blocked(pos, step) || (obstacles.contains(&pos) && !schedule.contains(pos))
+        };
+        let heuristic = |pos: Position| {
+            goals
+                .iter()
+                .map(|&goal| grid.heuristic(pos, goal))
+                .min()
+                .unwrap_or(0)
+        };
+
+        let start_state = (grid.index(start), self.step);
+        // Cost of the cheapest way found to each state reached, and the state before
+        let mut reached: HashMap<State, (u32, State)> =
+            HashMap::from([(start_state, (0, start_state))]);
+        let mut open = BinaryHeap::new();
+        let start_h = heuristic(start);
+        open.push(Reverse((start_h, start_h, start_state)));
+        self.metrics.pushed(open.len());
+
+        let mut neighbors = Vec::new();
+        let mut found = None;
+        'search: while let Some(Reverse((f, h, state))) = open.pop() {
+            self.metrics.popped();
+            let g = f - h;
+            if g > reached[&state].0 {
+                continue; // Reached more cheaply since this entry was pushed
+            }
+            let (index, step) = state;
+            let pos = grid.position(index);
+            if goals.contains(&pos) {
+                found = Some(state);
+                break;
+            }
+            // An aborted search stops expanding and returns no path
+            if self.abort.is_aborted_after(self.metrics.nodes_expanded) {
+                break;
+            }
+            self.metrics.nodes_expanded += 1;
+
+            // Waiting only helps while some scheduled obstacle is still to go
+            let next_step = (step + 1).min(end);
+            grid.neighbors_into(&pos, &mut neighbors);
+            if step < end {
+                neighbors.push(pos);
+            }
+            for &next in &neighbors {
+                if !grid.can_move(pos, next, |cell| blocked(cell, next_step)) {
+                    continue;
+                }
+                self.metrics.nodes_generated += 1;
+                let next_g = g.saturating_add(grid.move_cost(pos, next));
+                let next_state = (grid.index(next), next_step);
+                match reached.get(&next_state) {
+                    Some(&(known, _)) if known <= next_g => continue,
+                    // Too many states to keep: give up rather than run out of memory
+                    None if reached.len() >= self.state_limit => break 'search,
+                    _ => {}
+                }
+                reached.insert(next_state, (next_g, state));
+                let next_h = heuristic(next);
+                open.push(Reverse((next_g.saturating_add(next_h), next_h, next_state)));
+                self.metrics.pushed(open.len());
+            }
+        }
+
+        let mut state = found?;
+        let mut path = vec![grid.position(state.0)];
+        while state != start_state {
+            state = reached[&state].1;
+            path.push(grid.position(state.0));
+        }
+        path.reverse();
+        Some(path)
+    }
+}
+
+impl PathfindingAlgorithm for SpaceTimeAStar {
+    fn find_path(
+        &mut self,
+        grid: &Grid,
+        start: Position,
+        goal: Position,
+        obstacles: &HashSet<Position>,
+    ) -> Option<Vec<Position>> {
+        self.search(grid, start, &[goal], obstacles)
+    }
+
+    /// Finds a timed path to the closest of several goals, in one search.
+    fn find_path_to_any(
+        &mut self,
+        grid: &Grid,
+        start: Position,
+        goals: &[Position],
+        obstacles: &HashSet<Position>,
+    ) -> Option<Vec<Position>> {
+        self.search(grid, start, goals, obstacles)
+    }
+
+    fn search_metrics(&self) -> SearchMetrics {
+        self.metrics
+    }
+
+    fn set_abort_handle(&mut self, abort: AbortHandle) {
+        self.abort = abort;
+    }
+
+    fn update_obstacle_schedule(&mut self, schedule: &Arc<ObstacleSchedule>, step: usize) {
+        self.schedule = Arc::clone(schedule);
+        self.step = step;
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
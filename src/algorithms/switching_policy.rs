@@ -0,0 +1,183 @@
+use std::collections::VecDeque;
+use std::time::Duration;
+
+/// Smoothing factor for the moving averages kept by `TimeBudgetPolicy`.
+const TIME_AVERAGE_ALPHA: f64 = 0.3;
+/// After this many A* calls in a row, `TimeBudgetPolicy` gives D* Lite another try.
+const D_STAR_PROBE_INTERVAL: usize = 5;
+/// Number of recent calls `ChangeRatePolicy` averages obstacle changes over.
+const CHANGE_RATE_WINDOW: usize = 5;
+
+/// What the hybrid planner knows when it decides which planner to run.
+pub struct SwitchContext {
+    /// How far the start moved since the last call (heuristic distance; Manhattan on the grid)
+    pub start_jump: usize,
+    /// Net change in the number of blocked nodes since the last call
+    pub obstacle_delta: usize,
+    /// Number of nodes whose edge costs changed since the last call
+    pub changed_nodes: usize,
+}
+
+/// Decides when the hybrid planner replans from scratch with A* instead of
+/// repairing its D* Lite search.
+///
+/// The hybrid always uses A* for the first path and after the goal changes; the
+/// policy is only consulted for the calls in between.
+pub trait SwitchingPolicy {
+    /// Returns true if this call should be answered by A*.
+    fn should_use_astar(&mut self, context: &SwitchContext) -> bool;
+
+    /// Feedback after a call: which planner answered it and how long it took.
+    fn record(&mut self, _used_astar: bool, _elapsed: Duration) {}
+}
+
+/// Which switching policy to use, along with its tuning parameters.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SwitchingPolicyKind {
+    /// Fixed thresholds on how far the start jumped and how much the obstacle count changed.
+    Threshold {
+        max_start_jump: usize,
+        max_obstacle_delta: usize,
+    },
+    /// Stick with D* Lite while its recent calls stay within a time budget.
+    TimeBudget { budget: Duration },
+    /// Use A* while obstacles change faster than the given cells per call.
+    ChangeRate { max_rate: f64 },
+}
+
+impl SwitchingPolicyKind {
+    /// Thresholds the hybrid planner has always used: a start jump of more than
+    /// 3 cells or an obstacle count change of more than 5.
+    pub const DEFAULT: SwitchingPolicyKind = SwitchingPolicyKind::Threshold {
+        max_start_jump: 3,
+        max_obstacle_delta: 5,
+    };
+
+    /// Name as accepted on the command line.
+    pub fn name(&self) -> &'static str {
+        match self {
+            SwitchingPolicyKind::Threshold { .. } => "threshold",
+            SwitchingPolicyKind::TimeBudget { .. } => "time_budget",
+            SwitchingPolicyKind::ChangeRate { .. } => "change_rate",
+        }
+    }
+
+    /// Creates a fresh policy of this kind.
+    pub fn create(&self) -> Box<dyn SwitchingPolicy> {
+        match *self {
+            SwitchingPolicyKind::Threshold {
+                max_start_jump,
+                max_obstacle_delta,
+            } => Box::new(ThresholdPolicy::new(max_start_jump, max_obstacle_delta)),
+            SwitchingPolicyKind::TimeBudget { budget } => Box::new(TimeBudgetPolicy::new(budget)),
+            SwitchingPolicyKind::ChangeRate { max_rate } => {
+                Box::new(ChangeRatePolicy::new(max_rate))
+            }
+        }
+    }
+}
+
+/// Replans with A* when the start jumped more than `max_start_jump` cells
+/// or the number of known obstacles changed by more than `max_obstacle_delta`.
+pub struct ThresholdPolicy {
+    max_start_jump: usize,
+    max_obstacle_delta: usize,
+}
+
+impl ThresholdPolicy {
+    pub fn new(max_start_jump: usize, max_obstacle_delta: usize) -> Self {
+        ThresholdPolicy {
+            max_start_jump,
+            max_obstacle_delta,
+        }
+    }
+}
+
+impl SwitchingPolicy for ThresholdPolicy {
+    fn should_use_astar(&mut self, context: &SwitchContext) -> bool {
+        context.start_jump > self.max_start_jump || context.obstacle_delta > self.max_obstacle_delta
+    }
+}
+
+/// Prefers D* Lite, but switches to A* once D* Lite's moving-average call time
+/// exceeds the budget and A* has been (or might be) faster. D* Lite is retried
+/// every few calls so the policy can notice when repairs become cheap again.
+pub struct TimeBudgetPolicy {
+    budget: Duration,
+    a_star_average: Option<Duration>,
+    d_star_average: Option<Duration>,
+    a_star_streak: usize,
+}
+
+impl TimeBudgetPolicy {
+    pub fn new(budget: Duration) -> Self {
+        TimeBudgetPolicy {
+            budget,
+            a_star_average: None,
+            d_star_average: None,
+            a_star_streak: 0,
+        }
+    }
+
+    fn update_average(average: &mut Option<Duration>, elapsed: Duration) {
+        *average = Some(match *average {
+            Some(previous) => {
+                previous.mul_f64(1.0 - TIME_AVERAGE_ALPHA) + elapsed.mul_f64(TIME_AVERAGE_ALPHA)
+            }
+            None => elapsed,
+        });
+    }
+}
+
+impl SwitchingPolicy for TimeBudgetPolicy {
+    fn should_use_astar(&mut self, _context: &SwitchContext) -> bool {
+        let Some(d_star_average) = self.d_star_average else {
+            return false;
+        };
+        if d_star_average <= self.budget || self.a_star_streak >= D_STAR_PROBE_INTERVAL {
+            return false;
+        }
+        self.a_star_average
+            .is_none_or(|a_star_average| a_star_average < d_star_average)
+    }
+
+    fn record(&mut self, used_astar: bool, elapsed: Duration) {
+        if used_astar {
+            Self::update_average(&mut self.a_star_average, elapsed);
+            self.a_star_streak += 1;
+        } else {
+            Self::update_average(&mut self.d_star_average, elapsed);
+            self.a_star_streak = 0;
+        }
+    }
+}
+
+/// Replans with A* while the environment is changing quickly: when the average
+/// number of obstacle cells that appeared or disappeared per call, over the last
+/// few calls, exceeds `max_rate`. Slowly changing maps are left to D* Lite.
+pub struct ChangeRatePolicy {
+    max_rate: f64,
+    recent_changes: VecDeque<usize>,
+}
+
+impl ChangeRatePolicy {
+    pub fn new(max_rate: f64) -> Self {
+        ChangeRatePolicy {
+            max_rate,
+            recent_changes: VecDeque::with_capacity(CHANGE_RATE_WINDOW),
+        }
+    }
+}
+
+impl SwitchingPolicy for ChangeRatePolicy {
+    fn should_use_astar(&mut self, context: &SwitchContext) -> bool {
+        if self.recent_changes.len() == CHANGE_RATE_WINDOW {
+            self.recent_changes.pop_front();
+        }
+        self.recent_changes.push_back(context.changed_nodes);
+
+        let rate =
+            self.recent_changes.iter().sum::<usize>() as f64 / self.recent_changes.len() as f64;
+        rate > self.max_rate
+    }
+}
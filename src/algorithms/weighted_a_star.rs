@@ -0,0 +1,97 @@
+use crate::algorithms::common::{AbortHandle, PathfindingAlgorithm, SearchMetrics};
+use crate::grid::{Grid, Position};
+use pathfinding::prelude::astar;
+use std::any::Any;
+use std::collections::HashSet;
+
+/// Fixed-point scale used so fractional heuristic weights work with integer costs.
+const COST_SCALE: u32 = 1000;
+
+/// Implements Weighted A*, which inflates the heuristic by a constant factor.
+///
+/// With a weight `w >= 1` the returned path is guaranteed to be at most `w` times
+/// longer than the optimal path, in exchange for expanding fewer nodes.
+pub struct WeightedAStar {
+    weight: f64,
+    metrics: SearchMetrics,
+    abort: AbortHandle,
+}
+
+impl WeightedAStar {
+    /// Creates a new Weighted A* provider. Weights below 1.0 are clamped to 1.0 (plain A*).
+    pub fn new(weight: f64) -> Self {
+        WeightedAStar {
+            weight: weight.max(1.0),
+            metrics: SearchMetrics::default(),
+            abort: AbortHandle::default(),
+        }
+    }
+
+    /// The heuristic inflation factor, which is also the suboptimality bound.
+    pub fn weight(&self) -> f64 {
+        self.weight
+    }
+}
+
+impl PathfindingAlgorithm for WeightedAStar {
+    /// Finds a path from start to goal using A* with an inflated Manhattan (or octile) heuristic.
+    ///
+    /// # Arguments
+    ///
+    /// * `grid` - The simulation grid.
+    /// * `start` - The starting position.
+    /// * `goal` - The goal position.
+    /// * `obstacles` - A set of known obstacle positions to avoid.
+    ///
+    /// # Returns
+    ///
+    /// An `Option` containing a `Vec<Position>` representing the path, or `None` if no path is found.
+    fn find_path(
+        &mut self,
+        grid: &Grid,
+        start: Position,
+        goal: Position,
+        obstacles: &HashSet<Position>,
+    ) -> Option<Vec<Position>> {
+        let weighted_scale = self.weight * COST_SCALE as f64;
+
+        let (metrics, abort) = (&mut self.metrics, &self.abort);
+        let result = astar(
+            &start,
+            |p| {
+                // An aborted search expands nothing more, so the open list runs dry
+                if abort.is_aborted_after(metrics.nodes_expanded) {
+                    return Vec::new();
+                }
+                metrics.expanded(
+                    grid.get_neighbors(p)
+                        .into_iter()
+                        .filter(|&neighbor| {
+                            grid.can_move(*p, neighbor, |pos| obstacles.contains(&pos))
+                        })
+                        .map(|successor| (successor, grid.move_cost(*p, successor) * COST_SCALE))
+                        .collect::<Vec<_>>(),
+                )
+            },
+            |p| {
+                // Inflated grid heuristic, in the same fixed-point units as the step cost.
+                (grid.heuristic(*p, goal) as f64 * weighted_scale) as u32
+            },
+            |p| *p == goal,
+        );
+
+        result.map(|(path, _)| path)
+    }
+
+    fn search_metrics(&self) -> SearchMetrics {
+        self.metrics
+    }
+
+    fn set_abort_handle(&mut self, abort: AbortHandle) {
+        self.abort = abort;
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
@@ -0,0 +1,121 @@
+use crate::{messages, say};
+use indicatif::{ProgressBar, ProgressDrawTarget, ProgressState, ProgressStyle};
+use std::fmt;
+use std::io::IsTerminal;
+use std::time::{Duration, Instant};
+
+/// How often progress is logged when messages do not go to a terminal
+const LOG_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Progress of a batch: a progress bar with the completed simulations, their
+/// throughput, the time left and the configuration being run when messages go to a
+/// terminal, otherwise a progress line every `LOG_INTERVAL`, fit for log files.
+pub struct BatchProgress {
+    /// The bar, or `None` when progress is logged instead
+    bar: Option<ProgressBar>,
+    total: usize,
+    completed: usize,
+    /// Configuration of the last simulation completed, as shown
+    configuration: String,
+    started: Instant,
+    last_logged: Instant,
+}
+
+impl BatchProgress {
+    /// Progress of `total` simulations
+    pub fn new(total: usize) -> Self {
+        // Drawn where the other messages go
+        let (terminal, target) = match messages::to_stderr() {
+            true => (
+                std::io::stderr().is_terminal(),
+                ProgressDrawTarget::stderr(),
+            ),
+            false => (
+                std::io::stdout().is_terminal(),
+                ProgressDrawTarget::stdout(),
+            ),
+        };
+        let bar = terminal.then(|| {
+            let bar = ProgressBar::with_draw_target(Some(total as u64), target);
+            bar.set_style(
+                ProgressStyle::with_template(
+                    "[{elapsed_precise}] {wide_bar} {pos}/{len} simulations ({throughput}, ETA {eta}) {msg}",
+                )
+                .expect("the progress bar template is valid")
+                .with_key("throughput", |state: &ProgressState, out: &mut dyn fmt::Write| {
+                    let _ = write!(out, "{:.1}/s", state.per_sec());
+                }),
+            );
+            bar.enable_steady_tick(Duration::from_millis(250));
+            bar
+        });
+        let now = Instant::now();
+        BatchProgress {
+            bar,
+            total,
+            completed: 0,
+            configuration: String::new(),
+            started: now,
+            last_logged: now,
+        }
+    }
+
+    /// Print a line without breaking up the bar
+    pub fn println(&self, line: impl AsRef<str>) {
+        match &self.bar {
+            Some(bar) => bar.println(line),
+            None => say!("{}", line.as_ref()),
+        }
+    }
+
+    /// Run `f`, which may print, with the bar taken off the screen
+    pub fn suspend<R>(&self, f: impl FnOnce() -> R) -> R {
+        match &self.bar {
+            Some(bar) => bar.suspend(f),
+            None => f(),
+        }
+    }
+
+    /// Count a completed simulation of `configuration`
+    pub fn complete(&mut self, configuration: String) {
+        self.completed += 1;
+        if let Some(bar) = &self.bar {
+            bar.inc(1);
+            if configuration != self.configuration {
+                bar.set_message(configuration.clone());
+            }
+        }
+        self.configuration = configuration;
+    }
+
+    /// Log the progress when it is due; the bar draws itself
+    pub fn tick(&mut self) {
+        if self.bar.is_some() || self.last_logged.elapsed() < LOG_INTERVAL {
+            return;
+        }
+        let elapsed = self.started.elapsed().as_secs_f64();
+        let throughput = self.completed as f64 / elapsed.max(f64::EPSILON);
+        let remaining = match self.completed {
+            0 => 0.0,
+            completed => (self.total - completed) as f64 / throughput.max(f64::EPSILON),
+        };
+        say!(
+            "Progress: {:.1}% ({}/{}) - {:.1} simulations/s - Elapsed: {:.1}s - ETA: {:.1}s - {}",
+            self.completed as f64 / self.total.max(1) as f64 * 100.0,
+            self.completed,
+            self.total,
+            throughput,
+            elapsed,
+            remaining,
+            self.configuration
+        );
+        self.last_logged = Instant::now();
+    }
+
+    /// Take the bar off the screen, leaving what was printed above it
+    pub fn finish(&self) {
+        if let Some(bar) = &self.bar {
+            bar.finish_and_clear();
+        }
+    }
+}
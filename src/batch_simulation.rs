@@ -1,335 +1,783 @@
-use crate::config::Config;
-use crate::simulation::{AlgorithmResult, Simulation};
-use crate::statistics::AlgorithmStats;
-use std::collections::HashMap;
-use std::fs::OpenOptions;
+use crate::algorithms::common::AbortHandle;
+use crate::algorithms::registry::AlgorithmRegistry;
+use crate::batch_progress::BatchProgress;
+use crate::batch_summary::BatchSummary;
+use crate::config::{BatchOptions, Config};
+use crate::cooperative::Coordination;
+use crate::error::{ConfigError, SimulationError};
+use crate::map_file::MapLayout;
+#[cfg(feature = "parquet")]
+use crate::parquet_output::ParquetWriter;
+use crate::report::write_report;
+use crate::say;
+use crate::simulation::{AlgorithmResult, EnvironmentSetup, Simulation};
+use crate::sweep_spec::SweepSpec;
+use crate::wall_pattern::WallPattern;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::collections::{BTreeMap, HashMap};
+use std::fs::{File, OpenOptions};
 use std::io::Write;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{mpsc, Arc};
+use std::thread;
 use std::time::{Duration, Instant};
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct BatchResult {
+    /// Index of the swept configuration the simulation ran in, in the order they are
+    /// run; with `simulation_id` it names the simulation within the batch
+    pub configuration_id: usize,
     pub simulation_id: usize,
+    /// Seed the simulation's environment was generated from; `--seed` with it replays
+    /// the run
+    pub seed: u64,
     pub algorithm: String,
-    pub grid_size: usize,
+    pub grid_width: usize,
+    pub grid_height: usize,
     pub num_walls: usize,
     pub num_obstacles: usize,
     pub success: bool,
     pub total_moves: usize,
     pub optimal_path_length: usize,
     pub route_efficiency: f64,
+    /// Route efficiency against the best run the obstacle timeline allows
+    pub dynamic_route_efficiency: f64,
     pub execution_time_ms: u64,
     pub a_star_calls: usize,
     pub d_star_calls: usize,
+    /// Calls made to the planner, the initial plan included; A* and D* Lite runs
+    /// count them in `a_star_calls` or `d_star_calls` too
+    pub planner_calls: usize,
     pub average_find_path_time_ns: u64,
+    /// Spread of the find_path times (see `TimingPercentiles`)
+    pub min_find_path_time_ns: u64,
+    pub median_find_path_time_ns: u64,
+    pub p95_find_path_time_ns: u64,
+    pub p99_find_path_time_ns: u64,
+    pub max_find_path_time_ns: u64,
     pub total_pathfinding_calls: usize,
+    pub raw_path_length: usize,
+    pub smoothed_path_length: usize,
+    pub wall_pattern: String,
+    pub obstacle_interval: usize,
+    pub obstacle_persistence: usize,
+    pub stale_belief_steps: usize,
+    pub out_of_energy: bool,
+    /// Agents at their goals at the end of the run, this run's own agent included
+    pub agents_reached: usize,
+    pub agent_collisions: usize,
+    pub coordination: String,
+    /// Steps until the last agent reached its goal (`None` if any never did)
+    pub makespan: Option<usize>,
+    /// Travel cost summed over every agent
+    pub sum_of_costs: f64,
+    pub cbs_expansions: usize,
+    pub caught: bool,
+    /// The run was stopped by `--simulation-timeout-seconds`
+    pub timed_out: bool,
+    /// Replans that used up all of `--find-path-budget-ms`
+    pub budget_overruns: usize,
+    /// Work of the planner's searches (all 0 for planners that do not count it)
+    pub nodes_expanded: usize,
+    pub nodes_generated: usize,
+    pub max_open_list: usize,
+    pub heap_operations: usize,
+    pub priority_queue: String,
+    pub memory: String,
+    pub agents: usize,
+    /// Values of the options of the `--sweep` file, by name; written after the other
+    /// fields for the options that are not one of them
+    #[serde(skip)]
+    pub swept: Vec<toml::Value>,
+}
+
+/// How the results of a batch are written (`--output-format`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutputFormat {
+    /// A header, then a row per result
+    Csv,
+    /// One array of result objects
+    Json,
+    /// A result object per line
+    Jsonl,
+    /// Typed columns in a Parquet file
+    #[cfg(feature = "parquet")]
+    Parquet,
+}
+
+impl OutputFormat {
+    /// Parses an output format name as given on the command line.
+    pub fn from_name(name: &str) -> Result<Self, ConfigError> {
+        match name {
+            "csv" => Ok(OutputFormat::Csv),
+            "json" => Ok(OutputFormat::Json),
+            "jsonl" => Ok(OutputFormat::Jsonl),
+            #[cfg(feature = "parquet")]
+            "parquet" => Ok(OutputFormat::Parquet),
+            #[cfg(not(feature = "parquet"))]
+            "parquet" => Err(ConfigError::Invalid(
+                "Parquet output needs a build with the 'parquet' feature \
+                 (cargo build --release --features parquet)"
+                    .to_string(),
+            )),
+            _ => Err(ConfigError::Invalid(format!(
+                "Unknown output format: '{}' (expected 'csv', 'json', 'jsonl' or 'parquet')",
+                name
+            ))),
+        }
+    }
+
+    /// Name of the output format as given on the command line.
+    pub fn name(self) -> &'static str {
+        match self {
+            OutputFormat::Csv => "csv",
+            OutputFormat::Json => "json",
+            OutputFormat::Jsonl => "jsonl",
+            #[cfg(feature = "parquet")]
+            OutputFormat::Parquet => "parquet",
+        }
+    }
+}
+
+/// Columns of the CSV, before those of options swept by a `--sweep` file
+const CSV_HEADER: &str = "configuration_id,simulation_id,seed,algorithm,grid_width,grid_height,num_walls,num_obstacles,success,total_moves,optimal_path_length,route_efficiency,dynamic_route_efficiency,execution_time_ms,a_star_calls,d_star_calls,planner_calls,average_find_path_time_ns,min_find_path_time_ns,median_find_path_time_ns,p95_find_path_time_ns,p99_find_path_time_ns,max_find_path_time_ns,total_pathfinding_calls,priority_queue,raw_path_length,smoothed_path_length,wall_pattern,obstacle_interval,obstacle_persistence,memory,stale_belief_steps,out_of_energy,agents,agents_reached,agent_collisions,coordination,makespan,sum_of_costs,cbs_expansions,caught,timed_out,nodes_expanded,nodes_generated,max_open_list,heap_operations,budget_overruns";
+
+/// Sets of columns `--columns` takes by name, besides 'all' and single columns
+const COLUMN_PRESETS: [(&str, &[&str]); 4] = [
+    (
+        "core",
+        &[
+            "configuration_id",
+            "simulation_id",
+            "seed",
+            "algorithm",
+            "grid_width",
+            "grid_height",
+            "num_walls",
+            "num_obstacles",
+            "success",
+            "total_moves",
+            "optimal_path_length",
+            "route_efficiency",
+            "dynamic_route_efficiency",
+        ],
+    ),
+    (
+        "timing",
+        &[
+            "execution_time_ms",
+            "a_star_calls",
+            "d_star_calls",
+            "planner_calls",
+            "average_find_path_time_ns",
+            "min_find_path_time_ns",
+            "median_find_path_time_ns",
+            "p95_find_path_time_ns",
+            "p99_find_path_time_ns",
+            "max_find_path_time_ns",
+            "total_pathfinding_calls",
+            "budget_overruns",
+        ],
+    ),
+    (
+        "multi_agent",
+        &[
+            "agents",
+            "agents_reached",
+            "agent_collisions",
+            "coordination",
+            "makespan",
+            "sum_of_costs",
+            "cbs_expansions",
+        ],
+    ),
+    (
+        "search",
+        &[
+            "nodes_expanded",
+            "nodes_generated",
+            "max_open_list",
+            "heap_operations",
+        ],
+    ),
+];
+
+/// Swept options left out of the description of a configuration in the summary: the
+/// algorithm and coordination mode, which are summarized apart, and the options the
+/// description already gives
+const UNLABELLED: [&str; 9] = [
+    "algorithm",
+    "coordination",
+    "grid_width",
+    "grid_height",
+    "num_walls",
+    "wall_pattern",
+    "num_obstacles",
+    "obstacle_interval",
+    "obstacle_persistence",
+];
+
+/// The swept parameters of one batch configuration
+#[derive(Debug, Clone, Copy)]
+struct Sweep {
+    grid_size: usize,
+    wall_pattern: WallPattern,
+    num_walls: usize,
+    num_obstacles: usize,
+    obstacle_interval: usize,
+    obstacle_persistence: usize,
+    /// Combination of the values of the `--sweep` file
+    parameters: usize,
+}
+
+/// One simulation of a batch: its swept configuration and the seed of its environment
+#[derive(Debug, Clone, Copy)]
+struct Job {
+    /// Index of the configuration among the swept ones
+    configuration: usize,
+    sweep: Sweep,
+    simulation_id: usize,
+    seed: u64,
+}
+
+/// Simulations found in the output file of an interrupted batch (`--resume`)
+#[derive(Debug, Default)]
+struct Resumed {
+    /// Seed of every simulation in the file, by configuration and simulation ID
+    seeds: HashMap<(usize, usize), u64>,
+    /// The last of them, cut off the file to run again
+    rerun: Option<(usize, usize)>,
+}
+
+impl Resumed {
+    /// Whether the job's results are already in the file
+    fn finished(&self, job: &Job) -> bool {
+        let key = (job.configuration, job.simulation_id);
+        self.seeds.contains_key(&key) && self.rerun != Some(key)
+    }
+
+    /// Number of simulations whose results are already in the file
+    fn finished_count(&self) -> usize {
+        self.seeds.len() - usize::from(self.rerun.is_some())
+    }
 }
 
 pub struct BatchSimulation {
     config: Config,
+    options: BatchOptions,
+    registry: Arc<AlgorithmRegistry>,
     results: Vec<BatchResult>,
     start_time: Instant,
-    batch_size: usize,            // Add this
-    total_results_written: usize, // Add this
+    /// Results collected before they are written out (`--flush-every`)
+    batch_size: usize,
+    /// Rows in the output so far, those kept from a resumed output file included
+    total_results_written: usize,
+    /// Source of every simulation's seed, itself seeded from `--seed` when given
+    seeds: StdRng,
+    /// Options swept by the `--sweep` file
+    spec: SweepSpec,
+    /// Swept options that have no CSV column of their own, which get one at the end
+    spec_columns: Vec<usize>,
+    /// Indices of the `CSV_HEADER` columns written (`--columns`)
+    columns: Vec<usize>,
+    output_format: OutputFormat,
+    /// Statistics of every result, which are not kept once written
+    summary: BatchSummary,
+    /// Writer of the Parquet file, between its initialization and the end of the batch
+    #[cfg(feature = "parquet")]
+    parquet: Option<ParquetWriter>,
 }
 
 impl BatchSimulation {
-    pub fn new(config: Config) -> Self {
+    pub fn new(config: Config, options: BatchOptions) -> Self {
+        Self::with_registry(
+            config,
+            options,
+            Arc::new(AlgorithmRegistry::with_builtins()),
+        )
+    }
+
+    /// Batch runner that looks algorithms up in the given registry
+    pub fn with_registry(
+        config: Config,
+        options: BatchOptions,
+        registry: Arc<AlgorithmRegistry>,
+    ) -> Self {
+        let seeds = match config.seed {
+            Some(seed) => StdRng::seed_from_u64(seed),
+            None => StdRng::from_entropy(),
+        };
         BatchSimulation {
+            batch_size: options.flush_every,
             config,
+            options,
+            registry,
             results: Vec::new(),
             start_time: Instant::now(),
-            batch_size: 10,
             total_results_written: 0,
+            seeds,
+            spec: SweepSpec::default(),
+            spec_columns: Vec::new(),
+            columns: (0..CSV_HEADER.split(',').count()).collect(),
+            output_format: OutputFormat::Csv,
+            summary: BatchSummary::default(),
+            #[cfg(feature = "parquet")]
+            parquet: None,
         }
     }
 
-    pub fn run(&mut self) -> Result<(), String> {
-        if self.initialize_csv_file().is_ok() {
-            println!("Initialized CSV");
+    pub fn run(&mut self) -> Result<(), SimulationError> {
+        self.output_format = OutputFormat::from_name(&self.options.output_format)?;
+        self.columns = select_columns(&self.options.columns)?;
+        if self.options.streams_results() {
+            if !matches!(
+                self.output_format,
+                OutputFormat::Csv | OutputFormat::Json | OutputFormat::Jsonl
+            ) {
+                return Err(format!(
+                    "--output-file - streams csv, json or jsonl; a {} file cannot be written \
+                     to stdout",
+                    self.output_format.name().to_uppercase()
+                )
+                .into());
+            }
+            if self.options.resume {
+                return Err("--resume needs an output file to pick up, not stdout"
+                    .to_string()
+                    .into());
+            }
         }
-        if !self.config.quiet {
-            println!("=== BATCH SIMULATION STARTED ===");
-            println!("Grid size: {}", self.config.grid_size);
-            println!(
-                "Walls range: {} to {}",
-                self.config.min_walls, self.config.max_walls
+        if self.batch_size == 0 {
+            return Err("--flush-every must be at least 1".to_string().into());
+        }
+        // The ids and seed lead the columns they are in
+        if self.options.resume && !self.columns.starts_with(&[0, 1, 2]) {
+            return Err(
+                "--resume needs the configuration_id, simulation_id and seed columns, which \
+                 name the simulations already in the file"
+                    .to_string()
+                    .into(),
             );
-            println!(
-                "Obstacles range: {} to {}",
-                self.config.min_obstacles, self.config.max_obstacles
+        }
+        if self.options.resume
+            && !matches!(self.output_format, OutputFormat::Csv | OutputFormat::Jsonl)
+        {
+            return Err(format!(
+                "--resume needs --output-format csv or jsonl: a {} file cannot be added to \
+                 once it is cut off",
+                self.output_format.name().to_uppercase()
+            )
+            .into());
+        }
+        MapLayout::fit_config(&mut self.config)?;
+        if let Some(path) = &self.options.sweep {
+            self.spec = SweepSpec::load(path)?;
+        }
+        self.fit_sweep_spec();
+        if self.config.map_file.is_some()
+            || self.config.map_image.is_some()
+            || self.config.map_style != "random"
+        {
+            // The walls come from the map or maze, so only the obstacle counts are swept
+            self.options.min_walls = 0;
+            self.options.max_walls = 0;
+            self.config.wall_pattern = "random".to_string();
+        }
+        if self.config.map_file.is_some() || self.config.map_image.is_some() {
+            // The map sets the size of the grid
+            self.options.min_grid_size = None;
+            self.options.max_grid_size = None;
+        } else if self.options.sweeps_grid_size(&self.config)
+            && (self.config.grid_width.is_some() || self.config.grid_height.is_some())
+        {
+            return Err(
+                "--min-grid-size and --max-grid-size sweep square grids, so they cannot be \
+                 combined with --grid-width or --grid-height"
+                    .to_string()
+                    .into(),
             );
-            println!(
+        }
+        let sweeps_grid_size = self.options.sweeps_grid_size(&self.config);
+        if self.config.obstacle_script.is_some() {
+            // The obstacles come from the script, so only the wall counts are swept
+            self.options.min_obstacles = 0;
+            self.options.max_obstacles = 0;
+            self.options.min_obstacle_interval = None;
+            self.options.max_obstacle_interval = None;
+            self.options.min_obstacle_persistence = None;
+            self.options.max_obstacle_persistence = None;
+        }
+        self.options.validate(&self.config)?;
+        let configurations = self.configurations()?;
+        // The coordination modes of every combination of the sweep file, which checks
+        // its values too
+        let coordinations = (0..self.spec.combinations())
+            .map(|combination| {
+                Coordination::from_names(&self.spec.apply(&self.config, combination)?)
+            })
+            .collect::<Result<Vec<_>, String>>()?;
+        let resumed = match self.options.resume {
+            true => self.resume_output_file(&configurations, &coordinations)?,
+            false => None,
+        };
+        if resumed.is_none() && self.initialize_output_file().is_ok() {
+            say!("Initialized {}", self.output_format.name().to_uppercase());
+        }
+        let resuming = resumed.is_some();
+        let resumed = resumed.unwrap_or_default();
+        if !self.options.quiet {
+            say!("=== BATCH SIMULATION STARTED ===");
+            if sweeps_grid_size {
+                let sizes: Vec<usize> = self.options.grid_size_range(&self.config).collect();
+                say!(
+                    "Grid sizes: {} to {} in steps of {}",
+                    sizes[0],
+                    sizes[sizes.len() - 1],
+                    self.options.grid_size_step
+                );
+            } else {
+                say!(
+                    "Grid size: {}x{}",
+                    self.config.width(),
+                    self.config.height()
+                );
+            }
+            if let Some(map_file) = self
+                .config
+                .map_file
+                .as_ref()
+                .or(self.config.map_image.as_ref())
+            {
+                say!("Map: {}", map_file);
+            } else {
+                say!(
+                    "Walls range: {} to {}",
+                    self.options.min_walls,
+                    self.options.max_walls
+                );
+            }
+            if let Some(script) = &self.config.obstacle_script {
+                say!("Obstacle script: {}", script);
+            } else {
+                say!(
+                    "Obstacles range: {} to {}",
+                    self.options.min_obstacles,
+                    self.options.max_obstacles
+                );
+                let (intervals, persistences) = (
+                    self.options.obstacle_interval_range(&self.config),
+                    self.options.obstacle_persistence_range(&self.config),
+                );
+                say!(
+                    "Obstacle interval range: {} to {} steps",
+                    intervals.start(),
+                    intervals.end()
+                );
+                say!(
+                    "Obstacle persistence range: {} to {} steps",
+                    persistences.start(),
+                    persistences.end()
+                );
+            }
+            say!(
                 "Simulations per configuration: {}",
-                self.config.num_simulations
+                self.options.num_simulations
+            );
+            say!("Timeout: {} seconds", self.options.timeout_seconds);
+            if let Some(seconds) = self.config.simulation_timeout_seconds {
+                say!("Per-simulation timeout: {} seconds", seconds);
+            }
+            match self.options.jobs {
+                0 => say!("Workers: one per CPU core"),
+                jobs => say!("Workers: {}", jobs),
+            }
+            say!("Algorithm: {}", self.config.algorithm);
+            say!("Priority queue: {}", self.config.priority_queue);
+            say!(
+                "Path smoothing: {}",
+                if self.config.smooth_path { "on" } else { "off" }
             );
-            println!("Timeout: {} seconds", self.config.timeout_seconds);
-            println!("Algorithm: {}", self.config.algorithm);
-            println!("Output file: {}", self.config.output_file);
-            println!();
+            say!("Output: {}", self.output_name());
+            say!();
         }
 
-        let total_configurations = self.count_total_configurations();
-        let total_simulations = total_configurations * self.config.num_simulations;
+        let total_configurations = configurations.len();
+        let mut total_simulations = total_configurations * self.options.num_simulations;
 
-        if !self.config.quiet {
-            println!("Total configurations to test: {}", total_configurations);
-            println!("Total simulations to run: {}", total_simulations);
-            println!();
+        if !self.options.quiet {
+            say!("Total configurations to test: {}", total_configurations);
+            say!("Total simulations to run: {}", total_simulations);
+        }
+        let mut jobs = self.jobs(&configurations, &resumed);
+        if resuming {
+            jobs.retain(|job| !resumed.finished(job));
+            total_simulations = jobs.len();
+            say!(
+                "Resuming {}: {} simulations already done, {} to run",
+                self.options.output_file(),
+                resumed.finished_count(),
+                total_simulations
+            );
+        }
+        if !self.options.quiet {
+            say!();
         }
 
-        let mut configuration_count = 0;
-        let mut completed_simulations = 0;
-        let timeout_duration = Duration::from_secs(self.config.timeout_seconds);
-
-        // Progress reporting variables
-        let mut last_progress_report = Instant::now();
-        let progress_interval = Duration::from_secs(10); // Report every 10 seconds
-
-        // Iterate through all combinations of walls and obstacles
-        for num_walls in self.config.min_walls..=self.config.max_walls {
-            for num_obstacles in self.config.min_obstacles..=self.config.max_obstacles {
-                configuration_count += 1;
-
-                // Check timeout
-                if self.start_time.elapsed() > timeout_duration {
-                    if !self.config.quiet {
-                        println!(
-                            "⏰ Timeout reached after {} configurations",
-                            configuration_count - 1
-                        );
-                    }
-                    break;
-                }
+        let pool = rayon::ThreadPoolBuilder::new()
+            .num_threads(self.options.jobs)
+            .build()
+            .map_err(|e| format!("Failed to start the batch workers: {}", e))?;
+        let timeout_duration = Duration::from_secs(self.options.timeout_seconds);
+        let start_time = self.start_time;
+        let config = self.config.clone();
+        let registry = Arc::clone(&self.registry);
+        let spec = self.spec.clone();
+        let stopped = AtomicBool::new(false);
+        // Simulations still running at the timeout are stopped mid-search
+        let abort = AbortHandle::new();
+        abort.abort_at(start_time + timeout_duration);
+        let (sender, receiver) = mpsc::channel();
 
-                if !self.config.quiet {
-                    println!(
-                        "Configuration {}/{}: {} walls, {} obstacles",
-                        configuration_count, total_configurations, num_walls, num_obstacles
-                    );
-                }
+        let mut completed_simulations = 0;
+        let mut skipped_simulations = 0;
 
-                // Run simulations for this configuration
-                let sims_completed = self.run_configuration(num_walls, num_obstacles)?;
-                completed_simulations += sims_completed;
+        let mut progress = BatchProgress::new(total_simulations);
 
-                if self.results.len() >= self.batch_size {
-                    self.flush_results_to_csv()?;
-                }
+        let result = thread::scope(|scope| {
+            // The workers run the simulations in any order; their results are put back
+            // in order here, so the CSV reads the same however many workers there are
+            scope.spawn(|| {
+                pool.install(|| {
+                    jobs.par_iter()
+                        .enumerate()
+                        .for_each_with(sender, |sender, (index, job)| {
+                            // Simulations not started by the timeout are skipped
+                            let results = (start_time.elapsed() <= timeout_duration
+                                && !stopped.load(Ordering::Relaxed))
+                            .then(|| {
+                                run_simulation(
+                                    &config,
+                                    &registry,
+                                    &spec,
+                                    &coordinations,
+                                    job,
+                                    &abort,
+                                )
+                            });
+                            let _ = sender.send((index, results));
+                        })
+                })
+            });
 
-                // Progress reporting - show progress every 10 seconds regardless of quiet mode
-                if last_progress_report.elapsed() > progress_interval {
-                    let progress_percentage =
-                        (completed_simulations as f64 / total_simulations as f64) * 100.0;
-                    let elapsed = self.start_time.elapsed();
-                    let estimated_total = if completed_simulations > 0 {
-                        elapsed.mul_f64(total_simulations as f64 / completed_simulations as f64)
-                    } else {
-                        Duration::from_secs(0)
+            let mut finished = BTreeMap::new();
+            let mut next_job = 0;
+            for (index, results) in receiver {
+                finished.insert(index, results);
+                while let Some(results) = finished.remove(&next_job) {
+                    let job = jobs[next_job];
+                    next_job += 1;
+                    let Some(results) = results else {
+                        skipped_simulations += 1;
+                        continue;
                     };
-                    let remaining = estimated_total.saturating_sub(elapsed);
+                    if job.simulation_id == 0 && !self.options.quiet {
+                        let grid = match sweeps_grid_size {
+                            true => format!("{0}x{0} grid, ", job.sweep.grid_size),
+                            false => String::new(),
+                        };
+                        let swept: String = self
+                            .spec
+                            .names()
+                            .zip(self.spec.labels(job.sweep.parameters))
+                            .map(|(name, value)| format!(", {} = {}", name, value))
+                            .collect();
+                        progress.println(format!(
+                            "Configuration {}/{}: {}{} {} walls, {} obstacles every {} steps for {}{}",
+                            job.configuration + 1,
+                            total_configurations,
+                            grid,
+                            job.sweep.num_walls,
+                            job.sweep.wall_pattern.name(),
+                            job.sweep.num_obstacles,
+                            job.sweep.obstacle_interval,
+                            job.sweep.obstacle_persistence,
+                            swept
+                        ));
+                    }
+                    if let Some(result) = results.first() {
+                        let configuration = self.configuration_label(result);
+                        self.summary.record(
+                            configuration,
+                            &results,
+                            self.config.coordination.contains(','),
+                        );
+                    }
+                    self.results.extend(results);
+                    completed_simulations += 1;
+                    progress.complete(format!(
+                        "configuration {}/{}",
+                        job.configuration + 1,
+                        total_configurations
+                    ));
 
-                    println!("Progress: {:.1}% ({}/{}) - Elapsed: {:.1}s - ETA: {:.1}s - Batches written: {}",
-                             progress_percentage, completed_simulations, total_simulations,
-                             elapsed.as_secs_f64(), remaining.as_secs_f64(),
-                             self.total_results_written / self.batch_size);
-                    last_progress_report = Instant::now();
+                    if self.results.len() >= self.batch_size {
+                        if let Err(e) = progress.suspend(|| self.flush_results()) {
+                            stopped.store(true, Ordering::Relaxed);
+                            abort.abort();
+                            return Err(e);
+                        }
+                    }
                 }
-            }
 
-            // Check timeout again at outer loop level
-            if self.start_time.elapsed() > timeout_duration {
-                break;
+                progress.tick();
             }
+            Ok(())
+        });
+        progress.finish();
+        result?;
+
+        if skipped_simulations > 0 && !self.options.quiet {
+            say!(
+                "⏰ Timeout reached after {} of {} simulations",
+                completed_simulations,
+                total_simulations
+            );
         }
 
         if !self.results.is_empty() {
-            self.flush_results_to_csv()?;
+            self.flush_results()?;
+        }
+        self.finish_output_file()?;
+        if let Some(path) = &self.options.report {
+            write_report(path, &self.summary, &self.config, &self.options)?;
+            say!("Report written to {}", path);
         }
 
-        if !self.config.quiet {
-            println!("\n=== BATCH SIMULATION COMPLETED ===");
-            println!("Total results collected: {}", self.results.len());
-            println!("Results saved to: {}", self.config.output_file);
-            println!("Total time: {:.2?}", self.start_time.elapsed());
+        if !self.options.quiet {
+            say!("\n=== BATCH SIMULATION COMPLETED ===");
+            say!("Total results collected: {}", self.total_results_written);
+            say!("Results saved to: {}", self.output_name());
+            say!("Total time: {:.2?}", self.start_time.elapsed());
         } else {
-            println!(
+            say!(
                 "Batch simulation completed: {} results in {:.1}s -> {}",
-                self.results.len(),
+                self.total_results_written,
                 self.start_time.elapsed().as_secs_f64(),
-                self.config.output_file
+                self.output_name()
             );
         }
 
         Ok(())
     }
 
-    fn count_total_configurations(&self) -> usize {
-        let wall_count = (self.config.max_walls - self.config.min_walls) + 1;
-        let obstacle_count = (self.config.max_obstacles - self.config.min_obstacles) + 1;
-        wall_count * obstacle_count
+    /// Leave the options the sweep file sweeps out of the batch's own ranges, and give
+    /// the ones without a CSV column a column
+    fn fit_sweep_spec(&mut self) {
+        let spec = &self.spec;
+        if spec.sweeps("grid_size") || spec.sweeps("grid_width") || spec.sweeps("grid_height") {
+            self.options.min_grid_size = None;
+            self.options.max_grid_size = None;
+        }
+        if spec.sweeps("wall_pattern") {
+            self.config.wall_pattern = "random".to_string();
+        }
+        if spec.sweeps("num_walls") {
+            self.options.min_walls = self.config.num_walls;
+            self.options.max_walls = self.config.num_walls;
+        }
+        if spec.sweeps("num_obstacles") {
+            self.options.min_obstacles = self.config.num_obstacles;
+            self.options.max_obstacles = self.config.num_obstacles;
+        }
+        if spec.sweeps("obstacle_interval") {
+            self.options.min_obstacle_interval = None;
+            self.options.max_obstacle_interval = None;
+        }
+        if spec.sweeps("obstacle_persistence") {
+            self.options.min_obstacle_persistence = None;
+            self.options.max_obstacle_persistence = None;
+        }
+        let columns: Vec<&str> = CSV_HEADER.split(',').collect();
+        self.spec_columns = spec
+            .names()
+            .enumerate()
+            .filter(|(_, name)| !columns.contains(name))
+            .map(|(index, _)| index)
+            .collect();
     }
 
-    fn run_configuration(
-        &mut self,
-        num_walls: usize,
-        num_obstacles: usize,
-    ) -> Result<usize, String> {
-        // Create a configuration for this specific run
-        let mut run_config = self.config.clone();
-        run_config.num_walls = num_walls;
-        run_config.num_obstacles = num_obstacles;
-        run_config.no_visualization = true; // Always disable visualization in batch mode
-        run_config.quiet = true; // Force quiet mode for individual simulations
-
-        let mut completed_count = 0;
-
-        for sim_id in 0..self.config.num_simulations {
-            // Check timeout before each simulation
-            let timeout_duration = Duration::from_secs(self.config.timeout_seconds);
-            if self.start_time.elapsed() > timeout_duration {
-                return Ok(completed_count);
-            }
-
-            let simulation_start = Instant::now();
-
-            if self.config.algorithm == "all" {
-                // Run all algorithms for this configuration
-                match Simulation::run_all_algorithms(run_config.clone()) {
-                    Ok(results) => {
-                        for algorithm_result in results {
-                            let batch_result = self.convert_algorithm_result_to_batch_result(
-                                algorithm_result,
-                                sim_id,
-                                num_walls,
-                                num_obstacles,
-                                simulation_start.elapsed(),
-                            );
-                            self.results.push(batch_result);
-                        }
-                    }
-                    Err(_e) => {
-                        let algorithms = ["a_star", "d_star_lite"];
-                        for algorithm in &algorithms {
-                            let failed_result = BatchResult {
-                                simulation_id: sim_id,
-                                algorithm: algorithm.to_string(),
-                                grid_size: self.config.grid_size,
-                                num_walls,
-                                num_obstacles,
-                                success: false,
-                                total_moves: 0,
-                                optimal_path_length: 0,
-                                route_efficiency: 0.0,
-                                execution_time_ms: simulation_start.elapsed().as_millis() as u64,
-                                a_star_calls: 0,
-                                d_star_calls: 0,
-                                average_find_path_time_ns: 0,
-                                total_pathfinding_calls: 0,
-                            };
-                            self.results.push(failed_result);
+    /// Every combination of the swept parameters, in the order they are run
+    fn configurations(&self) -> Result<Vec<Sweep>, SimulationError> {
+        let mut configurations = Vec::new();
+        for grid_size in self.options.grid_size_range(&self.config) {
+            for wall_pattern in WallPattern::from_names(&self.config.wall_pattern)? {
+                for num_walls in self.options.min_walls..=self.options.max_walls {
+                    for num_obstacles in self.options.min_obstacles..=self.options.max_obstacles {
+                        for obstacle_interval in self.options.obstacle_interval_range(&self.config)
+                        {
+                            for obstacle_persistence in
+                                self.options.obstacle_persistence_range(&self.config)
+                            {
+                                for parameters in 0..self.spec.combinations() {
+                                    configurations.push(Sweep {
+                                        grid_size,
+                                        wall_pattern,
+                                        num_walls,
+                                        num_obstacles,
+                                        obstacle_interval,
+                                        obstacle_persistence,
+                                        parameters,
+                                    });
+                                }
+                            }
                         }
                     }
                 }
-            } else {
-                // Run single algorithm with error handling
-                match Simulation::new(run_config.clone()) {
-                    Ok(mut simulation) => {
-                        let (stats, algorithm_stats, timing_data) = simulation.run();
-
-                        let batch_result = BatchResult {
-                            simulation_id: sim_id,
-                            algorithm: self.config.algorithm.clone(),
-                            grid_size: self.config.grid_size,
-                            num_walls,
-                            num_obstacles,
-                            success: simulation.agent.position == simulation.grid.goal,
-                            total_moves: stats.total_moves,
-                            optimal_path_length: stats.optimal_path_length,
-                            route_efficiency: stats.route_efficiency,
-                            execution_time_ms: simulation_start.elapsed().as_millis() as u64,
-                            a_star_calls: match algorithm_stats {
-                                AlgorithmStats::AStar(calls) => calls,
-                                AlgorithmStats::Hybrid { a_star_calls, .. } => a_star_calls,
-                                _ => 0,
-                            },
-                            d_star_calls: match algorithm_stats {
-                                AlgorithmStats::DStarLite(calls) => calls,
-                                AlgorithmStats::Hybrid { d_star_calls, .. } => d_star_calls,
-                                _ => 0,
-                            },
-                            average_find_path_time_ns: timing_data
-                                .average_find_path_time()
-                                .as_nanos()
-                                as u64,
-                            total_pathfinding_calls: timing_data.total_calls(),
-                        };
-
-                        self.results.push(batch_result);
-                    }
-                    Err(_e) => {
-                        let failed_result = BatchResult {
-                            simulation_id: sim_id,
-                            algorithm: self.config.algorithm.clone(),
-                            grid_size: self.config.grid_size,
-                            num_walls,
-                            num_obstacles,
-                            success: false,
-                            total_moves: 0,
-                            optimal_path_length: 0,
-                            route_efficiency: 0.0,
-                            execution_time_ms: simulation_start.elapsed().as_millis() as u64,
-                            a_star_calls: 0,
-                            d_star_calls: 0,
-                            average_find_path_time_ns: 0,
-                            total_pathfinding_calls: 0,
-                        };
+            }
+        }
+        Ok(configurations)
+    }
 
-                        self.results.push(failed_result);
+    /// Every simulation of the batch, in the order they are written, with seeds drawn
+    /// in that order so a seeded batch runs the same simulations with any number of
+    /// workers. The simulations of every combination of the sweep file's values share
+    /// the seeds of the configuration's, as the coordination modes and the algorithms
+    /// of `--algorithm all` do, so they all run on the same environments and compare
+    /// in pairs. The simulations of a `resumed` batch keep the seeds they ran with,
+    /// which their pairs share.
+    fn jobs(&mut self, configurations: &[Sweep], resumed: &Resumed) -> Vec<Job> {
+        let mut jobs = Vec::new();
+        let mut seeds = Vec::new();
+        for (configuration, &sweep) in configurations.iter().enumerate() {
+            // The combinations of a configuration follow each other, starting from 0
+            if sweep.parameters == 0 {
+                seeds = (0..self.options.num_simulations)
+                    .map(|_| self.seeds.gen::<u64>())
+                    .collect();
+                let combinations = configuration..configuration + self.spec.combinations();
+                for (simulation_id, seed) in seeds.iter_mut().enumerate() {
+                    if let Some(&resumed_seed) = combinations
+                        .clone()
+                        .find_map(|c| resumed.seeds.get(&(c, simulation_id)))
+                    {
+                        *seed = resumed_seed;
                     }
                 }
             }
-
-            completed_count += 1;
-        }
-        if self.results.len() >= self.batch_size {
-            self.flush_results_to_csv()?;
-        }
-        Ok(completed_count)
-    }
-
-    fn convert_algorithm_result_to_batch_result(
-        &self,
-        result: AlgorithmResult,
-        sim_id: usize,
-        num_walls: usize,
-        num_obstacles: usize,
-        execution_time: Duration,
-    ) -> BatchResult {
-        BatchResult {
-            simulation_id: sim_id,
-            algorithm: result.name,
-            grid_size: self.config.grid_size,
-            num_walls,
-            num_obstacles,
-            success: result.success,
-            total_moves: result.statistics.total_moves,
-            optimal_path_length: result.statistics.optimal_path_length,
-            route_efficiency: result.statistics.route_efficiency,
-            execution_time_ms: execution_time.as_millis() as u64,
-            a_star_calls: match result.algorithm_stats {
-                AlgorithmStats::AStar(calls) => calls,
-                AlgorithmStats::Hybrid { a_star_calls, .. } => a_star_calls,
-                _ => 0,
-            },
-            d_star_calls: match result.algorithm_stats {
-                AlgorithmStats::DStarLite(calls) => calls,
-                AlgorithmStats::Hybrid { d_star_calls, .. } => d_star_calls,
-                _ => 0,
-            },
-            average_find_path_time_ns: result.timing_data.average_find_path_time().as_nanos()
-                as u64,
-            total_pathfinding_calls: result.timing_data.total_calls(),
+            for (simulation_id, &seed) in seeds.iter().enumerate() {
+                jobs.push(Job {
+                    configuration,
+                    sweep,
+                    simulation_id,
+                    seed,
+                });
+            }
         }
+        jobs
     }
 
     pub fn with_batch_size(mut self, batch_size: usize) -> Self {
@@ -337,44 +785,36 @@ impl BatchSimulation {
         self
     }
 
-    fn flush_results_to_csv(&mut self) -> Result<(), String> {
+    fn flush_results(&mut self) -> Result<(), SimulationError> {
         if self.results.is_empty() {
             return Ok(());
         }
 
-        let mut file = OpenOptions::new()
-            .create(true)
-            .append(true)
-            .open(&self.config.output_file)
-            .map_err(|e| format!("Failed to open output file for appending: {}", e))?;
-
-        for result in &self.results {
-            writeln!(
-                file,
-                "{},{},{},{},{},{},{},{},{:.6},{},{},{},{},{}",
-                result.simulation_id,
-                result.algorithm,
-                result.grid_size,
-                result.num_walls,
-                result.num_obstacles,
-                result.success,
-                result.total_moves,
-                result.optimal_path_length,
-                result.route_efficiency,
-                result.execution_time_ms,
-                result.a_star_calls,
-                result.d_star_calls,
-                result.average_find_path_time_ns,
-                result.total_pathfinding_calls
-            )
-            .map_err(|e| format!("Failed to write data row: {}", e))?;
+        match self.output_format {
+            #[cfg(feature = "parquet")]
+            OutputFormat::Parquet => {
+                if let Some(parquet) = self.parquet.as_mut() {
+                    parquet.write(&self.results)?;
+                }
+            }
+            format => {
+                let mut output = self.open_output(true)?;
+                match format {
+                    OutputFormat::Csv => self.write_csv_results(&mut output)?,
+                    _ => self.write_json_results(&mut output)?,
+                }
+                output
+                    .flush()
+                    .map_err(|e| SimulationError::io("Failed to write results", e))?;
+            }
         }
 
         self.total_results_written += self.results.len();
-        if !self.config.quiet {
-            println!(
-                "Flushed {} results to CSV (total: {})",
+        if !self.options.quiet {
+            say!(
+                "Flushed {} results to {} (total: {})",
                 self.results.len(),
+                self.output_format.name().to_uppercase(),
                 self.total_results_written
             );
         }
@@ -382,69 +822,770 @@ impl BatchSimulation {
         Ok(())
     }
 
-    fn initialize_csv_file(&self) -> Result<(), String> {
-        let mut file = std::fs::File::create(&self.config.output_file)
-            .map_err(|e| format!("Failed to create output file: {}", e))?;
+    /// Write the collected results as CSV rows
+    fn write_csv_results(&self, file: &mut dyn Write) -> Result<(), SimulationError> {
+        for result in &self.results {
+            let values = csv_values(result);
+            let row: Vec<&str> = self.columns.iter().map(|&i| values[i].as_str()).collect();
+            write!(file, "{}", row.join(","))
+                .map_err(|e| SimulationError::io("Failed to write data row", e))?;
+            for &index in &self.spec_columns {
+                write!(file, ",{}", SweepSpec::label(&result.swept[index]))
+                    .map_err(|e| SimulationError::io("Failed to write data row", e))?;
+            }
+            writeln!(file).map_err(|e| SimulationError::io("Failed to write data row", e))?;
+        }
+        Ok(())
+    }
+
+    /// Write the collected results as JSON objects: elements of the array the file
+    /// holds, or lines of their own
+    fn write_json_results(&self, file: &mut dyn Write) -> Result<(), SimulationError> {
+        let spec_names: Vec<&str> = self.spec.names().collect();
+        let columns = self.column_names();
+        for (i, result) in self.results.iter().enumerate() {
+            let mut object = serde_json::to_value(result)
+                .map_err(|e| format!("Failed to write result: {}", e))?;
+            if let Some(fields) = object.as_object_mut() {
+                fields.retain(|name, _| columns.contains(&name.as_str()));
+                for &index in &self.spec_columns {
+                    let value = serde_json::to_value(&result.swept[index])
+                        .map_err(|e| format!("Failed to write result: {}", e))?;
+                    fields.insert(spec_names[index].to_string(), value);
+                }
+            }
+            let written = match self.output_format {
+                OutputFormat::Json => {
+                    let separator = match self.total_results_written + i {
+                        0 => "\n",
+                        _ => ",\n",
+                    };
+                    let object = serde_json::to_string_pretty(&object)
+                        .map_err(|e| format!("Failed to write result: {}", e))?;
+                    write!(file, "{}  {}", separator, object.replace('\n', "\n  "))
+                }
+                _ => writeln!(file, "{}", object),
+            };
+            written.map_err(|e| SimulationError::io("Failed to write result", e))?;
+        }
+        Ok(())
+    }
 
-        writeln!(file, "simulation_id,algorithm,grid_size,num_walls,num_obstacles,success,total_moves,optimal_path_length,route_efficiency,execution_time_ms,a_star_calls,d_star_calls,average_find_path_time_ns,total_pathfinding_calls")
-            .map_err(|e| format!("Failed to write header: {}", e))?;
+    fn initialize_output_file(&mut self) -> Result<(), SimulationError> {
+        match self.output_format {
+            #[cfg(feature = "parquet")]
+            OutputFormat::Parquet => {
+                let file = File::create(self.options.output_file())
+                    .map_err(|e| SimulationError::io("Failed to create output file", e))?;
+                self.parquet = Some(ParquetWriter::new(
+                    file,
+                    &self.column_names(),
+                    &self.spec,
+                    &self.spec_columns,
+                )?)
+            }
+            format => {
+                let mut output = self.open_output(false)?;
+                match format {
+                    OutputFormat::Csv => self.write_csv_header(&mut output)?,
+                    OutputFormat::Json => write!(output, "[")
+                        .map_err(|e| SimulationError::io("Failed to write header", e))?,
+                    _ => {}
+                }
+                output
+                    .flush()
+                    .map_err(|e| SimulationError::io("Failed to write header", e))?;
+            }
+        }
 
-        if !self.config.quiet {
-            println!("Initialized CSV file: {}", self.config.output_file);
+        if !self.options.quiet {
+            say!(
+                "Initialized {} output: {}",
+                self.output_format.name().to_uppercase(),
+                self.output_name()
+            );
         }
         Ok(())
     }
 
-    pub fn print_summary(&self) {
-        if self.results.is_empty() {
-            println!("No results to summarize.");
-            return;
+    /// The output file, created afresh or opened to `append` to, or stdout when the
+    /// results stream to it
+    fn open_output(&self, append: bool) -> Result<Box<dyn Write>, SimulationError> {
+        if self.options.streams_results() {
+            return Ok(Box::new(std::io::stdout().lock()));
         }
+        let file = match append {
+            true => OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(self.options.output_file())
+                .map_err(|e| SimulationError::io("Failed to open output file for appending", e))?,
+            false => File::create(self.options.output_file())
+                .map_err(|e| SimulationError::io("Failed to create output file", e))?,
+        };
+        Ok(Box::new(file))
+    }
 
-        println!("\n=== BATCH SIMULATION SUMMARY ===");
+    /// Where the results go, for messages: the output file or stdout
+    fn output_name(&self) -> String {
+        match self.options.streams_results() {
+            true => "stdout".to_string(),
+            false => self.options.output_file(),
+        }
+    }
 
-        // Group results by algorithm
-        let mut algorithm_groups: HashMap<String, Vec<&BatchResult>> = HashMap::new();
-        for result in &self.results {
-            algorithm_groups
-                .entry(result.algorithm.clone())
-                .or_default()
-                .push(result);
+    /// Pick up the output file of an interrupted batch. The file is cut back to before
+    /// the last simulation in it, which is run again in case it was cut off partway;
+    /// the results of the others go into the summary with those the batch runs.
+    /// `None` when there is no file to resume, so the batch starts afresh.
+    fn resume_output_file(
+        &mut self,
+        configurations: &[Sweep],
+        coordinations: &[Vec<Coordination>],
+    ) -> Result<Option<Resumed>, SimulationError> {
+        let path = self.options.output_file();
+        let text = match std::fs::read_to_string(&path) {
+            Ok(text) => text,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => {
+                return Err(SimulationError::io(
+                    format!("Failed to read output file '{}'", path),
+                    e,
+                ))
+            }
+        };
+        let mut lines = text.split_inclusive('\n');
+        let mut offset = 0;
+        if self.output_format == OutputFormat::Csv {
+            let mut header = Vec::new();
+            self.write_csv_header(&mut header)?;
+            match lines.next() {
+                Some(line) if line.as_bytes() == header.as_slice() => offset = line.len(),
+                Some(_) => {
+                    return Err(format!(
+                        "Cannot resume '{}': its columns are not those of this batch",
+                        path
+                    )
+                    .into())
+                }
+                None => return Ok(None),
+            }
         }
+        // Names of the values of a CSV row
+        let spec_names: Vec<&str> = self.spec.names().collect();
+        let names: Vec<String> = self
+            .column_names()
+            .into_iter()
+            .chain(self.spec_columns.iter().map(|&index| spec_names[index]))
+            .map(str::to_string)
+            .collect();
 
-        for (algorithm, results) in algorithm_groups {
-            println!("\n{} Algorithm Results:", algorithm);
-            let successful = results.iter().filter(|r| r.success).count();
-            let total = results.len();
-            let success_rate = (successful as f64 / total as f64) * 100.0;
+        let mut resumed = Resumed::default();
+        // Where the rows of the last simulation start, and the rows before them
+        let mut last: Option<((usize, usize), usize, usize)> = None;
+        // The rows of the last simulation, by column name
+        let mut simulation: Vec<Map<String, Value>> = Vec::new();
+        let mut rows = 0;
+        // A last line without its newline was cut off while it was written
+        for line in lines.filter(|line| line.ends_with('\n')) {
+            let fields: Map<String, Value> = match self.output_format {
+                OutputFormat::Csv => names
+                    .iter()
+                    .cloned()
+                    .zip(line.trim_end().split(',').map(|field| field.into()))
+                    .collect(),
+                _ => match serde_json::from_str(line) {
+                    Ok(Value::Object(fields)) => fields,
+                    _ => Map::new(),
+                },
+            };
+            let id = |name: &str| match fields.get(name) {
+                Some(Value::String(field)) => field.parse::<u64>().ok(),
+                Some(field) => field.as_u64(),
+                None => None,
+            };
+            let (Some(configuration), Some(simulation_id), Some(seed)) =
+                (id("configuration_id"), id("simulation_id"), id("seed"))
+            else {
+                return Err(format!(
+                    "Cannot resume '{}': '{}' is not a result of a batch",
+                    path,
+                    line.trim_end()
+                )
+                .into());
+            };
+            let key = (configuration as usize, simulation_id as usize);
+            if key.0 >= configurations.len() || key.1 >= self.options.num_simulations {
+                return Err(format!(
+                    "Cannot resume '{}': it holds simulations this batch does not run; \
+                     resume with the options it was started with",
+                    path
+                )
+                .into());
+            }
+            if last.is_none_or(|(last_key, _, _)| last_key != key) {
+                if let Some((last_key, _, _)) = last {
+                    let job = Job {
+                        configuration: last_key.0,
+                        sweep: configurations[last_key.0],
+                        simulation_id: last_key.1,
+                        seed: resumed.seeds[&last_key],
+                    };
+                    let coordination = coordinations[job.sweep.parameters][0];
+                    self.summarize_resumed(&path, &job, coordination, &simulation)?;
+                    simulation.clear();
+                }
+                last = Some((key, offset, rows));
+            }
+            resumed.seeds.insert(key, seed);
+            simulation.push(fields);
+            offset += line.len();
+            rows += 1;
+        }
 
-            println!(
-                "  Success rate: {}/{} ({:.1}%)",
-                successful, total, success_rate
+        if let Some((key, start, rows_before)) = last {
+            resumed.rerun = Some(key);
+            offset = start;
+            rows = rows_before;
+        }
+        OpenOptions::new()
+            .write(true)
+            .open(&path)
+            .and_then(|file| file.set_len(offset as u64))
+            .map_err(|e| {
+                SimulationError::io(format!("Failed to cut off output file '{}'", path), e)
+            })?;
+        self.total_results_written = rows;
+        Ok(Some(resumed))
+    }
+
+    /// Add the results of a simulation found in the output file of an interrupted
+    /// batch to the summary: the values of the `rows` it wrote, by column name, and for
+    /// the columns left out those of a result that failed to run
+    fn summarize_resumed(
+        &mut self,
+        path: &str,
+        job: &Job,
+        coordination: Coordination,
+        rows: &[Map<String, Value>],
+    ) -> Result<(), SimulationError> {
+        let run_config = run_config_of(&self.config, &self.spec, job.sweep);
+        let swept = self.spec.combination(job.sweep.parameters);
+        let mut results = Vec::new();
+        for row in rows {
+            let template = failed_result(
+                &run_config,
+                job,
+                run_config.algorithm.clone(),
+                &swept,
+                coordination,
+                Duration::ZERO,
             );
+            let result = result_of_row(template, row).ok_or_else(|| {
+                format!(
+                    "Cannot resume '{}': a row of simulation {} of configuration {} holds \
+                     values that are not those of a result",
+                    path,
+                    job.simulation_id,
+                    job.configuration + 1
+                )
+            })?;
+            results.push(result);
+        }
+        if let Some(result) = results.first() {
+            let configuration = self.configuration_label(result);
+            self.summary.record(
+                configuration,
+                &results,
+                self.config.coordination.contains(','),
+            );
+        }
+        Ok(())
+    }
 
-            if successful > 0 {
-                let successful_results: Vec<_> = results.iter().filter(|r| r.success).collect();
-                let avg_moves: f64 = successful_results
-                    .iter()
-                    .map(|r| r.total_moves as f64)
-                    .sum::<f64>()
-                    / successful_results.len() as f64;
-                let avg_efficiency: f64 = successful_results
-                    .iter()
-                    .map(|r| r.route_efficiency)
-                    .sum::<f64>()
-                    / successful_results.len() as f64;
-                let avg_time: f64 = successful_results
-                    .iter()
-                    .map(|r| r.execution_time_ms as f64)
-                    .sum::<f64>()
-                    / successful_results.len() as f64;
+    /// Close the array of a JSON file, or write the footer of a Parquet one, once every
+    /// result is written
+    fn finish_output_file(&mut self) -> Result<(), SimulationError> {
+        #[cfg(feature = "parquet")]
+        if let Some(parquet) = self.parquet.take() {
+            return Ok(parquet.close()?);
+        }
+        if self.output_format != OutputFormat::Json {
+            return Ok(());
+        }
+        let mut output = self.open_output(true)?;
+        writeln!(output, "\n]")
+            .and_then(|_| output.flush())
+            .map_err(|e| SimulationError::io("Failed to write result", e))
+    }
+
+    /// Write the CSV header: the `--columns` of every result and those of the swept
+    /// options without one
+    fn write_csv_header(&self, file: &mut impl Write) -> Result<(), SimulationError> {
+        let spec_names: Vec<&str> = self.spec.names().collect();
+        let spec_columns: String = self
+            .spec_columns
+            .iter()
+            .map(|&index| format!(",{}", spec_names[index]))
+            .collect();
+        writeln!(file, "{}{}", self.column_names().join(","), spec_columns)
+            .map_err(|e| SimulationError::io("Failed to write header", e))
+    }
+
+    /// Names of the `--columns` written, in the order of the CSV header
+    fn column_names(&self) -> Vec<&'static str> {
+        let names: Vec<&'static str> = CSV_HEADER.split(',').collect();
+        self.columns.iter().map(|&index| names[index]).collect()
+    }
+
+    /// Description of the configuration a result ran in, the same for every algorithm
+    /// and coordination mode
+    fn configuration_label(&self, result: &BatchResult) -> String {
+        let mut label = format!(
+            "{}x{}, {} {} walls, {} obstacles every {} steps for {}",
+            result.grid_width,
+            result.grid_height,
+            result.num_walls,
+            result.wall_pattern,
+            result.num_obstacles,
+            result.obstacle_interval,
+            result.obstacle_persistence
+        );
+        for (name, value) in self.spec.names().zip(&result.swept) {
+            if !UNLABELLED.contains(&name) {
+                label += &format!(", {} = {}", name, SweepSpec::label(value));
+            }
+        }
+        label
+    }
+
+    /// Statistics of the batch's results, those of a resumed output file included
+    pub fn summary(&self) -> &BatchSummary {
+        &self.summary
+    }
+
+    pub fn print_summary(&self) {
+        if self.summary.is_empty() {
+            say!("No results to summarize.");
+            return;
+        }
+        self.summary.print(
+            &self.config,
+            self.options.summary_by_configuration,
+            self.options.significance_test,
+        );
+    }
+}
+
+/// Indices of the `CSV_HEADER` columns a `--columns` list selects, in the order of the
+/// header
+fn select_columns(list: &str) -> Result<Vec<usize>, String> {
+    let names: Vec<&str> = CSV_HEADER.split(',').collect();
+    let items: Vec<&str> = list
+        .split(',')
+        .map(str::trim)
+        .filter(|item| !item.is_empty())
+        .collect();
+    // A list that only leaves columns out starts from every column
+    let mut selected = vec![items.iter().all(|item| item.starts_with('-')); names.len()];
+    for item in items {
+        let (keep, name) = match item.strip_prefix('-') {
+            Some(name) => (false, name),
+            None => (true, item),
+        };
+        let columns = match COLUMN_PRESETS.iter().find(|(preset, _)| *preset == name) {
+            Some((_, columns)) => columns.to_vec(),
+            None if name == "all" => names.clone(),
+            None if names.contains(&name) => vec![name],
+            None => {
+                let presets: Vec<&str> = COLUMN_PRESETS.iter().map(|(preset, _)| *preset).collect();
+                return Err(format!(
+                    "Unknown column: '{}' (expected a column of the results or one of all, {})",
+                    name,
+                    presets.join(", ")
+                ));
+            }
+        };
+        for column in columns {
+            if let Some(index) = names.iter().position(|&name| name == column) {
+                selected[index] = keep;
+            }
+        }
+    }
+    let columns: Vec<usize> = (0..names.len()).filter(|&index| selected[index]).collect();
+    if columns.is_empty() {
+        return Err("--columns leaves no column to write".to_string());
+    }
+    Ok(columns)
+}
 
-                println!("  Average moves: {:.1}", avg_moves);
-                println!("  Average efficiency: {:.3}", avg_efficiency);
-                println!("  Average execution time: {:.1}ms", avg_time);
+/// Every value of a CSV row, in the order of `CSV_HEADER`
+fn csv_values(result: &BatchResult) -> Vec<String> {
+    vec![
+        result.configuration_id.to_string(),
+        result.simulation_id.to_string(),
+        result.seed.to_string(),
+        result.algorithm.clone(),
+        result.grid_width.to_string(),
+        result.grid_height.to_string(),
+        result.num_walls.to_string(),
+        result.num_obstacles.to_string(),
+        result.success.to_string(),
+        result.total_moves.to_string(),
+        result.optimal_path_length.to_string(),
+        format!("{:.6}", result.route_efficiency),
+        format!("{:.6}", result.dynamic_route_efficiency),
+        result.execution_time_ms.to_string(),
+        result.a_star_calls.to_string(),
+        result.d_star_calls.to_string(),
+        result.planner_calls.to_string(),
+        result.average_find_path_time_ns.to_string(),
+        result.min_find_path_time_ns.to_string(),
+        result.median_find_path_time_ns.to_string(),
+        result.p95_find_path_time_ns.to_string(),
+        result.p99_find_path_time_ns.to_string(),
+        result.max_find_path_time_ns.to_string(),
+        result.total_pathfinding_calls.to_string(),
+        result.priority_queue.clone(),
+        result.raw_path_length.to_string(),
+        result.smoothed_path_length.to_string(),
+        result.wall_pattern.clone(),
+        result.obstacle_interval.to_string(),
+        result.obstacle_persistence.to_string(),
+        result.memory.clone(),
+        result.stale_belief_steps.to_string(),
+        result.out_of_energy.to_string(),
+        result.agents.to_string(),
+        result.agents_reached.to_string(),
+        result.agent_collisions.to_string(),
+        result.coordination.clone(),
+        result
+            .makespan
+            .map_or(String::new(), |makespan| makespan.to_string()),
+        format!("{:.1}", result.sum_of_costs),
+        result.cbs_expansions.to_string(),
+        result.caught.to_string(),
+        result.timed_out.to_string(),
+        result.nodes_expanded.to_string(),
+        result.nodes_generated.to_string(),
+        result.max_open_list.to_string(),
+        result.heap_operations.to_string(),
+        result.budget_overruns.to_string(),
+    ]
+}
+
+/// The configuration of the simulations of a configuration of the batch
+fn run_config_of(config: &Config, spec: &SweepSpec, sweep: Sweep) -> Config {
+    let mut run_config = config.clone();
+    run_config.grid_size = sweep.grid_size;
+    run_config.wall_pattern = sweep.wall_pattern.name().to_string();
+    run_config.num_walls = sweep.num_walls;
+    run_config.num_obstacles = sweep.num_obstacles;
+    run_config.obstacle_interval = sweep.obstacle_interval;
+    run_config.obstacle_persistence = sweep.obstacle_persistence;
+    // Checked for every combination before the batch started
+    if let Ok(swept_config) = spec.apply(&run_config, sweep.parameters) {
+        run_config = swept_config;
+    }
+    run_config.no_visualization = true; // Always disable visualization in batch mode
+    run_config.quiet = true; // Force quiet mode for individual simulations
+    run_config.batch_mode = true;
+    run_config
+}
+
+/// Run one simulation of the batch in every coordination mode, giving a result per
+/// mode and algorithm
+fn run_simulation(
+    config: &Config,
+    registry: &Arc<AlgorithmRegistry>,
+    spec: &SweepSpec,
+    coordinations: &[Vec<Coordination>],
+    job: &Job,
+    abort: &AbortHandle,
+) -> Vec<BatchResult> {
+    let sweep = job.sweep;
+    let run_config = run_config_of(config, spec, sweep);
+    let swept = spec.combination(sweep.parameters);
+
+    let mut results = Vec::new();
+    for &coordination in &coordinations[sweep.parameters] {
+        let mut run_config = run_config.clone();
+        run_config.coordination = coordination.name().to_string();
+        let simulation_start = Instant::now();
+
+        if run_config.algorithm == "all" {
+            // Run all algorithms for this configuration, on this worker's thread
+            match EnvironmentSetup::from_config(&run_config, Some(job.seed)).and_then(
+                |environment| {
+                    Simulation::run_all_algorithms_in_turn(
+                        run_config.clone(),
+                        Arc::clone(registry),
+                        environment,
+                        abort,
+                    )
+                },
+            ) {
+                Ok(algorithm_results) => {
+                    for algorithm_result in algorithm_results {
+                        // Each algorithm's own run, not the time all of them took
+                        let run_time = algorithm_result.run_time;
+                        let batch_result = batch_result_of(
+                            &run_config,
+                            algorithm_result,
+                            job,
+                            &swept,
+                            coordination,
+                            run_time,
+                        );
+                        results.push(batch_result);
+                    }
+                }
+                Err(_e) => {
+                    let algorithms: Vec<String> = registry.names().map(str::to_string).collect();
+                    for algorithm in &algorithms {
+                        let failed_result = failed_result(
+                            &run_config,
+                            job,
+                            algorithm.clone(),
+                            &swept,
+                            coordination,
+                            simulation_start.elapsed(),
+                        );
+                        results.push(failed_result);
+                    }
+                }
+            }
+        } else {
+            // Run single algorithm with error handling
+            match EnvironmentSetup::from_config(&run_config, Some(job.seed)).and_then(
+                |environment| {
+                    Simulation::new_with_registry(
+                        run_config.clone(),
+                        Some(environment),
+                        Arc::clone(registry),
+                    )
+                },
+            ) {
+                Ok(mut simulation) => {
+                    simulation.set_abort_handle(abort.child());
+                    let (stats, planner_metrics, timing_data) = simulation.run();
+                    let percentiles = timing_data.percentiles();
+
+                    let batch_result = BatchResult {
+                        configuration_id: job.configuration,
+                        simulation_id: job.simulation_id,
+                        seed: job.seed,
+                        algorithm: run_config.algorithm.clone(),
+                        grid_width: run_config.width(),
+                        grid_height: run_config.height(),
+                        num_walls: run_config.num_walls,
+                        num_obstacles: run_config.num_obstacles,
+                        success: simulation.is_mission_complete(),
+                        total_moves: stats.total_moves,
+                        optimal_path_length: stats.optimal_path_length,
+                        route_efficiency: stats.route_efficiency,
+                        dynamic_route_efficiency: stats.dynamic_route_efficiency,
+                        execution_time_ms: simulation_start.elapsed().as_millis() as u64,
+                        a_star_calls: planner_metrics.calls_of("A*"),
+                        d_star_calls: planner_metrics.calls_of("D* Lite"),
+                        planner_calls: planner_metrics.calls,
+                        average_find_path_time_ns: timing_data.average_find_path_time().as_nanos()
+                            as u64,
+                        min_find_path_time_ns: percentiles.min.as_nanos() as u64,
+                        median_find_path_time_ns: percentiles.median.as_nanos() as u64,
+                        p95_find_path_time_ns: percentiles.p95.as_nanos() as u64,
+                        p99_find_path_time_ns: percentiles.p99.as_nanos() as u64,
+                        max_find_path_time_ns: percentiles.max.as_nanos() as u64,
+                        total_pathfinding_calls: timing_data.total_calls(),
+                        raw_path_length: stats.raw_path_length,
+                        smoothed_path_length: stats.smoothed_path_length,
+                        wall_pattern: run_config.wall_pattern.clone(),
+                        obstacle_interval: run_config.obstacle_interval,
+                        obstacle_persistence: run_config.obstacle_persistence,
+                        stale_belief_steps: stats.stale_belief_steps,
+                        out_of_energy: stats.out_of_energy,
+                        agents_reached: stats.agents_reached(simulation.is_mission_complete()),
+                        agent_collisions: stats.total_agent_collisions(),
+                        coordination: coordination.name().to_string(),
+                        makespan: stats.makespan,
+                        sum_of_costs: stats.sum_of_costs(),
+                        cbs_expansions: stats.cbs_expansions,
+                        caught: stats.caught,
+                        timed_out: stats.timed_out,
+                        budget_overruns: stats.budget_overruns,
+                        nodes_expanded: planner_metrics.search.nodes_expanded,
+                        nodes_generated: planner_metrics.search.nodes_generated,
+                        max_open_list: planner_metrics.search.max_open_list,
+                        heap_operations: planner_metrics.search.heap_operations,
+                        priority_queue: run_config.priority_queue.clone(),
+                        memory: run_config.memory.clone(),
+                        agents: run_config.agents,
+                        swept: swept.clone(),
+                    };
+
+                    results.push(batch_result);
+                }
+                Err(_e) => {
+                    let failed_result = failed_result(
+                        &run_config,
+                        job,
+                        run_config.algorithm.clone(),
+                        &swept,
+                        coordination,
+                        simulation_start.elapsed(),
+                    );
+
+                    results.push(failed_result);
+                }
+            }
+        }
+    }
+    results
+}
+
+/// The batch result of one algorithm's run from `Simulation::run_all_algorithms`
+fn batch_result_of(
+    config: &Config,
+    result: AlgorithmResult,
+    job: &Job,
+    swept: &[toml::Value],
+    coordination: Coordination,
+    execution_time: Duration,
+) -> BatchResult {
+    let percentiles = result.timing_data.percentiles();
+    BatchResult {
+        configuration_id: job.configuration,
+        simulation_id: job.simulation_id,
+        seed: job.seed,
+        algorithm: result.name,
+        grid_width: config.width(),
+        grid_height: config.height(),
+        num_walls: config.num_walls,
+        num_obstacles: config.num_obstacles,
+        success: result.success,
+        total_moves: result.statistics.total_moves,
+        optimal_path_length: result.statistics.optimal_path_length,
+        route_efficiency: result.statistics.route_efficiency,
+        dynamic_route_efficiency: result.statistics.dynamic_route_efficiency,
+        execution_time_ms: execution_time.as_millis() as u64,
+        a_star_calls: result.planner_metrics.calls_of("A*"),
+        d_star_calls: result.planner_metrics.calls_of("D* Lite"),
+        planner_calls: result.planner_metrics.calls,
+        average_find_path_time_ns: result.timing_data.average_find_path_time().as_nanos() as u64,
+        min_find_path_time_ns: percentiles.min.as_nanos() as u64,
+        median_find_path_time_ns: percentiles.median.as_nanos() as u64,
+        p95_find_path_time_ns: percentiles.p95.as_nanos() as u64,
+        p99_find_path_time_ns: percentiles.p99.as_nanos() as u64,
+        max_find_path_time_ns: percentiles.max.as_nanos() as u64,
+        total_pathfinding_calls: result.timing_data.total_calls(),
+        raw_path_length: result.statistics.raw_path_length,
+        smoothed_path_length: result.statistics.smoothed_path_length,
+        wall_pattern: config.wall_pattern.clone(),
+        obstacle_interval: config.obstacle_interval,
+        obstacle_persistence: config.obstacle_persistence,
+        stale_belief_steps: result.statistics.stale_belief_steps,
+        out_of_energy: result.statistics.out_of_energy,
+        agents_reached: result.statistics.agents_reached(result.success),
+        agent_collisions: result.statistics.total_agent_collisions(),
+        coordination: coordination.name().to_string(),
+        makespan: result.statistics.makespan,
+        sum_of_costs: result.statistics.sum_of_costs(),
+        cbs_expansions: result.statistics.cbs_expansions,
+        caught: result.statistics.caught,
+        timed_out: result.statistics.timed_out,
+        budget_overruns: result.statistics.budget_overruns,
+        nodes_expanded: result.planner_metrics.search.nodes_expanded,
+        nodes_generated: result.planner_metrics.search.nodes_generated,
+        max_open_list: result.planner_metrics.search.max_open_list,
+        heap_operations: result.planner_metrics.search.heap_operations,
+        priority_queue: config.priority_queue.clone(),
+        memory: config.memory.clone(),
+        agents: config.agents,
+        swept: swept.to_vec(),
+    }
+}
+
+/// The batch result of an algorithm's run that failed before it started, such as
+/// when its environment could not be generated
+fn failed_result(
+    config: &Config,
+    job: &Job,
+    algorithm: String,
+    swept: &[toml::Value],
+    coordination: Coordination,
+    execution_time: Duration,
+) -> BatchResult {
+    BatchResult {
+        configuration_id: job.configuration,
+        simulation_id: job.simulation_id,
+        seed: job.seed,
+        algorithm,
+        grid_width: config.width(),
+        grid_height: config.height(),
+        num_walls: config.num_walls,
+        num_obstacles: config.num_obstacles,
+        success: false,
+        total_moves: 0,
+        optimal_path_length: 0,
+        route_efficiency: 0.0,
+        dynamic_route_efficiency: 0.0,
+        execution_time_ms: execution_time.as_millis() as u64,
+        a_star_calls: 0,
+        d_star_calls: 0,
+        planner_calls: 0,
+        average_find_path_time_ns: 0,
+        min_find_path_time_ns: 0,
+        median_find_path_time_ns: 0,
+        p95_find_path_time_ns: 0,
+        p99_find_path_time_ns: 0,
+        max_find_path_time_ns: 0,
+        total_pathfinding_calls: 0,
+        raw_path_length: 0,
+        smoothed_path_length: 0,
+        wall_pattern: config.wall_pattern.clone(),
+        obstacle_interval: config.obstacle_interval,
+        obstacle_persistence: config.obstacle_persistence,
+        stale_belief_steps: 0,
+        out_of_energy: false,
+        agents_reached: 0,
+        agent_collisions: 0,
+        coordination: coordination.name().to_string(),
+        makespan: None,
+        sum_of_costs: 0.0,
+        cbs_expansions: 0,
+        caught: false,
+        timed_out: false,
+        budget_overruns: 0,
+        nodes_expanded: 0,
+        nodes_generated: 0,
+        max_open_list: 0,
+        heap_operations: 0,
+        priority_queue: config.priority_queue.clone(),
+        memory: config.memory.clone(),
+        agents: config.agents,
+        swept: swept.to_vec(),
+    }
+}
+
+/// The result a row of an output file holds, given as its `fields` by column name,
+/// with the values of `template` for the columns it does not have. CSV values are
+/// read as the type of the template's. `None` if a value is not of that type.
+fn result_of_row(template: BatchResult, fields: &Map<String, Value>) -> Option<BatchResult> {
+    let swept = template.swept.clone();
+    let mut object = serde_json::to_value(template).ok()?;
+    for (name, value) in object.as_object_mut()? {
+        match fields.get(name) {
+            Some(Value::String(field)) if !value.is_string() => {
+                *value = match field.as_str() {
+                    "" => Value::Null,
+                    field => serde_json::from_str(field).ok()?,
+                }
             }
+            Some(field) => *value = field.clone(),
+            None => {}
         }
     }
+    let mut result: BatchResult = serde_json::from_value(object).ok()?;
+    result.swept = swept;
+    Some(result)
 }
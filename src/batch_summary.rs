@@ -0,0 +1,598 @@
+use crate::batch_simulation::BatchResult;
+use crate::config::Config;
+use crate::say;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::collections::HashMap;
+
+/// Resamples drawn for a bootstrap confidence interval
+const BOOTSTRAP_RESAMPLES: usize = 1000;
+/// Random sign flips drawn for a paired permutation test
+const PERMUTATIONS: usize = 5000;
+/// Largest sample that is resampled; larger ones use the normal approximation, which
+/// the resampled statistics agree with closely at that size
+const RESAMPLED_UP_TO: usize = 1000;
+/// p-values below this are marked significant
+const SIGNIFICANCE_LEVEL: f64 = 0.05;
+
+/// What the summary keeps of one result of a batch
+#[derive(Debug, Clone)]
+pub(crate) struct Run {
+    /// Index of the algorithm (and coordination mode, when several are compared)
+    group: usize,
+    /// Index of the configuration the run is in, the same for every algorithm
+    pub(crate) configuration: usize,
+    seed: u64,
+    pub(crate) success: bool,
+    caught: bool,
+    timed_out: bool,
+    agents_reached: usize,
+    agent_collisions: usize,
+    makespan: Option<usize>,
+    sum_of_costs: f64,
+    moves: f64,
+    efficiency: f64,
+    dynamic_efficiency: f64,
+    execution_time_ms: f64,
+    /// Share of the grid's cells that are walls
+    pub(crate) wall_density: f64,
+    /// Average time of the run's find_path calls, in microseconds (0 without any)
+    pub(crate) find_path_time_us: f64,
+}
+
+/// Statistics of every result of a batch by algorithm and configuration, collected as
+/// the results are written: success rates, the spread of the moves, route efficiency
+/// and execution time of successful runs with bootstrap confidence intervals, and
+/// paired significance tests between the algorithms.
+#[derive(Debug, Clone, Default)]
+pub struct BatchSummary {
+    groups: Vec<String>,
+    configurations: Vec<String>,
+    group_ids: HashMap<String, usize>,
+    configuration_ids: HashMap<String, usize>,
+    runs: Vec<Run>,
+}
+
+/// A metric of successful runs, as printed
+pub(crate) struct Metric {
+    pub(crate) name: &'static str,
+    pub(crate) value: fn(&Run) -> f64,
+    /// Decimals printed
+    pub(crate) precision: usize,
+    pub(crate) unit: &'static str,
+    /// Only measured when asked for; left out when no run has it (all 0)
+    pub(crate) optional: bool,
+}
+
+const METRICS: [Metric; 4] = [
+    Metric {
+        name: "Moves",
+        value: |run| run.moves,
+        precision: 1,
+        unit: "",
+        optional: false,
+    },
+    Metric {
+        name: "Efficiency",
+        value: |run| run.efficiency,
+        precision: 3,
+        unit: "",
+        optional: false,
+    },
+    Metric {
+        name: "Dynamic efficiency",
+        value: |run| run.dynamic_efficiency,
+        precision: 3,
+        unit: "",
+        optional: true,
+    },
+    Metric {
+        name: "Execution time",
+        value: |run| run.execution_time_ms,
+        precision: 1,
+        unit: "ms",
+        optional: false,
+    },
+];
+
+impl BatchSummary {
+    /// Add the results of one simulation, run in the configuration described by
+    /// `configuration`; with `by_coordination`, each coordination mode of an algorithm
+    /// is summarized on its own
+    pub fn record(
+        &mut self,
+        configuration: String,
+        results: &[BatchResult],
+        by_coordination: bool,
+    ) {
+        let configuration = intern(
+            &mut self.configurations,
+            &mut self.configuration_ids,
+            configuration,
+        );
+        for result in results {
+            let group = match by_coordination {
+                true => format!("{} ({})", result.algorithm, result.coordination),
+                false => result.algorithm.clone(),
+            };
+            let group = intern(&mut self.groups, &mut self.group_ids, group);
+            self.runs.push(Run {
+                group,
+                configuration,
+                seed: result.seed,
+                success: result.success,
+                caught: result.caught,
+                timed_out: result.timed_out,
+                agents_reached: result.agents_reached,
+                agent_collisions: result.agent_collisions,
+                makespan: result.makespan,
+                sum_of_costs: result.sum_of_costs,
+                moves: result.total_moves as f64,
+                efficiency: result.route_efficiency,
+                dynamic_efficiency: result.dynamic_route_efficiency,
+                execution_time_ms: result.execution_time_ms as f64,
+                wall_density: result.num_walls as f64
+                    / (result.grid_width * result.grid_height).max(1) as f64,
+                find_path_time_us: result.average_find_path_time_ns as f64 / 1000.0,
+            });
+        }
+    }
+
+    /// The metrics to show: every one but those only measured when asked for, such
+    /// as dynamic efficiency without `--dynamic-baseline`, that no run has
+    pub(crate) fn metrics(&self) -> Vec<&'static Metric> {
+        METRICS
+            .iter()
+            .filter(|metric| {
+                !metric.optional || self.runs.iter().any(|run| (metric.value)(run) != 0.0)
+            })
+            .collect()
+    }
+
+    /// Whether no results were recorded
+    pub fn is_empty(&self) -> bool {
+        self.runs.is_empty()
+    }
+
+    /// Every algorithm's name, runs and successful runs
+    pub fn totals(&self) -> Vec<(String, usize, usize)> {
+        self.algorithms()
+            .into_iter()
+            .map(|(name, runs)| {
+                let successful = runs.iter().filter(|run| run.success).count();
+                (name.to_string(), runs.len(), successful)
+            })
+            .collect()
+    }
+
+    /// Print the statistics of every algorithm, broken down by configuration with
+    /// `by_configuration`, and with `significance_test` the paired comparisons of every
+    /// two algorithms
+    pub fn print(&self, config: &Config, by_configuration: bool, significance_test: bool) {
+        let mut rng = Self::rng();
+        let metrics = self.metrics();
+        say!("\n=== BATCH SIMULATION SUMMARY ===");
+        say!(
+            "Intervals are 95% confidence intervals of the mean, bootstrapped up to {} runs; \
+             moves, efficiency and time are of successful runs",
+            RESAMPLED_UP_TO
+        );
+
+        for (name, runs) in self.algorithms() {
+            say!("\n{} Algorithm Results:", name);
+            let total = runs.len();
+            let successes: Vec<f64> = runs.iter().map(|run| f64::from(run.success)).collect();
+            let (low, high) = confidence_interval(&successes, &mut rng);
+            let successful = successes.iter().filter(|&&success| success > 0.0).count();
+            say!(
+                "  Success rate: {}/{} ({:.1}%) [{:.1}%, {:.1}%]",
+                successful,
+                total,
+                mean(&successes) * 100.0,
+                low * 100.0,
+                high * 100.0
+            );
+            if config.pursuer {
+                say!(
+                    "  Caught by the pursuer: {}/{}",
+                    runs.iter().filter(|run| run.caught).count(),
+                    total
+                );
+            }
+            if config.simulation_timeout_seconds.is_some() {
+                say!(
+                    "  Timed out: {}/{}",
+                    runs.iter().filter(|run| run.timed_out).count(),
+                    total
+                );
+            }
+            if config.agents > 1 {
+                say!(
+                    "  Agents at their goals: {}/{}, collisions avoided: {}",
+                    runs.iter().map(|run| run.agents_reached).sum::<usize>(),
+                    total * config.agents,
+                    runs.iter().map(|run| run.agent_collisions).sum::<usize>()
+                );
+                let makespans: Vec<usize> = runs.iter().filter_map(|run| run.makespan).collect();
+                if !makespans.is_empty() {
+                    say!(
+                        "  Average makespan: {:.1} steps, average sum of costs: {:.1} (runs where every agent arrived)",
+                        makespans.iter().sum::<usize>() as f64 / makespans.len() as f64,
+                        runs.iter()
+                            .filter(|run| run.makespan.is_some())
+                            .map(|run| run.sum_of_costs)
+                            .sum::<f64>()
+                            / makespans.len() as f64
+                    );
+                }
+            }
+
+            let successful_runs: Vec<&Run> =
+                runs.iter().copied().filter(|run| run.success).collect();
+            if !successful_runs.is_empty() {
+                for metric in &metrics {
+                    let values: Vec<f64> = successful_runs
+                        .iter()
+                        .map(|run| (metric.value)(run))
+                        .collect();
+                    let spread = Spread::of(&values, &mut rng);
+                    say!(
+                        "  {}: mean {:.p$}{u} [{:.p$}, {:.p$}], sd {:.p$}, median {:.p$}, p95 {:.p$}, p99 {:.p$}",
+                        metric.name,
+                        spread.mean,
+                        spread.low,
+                        spread.high,
+                        spread.standard_deviation,
+                        spread.median,
+                        spread.p95,
+                        spread.p99,
+                        p = metric.precision,
+                        u = metric.unit
+                    );
+                }
+            }
+
+            if by_configuration {
+                say!("  By configuration:");
+                for (configuration, label) in self.configurations.iter().enumerate() {
+                    let runs: Vec<&Run> = runs
+                        .iter()
+                        .copied()
+                        .filter(|run| run.configuration == configuration)
+                        .collect();
+                    if runs.is_empty() {
+                        continue;
+                    }
+                    let successful_runs: Vec<&Run> =
+                        runs.iter().copied().filter(|run| run.success).collect();
+                    let mut line = format!(
+                        "    {}: {}/{} successful",
+                        label,
+                        successful_runs.len(),
+                        runs.len()
+                    );
+                    if !successful_runs.is_empty() {
+                        for metric in &metrics {
+                            let values: Vec<f64> = successful_runs
+                                .iter()
+                                .map(|run| (metric.value)(run))
+                                .collect();
+                            let (low, high) = confidence_interval(&values, &mut rng);
+                            line += &format!(
+                                ", {} {:.p$}{u} [{:.p$}, {:.p$}]",
+                                metric.name.to_lowercase(),
+                                mean(&values),
+                                low,
+                                high,
+                                p = metric.precision,
+                                u = metric.unit
+                            );
+                        }
+                    }
+                    say!("{}", line);
+                }
+            }
+        }
+
+        if significance_test {
+            self.print_comparisons(&mut rng);
+        }
+    }
+
+    /// Random numbers of the resampling, fixed so the same results always summarize
+    /// the same
+    pub(crate) fn rng() -> StdRng {
+        StdRng::seed_from_u64(0)
+    }
+
+    /// Every algorithm's runs, by name
+    pub(crate) fn algorithms(&self) -> Vec<(&str, Vec<&Run>)> {
+        let mut groups: Vec<usize> = (0..self.groups.len()).collect();
+        groups.sort_by(|&a, &b| self.groups[a].cmp(&self.groups[b]));
+        groups
+            .into_iter()
+            .map(|group| {
+                let runs = self.runs.iter().filter(|run| run.group == group).collect();
+                (self.groups[group].as_str(), runs)
+            })
+            .collect()
+    }
+
+    /// Descriptions of the configurations, by index
+    pub(crate) fn configurations(&self) -> &[String] {
+        &self.configurations
+    }
+
+    /// Every two algorithms compared on the runs they made on the same environments
+    pub(crate) fn comparisons(&self, rng: &mut StdRng) -> Vec<Comparison> {
+        let algorithms = self.algorithms();
+        let metrics = self.metrics();
+        // Each algorithm's run of every environment, by configuration and seed
+        let environments: Vec<HashMap<(usize, u64), &Run>> = algorithms
+            .iter()
+            .map(|(_, runs)| {
+                runs.iter()
+                    .map(|&run| ((run.configuration, run.seed), run))
+                    .collect()
+            })
+            .collect();
+
+        let mut comparisons = Vec::new();
+        for (i, (first, _)) in algorithms.iter().enumerate() {
+            for (j, (second, _)) in algorithms.iter().enumerate().skip(i + 1) {
+                let mut pairs: Vec<(&Run, &Run)> = environments[i]
+                    .iter()
+                    .filter_map(|(environment, &run)| {
+                        environments[j].get(environment).map(|&other| (run, other))
+                    })
+                    .collect();
+                // The maps are unordered, and the permutation test draws signs in order
+                pairs.sort_by_key(|(run, _)| (run.configuration, run.seed));
+                let both_successful: Vec<(&Run, &Run)> = pairs
+                    .iter()
+                    .copied()
+                    .filter(|(run, other)| run.success && other.success)
+                    .collect();
+
+                let mut differences = Vec::new();
+                if !pairs.is_empty() {
+                    let success: Vec<f64> = pairs
+                        .iter()
+                        .map(|(run, other)| f64::from(run.success) - f64::from(other.success))
+                        .collect();
+                    differences.push(Difference {
+                        name: "Success rate",
+                        mean: mean(&success) * 100.0,
+                        p: paired_p_value(&success, rng),
+                        precision: 0,
+                        unit: "%",
+                    });
+                }
+                if !both_successful.is_empty() {
+                    for metric in &metrics {
+                        let values: Vec<f64> = both_successful
+                            .iter()
+                            .map(|(run, other)| (metric.value)(run) - (metric.value)(other))
+                            .collect();
+                        differences.push(Difference {
+                            name: metric.name,
+                            mean: mean(&values),
+                            p: paired_p_value(&values, rng),
+                            precision: metric.precision,
+                            unit: metric.unit,
+                        });
+                    }
+                }
+                comparisons.push(Comparison {
+                    first: first.to_string(),
+                    second: second.to_string(),
+                    pairs: pairs.len(),
+                    both_successful: both_successful.len(),
+                    differences,
+                });
+            }
+        }
+        comparisons
+    }
+
+    /// Print the paired comparisons of every two algorithms
+    fn print_comparisons(&self, rng: &mut StdRng) {
+        say!("\n=== PAIRED COMPARISONS ===");
+        say!("{}", comparisons_note());
+        let comparisons = self.comparisons(rng);
+        if comparisons.is_empty() {
+            say!("\nOnly one algorithm ran, so there is nothing to compare");
+        }
+        for comparison in comparisons {
+            say!(
+                "\n{} vs {}: {} paired runs, {} where both succeeded",
+                comparison.first,
+                comparison.second,
+                comparison.pairs,
+                comparison.both_successful
+            );
+            for difference in &comparison.differences {
+                say!(
+                    "  {}: {:+.p$}{} ({})",
+                    difference.name,
+                    difference.mean,
+                    difference.unit,
+                    significance(difference.p),
+                    p = difference.precision + 1
+                );
+            }
+        }
+    }
+}
+
+/// How the paired comparisons are made, printed above them
+pub(crate) fn comparisons_note() -> String {
+    format!(
+        "Differences are the first algorithm's mean minus the second's over runs on the same \
+         environments; p-values are from a paired sign-flip permutation test up to {} pairs, \
+         and the normal approximation beyond (* below {})",
+        RESAMPLED_UP_TO, SIGNIFICANCE_LEVEL
+    )
+}
+
+/// Two algorithms compared on the runs they made on the same environments
+pub(crate) struct Comparison {
+    pub(crate) first: String,
+    pub(crate) second: String,
+    /// Environments both ran on
+    pub(crate) pairs: usize,
+    /// Environments both succeeded on, which the metrics other than success compare
+    pub(crate) both_successful: usize,
+    pub(crate) differences: Vec<Difference>,
+}
+
+/// Mean difference in a metric between two algorithms, and its p-value
+pub(crate) struct Difference {
+    pub(crate) name: &'static str,
+    pub(crate) mean: f64,
+    pub(crate) p: f64,
+    /// Decimals the metric's values are printed with
+    pub(crate) precision: usize,
+    pub(crate) unit: &'static str,
+}
+
+/// Mean, confidence interval, standard deviation, median and tail percentiles of a
+/// metric
+pub(crate) struct Spread {
+    pub(crate) mean: f64,
+    /// Bounds of the 95% confidence interval of the mean
+    pub(crate) low: f64,
+    pub(crate) high: f64,
+    pub(crate) standard_deviation: f64,
+    pub(crate) median: f64,
+    pub(crate) p95: f64,
+    pub(crate) p99: f64,
+}
+
+impl Spread {
+    pub(crate) fn of(values: &[f64], rng: &mut StdRng) -> Self {
+        let (low, high) = confidence_interval(values, rng);
+        let mut sorted = values.to_vec();
+        sorted.sort_by(f64::total_cmp);
+        Spread {
+            mean: mean(values),
+            low,
+            high,
+            standard_deviation: standard_deviation(values),
+            median: percentile(&sorted, 0.5),
+            p95: percentile(&sorted, 0.95),
+            p99: percentile(&sorted, 0.99),
+        }
+    }
+}
+
+/// Index of a name in a list of names, added if it is not in it yet
+fn intern(names: &mut Vec<String>, ids: &mut HashMap<String, usize>, name: String) -> usize {
+    if let Some(&id) = ids.get(&name) {
+        return id;
+    }
+    names.push(name.clone());
+    ids.insert(name, names.len() - 1);
+    names.len() - 1
+}
+
+pub(crate) fn mean(values: &[f64]) -> f64 {
+    values.iter().sum::<f64>() / values.len().max(1) as f64
+}
+
+/// Sample standard deviation, 0 for fewer than two values
+fn standard_deviation(values: &[f64]) -> f64 {
+    if values.len() < 2 {
+        return 0.0;
+    }
+    let mean = mean(values);
+    let squares: f64 = values.iter().map(|value| (value - mean).powi(2)).sum();
+    (squares / (values.len() - 1) as f64).sqrt()
+}
+
+/// Value at a fraction of sorted values, interpolated between the two nearest
+pub(crate) fn percentile(sorted: &[f64], fraction: f64) -> f64 {
+    if sorted.is_empty() {
+        return 0.0;
+    }
+    let rank = fraction * (sorted.len() - 1) as f64;
+    let (below, above) = (rank.floor() as usize, rank.ceil() as usize);
+    sorted[below] + (sorted[above] - sorted[below]) * (rank - below as f64)
+}
+
+/// 95% confidence interval of the mean: the percentile bootstrap, or the normal
+/// approximation for large samples
+pub(crate) fn confidence_interval(values: &[f64], rng: &mut StdRng) -> (f64, f64) {
+    let mean = mean(values);
+    if values.len() < 2 {
+        return (mean, mean);
+    }
+    if values.len() > RESAMPLED_UP_TO {
+        let margin = 1.96 * standard_deviation(values) / (values.len() as f64).sqrt();
+        return (mean - margin, mean + margin);
+    }
+    let mut means: Vec<f64> = (0..BOOTSTRAP_RESAMPLES)
+        .map(|_| {
+            (0..values.len())
+                .map(|_| values[rng.gen_range(0..values.len())])
+                .sum::<f64>()
+                / values.len() as f64
+        })
+        .collect();
+    means.sort_by(f64::total_cmp);
+    (percentile(&means, 0.025), percentile(&means, 0.975))
+}
+
+/// Two-sided p-value of paired differences having a mean of 0: the share of random
+/// sign flips of the differences whose mean is at least as far from 0, or the normal
+/// approximation of it for large samples
+fn paired_p_value(differences: &[f64], rng: &mut StdRng) -> f64 {
+    let n = differences.len() as f64;
+    let observed = mean(differences).abs();
+    let spread = differences.iter().map(|d| d * d).sum::<f64>().sqrt() / n;
+    if spread == 0.0 {
+        return 1.0;
+    }
+    if differences.len() > RESAMPLED_UP_TO {
+        return erfc(observed / spread / std::f64::consts::SQRT_2);
+    }
+    // Tolerates the rounding of the flipped sums, which would otherwise miss ties
+    let threshold = observed * n - 1e-9 * spread * n;
+    let mut as_extreme = 0;
+    for _ in 0..PERMUTATIONS {
+        let mut sum = 0.0;
+        for chunk in differences.chunks(64) {
+            let signs: u64 = rng.gen();
+            for (bit, difference) in chunk.iter().enumerate() {
+                sum += match signs >> bit & 1 {
+                    1 => *difference,
+                    _ => -difference,
+                };
+            }
+        }
+        if sum.abs() >= threshold {
+            as_extreme += 1;
+        }
+    }
+    (as_extreme + 1) as f64 / (PERMUTATIONS + 1) as f64
+}
+
+/// Complementary error function of a non-negative value, to within 1.5e-7
+/// (Abramowitz and Stegun 7.1.26)
+fn erfc(x: f64) -> f64 {
+    let t = 1.0 / (1.0 + 0.3275911 * x);
+    let polynomial = t
+        * (0.254829592
+            + t * (-0.284496736 + t * (1.421413741 + t * (-1.453152027 + t * 1.061405429))));
+    polynomial * (-x * x).exp()
+}
+
+/// A p-value as printed, marked when it is significant
+pub(crate) fn significance(p: f64) -> String {
+    let marker = if p < SIGNIFICANCE_LEVEL { " *" } else { "" };
+    if p < 0.001 {
+        format!("p < 0.001{}", marker)
+    } else {
+        format!("p = {:.3}{}", p, marker)
+    }
+}
@@ -0,0 +1,476 @@
+use crate::algorithms::registry::{AlgorithmContext, AlgorithmRegistry};
+use crate::batch_summary::{percentile, Spread};
+use crate::config::Config;
+use crate::error::{ConfigError, SimulationError};
+use crate::grid::Position;
+use crate::map_file::MapLayout;
+use crate::simulation::{EnvironmentSetup, Simulation};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::collections::HashSet;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// How long one algorithm planned over every run of a benchmark
+#[derive(Debug, Clone)]
+pub struct BenchResult {
+    pub algorithm: String,
+    pub runs: usize,
+    pub successes: usize,
+    /// Time of every `find_path` call over all runs
+    pub find_path_times: Vec<Duration>,
+    /// Time of the whole runs, planning and everything else
+    pub run_time: Duration,
+}
+
+impl BenchResult {
+    pub fn average_find_path_time(&self) -> Duration {
+        match self.find_path_times.len() {
+            0 => Duration::ZERO,
+            calls => self.find_path_times.iter().sum::<Duration>() / calls as u32,
+        }
+    }
+
+    pub fn max_find_path_time(&self) -> Duration {
+        self.find_path_times
+            .iter()
+            .copied()
+            .max()
+            .unwrap_or_default()
+    }
+}
+
+/// One of the standard workloads of `bench --suite`: square grids of growing size,
+/// with walls and obstacles in the proportions of the default options, drawn from a
+/// seed of their own so every build times the same environments
+#[derive(Debug, Clone, Copy)]
+pub struct Workload {
+    pub name: &'static str,
+    pub size: usize,
+    pub walls: usize,
+    pub obstacles: usize,
+    pub seed: u64,
+}
+
+/// The workloads of `bench --suite`, smallest first
+pub const STANDARD_WORKLOADS: [Workload; 4] = [
+    Workload {
+        name: "small",
+        size: 25,
+        walls: 80,
+        obstacles: 15,
+        seed: 1,
+    },
+    Workload {
+        name: "medium",
+        size: 50,
+        walls: 310,
+        obstacles: 60,
+        seed: 2,
+    },
+    Workload {
+        name: "large",
+        size: 100,
+        walls: 1250,
+        obstacles: 250,
+        seed: 3,
+    },
+    Workload {
+        name: "huge",
+        size: 200,
+        walls: 5000,
+        obstacles: 1000,
+        seed: 4,
+    },
+];
+
+/// The workload of `bench --replans`: grids as large as the suite's largest, where a
+/// search from scratch costs the most
+pub const REPLAN_WORKLOAD: Workload = Workload {
+    name: "replans",
+    size: 200,
+    walls: 5000,
+    obstacles: 1000,
+    seed: 5,
+};
+
+/// How one algorithm did on one workload of the suite, over its timed samples
+#[derive(Debug, Clone)]
+pub struct SuiteResult {
+    pub workload: Workload,
+    pub algorithm: String,
+    pub runs: usize,
+    pub successes: usize,
+    /// Milliseconds of every timed run, `--samples` of them per environment
+    pub run_times_ms: Vec<f64>,
+    /// Steps of every timed run, in the order of `run_times_ms`
+    pub steps: Vec<usize>,
+    /// Time of every `find_path` call of the timed runs
+    pub find_path_times: Vec<Duration>,
+}
+
+impl SuiteResult {
+    /// Steps simulated per second of run time
+    pub fn steps_per_second(&self) -> f64 {
+        let seconds = self.run_times_ms.iter().sum::<f64>() / 1000.0;
+        self.steps.iter().sum::<usize>() as f64 / seconds.max(f64::EPSILON)
+    }
+
+    /// `find_path` calls answered per second of planning
+    pub fn calls_per_second(&self) -> f64 {
+        let seconds = self.find_path_times.iter().sum::<Duration>().as_secs_f64();
+        self.find_path_times.len() as f64 / seconds.max(f64::EPSILON)
+    }
+}
+
+/// Times the planners (`--algorithm`, or every one with `all`) on the same
+/// environments, drawn from `--seed`, without drawing the grid.
+pub struct Bench {
+    config: Config,
+    registry: Arc<AlgorithmRegistry>,
+    runs: usize,
+    pub results: Vec<BenchResult>,
+    pub suite_results: Vec<SuiteResult>,
+}
+
+impl Bench {
+    pub fn new(config: Config, runs: usize) -> Self {
+        Self::with_registry(config, runs, Arc::new(AlgorithmRegistry::with_builtins()))
+    }
+
+    /// Benchmark that looks algorithms up in the given registry
+    pub fn with_registry(config: Config, runs: usize, registry: Arc<AlgorithmRegistry>) -> Self {
+        Bench {
+            config,
+            registry,
+            runs,
+            results: Vec::new(),
+            suite_results: Vec::new(),
+        }
+    }
+
+    /// Names of the algorithms to time
+    fn algorithms(&self) -> Result<Vec<String>, ConfigError> {
+        if self.config.algorithm == "all" {
+            Ok(self.registry.names().map(str::to_string).collect())
+        } else if self.registry.contains(&self.config.algorithm) {
+            Ok(vec![self.config.algorithm.clone()])
+        } else {
+            Err(ConfigError::UnknownAlgorithm(self.config.algorithm.clone()))
+        }
+    }
+
+    pub fn run(&mut self) -> Result<(), SimulationError> {
+        if self.runs == 0 {
+            return Err("--runs must be at least 1".to_string().into());
+        }
+        MapLayout::fit_config(&mut self.config)?;
+        self.results = self
+            .algorithms()?
+            .iter()
+            .map(|algorithm| BenchResult {
+                algorithm: algorithm.clone(),
+                runs: 0,
+                successes: 0,
+                find_path_times: Vec::new(),
+                run_time: Duration::ZERO,
+            })
+            .collect();
+
+        let mut seeds = match self.config.seed {
+            Some(seed) => StdRng::seed_from_u64(seed),
+            None => StdRng::from_entropy(),
+        };
+        // Why each algorithm could not run, until it could once
+        let mut failures: Vec<Option<SimulationError>> =
+            (0..self.results.len()).map(|_| None).collect();
+        let mut ran = vec![false; self.results.len()];
+        for _ in 0..self.runs {
+            let run_seed: u64 = seeds.gen();
+            let environment = EnvironmentSetup::from_config(&self.config, Some(run_seed))?;
+            for (index, result) in self.results.iter_mut().enumerate() {
+                let mut config = self.config.clone();
+                config.algorithm = result.algorithm.clone();
+                config.batch_mode = true;
+                result.runs += 1;
+                // An environment without a way to the goal counts as a failed run, as
+                // in batch mode
+                let mut simulation = match Simulation::new_with_registry(
+                    config,
+                    Some(environment.clone()),
+                    Arc::clone(&self.registry),
+                ) {
+                    Ok(simulation) => simulation,
+                    Err(e) => {
+                        failures[index] = Some(e);
+                        continue;
+                    }
+                };
+                ran[index] = true;
+                let started = Instant::now();
+                let (_, _, timing_data) = simulation.run();
+                result.run_time += started.elapsed();
+                result.successes += usize::from(simulation.is_mission_complete());
+                result.find_path_times.extend(timing_data.find_path_times);
+            }
+        }
+        // Options no environment could run with are a mistake, not a result
+        match ran.iter().zip(failures).find(|(&ran, _)| !ran) {
+            Some((_, Some(e))) => Err(e),
+            _ => Ok(()),
+        }
+    }
+
+    /// Time the planners on `--runs` environments of each of the `STANDARD_WORKLOADS`,
+    /// like a statistics-minded benchmark harness: each run is made once to warm up
+    /// and then timed `samples` times
+    pub fn run_suite(&mut self, samples: usize) -> Result<(), SimulationError> {
+        if self.runs == 0 {
+            return Err("--runs must be at least 1".to_string().into());
+        }
+        if samples == 0 {
+            return Err("--samples must be at least 1".to_string().into());
+        }
+        if self.config.map_file.is_some() || self.config.map_image.is_some() {
+            return Err("--suite draws grids of its own; leave out the map options"
+                .to_string()
+                .into());
+        }
+        let algorithms = self.algorithms()?;
+        self.suite_results.clear();
+        for workload in STANDARD_WORKLOADS {
+            let mut config = self.config.clone();
+            config.grid_size = workload.size;
+            config.grid_width = None;
+            config.grid_height = None;
+            config.num_walls = workload.walls;
+            config.num_obstacles = workload.obstacles;
+            config.batch_mode = true;
+
+            let mut seeds = StdRng::seed_from_u64(workload.seed);
+            let environments = (0..self.runs)
+                .map(|_| EnvironmentSetup::from_config(&config, Some(seeds.gen())))
+                .collect::<Result<Vec<_>, _>>()?;
+            for algorithm in &algorithms {
+                let mut result = SuiteResult {
+                    workload,
+                    algorithm: algorithm.clone(),
+                    runs: 0,
+                    successes: 0,
+                    run_times_ms: Vec::new(),
+                    steps: Vec::new(),
+                    find_path_times: Vec::new(),
+                };
+                let mut config = config.clone();
+                config.algorithm = algorithm.clone();
+                for environment in &environments {
+                    result.runs += 1;
+                    // The untimed warm-up run, which also tells whether the run succeeds
+                    let Ok(mut simulation) = self.simulation(&config, environment) else {
+                        continue;
+                    };
+                    simulation.run();
+                    result.successes += usize::from(simulation.is_mission_complete());
+                    for _ in 0..samples {
+                        let mut simulation = self.simulation(&config, environment)?;
+                        let started = Instant::now();
+                        let (_, _, timing_data) = simulation.run();
+                        result
+                            .run_times_ms
+                            .push(started.elapsed().as_secs_f64() * 1000.0);
+                        result.steps.push(simulation.steps());
+                        result.find_path_times.extend(timing_data.find_path_times);
+                    }
+                }
+                self.suite_results.push(result);
+            }
+        }
+        Ok(())
+    }
+
+    /// Time `replans` calls of one planner of each algorithm on `--runs` environments
+    /// of the `REPLAN_WORKLOAD`, the way a simulation replans: between calls the
+    /// agent moves a cell along the latest path, and the obstacles change every
+    /// obstacle cycle. A planner that keeps state between calls is given it all.
+    pub fn run_replans(&mut self, replans: usize) -> Result<(), SimulationError> {
+        if self.runs == 0 {
+            return Err("--runs must be at least 1".to_string().into());
+        }
+        if replans == 0 {
+            return Err("--replans must be at least 1".to_string().into());
+        }
+        if self.config.map_file.is_some() || self.config.map_image.is_some() {
+            return Err(
+                "--replans draws grids of its own; leave out the map options"
+                    .to_string()
+                    .into(),
+            );
+        }
+        let workload = REPLAN_WORKLOAD;
+        let mut config = self.config.clone();
+        config.grid_size = workload.size;
+        config.grid_width = None;
+        config.grid_height = None;
+        config.num_walls = workload.walls;
+        config.num_obstacles = workload.obstacles;
+        config.batch_mode = true;
+
+        let mut seeds = StdRng::seed_from_u64(workload.seed);
+        let environments = (0..self.runs)
+            .map(|_| EnvironmentSetup::from_config(&config, Some(seeds.gen())))
+            .collect::<Result<Vec<_>, _>>()?;
+        self.results.clear();
+        for algorithm in self.algorithms()? {
+            let mut result = BenchResult {
+                algorithm: algorithm.clone(),
+                runs: 0,
+                successes: 0,
+                find_path_times: Vec::new(),
+                run_time: Duration::ZERO,
+            };
+            let mut config = config.clone();
+            config.algorithm = algorithm;
+            for environment in &environments {
+                result.runs += 1;
+                let grid = environment.create_grid();
+                let mut planner = self.registry.create(
+                    &config.algorithm,
+                    &AlgorithmContext {
+                        config: &config,
+                        start: grid.start,
+                        goal: grid.goal,
+                        width: grid.width,
+                        height: grid.height,
+                    },
+                )?;
+                let mut position = grid.start;
+                let mut path: Vec<Position> = Vec::new();
+                let started = Instant::now();
+                for replan in 0..replans {
+                    // Where the agent stands is never blocked to it
+                    let mut obstacles = match environment.obstacle_timeline.len() {
+                        0 => HashSet::new(),
+                        cycles => environment.obstacle_timeline
+                            [replan / environment.obstacle_cycle_interval.max(1) % cycles]
+                            .clone(),
+                    };
+                    obstacles.remove(&position);
+                    let called = Instant::now();
+                    let found = planner.find_path(&grid, position, grid.goal, &obstacles);
+                    result.find_path_times.push(called.elapsed());
+                    if let Some(found) = found {
+                        path = found;
+                    }
+                    // The next cell of the latest path, if it is free now
+                    let next = path.iter().skip_while(|&&cell| cell != position).nth(1);
+                    if let Some(&next) = next.filter(|next| !obstacles.contains(next)) {
+                        position = next;
+                    }
+                    if position == grid.goal {
+                        result.successes += 1;
+                        break;
+                    }
+                }
+                result.run_time += started.elapsed();
+            }
+            self.results.push(result);
+        }
+        Ok(())
+    }
+
+    fn simulation(
+        &self,
+        config: &Config,
+        environment: &EnvironmentSetup,
+    ) -> Result<Simulation, SimulationError> {
+        Simulation::new_with_registry(
+            config.clone(),
+            Some(environment.clone()),
+            Arc::clone(&self.registry),
+        )
+    }
+
+    /// Print each workload's timings: the mean run time with its 95% confidence
+    /// interval and its median, the throughput in steps and `find_path` calls per
+    /// second, and the median and p95 `find_path` time
+    pub fn print_suite_summary(&self) {
+        let mut rng = StdRng::seed_from_u64(0);
+        for workload in STANDARD_WORKLOADS {
+            println!(
+                "\n=== {} ({}x{}, {} walls, {} obstacles) ===",
+                workload.name.to_uppercase(),
+                workload.size,
+                workload.size,
+                workload.walls,
+                workload.obstacles
+            );
+            println!(
+                "{:<20} {:<10} {:<32} {:<12} {:<14} {:<14} {:<17} {:<15}",
+                "Algorithm",
+                "Success",
+                "Run time (95% CI)",
+                "Median run",
+                "Steps/s",
+                "Calls/s",
+                "Median find_path",
+                "p95 find_path"
+            );
+            for result in self
+                .suite_results
+                .iter()
+                .filter(|result| result.workload.name == workload.name)
+            {
+                let spread = Spread::of(&result.run_times_ms, &mut rng);
+                let mut find_path_ns: Vec<f64> = result
+                    .find_path_times
+                    .iter()
+                    .map(|time| time.as_nanos() as f64)
+                    .collect();
+                find_path_ns.sort_by(f64::total_cmp);
+                let nanos =
+                    |fraction| Duration::from_nanos(percentile(&find_path_ns, fraction) as u64);
+                println!(
+                    "{:<20} {:<10} {:<32} {:<12} {:<14} {:<14} {:<17} {:<15}",
+                    result.algorithm,
+                    format!("{}/{}", result.successes, result.runs),
+                    format!(
+                        "{:.2}ms [{:.2}, {:.2}]",
+                        spread.mean, spread.low, spread.high
+                    ),
+                    format!("{:.2}ms", spread.median),
+                    format!("{:.0}", result.steps_per_second()),
+                    format!("{:.0}", result.calls_per_second()),
+                    format!("{:.2?}", nanos(0.5)),
+                    format!("{:.2?}", nanos(0.95)),
+                );
+            }
+        }
+    }
+
+    pub fn print_summary(&self) {
+        println!("\n=== BENCHMARK RESULTS ===");
+        println!();
+        println!(
+            "{:<20} {:<10} {:<10} {:<15} {:<15} {:<15} {:<15}",
+            "Algorithm",
+            "Success",
+            "Calls",
+            "Avg find_path",
+            "Max find_path",
+            "Planning",
+            "Run time"
+        );
+        for result in &self.results {
+            println!(
+                "{:<20} {:<10} {:<10} {:<15} {:<15} {:<15} {:<15}",
+                result.algorithm,
+                format!("{}/{}", result.successes, result.runs),
+                result.find_path_times.len(),
+                format!("{:.2?}", result.average_find_path_time()),
+                format!("{:.2?}", result.max_find_path_time()),
+                format!("{:.2?}", result.find_path_times.iter().sum::<Duration>()),
+                format!("{:.2?}", result.run_time),
+            );
+        }
+    }
+}
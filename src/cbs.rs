@@ -0,0 +1,212 @@
+use crate::cooperative::distances_to;
+use crate::grid::{Grid, Position};
+use pathfinding::prelude::astar;
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap, HashSet};
+
+/// One agent's part of a multi-agent instance: where it stands, where it is going,
+/// and the grid and obstacles it knows of.
+pub struct Task<'a> {
+    pub grid: &'a Grid,
+    pub start: Position,
+    pub goals: Vec<Position>,
+    pub obstacles: HashSet<Position>,
+}
+
+/// A cell the agent may not be on at the given step of the joint plan
+type Constraint = (Position, usize);
+
+/// A node of the constraint tree: constraints per agent and the cheapest paths that
+/// keep to them
+struct TreeNode {
+    constraints: Vec<HashSet<Constraint>>,
+    paths: Vec<Vec<Position>>,
+    cost: u32,
+}
+
+/// Conflict-Based Search: optimal joint paths for every agent of a multi-agent
+/// instance, found by planning each agent alone and, wherever two paths meet,
+/// branching on which of the two agents has to keep off the cell.
+///
+/// Agents move one after the other within a step, in the order of the tasks, so
+/// besides two agents on the same cell at once it is a conflict for an agent to step
+/// onto a cell an agent after it is only about to leave. Moving onto a cell an agent
+/// before it has just left is fine.
+#[derive(Debug, Clone)]
+pub struct ConflictSearch {
+    /// Constraint tree nodes to expand per joint plan before settling for paths
+    /// planned without regard for each other
+    node_limit: usize,
+    /// Constraint tree nodes expanded over every joint plan so far
+    pub expansions: usize,
+}
+
+impl ConflictSearch {
+    pub fn new(node_limit: usize) -> Self {
+        ConflictSearch {
+            node_limit,
+            expansions: 0,
+        }
+    }
+
+    /// Joint plan for the tasks: a path per agent (with waits) to the closest of its
+    /// goals, which it stays on. Agents that cannot reach a goal at all get `None` and
+    /// stay where they are, in the others' way.
+    pub fn solve(&mut self, tasks: &[Task]) -> Vec<Option<Vec<Position>>> {
+        let distances: Vec<HashMap<Position, u32>> = tasks
+            .iter()
+            .map(|task| distances_to(task.grid, &task.goals, &task.obstacles))
+            .collect();
+        let root_paths: Vec<Option<Vec<Position>>> = tasks
+            .iter()
+            .zip(&distances)
+            .map(|(task, distances)| plan_agent(task, distances, &HashSet::new()))
+            .collect();
+        let stuck: Vec<bool> = root_paths.iter().map(Option::is_none).collect();
+        let paths: Vec<Vec<Position>> = root_paths
+            .iter()
+            .zip(tasks)
+            .map(|(path, task)| path.clone().unwrap_or_else(|| vec![task.start]))
+            .collect();
+        let root = TreeNode {
+            constraints: vec![HashSet::new(); tasks.len()],
+            cost: joint_cost(tasks, &paths),
+            paths,
+        };
+
+        let mut nodes = vec![root];
+        let mut open = BinaryHeap::from([Reverse((nodes[0].cost, 0))]);
+        let mut expanded = 0;
+        while let Some(Reverse((_, index))) = open.pop() {
+            if expanded == self.node_limit {
+                break;
+            }
+            expanded += 1;
+            self.expansions += 1;
+            let Some(conflict) = first_conflict(&nodes[index].paths) else {
+                return finish(&nodes[index].paths, &stuck);
+            };
+            for (agent, constraint) in conflict {
+                if stuck[agent] {
+                    continue;
+                }
+                let mut constraints = nodes[index].constraints.clone();
+                constraints[agent].insert(constraint);
+                let Some(path) = plan_agent(&tasks[agent], &distances[agent], &constraints[agent])
+                else {
+                    continue;
+                };
+                let mut paths = nodes[index].paths.clone();
+                paths[agent] = path;
+                let child = TreeNode {
+                    cost: joint_cost(tasks, &paths),
+                    constraints,
+                    paths,
+                };
+                open.push(Reverse((child.cost, nodes.len())));
+                nodes.push(child);
+            }
+        }
+        // Out of nodes to expand: every agent takes its own cheapest path and has to
+        // wait where the others get in its way
+        root_paths
+    }
+}
+
+/// Sum of the agents' path costs, waits included
+fn joint_cost(tasks: &[Task], paths: &[Vec<Position>]) -> u32 {
+    tasks
+        .iter()
+        .zip(paths)
+        .map(|(task, path)| {
+            path.windows(2)
+                .map(|step| task.grid.move_cost(step[0], step[1]))
+                .sum::<u32>()
+        })
+        .sum()
+}
+
+/// Strip the agents that found no path from a finished joint plan
+fn finish(paths: &[Vec<Position>], stuck: &[bool]) -> Vec<Option<Vec<Position>>> {
+    paths
+        .iter()
+        .zip(stuck)
+        .map(|(path, &stuck)| (!stuck).then(|| path.clone()))
+        .collect()
+}
+
+/// Cell an agent is on at the given step, staying on the last cell of its path
+fn cell_at(path: &[Position], step: usize) -> Position {
+    path[step.min(path.len() - 1)]
+}
+
+/// The earliest conflict between two paths, as the constraint each of the two agents
+/// would need to keep out of it
+fn first_conflict(paths: &[Vec<Position>]) -> Option<[(usize, Constraint); 2]> {
+    let steps = paths.iter().map(Vec::len).max().unwrap_or(0);
+    for step in 0..steps {
+        for first in 0..paths.len() {
+            for second in first + 1..paths.len() {
+                let cell = cell_at(&paths[first], step);
+                if cell == cell_at(&paths[second], step) {
+                    return Some([(first, (cell, step)), (second, (cell, step))]);
+                }
+                // The first agent moves before the second one, so it cannot step onto
+                // the cell the second one is about to leave
+                let next = cell_at(&paths[first], step + 1);
+                if next != cell && next == cell_at(&paths[second], step) {
+                    return Some([(first, (next, step + 1)), (second, (next, step))]);
+                }
+            }
+        }
+    }
+    None
+}
+
+/// Cheapest path for one agent that keeps to its constraints, ending on the first goal
+/// it gets to, which it can stay on for good; found by A* over cells and steps
+fn plan_agent(
+    task: &Task,
+    distances: &HashMap<Position, u32>,
+    constraints: &HashSet<Constraint>,
+) -> Option<Vec<Position>> {
+    if !distances.contains_key(&task.start) || constraints.contains(&(task.start, 0)) {
+        return None;
+    }
+    // Past the last constraint nothing is in the way, so no path needs to be longer
+    // than that plus a walk over every cell
+    let horizon =
+        constraints.iter().map(|&(_, step)| step).max().unwrap_or(0) + task.grid.cell_count();
+    let mut last_constraint: HashMap<Position, usize> = HashMap::new();
+    for &(cell, step) in constraints {
+        let last = last_constraint.entry(cell).or_default();
+        *last = (*last).max(step);
+    }
+
+    astar(
+        &(task.start, 0),
+        |&(pos, step)| {
+            // Agents stop on the first goal they get to, so a path never goes on from one
+            if step == horizon || task.goals.contains(&pos) {
+                return Vec::new();
+            }
+            task.grid
+                .get_neighbors(&pos)
+                .into_iter()
+                .chain([pos])
+                .filter(|&next| {
+                    task.grid
+                        .can_move(pos, next, |cell| task.obstacles.contains(&cell))
+                        && distances.contains_key(&next)
+                        && !constraints.contains(&(next, step + 1))
+                })
+                .map(|next| ((next, step + 1), task.grid.move_cost(pos, next)))
+                .collect::<Vec<_>>()
+        },
+        |&(pos, _)| distances.get(&pos).copied().unwrap_or(0),
+        |&(pos, step)| {
+            task.goals.contains(&pos) && last_constraint.get(&pos).is_none_or(|&last| last < step)
+        },
+    )
+    .map(|(nodes, _)| nodes.into_iter().map(|(pos, _)| pos).collect())
+}
@@ -0,0 +1,103 @@
+use crate::grid::Position;
+use crate::observer::SimulationObserver;
+use crate::scenario::Scenario;
+use crate::simulation::{EnvironmentSetup, Simulation, StepOutcome};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// How far a run got, at the end of one of its steps. Written to a scenario file
+/// along with the options and environment of the run (`--checkpoint`); since a run of
+/// a scenario always plays out the same way, a new run of the file is taken back to
+/// the checkpoint by running it that far, which restores the planners' state and the
+/// sensor's random numbers exactly. The rest is there to check it got to the same
+/// state.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Checkpoint {
+    /// Steps taken
+    pub step: usize,
+    pub agent: Position,
+    pub moves: usize,
+    pub replans: usize,
+    /// Cells of the other agents, in planning order
+    pub companions: Vec<Position>,
+    pub pursuer: Option<Position>,
+}
+
+impl Checkpoint {
+    /// Checkpoint of the run as it stands
+    pub fn of(simulation: &Simulation) -> Self {
+        Checkpoint {
+            step: simulation.steps(),
+            agent: simulation.agent.position,
+            moves: simulation.statistics().total_moves,
+            replans: simulation.timing_data().total_calls(),
+            companions: simulation.companion_positions(),
+            pursuer: simulation.pursuer().map(|pursuer| pursuer.position),
+        }
+    }
+
+    /// Take a new run of the checkpoint's scenario to the checkpoint, without telling
+    /// its observers about the steps on the way. Fails when the run ends first or
+    /// gets somewhere else, as runs with planners that go by the clock (the hybrid's
+    /// `time_budget` policy) can.
+    pub fn resume(&self, simulation: &mut Simulation) -> Result<(), String> {
+        simulation.skip_to(self.step);
+        let reached = Checkpoint::of(simulation);
+        if reached != *self {
+            return Err(format!(
+                "The run did not get back to the checkpoint at step {} (it got to step {}, \
+                 agent at ({}, {}) instead of ({}, {}))",
+                self.step,
+                reached.step,
+                reached.agent.x,
+                reached.agent.y,
+                self.agent.x,
+                self.agent.y
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Writes a checkpoint of the run every `interval` steps to a scenario file, over the
+/// one before. Stops, with a message, the first time the file cannot be written.
+pub struct CheckpointWriter {
+    path: String,
+    interval: usize,
+    /// The environment the run started from, before the options' waypoints, goal
+    /// route, portals and obstacle script were applied to it
+    environment: EnvironmentSetup,
+    failed: bool,
+}
+
+impl CheckpointWriter {
+    pub fn new(path: &str, interval: usize, environment: EnvironmentSetup) -> Result<Self, String> {
+        if interval == 0 {
+            return Err("--checkpoint-interval must be at least 1".to_string());
+        }
+        Ok(CheckpointWriter {
+            path: path.to_string(),
+            interval,
+            environment,
+            failed: false,
+        })
+    }
+}
+
+impl SimulationObserver for CheckpointWriter {
+    fn on_step(&mut self, simulation: &Simulation, outcome: StepOutcome) {
+        if self.failed || outcome.is_over() || !simulation.steps().is_multiple_of(self.interval) {
+            return;
+        }
+        if let Err(e) = Scenario::save_checkpoint(
+            simulation.config(),
+            &self.environment,
+            &Checkpoint::of(simulation),
+            &self.path,
+        ) {
+            tracing::error!("{}", e);
+            self.failed = true;
+        }
+    }
+}
@@ -1,35 +1,627 @@
-use clap::Parser;
+use crate::algorithms::registry::AlgorithmRegistry;
+use crate::error::ConfigError;
+use crate::observer::Viewport;
+use clap::{Args, CommandFactory, Parser, Subcommand};
+use serde::Serialize;
+use std::iter::StepBy;
+use std::ops::RangeInclusive;
 
-#[derive(Parser, Debug, Clone)]
-#[command(author, version, about, long_about = None)]
+/// Prefix of the environment variables that set options: the option's long name in
+/// capitals follows it, e.g. `DYNAMIC_PATHFINDING_VISION_RADIUS=5`
+pub const ENV_PREFIX: &str = "DYNAMIC_PATHFINDING_";
+
+/// Options of every simulation, whatever the command
+#[derive(Parser, Serialize, Debug, Clone)]
 pub struct Config {
+    /// Side length of a square grid; see --grid-width and --grid-height for other shapes
     #[arg(long, default_value_t = 20)]
     pub grid_size: usize,
 
+    /// Number of grid columns (defaults to --grid-size)
+    #[arg(long)]
+    pub grid_width: Option<usize>,
+
+    /// Number of grid rows (defaults to --grid-size)
+    #[arg(long)]
+    pub grid_height: Option<usize>,
+
+    /// Number of floors, connected by stairs; the agent starts on the ground floor
+    /// and the goal is on the top floor
+    #[arg(long, default_value_t = 1)]
+    pub floors: usize,
+
+    /// Number of stairwells between each pair of adjacent floors
+    #[arg(long, default_value_t = 3)]
+    pub stairs_per_floor: usize,
+
+    /// Number of randomly placed portal pairs; stepping onto one end of a pair
+    /// leads to the other end in a single move
+    #[arg(long, default_value_t = 0)]
+    pub portals: usize,
+
+    /// Portal pairs to place instead of random ones, as "x,y:x,y;..." (append ",floor"
+    /// to a cell on multi-floor grids)
+    #[arg(long, default_value = "")]
+    pub portal_pairs: String,
+
+    /// Grid map in the MovingAI benchmark format (.map) to use instead of random walls;
+    /// the grid takes the map's size
+    #[arg(long)]
+    pub map_file: Option<String>,
+
+    /// PNG or BMP image to use as the map, one pixel per cell: dark pixels are walls,
+    /// green marks the start and red the goal
+    #[arg(long)]
+    pub map_image: Option<String>,
+
+    /// MovingAI scenario file (.scen) for --map-file; each run picks one of its
+    /// start/goal queries at random
+    #[arg(long)]
+    pub scen_file: Option<String>,
+
+    /// Always use this query (counted from 0) of --scen-file
+    #[arg(long)]
+    pub scen_index: Option<usize>,
+
+    /// How the walls are laid out: 'random' (--num-walls scattered walls),
+    /// 'recursive_backtracker' or 'prim' mazes, or 'rooms' joined by corridors
+    #[arg(long, default_value = "random")]
+    pub map_style: String,
+
+    /// Where the random style's walls go: 'random', 'clustered' (blobs), 'corridors'
+    /// (straight segments) or 'border' (mostly near the edges); batch mode sweeps a
+    /// comma-separated list
+    #[arg(long, default_value = "random")]
+    pub wall_pattern: String,
+
     #[arg(long, default_value_t = 50)]
     pub num_walls: usize,
 
     #[arg(long, default_value_t = 10)]
     pub num_obstacles: usize,
 
+    /// Steps between two groups of random obstacles appearing
+    #[arg(long, default_value_t = 5)]
+    pub obstacle_interval: usize,
+
+    /// Steps each group of random obstacles stays before it disappears
+    #[arg(long, default_value_t = 5)]
+    pub obstacle_persistence: usize,
+
+    /// Where random obstacles appear: uniform, hotspots or drifting (hotspots that move)
+    #[arg(long, default_value = "uniform")]
+    pub obstacle_model: String,
+
+    /// Mean number of random obstacles arriving per step; each group then holds a
+    /// Poisson-distributed number of obstacles instead of --num-obstacles
+    #[arg(long)]
+    pub arrival_rate: Option<f64>,
+
+    /// Number of hotspots for the hotspots and drifting obstacle models
+    #[arg(long, default_value_t = 3)]
+    pub hotspots: usize,
+
+    /// Standard deviation, in cells, of how far obstacles land from their hotspot
+    #[arg(long, default_value_t = 2.0)]
+    pub hotspot_spread: f64,
+
+    /// Standard deviation, in cells, of how far each drifting hotspot moves per group
+    #[arg(long, default_value_t = 1.0)]
+    pub hotspot_drift: f64,
+
+    /// TOML or JSON file listing exactly which cells become obstacles at which step
+    /// and for how long, instead of random obstacles
+    #[arg(long)]
+    pub obstacle_script: Option<String>,
+
+    /// Obstacles that keep moving one cell per step for the whole run
+    #[arg(long, default_value_t = 0)]
+    pub moving_obstacles: usize,
+
+    /// How moving obstacles travel: 'linear' (back and forth in a straight line),
+    /// 'patrol' (round a loop of cells), 'random_walk', or 'mixed' (any of them)
+    #[arg(long, default_value = "mixed")]
+    pub obstacle_trajectory: String,
+
+    /// Steps ahead the agent predicts a moving obstacle in view to travel, keeping
+    /// clear of the cells it will pass through (0 only avoids where it is now)
+    #[arg(long, default_value_t = 0)]
+    pub predict_steps: usize,
+
+    /// Cells the agent sees obstacles within (default: only the cells it can step to next)
+    #[arg(long)]
+    pub vision_radius: Option<usize>,
+
+    /// Which cells within --vision-radius the agent sees: 'square', 'circle', or
+    /// 'line_of_sight' (a circle whose cells behind walls stay hidden)
+    #[arg(long, default_value = "square")]
+    pub vision_shape: String,
+
+    /// Chance the agent misses an obstacle in view each time it looks
+    #[arg(long, default_value_t = 0.0)]
+    pub miss_rate: f64,
+
+    /// Chance the agent sees an obstacle on a free cell in view each time it looks
+    #[arg(long, default_value_t = 0.0)]
+    pub false_alarm_rate: f64,
+
+    /// What the agent knows from the start: 'full' (the map and every obstacle),
+    /// 'map' (the walls, discovering obstacles as it goes) or 'explore' (nothing,
+    /// discovering walls too)
+    #[arg(long, default_value = "map")]
+    pub knowledge: String,
+
+    /// How the agent forgets obstacles it has seen: 'forever' (never), 'ttl' (after
+    /// --memory-ttl steps out of view), 'reobserve' (once it sees the cell free), or
+    /// 'decay' (as its confidence fades by --memory-decay per step out of view)
+    #[arg(long, default_value = "forever")]
+    pub memory: String,
+
+    /// Steps out of view after which the 'ttl' memory forgets an obstacle
+    #[arg(long, default_value_t = 10)]
+    pub memory_ttl: usize,
+
+    /// Fraction of its confidence the 'decay' memory loses per step out of view
+    #[arg(long, default_value_t = 0.2)]
+    pub memory_decay: f64,
+
+    /// Travel cost (in straight moves, waits included) the agent can spend before it
+    /// gives up; running out is reported as its own kind of failure
+    #[arg(long)]
+    pub energy_budget: Option<f64>,
+
+    /// Seconds a run may take before it is stopped and counted as timed out, even in
+    /// the middle of a search
+    #[arg(long)]
+    pub simulation_timeout_seconds: Option<f64>,
+
+    /// Also measure runs against the best run the obstacle timeline allows, found
+    /// before the run by a space-time A* over the whole timeline; that search gives
+    /// up on timelines too long to search, and after --simulation-timeout-seconds
+    #[arg(long)]
+    pub dynamic_baseline: bool,
+
+    /// Milliseconds each replan may take; a search still running then is given up,
+    /// counted as a budget overrun, and the agent keeps to its previous path
+    #[arg(long)]
+    pub find_path_budget_ms: Option<f64>,
+
+    /// Permanent wall changes during the run, like doors closing or rubble being
+    /// cleared: each one adds a wall on a free cell or removes an existing wall
+    #[arg(long, default_value_t = 0)]
+    pub wall_events: usize,
+
     /// Pathfinding algorithm to use
     #[arg(long, default_value = "a_star")]
-    #[arg(help = "Algorithm: 'a_star', 'd_star_lite', 'hybrid', or 'all'")]
+    #[arg(
+        help = "Algorithm: 'a_star', 'a_star_fast', 'd_star_lite', 'hybrid', 'jps', 'weighted_a_star', 'ara_star', 'mt_d_star_lite', 'lrta_star', 'rtaa_star', 'dijkstra', 'bfs', 'field_d_star', 'hpa_star', 'flow_field', 'potential_field', 'space_time_a_star', or 'all'"
+    )]
     pub algorithm: String,
 
+    /// Heuristic inflation factor for weighted_a_star (>= 1.0, also the suboptimality bound)
+    #[arg(long, default_value_t = 1.5)]
+    pub heuristic_weight: f64,
+
+    /// Time budget per simulation step for anytime planners like ara_star
+    #[arg(long, default_value_t = 5)]
+    pub plan_budget_ms: u64,
+
+    /// Node expansions per move for the rtaa_star real-time planner
+    #[arg(long, default_value_t = 64)]
+    pub lookahead: usize,
+
+    /// Cluster side length for the hpa_star hierarchical planner
+    #[arg(long, default_value_t = 10)]
+    pub cluster_size: usize,
+
+    /// Number of ALT landmarks for a_star and d_star_lite (0 disables the landmark heuristic)
+    #[arg(long, default_value_t = 0)]
+    pub landmarks: usize,
+
+    /// How ALT landmarks are chosen: 'farthest', 'corners' or 'random'
+    #[arg(long, default_value = "farthest")]
+    pub landmark_strategy: String,
+
+    /// Priority queue behind d_star_lite: 'binary_heap', 'bucket_queue' or 'pairing_heap'
+    #[arg(long, default_value = "binary_heap")]
+    pub priority_queue: String,
+
+    /// When hybrid replans with A* instead of D* Lite: 'threshold', 'time_budget' or 'change_rate'
+    #[arg(long, default_value = "threshold")]
+    pub switching_policy: String,
+
+    /// threshold policy: replan with A* when the start jumps more than this many cells
+    #[arg(long, default_value_t = 3)]
+    pub switch_start_jump: usize,
+
+    /// threshold policy: replan with A* when the known obstacle count changes by more than this
+    #[arg(long, default_value_t = 5)]
+    pub switch_obstacle_delta: usize,
+
+    /// time_budget policy: D* Lite call time (microseconds) above which A* is tried instead
+    #[arg(long, default_value_t = 500)]
+    pub switch_budget_us: u64,
+
+    /// change_rate policy: obstacle cells changed per call above which A* is used
+    #[arg(long, default_value_t = 4.0)]
+    pub switch_change_rate: f64,
+
+    /// How potential_field escapes local minima: 'random_walk' or 'wall_follow'
+    #[arg(long, default_value = "random_walk")]
+    pub escape_strategy: String,
+
+    /// Let a_star wait in place for a known obstacle to expire when that is cheaper than
+    /// going around it
+    #[arg(long, default_value_t = false)]
+    pub wait_for_obstacles: bool,
+
+    /// How a_star, d_star_lite and hybrid price a known obstacle that is going to
+    /// expire: 'blocked' or 'time_aware' (a wait for every step it stays)
+    #[arg(long, default_value = "blocked")]
+    pub obstacle_cost: String,
+
+    /// Shortcut every planned path along straight lines of sight before following it
+    #[arg(long, default_value_t = false)]
+    pub smooth_path: bool,
+
+    /// Number of goals; with more than one, reaching any of them counts as success
+    #[arg(long, default_value_t = 1)]
+    pub num_goals: usize,
+
+    /// Mission waypoints to visit in order before the goal, as "x,y;x,y;..." (append
+    /// ",floor" on multi-floor grids)
+    #[arg(long, default_value = "")]
+    pub waypoints: String,
+
+    /// File with mission waypoints, one "x,y" per line ('#' starts a comment)
+    #[arg(long)]
+    pub waypoints_file: Option<String>,
+
+    /// Let the goal wander one cell per cycle; the agent has to chase it down
+    #[arg(long, default_value_t = false)]
+    pub moving_goal: bool,
+
+    /// Cycles between the goal's moves with --moving-goal or --goal-route
+    #[arg(long, default_value_t = 1)]
+    pub goal_move_interval: usize,
+
+    /// Scripted route for the goal instead of a random wander, as "x,y;x,y;..." (append
+    /// ",floor" on multi-floor grids): it heads for each cell in turn around the walls
+    /// and stays on the last one
+    #[arg(long, default_value = "")]
+    pub goal_route: String,
+
+    /// Number of agents sharing the grid, each with its own start, goal and planner of
+    /// the --algorithm kind; agents are moving obstacles to each other
+    #[arg(long, default_value_t = 1)]
+    pub agents: usize,
+
+    /// How the agents plan around each other: 'independent' (each treats the others
+    /// as moving obstacles), 'whca' (cooperatively, through a shared space-time
+    /// reservation table) or 'cbs' (one optimal joint plan from Conflict-Based
+    /// Search); batch mode runs each of a comma-separated list on the same environments
+    #[arg(long, default_value = "independent")]
+    pub coordination: String,
+
+    /// Steps ahead 'whca' agents reserve their paths for
+    #[arg(long, default_value_t = 8)]
+    pub whca_window: usize,
+
+    /// Constraint tree nodes 'cbs' expands per joint plan before falling back on
+    /// paths planned without regard for each other
+    #[arg(long, default_value_t = 10000)]
+    pub cbs_node_limit: usize,
+
+    /// Add a pursuer that knows the whole map and chases the agent with its own
+    /// planner; the run fails if it catches the agent before the goal
+    #[arg(long, default_value_t = false)]
+    pub pursuer: bool,
+
+    /// Planner the pursuer chases with (any --algorithm name)
+    #[arg(long, default_value = "a_star")]
+    pub pursuer_algorithm: String,
+
+    /// Allow diagonal moves (8-connected, straight moves cost 10 and diagonal ones 14)
+    #[arg(long, default_value_t = false)]
+    pub diagonal_movement: bool,
+
+    /// Use hexagonal cells (axial coordinates, 6 neighbors, unit moves) instead of squares
+    #[arg(long, default_value_t = false)]
+    pub hex_grid: bool,
+
+    /// Make the grid toroidal: moving off one edge enters the cell on the opposite edge
+    #[arg(long, default_value_t = false)]
+    pub wrap_edges: bool,
+
+    /// Fraction of cells (0.0 to 1.0) covered by patches of mud (cost 3) and water (cost 5)
+    #[arg(long, default_value_t = 0.0)]
+    pub terrain_density: f64,
+
+    /// Seed for every random draw of a run (the environment, obstacle timelines and
+    /// sensor noise), so it can be replayed exactly; batch mode draws each
+    /// simulation's seed from it. A random one is picked (and printed) if not given.
+    #[arg(long)]
+    #[serde(skip)] // scenario files hold the environment it generates instead
+    pub seed: Option<u64>,
+
+    /// Print a histogram of the find_path times once a run is over (of every
+    /// algorithm's when comparing)
+    #[arg(long, default_value_t = false)]
+    #[serde(skip)]
+    pub timing_histogram: bool,
+
+    /// Most detailed log events printed to stderr: 'off', 'error', 'warn', 'info' (the
+    /// usual messages), 'debug' (each run's start and end and the span of every
+    /// replan) or 'trace' (the span of every step too)
+    #[arg(long, default_value = "info")]
+    #[serde(skip)]
+    pub log_level: String,
+
+    /// Write log events as JSON lines rather than text
+    #[arg(long, default_value_t = false)]
+    #[serde(skip)]
+    pub log_json: bool,
+
+    /// Serve the runs' metrics for Prometheus at http://ADDRESS/metrics while the
+    /// command runs (e.g. 127.0.0.1:9184); needs the 'metrics' feature
+    #[arg(long)]
+    #[serde(skip)]
+    pub metrics_address: Option<String>,
+
+    /// TOML file of option values by long name (vision_radius = 5), which
+    /// DYNAMIC_PATHFINDING_* environment variables and the command line override
+    #[arg(long)]
+    #[serde(skip)]
+    pub config: Option<String>,
+
+    /// TOML file with a fully specified experiment: options under [settings] (which
+    /// the command line overrides) and the environment to run on under [environment]
+    #[arg(long)]
+    #[serde(skip)]
+    pub scenario: Option<String>,
+
+    /// Milliseconds between drawn steps; set by the run and replay commands
+    #[arg(skip = 50u64)]
+    #[serde(skip)]
+    pub delay_ms: u64,
+
+    /// Whether to run without drawing the grid; only the run and replay commands draw
+    /// it
+    #[arg(skip = true)]
+    #[serde(skip)]
+    pub no_visualization: bool,
+
+    /// Whether to draw the grid without colors; set by the run and replay commands
+    #[arg(skip)]
+    #[serde(skip)]
+    pub no_color: bool,
+
+    /// Whether to draw what the agent knows of the grid beside the grid itself; set
+    /// by the run and replay commands
+    #[arg(skip)]
+    #[serde(skip)]
+    pub show_belief: bool,
+
+    /// Whether to draw the run as a full-screen dashboard; set by the run and replay
+    /// commands
+    #[arg(skip)]
+    #[serde(skip)]
+    pub tui: bool,
+
+    /// How much of a large grid to draw around the agent; set by the run and replay
+    /// commands
+    #[arg(skip)]
+    #[serde(skip)]
+    pub viewport: Viewport,
+
+    /// How many times to shrink the drawn grid each way, in place of drawing its
+    /// cells; set by the run and replay commands
+    #[arg(skip)]
+    #[serde(skip)]
+    pub overview: Option<usize>,
+
+    /// Whether to record the planner's searches and draw them beside the grid; set by
+    /// the run and replay commands
+    #[arg(skip)]
+    #[serde(skip)]
+    pub debug_planner: bool,
+
+    /// Whether the run is one of many, which are never drawn; set by the batch and
+    /// bench commands
+    #[arg(skip)]
+    #[serde(skip)]
+    pub batch_mode: bool,
+
+    /// Suppress most output for faster processing; set by the batch command
+    #[arg(skip)]
+    #[serde(skip)]
+    pub quiet: bool,
+}
+
+// Command line of the simulator: a command for the mode to run in, with the options
+// of every simulation and those of the mode
+#[derive(Parser, Debug, Clone)]
+#[command(author, version, about, long_about = None)]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Command,
+}
+
+// Options may be given more than once (e.g. on the command line after a config
+// file's values); the last one wins
+#[derive(Subcommand, Debug, Clone)]
+pub enum Command {
+    /// Run one algorithm, drawing the grid step by step
+    #[command(args_override_self = true)]
+    Run(RunArgs),
+    /// Run every algorithm on the same environment and compare them
+    #[command(args_override_self = true)]
+    Compare(CompareArgs),
+    /// Run many simulations over swept parameters and write the results to CSV
+    // Boxed: the sweep ranges and output options make it far larger than the others
+    #[command(args_override_self = true)]
+    Batch(Box<BatchArgs>),
+    /// Time the planners over runs on several environments
+    #[command(args_override_self = true)]
+    Bench(BenchArgs),
+    /// Run a scenario file's environment again, drawing the grid step by step
+    #[command(args_override_self = true)]
+    Replay(ReplayArgs),
+    /// Serve a planning API over HTTP: clients upload grids, report obstacles and
+    /// ask for paths, each session keeping its planner between queries
+    #[command(args_override_self = true)]
+    Serve(ServeArgs),
+}
+
+/// Options of the commands that draw the grid
+#[derive(Args, Debug, Clone)]
+pub struct DisplayOptions {
+    /// Milliseconds between drawn steps
     #[arg(long, default_value_t = 50)]
     pub delay_ms: u64,
 
+    /// Run without drawing the grid
     #[arg(long, default_value_t = false)]
     pub no_visualization: bool,
 
-    // New batch simulation parameters
+    /// Draw the grid without colors (as when NO_COLOR is set or the output is not a
+    /// terminal)
     #[arg(long, default_value_t = false)]
-    pub batch_mode: bool,
+    pub no_color: bool,
+
+    /// Draw the agent's belief beside the real grid: the walls and obstacles it
+    /// knows of, the cells it has seen, and the rest as unseen
+    #[arg(long, default_value_t = false)]
+    pub show_belief: bool,
+
+    /// Draw the run as a full-screen dashboard: the grid around the agent, the run's
+    /// statistics, a sparkline of the find_path times and the obstacle groups
+    #[arg(long, default_value_t = false)]
+    pub tui: bool,
+
+    /// How much of a large grid to draw around the agent, with a minimap of the whole
+    /// grid beside it: 'auto' (what fits the terminal), 'full' (the whole grid) or a
+    /// size in cells such as '60x30'
+    #[arg(long, default_value = "auto")]
+    pub viewport: String,
+
+    /// Draw the whole grid shrunk this many times each way, a character for each
+    /// block of cells, in place of the cells themselves
+    #[arg(long)]
+    pub overview: Option<usize>,
+
+    /// Draw what the planner's latest search did beside the grid: the cells A*
+    /// expanded and left open, or D* Lite's expansions, inconsistent cells and costs
+    /// kept from earlier searches
+    #[arg(long, default_value_t = false)]
+    pub debug_planner: bool,
+
+    /// Record the run as an animated GIF at this path, a frame for each step shown
+    /// for --delay-ms; works with --no-visualization too
+    #[arg(long)]
+    pub record_gif: Option<String>,
+
+    /// Pixels on each side of a cell in the recorded GIF
+    #[arg(long, default_value_t = 8)]
+    pub gif_cell_size: u32,
+
+    /// Save a picture of the run at this path once it is over, as SVG or PNG by the
+    /// extension: the final grid, the agent's trajectory, where it replanned and
+    /// where obstacles have been
+    #[arg(long)]
+    pub snapshot: Option<String>,
+
+    /// Pixels on each side of a cell in the snapshot
+    #[arg(long, default_value_t = 16)]
+    pub snapshot_cell_size: u32,
+
+    /// Serve a page drawing the run to browsers at this address, e.g. 127.0.0.1:8080,
+    /// sending it the run's state over a WebSocket; the run waits for a browser
+    #[arg(long)]
+    pub serve: Option<String>,
+}
+
+/// Checkpoints of a long run, to resume it from with the replay command
+#[derive(Args, Debug, Clone)]
+pub struct CheckpointOptions {
+    /// Write a checkpoint of the run to this scenario file every
+    /// --checkpoint-interval steps, over the one before
+    #[arg(long)]
+    pub checkpoint: Option<String>,
+
+    /// Steps between checkpoints
+    #[arg(long, default_value_t = 100)]
+    pub checkpoint_interval: usize,
+}
+
+#[derive(Args, Debug, Clone)]
+pub struct RunArgs {
+    #[command(flatten)]
+    pub config: Config,
+
+    #[command(flatten)]
+    pub display: DisplayOptions,
+
+    #[command(flatten)]
+    pub checkpoints: CheckpointOptions,
+
+    /// Write the options and the generated environment of this run to a scenario file
+    #[arg(long)]
+    pub dump_scenario: Option<String>,
+}
+
+#[derive(Args, Debug, Clone)]
+pub struct CompareArgs {
+    #[command(flatten)]
+    pub config: Config,
 
+    /// Write the options and the generated environment of the comparison to a
+    /// scenario file
+    #[arg(long)]
+    pub dump_scenario: Option<String>,
+
+    /// Step every algorithm's agent in lockstep and draw their grids side by side
+    /// after every step, before the comparison table
+    #[arg(long, default_value_t = false)]
+    pub side_by_side: bool,
+
+    /// Milliseconds between drawn steps with --side-by-side
+    #[arg(long, default_value_t = 50)]
+    pub delay_ms: u64,
+
+    /// Draw the grids of --side-by-side without colors (as when NO_COLOR is set or
+    /// the output is not a terminal)
+    #[arg(long, default_value_t = false)]
+    pub no_color: bool,
+}
+
+#[derive(Args, Debug, Clone)]
+pub struct BatchArgs {
+    #[command(flatten)]
+    pub config: Config,
+
+    #[command(flatten)]
+    pub options: BatchOptions,
+}
+
+/// Options of a batch: how many simulations, the swept ranges and the output
+#[derive(Args, Debug, Clone)]
+pub struct BatchOptions {
     #[arg(long, default_value_t = 10)]
     pub num_simulations: usize,
 
+    /// Smallest grid size swept (default: --grid-size)
+    #[arg(long)]
+    pub min_grid_size: Option<usize>,
+
+    /// Largest grid size swept (default: --grid-size)
+    #[arg(long)]
+    pub max_grid_size: Option<usize>,
+
+    /// Difference between grid sizes swept
+    #[arg(long, default_value_t = 1)]
+    pub grid_size_step: usize,
+
     #[arg(long, default_value_t = 10)]
     pub min_walls: usize,
 
@@ -42,13 +634,586 @@ pub struct Config {
     #[arg(long, default_value_t = 15)]
     pub max_obstacles: usize,
 
+    /// Smallest obstacle interval swept (default: --obstacle-interval)
+    #[arg(long)]
+    pub min_obstacle_interval: Option<usize>,
+
+    /// Largest obstacle interval swept (default: --obstacle-interval)
+    #[arg(long)]
+    pub max_obstacle_interval: Option<usize>,
+
+    /// Smallest obstacle persistence swept (default: --obstacle-persistence)
+    #[arg(long)]
+    pub min_obstacle_persistence: Option<usize>,
+
+    /// Largest obstacle persistence swept (default: --obstacle-persistence)
+    #[arg(long)]
+    pub max_obstacle_persistence: Option<usize>,
+
     #[arg(long, default_value_t = 300)]
     pub timeout_seconds: u64,
 
-    #[arg(long, default_value = "simulation_results.csv")]
-    pub output_file: String,
+    /// File for the results (default: simulation_results.csv, .json, .jsonl or
+    /// .parquet, by --output-format); '-' streams them to stdout, with every other
+    /// message on stderr
+    #[arg(long, visible_alias = "output")]
+    pub output_file: Option<String>,
+
+    /// Format of the results: 'csv' (a row per result), 'json' (an array of objects),
+    /// 'jsonl' (an object per line) or 'parquet' (typed columns; needs the 'parquet'
+    /// feature)
+    #[arg(long, default_value = "csv")]
+    pub output_format: String,
+
+    /// Results collected before they are written out to the file
+    #[arg(long, default_value_t = 10)]
+    pub flush_every: usize,
+
+    /// Columns of the results to write, comma-separated: column names and the presets
+    /// 'all', 'core', 'timing', 'multi_agent' and 'search'. One starting with '-' is
+    /// left out, of every column when the list only leaves columns out
+    #[arg(long, default_value = "all")]
+    pub columns: String,
+
+    /// TOML file of further options to sweep, each with a list or a { min, max, step }
+    /// range of values; every combination is run
+    #[arg(long)]
+    pub sweep: Option<String>,
+
+    /// Simulations run at once, on as many threads; 0 runs one per CPU core
+    #[arg(long, default_value_t = 1)]
+    pub jobs: usize,
+
+    /// Break the summary of every algorithm down by swept configuration
+    #[arg(long, default_value_t = false)]
+    pub summary_by_configuration: bool,
+
+    /// Compare every two algorithms in the summary with paired significance tests on
+    /// the environments they both ran
+    #[arg(long, default_value_t = false)]
+    pub significance_test: bool,
+
+    /// Write the summary to this file as a report with tables and charts: an HTML page,
+    /// or Markdown when the name ends in .md
+    #[arg(long)]
+    pub report: Option<String>,
+
+    /// Continue an interrupted batch: keep the results already in the CSV or JSON Lines
+    /// output file and run only the simulations missing from it
+    #[arg(long, default_value_t = false)]
+    pub resume: bool,
 
     /// Suppress most output for faster processing
     #[arg(long, default_value_t = false)]
     pub quiet: bool,
 }
+
+#[derive(Args, Debug, Clone)]
+pub struct BenchArgs {
+    #[command(flatten)]
+    pub config: Config,
+
+    /// Environments to time the planners on, drawn from --seed (of each workload
+    /// with --suite)
+    #[arg(long, default_value_t = 10)]
+    pub runs: usize,
+
+    /// Time the planners on the standard workloads, grids of 25 to 200 cells a side
+    /// drawn from fixed seeds, instead of environments of the options
+    #[arg(long, default_value_t = false)]
+    pub suite: bool,
+
+    /// Timed runs of each environment of the suite, after an untimed warm-up run
+    #[arg(long, default_value_t = 5)]
+    pub samples: usize,
+
+    /// Time this many replans of one planner on each environment of the replan
+    /// workload, 200x200 grids drawn from a fixed seed, as the agent moves a cell
+    /// along its latest path between replans
+    #[arg(long, value_name = "REPLANS", conflicts_with = "suite")]
+    pub replans: Option<usize>,
+}
+
+#[derive(Args, Debug, Clone)]
+pub struct ReplayArgs {
+    /// Scenario file with the environment to run on, such as one --dump-scenario
+    /// wrote; a checkpoint resumes where it was written
+    #[arg(value_name = "SCENARIO")]
+    pub file: String,
+
+    #[command(flatten)]
+    pub config: Config,
+
+    #[command(flatten)]
+    pub display: DisplayOptions,
+
+    #[command(flatten)]
+    pub checkpoints: CheckpointOptions,
+}
+
+#[derive(Args, Debug, Clone)]
+pub struct ServeArgs {
+    // Options of the sessions' planners (their priority queue, heuristic and so on);
+    // each session names its algorithm, D* Lite when it does not
+    #[command(flatten)]
+    pub config: Config,
+
+    /// Address to serve the planning API at; port 0 picks a free port
+    #[arg(long, default_value = "127.0.0.1:7878")]
+    pub listen: String,
+}
+
+impl Command {
+    /// Options of the simulations the command runs
+    pub fn config(&self) -> &Config {
+        match self {
+            Command::Run(args) => &args.config,
+            Command::Compare(args) => &args.config,
+            Command::Batch(args) => &args.config,
+            Command::Bench(args) => &args.config,
+            Command::Replay(args) => &args.config,
+            Command::Serve(args) => &args.config,
+        }
+    }
+
+    pub fn config_mut(&mut self) -> &mut Config {
+        match self {
+            Command::Run(args) => &mut args.config,
+            Command::Compare(args) => &mut args.config,
+            Command::Batch(args) => &mut args.config,
+            Command::Bench(args) => &mut args.config,
+            Command::Replay(args) => &mut args.config,
+            Command::Serve(args) => &mut args.config,
+        }
+    }
+
+    /// Scenario file the command reads: the one replayed, or `--scenario`
+    pub fn scenario(&self) -> Option<&str> {
+        match self {
+            Command::Replay(args) => Some(&args.file),
+            command => command.config().scenario.as_deref(),
+        }
+    }
+
+    /// Check the command's options go together before anything is set up, so a
+    /// mistake is reported with what to change rather than partway through a run
+    pub fn validate(&self, registry: &AlgorithmRegistry) -> Result<(), ConfigError> {
+        match self {
+            Command::Run(args) => args.config.validate(registry),
+            Command::Replay(args) => args.config.validate(registry),
+            // Every algorithm is compared, whatever --algorithm says
+            Command::Compare(args) => {
+                args.config.validate_grid()?;
+                args.config.validate_pursuer(registry)
+            }
+            Command::Batch(args) => {
+                args.config.validate_algorithms(registry, true)?;
+                args.options.validate(&args.config)
+            }
+            Command::Bench(args) => {
+                args.config.validate_algorithms(registry, true)?;
+                match args.suite {
+                    // The suite brings its own grids
+                    true => Ok(()),
+                    false => args.config.validate_grid(),
+                }
+            }
+            // Each session names its planner and uploads its grid
+            Command::Serve(_) => Ok(()),
+        }
+    }
+}
+
+impl Cli {
+    /// Options in layers of arguments, each overriding the ones before: the defaults,
+    /// then every layer in order, then the command line `args` (program name and
+    /// command first). Options of other commands in the layers are left out.
+    pub fn layered(layers: &[Vec<String>], args: &[String]) -> Result<Self, String> {
+        let invalid = |e: clap::Error| format!("Invalid options: {}", e);
+        let [program, name, rest @ ..] = args else {
+            return Cli::try_parse_from(args).map_err(invalid);
+        };
+        let command = Cli::command();
+        let Some(subcommand) = command.find_subcommand(name) else {
+            return Cli::try_parse_from(args).map_err(invalid);
+        };
+        let mut command_line = vec![program.clone(), name.clone()];
+        for arg in layers.iter().flatten() {
+            let long = arg.trim_start_matches("--");
+            let long = long.split_once('=').map_or(long, |(long, _)| long);
+            if find_option(subcommand, long).is_some() {
+                command_line.push(arg.clone());
+            } else if find_option(&command, long).is_none() {
+                return Err(format!("Invalid options: unknown option '--{}'", long));
+            }
+        }
+        command_line.extend(rest.iter().cloned());
+        Cli::try_parse_from(command_line).map_err(invalid)
+    }
+
+    /// Arguments for the option values of a TOML table (`vision_radius = 5` gives
+    /// `--vision-radius=5`; switches set to true are turned on)
+    pub fn table_args(table: &toml::Table) -> Result<Vec<String>, String> {
+        let mut args = Vec::with_capacity(table.len());
+        for (key, value) in table {
+            let option = format!("--{}", key.replace('_', "-"));
+            let value = match value {
+                toml::Value::Boolean(true) => {
+                    args.push(option);
+                    continue;
+                }
+                toml::Value::Boolean(false) => continue,
+                toml::Value::String(text) => text.clone(),
+                toml::Value::Integer(number) => number.to_string(),
+                toml::Value::Float(number) => number.to_string(),
+                _ => return Err(format!("'{}' is not a single value", key)),
+            };
+            args.push(format!("{}={}", option, value));
+        }
+        Ok(args)
+    }
+
+    /// Arguments for the option values of a `--config` file
+    pub fn file_args(path: &str) -> Result<Vec<String>, String> {
+        let text = std::fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read config file '{}': {}", path, e))?;
+        let table: toml::Table =
+            toml::from_str(&text).map_err(|e| format!("Invalid config file '{}': {}", path, e))?;
+        Self::table_args(&table).map_err(|e| format!("Invalid config file '{}': {}", path, e))
+    }
+
+    /// Arguments for the options set by `DYNAMIC_PATHFINDING_*` environment
+    /// variables among `vars`; a switch is turned on by `true` or `1`
+    pub fn environment_args(
+        vars: impl IntoIterator<Item = (String, String)>,
+    ) -> Result<Vec<String>, String> {
+        let command = Cli::command();
+        let mut vars: Vec<(String, String)> = vars
+            .into_iter()
+            .filter(|(name, _)| name.starts_with(ENV_PREFIX))
+            .collect();
+        vars.sort();
+        let mut args = Vec::with_capacity(vars.len());
+        for (name, value) in vars {
+            let option = name[ENV_PREFIX.len()..].to_lowercase().replace('_', "-");
+            let Some(arg) = find_option(&command, &option) else {
+                return Err(format!(
+                    "{} does not name an option (no --{})",
+                    name, option
+                ));
+            };
+            if arg.get_action().takes_values() {
+                args.push(format!("--{}={}", option, value));
+                continue;
+            }
+            match value.as_str() {
+                "true" | "1" => args.push(format!("--{}", option)),
+                "false" | "0" | "" => {}
+                _ => return Err(format!("{} must be true or false", name)),
+            }
+        }
+        Ok(args)
+    }
+}
+
+/// The option with the given long name of a command or any of its subcommands
+fn find_option<'a>(command: &'a clap::Command, long: &str) -> Option<&'a clap::Arg> {
+    command
+        .get_arguments()
+        .find(|arg| arg.get_long() == Some(long))
+        .or_else(|| {
+            command
+                .get_subcommands()
+                .find_map(|subcommand| find_option(subcommand, long))
+        })
+}
+
+/// The options with every one at its default, as with none on the command line
+impl Default for Config {
+    fn default() -> Self {
+        Config::parse_from([env!("CARGO_PKG_NAME")])
+    }
+}
+
+impl Config {
+    /// Number of grid columns: `--grid-width`, or `--grid-size` when not given
+    pub fn width(&self) -> usize {
+        self.grid_width.unwrap_or(self.grid_size)
+    }
+
+    /// Number of grid rows: `--grid-height`, or `--grid-size` when not given
+    pub fn height(&self) -> usize {
+        self.grid_height.unwrap_or(self.grid_size)
+    }
+
+    /// Number of floors, at least 1
+    pub fn floors(&self) -> usize {
+        self.floors.max(1)
+    }
+
+    /// These options with some replaced by settings by long name
+    /// (`vision_radius = 5`). The options are written out as a scenario file's
+    /// settings are, the settings put over them, and parsed again.
+    pub fn with_settings(&self, settings: toml::Table) -> Result<Config, String> {
+        let mut table = toml::Table::try_from(self)
+            .map_err(|e| format!("Failed to write the options: {}", e))?;
+        table.extend(settings);
+        let mut command_line = vec![env!("CARGO_PKG_NAME").to_string()];
+        command_line.extend(Cli::table_args(&table)?);
+        let mut config =
+            Config::try_parse_from(command_line).map_err(|e| format!("Invalid options: {}", e))?;
+        // The options the settings leave out
+        config.seed = self.seed;
+        config.config = self.config.clone();
+        config.scenario = self.scenario.clone();
+        config.delay_ms = self.delay_ms;
+        config.no_visualization = self.no_visualization;
+        config.batch_mode = self.batch_mode;
+        config.quiet = self.quiet;
+        Ok(config)
+    }
+
+    /// Check the options of a single run go together: a grid with room for the
+    /// walls and obstacles, and algorithms the registry has
+    pub fn validate(&self, registry: &AlgorithmRegistry) -> Result<(), ConfigError> {
+        self.validate_algorithms(registry, false)?;
+        self.validate_grid()
+    }
+
+    /// Check `--algorithm` (`all` too, where every algorithm can be run) and the
+    /// pursuer's algorithm name registered algorithms
+    pub(crate) fn validate_algorithms(
+        &self,
+        registry: &AlgorithmRegistry,
+        all: bool,
+    ) -> Result<(), ConfigError> {
+        if self.algorithm == "all" && !all {
+            return Err(ConfigError::Invalid(
+                "--algorithm all runs every algorithm only with the compare, batch and bench \
+                 commands; name one algorithm to run"
+                    .to_string(),
+            ));
+        }
+        if self.algorithm != "all" && !registry.contains(&self.algorithm) {
+            return Err(ConfigError::UnknownAlgorithm(self.algorithm.clone()));
+        }
+        self.validate_pursuer(registry)
+    }
+
+    fn validate_pursuer(&self, registry: &AlgorithmRegistry) -> Result<(), ConfigError> {
+        if self.pursuer && !registry.contains(&self.pursuer_algorithm) {
+            return Err(ConfigError::UnknownAlgorithm(
+                self.pursuer_algorithm.clone(),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Check the grid has room for a start and a goal, and the generated walls and
+    /// each group of random obstacles fit on it
+    pub(crate) fn validate_grid(&self) -> Result<(), ConfigError> {
+        let (width, height) = (self.width(), self.height());
+        if width < 2 || height < 2 {
+            return Err(format!(
+                "The grid is {}x{}: --grid-size (or --grid-width and --grid-height) must be \
+                 at least 2 to fit a start and a goal",
+                width, height
+            )
+            .into());
+        }
+        if self.obstacle_interval == 0 || self.obstacle_persistence == 0 {
+            return Err(
+                "--obstacle-interval and --obstacle-persistence must be at least 1"
+                    .to_string()
+                    .into(),
+            );
+        }
+        if self.generates_walls() {
+            check_fits("--num-walls", self.num_walls, width, height, self.floors())?;
+        }
+        if self.obstacle_script.is_none() {
+            check_fits(
+                "--num-obstacles",
+                self.num_obstacles,
+                width,
+                height,
+                self.floors(),
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Whether `--num-walls` walls are placed, rather than the walls coming from a
+    /// map or a maze
+    fn generates_walls(&self) -> bool {
+        self.map_file.is_none() && self.map_image.is_none() && self.map_style == "random"
+    }
+}
+
+/// Check `count` of what `option` places fits on a grid, leaving the start and the
+/// goal free
+fn check_fits(
+    option: &str,
+    count: usize,
+    width: usize,
+    height: usize,
+    floors: usize,
+) -> Result<(), ConfigError> {
+    let room = (width * height * floors).saturating_sub(2);
+    if count > room {
+        return Err(format!(
+            "{} {} does not fit a {}x{} grid{}: at most {} leave the start and goal free",
+            option,
+            count,
+            width,
+            height,
+            match floors {
+                1 => String::new(),
+                floors => format!(" of {} floors", floors),
+            },
+            room
+        )
+        .into());
+    }
+    Ok(())
+}
+
+impl BatchOptions {
+    /// File the results are written to
+    pub fn output_file(&self) -> String {
+        self.output_file.clone().unwrap_or_else(|| {
+            let extension = match self.output_format.as_str() {
+                "json" | "jsonl" | "parquet" => self.output_format.as_str(),
+                _ => "csv",
+            };
+            format!("simulation_results.{}", extension)
+        })
+    }
+
+    /// Whether the results go to stdout (`--output-file -`) rather than to a file
+    pub fn streams_results(&self) -> bool {
+        self.output_file.as_deref() == Some("-")
+    }
+
+    /// Grid sizes swept, around the options' --grid-size
+    pub fn grid_size_range(&self, config: &Config) -> StepBy<RangeInclusive<usize>> {
+        (self.min_grid_size.unwrap_or(config.grid_size)
+            ..=self.max_grid_size.unwrap_or(config.grid_size))
+            .step_by(self.grid_size_step.max(1))
+    }
+
+    /// Whether more than one grid size is swept
+    pub fn sweeps_grid_size(&self, config: &Config) -> bool {
+        self.grid_size_range(config).nth(1).is_some()
+    }
+
+    /// Obstacle intervals swept, around the options' --obstacle-interval
+    pub fn obstacle_interval_range(&self, config: &Config) -> RangeInclusive<usize> {
+        self.min_obstacle_interval
+            .unwrap_or(config.obstacle_interval)
+            ..=self
+                .max_obstacle_interval
+                .unwrap_or(config.obstacle_interval)
+    }
+
+    /// Obstacle persistences swept, around the options' --obstacle-persistence
+    pub fn obstacle_persistence_range(&self, config: &Config) -> RangeInclusive<usize> {
+        self.min_obstacle_persistence
+            .unwrap_or(config.obstacle_persistence)
+            ..=self
+                .max_obstacle_persistence
+                .unwrap_or(config.obstacle_persistence)
+    }
+
+    /// Check the sweep makes sense: every range runs from its smallest to its largest
+    /// value, and the most walls and obstacles swept fit on the smallest grid. Walls
+    /// and grid sizes a map or maze sets, and obstacles a script sets, are not swept.
+    pub fn validate(&self, config: &Config) -> Result<(), ConfigError> {
+        if self.num_simulations == 0 {
+            return Err("--num-simulations must be at least 1".to_string().into());
+        }
+        if self.grid_size_step == 0 {
+            return Err("--grid-size-step must be at least 1".to_string().into());
+        }
+        let map = config.map_file.is_some() || config.map_image.is_some();
+        let scripted = config.obstacle_script.is_some();
+        let smallest = self.min_grid_size.unwrap_or(config.grid_size);
+        let largest = self.max_grid_size.unwrap_or(config.grid_size);
+        let intervals = self.obstacle_interval_range(config);
+        let persistences = self.obstacle_persistence_range(config);
+        let ranges = [
+            ("grid-size", smallest, largest, !map),
+            (
+                "walls",
+                self.min_walls,
+                self.max_walls,
+                config.generates_walls(),
+            ),
+            (
+                "obstacles",
+                self.min_obstacles,
+                self.max_obstacles,
+                !scripted,
+            ),
+            (
+                "obstacle-interval",
+                *intervals.start(),
+                *intervals.end(),
+                !scripted,
+            ),
+            (
+                "obstacle-persistence",
+                *persistences.start(),
+                *persistences.end(),
+                !scripted,
+            ),
+        ];
+        for (name, min, max, swept) in ranges {
+            if swept && min > max {
+                return Err(format!(
+                    "--min-{} {} is above --max-{} {}; swap them or raise --max-{}",
+                    name, min, name, max, name
+                )
+                .into());
+            }
+        }
+        if !scripted && (*intervals.start() == 0 || *persistences.start() == 0) {
+            return Err(
+                "Obstacle intervals and persistences swept must be at least 1"
+                    .to_string()
+                    .into(),
+            );
+        }
+        let (width, height) = match !map && self.sweeps_grid_size(config) {
+            true => (smallest, smallest),
+            false => (config.width(), config.height()),
+        };
+        if width < 2 || height < 2 {
+            return Err(format!(
+                "The smallest grid swept is {}x{}: grids must be at least 2 across to fit a \
+                 start and a goal",
+                width, height
+            )
+            .into());
+        }
+        if config.generates_walls() {
+            check_fits(
+                "--max-walls",
+                self.max_walls,
+                width,
+                height,
+                config.floors(),
+            )?;
+        }
+        if !scripted {
+            check_fits(
+                "--max-obstacles",
+                self.max_obstacles,
+                width,
+                height,
+                config.floors(),
+            )?;
+        }
+        Ok(())
+    }
+}
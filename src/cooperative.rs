@@ -0,0 +1,248 @@
+use crate::algorithms::search_space::{GridSpace, SearchSpace};
+use crate::config::Config;
+use crate::grid::{Grid, Position};
+use pathfinding::prelude::{astar, dijkstra_all};
+use std::collections::{HashMap, HashSet};
+
+/// How the agents of a multi-agent run plan around each other.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Coordination {
+    /// Every agent plans on its own and treats the others as moving obstacles.
+    Independent,
+    /// Windowed Hierarchical Cooperative A*: the agents plan one after the other every
+    /// step, each `window` steps ahead through the cells and moves the agents before
+    /// it have reserved.
+    Whca { window: usize },
+    /// Conflict-Based Search: one optimal joint plan for every agent at once, made
+    /// again whenever any agent needs a new path, expanding up to `node_limit`
+    /// constraint tree nodes.
+    Cbs { node_limit: usize },
+}
+
+impl Coordination {
+    /// Parses a coordination name as given on the command line, with the window
+    /// and node limit of the options.
+    pub fn from_name(name: &str, config: &Config) -> Result<Self, String> {
+        match name {
+            "independent" => Ok(Coordination::Independent),
+            "whca" if config.whca_window == 0 => {
+                Err("--whca-window must be at least 1".to_string())
+            }
+            "whca" => Ok(Coordination::Whca {
+                window: config.whca_window,
+            }),
+            "cbs" if config.cbs_node_limit == 0 => {
+                Err("--cbs-node-limit must be at least 1".to_string())
+            }
+            "cbs" => Ok(Coordination::Cbs {
+                node_limit: config.cbs_node_limit,
+            }),
+            _ => Err(format!(
+                "Unknown coordination: '{}' (expected 'independent', 'whca' or 'cbs')",
+                name
+            )),
+        }
+    }
+
+    /// Parses the comma-separated list of coordination names of `--coordination`.
+    pub fn from_names(config: &Config) -> Result<Vec<Self>, String> {
+        config
+            .coordination
+            .split(',')
+            .map(|name| Self::from_name(name.trim(), config))
+            .collect()
+    }
+
+    /// Coordination for `--coordination` in a single run
+    pub fn from_config(config: &Config) -> Result<Self, String> {
+        let coordination = match Self::from_names(config)?[..] {
+            [coordination] => coordination,
+            _ => return Err("Only batch mode can compare several coordinations".to_string()),
+        };
+        if coordination != Coordination::Independent && config.smooth_path {
+            // Smoothing would skip the steps the plans are timed by
+            return Err(format!(
+                "--smooth-path cannot be combined with --coordination {}",
+                coordination.name()
+            ));
+        }
+        Ok(coordination)
+    }
+
+    /// Name of the coordination as given on the command line.
+    pub fn name(self) -> &'static str {
+        match self {
+            Coordination::Independent => "independent",
+            Coordination::Whca { .. } => "whca",
+            Coordination::Cbs { .. } => "cbs",
+        }
+    }
+}
+
+/// A node of the space-time search: a cell some steps into the window, or the end
+/// of the search past the window (or on a goal the agent can stay on)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum Node {
+    At(Position, usize),
+    Done,
+}
+
+/// Who holds which cell at which step of the window, and which moves are taken, for
+/// cooperative planning. Agents are numbered in planning order, this run's own agent
+/// first; step 0 is now.
+#[derive(Debug, Clone)]
+pub struct ReservationTable {
+    pub window: usize,
+    cells: HashMap<(Position, usize), usize>,
+    /// Moves other agents may not make, as (from, to, step they start on): the
+    /// reverse of every reserved move, so no two agents swap cells
+    moves: HashSet<(Position, Position, usize)>,
+    /// Agents that found every way taken last step. They keep their cell for the
+    /// whole next window, so the agents before them plan around them instead of
+    /// waiting for them to make way.
+    stuck: HashSet<usize>,
+}
+
+impl ReservationTable {
+    pub fn new(window: usize) -> Self {
+        ReservationTable {
+            window,
+            cells: HashMap::new(),
+            moves: HashSet::new(),
+            stuck: HashSet::new(),
+        }
+    }
+
+    /// Start a new step with the agents standing on the given cells: each keeps its
+    /// cell for the next step, since the agents move one after the other, and the
+    /// ones that have stopped or are stuck keep it for the whole window
+    pub fn reset(&mut self, agents: &[(Position, bool)]) {
+        self.cells.clear();
+        self.moves.clear();
+        for (agent, &(pos, stopped)) in agents.iter().enumerate() {
+            let last = if stopped || self.stuck.remove(&agent) {
+                self.window
+            } else {
+                1
+            };
+            for step in 1..=last {
+                self.cells.insert((pos, step), agent);
+            }
+        }
+    }
+
+    /// Whether `pos` is free for `agent` on the given step
+    fn is_free(&self, pos: Position, step: usize, agent: usize) -> bool {
+        self.cells
+            .get(&(pos, step))
+            .is_none_or(|&owner| owner == agent)
+    }
+
+    /// Plan `agent`'s next `window` steps from `start` towards the closest of `goals`,
+    /// around the walls, the `obstacles` and the reservations of the agents planned
+    /// before it, and reserve the steps planned. Past the window the true distance
+    /// to the goals ignoring other agents (the hierarchical part) estimates the rest.
+    /// The path includes waits; when every way is taken for now it is a single wait.
+    /// Returns `None` when no goal can be reached at all.
+    pub fn plan(
+        &mut self,
+        grid: &Grid,
+        start: Position,
+        goals: &[Position],
+        obstacles: &HashSet<Position>,
+        agent: usize,
+    ) -> Option<Vec<Position>> {
+        let distances = distances_to(grid, goals, obstacles);
+        let distance = |pos: Position| distances.get(&pos).copied();
+        distance(start)?;
+
+        let path = astar(
+            &Node::At(start, 0),
+            |&node| {
+                let Node::At(pos, step) = node else {
+                    return Vec::new();
+                };
+                if step == self.window {
+                    return distance(pos)
+                        .map(|left| (Node::Done, left))
+                        .into_iter()
+                        .collect();
+                }
+                // Agents stop on the first goal they get to, which ends the search if
+                // they can keep it for the rest of the window
+                if goals.contains(&pos) {
+                    return if (step + 1..=self.window).all(|later| self.is_free(pos, later, agent))
+                    {
+                        vec![(Node::Done, 0)]
+                    } else {
+                        Vec::new()
+                    };
+                }
+                grid.get_neighbors(&pos)
+                    .into_iter()
+                    .chain([pos])
+                    .filter(|&next| {
+                        grid.can_move(pos, next, |cell| obstacles.contains(&cell))
+                            && distance(next).is_some()
+                            && self.is_free(next, step + 1, agent)
+                            && !self.moves.contains(&(pos, next, step))
+                    })
+                    .map(|next| (Node::At(next, step + 1), grid.move_cost(pos, next)))
+                    .collect()
+            },
+            |&node| match node {
+                Node::At(pos, _) => distance(pos).unwrap_or(0),
+                Node::Done => 0,
+            },
+            |&node| node == Node::Done,
+        );
+
+        let Some((nodes, _)) = path else {
+            self.stuck.insert(agent);
+            self.cells.insert((start, 1), agent);
+            return Some(vec![start, start]);
+        };
+        let path: Vec<Position> = nodes
+            .into_iter()
+            .filter_map(|node| match node {
+                Node::At(pos, _) => Some(pos),
+                Node::Done => None,
+            })
+            .collect();
+        for (step, cells) in path.windows(2).enumerate() {
+            self.cells.insert((cells[1], step + 1), agent);
+            self.moves.insert((cells[1], cells[0], step));
+        }
+        // An agent that gets to its goal inside the window stays there
+        let last = *path.last().unwrap_or(&start);
+        for step in path.len()..=self.window {
+            self.cells.insert((last, step), agent);
+        }
+        Some(path)
+    }
+}
+
+/// Cheapest cost from every cell that can reach one of `goals` to the closest of them,
+/// around the walls and `obstacles` (the heuristic of every space-time search)
+pub fn distances_to(
+    grid: &Grid,
+    goals: &[Position],
+    obstacles: &HashSet<Position>,
+) -> HashMap<Position, u32> {
+    let space = GridSpace::new(grid, obstacles);
+    // Searched backwards from a virtual node next to every goal
+    dijkstra_all(&None, |&node: &Option<Position>| match node {
+        None => goals
+            .iter()
+            .map(|&goal| (Some(goal), 0))
+            .collect::<Vec<_>>(),
+        Some(pos) => space
+            .predecessors(pos)
+            .into_iter()
+            .filter_map(|from| space.cost(from, pos).map(|cost| (Some(from), cost)))
+            .collect(),
+    })
+    .into_iter()
+    .filter_map(|(node, (_, cost))| Some((node?, cost)))
+    .collect()
+}
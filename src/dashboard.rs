@@ -0,0 +1,446 @@
+use crate::grid::{GridView, Position, Window};
+use crate::multi_agent::MAX_STUCK_ATTEMPTS;
+use crate::observer::{Replan, SimulationObserver};
+use crate::simulation::{Failure, Simulation, StepOutcome};
+use crate::terminal_renderer::{read_control, Control, RawMode};
+use crossterm::event::{self, Event, KeyEventKind};
+use crossterm::terminal::{EnterAlternateScreen, LeaveAlternateScreen};
+use crossterm::{cursor, execute};
+use ratatui::backend::CrosstermBackend;
+use ratatui::layout::{Constraint, Layout, Rect};
+use ratatui::style::{Style, Stylize};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, Paragraph, Row, Sparkline, Table, Wrap};
+use ratatui::{Frame, Terminal};
+use std::collections::HashSet;
+use std::io::{self, IsTerminal, Stdout};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Width of the column of panels right of the grid, borders included
+const SIDE_WIDTH: u16 = 44;
+
+/// Smallest terminal the dashboard is drawn in, in columns and rows
+const MIN_SIZE: (u16, u16) = (SIDE_WIDTH + 12, 16);
+
+/// Height of the find_path sparkline panel, borders included
+const SPARKLINE_HEIGHT: u16 = 4;
+
+/// Draws the run as a full-screen dashboard (`--tui`) in place of the
+/// `TerminalRenderer`, with ratatui widgets: a grid panel that follows the agent, so
+/// grids larger than the terminal stay watchable, the statistics of the run, a
+/// sparkline of the find_path times and a table of the obstacle groups, all redrawn
+/// after every move.
+///
+/// It takes the renderer's keys: space pauses and resumes the run, n shows the next
+/// step and pauses again, + and - double and halve the delay, and q (or Ctrl+C)
+/// stops the run. The last frame stays up until a key is pressed.
+#[derive(Default)]
+pub struct Dashboard {
+    paused: bool,
+    /// Delay set with + and -, in place of `--delay-ms`
+    delay_ms: Option<u64>,
+    quit: bool,
+    /// Cells the agent has stood on, in the order it came to them
+    trail: Vec<Position>,
+    /// What happened last, shown under the panels
+    event: String,
+    /// The screen, while the dashboard has it
+    terminal: Option<Terminal<CrosstermBackend<Stdout>>>,
+    /// Raw mode while the dashboard has the screen, when there is a keyboard
+    raw_mode: Option<RawMode>,
+}
+
+impl Dashboard {
+    /// Take over the screen: the alternate screen, without the cursor, reading keys
+    fn enter(&mut self) {
+        if self.terminal.is_some() {
+            return;
+        }
+        let _ = execute!(io::stdout(), EnterAlternateScreen);
+        let Ok(mut terminal) = Terminal::new(CrosstermBackend::new(io::stdout())) else {
+            let _ = execute!(io::stdout(), LeaveAlternateScreen);
+            return;
+        };
+        let _ = terminal.hide_cursor();
+        if io::stdin().is_terminal() {
+            self.raw_mode = RawMode::enable().ok();
+        }
+        self.terminal = Some(terminal);
+    }
+
+    /// Give the screen back as it was before the run
+    fn leave(&mut self) {
+        if self.terminal.take().is_none() {
+            return;
+        }
+        self.raw_mode = None;
+        let _ = execute!(io::stdout(), cursor::Show, LeaveAlternateScreen);
+    }
+
+    /// Draw the dashboard over the screen
+    fn draw(&mut self, simulation: &Simulation) {
+        // Taken while drawing, as the frame is drawn from the rest of the dashboard
+        let Some(mut terminal) = self.terminal.take() else {
+            return;
+        };
+        let _ = terminal.draw(|frame| self.render(frame, simulation));
+        self.terminal = Some(terminal);
+    }
+
+    /// Lay the panels out over the frame: the grid panel on the left, the other panels
+    /// stacked on the right, and the footer under them
+    fn render(&self, frame: &mut Frame, simulation: &Simulation) {
+        let area = frame.area();
+        if area.width < MIN_SIZE.0 || area.height < MIN_SIZE.1 {
+            let message = format!(
+                "The dashboard needs a terminal of at least {}x{} - q stops the run",
+                MIN_SIZE.0, MIN_SIZE.1
+            );
+            frame.render_widget(Paragraph::new(message).wrap(Wrap { trim: true }), area);
+            return;
+        }
+        let [panels, footer] =
+            Layout::vertical([Constraint::Min(0), Constraint::Length(1)]).areas(area);
+        let [grid, side] =
+            Layout::horizontal([Constraint::Min(0), Constraint::Length(SIDE_WIDTH)]).areas(panels);
+
+        let statistics = self.statistics(simulation);
+        let [statistics_area, sparkline_area, groups_area] = Layout::vertical([
+            Constraint::Length(statistics.len() as u16 + 2),
+            Constraint::Length(SPARKLINE_HEIGHT),
+            Constraint::Min(3),
+        ])
+        .areas(side);
+
+        self.render_grid(frame, simulation, grid);
+        frame.render_widget(
+            Paragraph::new(statistics.into_iter().map(Line::from).collect::<Vec<_>>())
+                .block(Block::bordered().title(" Statistics ")),
+            statistics_area,
+        );
+        render_sparkline(frame, simulation, sparkline_area);
+        render_obstacle_groups(frame, simulation, groups_area);
+        frame.render_widget(
+            Paragraph::new(format!(
+                " space pause | n step | + - delay | q quit | {}",
+                self.event
+            ))
+            .reversed(),
+            footer,
+        );
+    }
+
+    /// The grid panel: the cells around the agent on its floor, as many as fit
+    fn render_grid(&self, frame: &mut Frame, simulation: &Simulation, area: Rect) {
+        let grid = &simulation.grid;
+        let agent = simulation.agent.position;
+        let (progress, _) = simulation.agent.get_path_progress();
+        // Each cell takes two columns, after the borders and the row numbers
+        let window = Window::around(
+            agent,
+            (area.width as usize).saturating_sub(6) / 2,
+            (area.height as usize).saturating_sub(3),
+            grid,
+        );
+        let view = GridView {
+            agent: Some(agent),
+            path: simulation
+                .agent
+                .get_current_path()
+                .map_or(&[], |path| &path[progress.min(path.len())..]),
+            visited: &self.trail,
+            seen: None,
+            marks: None,
+            window: Some(window),
+            color: !simulation.config().no_color && std::env::var_os("NO_COLOR").is_none(),
+        };
+        let path: HashSet<Position> = view.path.iter().copied().collect();
+        let visited: HashSet<Position> = view.visited.iter().copied().collect();
+
+        let mut lines = vec![Line::from(format!(
+            "   {}",
+            window
+                .columns()
+                .map(|x| format!("{:2}", x % 10))
+                .collect::<String>()
+        ))];
+        for y in window.rows() {
+            let mut spans = vec![Span::raw(format!("{:3} ", y))];
+            for x in window.columns() {
+                let (char, color) =
+                    grid.glyph(Position { x, y, z: agent.z }, &view, (&path, &visited));
+                let style = match view.color {
+                    true => Style::new().fg(color.into()),
+                    false => Style::new(),
+                };
+                spans.push(Span::styled(format!("{} ", char), style));
+            }
+            lines.push(Line::from(spans));
+        }
+
+        let mut title = format!(" Grid {}x{}", grid.width, grid.height);
+        if grid.floors > 1 {
+            title.push_str(&format!(", floor {}", agent.z));
+        }
+        if window.is_cropped(grid) {
+            title.push_str(&format!(
+                " - columns {}-{}, rows {}-{}",
+                window.left,
+                window.columns().end - 1,
+                window.top,
+                window.rows().end - 1
+            ));
+        }
+        title.push(' ');
+        frame.render_widget(
+            Paragraph::new(lines).block(Block::bordered().title(title)),
+            area,
+        );
+    }
+
+    /// Lines of the statistics panel
+    fn statistics(&self, simulation: &Simulation) -> Vec<String> {
+        let config = simulation.config();
+        let agent = &simulation.agent;
+        let grid = &simulation.grid;
+        let timing_data = simulation.timing_data();
+        let (path_progress, path_total) = agent.get_path_progress();
+
+        let mut lines = vec![
+            format!("Algorithm: {}", config.algorithm),
+            format!(
+                "Step: {} | Moves: {}",
+                simulation.steps(),
+                simulation.statistics().total_moves
+            ),
+            format!("Agent: ({}, {})", agent.position.x, agent.position.y),
+            match grid.extra_goals.is_empty() {
+                true => format!("Goal: ({}, {})", grid.goal.x, grid.goal.y),
+                false => format!("Goals (any one): {}", grid.goals().len()),
+            },
+            format!("Path progress: {}/{}", path_progress, path_total),
+            format!("Optimal path (A*): {}", simulation.optimal_path_length()),
+            format!(
+                "Replans: {} | Planner calls: {}",
+                timing_data.find_path_times.len(),
+                simulation.planner_calls()
+            ),
+        ];
+        if let Some(last) = timing_data.find_path_times.last() {
+            lines.push(format!(
+                "find_path: last {:.2?}, avg {:.2?}",
+                last,
+                timing_data.average_find_path_time()
+            ));
+        }
+        lines.push(format!(
+            "Obstacle cycle: {} | Next in: {}",
+            simulation.obstacle_cycle(),
+            simulation.cycles_until_next_obstacles()
+        ));
+        if !grid.waypoints.is_empty() {
+            lines.push(format!(
+                "Mission leg: {}/{}",
+                simulation.current_leg() + 1,
+                grid.waypoints.len() + 1
+            ));
+        }
+        let movers = simulation.moving_obstacle_positions();
+        if !movers.is_empty() {
+            lines.push(format!("Moving obstacles: {}", movers.len()));
+        }
+        if let Some(pursuer) = simulation.pursuer() {
+            lines.push(format!(
+                "Pursuer: ({}, {})",
+                pursuer.position.x, pursuer.position.y
+            ));
+        }
+        let delay_ms = self.delay_ms.unwrap_or(config.delay_ms);
+        lines.push(match self.paused {
+            true => format!("Delay: {}ms (paused)", delay_ms),
+            false => format!("Delay: {}ms", delay_ms),
+        });
+        lines.push(format!("Status: {}", status(simulation, self.quit)));
+        lines
+    }
+
+    /// Show the frame for the delay, or for as long as the run is paused, acting on
+    /// the keys pressed meanwhile. Just waits out the delay without a keyboard.
+    fn pause(&mut self, simulation: &Simulation) {
+        let delay_ms = self.delay_ms.unwrap_or(simulation.config().delay_ms);
+        if self.raw_mode.is_none() {
+            thread::sleep(Duration::from_millis(delay_ms));
+            return;
+        }
+        let deadline = Instant::now() + Duration::from_millis(delay_ms);
+        loop {
+            let timeout = match self.paused {
+                true => None,
+                false => Some(deadline.saturating_duration_since(Instant::now())),
+            };
+            let control = match read_control(timeout) {
+                Ok(Some(control)) => control,
+                // Out of time, or no keys to read after all
+                Ok(None) | Err(_) => break,
+            };
+            match control {
+                Control::Pause if self.paused => {
+                    self.paused = false;
+                    break;
+                }
+                Control::Pause => {
+                    self.paused = true;
+                    self.event = "Paused - space resumes, n shows the next step".to_string();
+                }
+                Control::Next => {
+                    self.paused = true;
+                    break;
+                }
+                Control::Slower | Control::Faster => {
+                    let delay_ms = self.delay_ms.unwrap_or(simulation.config().delay_ms);
+                    self.delay_ms = Some(match control {
+                        Control::Slower => (delay_ms * 2).max(10),
+                        _ => delay_ms / 2,
+                    });
+                }
+                Control::Quit => {
+                    self.quit = true;
+                    self.paused = false;
+                    break;
+                }
+            }
+            self.draw(simulation);
+        }
+    }
+}
+
+impl SimulationObserver for Dashboard {
+    fn on_start(&mut self, simulation: &Simulation) {
+        self.trail = vec![simulation.agent.position];
+        self.event = "Started".to_string();
+        self.enter();
+        self.draw(simulation);
+        self.pause(simulation);
+    }
+
+    fn on_replan(&mut self, simulation: &Simulation, replan: &Replan) {
+        self.event = match replan.stuck_attempts {
+            0 => format!(
+                "New path found with {} steps",
+                simulation.agent.get_current_path().map_or(0, Vec::len)
+            ),
+            attempts => format!(
+                "No path found - waiting (attempt {}/{})",
+                attempts.min(MAX_STUCK_ATTEMPTS),
+                MAX_STUCK_ATTEMPTS
+            ),
+        };
+    }
+
+    fn on_step(&mut self, simulation: &Simulation, outcome: StepOutcome) {
+        if let StepOutcome::Moved { to, .. } = outcome {
+            self.trail.push(to);
+            self.draw(simulation);
+            self.pause(simulation);
+        }
+    }
+
+    fn on_finish(&mut self, simulation: &Simulation) {
+        if simulation.outcome() != Some(StepOutcome::Failed(Failure::NoPath))
+            && self.raw_mode.is_some()
+        {
+            self.event = format!("{} - press any key to leave", status(simulation, self.quit));
+            self.draw(simulation);
+            // Any key leaves, not just the ones acted on during the run
+            while let Ok(event) = event::read() {
+                if matches!(event, Event::Key(key) if key.kind == KeyEventKind::Press) {
+                    break;
+                }
+            }
+        }
+        self.leave();
+    }
+
+    fn stop_requested(&self) -> bool {
+        self.quit
+    }
+}
+
+impl Drop for Dashboard {
+    fn drop(&mut self) {
+        // A run given up on without finishing still gives the screen back
+        self.leave();
+    }
+}
+
+/// How the run stands, in a few words; `quit` when q stopped it
+fn status(simulation: &Simulation, quit: bool) -> &'static str {
+    match simulation.outcome() {
+        None if quit => "STOPPED before the run ended",
+        None => "running",
+        Some(StepOutcome::Finished) => "SUCCESS: reached the goal",
+        Some(StepOutcome::Failed(failure)) => match failure {
+            Failure::NoPath => "FAILED: no path to the goal",
+            Failure::Stuck => "FAILED: permanently stuck",
+            Failure::OutOfEnergy => "FAILED: out of energy",
+            Failure::Caught => "FAILED: caught by the pursuer",
+            Failure::StepLimit => "FAILED: reached max iterations",
+            Failure::TimedOut => "FAILED: timed out",
+        },
+        Some(_) => "over",
+    }
+}
+
+/// The find_path sparkline panel: a bar for each of the latest find_path times that
+/// fit, scaled to the slowest of them
+fn render_sparkline(frame: &mut Frame, simulation: &Simulation, area: Rect) {
+    let times = &simulation.timing_data().find_path_times;
+    let latest = &times[times
+        .len()
+        .saturating_sub(area.width.saturating_sub(2) as usize)..];
+    let block = Block::bordered().title(" find_path times ");
+    let Some(slowest) = latest.iter().max() else {
+        frame.render_widget(Paragraph::new("No replans yet").block(block), area);
+        return;
+    };
+    let nanos: Vec<u64> = latest.iter().map(|time| time.as_nanos() as u64).collect();
+    let block = block.title_bottom(format!(
+        " Latest {} replans, slowest {:.2?} ",
+        latest.len(),
+        slowest
+    ));
+    frame.render_widget(Sparkline::default().data(&nanos).block(block), area);
+}
+
+/// The table of the obstacle groups
+fn render_obstacle_groups(frame: &mut Frame, simulation: &Simulation, area: Rect) {
+    let groups = simulation.obstacle_groups();
+    let block = Block::bordered().title(" Obstacle groups ");
+    if groups.is_empty() {
+        frame.render_widget(
+            Paragraph::new("No active obstacle groups").block(block),
+            area,
+        );
+        return;
+    }
+    let rows = groups.iter().enumerate().map(|(i, group)| {
+        Row::new([
+            Line::from((i + 1).to_string()),
+            Line::from(group.positions.len().to_string()).right_aligned(),
+            Line::from(group.cycles_remaining.to_string()).right_aligned(),
+        ])
+    });
+    let header = Row::new([
+        Line::from("Group"),
+        Line::from("Obstacles").right_aligned(),
+        Line::from("Cycles left").right_aligned(),
+    ])
+    .bold();
+    let widths = [
+        Constraint::Length(6),
+        Constraint::Length(9),
+        Constraint::Length(14),
+    ];
+    frame.render_widget(Table::new(rows, widths).header(header).block(block), area);
+}
@@ -0,0 +1,67 @@
+use crate::grid::Position;
+use std::io;
+use thiserror::Error;
+
+/// Options, or files they name, that a run cannot be set up with
+#[derive(Debug, Error)]
+pub enum ConfigError {
+    /// `--algorithm` (or a planner option) names no registered algorithm
+    #[error("Unknown algorithm: '{0}'")]
+    UnknownAlgorithm(String),
+    /// An option, a combination of options or the contents of a file, with what is
+    /// wrong with it
+    #[error("{0}")]
+    Invalid(String),
+}
+
+/// The option parsers and file readers describe what is wrong in words
+impl From<String> for ConfigError {
+    fn from(message: String) -> Self {
+        ConfigError::Invalid(message)
+    }
+}
+
+/// Environments no planner can solve
+#[derive(Debug, Error)]
+pub enum PlanningError {
+    /// The walls cut the goal off from the start
+    #[error("No valid path exists from start {start:?} to goal {goal:?}! Grid has {walls} walls.")]
+    NoPathExists {
+        start: Position,
+        goal: Position,
+        walls: usize,
+    },
+}
+
+/// Why a simulation, a comparison or a batch could not be run
+#[derive(Debug, Error)]
+pub enum SimulationError {
+    #[error(transparent)]
+    Config(#[from] ConfigError),
+    #[error(transparent)]
+    Planning(#[from] PlanningError),
+    /// A file could not be read or written; `context` says which and what for
+    #[error("{context}: {source}")]
+    Io {
+        context: String,
+        #[source]
+        source: io::Error,
+    },
+}
+
+impl SimulationError {
+    /// The error for an I/O failure, with what was being done (`Failed to read
+    /// waypoints file 'route.txt'`)
+    pub fn io(context: impl Into<String>, source: io::Error) -> Self {
+        SimulationError::Io {
+            context: context.into(),
+            source,
+        }
+    }
+}
+
+impl From<String> for SimulationError {
+    fn from(message: String) -> Self {
+        SimulationError::Config(ConfigError::Invalid(message))
+    }
+}
@@ -0,0 +1,90 @@
+use crate::grid::{Cell, Grid, Position};
+use crate::run_log::failure_name;
+use crate::simulation::{Simulation, StepOutcome};
+use serde_json::{json, Value};
+
+/// The grid's layout, which changes only with wall events: its `width`, `height`,
+/// `floors`, `start`, `walls`, `terrain` (`[x, y, floor, cost]`), `stairs` and
+/// `portals`
+pub fn grid_frame(simulation: &Simulation) -> Value {
+    let grid = &simulation.grid;
+    let terrain: Vec<Value> = (0..grid.cell_count())
+        .map(|index| grid.position(index))
+        .filter_map(|pos| match grid.cell(pos) {
+            Cell::Terrain(cost) => Some(json!([pos.x, pos.y, pos.z, cost])),
+            _ => None,
+        })
+        .collect();
+    let portals: Vec<Value> = grid
+        .portals
+        .iter()
+        .filter(|(a, b)| a < b)
+        .map(|(&a, &b)| json!([cell(a), cell(b)]))
+        .collect();
+    json!({
+        "type": "grid",
+        "width": grid.width,
+        "height": grid.height,
+        "floors": grid.floors,
+        "hex": grid.hex,
+        "start": cell(grid.start),
+        "walls": cells(&cells_holding(grid, Cell::Wall)),
+        "terrain": terrain,
+        "stairs": cells(&cells_holding(grid, Cell::Stairs)),
+        "portals": portals,
+    })
+}
+
+/// The state of the run: the `step`, `moves`, `replans`, the `agent`'s cell, the
+/// rest of its `path`, the given cells of its `trail`, the `obstacles`, `goals` and
+/// `waypoints`, the `other_agents`, the `pursuer` and the `outcome` once the run is
+/// over (`finished`, a failure such as `stuck`, or `stopped` for a run `stopped`
+/// before it was over)
+pub fn state_frame(simulation: &Simulation, trail: &[Position], stopped: bool) -> Value {
+    let outcome = match simulation.outcome() {
+        Some(StepOutcome::Finished) => json!("finished"),
+        Some(StepOutcome::Failed(failure)) => json!(failure_name(failure)),
+        _ if stopped => json!("stopped"),
+        _ => Value::Null,
+    };
+    let agent = &simulation.agent;
+    let (progress, _) = agent.get_path_progress();
+    let path = agent
+        .get_current_path()
+        .map_or(&[][..], |path| &path[progress.min(path.len())..]);
+    let grid = &simulation.grid;
+    json!({
+        "type": "state",
+        "algorithm": simulation.config().algorithm,
+        "step": simulation.steps(),
+        "moves": simulation.statistics().total_moves,
+        // The initial plan is not a replan
+        "replans": simulation.planner_calls().saturating_sub(1),
+        "agent": cell(agent.position),
+        "path": cells(path),
+        "trail": cells(trail),
+        "obstacles": cells(&cells_holding(grid, Cell::Obstacle)),
+        "goals": cells(&grid.goals()),
+        "waypoints": cells(&grid.waypoints),
+        "other_agents": cells(&simulation.companion_positions()),
+        "pursuer": simulation.pursuer().map(|pursuer| cell(pursuer.position)),
+        "outcome": outcome,
+    })
+}
+
+/// A cell of a frame
+fn cell(position: Position) -> Value {
+    json!([position.x, position.y, position.z])
+}
+
+fn cells(positions: &[Position]) -> Value {
+    positions.iter().copied().map(cell).collect()
+}
+
+/// The cells of the grid holding `kind`
+fn cells_holding(grid: &Grid, kind: Cell) -> Vec<Position> {
+    (0..grid.cell_count())
+        .map(|index| grid.position(index))
+        .filter(|&pos| grid.cell(pos) == kind)
+        .collect()
+}
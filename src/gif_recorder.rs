@@ -0,0 +1,243 @@
+use crate::grid::{Grid, GridView, Position};
+use crate::observer::SimulationObserver;
+use crate::say;
+use crate::simulation::{Simulation, StepOutcome};
+use image::codecs::gif::{GifEncoder, Repeat};
+use image::{Delay, Frame, Rgba, RgbaImage};
+use std::collections::HashSet;
+use std::fs::File;
+use std::io::BufWriter;
+
+/// How long the animation shows the run's last frame before it starts over
+const HOLD_MS: u32 = 2000;
+
+/// Shortest time a frame is shown; many viewers slow shorter GIF frames down
+const MIN_FRAME_MS: u32 = 20;
+
+/// Color of the lines between cells, when they are large enough to have them
+pub(crate) const GRID_LINE: Rgba<u8> = Rgba([220, 220, 220, 255]);
+
+/// Records a run as an animated GIF: a frame for the start and for each step, with
+/// the agent, its planned path, the cells it has visited and the obstacles where
+/// they are at the time. Frames are written as the run goes, so a long run does
+/// not have to fit in memory, and the animation is finished when the run is.
+/// Stops, with a message, the first time the file cannot be written.
+pub struct GifRecorder {
+    path: String,
+    layout: Layout,
+    /// How long each step is shown
+    frame_ms: u32,
+    encoder: Option<GifEncoder<BufWriter<File>>>,
+    /// The latest frame, written once the next one differs from it, and how many
+    /// steps it stands for
+    pending: Option<(RgbaImage, u32)>,
+    trail: Vec<Position>,
+    frames: usize,
+}
+
+impl GifRecorder {
+    /// Record to the GIF file at `path`, drawing each cell `cell_size` pixels wide
+    /// and showing each step for `frame_ms` milliseconds
+    pub fn new(path: &str, cell_size: u32, frame_ms: u64) -> Result<Self, String> {
+        if cell_size == 0 {
+            return Err("--gif-cell-size must be at least 1".to_string());
+        }
+        let file = File::create(path).map_err(|e| format!("Cannot record to {}: {}", path, e))?;
+        let mut encoder = GifEncoder::new_with_speed(BufWriter::new(file), 10);
+        encoder
+            .set_repeat(Repeat::Infinite)
+            .map_err(|e| format!("Cannot record to {}: {}", path, e))?;
+        Ok(GifRecorder {
+            path: path.to_string(),
+            layout: Layout { cell_size },
+            frame_ms: (frame_ms.min(u32::MAX as u64) as u32).max(MIN_FRAME_MS),
+            encoder: Some(encoder),
+            pending: None,
+            trail: Vec::new(),
+            frames: 0,
+        })
+    }
+
+    /// The simulation as it is now, drawn as `Grid::render` would draw it
+    fn draw(&self, simulation: &Simulation) -> RgbaImage {
+        let (progress, _) = simulation.agent.get_path_progress();
+        let view = GridView {
+            agent: Some(simulation.agent.position),
+            path: simulation
+                .agent
+                .get_current_path()
+                .map_or(&[], |path| &path[progress.min(path.len())..]),
+            visited: &self.trail,
+            seen: None,
+            window: None,
+            marks: None,
+            color: false,
+        };
+        self.layout.draw(&simulation.grid, &view)
+    }
+
+    /// Add the simulation as it is now, unless it looks as it did on the last frame,
+    /// which is then shown for a step longer
+    fn capture(&mut self, simulation: &Simulation) {
+        if self.encoder.is_none() {
+            return;
+        }
+        let image = self.draw(simulation);
+        match &mut self.pending {
+            Some((pending, steps)) if *pending == image => *steps += 1,
+            _ => {
+                if let Some((pending, steps)) = self.pending.replace((image, 1)) {
+                    self.write(pending, steps * self.frame_ms);
+                }
+            }
+        }
+    }
+
+    /// Write a frame shown for `ms` milliseconds
+    fn write(&mut self, image: RgbaImage, ms: u32) {
+        let Some(encoder) = &mut self.encoder else {
+            return;
+        };
+        let frame = Frame::from_parts(image, 0, 0, Delay::from_numer_denom_ms(ms, 1));
+        match encoder.encode_frame(frame) {
+            Ok(()) => self.frames += 1,
+            Err(e) => {
+                tracing::error!("Cannot record to {}: {}", self.path, e);
+                self.encoder = None;
+            }
+        }
+    }
+}
+
+/// Where the cells of a grid go in a picture of it: `cell_size` pixels on a side,
+/// the floors side by side a cell apart, and hex rows shifted by half a cell each
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Layout {
+    pub cell_size: u32,
+}
+
+impl Layout {
+    /// Size of the picture of `grid` in pixels
+    pub fn size(&self, grid: &Grid) -> (u32, u32) {
+        let width =
+            self.floor_width(grid) * grid.floors as u32 + self.cell_size * (grid.floors as u32 - 1);
+        (width, grid.height as u32 * self.cell_size)
+    }
+
+    fn floor_width(&self, grid: &Grid) -> u32 {
+        grid.width as u32 * self.cell_size + self.hex_shift(grid, grid.height)
+    }
+
+    /// How far row `y` of a hex grid is shifted right
+    fn hex_shift(&self, grid: &Grid, y: usize) -> u32 {
+        match grid.hex {
+            true => y as u32 * self.cell_size / 2,
+            false => 0,
+        }
+    }
+
+    /// Top left corner of the cell at `pos`
+    pub fn corner(&self, grid: &Grid, pos: Position) -> (u32, u32) {
+        let left = pos.z as u32 * (self.floor_width(grid) + self.cell_size)
+            + self.hex_shift(grid, pos.y)
+            + pos.x as u32 * self.cell_size;
+        (left, pos.y as u32 * self.cell_size)
+    }
+
+    /// Center of the cell at `pos`
+    pub fn center(&self, grid: &Grid, pos: Position) -> (f32, f32) {
+        let (left, top) = self.corner(grid, pos);
+        let half = self.cell_size as f32 / 2.0;
+        (left as f32 + half, top as f32 + half)
+    }
+
+    /// Side of the part of a cell that is filled in; cells large enough for it
+    /// leave a line between them
+    pub fn inner(&self) -> u32 {
+        match self.cell_size >= 4 {
+            true => self.cell_size - 1,
+            false => self.cell_size,
+        }
+    }
+
+    /// `grid` as `view` shows it, a cell filled with the color of its glyph
+    pub fn draw(&self, grid: &Grid, view: &GridView) -> RgbaImage {
+        let path: HashSet<Position> = view.path.iter().copied().collect();
+        let visited: HashSet<Position> = view.visited.iter().copied().collect();
+        let (width, height) = self.size(grid);
+        let mut image = RgbaImage::from_pixel(width, height, Rgba([255, 255, 255, 255]));
+        let inner = self.inner();
+        for index in 0..grid.cell_count() {
+            let pos = grid.position(index);
+            let (glyph, _) = grid.glyph(pos, view, (&path, &visited));
+            let (left, top) = self.corner(grid, pos);
+            for dy in 0..self.cell_size {
+                for dx in 0..self.cell_size {
+                    let color = match dx < inner && dy < inner {
+                        true => fill(glyph),
+                        false => GRID_LINE,
+                    };
+                    image.put_pixel(left + dx, top + dy, color);
+                }
+            }
+        }
+        image
+    }
+}
+
+/// Color of a cell drawn as `glyph`
+pub(crate) fn fill(glyph: char) -> Rgba<u8> {
+    Rgba(match glyph {
+        'A' => [235, 175, 0, 255],
+        'S' => [40, 160, 60, 255],
+        'G' => [210, 40, 40, 255],
+        'W' => [0, 170, 200, 255],
+        'P' => [170, 60, 200, 255],
+        'H' => [200, 120, 220, 255],
+        '#' => [55, 55, 65, 255],
+        'O' => [140, 30, 30, 255],
+        '*' => [140, 210, 140, 255],
+        '·' => [185, 215, 235, 255],
+        '~' => [90, 140, 230, 255],
+        '%' => [205, 170, 110, 255],
+        _ => [248, 248, 248, 255],
+    })
+}
+
+impl SimulationObserver for GifRecorder {
+    fn on_start(&mut self, simulation: &Simulation) {
+        let (width, height) = self.layout.size(&simulation.grid);
+        if width > u16::MAX as u32 || height > u16::MAX as u32 {
+            tracing::error!(
+                "Cannot record to {}: a {}x{} frame is larger than a GIF can be; try a smaller --gif-cell-size",
+                self.path, width, height
+            );
+            self.encoder = None;
+            return;
+        }
+        self.trail = vec![simulation.agent.position];
+        self.capture(simulation);
+    }
+
+    fn on_step(&mut self, simulation: &Simulation, outcome: StepOutcome) {
+        if let StepOutcome::Moved { to, .. } = outcome {
+            self.trail.push(to);
+        }
+        self.capture(simulation);
+    }
+
+    fn on_finish(&mut self, simulation: &Simulation) {
+        self.capture(simulation);
+        if let Some((pending, steps)) = self.pending.take() {
+            self.write(pending, (steps - 1) * self.frame_ms + HOLD_MS);
+        }
+        // Dropping the encoder ends the animation
+        if self.encoder.take().is_some() {
+            say!(
+                "Recorded {} frames of the run to {}",
+                self.frames,
+                self.path
+            );
+        }
+    }
+}
@@ -1,116 +1,1026 @@
+#[cfg(feature = "terminal")]
+use crossterm::style::{Color, Stylize};
 use rand::Rng;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+#[cfg(feature = "terminal")]
+use std::collections::HashSet;
+use std::sync::atomic::{AtomicU64, Ordering};
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Position {
     pub x: usize,
     pub y: usize,
+    /// Floor, 0 for the ground floor (always 0 on single-floor grids)
+    pub z: usize,
+}
+
+/// Cost of a horizontal or vertical move when diagonal movement is enabled.
+pub const STRAIGHT_COST: u32 = 10;
+/// Cost of a diagonal move when diagonal movement is enabled (10 * sqrt(2), rounded).
+pub const DIAGONAL_COST: u32 = 14;
+
+/// Cheapest cost between two cells on an open 8-connected grid
+pub fn octile_distance(from: Position, to: Position) -> u32 {
+    let dx = from.x.abs_diff(to.x) as u32;
+    let dy = from.y.abs_diff(to.y) as u32;
+    octile_length(dx, dy)
+}
+
+fn octile_length(dx: u32, dy: u32) -> u32 {
+    STRAIGHT_COST * dx.max(dy) + (DIAGONAL_COST - STRAIGHT_COST) * dx.min(dy)
+}
+
+/// Offsets of the six neighbors of a hex cell in axial coordinates, in ring order
+/// (each direction is adjacent to the next, and the last to the first)
+pub const HEX_DIRECTIONS: [(i32, i32); 6] = [(1, 0), (1, -1), (0, -1), (-1, 0), (-1, 1), (0, 1)];
+
+/// Number of moves between two cells of a floor on an open hex grid in axial coordinates
+pub fn hex_distance(from: Position, to: Position) -> u32 {
+    hex_length(to.x as i32 - from.x as i32, to.y as i32 - from.y as i32)
+}
+
+fn hex_length(dq: i32, dr: i32) -> u32 {
+    (dq.unsigned_abs() + dr.unsigned_abs() + (dq + dr).unsigned_abs()) / 2
+}
+
+/// Shape of the moves on a grid: which cells are adjacent and what moving between
+/// them costs
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MetricKind {
+    /// 4-connected unit moves
+    Manhattan,
+    /// 8-connected moves costing `STRAIGHT_COST` or `DIAGONAL_COST`
+    Octile,
+    /// 6-connected unit moves on a hex grid
+    Hex,
+}
+
+/// The cheapest cost between two cells on an open grid of a given topology, used
+/// as the admissible heuristic by every planner that does not own a grid. Changing
+/// floors takes at least one straight move per floor. On a toroidal grid the
+/// distance goes across the edges whenever that is shorter.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Metric {
+    pub kind: MetricKind,
+    /// Width and height of a toroidal grid, whose edges wrap around
+    pub wrap: Option<(usize, usize)>,
+}
+
+impl Metric {
+    /// 4-connected unit moves on a grid without wrapping edges
+    pub const MANHATTAN: Metric = Metric {
+        kind: MetricKind::Manhattan,
+        wrap: None,
+    };
+
+    pub fn distance(self, from: Position, to: Position) -> u32 {
+        let floors = from.z.abs_diff(to.z) as u32;
+        let (dx, dy) = self.offset(from, to);
+        match self.kind {
+            MetricKind::Manhattan => dx.unsigned_abs() + dy.unsigned_abs() + floors,
+            MetricKind::Octile => {
+                octile_length(dx.unsigned_abs(), dy.unsigned_abs()) + STRAIGHT_COST * floors
+            }
+            MetricKind::Hex => hex_length(dx, dy) + floors,
+        }
+    }
+
+    /// Offset from `from` to `to` within a floor, in cells. On a toroidal grid this
+    /// is the offset to the nearest copy of `to` when the map is tiled, so it may
+    /// point across an edge.
+    pub fn offset(self, from: Position, to: Position) -> (i32, i32) {
+        let dx = to.x as i32 - from.x as i32;
+        let dy = to.y as i32 - from.y as i32;
+        let Some((width, height)) = self.wrap else {
+            return (dx, dy);
+        };
+        let (width, height) = (width as i32, height as i32);
+        if self.kind != MetricKind::Hex {
+            let shortest = |d: i32, size: i32| {
+                let d = d.rem_euclid(size);
+                if 2 * d > size {
+                    d - size
+                } else {
+                    d
+                }
+            };
+            return (shortest(dx, width), shortest(dy, height));
+        }
+
+        // Hex distance mixes both axes, so try the copies of `to` around it. The
+        // nearest copy is never more than max(width, height / 2) columns away (and
+        // likewise for rows), which bounds how many copies have to be tried.
+        let copies_x = 2 + height / (2 * width);
+        let copies_y = 2 + width / (2 * height);
+        (-copies_x..=copies_x)
+            .flat_map(|i| (-copies_y..=copies_y).map(move |j| (dx + i * width, dy + j * height)))
+            .min_by_key(|&(q, r)| hex_length(q, r))
+            .unwrap_or((dx, dy))
+    }
+
+    /// Cost of a single straight move
+    pub fn straight_cost(self) -> u32 {
+        if self.kind == MetricKind::Octile {
+            STRAIGHT_COST
+        } else {
+            1
+        }
+    }
+
+    /// `distance`, allowing for shortcuts through portals (each portal cell mapped to
+    /// the other end of its pair): the cheapest way there when walking between cells
+    /// costs at least their distance and stepping through a portal one straight move.
+    /// Like `distance` it never overestimates, however the portals are chained.
+    pub fn distance_via(
+        self,
+        from: Position,
+        to: Position,
+        portals: &HashMap<Position, Position>,
+    ) -> u32 {
+        via_portals(from, to, portals, self.straight_cost(), |a, b| {
+            self.distance(a, b)
+        })
+    }
+
+    /// `euclidean`, allowing for shortcuts through portals like `distance_via`
+    pub fn euclidean_via(
+        self,
+        from: Position,
+        to: Position,
+        portals: &HashMap<Position, Position>,
+    ) -> f64 {
+        via_portals(from, to, portals, 1.0, |a, b| self.euclidean(a, b))
+    }
+
+    /// Straight-line distance between two cell centers, in cell widths, with floors
+    /// one cell apart. Hex centers sit on a triangular lattice, with the rows shifted
+    /// half a cell per row.
+    pub fn euclidean(self, from: Position, to: Position) -> f64 {
+        let (dx, dy) = self.offset(from, to);
+        let (dx, dy) = (dx as f64, dy as f64);
+        let planar = if self.kind == MetricKind::Hex {
+            (dx + dy / 2.0).hypot(dy * 3f64.sqrt() / 2.0)
+        } else {
+            dx.hypot(dy)
+        };
+        planar.hypot(to.z as f64 - from.z as f64)
+    }
+}
+
+/// Shortest route from `from` to `to` when walking between any two cells costs
+/// `walk` and stepping through a portal costs `step`: Dijkstra over the portal
+/// cells, which are few
+fn via_portals<T>(
+    from: Position,
+    to: Position,
+    portals: &HashMap<Position, Position>,
+    step: T,
+    walk: impl Fn(Position, Position) -> T,
+) -> T
+where
+    T: Copy + PartialOrd + std::ops::Add<Output = T>,
+{
+    let mut best = walk(from, to);
+    let mut entrances: Vec<(Position, Position, T)> = portals
+        .iter()
+        .map(|(&entrance, &exit)| (entrance, exit, walk(from, entrance)))
+        .collect();
+    while !entrances.is_empty() {
+        // Closest portal not yet stepped through
+        let mut closest = 0;
+        for (i, entrance) in entrances.iter().enumerate() {
+            if entrance.2 < entrances[closest].2 {
+                closest = i;
+            }
+        }
+        let (_, exit, reached) = entrances.swap_remove(closest);
+        if reached >= best {
+            break;
+        }
+        let arrived = reached + step;
+        if arrived + walk(exit, to) < best {
+            best = arrived + walk(exit, to);
+        }
+        for entrance in &mut entrances {
+            let through = arrived + walk(exit, entrance.0);
+            if through < entrance.2 {
+                entrance.2 = through;
+            }
+        }
+    }
+    best
 }
 
 #[derive(Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
 pub enum Cell {
     Empty,
     Wall,
     Obstacle,
+    /// Passable ground that is slower to cross, such as mud or water: entering the
+    /// cell costs this many times as much as entering an empty one (at least 1)
+    Terrain(u32),
+    /// Open ground connected to the stairs directly above and below it, if any
+    Stairs,
+}
+
+/// Cost multiplier of mud terrain
+pub const MUD_COST: u32 = 3;
+/// Cost multiplier of water terrain
+pub const WATER_COST: u32 = 5;
+
+/// Maps keyed by cell written as lists of `[cell, value]` pairs, in cell order, since
+/// JSON objects only have text keys
+#[cfg(feature = "serde")]
+pub(crate) mod cell_map {
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+    use std::collections::HashMap;
+    use std::hash::Hash;
+
+    pub fn serialize<K, V, S>(map: &HashMap<K, V>, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        K: Serialize + Ord,
+        V: Serialize,
+        S: Serializer,
+    {
+        let mut pairs: Vec<(&K, &V)> = map.iter().collect();
+        pairs.sort_by_key(|&(key, _)| key);
+        pairs.serialize(serializer)
+    }
+
+    pub fn deserialize<'de, K, V, D>(deserializer: D) -> Result<HashMap<K, V>, D::Error>
+    where
+        K: Deserialize<'de> + Eq + Hash,
+        V: Deserialize<'de>,
+        D: Deserializer<'de>,
+    {
+        Ok(Vec::<(K, V)>::deserialize(deserializer)?
+            .into_iter()
+            .collect())
+    }
+}
+
+/// Source of wall generations, shared by every grid so no two layouts of walls get
+/// the same one
+static WALL_GENERATIONS: AtomicU64 = AtomicU64::new(0);
+
+/// The cells of a grid in one flat buffer, floor by floor and row by row (the order
+/// of `Grid::index`), with a bit per cell marking the walls, so the wall checks
+/// planners make on every expansion read one word rather than a whole cell
+#[derive(Clone)]
+struct Cells {
+    cells: Vec<Cell>,
+    walls: Vec<u64>,
+    /// Drawn afresh whenever a wall is placed or removed (see `Grid::wall_generation`)
+    wall_generation: u64,
 }
 
+impl Cells {
+    fn new(count: usize) -> Self {
+        Cells {
+            cells: vec![Cell::Empty; count],
+            walls: vec![0; count.div_ceil(64)],
+            wall_generation: WALL_GENERATIONS.fetch_add(1, Ordering::Relaxed),
+        }
+    }
+
+    fn get(&self, index: usize) -> Cell {
+        self.cells[index]
+    }
+
+    fn set(&mut self, index: usize, cell: Cell) {
+        self.cells[index] = cell;
+        let (word, bit) = (index / 64, 1 << (index % 64));
+        let walls = match cell {
+            Cell::Wall => self.walls[word] | bit,
+            _ => self.walls[word] & !bit,
+        };
+        if walls != self.walls[word] {
+            self.walls[word] = walls;
+            self.wall_generation = WALL_GENERATIONS.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    fn is_wall(&self, index: usize) -> bool {
+        self.walls[index / 64] & (1 << (index % 64)) != 0
+    }
+}
+
+/// Written as the flat list of cells alone; the wall bits are rebuilt from it
+#[cfg(feature = "serde")]
+impl Serialize for Cells {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.cells.serialize(serializer)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> Deserialize<'de> for Cells {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let list = Vec::<Cell>::deserialize(deserializer)?;
+        let mut cells = Cells::new(list.len());
+        for (index, cell) in list.into_iter().enumerate() {
+            cells.set(index, cell);
+        }
+        Ok(cells)
+    }
+}
+
+/// Serialized field by field; deserializing checks the cells fit the grid's size
 #[derive(Clone)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(remote = "Self")
+)]
 pub struct Grid {
-    pub size: usize,
-    pub cells: Vec<Vec<Cell>>,
+    /// Number of columns (x runs from 0 to `width - 1`)
+    pub width: usize,
+    /// Number of rows (y runs from 0 to `height - 1`)
+    pub height: usize,
+    /// Number of floors (z runs from 0 to `floors - 1`), connected by stairs
+    pub floors: usize,
+    /// Read and written through `cell`, `set_cell` and `is_wall`; sized for the
+    /// width, height and floors the grid was made with
+    cells: Cells,
     pub start: Position,
     pub goal: Position,
+    /// Additional goals in multi-goal mode; reaching any goal counts as success
+    pub extra_goals: Vec<Position>,
+    /// Ordered waypoints the agent must visit before heading for the goal
+    pub waypoints: Vec<Position>,
+    /// 8-connected movement with octile costs instead of 4-connected unit moves
+    pub diagonal: bool,
+    /// Hexagonal cells in axial coordinates (x is the column q, y the row r) with six
+    /// neighbors and unit moves. The map is a parallelogram: each row sits half a
+    /// cell further right than the one above. Takes precedence over `diagonal`.
+    pub hex: bool,
+    /// Toroidal grid: moving off one edge enters the cell on the opposite edge
+    pub wrap: bool,
+    /// Portal cells, each mapped to the other end of its pair. Stepping into a
+    /// portal cell's partner from the portal cell is a single straight move.
+    #[cfg_attr(feature = "serde", serde(with = "cell_map"))]
+    pub portals: HashMap<Position, Position>,
+}
+
+#[cfg(feature = "serde")]
+impl Serialize for Grid {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        Grid::serialize(self, serializer)
+    }
+}
+
+/// Fails on a list of cells longer or shorter than the width, height and floors
+/// make, which `cell` and `is_wall` would otherwise read past or leave unread
+#[cfg(feature = "serde")]
+impl<'de> Deserialize<'de> for Grid {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let grid = Grid::deserialize(deserializer)?;
+        let count = grid
+            .width
+            .checked_mul(grid.height)
+            .and_then(|cells| cells.checked_mul(grid.floors));
+        if count != Some(grid.cells.cells.len()) {
+            return Err(serde::de::Error::custom(format!(
+                "a grid of {}x{} cells on {} floors cannot have {} cells",
+                grid.width,
+                grid.height,
+                grid.floors,
+                grid.cells.cells.len()
+            )));
+        }
+        Ok(grid)
+    }
 }
 
 impl Grid {
-    pub fn new(size: usize, num_walls: usize) -> Self {
-        let mut cells = vec![vec![Cell::Empty; size]; size];
+    pub fn new(width: usize, height: usize, num_walls: usize) -> Self {
+        let mut grid = Grid::empty(width, height, 1);
         let mut rng = rand::thread_rng();
-        
+
         // Generate random start and goal positions
-        let start = Position { 
-            x: rng.gen_range(0..size/2), 
-            y: rng.gen_range(0..size/2) 
+        let start = Position {
+            x: rng.gen_range(0..width / 2),
+            y: rng.gen_range(0..height / 2),
+            z: 0,
         };
-        let goal = Position { 
-            x: rng.gen_range(size/2..size), 
-            y: rng.gen_range(size/2..size) 
+        let goal = Position {
+            x: rng.gen_range(width / 2..width),
+            y: rng.gen_range(height / 2..height),
+            z: 0,
         };
 
         // Place walls randomly, ensuring we don't block start/goal
         let mut walls_placed = 0;
         let mut attempts = 0;
         while walls_placed < num_walls && attempts < num_walls * 3 {
-            let x = rng.gen_range(0..size);
-            let y = rng.gen_range(0..size);
-            let pos = Position { x, y };
-            
-            if pos != start && pos != goal && cells[x][y] == Cell::Empty {
-                cells[x][y] = Cell::Wall;
+            let x = rng.gen_range(0..width);
+            let y = rng.gen_range(0..height);
+            let pos = Position { x, y, z: 0 };
+
+            if pos != start && pos != goal && grid.cell(pos) == Cell::Empty {
+                grid.set_cell(pos, Cell::Wall);
                 walls_placed += 1;
             }
             attempts += 1;
         }
 
+        grid.start = start;
+        grid.goal = goal;
+        grid
+    }
+
+    /// A grid of `floors` floors of `width` by `height` empty cells, 4-connected,
+    /// with the start and goal both on the corner (0, 0) of the ground floor until
+    /// they are set
+    pub fn empty(width: usize, height: usize, floors: usize) -> Self {
+        let corner = Position { x: 0, y: 0, z: 0 };
         Grid {
-            size,
-            cells,
-            start,
-            goal,
+            width,
+            height,
+            floors,
+            cells: Cells::new(width * height * floors),
+            start: corner,
+            goal: corner,
+            extra_goals: Vec::new(),
+            waypoints: Vec::new(),
+            diagonal: false,
+            hex: false,
+            wrap: false,
+            portals: HashMap::new(),
+        }
+    }
+
+    /// Number of cells on the grid, over all floors
+    pub fn cell_count(&self) -> usize {
+        self.width * self.height * self.floors
+    }
+
+    /// Names the grid's walls as they are: it changes whenever a wall is placed or
+    /// removed, and copies of a grid share it until one of them changes, so two grids
+    /// of the same generation have the same walls
+    pub fn wall_generation(&self) -> u64 {
+        self.cells.wall_generation
+    }
+
+    /// Dense index of a cell, row-major within each floor and floor by floor,
+    /// below `cell_count()`
+    pub fn index(&self, pos: Position) -> usize {
+        debug_assert!(
+            pos.x < self.width && pos.y < self.height && pos.z < self.floors,
+            "{:?} is off the grid",
+            pos
+        );
+        (pos.z * self.height + pos.y) * self.width + pos.x
+    }
+
+    /// The cell at a dense index, the inverse of `index`
+    pub fn position(&self, index: usize) -> Position {
+        Position {
+            x: index % self.width,
+            y: index / self.width % self.height,
+            z: index / (self.width * self.height),
+        }
+    }
+
+    /// Check whether signed coordinates (e.g. a neighbor offset) lie on the grid
+    pub fn in_bounds(&self, x: i32, y: i32) -> bool {
+        x >= 0 && y >= 0 && x < self.width as i32 && y < self.height as i32
+    }
+
+    /// The cell `dx` columns and `dy` rows away from `pos` on the same floor, or
+    /// `None` if that is off the grid (never on a toroidal grid, where it wraps)
+    pub fn offset(&self, pos: Position, dx: i32, dy: i32) -> Option<Position> {
+        let (mut x, mut y) = (pos.x as i32 + dx, pos.y as i32 + dy);
+        if self.wrap {
+            x = x.rem_euclid(self.width as i32);
+            y = y.rem_euclid(self.height as i32);
+        } else if !self.in_bounds(x, y) {
+            return None;
+        }
+        Some(Position {
+            x: x as usize,
+            y: y as usize,
+            z: pos.z,
+        })
+    }
+
+    pub fn cell(&self, pos: Position) -> Cell {
+        self.cells.get(self.index(pos))
+    }
+
+    pub fn set_cell(&mut self, pos: Position, cell: Cell) {
+        let index = self.index(pos);
+        self.cells.set(index, cell);
+    }
+
+    /// Check whether a cell is a wall, the cheapest look at a cell there is
+    pub fn is_wall(&self, pos: Position) -> bool {
+        self.cells.is_wall(self.index(pos))
+    }
+
+    /// Every cell, in the order of `index`
+    pub fn cells(&self) -> &[Cell] {
+        &self.cells.cells
+    }
+
+    /// Turn every cell holding `from` into `to`, such as every wall into open ground
+    pub fn replace_cells(&mut self, from: Cell, to: Cell) {
+        for index in 0..self.cells.cells.len() {
+            if self.cells.get(index) == from {
+                self.cells.set(index, to);
+            }
+        }
+    }
+
+    /// Cells directly above and below a stair cell that are stairs too
+    pub fn stair_links(&self, pos: &Position) -> Vec<Position> {
+        if self.cell(*pos) != Cell::Stairs {
+            return Vec::new();
         }
+        [pos.z.checked_sub(1), Some(pos.z + 1)]
+            .into_iter()
+            .flatten()
+            .filter(|&z| z < self.floors)
+            .map(|z| Position { z, ..*pos })
+            .filter(|&other| self.cell(other) == Cell::Stairs)
+            .collect()
+    }
+
+    /// Cells one straight move from `pos` without being next to it: the stairs above
+    /// and below a stair cell, and the other end of a portal
+    pub fn links(&self, pos: &Position) -> Vec<Position> {
+        let mut links = self.stair_links(pos);
+        links.extend(self.portals.get(pos));
+        links
+    }
+
+    /// Check whether a move steps through a portal
+    pub fn is_portal_move(&self, from: Position, to: Position) -> bool {
+        self.portals.get(&from) == Some(&to)
+    }
+
+    /// All goals: the main goal followed by any extra goals
+    pub fn goals(&self) -> Vec<Position> {
+        let mut goals = vec![self.goal];
+        goals.extend_from_slice(&self.extra_goals);
+        goals
+    }
+
+    /// Check whether a position is one of the goals
+    pub fn is_goal(&self, pos: Position) -> bool {
+        pos == self.goal || self.extra_goals.contains(&pos)
     }
 
     pub fn get_neighbors(&self, pos: &Position) -> Vec<Position> {
         let mut neighbors = Vec::new();
-        let (x, y) = (pos.x as i32, pos.y as i32);
+        self.neighbors_into(pos, &mut neighbors);
+        neighbors
+    }
 
-        for (dx, dy) in &[(0, 1), (0, -1), (1, 0), (-1, 0)] {
-            let nx = x + dx;
-            let ny = y + dy;
+    /// `get_neighbors` into a buffer of the caller's, cleared first, so searches
+    /// can reuse one buffer for every expansion
+    pub fn neighbors_into(&self, pos: &Position, neighbors: &mut Vec<Position>) {
+        neighbors.clear();
 
-            if nx >= 0 && nx < self.size as i32 && ny >= 0 && ny < self.size as i32 {
-                let next_pos = Position { x: nx as usize, y: ny as usize };
-                if self.cells[next_pos.x][next_pos.y] != Cell::Wall {
+        let directions: &[(i32, i32)] = if self.hex {
+            &HEX_DIRECTIONS
+        } else {
+            &[(0, 1), (0, -1), (1, 0), (-1, 0)]
+        };
+        for &(dx, dy) in directions {
+            if let Some(next_pos) = self.offset(*pos, dx, dy) {
+                if !self.is_wall(next_pos) && !neighbors.contains(&next_pos) {
                     neighbors.push(next_pos);
                 }
             }
         }
-        neighbors
+
+        if self.diagonal && !self.hex {
+            for &(dx, dy) in &[(1, 1), (1, -1), (-1, 1), (-1, -1)] {
+                if let Some(next_pos) = self.offset(*pos, dx, dy) {
+                    // No corner cutting: both cells the move squeezes past must be open too
+                    if !self.is_wall(next_pos)
+                        && !self.is_wall(Position {
+                            x: next_pos.x,
+                            ..*pos
+                        })
+                        && !self.is_wall(Position {
+                            y: next_pos.y,
+                            ..*pos
+                        })
+                        && !neighbors.contains(&next_pos)
+                    {
+                        neighbors.push(next_pos);
+                    }
+                }
+            }
+        }
+        for link in self.links(pos) {
+            if !self.is_wall(link) && !neighbors.contains(&link) {
+                neighbors.push(link);
+            }
+        }
     }
 
-    /// Print a visual representation of the grid with enhanced formatting
+    /// Check whether a move between two adjacent cells is possible when the cells in
+    /// `blocked` (e.g. known obstacles) are off limits as well as walls. Diagonal moves
+    /// need diagonal movement enabled and both cells they squeeze past open, so they
+    /// never cut a corner. Hex cells share an edge with all six neighbors, so there
+    /// are no corners to cut. Climbing stairs and stepping through a portal are
+    /// straight moves. Waiting in place (`from == to`) is always possible.
+    pub fn can_move(
+        &self,
+        from: Position,
+        to: Position,
+        blocked: impl Fn(Position) -> bool,
+    ) -> bool {
+        if from == to {
+            return true;
+        }
+        let is_open = |pos: Position| !self.is_wall(pos) && !blocked(pos);
+        if !is_open(to) {
+            return false;
+        }
+        if self.hex || from.x == to.x || from.y == to.y || self.is_portal_move(from, to) {
+            return true;
+        }
+        self.diagonal
+            && is_open(Position { x: to.x, ..from })
+            && is_open(Position { y: to.y, ..from })
+    }
+
+    /// Cost multiplier for entering a cell: its terrain cost, or 1 for any other cell
+    pub fn terrain_cost(&self, pos: Position) -> u32 {
+        match self.cell(pos) {
+            Cell::Terrain(cost) => cost.max(1),
+            _ => 1,
+        }
+    }
+
+    /// Check whether any cell carries a terrain cost, i.e. whether moves can cost
+    /// more than the plain grid distance
+    pub fn has_terrain(&self) -> bool {
+        self.cells()
+            .iter()
+            .any(|cell| matches!(cell, Cell::Terrain(cost) if *cost > 1))
+    }
+
+    /// Cost of a move between two adjacent cells: 1 on a 4-connected or hex grid,
+    /// `STRAIGHT_COST` or `DIAGONAL_COST` with diagonal movement (climbing stairs and
+    /// stepping through a portal are straight moves), multiplied by the terrain cost
+    /// of the cell entered. Terrain makes costs direction dependent: leaving mud is
+    /// cheaper than wading into it. Waiting in place (`from == to`) costs a straight
+    /// move wherever the agent stands.
+    pub fn move_cost(&self, from: Position, to: Position) -> u32 {
+        if from == to {
+            return self.straight_cost();
+        }
+        let base = if !self.diagonal || self.hex {
+            1
+        } else if from.x != to.x && from.y != to.y && !self.is_portal_move(from, to) {
+            DIAGONAL_COST
+        } else {
+            STRAIGHT_COST
+        };
+        base * self.terrain_cost(to)
+    }
+
+    /// Cost of a single straight move, the unit path costs are reported in
+    pub fn straight_cost(&self) -> u32 {
+        if self.diagonal && !self.hex {
+            STRAIGHT_COST
+        } else {
+            1
+        }
+    }
+
+    /// Distance metric of this grid's topology
+    pub fn metric(&self) -> Metric {
+        let kind = if self.hex {
+            MetricKind::Hex
+        } else if self.diagonal {
+            MetricKind::Octile
+        } else {
+            MetricKind::Manhattan
+        };
+        Metric {
+            kind,
+            wrap: self.wrap.then_some((self.width, self.height)),
+        }
+    }
+
+    /// Admissible distance estimate in move costs: Manhattan distance on a 4-connected
+    /// grid, octile distance with diagonal movement and hex distance on a hex grid,
+    /// plus a straight move per floor apart, measured across the edges of a toroidal
+    /// grid where that is shorter, and through portals where those are shorter.
+    /// Terrain only ever raises costs, so all stay admissible on weighted grids.
+    pub fn heuristic(&self, from: Position, to: Position) -> u32 {
+        self.metric().distance_via(from, to, &self.portals)
+    }
+
+    /// Total cost of a path, in straight moves (so equal to its number of moves
+    /// on a 4-connected or hex grid without terrain)
+    pub fn path_cost(&self, path: &[Position]) -> f64 {
+        let cost: u32 = path
+            .windows(2)
+            .map(|step| self.move_cost(step[0], step[1]))
+            .sum();
+        cost as f64 / self.straight_cost() as f64
+    }
+}
+
+/// The grid's text rendering, in builds with the `terminal` feature
+#[cfg(feature = "terminal")]
+impl Grid {
+    /// Print a visual representation of the grid with enhanced formatting, one
+    /// floor after the other on multi-floor grids
     pub fn print_grid(&self, agent_pos: Option<Position>) {
-        println!("Legend: S=Start, G=Goal, A=Agent, #=Wall, O=Obstacle, .=Empty");
-        
+        print!(
+            "{}",
+            self.render(&GridView {
+                agent: agent_pos,
+                ..GridView::default()
+            })
+        );
+    }
+
+    /// The grid as `print_grid` shows it, with what `view` adds: the planned path, the
+    /// cells visited, the cells not seen yet and colors
+    pub fn render(&self, view: &GridView) -> String {
+        self.legend(view) + &self.render_cells(view)
+    }
+
+    /// The legend `render` starts with
+    pub fn legend(&self, view: &GridView) -> String {
+        let mut out = String::new();
+        out.push_str("Legend: S=Start, G=Goal, W=Waypoint, A=Agent, #=Wall, O=Obstacle, .=Empty, ~=Water, %=Mud\n");
+        if !view.path.is_empty() || !view.visited.is_empty() {
+            out.push_str("*=Planned path, ·=Visited\n");
+        }
+        if view.seen.is_some() {
+            out.push_str("?=Unseen\n");
+        }
+        if self.hex {
+            out.push_str("Hex grid: each row is shifted half a cell right of the one above\n");
+        }
+        if self.wrap {
+            out.push_str("Edges wrap around: leaving one side enters the opposite side\n");
+        }
+        if !self.portals.is_empty() {
+            let mut pairs: Vec<(Position, Position)> = self
+                .portals
+                .iter()
+                .filter(|(a, b)| a < b)
+                .map(|(&a, &b)| (a, b))
+                .collect();
+            pairs.sort();
+            let describe = |pos: Position| {
+                if self.floors > 1 {
+                    format!("({},{},{})", pos.x, pos.y, pos.z)
+                } else {
+                    format!("({},{})", pos.x, pos.y)
+                }
+            };
+            let pairs: Vec<String> = pairs
+                .iter()
+                .map(|&(a, b)| format!("{}<->{}", describe(a), describe(b)))
+                .collect();
+            out.push_str(&format!("P=Portal, pairs: {}\n", pairs.join(", ")));
+        }
+        if self.floors > 1 {
+            out.push_str(
+                "H=Stairs, leading to the stairs at the same spot on the floor above or below\n",
+            );
+        }
+        out
+    }
+
+    /// The floors of the grid as `render` draws them, without the legend
+    pub fn render_cells(&self, view: &GridView) -> String {
+        let mut out = String::new();
+        let path: HashSet<Position> = view.path.iter().copied().collect();
+        let visited: HashSet<Position> = view.visited.iter().copied().collect();
+        for z in 0..self.floors {
+            if self.floors > 1 {
+                out.push_str(&format!("Floor {}:\n", z));
+            }
+            self.render_floor(z, view, (&path, &visited), &mut out);
+        }
+        out
+    }
+
+    fn render_floor(
+        &self,
+        z: usize,
+        view: &GridView,
+        (path, visited): (&HashSet<Position>, &HashSet<Position>),
+        out: &mut String,
+    ) {
+        let window = view.window.unwrap_or(Window::all(self));
+        let label = (self.height - 1).to_string().len().max(2);
+
         // Print column numbers header
-        print!("   ");
-        for x in 0..self.size {
-            print!("{:2}", x % 10);
-        }
-        println!();
-        
-        for y in 0..self.size {
-            // Print row number
-            print!("{:2} ", y);
-            
-            for x in 0..self.size {
-                let pos = Position { x, y };
-                let char = if Some(pos) == agent_pos {
-                    'A'
-                } else if pos == self.start {
-                    'S'
-                } else if pos == self.goal {
-                    'G'
+        out.push_str(&" ".repeat(label + 1));
+        for x in window.columns() {
+            out.push_str(&format!("{:2}", x % 10));
+        }
+        out.push('\n');
+
+        for y in window.rows() {
+            // Print row number; hex rows are indented so each cell sits between
+            // the two cells above it that it borders
+            out.push_str(&format!("{:>label$} ", y));
+            if self.hex {
+                out.push_str(&" ".repeat(y - window.top));
+            }
+
+            for x in window.columns() {
+                let (char, color) = self.glyph(Position { x, y, z }, view, (path, visited));
+                match view.color {
+                    true => out.push_str(&format!("{} ", char.with(color))),
+                    false => out.push_str(&format!("{} ", char)),
+                }
+            }
+            out.push('\n');
+        }
+        out.push('\n');
+    }
+
+    /// The agent's floor (the ground floor without one) shrunk `scale` times each way,
+    /// a character for each block of cells: the agent, start, goal or waypoint in it,
+    /// `O` for obstacles, `*` for the planned path, `·` for visited cells, `#` when
+    /// walls fill at least half of it and `.` for open ground, or `:` within
+    /// `view.window`, so a minimap shows which part of the grid the view draws. Hex
+    /// rows are not shifted.
+    pub fn render_overview(&self, view: &GridView, scale: usize) -> String {
+        let scale = scale.max(1);
+        let z = view.agent.map_or(0, |agent| agent.z);
+        let path: HashSet<Position> = view.path.iter().copied().collect();
+        let visited: HashSet<Position> = view.visited.iter().copied().collect();
+        let mut out = String::new();
+        for top in (0..self.height).step_by(scale) {
+            for left in (0..self.width).step_by(scale) {
+                let block: Vec<Position> = (left..(left + scale).min(self.width))
+                    .flat_map(|x| {
+                        (top..(top + scale).min(self.height)).map(move |y| Position { x, y, z })
+                    })
+                    .collect();
+                let walls = block.iter().filter(|&&pos| self.is_wall(pos)).count();
+                let in_view = view
+                    .window
+                    .is_some_and(|window| block.iter().any(|pos| window.contains(pos.x, pos.y)));
+                let (char, color) = if view.agent.is_some_and(|agent| block.contains(&agent)) {
+                    ('A', Color::Yellow)
+                } else if block.iter().any(|&pos| self.is_goal(pos)) {
+                    ('G', Color::Red)
+                } else if block.contains(&self.start) {
+                    ('S', Color::Green)
+                } else if block.iter().any(|pos| self.waypoints.contains(pos)) {
+                    ('W', Color::Cyan)
+                } else if block.iter().any(|&pos| self.cell(pos) == Cell::Obstacle) {
+                    ('O', Color::DarkRed)
+                } else if block.iter().any(|pos| path.contains(pos)) {
+                    ('*', Color::Green)
+                } else if block.iter().any(|pos| visited.contains(pos)) {
+                    ('·', Color::DarkCyan)
+                } else if walls * 2 >= block.len() {
+                    ('#', Color::Grey)
+                } else if in_view {
+                    (':', Color::White)
                 } else {
-                    match self.cells[x][y] {
-                        Cell::Wall => '#',
-                        Cell::Obstacle => 'O',
-                        Cell::Empty => '.',
-                    }
+                    ('.', Color::DarkGrey)
                 };
-                print!("{} ", char);
+                match view.color {
+                    true => out.push_str(&format!("{}", char.with(color))),
+                    false => out.push(char),
+                }
+            }
+            out.push('\n');
+        }
+        out
+    }
+
+    /// How `render` draws a cell, and in which color; `path` and `visited` hold the
+    /// cells of the view's path and visited cells
+    pub fn glyph(
+        &self,
+        pos: Position,
+        view: &GridView,
+        (path, visited): (&HashSet<Position>, &HashSet<Position>),
+    ) -> (char, Color) {
+        if Some(pos) == view.agent {
+            ('A', Color::Yellow)
+        } else if pos == self.start {
+            ('S', Color::Green)
+        } else if self.is_goal(pos) {
+            ('G', Color::Red)
+        } else if self.waypoints.contains(&pos) {
+            ('W', Color::Cyan)
+        } else if self.portals.contains_key(&pos) {
+            ('P', Color::Magenta)
+        } else if let (Some(&mark), Cell::Empty | Cell::Terrain(_) | Cell::Stairs) =
+            (view.marks.and_then(|marks| marks.get(&pos)), self.cell(pos))
+        {
+            mark
+        } else {
+            match self.cell(pos) {
+                Cell::Wall => ('#', Color::Grey),
+                Cell::Obstacle => ('O', Color::DarkRed),
+                // The planned path and the cells visited show on open ground
+                Cell::Empty | Cell::Terrain(0..=1) if path.contains(&pos) => ('*', Color::Green),
+                Cell::Empty | Cell::Terrain(0..=1) if visited.contains(&pos) => {
+                    ('·', Color::DarkCyan)
+                }
+                _ if view.seen.is_some_and(|seen| !seen.contains(&pos)) => ('?', Color::DarkGrey),
+                Cell::Empty => ('.', Color::DarkGrey),
+                Cell::Terrain(cost) if cost >= WATER_COST => ('~', Color::Blue),
+                Cell::Terrain(cost) if cost > 1 => ('%', Color::DarkYellow),
+                Cell::Terrain(_) => ('.', Color::DarkGrey),
+                Cell::Stairs => ('H', Color::Magenta),
             }
-            println!();
         }
-        println!();
+    }
+}
+
+/// What `Grid::render` draws over the cells, and how
+#[cfg(feature = "terminal")]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct GridView<'a> {
+    /// Where the agent stands
+    pub agent: Option<Position>,
+    /// Cells of the agent's planned path, drawn as `*`
+    pub path: &'a [Position],
+    /// Cells the agent has stood on, drawn as `·`
+    pub visited: &'a [Position],
+    /// Cells the agent has had in view, when drawing what it knows: the others are
+    /// drawn as `?`, unless they are walls (or obstacles) it knows of anyway
+    pub seen: Option<&'a HashSet<Position>>,
+    /// Part of the grid to draw, on every floor; all of it when `None`
+    pub window: Option<Window>,
+    /// Marks drawn on cells that are not walls or obstacles, over what they hold,
+    /// such as a planner's search
+    pub marks: Option<&'a HashMap<Position, (char, Color)>>,
+    /// Whether to color the cells with ANSI escapes
+    pub color: bool,
+}
+
+/// A rectangle of a grid's cells: the part of a large grid a view has room for
+#[cfg(feature = "terminal")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Window {
+    pub left: usize,
+    pub top: usize,
+    pub width: usize,
+    pub height: usize,
+}
+
+#[cfg(feature = "terminal")]
+impl Window {
+    /// The whole of `grid`
+    pub fn all(grid: &Grid) -> Self {
+        Window {
+            left: 0,
+            top: 0,
+            width: grid.width,
+            height: grid.height,
+        }
+    }
+
+    /// `width` by `height` cells of `grid`, centered on `center` as far as the edges
+    /// of the grid allow; the whole of a grid that fits
+    pub fn around(center: Position, width: usize, height: usize, grid: &Grid) -> Self {
+        let width = width.clamp(1, grid.width);
+        let height = height.clamp(1, grid.height);
+        Window {
+            left: center.x.saturating_sub(width / 2).min(grid.width - width),
+            top: center
+                .y
+                .saturating_sub(height / 2)
+                .min(grid.height - height),
+            width,
+            height,
+        }
+    }
+
+    /// Whether the window leaves out part of `grid`
+    pub fn is_cropped(&self, grid: &Grid) -> bool {
+        self.width < grid.width || self.height < grid.height
+    }
+
+    pub fn contains(&self, x: usize, y: usize) -> bool {
+        self.columns().contains(&x) && self.rows().contains(&y)
+    }
+
+    pub fn columns(&self) -> std::ops::Range<usize> {
+        self.left..self.left + self.width
+    }
+
+    pub fn rows(&self) -> std::ops::Range<usize> {
+        self.top..self.top + self.height
     }
 }
@@ -0,0 +1,83 @@
+use crate::config::Config;
+
+/// What the agent knows about the environment before it looks around.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Knowledge {
+    /// The whole map, and every obstacle from the moment it appears until it is gone.
+    Full,
+    /// The walls, but only the obstacles it has seen.
+    Map,
+    /// Nothing: walls have to be discovered like obstacles, and the planners only
+    /// know the walls the agent has seen.
+    Explore,
+}
+
+impl Knowledge {
+    /// Parses a knowledge mode name as given on the command line.
+    pub fn from_name(name: &str) -> Result<Self, String> {
+        match name {
+            "full" => Ok(Knowledge::Full),
+            "map" => Ok(Knowledge::Map),
+            "explore" => Ok(Knowledge::Explore),
+            _ => Err(format!(
+                "Unknown knowledge mode: '{}' (expected 'full', 'map' or 'explore')",
+                name
+            )),
+        }
+    }
+
+    /// Name of the knowledge mode as given on the command line.
+    pub fn name(self) -> &'static str {
+        match self {
+            Knowledge::Full => "full",
+            Knowledge::Map => "map",
+            Knowledge::Explore => "explore",
+        }
+    }
+}
+
+/// How the agent forgets obstacles it has seen once they are out of view. Moving
+/// obstacles are always forgotten as soon as they move on.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Memory {
+    /// Never: every obstacle seen is avoided for the rest of the run.
+    Forever,
+    /// After this many steps without seeing it again.
+    Ttl(usize),
+    /// When its cell is seen to be free again.
+    Reobserve,
+    /// Confidence in an obstacle grows by 1 every step it is seen and shrinks by this
+    /// fraction every step it is not; it is forgotten below 0.5, so obstacles that
+    /// have been around for a while are remembered longer.
+    Decay(f64),
+}
+
+impl Memory {
+    /// Memory model for `--memory`, with its `--memory-ttl` or `--memory-decay`
+    pub fn from_config(config: &Config) -> Result<Self, String> {
+        match config.memory.as_str() {
+            "forever" => Ok(Memory::Forever),
+            "ttl" if config.memory_ttl == 0 => Err("--memory-ttl must be at least 1".to_string()),
+            "ttl" => Ok(Memory::Ttl(config.memory_ttl)),
+            "reobserve" => Ok(Memory::Reobserve),
+            "decay" if !(config.memory_decay > 0.0 && config.memory_decay < 1.0) => {
+                Err("--memory-decay must be between 0 and 1".to_string())
+            }
+            "decay" => Ok(Memory::Decay(config.memory_decay)),
+            name => Err(format!(
+                "Unknown memory model: '{}' (expected 'forever', 'ttl', 'reobserve' or 'decay')",
+                name
+            )),
+        }
+    }
+
+    /// Name of the memory model as given on the command line.
+    pub fn name(self) -> &'static str {
+        match self {
+            Memory::Forever => "forever",
+            Memory::Ttl(_) => "ttl",
+            Memory::Reobserve => "reobserve",
+            Memory::Decay(_) => "decay",
+        }
+    }
+}
@@ -1,7 +1,83 @@
+//! Pathfinding in simulated worlds: grids with walls and obstacles that come and go,
+//! an agent that discovers them as it moves, and planners from A* and D* Lite to
+//! real-time and hierarchical search, compared on the same runs.
+//!
+//! The `dynamic_pathfinding` binary is a thin command line over this library. To use
+//! it as a dependency, start from the `prelude`:
+//!
+//! ```no_run
+//! use dynamic_pathfinding::prelude::*;
+//!
+//! let mut config = Config::default();
+//! config.algorithm = "d_star_lite".to_string();
+//! let mut simulation = Simulation::new(config)?;
+//! simulation.clear_observers();
+//! while !simulation.step().is_over() {}
+//! let (statistics, _, _) = simulation.finish();
+//! println!("{} moves", statistics.total_moves);
+//! # Ok::<(), SimulationError>(())
+//! ```
+//!
+//! The default features draw runs in the terminal (`terminal`) and run batches and
+//! benchmarks (`batch`); without them the grid, the planners and the simulation
+//! stepping build on their own, for WebAssembly too.
+
 pub mod agent;
 pub mod algorithms;
+#[cfg(feature = "batch")]
+pub mod batch_progress;
+#[cfg(feature = "batch")]
 pub mod batch_simulation;
+#[cfg(feature = "batch")]
+pub mod batch_summary;
+#[cfg(feature = "batch")]
+pub mod bench;
+pub mod cbs;
+pub mod checkpoint;
 pub mod config;
+pub mod cooperative;
+#[cfg(feature = "terminal")]
+pub mod dashboard;
+pub mod error;
+pub mod frames;
+#[cfg(feature = "terminal")]
+pub mod gif_recorder;
 pub mod grid;
+pub mod knowledge;
+#[cfg(feature = "terminal")]
+pub mod lockstep;
+pub mod map_file;
+pub mod maze;
+pub mod messages;
+#[cfg(feature = "metrics")]
+pub mod metrics_exporter;
+pub mod moving_obstacle;
+pub mod multi_agent;
+pub mod observer;
+pub mod obstacle_model;
+pub mod obstacle_script;
+#[cfg(feature = "parquet")]
+pub mod parquet_output;
+#[cfg(feature = "service")]
+pub mod planning_service;
+pub mod prelude;
+pub mod pursuer;
+#[cfg(feature = "batch")]
+pub mod report;
+pub mod run_log;
+pub mod scenario;
 pub mod simulation;
-pub mod statistics;
\ No newline at end of file
+pub mod simulation_builder;
+pub mod smoothing;
+#[cfg(feature = "terminal")]
+pub mod snapshot;
+pub mod statistics;
+pub mod sweep_spec;
+#[cfg(feature = "terminal")]
+pub mod terminal_renderer;
+pub mod vision;
+pub mod wall_pattern;
+#[cfg(feature = "wasm")]
+pub mod wasm;
+#[cfg(feature = "web")]
+pub mod web_viewer;
@@ -0,0 +1,288 @@
+use crate::algorithms::registry::AlgorithmRegistry;
+use crate::config::Config;
+use crate::error::SimulationError;
+use crate::grid::{GridView, Position};
+use crate::simulation::{AlgorithmResult, EnvironmentSetup, Failure, Simulation, StepOutcome};
+use crate::terminal_renderer::{
+    draw_frame, read_control, side_by_side, visible_width, Control, RawMode,
+};
+use crossterm::terminal;
+use crossterm::{cursor, execute};
+use std::fmt::Write as _;
+use std::io::{self, IsTerminal};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Columns a frame fills when it does not go to a terminal
+const PRINTED_WIDTH: usize = 160;
+
+/// Steps every algorithm's agent on the same environment in lockstep, drawing their
+/// grids side by side after every step (`compare --side-by-side`), so where their
+/// routes part ways shows as it happens rather than only in the final table. Grids
+/// that do not fit beside each other wrap onto more rows. An agent whose run is over
+/// stays where it ended, under how it ended.
+///
+/// It takes the renderer's keys: space pauses and resumes the runs, n shows the next
+/// step and pauses again, + and - double and halve the delay, and q (or Ctrl+C)
+/// stops every run.
+pub struct LockstepView {
+    lanes: Vec<Lane>,
+    delay_ms: u64,
+    paused: bool,
+    quit: bool,
+    color: bool,
+    /// Steps taken by the agents still running
+    steps: usize,
+    /// Where every agent starts
+    start: Position,
+}
+
+/// One algorithm's run in the view
+struct Lane {
+    name: String,
+    /// The run, or why it could not be set up
+    simulation: Result<Simulation, SimulationError>,
+    /// Cells the agent has stood on, in the order it came to them
+    trail: Vec<Position>,
+}
+
+impl Lane {
+    fn is_running(&self) -> bool {
+        self.simulation
+            .as_ref()
+            .is_ok_and(|simulation| !simulation.is_over())
+    }
+}
+
+impl LockstepView {
+    /// A view of every algorithm in the registry on the given environment, drawn
+    /// `config.delay_ms` apart
+    pub fn new(
+        config: &Config,
+        registry: Arc<AlgorithmRegistry>,
+        environment: EnvironmentSetup,
+    ) -> Result<Self, SimulationError> {
+        let start = environment.start;
+        let lanes = Simulation::comparison_simulations(config, registry, environment)?
+            .into_iter()
+            .map(|(name, mut simulation)| {
+                let trail = match &mut simulation {
+                    Ok(simulation) => {
+                        simulation.clear_observers();
+                        vec![simulation.agent.position]
+                    }
+                    Err(_) => Vec::new(),
+                };
+                Lane {
+                    name,
+                    simulation,
+                    trail,
+                }
+            })
+            .collect();
+        Ok(LockstepView {
+            lanes,
+            delay_ms: config.delay_ms,
+            paused: false,
+            quit: false,
+            color: !config.no_color
+                && io::stdout().is_terminal()
+                && std::env::var_os("NO_COLOR").is_none(),
+            steps: 0,
+            start,
+        })
+    }
+
+    /// Step the agents together until every run is over or q is pressed, drawing
+    /// them after every step, and return the result of each algorithm in the
+    /// registry's order
+    pub fn run(mut self, config: &Config) -> Vec<AlgorithmResult> {
+        let in_place = io::stdout().is_terminal();
+        if in_place {
+            let _ = execute!(
+                io::stdout(),
+                terminal::Clear(terminal::ClearType::All),
+                cursor::Hide
+            );
+        }
+        draw_frame(&self.frame());
+        self.pause();
+        while !self.quit && self.lanes.iter().any(Lane::is_running) {
+            for lane in &mut self.lanes {
+                let Ok(simulation) = &mut lane.simulation else {
+                    continue;
+                };
+                if simulation.is_over() {
+                    continue;
+                }
+                if let StepOutcome::Moved { to, .. } = simulation.step() {
+                    lane.trail.push(to);
+                }
+            }
+            self.steps += 1;
+            draw_frame(&self.frame());
+            self.pause();
+        }
+        if in_place {
+            let _ = execute!(io::stdout(), cursor::Show);
+        }
+
+        self.lanes
+            .into_iter()
+            .map(|lane| match lane.simulation {
+                Ok(mut simulation) => {
+                    let results = simulation.finish();
+                    AlgorithmResult::finished(lane.name, &simulation, results)
+                }
+                Err(e) => {
+                    AlgorithmResult::not_started(lane.name, config, self.start, &e.to_string())
+                }
+            })
+            .collect()
+    }
+
+    /// Every agent's grid under its name and how its run is going, with as many
+    /// grids beside each other as fit the terminal
+    fn frame(&self) -> String {
+        let mut frame = String::new();
+        let running = self.lanes.iter().filter(|lane| lane.is_running()).count();
+        let _ = writeln!(frame, "=== SIDE-BY-SIDE COMPARISON ===");
+        let _ = writeln!(
+            frame,
+            "Step: {} | Running: {}/{}",
+            self.steps,
+            running,
+            self.lanes.len()
+        );
+        if let Some(simulation) = self
+            .lanes
+            .iter()
+            .find_map(|lane| lane.simulation.as_ref().ok())
+        {
+            let view = GridView {
+                agent: Some(simulation.agent.position),
+                path: &[],
+                visited: &[],
+                seen: None,
+                marks: None,
+                window: None,
+                color: self.color,
+            };
+            frame.push_str(&simulation.grid.legend(&view));
+        }
+
+        let columns: Vec<(String, String)> =
+            self.lanes.iter().map(|lane| self.column(lane)).collect();
+        let room = match io::stdout().is_terminal() {
+            true => terminal::size().map_or(PRINTED_WIDTH, |(columns, _)| columns as usize),
+            false => PRINTED_WIDTH,
+        };
+        let mut row: Vec<(String, String)> = Vec::new();
+        let mut width = 0;
+        for column in columns {
+            let column_width = [column.0.as_str()]
+                .into_iter()
+                .chain(column.1.lines())
+                .map(visible_width)
+                .max()
+                .unwrap_or(0)
+                + 3;
+            if !row.is_empty() && width + column_width > room {
+                frame.push_str(&side_by_side(&row));
+                frame.push('\n');
+                row.clear();
+                width = 0;
+            }
+            width += column_width;
+            row.push(column);
+        }
+        frame.push_str(&side_by_side(&row));
+        frame
+    }
+
+    /// The title of an agent's grid, and the grid under a line on how its run is
+    /// going
+    fn column(&self, lane: &Lane) -> (String, String) {
+        let simulation = match &lane.simulation {
+            Ok(simulation) => simulation,
+            Err(e) => return (lane.name.clone(), format!("Not started: {}\n", e)),
+        };
+        let (progress, _) = simulation.agent.get_path_progress();
+        let path = simulation
+            .agent
+            .get_current_path()
+            .map_or(&[][..], |path| &path[progress.min(path.len())..]);
+        let view = GridView {
+            agent: Some(simulation.agent.position),
+            path,
+            visited: &lane.trail,
+            seen: None,
+            marks: None,
+            window: None,
+            color: self.color,
+        };
+        let status = match simulation.outcome() {
+            Some(StepOutcome::Finished) => "reached the goal",
+            Some(StepOutcome::Failed(Failure::NoPath)) => "no path",
+            Some(StepOutcome::Failed(Failure::Stuck)) => "stuck",
+            Some(StepOutcome::Failed(Failure::OutOfEnergy)) => "out of energy",
+            Some(StepOutcome::Failed(Failure::Caught)) => "caught",
+            Some(StepOutcome::Failed(Failure::StepLimit)) => "step limit",
+            Some(StepOutcome::Failed(Failure::TimedOut)) => "timed out",
+            _ => "running",
+        };
+        let mut block = format!(
+            "Moves: {} | Replans: {} | {}\n",
+            simulation.statistics().total_moves,
+            simulation.planner_calls().saturating_sub(1),
+            status
+        );
+        block.push_str(&simulation.grid.render_cells(&view));
+        (lane.name.clone(), block)
+    }
+
+    /// Show a frame for the delay, or for as long as the view is paused, acting on
+    /// the keys pressed meanwhile. Just waits out the delay without a keyboard.
+    fn pause(&mut self) {
+        let raw_mode = match io::stdin().is_terminal() {
+            true => RawMode::enable().ok(),
+            false => None,
+        };
+        if raw_mode.is_none() {
+            thread::sleep(Duration::from_millis(self.delay_ms));
+            return;
+        }
+        let deadline = Instant::now() + Duration::from_millis(self.delay_ms);
+        loop {
+            let timeout = match self.paused {
+                true => None,
+                false => Some(deadline.saturating_duration_since(Instant::now())),
+            };
+            let control = match read_control(timeout) {
+                Ok(Some(control)) => control,
+                // Out of time, or no keys to read after all
+                Ok(None) | Err(_) => break,
+            };
+            match control {
+                Control::Pause => {
+                    self.paused = !self.paused;
+                    if !self.paused {
+                        break;
+                    }
+                }
+                Control::Next => {
+                    self.paused = true;
+                    break;
+                }
+                Control::Slower => self.delay_ms = (self.delay_ms * 2).max(10),
+                Control::Faster => self.delay_ms /= 2,
+                Control::Quit => {
+                    self.quit = true;
+                    self.paused = false;
+                    break;
+                }
+            }
+        }
+    }
+}
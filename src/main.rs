@@ -1,98 +1,699 @@
 use clap::Parser;
 
+use dynamic_pathfinding::algorithms::registry::AlgorithmRegistry;
 use dynamic_pathfinding::batch_simulation::BatchSimulation;
-use dynamic_pathfinding::config::Config;
-use dynamic_pathfinding::simulation::Simulation;
+use dynamic_pathfinding::bench::{Bench, REPLAN_WORKLOAD};
+use dynamic_pathfinding::checkpoint::{Checkpoint, CheckpointWriter};
+use dynamic_pathfinding::config::{
+    CheckpointOptions, Cli, Command, Config, DisplayOptions, ReplayArgs, RunArgs,
+};
+use dynamic_pathfinding::error::{ConfigError, SimulationError};
+use dynamic_pathfinding::gif_recorder::GifRecorder;
+use dynamic_pathfinding::lockstep::LockstepView;
+use dynamic_pathfinding::map_file::MapLayout;
+use dynamic_pathfinding::messages;
+use dynamic_pathfinding::observer::{SimulationObserver, Viewport};
+use dynamic_pathfinding::say;
+use dynamic_pathfinding::scenario::Scenario;
+use dynamic_pathfinding::simulation::{AlgorithmResult, EnvironmentSetup, Simulation};
+use dynamic_pathfinding::snapshot::SnapshotExporter;
+use std::io::IsTerminal;
+use std::sync::Arc;
 use std::time::Duration;
+use tracing::level_filters::LevelFilter;
+use tracing_subscriber::fmt::format::FmtSpan;
 
-fn main() {
-    let config = Config::parse();
+/// The command with its options in layers, each overriding the ones before: the
+/// defaults, the `--config` file, the scenario's settings, `DYNAMIC_PATHFINDING_*`
+/// environment variables and the command line; along with the scenario's
+/// environment and checkpoint, if any
+fn load_command() -> Result<(Command, Option<EnvironmentSetup>, Option<Checkpoint>), String> {
+    let args: Vec<String> = std::env::args().collect();
+    let environment_args = Cli::environment_args(std::env::vars())?;
+    // The files to read can come from the environment or the command line
+    let files = Cli::layered(std::slice::from_ref(&environment_args), &args)?.command;
 
-    println!("Starting pathfinding simulation...");
-    println!("Grid size: {}x{}", config.grid_size, config.grid_size);
-    println!(
-        "Walls: {}, Obstacles: {}",
-        config.num_walls, config.num_obstacles
-    );
-    println!("Algorithm: {}", config.algorithm);
+    let mut layers = Vec::new();
+    if let Some(path) = &files.config().config {
+        layers.push(Cli::file_args(path)?);
+    }
+    let scenario = files.scenario().map(Scenario::load).transpose()?;
+    if let Some(scenario) = &scenario {
+        layers.push(scenario.settings_args()?);
+    }
+    layers.push(environment_args);
+    let command = Cli::layered(&layers, &args)?.command;
 
-    if config.no_visualization || config.batch_mode {
-        println!("Visualization disabled - running in fast mode");
+    let (environment, checkpoint) = match &scenario {
+        Some(scenario) => (
+            scenario.environment(command.config())?,
+            scenario.checkpoint()?,
+        ),
+        None => (None, None),
+    };
+    if let (Command::Replay(args), None) = (&command, &environment) {
+        return Err(format!(
+            "Scenario '{}' has no [environment] to replay",
+            args.file
+        ));
+    }
+    Ok((command, environment, checkpoint))
+}
+
+/// Print the options every command shares
+fn print_settings(command: &Command) {
+    let config = command.config();
+    say!("Grid size: {}x{}", config.width(), config.height());
+    if let Some(map_image) = &config.map_image {
+        say!("Map: {}", map_image);
+    }
+    if let Some(map_file) = &config.map_file {
+        match (&config.scen_file, config.scen_index) {
+            (Some(scen_file), Some(index)) => {
+                say!("Map: {} (scenario {} of {})", map_file, index, scen_file)
+            }
+            (Some(scen_file), None) => {
+                say!("Map: {} (random scenarios from {})", map_file, scen_file)
+            }
+            (None, _) => say!("Map: {}", map_file),
+        }
+    }
+    if config.floors() > 1 {
+        say!(
+            "Floors: {} ({} stairwells between floors)",
+            config.floors(),
+            config.stairs_per_floor
+        );
+    }
+    if config.portals > 0 {
+        say!("Portals: {} pairs", config.portals);
+    }
+    if let Some(script) = &config.obstacle_script {
+        say!(
+            "Obstacle script: {} (replaces the random obstacles)",
+            script
+        );
+    }
+    if config.obstacle_model != "uniform" {
+        say!(
+            "Obstacle model: {} ({} hotspots)",
+            config.obstacle_model,
+            config.hotspots
+        );
+    }
+    if let Some(rate) = config.arrival_rate {
+        say!("Obstacle arrival rate: {} per step", rate);
+    }
+    if config.moving_obstacles > 0 {
+        say!(
+            "Moving obstacles: {} ({})",
+            config.moving_obstacles,
+            config.obstacle_trajectory
+        );
+    }
+    if config.knowledge != "map" {
+        say!("Knowledge: {}", config.knowledge);
+    }
+    if config.miss_rate > 0.0 || config.false_alarm_rate > 0.0 {
+        say!(
+            "Sensor noise: {} miss rate, {} false alarm rate",
+            config.miss_rate,
+            config.false_alarm_rate
+        );
+    }
+    if config.memory != "forever" {
+        say!("Memory: {}", config.memory);
+    }
+    if let Some(radius) = config.vision_radius {
+        say!("Vision: {} cells ({})", radius, config.vision_shape);
+    }
+    if config.moving_goal || !config.goal_route.is_empty() {
+        say!(
+            "Moving goal: {}, one cell every {} cycle(s)",
+            if config.moving_goal {
+                "random wander"
+            } else {
+                "scripted route"
+            },
+            config.goal_move_interval
+        );
+    }
+    if config.agents > 1 {
+        say!("Agents: {}", config.agents);
+    }
+    match config.coordination.as_str() {
+        "independent" => {}
+        "whca" => say!("Coordination: whca ({}-step window)", config.whca_window),
+        "cbs" => say!(
+            "Coordination: cbs (up to {} nodes per joint plan)",
+            config.cbs_node_limit
+        ),
+        coordination => say!("Coordination: {}", coordination),
+    }
+    if config.pursuer {
+        say!("Pursuer: chasing with {}", config.pursuer_algorithm);
+    }
+    if config.wait_for_obstacles {
+        say!("Waiting for obstacles: enabled");
+    }
+    if config.obstacle_cost != "blocked" {
+        say!("Obstacle cost: {}", config.obstacle_cost);
+    }
+    if let Some(budget) = config.energy_budget {
+        say!("Energy budget: {}", budget);
+    }
+    if let Some(seconds) = config.simulation_timeout_seconds {
+        say!("Simulation timeout: {} seconds", seconds);
+    }
+    if let Some(ms) = config.find_path_budget_ms {
+        say!("find_path budget: {} ms", ms);
+    }
+    if config.wall_events > 0 {
+        say!("Wall events: {}", config.wall_events);
+    }
+    if config.map_file.is_some() || config.map_image.is_some() {
+        say!("Obstacles: {}", config.num_obstacles);
+    } else if config.map_style != "random" {
+        say!(
+            "Map style: {}, Obstacles: {}",
+            config.map_style,
+            config.num_obstacles
+        );
+    } else if config.wall_pattern != "random" {
+        say!(
+            "Walls: {} ({}), Obstacles: {}",
+            config.num_walls,
+            config.wall_pattern,
+            config.num_obstacles
+        );
     } else {
-        println!("Visualization enabled with {}ms delay", config.delay_ms);
-        println!("Press Ctrl+C to stop the simulation");
+        say!(
+            "Walls: {}, Obstacles: {}",
+            config.num_walls,
+            config.num_obstacles
+        );
+    }
+    match command {
+        Command::Compare(_) => say!("Algorithm: all"),
+        _ => say!("Algorithm: {}", config.algorithm),
+    }
+    if let Some(seed) = config.seed {
+        say!("Seed: {}", seed);
     }
+    if let Some(path) = &config.config {
+        say!("Config file: {}", path);
+    }
+    match (command, command.scenario()) {
+        (Command::Batch(_) | Command::Bench(_), Some(path)) => say!(
+            "Scenario: {} (settings only; this command generates its environments)",
+            path
+        ),
+        (_, Some(path)) => say!("Scenario: {}", path),
+        (_, None) => {}
+    }
+    if let Command::Run(RunArgs { checkpoints, .. })
+    | Command::Replay(ReplayArgs { checkpoints, .. }) = command
+    {
+        if let Some(path) = &checkpoints.checkpoint {
+            say!(
+                "Checkpoints: every {} steps to {}",
+                checkpoints.checkpoint_interval,
+                path
+            );
+        }
+    }
+}
 
-    if config.quiet {
-        println!("Quiet mode enabled - minimal output");
+/// Write the options and environment of a run to a scenario file, and return the
+/// environment (generated now if there was none yet) for the run to use
+fn dump_scenario(
+    config: &Config,
+    environment: Option<EnvironmentSetup>,
+    path: &str,
+) -> EnvironmentSetup {
+    let dumped = match environment {
+        Some(environment) => Ok(environment),
+        None => EnvironmentSetup::from_config(config, config.seed),
+    }
+    .and_then(|environment| {
+        Scenario::save(config, &environment, path)
+            .map(|()| environment)
+            .map_err(SimulationError::from)
+    });
+    match dumped {
+        Ok(dumped) => {
+            say!("Scenario written to {}", path);
+            dumped
+        }
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
     }
+}
+
+/// Set up drawing for the run and replay commands
+fn show(config: &mut Config, display: &DisplayOptions) -> Result<(), String> {
+    config.delay_ms = display.delay_ms;
+    config.no_visualization = display.no_visualization;
+    config.no_color = display.no_color;
+    config.show_belief = display.show_belief;
+    config.tui = display.tui;
+    config.debug_planner = display.debug_planner;
+    config.viewport = Viewport::from_name(&display.viewport)?;
+    config.overview = match display.overview {
+        Some(0) => return Err("--overview must be at least 1".to_string()),
+        overview => overview,
+    };
+    if config.no_visualization {
+        say!("Visualization disabled - running in fast mode");
+    } else if config.tui && !std::io::stdout().is_terminal() {
+        return Err("--tui draws a dashboard, which needs the output to be a terminal".to_string());
+    } else {
+        say!("Visualization enabled with {}ms delay", config.delay_ms);
+        say!("Keys: space pauses, n steps, + and - change the delay, q stops the run");
+    }
+    Ok(())
+}
 
-    println!();
+/// Serve the metrics of the runs at `address`
+#[cfg(feature = "metrics")]
+fn serve_metrics(address: &str) -> Result<(), String> {
+    let local = dynamic_pathfinding::metrics_exporter::serve(address)?;
+    say!("Serving metrics at http://{}/metrics", local);
+    Ok(())
+}
 
-    // Small delay before starting (only if visualization is enabled)
-    if !config.no_visualization || !config.batch_mode {
-        std::thread::sleep(std::time::Duration::from_millis(1000));
+#[cfg(not(feature = "metrics"))]
+fn serve_metrics(_address: &str) -> Result<(), String> {
+    Err(
+        "--metrics-address needs a build with the 'metrics' feature \
+         (cargo build --release --features metrics)"
+            .to_string(),
+    )
+}
+
+/// An observer serving the run to browsers at `address`
+#[cfg(feature = "web")]
+fn serve_viewer(address: &str) -> Result<Box<dyn SimulationObserver>, String> {
+    let viewer = dynamic_pathfinding::web_viewer::WebViewer::serve(address)?;
+    say!(
+        "Serving the run at http://{}/ - it starts once a browser opens the page",
+        viewer.address()
+    );
+    Ok(Box::new(viewer))
+}
+
+#[cfg(not(feature = "web"))]
+fn serve_viewer(_address: &str) -> Result<Box<dyn SimulationObserver>, String> {
+    Err(
+        "--serve needs a build with the 'web' feature (cargo build --release --features web)"
+            .to_string(),
+    )
+}
+
+/// Serve the planning API at `address` until the process is stopped
+#[cfg(feature = "service")]
+fn serve_planning(address: &str, config: Config) -> Result<(), String> {
+    let service = dynamic_pathfinding::planning_service::PlanningService::bind(address, config)?;
+    say!(
+        "Serving the planning API at http://{}/sessions",
+        service.address()
+    );
+    service.run();
+    Ok(())
+}
+
+#[cfg(not(feature = "service"))]
+fn serve_planning(_address: &str, _config: Config) -> Result<(), String> {
+    Err(
+        "The serve command needs a build with the 'service' feature \
+         (cargo build --release --features service)"
+            .to_string(),
+    )
+}
+
+/// Log `tracing` events up to `--log-level` to stderr, as text or with `--log-json`
+/// as JSON lines. Spans are logged as they close, with the time spent in them, so a
+/// `replan` span gives the time of each replan.
+fn start_logging(config: &Config) -> Result<(), String> {
+    let level: LevelFilter = config.log_level.parse().map_err(|_| {
+        format!(
+            "Unknown log level: '{}' (expected 'off', 'error', 'warn', 'info', 'debug' or \
+             'trace')",
+            config.log_level
+        )
+    })?;
+    messages::set_level(level);
+    let subscriber = tracing_subscriber::fmt()
+        .with_max_level(level)
+        .with_writer(std::io::stderr)
+        .with_ansi(std::io::stderr().is_terminal())
+        .with_span_events(FmtSpan::CLOSE);
+    match config.log_json {
+        true => subscriber.json().init(),
+        false => subscriber.init(),
     }
+    Ok(())
+}
 
-    // Check if we should run batch mode
-    if config.batch_mode {
-        let mut batch_sim = BatchSimulation::new(config.clone());
-        match batch_sim.run() {
-            Ok(()) => {
-                if !config.quiet {
-                    batch_sim.print_summary();
+fn main() {
+    // Parsed on its own first for --help and mistakes on the command line
+    Cli::parse();
+
+    let (mut command, mut environment, checkpoint) = match load_command() {
+        Ok((mut command, environment, checkpoint)) => {
+            // The grid is the scenario's, whatever its settings say
+            if let Some(environment) = &environment {
+                let config = command.config_mut();
+                config.grid_width = Some(environment.width);
+                config.grid_height = Some(environment.height);
+                config.floors = environment.floors;
+            }
+            (command, environment, checkpoint)
+        }
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+    };
+    if let Err(e) = MapLayout::fit_config(command.config_mut()) {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    }
+    let registry = AlgorithmRegistry::with_builtins();
+    if let Err(e) = command.validate(&registry) {
+        eprintln!("{}", e);
+        if let ConfigError::UnknownAlgorithm(_) = e {
+            let names: Vec<&str> = registry.names().collect();
+            eprintln!("Algorithms: {}", names.join(", "));
+        }
+        std::process::exit(1);
+    }
+    if let Err(e) = start_logging(command.config()) {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    }
+    if let Some(address) = &command.config().metrics_address {
+        if let Err(e) = serve_metrics(address) {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+    }
+    // Every run is seeded, so any run can be replayed with --seed
+    command.config_mut().seed.get_or_insert_with(rand::random);
+
+    // Results streamed to stdout leave it to them alone
+    if let Command::Batch(args) = &command {
+        if args.options.streams_results() {
+            messages::send_to_stderr();
+        }
+    }
+    // The planning API runs no simulations of its own
+    if !matches!(command, Command::Serve(_)) {
+        say!("Starting pathfinding simulation...");
+        print_settings(&command);
+    }
+
+    match command {
+        Command::Run(mut args) => {
+            if let Some(path) = &args.dump_scenario {
+                environment = Some(dump_scenario(&args.config, environment, path));
+            }
+            if let Err(e) = show(&mut args.config, &args.display) {
+                eprintln!("{}", e);
+                std::process::exit(1);
+            }
+            run_single(
+                args.config,
+                environment,
+                &args.display,
+                &args.checkpoints,
+                checkpoint,
+            );
+        }
+        Command::Replay(mut args) => {
+            if let Err(e) = show(&mut args.config, &args.display) {
+                eprintln!("{}", e);
+                std::process::exit(1);
+            }
+            run_single(
+                args.config,
+                environment,
+                &args.display,
+                &args.checkpoints,
+                checkpoint,
+            );
+        }
+        Command::Compare(mut args) => {
+            if let Some(path) = &args.dump_scenario {
+                environment = Some(dump_scenario(&args.config, environment, path));
+            }
+            let timing_histogram = args.config.timing_histogram;
+            // Run all algorithms and compare results
+            let results = if args.side_by_side {
+                say!("Side-by-side view enabled with {}ms delay", args.delay_ms);
+                say!("Keys: space pauses, n steps, + and - change the delay, q stops the runs");
+                say!();
+                args.config.delay_ms = args.delay_ms;
+                args.config.no_color = args.no_color;
+                compare_side_by_side(args.config, environment)
+            } else {
+                say!("Visualization disabled - running in fast mode");
+                say!();
+                match environment {
+                    Some(environment) => Simulation::run_all_algorithms_on(
+                        args.config,
+                        Arc::new(AlgorithmRegistry::with_builtins()),
+                        environment,
+                    ),
+                    None => Simulation::run_all_algorithms(args.config),
+                }
+            };
+            match results {
+                Ok(results) => {
+                    Simulation::print_comparison_results(&results);
+                    if timing_histogram {
+                        println!("\n=== FIND_PATH TIME HISTOGRAMS ===");
+                        for result in &results {
+                            println!("\n{}:", result.name);
+                            result.timing_data.print_histogram();
+                        }
+                    }
                 }
+                Err(e) => {
+                    eprintln!("Error running all algorithms: {}", e);
+                    std::process::exit(1);
+                }
+            }
+        }
+        Command::Batch(args) => {
+            say!("Visualization disabled - running in fast mode");
+            if args.options.quiet {
+                say!("Quiet mode enabled - minimal output");
+            }
+            say!();
+            let quiet = args.options.quiet;
+            let mut batch_sim = BatchSimulation::new(args.config, args.options);
+            match batch_sim.run() {
+                Ok(()) => {
+                    if !quiet {
+                        batch_sim.print_summary();
+                    }
+                }
+                Err(e) => {
+                    eprintln!("Batch simulation failed: {}", e);
+                    std::process::exit(1);
+                }
+            }
+        }
+        Command::Bench(args) => {
+            let mut bench = Bench::new(args.config, args.runs);
+            let result = if args.suite {
+                println!(
+                    "Benchmark suite: {} runs of each workload, {} timed samples each",
+                    args.runs, args.samples
+                );
+                bench
+                    .run_suite(args.samples)
+                    .map(|()| bench.print_suite_summary())
+            } else if let Some(replans) = args.replans {
+                println!(
+                    "Replan benchmark: {} replans on each of {} {}x{} grids",
+                    replans, args.runs, REPLAN_WORKLOAD.size, REPLAN_WORKLOAD.size
+                );
+                bench.run_replans(replans).map(|()| bench.print_summary())
+            } else {
+                println!("Benchmark: {} runs", args.runs);
+                println!();
+                bench.run().map(|()| bench.print_summary())
+            };
+            if let Err(e) = result {
+                eprintln!("Benchmark failed: {}", e);
+                std::process::exit(1);
             }
+        }
+        Command::Serve(args) => {
+            if let Err(e) = serve_planning(&args.listen, args.config) {
+                eprintln!("{}", e);
+                std::process::exit(1);
+            }
+        }
+    }
+}
+
+/// Run every algorithm on the environment (or one generated from the seed) in
+/// lockstep, drawing their grids side by side, and return their results
+fn compare_side_by_side(
+    config: Config,
+    environment: Option<EnvironmentSetup>,
+) -> Result<Vec<AlgorithmResult>, SimulationError> {
+    let environment = match environment {
+        Some(environment) => environment,
+        None => EnvironmentSetup::from_config(&config, config.seed)?,
+    };
+    let view = LockstepView::new(
+        &config,
+        Arc::new(AlgorithmRegistry::with_builtins()),
+        environment,
+    )?;
+    Ok(view.run(&config))
+}
+
+/// Run one algorithm on the environment (or a generated one), from the checkpoint
+/// if there is one, recording it and saving a snapshot of it if asked to, and print
+/// its results
+fn run_single(
+    config: Config,
+    environment: Option<EnvironmentSetup>,
+    display: &DisplayOptions,
+    checkpoints: &CheckpointOptions,
+    resume: Option<Checkpoint>,
+) {
+    if config.algorithm == "all" {
+        eprintln!("Use the compare command to run every algorithm");
+        std::process::exit(1);
+    }
+    // Checkpoints are written with the environment the run starts from
+    let environment = match (&checkpoints.checkpoint, environment) {
+        (Some(_), None) => match EnvironmentSetup::from_config(&config, config.seed) {
+            Ok(environment) => Some(environment),
             Err(e) => {
-                eprintln!("Batch simulation failed: {}", e);
+                eprintln!("{}", e);
                 std::process::exit(1);
             }
+        },
+        (_, environment) => environment,
+    };
+    let writer = match (&checkpoints.checkpoint, &environment) {
+        (Some(path), Some(environment)) => {
+            match CheckpointWriter::new(path, checkpoints.checkpoint_interval, environment.clone())
+            {
+                Ok(writer) => Some(writer),
+                Err(e) => {
+                    eprintln!("{}", e);
+                    std::process::exit(1);
+                }
+            }
         }
-    } else if config.algorithm == "all" {
-        // Run all algorithms and compare results
-        match Simulation::run_all_algorithms(config) {
-            Ok(results) => {
-                Simulation::print_comparison_results(&results);
+        _ => None,
+    };
+    let recorder = match &display.record_gif {
+        Some(path) => match GifRecorder::new(path, display.gif_cell_size, display.delay_ms) {
+            Ok(recorder) => Some(recorder),
+            Err(e) => {
+                eprintln!("{}", e);
+                std::process::exit(1);
+            }
+        },
+        None => None,
+    };
+    let snapshot = match &display.snapshot {
+        Some(path) => match SnapshotExporter::new(path, display.snapshot_cell_size) {
+            Ok(snapshot) => Some(snapshot),
+            Err(e) => {
+                eprintln!("{}", e);
+                std::process::exit(1);
             }
+        },
+        None => None,
+    };
+    let viewer = match &display.serve {
+        Some(address) => match serve_viewer(address) {
+            Ok(viewer) => Some(viewer),
             Err(e) => {
-                eprintln!("Error running all algorithms: {}", e);
+                eprintln!("{}", e);
                 std::process::exit(1);
             }
+        },
+        None => None,
+    };
+    say!();
+    // Small delay before starting (only if visualization is enabled)
+    if !config.no_visualization {
+        std::thread::sleep(std::time::Duration::from_millis(1000));
+    }
+
+    // Run single algorithm
+    if let Ok(mut simulation) = Simulation::new_with_environment(config.clone(), environment) {
+        if let Some(writer) = writer {
+            simulation.add_observer(Box::new(writer));
         }
-    } else {
-        // Run single algorithm
-        if let Ok(mut simulation) = Simulation::new(config.clone()) {
-            let (stats, algorithm_stats, timing_data) = simulation.run();
-
-            println!("\n=== FINAL RESULTS ===");
-            println!("{}", stats);
-            println!("{}", algorithm_stats);
-
-            // Print timing information
-            println!("\n=== TIMING ANALYSIS ===");
-            println!("Total pathfinding calls: {}", timing_data.total_calls());
-            println!(
-                "Average find_path time: {:.2?}",
-                timing_data.average_find_path_time()
-            );
+        if let Some(recorder) = recorder {
+            simulation.add_observer(Box::new(recorder));
+        }
+        if let Some(snapshot) = snapshot {
+            simulation.add_observer(Box::new(snapshot));
+        }
+        if let Some(viewer) = viewer {
+            simulation.add_observer(viewer);
+        }
+        if let Some(checkpoint) = resume {
+            if let Err(e) = checkpoint.resume(&mut simulation) {
+                eprintln!("{}", e);
+                std::process::exit(1);
+            }
+            say!("Resumed from the checkpoint at step {}", checkpoint.step);
+        }
+        let (stats, planner_metrics, timing_data) = simulation.run();
 
-            if timing_data.total_calls() > 0 {
-                let total_find_path_time: Duration = timing_data.find_path_times.iter().sum();
+        println!("\n=== FINAL RESULTS ===");
+        println!("{}", stats);
+        println!("{}", planner_metrics);
 
-                println!("Total time in find_path: {:.2?}", total_find_path_time);
+        // Print timing information
+        println!("\n=== TIMING ANALYSIS ===");
+        // The initial plan is not timed, so this leaves it out of the planner's calls
+        println!("Timed replans: {}", timing_data.total_calls());
+        println!(
+            "Average find_path time: {:.2?}",
+            timing_data.average_find_path_time()
+        );
 
-                // Additional analysis
-                if stats.total_moves > 0 {
-                    let extra_moves = stats.total_moves.saturating_sub(stats.optimal_path_length);
-                    println!(
-                        "Extra moves due to obstacles/limited vision: {}",
-                        extra_moves
-                    );
-                }
-            } else {
-                println!("Failed to create simulation - likely no valid path exists with current configuration");
-                println!("Try reducing --num-walls or increasing --grid-size");
+        if timing_data.total_calls() > 0 {
+            let total_find_path_time: Duration = timing_data.find_path_times.iter().sum();
+
+            println!("Total time in find_path: {:.2?}", total_find_path_time);
+            println!("find_path time spread: {}", timing_data.percentiles());
+            if config.timing_histogram {
+                println!("find_path time histogram:");
+                timing_data.print_histogram();
+            }
+
+            // Additional analysis
+            if stats.total_moves > 0 {
+                let extra_moves = stats.total_moves.saturating_sub(stats.optimal_path_length);
+                println!(
+                    "Extra moves due to obstacles/limited vision: {}",
+                    extra_moves
+                );
             }
+        } else if stats.timed_out {
+            println!("Timed out before the first plan was found");
+            println!("Try raising --simulation-timeout-seconds");
+        } else {
+            println!("Failed to create simulation - likely no valid path exists with current configuration");
+            println!("Try reducing --num-walls or increasing --grid-size");
         }
     }
 }
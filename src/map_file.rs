@@ -0,0 +1,300 @@
+use crate::config::Config;
+use crate::error::SimulationError;
+use crate::grid::Position;
+use image::ImageError;
+use std::collections::HashSet;
+
+/// A fixed grid layout read from a file instead of generated at random
+#[derive(Debug, Clone)]
+pub struct MapLayout {
+    pub width: usize,
+    pub height: usize,
+    pub walls: HashSet<Position>,
+    /// Start and goal pairs from a scenario file (empty without one)
+    pub scenarios: Vec<Scenario>,
+    /// Start marked on the map itself, as on image maps
+    pub start: Option<Position>,
+    /// Goal marked on the map itself, as on image maps
+    pub goal: Option<Position>,
+}
+
+/// One start/goal query of a MovingAI scenario file
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Scenario {
+    pub start: Position,
+    pub goal: Position,
+    /// Optimal octile path length published with the scenario
+    pub optimal_length: f64,
+}
+
+impl MapLayout {
+    /// The layout named by `--map-file` (with the queries of `--scen-file`) or
+    /// `--map-image`, or `None` to generate random walls
+    pub fn from_config(config: &Config) -> Result<Option<Self>, SimulationError> {
+        if config.map_file.is_some() && config.map_image.is_some() {
+            return Err("Use either --map-file or --map-image, not both"
+                .to_string()
+                .into());
+        }
+        if config.floors() > 1 && (config.map_file.is_some() || config.map_image.is_some()) {
+            return Err("Maps loaded from a file have a single floor"
+                .to_string()
+                .into());
+        }
+        if config.map_file.is_none() && config.scen_file.is_some() {
+            return Err("--scen-file needs a --map-file".to_string().into());
+        }
+        if let Some(path) = &config.map_image {
+            return Self::load_image(path).map(Some);
+        }
+        let Some(path) = &config.map_file else {
+            return Ok(None);
+        };
+
+        let mut layout = Self::load_moving_ai(path)?;
+        if let Some(scen_path) = &config.scen_file {
+            layout.load_scenarios(scen_path)?;
+            if let Some(index) = config.scen_index {
+                if index >= layout.scenarios.len() {
+                    return Err(format!(
+                        "--scen-index {} is out of range, '{}' has {} scenario(s)",
+                        index,
+                        scen_path,
+                        layout.scenarios.len()
+                    )
+                    .into());
+                }
+            }
+        } else if config.scen_index.is_some() {
+            return Err("--scen-index needs a --scen-file".to_string().into());
+        }
+        Ok(Some(layout))
+    }
+
+    /// Set `--grid-width` and `--grid-height` to the size of the loaded map, so
+    /// everything that reports the grid size matches the loaded map
+    pub fn fit_config(config: &mut Config) -> Result<(), SimulationError> {
+        if let Some(layout) = Self::from_config(config)? {
+            config.grid_width = Some(layout.width);
+            config.grid_height = Some(layout.height);
+        }
+        Ok(())
+    }
+
+    /// Read a map in the MovingAI benchmark format: a header with "type", "height" and
+    /// "width" lines, then "map" followed by one row of cells per line. '.', 'G' and
+    /// 'S' are passable; '@', 'O', 'T' and 'W' are walls.
+    pub fn load_moving_ai(path: &str) -> Result<Self, SimulationError> {
+        let text = std::fs::read_to_string(path)
+            .map_err(|e| SimulationError::io(format!("Failed to read map file '{}'", path), e))?;
+        let mut lines = text.lines();
+
+        let (mut width, mut height) = (None, None);
+        for line in lines.by_ref() {
+            let mut fields = line.split_whitespace();
+            match (fields.next(), fields.next()) {
+                (Some("map"), None) => break,
+                (Some("type"), _) | (None, _) => {}
+                (Some(key @ ("width" | "height")), Some(value)) => {
+                    let value = value.parse::<usize>().map_err(|_| {
+                        format!("Invalid {} '{}' in map file '{}'", key, value, path)
+                    })?;
+                    if key == "width" {
+                        width = Some(value);
+                    } else {
+                        height = Some(value);
+                    }
+                }
+                _ => {
+                    return Err(
+                        format!("Unexpected header line '{}' in map file '{}'", line, path).into(),
+                    )
+                }
+            }
+        }
+        let (Some(width), Some(height)) = (width, height) else {
+            return Err(format!("Map file '{}' is missing its width or height", path).into());
+        };
+
+        let mut walls = HashSet::new();
+        let rows: Vec<&str> = lines.filter(|line| !line.trim().is_empty()).collect();
+        if rows.len() != height {
+            return Err(format!(
+                "Map file '{}' has {} rows, but its header says {}",
+                path,
+                rows.len(),
+                height
+            )
+            .into());
+        }
+        for (y, row) in rows.iter().enumerate() {
+            let row = row.trim_end();
+            if row.chars().count() != width {
+                return Err(format!(
+                    "Row {} of map file '{}' has {} cells, but its header says {}",
+                    y,
+                    path,
+                    row.chars().count(),
+                    width
+                )
+                .into());
+            }
+            for (x, cell) in row.chars().enumerate() {
+                match cell {
+                    '.' | 'G' | 'S' => {}
+                    '@' | 'O' | 'T' | 'W' => {
+                        walls.insert(Position { x, y, z: 0 });
+                    }
+                    _ => {
+                        return Err(format!(
+                            "Unknown cell '{}' at ({}, {}) in map file '{}'",
+                            cell, x, y, path
+                        )
+                        .into())
+                    }
+                }
+            }
+        }
+        if width * height < walls.len() + 2 {
+            return Err(format!("Map file '{}' needs at least two passable cells", path).into());
+        }
+
+        Ok(MapLayout {
+            width,
+            height,
+            walls,
+            scenarios: Vec::new(),
+            start: None,
+            goal: None,
+        })
+    }
+
+    /// Read a map drawn as a PNG or BMP image, one pixel per cell: dark pixels are
+    /// walls, a green pixel marks the start and a red one the goal. Larger markers are
+    /// fine; the topmost, leftmost pixel of each is used. Anything else (including
+    /// transparent pixels) is free.
+    pub fn load_image(path: &str) -> Result<Self, SimulationError> {
+        let image = image::open(path)
+            .map_err(|e| {
+                let context = format!("Failed to read map image '{}'", path);
+                match e {
+                    ImageError::IoError(e) => SimulationError::io(context, e),
+                    e => format!("{}: {}", context, e).into(),
+                }
+            })?
+            .to_rgba8();
+        let (width, height) = (image.width() as usize, image.height() as usize);
+
+        let (mut walls, mut start, mut goal) = (HashSet::new(), None, None);
+        for y in 0..height {
+            for x in 0..width {
+                let [r, g, b, a] = image.get_pixel(x as u32, y as u32).0;
+                let pos = Position { x, y, z: 0 };
+                if a < 128 {
+                    continue;
+                }
+                if g >= 128 && r < 100 && b < 100 {
+                    start.get_or_insert(pos);
+                } else if r >= 128 && g < 100 && b < 100 {
+                    goal.get_or_insert(pos);
+                } else if (r as u32 * 299 + g as u32 * 587 + b as u32 * 114) / 1000 < 128 {
+                    walls.insert(pos);
+                }
+            }
+        }
+        if width * height < walls.len() + 2 {
+            return Err(format!("Map image '{}' needs at least two passable cells", path).into());
+        }
+
+        Ok(MapLayout {
+            width,
+            height,
+            walls,
+            scenarios: Vec::new(),
+            start,
+            goal,
+        })
+    }
+
+    /// Read the queries of a MovingAI scenario file: an optional "version" line, then
+    /// one "bucket map width height start_x start_y goal_x goal_y optimal_length" line
+    /// per query
+    pub fn load_scenarios(&mut self, path: &str) -> Result<(), SimulationError> {
+        let text = std::fs::read_to_string(path).map_err(|e| {
+            SimulationError::io(format!("Failed to read scenario file '{}'", path), e)
+        })?;
+
+        let mut scenarios = Vec::new();
+        for (number, line) in text.lines().enumerate() {
+            if line.trim().is_empty() || line.starts_with("version") {
+                continue;
+            }
+            let invalid = || format!("Invalid line {} in scenario file '{}'", number + 1, path);
+
+            // Counted from the end, since map names may contain spaces
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            if fields.len() < 9 {
+                return Err(invalid().into());
+            }
+            let numbers = fields[fields.len() - 7..fields.len() - 1]
+                .iter()
+                .map(|value| value.parse::<usize>().map_err(|_| invalid()))
+                .collect::<Result<Vec<_>, _>>()?;
+            let optimal_length = fields[fields.len() - 1]
+                .parse::<f64>()
+                .map_err(|_| invalid())?;
+
+            if (numbers[0], numbers[1]) != (self.width, self.height) {
+                return Err(format!(
+                    "Line {} of scenario file '{}' is for a {}x{} map, but the map is {}x{}",
+                    number + 1,
+                    path,
+                    numbers[0],
+                    numbers[1],
+                    self.width,
+                    self.height
+                )
+                .into());
+            }
+            let start = Position {
+                x: numbers[2],
+                y: numbers[3],
+                z: 0,
+            };
+            let goal = Position {
+                x: numbers[4],
+                y: numbers[5],
+                z: 0,
+            };
+            for (what, pos) in [("starts", start), ("ends", goal)] {
+                if !self.is_free(pos) {
+                    return Err(format!(
+                        "Line {} of scenario file '{}' {} at ({}, {}), which is not a free cell",
+                        number + 1,
+                        path,
+                        what,
+                        pos.x,
+                        pos.y
+                    )
+                    .into());
+                }
+            }
+            scenarios.push(Scenario {
+                start,
+                goal,
+                optimal_length,
+            });
+        }
+
+        if scenarios.is_empty() {
+            return Err(format!("Scenario file '{}' has no scenarios", path).into());
+        }
+        self.scenarios = scenarios;
+        Ok(())
+    }
+
+    /// Whether the cell is on the map and not a wall
+    pub fn is_free(&self, pos: Position) -> bool {
+        pos.x < self.width && pos.y < self.height && pos.z == 0 && !self.walls.contains(&pos)
+    }
+}
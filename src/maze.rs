@@ -0,0 +1,205 @@
+use crate::grid::Position;
+use rand::seq::SliceRandom;
+use rand::Rng;
+use std::collections::HashSet;
+
+/// How the static walls of a generated environment are laid out.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MapStyle {
+    /// `--num-walls` walls scattered uniformly at random.
+    Random,
+    /// Perfect maze carved by a randomized depth-first search: long, winding
+    /// corridors with few branches.
+    RecursiveBacktracker,
+    /// Perfect maze grown with randomized Prim's algorithm: many short dead ends.
+    Prim,
+    /// Rectangular rooms joined by one-cell-wide L-shaped corridors.
+    Rooms,
+}
+
+impl MapStyle {
+    /// Parses a map style name as given on the command line.
+    pub fn from_name(name: &str) -> Result<Self, String> {
+        match name {
+            "random" => Ok(MapStyle::Random),
+            "recursive_backtracker" => Ok(MapStyle::RecursiveBacktracker),
+            "prim" => Ok(MapStyle::Prim),
+            "rooms" => Ok(MapStyle::Rooms),
+            _ => Err(format!(
+                "Unknown map style: '{}' (expected 'random', 'recursive_backtracker', 'prim' or 'rooms')",
+                name
+            )),
+        }
+    }
+
+    /// Walls of one floor in this style, or `None` for `Random`, which places
+    /// `--num-walls` walls instead.
+    pub fn generate<R: Rng>(
+        self,
+        rng: &mut R,
+        width: usize,
+        height: usize,
+        z: usize,
+    ) -> Option<HashSet<Position>> {
+        let open = match self {
+            MapStyle::Random => return None,
+            MapStyle::RecursiveBacktracker => recursive_backtracker(rng, width, height),
+            MapStyle::Prim => prim(rng, width, height),
+            MapStyle::Rooms => rooms(rng, width, height),
+        };
+        Some(
+            (0..width)
+                .flat_map(|x| (0..height).map(move |y| (x, y)))
+                .filter(|cell| !open.contains(cell))
+                .map(|(x, y)| Position { x, y, z })
+                .collect(),
+        )
+    }
+}
+
+/// Maze rooms sit on even coordinates, with the cell between two neighbouring
+/// rooms opened when they are joined. Returns the number of rooms across and down.
+fn maze_rooms(width: usize, height: usize) -> (usize, usize) {
+    (width.div_ceil(2), height.div_ceil(2))
+}
+
+/// Neighbouring maze rooms, in room coordinates.
+fn room_neighbors(room: (usize, usize), rooms: (usize, usize)) -> Vec<(usize, usize)> {
+    let (x, y) = room;
+    let mut neighbors = Vec::with_capacity(4);
+    if x > 0 {
+        neighbors.push((x - 1, y));
+    }
+    if y > 0 {
+        neighbors.push((x, y - 1));
+    }
+    if x + 1 < rooms.0 {
+        neighbors.push((x + 1, y));
+    }
+    if y + 1 < rooms.1 {
+        neighbors.push((x, y + 1));
+    }
+    neighbors
+}
+
+/// Open both rooms and the cell between them.
+fn join(open: &mut HashSet<(usize, usize)>, a: (usize, usize), b: (usize, usize)) {
+    open.insert((a.0 * 2, a.1 * 2));
+    open.insert((a.0 + b.0, a.1 + b.1));
+    open.insert((b.0 * 2, b.1 * 2));
+}
+
+fn recursive_backtracker<R: Rng>(
+    rng: &mut R,
+    width: usize,
+    height: usize,
+) -> HashSet<(usize, usize)> {
+    let rooms = maze_rooms(width, height);
+    let first = (rng.gen_range(0..rooms.0), rng.gen_range(0..rooms.1));
+    let mut open = HashSet::from([(first.0 * 2, first.1 * 2)]);
+    let mut visited = HashSet::from([first]);
+    let mut stack = vec![first];
+
+    while let Some(&room) = stack.last() {
+        let unvisited: Vec<_> = room_neighbors(room, rooms)
+            .into_iter()
+            .filter(|neighbor| !visited.contains(neighbor))
+            .collect();
+        match unvisited.choose(rng) {
+            Some(&next) => {
+                join(&mut open, room, next);
+                visited.insert(next);
+                stack.push(next);
+            }
+            None => {
+                stack.pop();
+            }
+        }
+    }
+    open
+}
+
+fn prim<R: Rng>(rng: &mut R, width: usize, height: usize) -> HashSet<(usize, usize)> {
+    let rooms = maze_rooms(width, height);
+    let first = (rng.gen_range(0..rooms.0), rng.gen_range(0..rooms.1));
+    let mut open = HashSet::from([(first.0 * 2, first.1 * 2)]);
+    let mut visited = HashSet::from([first]);
+    let mut frontier: Vec<_> = room_neighbors(first, rooms)
+        .into_iter()
+        .map(|neighbor| (first, neighbor))
+        .collect();
+
+    while !frontier.is_empty() {
+        let (from, room) = frontier.swap_remove(rng.gen_range(0..frontier.len()));
+        if !visited.insert(room) {
+            continue;
+        }
+        join(&mut open, from, room);
+        frontier.extend(
+            room_neighbors(room, rooms)
+                .into_iter()
+                .filter(|neighbor| !visited.contains(neighbor))
+                .map(|neighbor| (room, neighbor)),
+        );
+    }
+    open
+}
+
+fn rooms<R: Rng>(rng: &mut R, width: usize, height: usize) -> HashSet<(usize, usize)> {
+    let mut open = HashSet::new();
+    // Room corners and sizes; each room keeps a wall between itself and the others
+    let mut placed: Vec<(usize, usize, usize, usize)> = Vec::new();
+    let target = (width * height / 60).max(2);
+    let max_side = (width.min(height) / 3).clamp(2, 8);
+
+    for _ in 0..target * 10 {
+        if placed.len() >= target {
+            break;
+        }
+        let (w, h) = (rng.gen_range(2..=max_side), rng.gen_range(2..=max_side));
+        if w >= width || h >= height {
+            continue;
+        }
+        let (x, y) = (rng.gen_range(0..=width - w), rng.gen_range(0..=height - h));
+        let overlaps = placed
+            .iter()
+            .any(|&(ox, oy, ow, oh)| x <= ox + ow && ox <= x + w && y <= oy + oh && oy <= y + h);
+        if !overlaps {
+            placed.push((x, y, w, h));
+        }
+    }
+    if placed.is_empty() {
+        placed.push((0, 0, width, height));
+    }
+
+    for &(x, y, w, h) in &placed {
+        for cx in x..x + w {
+            for cy in y..y + h {
+                open.insert((cx, cy));
+            }
+        }
+    }
+
+    // Corridors join the rooms in the order they were placed, so they are all connected
+    let center = |&(x, y, w, h): &(usize, usize, usize, usize)| (x + w / 2, y + h / 2);
+    for pair in placed.windows(2) {
+        let ((ax, ay), (bx, by)) = (center(&pair[0]), center(&pair[1]));
+        let corner = if rng.gen_bool(0.5) {
+            (bx, ay)
+        } else {
+            (ax, by)
+        };
+        carve_line(&mut open, (ax, ay), corner);
+        carve_line(&mut open, corner, (bx, by));
+    }
+    open
+}
+
+/// Open every cell of a horizontal or vertical line.
+fn carve_line(open: &mut HashSet<(usize, usize)>, from: (usize, usize), to: (usize, usize)) {
+    for x in from.0.min(to.0)..=from.0.max(to.0) {
+        for y in from.1.min(to.1)..=from.1.max(to.1) {
+            open.insert((x, y));
+        }
+    }
+}
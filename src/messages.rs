@@ -0,0 +1,50 @@
+use std::fmt;
+use std::sync::atomic::{AtomicBool, Ordering};
+use tracing::level_filters::LevelFilter;
+
+/// Whether messages go to stderr, leaving stdout to the results of a batch
+static TO_STDERR: AtomicBool = AtomicBool::new(false);
+
+/// Whether `say!` prints, which it does at `--log-level info` and above
+static SAY: AtomicBool = AtomicBool::new(true);
+
+/// Print a line of the messages meant for people (settings, progress, summaries):
+/// to stdout, or to stderr once `send_to_stderr` leaves stdout to the results.
+/// Nothing is printed below `--log-level info`.
+#[macro_export]
+macro_rules! say {
+    () => {
+        $crate::messages::line(format_args!(""))
+    };
+    ($($arg:tt)*) => {
+        $crate::messages::line(format_args!($($arg)*))
+    };
+}
+
+/// Send every later message to stderr, as a batch does when it streams its results to
+/// stdout (`--output-file -`)
+pub fn send_to_stderr() {
+    TO_STDERR.store(true, Ordering::Relaxed);
+}
+
+/// Whether messages go to stderr
+pub fn to_stderr() -> bool {
+    TO_STDERR.load(Ordering::Relaxed)
+}
+
+/// Print messages only if `level`, the most detailed of the log events (`--log-level`),
+/// takes in `info`; the log events themselves are `tracing`'s
+pub fn set_level(level: LevelFilter) {
+    SAY.store(level >= LevelFilter::INFO, Ordering::Relaxed);
+}
+
+/// Print a message line where messages go; see `say!`
+pub fn line(message: fmt::Arguments) {
+    if !SAY.load(Ordering::Relaxed) {
+        return;
+    }
+    match to_stderr() {
+        true => eprintln!("{}", message),
+        false => println!("{}", message),
+    }
+}
@@ -0,0 +1,228 @@
+use crate::observer::{Replan, SimulationObserver};
+use crate::simulation::{Simulation, StepOutcome};
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::io::{BufRead, BufReader, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::sync::{Mutex, OnceLock};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// What the runs of one algorithm have done since the exporter started
+#[derive(Debug, Clone, Default)]
+struct AlgorithmCounters {
+    runs_started: u64,
+    runs_succeeded: u64,
+    runs_failed: u64,
+    steps: u64,
+    replans: u64,
+    find_path_nanos: u64,
+}
+
+/// Counters of every run, by algorithm, and when serving them started
+struct Exporter {
+    started: Instant,
+    counters: Mutex<BTreeMap<String, AlgorithmCounters>>,
+}
+
+/// The exporter, once `serve` has started it
+static EXPORTER: OnceLock<Exporter> = OnceLock::new();
+
+/// Longest a scrape may leave a read or write waiting; scrapes are answered one at
+/// a time, so a client that stalls would hold up every other
+const STREAM_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Serve the metrics of every later run in Prometheus' text format at
+/// `http://<address>/metrics`, from a thread of its own, for as long as the process
+/// runs. Returns the address bound, which has the port picked for port 0.
+pub fn serve(address: &str) -> Result<SocketAddr, String> {
+    let listener = TcpListener::bind(address)
+        .map_err(|e| format!("Cannot serve metrics at {}: {}", address, e))?;
+    let local = listener
+        .local_addr()
+        .map_err(|e| format!("Cannot serve metrics at {}: {}", address, e))?;
+    EXPORTER
+        .set(Exporter {
+            started: Instant::now(),
+            counters: Mutex::new(BTreeMap::new()),
+        })
+        .map_err(|_| "Metrics are already being served".to_string())?;
+    thread::spawn(move || {
+        for stream in listener.incoming().flatten() {
+            // A client that goes away mid-answer only loses its own answer
+            let _ = answer(stream);
+        }
+    });
+    Ok(local)
+}
+
+/// Whether `serve` has started, so new simulations should be counted
+pub fn is_serving() -> bool {
+    EXPORTER.get().is_some()
+}
+
+/// Answer a request: the metrics for GET /metrics, 404 for anything else
+fn answer(mut stream: TcpStream) -> std::io::Result<()> {
+    stream.set_read_timeout(Some(STREAM_TIMEOUT))?;
+    stream.set_write_timeout(Some(STREAM_TIMEOUT))?;
+    let mut request_line = String::new();
+    BufReader::new(&stream).read_line(&mut request_line)?;
+    let mut parts = request_line.split_whitespace();
+    let (method, path) = (parts.next().unwrap_or(""), parts.next().unwrap_or(""));
+    let (status, body) = match (method, path.split('?').next().unwrap_or("")) {
+        ("GET", "/metrics") => ("200 OK", render()),
+        _ => (
+            "404 Not Found",
+            "Metrics are served at /metrics\n".to_string(),
+        ),
+    };
+    write!(
+        stream,
+        "HTTP/1.1 {}\r\nContent-Type: text/plain; version=0.0.4; charset=utf-8\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        body.len(),
+        body
+    )
+}
+
+/// The metrics in Prometheus' text exposition format
+fn render() -> String {
+    let Some(exporter) = EXPORTER.get() else {
+        return String::new();
+    };
+    let uptime = exporter.started.elapsed().as_secs_f64();
+    let counters = exporter
+        .counters
+        .lock()
+        .map(|counters| counters.clone())
+        .unwrap_or_default();
+
+    let mut out = String::new();
+    let mut family =
+        |name: &str, kind: &str, help: &str, value: &dyn Fn(&AlgorithmCounters) -> f64| {
+            let _ = writeln!(out, "# HELP {} {}", name, help);
+            let _ = writeln!(out, "# TYPE {} {}", name, kind);
+            for (algorithm, counters) in &counters {
+                let _ = writeln!(
+                    out,
+                    "{}{{algorithm=\"{}\"}} {}",
+                    name,
+                    label_value(algorithm),
+                    value(counters)
+                );
+            }
+        };
+    family(
+        "pathfinding_runs_started_total",
+        "counter",
+        "Simulations started",
+        &|c| c.runs_started as f64,
+    );
+    family(
+        "pathfinding_runs_succeeded_total",
+        "counter",
+        "Simulations that ended with the agent on its goal",
+        &|c| c.runs_succeeded as f64,
+    );
+    family(
+        "pathfinding_runs_failed_total",
+        "counter",
+        "Simulations that ended without the agent on its goal",
+        &|c| c.runs_failed as f64,
+    );
+    family(
+        "pathfinding_steps_total",
+        "counter",
+        "Simulation steps taken",
+        &|c| c.steps as f64,
+    );
+    family(
+        "pathfinding_steps_per_second",
+        "gauge",
+        "Steps taken per second since the exporter started",
+        &|c| c.steps as f64 / uptime.max(f64::EPSILON),
+    );
+    family(
+        "pathfinding_replans_total",
+        "counter",
+        "Plans made after the initial one",
+        &|c| c.replans as f64,
+    );
+    family(
+        "pathfinding_find_path_seconds_total",
+        "counter",
+        "Time spent replanning",
+        &|c| c.find_path_nanos as f64 / 1e9,
+    );
+    family(
+        "pathfinding_average_find_path_seconds",
+        "gauge",
+        "Average time of a replan",
+        &|c| match c.replans {
+            0 => 0.0,
+            replans => c.find_path_nanos as f64 / 1e9 / replans as f64,
+        },
+    );
+    let _ = writeln!(
+        out,
+        "# HELP pathfinding_exporter_uptime_seconds Time since the exporter started"
+    );
+    let _ = writeln!(out, "# TYPE pathfinding_exporter_uptime_seconds gauge");
+    let _ = writeln!(out, "pathfinding_exporter_uptime_seconds {}", uptime);
+    out
+}
+
+/// `value` escaped to go between the quotes of a label: backslashes, double quotes
+/// and line breaks would otherwise end the label or the line early
+fn label_value(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+/// Counts what a simulation does into the exporter's metrics; every simulation
+/// has one while metrics are served
+#[derive(Debug, Clone, Copy, Default)]
+pub struct MetricsObserver;
+
+impl MetricsObserver {
+    /// Update the counters of the simulation's algorithm
+    fn count(simulation: &Simulation, update: impl FnOnce(&mut AlgorithmCounters)) {
+        let Some(exporter) = EXPORTER.get() else {
+            return;
+        };
+        if let Ok(mut counters) = exporter.counters.lock() {
+            update(
+                counters
+                    .entry(simulation.config().algorithm.clone())
+                    .or_default(),
+            );
+        }
+    }
+}
+
+impl SimulationObserver for MetricsObserver {
+    fn on_start(&mut self, simulation: &Simulation) {
+        Self::count(simulation, |c| c.runs_started += 1);
+    }
+
+    fn on_replan(&mut self, simulation: &Simulation, replan: &Replan) {
+        Self::count(simulation, |c| {
+            c.replans += 1;
+            c.find_path_nanos += replan.duration.as_nanos() as u64;
+        });
+    }
+
+    fn on_step(&mut self, simulation: &Simulation, _outcome: StepOutcome) {
+        Self::count(simulation, |c| c.steps += 1);
+    }
+
+    fn on_finish(&mut self, simulation: &Simulation) {
+        let succeeded = simulation.outcome() == Some(StepOutcome::Finished);
+        Self::count(simulation, |c| match succeeded {
+            true => c.runs_succeeded += 1,
+            false => c.runs_failed += 1,
+        });
+    }
+}
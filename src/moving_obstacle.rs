@@ -0,0 +1,210 @@
+use crate::grid::{Cell, Grid, Position, HEX_DIRECTIONS};
+use pathfinding::prelude::bfs;
+use rand::Rng;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// How a moving obstacle travels, one cell per simulation step.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum Trajectory {
+    /// Straight ahead, turning back when it runs into a wall or the edge of the grid.
+    Linear,
+    /// Round a loop of 2 to 4 random cells, along the shortest way between them.
+    Patrol,
+    /// A random neighbouring cell (or a pause) every step.
+    RandomWalk,
+}
+
+impl Trajectory {
+    /// Parses a trajectory name as given on the command line; `mixed` gives `None`,
+    /// which picks a trajectory at random for every obstacle.
+    pub fn from_name(name: &str) -> Result<Option<Self>, String> {
+        match name {
+            "linear" => Ok(Some(Trajectory::Linear)),
+            "patrol" => Ok(Some(Trajectory::Patrol)),
+            "random_walk" => Ok(Some(Trajectory::RandomWalk)),
+            "mixed" => Ok(None),
+            _ => Err(format!(
+                "Unknown obstacle trajectory: '{}' (expected 'linear', 'patrol', 'random_walk' or 'mixed')",
+                name
+            )),
+        }
+    }
+
+    /// Name of the trajectory as given on the command line.
+    pub fn name(self) -> &'static str {
+        match self {
+            Trajectory::Linear => "linear",
+            Trajectory::Patrol => "patrol",
+            Trajectory::RandomWalk => "random_walk",
+        }
+    }
+}
+
+/// An obstacle that keeps moving for the whole run.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct MovingObstacle {
+    pub trajectory: Trajectory,
+    /// Cells visited one per step, starting over at the beginning after the last one,
+    /// which is next to the first. Random walks are walked back to make the loop.
+    pub route: Vec<Position>,
+}
+
+impl MovingObstacle {
+    /// Up to `count` moving obstacles on the open cells of `grid`, avoiding its start
+    /// and goals. Random walks are `length` steps long before they turn back.
+    pub fn generate<R: Rng>(
+        rng: &mut R,
+        grid: &Grid,
+        count: usize,
+        trajectory: Option<Trajectory>,
+        length: usize,
+    ) -> Vec<Self> {
+        let mut obstacles = Vec::with_capacity(count);
+        let mut attempts = 0;
+        while obstacles.len() < count && attempts < count * 10 {
+            attempts += 1;
+            let trajectory = trajectory.unwrap_or_else(|| {
+                [
+                    Trajectory::Linear,
+                    Trajectory::Patrol,
+                    Trajectory::RandomWalk,
+                ][rng.gen_range(0..3)]
+            });
+            let Some(first) = random_open_cell(rng, grid) else {
+                continue;
+            };
+            let route = match trajectory {
+                Trajectory::Linear => linear(rng, grid, first),
+                Trajectory::Patrol => patrol(rng, grid, first),
+                Trajectory::RandomWalk => random_walk(rng, grid, first, length),
+            };
+            if let Some(route) = route {
+                obstacles.push(MovingObstacle { trajectory, route });
+            }
+        }
+        obstacles
+    }
+
+    /// Cell the obstacle heads for after `index` steps of its route
+    pub fn cell(&self, index: usize) -> Position {
+        self.route[index % self.route.len()]
+    }
+}
+
+/// Whether a moving obstacle may travel over the cell: plain ground that is not the
+/// start, a goal or a portal, so terrain costs and portals never change during a run
+fn is_open(grid: &Grid, pos: Position) -> bool {
+    grid.cell(pos) == Cell::Empty
+        && pos != grid.start
+        && !grid.is_goal(pos)
+        && !grid.portals.contains_key(&pos)
+}
+
+fn random_open_cell<R: Rng>(rng: &mut R, grid: &Grid) -> Option<Position> {
+    let pos = Position {
+        x: rng.gen_range(0..grid.width),
+        y: rng.gen_range(0..grid.height),
+        z: rng.gen_range(0..grid.floors),
+    };
+    is_open(grid, pos).then_some(pos)
+}
+
+/// Moves an obstacle can make: straight steps on its own floor
+fn directions(grid: &Grid) -> &'static [(i32, i32)] {
+    if grid.hex {
+        &HEX_DIRECTIONS
+    } else {
+        &[(0, 1), (0, -1), (1, 0), (-1, 0)]
+    }
+}
+
+fn open_neighbors(grid: &Grid, pos: Position) -> Vec<Position> {
+    directions(grid)
+        .iter()
+        .filter_map(|&(dx, dy)| grid.offset(pos, dx, dy))
+        .filter(|&next| is_open(grid, next))
+        .collect()
+}
+
+/// Back and forth along the open stretch through `first` in a random direction
+fn linear<R: Rng>(rng: &mut R, grid: &Grid, first: Position) -> Option<Vec<Position>> {
+    let all = directions(grid);
+    let (dx, dy) = all[rng.gen_range(0..all.len())];
+    let mut line = vec![first];
+    let mut ahead = first;
+    while let Some(next) = grid.offset(ahead, dx, dy) {
+        // On a wrapping grid a clear row or column would lead back to the start
+        if !is_open(grid, next) || line.contains(&next) {
+            break;
+        }
+        line.push(next);
+        ahead = next;
+    }
+    if line.len() < 2 {
+        return None;
+    }
+    // Out to the far end, then back to just before the start
+    let back = line[1..line.len() - 1]
+        .iter()
+        .rev()
+        .copied()
+        .collect::<Vec<_>>();
+    line.extend(back);
+    Some(line)
+}
+
+/// A loop through `first` and 1 to 3 more random cells
+fn patrol<R: Rng>(rng: &mut R, grid: &Grid, first: Position) -> Option<Vec<Position>> {
+    let mut stops = vec![first];
+    for _ in 0..rng.gen_range(1..=3) {
+        let stop = random_open_cell(rng, grid).filter(|stop| stop.z == first.z)?;
+        stops.push(stop);
+    }
+    stops.push(first);
+
+    let mut route = vec![first];
+    for leg in stops.windows(2) {
+        let path = bfs(
+            &leg[0],
+            |&pos| open_neighbors(grid, pos),
+            |&pos| pos == leg[1],
+        )?;
+        route.extend(path.into_iter().skip(1));
+    }
+    // The last cell is `first` again, where the loop starts over
+    route.pop();
+    (route.len() >= 2).then_some(route)
+}
+
+/// `length` random steps (or pauses), then the same steps back
+fn random_walk<R: Rng>(
+    rng: &mut R,
+    grid: &Grid,
+    first: Position,
+    length: usize,
+) -> Option<Vec<Position>> {
+    let mut route = vec![first];
+    let mut current = first;
+    for _ in 0..length {
+        let neighbors = open_neighbors(grid, current);
+        // A pause one time in five, or when boxed in
+        if !neighbors.is_empty() && !rng.gen_bool(0.2) {
+            current = neighbors[rng.gen_range(0..neighbors.len())];
+        }
+        route.push(current);
+    }
+    if route.len() < 2 {
+        return None;
+    }
+    let back = route[1..route.len() - 1]
+        .iter()
+        .rev()
+        .copied()
+        .collect::<Vec<_>>();
+    route.extend(back);
+    Some(route)
+}
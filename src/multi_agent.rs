@@ -0,0 +1,275 @@
+use crate::agent::Agent;
+use crate::algorithms::common::{EnvironmentChanges, PathfindingAlgorithm};
+use crate::algorithms::registry::{AlgorithmContext, AlgorithmRegistry};
+use crate::config::Config;
+use crate::cooperative::ReservationTable;
+use crate::error::ConfigError;
+use crate::grid::{Grid, Position};
+use crate::knowledge::{Knowledge, Memory};
+use crate::smoothing::smooth_path;
+use crate::statistics::AgentStatistics;
+use crate::vision::{SensorNoise, Vision};
+use std::collections::{HashMap, HashSet};
+
+/// Failed plans in a row after which an agent gives up, as in the single-agent loop
+pub const MAX_STUCK_ATTEMPTS: usize = 5;
+
+/// Another agent sharing the grid in a multi-agent run, with its own start, goal and
+/// planner. It sees, remembers and plans like the run's own agent, and stops on its
+/// goal once it gets there.
+pub struct Companion {
+    pub agent: Agent,
+    /// Its place in the planning order of a cooperative run, after this run's own
+    /// agent (0)
+    pub id: usize,
+    algorithm: Box<dyn PathfindingAlgorithm>,
+    pub goal: Position,
+    /// Cell it stood on before its last turn, so others can tell which way it is heading
+    pub previous: Position,
+    stuck_attempts: usize,
+    /// It found its part of the joint plan no longer fits and waits for a new one
+    pub wants_joint_plan: bool,
+    pub stats: AgentStatistics,
+}
+
+/// Where an agent's paths come from.
+pub enum Planning<'a> {
+    /// Its own planner of the `--algorithm` kind
+    Own,
+    /// Its own search through the reservations of the agents before it, every turn
+    Reserved(&'a mut ReservationTable),
+    /// The joint plan made for every agent at once; the agent only asks for a new one
+    Joint,
+}
+
+/// What changed around an agent since its last turn.
+pub struct Turn<'a> {
+    /// Current and previous cell of every moving obstacle and every other agent
+    pub sightings: &'a [(Position, Position)],
+    /// Cells the other agents stand on, which cannot be stepped into
+    pub occupied: &'a HashSet<Position>,
+    /// Whether obstacles from the timeline appeared or expired
+    pub obstacles_changed: bool,
+    /// Cells that became walls or stopped being walls
+    pub changed_walls: &'a [Position],
+    /// Steps each obstacle from the timeline the agent knows of stays before it
+    /// expires (0 once it has)
+    pub lifetimes: &'a HashMap<Position, usize>,
+}
+
+impl Companion {
+    /// An agent going from `start` to `goal`, seeing and remembering as the options
+    /// say, with a fresh planner of the `--algorithm` kind
+    pub fn new(
+        id: usize,
+        config: &Config,
+        registry: &AlgorithmRegistry,
+        grid: &Grid,
+        (start, goal): (Position, Position),
+        optimal_path_cost: f64,
+        sensor_seed: u64,
+    ) -> Result<Self, ConfigError> {
+        let agent = Agent::new(start)
+            .with_vision(Vision::from_config(config)?)
+            .with_knowledge(Knowledge::from_name(&config.knowledge)?, grid)
+            .with_memory(Memory::from_config(config)?)
+            .with_noise(SensorNoise::from_config(config)?, sensor_seed);
+        let algorithm = registry.create(
+            &config.algorithm,
+            &AlgorithmContext {
+                config,
+                start,
+                goal,
+                width: grid.width,
+                height: grid.height,
+            },
+        )?;
+        Ok(Companion {
+            agent,
+            id,
+            algorithm,
+            goal,
+            previous: start,
+            stuck_attempts: 0,
+            wants_joint_plan: false,
+            stats: AgentStatistics {
+                start,
+                goal,
+                moves: 0,
+                travel_cost: 0.0,
+                optimal_path_cost,
+                replans: 0,
+                agent_collisions: 0,
+                reached: false,
+                out_of_energy: false,
+                gave_up: false,
+            },
+        })
+    }
+
+    /// Whether the agent has stopped: on its goal, out of energy or stuck
+    pub fn is_done(&self) -> bool {
+        self.stats.reached || self.stats.out_of_energy || self.stats.gave_up
+    }
+
+    /// Take its part of a joint plan (`None`: the plan found no way to its goal),
+    /// giving up after too many plans in a row without one
+    pub fn follow(&mut self, path: Option<Vec<Position>>) {
+        self.wants_joint_plan = false;
+        self.stats.replans += 1;
+        match path {
+            Some(path) => {
+                self.agent.set_path(path);
+                self.stuck_attempts = 0;
+            }
+            None => {
+                self.agent.clear_path();
+                self.stuck_attempts += 1;
+                self.stats.gave_up = self.stuck_attempts > MAX_STUCK_ATTEMPTS;
+            }
+        }
+    }
+
+    /// Look around, replan if anything on the way changed, and take a step (or wait,
+    /// when the next cell is taken by another agent or an obstacle it missed)
+    pub fn take_turn(&mut self, grid: &Grid, config: &Config, turn: &Turn, planning: Planning) {
+        self.previous = self.agent.position;
+        if self.is_done() {
+            return;
+        }
+
+        // Another agent on the next cell of the path is a collision to avoid, by
+        // replanning or waiting
+        if self
+            .agent
+            .get_next_step()
+            .is_some_and(|next| turn.occupied.contains(&next))
+        {
+            self.stats.agent_collisions += 1;
+        }
+
+        let movers_seen = self
+            .agent
+            .observe_movers(grid, turn.sightings, config.predict_steps);
+        let obstacles_forgotten = self.agent.observe(grid);
+        let changed_walls = self
+            .agent
+            .observe_walls(grid)
+            .unwrap_or_else(|| turn.changed_walls.to_vec());
+
+        let joint = matches!(planning, Planning::Joint);
+        let path_broken =
+            self.agent.path_needs_recalculation(grid) || self.agent.is_path_blocked(grid);
+        let needs_recalc = match planning {
+            Planning::Own => {
+                path_broken
+                    || turn.obstacles_changed
+                    || !changed_walls.is_empty()
+                    || movers_seen
+                    || obstacles_forgotten
+            }
+            Planning::Reserved(_) => true,
+            // A joint plan is only made again once some agent's part stops fitting
+            Planning::Joint => path_broken,
+        };
+        if needs_recalc {
+            let changes = EnvironmentChanges {
+                walls: changed_walls,
+                obstacles: Some(self.agent.take_obstacle_changes()),
+            };
+            let planning_grid = self.agent.planning_grid(grid);
+            let known = &self.agent.known_obstacles;
+            let path = match planning {
+                Planning::Joint => {
+                    // Wait for a new joint plan rather than leave the current one
+                    self.wants_joint_plan = true;
+                    if self.spend_energy(config, 1.0) {
+                        self.stats.moves += 1;
+                    }
+                    return;
+                }
+                Planning::Reserved(table) => table.plan(
+                    planning_grid,
+                    self.agent.position,
+                    &[self.goal],
+                    known,
+                    self.id,
+                ),
+                Planning::Own => {
+                    self.algorithm
+                        .update_environment(planning_grid, known, &changes);
+                    self.algorithm.update_obstacle_lifetimes(turn.lifetimes);
+                    self.algorithm.find_path_to_any(
+                        planning_grid,
+                        self.agent.position,
+                        &[self.goal],
+                        known,
+                    )
+                }
+            };
+            self.stats.replans += 1;
+            match path {
+                Some(path) => {
+                    let path = if config.smooth_path {
+                        smooth_path(planning_grid, &path, known)
+                    } else {
+                        path
+                    };
+                    self.agent.set_path(path);
+                    self.stuck_attempts = 0;
+                }
+                None => {
+                    // No path for now: wait, or give up after too many tries
+                    self.stuck_attempts += 1;
+                    if self.stuck_attempts > MAX_STUCK_ATTEMPTS {
+                        self.stats.gave_up = true;
+                    } else if self.spend_energy(config, 1.0) {
+                        self.stats.moves += 1;
+                    }
+                    return;
+                }
+            }
+        }
+
+        // Running into an obstacle it missed, or finding another agent on the next
+        // cell, costs it a step (and puts it out of step with a joint plan)
+        let bumped = self.agent.bump(grid);
+        let blocked = !bumped
+            && self
+                .agent
+                .get_next_step()
+                .is_some_and(|next| turn.occupied.contains(&next));
+        if bumped || blocked {
+            self.wants_joint_plan |= joint;
+            if self.spend_energy(config, 1.0) {
+                self.stats.moves += 1;
+            }
+            return;
+        }
+        match self.agent.get_next_step() {
+            Some(next) => {
+                if self.spend_energy(config, grid.path_cost(&[self.agent.position, next])) {
+                    self.agent.move_to(next);
+                    self.stats.moves += 1;
+                    self.stats.reached = self.agent.position == self.goal;
+                }
+            }
+            // End of a partial path from a real-time planner: plan again next turn
+            None => self.agent.clear_path(),
+        }
+    }
+
+    /// Pay `cost` out of the `--energy-budget`, or run out of energy if it is not
+    /// left; returns whether the agent could afford it
+    fn spend_energy(&mut self, config: &Config, cost: f64) -> bool {
+        if config
+            .energy_budget
+            .is_some_and(|budget| self.stats.travel_cost + cost > budget + 1e-9)
+        {
+            self.stats.out_of_energy = true;
+            return false;
+        }
+        self.stats.travel_cost += cost;
+        true
+    }
+}
@@ -0,0 +1,82 @@
+use crate::grid::Position;
+use crate::simulation::{Simulation, StepOutcome};
+use std::time::Duration;
+
+/// How much of a large grid the plain view draws (`--viewport`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Viewport {
+    /// As much as fits the terminal, around the agent; the whole grid when the frames
+    /// do not go to a terminal
+    #[default]
+    Auto,
+    /// The whole grid, however large
+    Full,
+    /// At most this many columns and rows of cells, around the agent
+    Size(usize, usize),
+}
+
+impl Viewport {
+    /// Parse a viewport from the command line: 'auto', 'full' or a size such as '60x30'
+    pub fn from_name(name: &str) -> Result<Self, String> {
+        match name {
+            "auto" => Ok(Viewport::Auto),
+            "full" => Ok(Viewport::Full),
+            _ => match name
+                .split_once('x')
+                .and_then(|(width, height)| Some((width.parse().ok()?, height.parse().ok()?)))
+            {
+                Some((width, height)) if width > 0 && height > 0 => {
+                    Ok(Viewport::Size(width, height))
+                }
+                _ => Err(format!(
+                    "Unknown viewport: '{}' (expected 'auto', 'full' or a size such as '60x30')",
+                    name
+                )),
+            },
+        }
+    }
+}
+
+/// A plan the agent made during a step, after the first one
+#[derive(Debug, Clone, Copy)]
+pub struct Replan {
+    /// Time the plan took
+    pub duration: Duration,
+    /// Plans in a row that have found no path, 0 when this one found one
+    pub stuck_attempts: usize,
+    /// Whether the agent plans every step anyway, cooperating with the other agents,
+    /// rather than because its path stopped fitting
+    pub routine: bool,
+}
+
+/// Callbacks a `Simulation` makes as its run goes on, for drawing it, logging it or
+/// recording it. Every callback gets the simulation to look at, and does nothing
+/// unless implemented. Observers are called in the order they were added; every
+/// simulation starts with a `TerminalRenderer`, a `RunLog` at `--log-level debug` or
+/// `trace`, and a `MetricsObserver` with `--metrics-address`.
+pub trait SimulationObserver {
+    /// The moving obstacles are in place and the agent is about to make its
+    /// initial plan
+    fn on_start(&mut self, _simulation: &Simulation) {}
+
+    /// Obstacles of the timeline appeared or cleared, or walls were added or removed
+    /// (`changed_walls`), at the start of a step
+    fn on_obstacle_change(&mut self, _simulation: &Simulation, _changed_walls: &[Position]) {}
+
+    /// The agent planned again; its new path, if it found one, is the agent's
+    /// current path
+    fn on_replan(&mut self, _simulation: &Simulation, _replan: &Replan) {}
+
+    /// A step is over, and how it went for the agent; a step that ends the run is
+    /// reported too
+    fn on_step(&mut self, _simulation: &Simulation, _outcome: StepOutcome) {}
+
+    /// The run is over and its statistics are final
+    fn on_finish(&mut self, _simulation: &Simulation) {}
+
+    /// Whether `Simulation::run` should stop the run after the step it is on, as the
+    /// `TerminalRenderer` asks when q is pressed
+    fn stop_requested(&self) -> bool {
+        false
+    }
+}
@@ -0,0 +1,147 @@
+use crate::grid::Position;
+use rand::Rng;
+use rand_distr::{Distribution, Normal, Poisson};
+use std::collections::HashSet;
+
+/// Where the obstacles of each random obstacle group appear.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ObstacleModel {
+    /// Anywhere on the grid with equal probability.
+    Uniform,
+    /// Scattered around a few fixed hotspots, in a Gaussian around each.
+    Hotspots,
+    /// Like `Hotspots`, but the hotspots wander a little every group.
+    Drifting,
+}
+
+impl ObstacleModel {
+    /// Parses an obstacle model name as given on the command line.
+    pub fn from_name(name: &str) -> Result<Self, String> {
+        match name {
+            "uniform" => Ok(ObstacleModel::Uniform),
+            "hotspots" => Ok(ObstacleModel::Hotspots),
+            "drifting" => Ok(ObstacleModel::Drifting),
+            _ => Err(format!(
+                "Unknown obstacle model: '{}' (expected 'uniform', 'hotspots' or 'drifting')",
+                name
+            )),
+        }
+    }
+
+    /// Name of the obstacle model as given on the command line.
+    pub fn name(self) -> &'static str {
+        match self {
+            ObstacleModel::Uniform => "uniform",
+            ObstacleModel::Hotspots => "hotspots",
+            ObstacleModel::Drifting => "drifting",
+        }
+    }
+}
+
+/// Settings for drawing a timeline of obstacle groups.
+#[derive(Debug, Clone, Copy)]
+pub struct ObstacleGenerator {
+    pub model: ObstacleModel,
+    /// Obstacles per group, unless `arrival_rate` is set
+    pub group_size: usize,
+    /// Mean number of obstacles arriving per step; each group then holds a Poisson
+    /// number of obstacles with a mean of the arrivals since the previous group
+    pub arrival_rate: Option<f64>,
+    /// Steps between two groups
+    pub interval: usize,
+    pub hotspots: usize,
+    /// Standard deviation of the distance of an obstacle from its hotspot, in cells
+    pub spread: f64,
+    /// Standard deviation of how far a drifting hotspot moves per group, in cells
+    pub drift: f64,
+}
+
+impl ObstacleGenerator {
+    /// One group of obstacles per entry, never on a wall or a `reserved` cell.
+    pub fn timeline<R: Rng>(
+        &self,
+        rng: &mut R,
+        (width, height, floors): (usize, usize, usize),
+        groups: usize,
+        reserved: &[Position],
+        walls: &HashSet<Position>,
+    ) -> Vec<HashSet<Position>> {
+        let mut centers: Vec<(f64, f64, usize)> = match self.model {
+            ObstacleModel::Uniform => Vec::new(),
+            _ => (0..self.hotspots.max(1))
+                .map(|_| {
+                    (
+                        rng.gen_range(0.0..width as f64),
+                        rng.gen_range(0.0..height as f64),
+                        random_floor(rng, floors),
+                    )
+                })
+                .collect(),
+        };
+        let arrivals = self
+            .arrival_rate
+            .filter(|&rate| rate > 0.0)
+            .and_then(|rate| Poisson::new(rate * self.interval as f64).ok());
+        let scatter = Normal::new(0.0, self.spread.max(0.0)).ok();
+        let wander = Normal::new(0.0, self.drift.max(0.0)).ok();
+
+        let mut timeline = Vec::with_capacity(groups);
+        for _cycle in 0..groups {
+            let count = match arrivals {
+                Some(arrivals) => arrivals.sample(rng) as usize,
+                None => self.group_size,
+            };
+            let mut obstacle_group = HashSet::new();
+            let mut attempts = 0;
+            let max_attempts = count * 10;
+
+            while obstacle_group.len() < count && attempts < max_attempts {
+                attempts += 1;
+                let pos = if centers.is_empty() {
+                    let x = rng.gen_range(0..width);
+                    let y = rng.gen_range(0..height);
+                    let z = random_floor(rng, floors);
+                    Position { x, y, z }
+                } else {
+                    let (cx, cy, z) = centers[rng.gen_range(0..centers.len())];
+                    let (dx, dy) = match scatter {
+                        Some(scatter) => (scatter.sample(rng), scatter.sample(rng)),
+                        None => (0.0, 0.0),
+                    };
+                    let (x, y) = ((cx + dx).floor(), (cy + dy).floor());
+                    if x < 0.0 || y < 0.0 || x >= width as f64 || y >= height as f64 {
+                        continue;
+                    }
+                    Position {
+                        x: x as usize,
+                        y: y as usize,
+                        z,
+                    }
+                };
+
+                if !reserved.contains(&pos) && !walls.contains(&pos) {
+                    obstacle_group.insert(pos);
+                }
+            }
+            timeline.push(obstacle_group);
+
+            if let (ObstacleModel::Drifting, Some(wander)) = (self.model, wander) {
+                for (x, y, _) in &mut centers {
+                    *x = (*x + wander.sample(rng)).clamp(0.0, width as f64 - f64::EPSILON);
+                    *y = (*y + wander.sample(rng)).clamp(0.0, height as f64 - f64::EPSILON);
+                }
+            }
+        }
+        timeline
+    }
+}
+
+/// Random floor for an obstacle; single-floor grids draw nothing, so seeded
+/// environments are the same as before floors existed.
+fn random_floor<R: Rng>(rng: &mut R, floors: usize) -> usize {
+    if floors > 1 {
+        rng.gen_range(0..floors)
+    } else {
+        0
+    }
+}
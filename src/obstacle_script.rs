@@ -0,0 +1,105 @@
+use crate::grid::Position;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::path::Path;
+
+/// Obstacles that appear together at the start of a step and stay for `duration` steps
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct ObstacleWave {
+    /// Simulation step the obstacles appear at, counted from 1
+    pub step: usize,
+    pub duration: usize,
+    pub cells: HashSet<Position>,
+}
+
+/// A hand-written obstacle timeline, read from a TOML or JSON file with a list of
+/// `obstacles` entries, each giving a `step`, a `duration` and its `cells` as
+/// `[x, y]` or `[x, y, floor]`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ObstacleScript {
+    pub waves: Vec<ObstacleWave>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ScriptFile {
+    obstacles: Vec<WaveEntry>,
+}
+
+/// An obstacle entry as written in a file (also inside scenario files)
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct WaveEntry {
+    step: usize,
+    duration: usize,
+    cells: Vec<Vec<usize>>,
+}
+
+impl WaveEntry {
+    /// The entry for a wave, its cells in order
+    pub(crate) fn new(wave: &ObstacleWave) -> Self {
+        let mut cells: Vec<Position> = wave.cells.iter().copied().collect();
+        cells.sort();
+        WaveEntry {
+            step: wave.step,
+            duration: wave.duration,
+            cells: cells
+                .into_iter()
+                .map(|pos| match pos.z {
+                    0 => vec![pos.x, pos.y],
+                    z => vec![pos.x, pos.y, z],
+                })
+                .collect(),
+        }
+    }
+}
+
+impl ObstacleScript {
+    /// Read a script, in TOML unless the file name ends in `.json`
+    pub fn load(path: &str) -> Result<Self, String> {
+        let text = std::fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read obstacle script '{}': {}", path, e))?;
+        let is_json = Path::new(path)
+            .extension()
+            .is_some_and(|extension| extension.eq_ignore_ascii_case("json"));
+        let file: ScriptFile = if is_json {
+            serde_json::from_str(&text).map_err(|e| e.to_string())
+        } else {
+            toml::from_str(&text).map_err(|e| e.to_string())
+        }
+        .map_err(|e| format!("Invalid obstacle script '{}': {}", path, e))?;
+        Self::from_entries(file.obstacles, path)
+    }
+
+    /// The script of the obstacle entries read from `path`, ordered by step
+    pub(crate) fn from_entries(entries: Vec<WaveEntry>, path: &str) -> Result<Self, String> {
+        let mut waves = Vec::with_capacity(entries.len());
+        for (number, entry) in entries.into_iter().enumerate() {
+            let invalid =
+                |problem: &str| format!("Obstacle entry {} of '{}' {}", number + 1, path, problem);
+            if entry.step == 0 {
+                return Err(invalid("starts at step 0; steps are counted from 1"));
+            }
+            if entry.duration == 0 {
+                return Err(invalid("has a duration of 0 steps"));
+            }
+            let cells = entry
+                .cells
+                .iter()
+                .map(|cell| match cell[..] {
+                    [x, y] => Ok(Position { x, y, z: 0 }),
+                    [x, y, z] => Ok(Position { x, y, z }),
+                    _ => Err(invalid("has a cell that is not [x, y] or [x, y, floor]")),
+                })
+                .collect::<Result<HashSet<_>, _>>()?;
+            waves.push(ObstacleWave {
+                step: entry.step,
+                duration: entry.duration,
+                cells,
+            });
+        }
+        waves.sort_by_key(|wave| wave.step);
+        Ok(ObstacleScript { waves })
+    }
+}
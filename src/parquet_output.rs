@@ -0,0 +1,220 @@
+use crate::batch_simulation::BatchResult;
+use crate::sweep_spec::SweepSpec;
+use arrow_array::{
+    ArrayRef, BooleanArray, Float64Array, Int64Array, RecordBatch, StringArray, UInt64Array,
+};
+use arrow_schema::{DataType, Field, Schema, SchemaRef};
+use parquet::arrow::ArrowWriter;
+use parquet::basic::Compression;
+use parquet::file::properties::WriterProperties;
+use std::fs::File;
+use std::sync::Arc;
+
+/// Writes the results of a batch to a Parquet file (`--output-format parquet`), with
+/// the columns of the CSV as typed, Snappy-compressed columns. Results are buffered
+/// into row groups as they are written; the file is only complete once `close`d.
+pub struct ParquetWriter {
+    writer: ArrowWriter<File>,
+    schema: SchemaRef,
+    /// Names of the columns of the results written (`--columns`)
+    columns: Vec<&'static str>,
+    /// Swept options with columns of their own, by index in the sweep, and the type
+    /// of their values
+    spec_columns: Vec<(String, usize, DataType)>,
+}
+
+impl ParquetWriter {
+    /// Start the file, with the `columns` of the results and a column for each of the
+    /// sweep's `spec_columns`
+    pub fn new(
+        file: File,
+        columns: &[&'static str],
+        spec: &SweepSpec,
+        spec_columns: &[usize],
+    ) -> Result<Self, String> {
+        let spec_columns: Vec<_> = spec_columns
+            .iter()
+            .map(|&index| {
+                let (name, values) = spec.parameter(index);
+                (name.to_string(), index, Self::data_type(values))
+            })
+            .collect();
+        let (fields, _): (Vec<Field>, Vec<ArrayRef>) = self::columns(columns, &spec_columns, &[])
+            .into_iter()
+            .unzip();
+        let schema = Arc::new(Schema::new(fields));
+        let properties = WriterProperties::builder()
+            .set_compression(Compression::SNAPPY)
+            .build();
+        let writer = ArrowWriter::try_new(file, Arc::clone(&schema), Some(properties))
+            .map_err(|e| format!("Failed to start the Parquet file: {}", e))?;
+        Ok(ParquetWriter {
+            writer,
+            schema,
+            columns: columns.to_vec(),
+            spec_columns,
+        })
+    }
+
+    /// Column type of a swept option's values: integers, numbers, switches, or the
+    /// values as text when they are of different kinds
+    fn data_type(values: &[toml::Value]) -> DataType {
+        if values.iter().all(|value| value.is_integer()) {
+            DataType::Int64
+        } else if values
+            .iter()
+            .all(|value| value.is_integer() || value.is_float())
+        {
+            DataType::Float64
+        } else if values.iter().all(|value| value.is_bool()) {
+            DataType::Boolean
+        } else {
+            DataType::Utf8
+        }
+    }
+
+    /// Add results to the file
+    pub fn write(&mut self, results: &[BatchResult]) -> Result<(), String> {
+        let (_, arrays): (Vec<Field>, Vec<ArrayRef>) =
+            columns(&self.columns, &self.spec_columns, results)
+                .into_iter()
+                .unzip();
+        let batch = RecordBatch::try_new(Arc::clone(&self.schema), arrays)
+            .map_err(|e| format!("Failed to write results: {}", e))?;
+        self.writer
+            .write(&batch)
+            .map_err(|e| format!("Failed to write results: {}", e))
+    }
+
+    /// Write out the buffered results and the file's footer
+    pub fn close(self) -> Result<(), String> {
+        self.writer
+            .close()
+            .map(|_| ())
+            .map_err(|e| format!("Failed to finish the Parquet file: {}", e))
+    }
+}
+
+/// The `selected` columns of the results, in the order of the CSV's, then those of the
+/// swept options
+fn columns(
+    selected: &[&str],
+    spec_columns: &[(String, usize, DataType)],
+    results: &[BatchResult],
+) -> Vec<(Field, ArrayRef)> {
+    let count = |name: &str, value: fn(&BatchResult) -> usize| {
+        let values = results.iter().map(|result| value(result) as u64);
+        let array: ArrayRef = Arc::new(UInt64Array::from_iter_values(values));
+        (Field::new(name, DataType::UInt64, false), array)
+    };
+    let nanos = |name: &str, value: fn(&BatchResult) -> u64| {
+        let array: ArrayRef = Arc::new(UInt64Array::from_iter_values(results.iter().map(value)));
+        (Field::new(name, DataType::UInt64, false), array)
+    };
+    let number = |name: &str, value: fn(&BatchResult) -> f64| {
+        let array: ArrayRef = Arc::new(Float64Array::from_iter_values(results.iter().map(value)));
+        (Field::new(name, DataType::Float64, false), array)
+    };
+    let text = |name: &str, value: fn(&BatchResult) -> &str| {
+        let array: ArrayRef = Arc::new(StringArray::from_iter_values(results.iter().map(value)));
+        (Field::new(name, DataType::Utf8, false), array)
+    };
+    let flag = |name: &str, value: fn(&BatchResult) -> bool| {
+        let array: ArrayRef = Arc::new(BooleanArray::from(
+            results.iter().map(value).collect::<Vec<_>>(),
+        ));
+        (Field::new(name, DataType::Boolean, false), array)
+    };
+
+    let mut columns = vec![
+        count("configuration_id", |r| r.configuration_id),
+        count("simulation_id", |r| r.simulation_id),
+        (
+            Field::new("seed", DataType::UInt64, false),
+            Arc::new(UInt64Array::from_iter_values(
+                results.iter().map(|r| r.seed),
+            )) as ArrayRef,
+        ),
+        text("algorithm", |r| &r.algorithm),
+        count("grid_width", |r| r.grid_width),
+        count("grid_height", |r| r.grid_height),
+        count("num_walls", |r| r.num_walls),
+        count("num_obstacles", |r| r.num_obstacles),
+        flag("success", |r| r.success),
+        count("total_moves", |r| r.total_moves),
+        count("optimal_path_length", |r| r.optimal_path_length),
+        number("route_efficiency", |r| r.route_efficiency),
+        number("dynamic_route_efficiency", |r| r.dynamic_route_efficiency),
+        (
+            Field::new("execution_time_ms", DataType::UInt64, false),
+            Arc::new(UInt64Array::from_iter_values(
+                results.iter().map(|r| r.execution_time_ms),
+            )),
+        ),
+        count("a_star_calls", |r| r.a_star_calls),
+        count("d_star_calls", |r| r.d_star_calls),
+        count("planner_calls", |r| r.planner_calls),
+        nanos("average_find_path_time_ns", |r| r.average_find_path_time_ns),
+        nanos("min_find_path_time_ns", |r| r.min_find_path_time_ns),
+        nanos("median_find_path_time_ns", |r| r.median_find_path_time_ns),
+        nanos("p95_find_path_time_ns", |r| r.p95_find_path_time_ns),
+        nanos("p99_find_path_time_ns", |r| r.p99_find_path_time_ns),
+        nanos("max_find_path_time_ns", |r| r.max_find_path_time_ns),
+        count("total_pathfinding_calls", |r| r.total_pathfinding_calls),
+        text("priority_queue", |r| &r.priority_queue),
+        count("raw_path_length", |r| r.raw_path_length),
+        count("smoothed_path_length", |r| r.smoothed_path_length),
+        text("wall_pattern", |r| &r.wall_pattern),
+        count("obstacle_interval", |r| r.obstacle_interval),
+        count("obstacle_persistence", |r| r.obstacle_persistence),
+        text("memory", |r| &r.memory),
+        count("stale_belief_steps", |r| r.stale_belief_steps),
+        flag("out_of_energy", |r| r.out_of_energy),
+        count("agents", |r| r.agents),
+        count("agents_reached", |r| r.agents_reached),
+        count("agent_collisions", |r| r.agent_collisions),
+        text("coordination", |r| &r.coordination),
+        (
+            Field::new("makespan", DataType::UInt64, true),
+            Arc::new(UInt64Array::from(
+                results
+                    .iter()
+                    .map(|r| r.makespan.map(|makespan| makespan as u64))
+                    .collect::<Vec<_>>(),
+            )),
+        ),
+        number("sum_of_costs", |r| r.sum_of_costs),
+        count("cbs_expansions", |r| r.cbs_expansions),
+        flag("caught", |r| r.caught),
+        flag("timed_out", |r| r.timed_out),
+        count("nodes_expanded", |r| r.nodes_expanded),
+        count("nodes_generated", |r| r.nodes_generated),
+        count("max_open_list", |r| r.max_open_list),
+        count("heap_operations", |r| r.heap_operations),
+        count("budget_overruns", |r| r.budget_overruns),
+    ];
+    columns.retain(|(field, _)| selected.contains(&field.name().as_str()));
+
+    for (name, index, data_type) in spec_columns {
+        let values = results.iter().map(|r| &r.swept[*index]);
+        let array: ArrayRef = match data_type {
+            DataType::Int64 => Arc::new(Int64Array::from_iter_values(
+                values.map(|value| value.as_integer().unwrap_or_default()),
+            )),
+            DataType::Float64 => Arc::new(Float64Array::from_iter_values(values.map(|value| {
+                value
+                    .as_float()
+                    .or(value.as_integer().map(|n| n as f64))
+                    .unwrap_or_default()
+            }))),
+            DataType::Boolean => Arc::new(BooleanArray::from(
+                values
+                    .map(|value| value.as_bool().unwrap_or_default())
+                    .collect::<Vec<_>>(),
+            )),
+            _ => Arc::new(StringArray::from_iter_values(values.map(SweepSpec::label))),
+        };
+        columns.push((Field::new(name, data_type.clone(), false), array));
+    }
+    columns
+}
@@ -0,0 +1,401 @@
+use crate::algorithms::common::{
+    AbortHandle, EnvironmentChanges, ObstacleChanges, PathfindingAlgorithm,
+};
+use crate::algorithms::registry::{AlgorithmContext, AlgorithmRegistry};
+use crate::config::Config;
+use crate::grid::{Cell, Grid, Position};
+use serde_json::{json, Value};
+use std::collections::{BTreeMap, HashSet};
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::time::{Duration, Instant};
+
+/// Planner of a session that does not name one: D* Lite keeps its search between
+/// queries, so replanning after a few obstacle updates costs little
+pub const DEFAULT_ALGORITHM: &str = "d_star_lite";
+
+/// Largest request body read
+const MAX_BODY: usize = 64 << 20;
+
+/// Most cells a session's grid may have (2048 x 2048), so one request cannot make
+/// the service allocate more than it can hold
+const MAX_CELLS: usize = 1 << 22;
+
+/// Longest a connection may leave a read or write of its request waiting; requests
+/// are answered one at a time, so a client that stalls would hold up every other
+const STREAM_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// One client's grid, the obstacles it has reported on it and the planner that keeps
+/// its state from query to query
+struct Session {
+    algorithm: String,
+    grid: Grid,
+    obstacles: HashSet<Position>,
+    planner: Box<dyn PathfindingAlgorithm>,
+    /// Gives up a search that outlasts its query's `timeout_ms`
+    abort: AbortHandle,
+    /// Paths asked for so far
+    queries: usize,
+}
+
+/// Status line and JSON body of an answer
+pub type Answer = (&'static str, Value);
+
+/// The planning API of the `serve` command, as JSON over HTTP. A client uploads a
+/// grid to open a session, reports obstacles (and wall changes) as they come and asks
+/// for paths; each session keeps its own planner, so with an incremental one (D* Lite,
+/// the default) a query after a few updates only repairs the previous search.
+///
+/// - `POST /sessions` with `{"width", "height", "walls": [[x, y], ...], "diagonal",
+///   "algorithm"}` opens a session and answers `{"session": id, ...}`
+/// - `POST /sessions/{id}/obstacles` with `{"add", "remove", "add_walls",
+///   "remove_walls"}`, each a list of cells, updates what the planner knows
+/// - `POST /sessions/{id}/path` with `{"start": [x, y], "goal": [x, y]}` answers the
+///   `path` (null when there is none), its `cost`, the `nodes_expanded` finding it and
+///   the `micros` it took; with `"timeout_ms"` a search that takes longer is given up,
+///   answering a null path that is `aborted`
+/// - `GET /sessions/{id}` describes a session and `DELETE /sessions/{id}` closes it
+///
+/// Requests are answered one at a time, in the order they arrive, and a connection
+/// that stalls mid-request is dropped after `STREAM_TIMEOUT`. Errors are answered as
+/// `{"error": message}` with a 4xx status; grids of more than `MAX_CELLS` cells are
+/// refused.
+pub struct PlanningService {
+    listener: TcpListener,
+    address: SocketAddr,
+    config: Config,
+    registry: AlgorithmRegistry,
+    sessions: BTreeMap<u64, Session>,
+    next_session: u64,
+}
+
+impl PlanningService {
+    /// Listen at `address`, building every session's planner with the options of
+    /// `config`
+    pub fn bind(address: &str, config: Config) -> Result<Self, String> {
+        let listener = TcpListener::bind(address)
+            .map_err(|e| format!("Cannot serve the planning API at {}: {}", address, e))?;
+        let local = listener
+            .local_addr()
+            .map_err(|e| format!("Cannot serve the planning API at {}: {}", address, e))?;
+        Ok(PlanningService {
+            listener,
+            address: local,
+            config,
+            registry: AlgorithmRegistry::with_builtins(),
+            sessions: BTreeMap::new(),
+            next_session: 1,
+        })
+    }
+
+    /// Address bound, which has the port picked for port 0
+    pub fn address(&self) -> SocketAddr {
+        self.address
+    }
+
+    /// Answer requests for as long as the process runs
+    pub fn run(mut self) {
+        let Ok(listener) = self.listener.try_clone() else {
+            return;
+        };
+        for stream in listener.incoming().flatten() {
+            // A client that goes away mid-answer only loses its own answer
+            let _ = self.answer(stream);
+        }
+    }
+
+    /// Answer one request on a connection
+    fn answer(&mut self, mut stream: TcpStream) -> io::Result<()> {
+        stream.set_read_timeout(Some(STREAM_TIMEOUT))?;
+        stream.set_write_timeout(Some(STREAM_TIMEOUT))?;
+        let mut reader = BufReader::new(&stream);
+        let mut request_line = String::new();
+        reader.read_line(&mut request_line)?;
+        let mut content_length = 0;
+        loop {
+            let mut header = String::new();
+            if reader.read_line(&mut header)? == 0 || header.trim().is_empty() {
+                break;
+            }
+            if let Some((name, value)) = header.split_once(':') {
+                if name.trim().eq_ignore_ascii_case("content-length") {
+                    content_length = value.trim().parse().unwrap_or(0);
+                }
+            }
+        }
+        let (status, body) = if content_length > MAX_BODY {
+            error("413 Payload Too Large", "The request body is too large")
+        } else {
+            let mut body = vec![0; content_length];
+            reader.read_exact(&mut body)?;
+            let mut parts = request_line.split_whitespace();
+            let (method, path) = (parts.next().unwrap_or(""), parts.next().unwrap_or(""));
+            self.handle(method, path, &String::from_utf8_lossy(&body))
+        };
+        let body = body.to_string();
+        write!(
+            stream,
+            "HTTP/1.1 {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            status,
+            body.len(),
+            body
+        )
+    }
+
+    /// Answer a request for `path` with a JSON `body` (empty for none)
+    pub fn handle(&mut self, method: &str, path: &str, body: &str) -> Answer {
+        let body = match body.trim() {
+            "" => Value::Null,
+            body => match serde_json::from_str(body) {
+                Ok(body) => body,
+                Err(e) => return error("400 Bad Request", &format!("Invalid JSON: {}", e)),
+            },
+        };
+        let segments: Vec<&str> = path
+            .split('?')
+            .next()
+            .unwrap_or("")
+            .split('/')
+            .filter(|segment| !segment.is_empty())
+            .collect();
+        if segments == ["sessions"] {
+            return match method {
+                "POST" => match self.open_session(&body) {
+                    Ok(session) => ("201 Created", session),
+                    Err(e) => error("400 Bad Request", &e),
+                },
+                _ => error("405 Method Not Allowed", "Sessions are opened with POST"),
+            };
+        }
+        let (id, action) = match segments.as_slice() {
+            ["sessions", id] => (id, None),
+            ["sessions", id, action] => (id, Some(*action)),
+            _ => return error("404 Not Found", "The planning API is served at /sessions"),
+        };
+        let no_session = || error("404 Not Found", &format!("No session {}", id));
+        let Ok(id) = id.parse() else {
+            return no_session();
+        };
+        if (method, action) == ("DELETE", None) {
+            return match self.sessions.remove(&id) {
+                Some(_) => ("200 OK", json!({ "session": id, "closed": true })),
+                None => no_session(),
+            };
+        }
+        let Some(session) = self.sessions.get_mut(&id) else {
+            return no_session();
+        };
+        let answer = match (method, action) {
+            ("GET", None) => Ok(describe(id, session)),
+            ("POST", Some("obstacles")) => update(session, &body),
+            ("POST", Some("path")) => plan(session, &body),
+            _ => return error("404 Not Found", &format!("No {} {}", method, path)),
+        };
+        match answer {
+            Ok(answer) => ("200 OK", answer),
+            Err(e) => error("400 Bad Request", &e),
+        }
+    }
+
+    /// Open a session on the grid of a request, answering its description
+    fn open_session(&mut self, body: &Value) -> Result<Value, String> {
+        let size = |name: &str| {
+            body[name]
+                .as_u64()
+                .map(|size| size as usize)
+                .filter(|&size| size >= 2)
+                .ok_or_else(|| format!("'{}' must be a whole number of at least 2", name))
+        };
+        let (width, height) = (size("width")?, size("height")?);
+        if width
+            .checked_mul(height)
+            .is_none_or(|cells| cells > MAX_CELLS)
+        {
+            return Err(format!(
+                "A {}x{} grid is larger than the {} cells a session may have",
+                width, height, MAX_CELLS
+            ));
+        }
+        let mut grid = Grid::new(width, height, 0);
+        grid.diagonal = body["diagonal"].as_bool().unwrap_or(false);
+        for wall in cells(&grid, body, "walls")? {
+            grid.set_cell(wall, Cell::Wall);
+        }
+        let algorithm = body["algorithm"]
+            .as_str()
+            .unwrap_or(DEFAULT_ALGORITHM)
+            .to_string();
+        let mut planner = self
+            .registry
+            .create(
+                &algorithm,
+                &AlgorithmContext {
+                    config: &self.config,
+                    start: grid.start,
+                    goal: grid.goal,
+                    width,
+                    height,
+                },
+            )
+            .map_err(|e| e.to_string())?;
+        let abort = AbortHandle::new();
+        planner.set_abort_handle(abort.clone());
+        let id = self.next_session;
+        self.next_session += 1;
+        self.sessions.insert(
+            id,
+            Session {
+                algorithm,
+                grid,
+                obstacles: HashSet::new(),
+                planner,
+                abort,
+                queries: 0,
+            },
+        );
+        Ok(describe(id, &self.sessions[&id]))
+    }
+}
+
+/// An error answer
+fn error(status: &'static str, message: &str) -> Answer {
+    (status, json!({ "error": message }))
+}
+
+/// What a session plans on: its algorithm, grid size, walls, obstacles and queries
+fn describe(id: u64, session: &Session) -> Value {
+    let grid = &session.grid;
+    let walls: Vec<Value> = (0..grid.cell_count())
+        .map(|index| grid.position(index))
+        .filter(|&pos| grid.is_wall(pos))
+        .map(cell)
+        .collect();
+    let mut obstacles: Vec<Position> = session.obstacles.iter().copied().collect();
+    obstacles.sort();
+    json!({
+        "session": id,
+        "algorithm": session.algorithm,
+        "width": grid.width,
+        "height": grid.height,
+        "diagonal": grid.diagonal,
+        "walls": walls,
+        "obstacles": obstacles.into_iter().map(cell).collect::<Vec<_>>(),
+        "queries": session.queries,
+    })
+}
+
+/// Report obstacles that appeared or went away and walls that were raised or taken
+/// down, answering how many obstacles the session now knows of
+fn update(session: &mut Session, body: &Value) -> Result<Value, String> {
+    let grid = &session.grid;
+    let (add, remove) = (cells(grid, body, "add")?, cells(grid, body, "remove")?);
+    let add_walls = cells(grid, body, "add_walls")?;
+    let remove_walls = cells(grid, body, "remove_walls")?;
+
+    // Only the cells that really changed are passed on to the planner
+    let mut obstacles = ObstacleChanges::default();
+    for pos in add {
+        if session.obstacles.insert(pos) {
+            obstacles.added.push(pos);
+        }
+    }
+    for pos in remove {
+        if session.obstacles.remove(&pos) {
+            obstacles.removed.push(pos);
+        }
+    }
+    let mut changed_walls = Vec::new();
+    for (walls, cell) in [(add_walls, Cell::Wall), (remove_walls, Cell::Empty)] {
+        for pos in walls {
+            if session.grid.cell(pos) != cell {
+                session.grid.set_cell(pos, cell);
+                changed_walls.push(pos);
+            }
+        }
+    }
+    let changes = EnvironmentChanges {
+        walls: changed_walls,
+        obstacles: Some(obstacles),
+    };
+    session
+        .planner
+        .update_environment(&session.grid, &session.obstacles, &changes);
+    Ok(json!({
+        "obstacles": session.obstacles.len(),
+        "walls_changed": changes.walls.len(),
+    }))
+}
+
+/// Find a path between two cells around the walls and obstacles the session knows of
+fn plan(session: &mut Session, body: &Value) -> Result<Value, String> {
+    let grid = &session.grid;
+    let endpoint = |name: &str| match &body[name] {
+        Value::Null => Err(format!("'{}' is missing", name)),
+        value => position(grid, value).map_err(|e| format!("'{}': {}", name, e)),
+    };
+    let (start, goal) = (endpoint("start")?, endpoint("goal")?);
+    let timeout = match &body["timeout_ms"] {
+        Value::Null => None,
+        value => Some(
+            value
+                .as_u64()
+                .ok_or("'timeout_ms' must be a whole number of milliseconds")?,
+        ),
+    };
+    let expanded = session.planner.search_metrics().nodes_expanded;
+    let started = Instant::now();
+    session.abort.reset();
+    if let Some(timeout) = timeout {
+        session
+            .abort
+            .abort_at(started + Duration::from_millis(timeout));
+    }
+    let path = session
+        .planner
+        .find_path(grid, start, goal, &session.obstacles);
+    let micros = started.elapsed().as_micros() as u64;
+    let aborted = path.is_none() && session.abort.is_aborted();
+    session.queries += 1;
+    Ok(json!({
+        "cost": path.as_ref().map(|path| grid.path_cost(path)),
+        "path": path.map(|path| path.into_iter().map(cell).collect::<Vec<_>>()),
+        "nodes_expanded": session.planner.search_metrics().nodes_expanded - expanded,
+        "micros": micros,
+        "aborted": aborted,
+    }))
+}
+
+/// A cell of an answer, `[x, y]`
+fn cell(position: Position) -> Value {
+    json!([position.x, position.y])
+}
+
+/// The cell `[x, y]` on the grid
+fn position(grid: &Grid, value: &Value) -> Result<Position, String> {
+    let coordinates: Option<Vec<usize>> = value.as_array().and_then(|coordinates| {
+        coordinates
+            .iter()
+            .map(|coordinate| coordinate.as_u64().map(|coordinate| coordinate as usize))
+            .collect()
+    });
+    match coordinates.as_deref() {
+        Some(&[x, y]) if x < grid.width && y < grid.height => Ok(Position { x, y, z: 0 }),
+        Some(&[x, y]) => Err(format!(
+            "[{}, {}] is off the {}x{} grid",
+            x, y, grid.width, grid.height
+        )),
+        _ => Err(format!("{} is not a cell [x, y]", value)),
+    }
+}
+
+/// The list of cells under `name` in a request, empty when it is left out
+fn cells(grid: &Grid, body: &Value, name: &str) -> Result<Vec<Position>, String> {
+    match &body[name] {
+        Value::Null => Ok(Vec::new()),
+        Value::Array(values) => values
+            .iter()
+            .map(|value| position(grid, value).map_err(|e| format!("'{}': {}", name, e)))
+            .collect(),
+        _ => Err(format!("'{}' must be a list of cells [x, y]", name)),
+    }
+}
@@ -0,0 +1,28 @@
+//! The types most uses of the library need, to import at once with
+//! `use dynamic_pathfinding::prelude::*;`: the grid, the options, a simulation with
+//! its builder, outcomes, results and errors, the agent and obstacle settings, the
+//! observer callbacks, and the planner traits with the registry and the planners
+//! that also work on graphs of your own.
+
+pub use crate::agent::Agent;
+pub use crate::algorithms::a_star::AStar;
+pub use crate::algorithms::common::{
+    AbortHandle, EnvironmentChanges, KnownObstacles, ObstacleChanges, ObstacleLifetimes,
+    ObstacleSchedule, PathfindingAlgorithm, SearchMetrics, SearchTrace,
+};
+pub use crate::algorithms::d_star_lite::DStarLite;
+pub use crate::algorithms::hybrid_a_star_d_star::HybridAStarDStar;
+pub use crate::algorithms::registry::{AlgorithmContext, AlgorithmRegistry};
+pub use crate::algorithms::search_space::SearchSpace;
+pub use crate::config::Config;
+pub use crate::error::{ConfigError, PlanningError, SimulationError};
+pub use crate::grid::{Cell, Grid, Position};
+pub use crate::knowledge::{Knowledge, Memory};
+pub use crate::observer::{Replan, SimulationObserver};
+pub use crate::obstacle_model::ObstacleModel;
+pub use crate::simulation::{
+    AlgorithmResult, EnvironmentSetup, Failure, Simulation, StepOutcome, TimingData,
+};
+pub use crate::simulation_builder::SimulationBuilder;
+pub use crate::statistics::{PlannerMetrics, Statistics};
+pub use crate::vision::VisionShape;
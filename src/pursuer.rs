@@ -0,0 +1,70 @@
+use crate::algorithms::common::{EnvironmentChanges, PathfindingAlgorithm};
+use crate::algorithms::registry::{AlgorithmContext, AlgorithmRegistry};
+use crate::config::Config;
+use crate::error::ConfigError;
+use crate::grid::{Grid, Position};
+use std::collections::HashSet;
+
+/// An adversary chasing this run's own agent (`--pursuer`). It knows the whole map,
+/// plans towards wherever the agent stands every step with a planner of the
+/// `--pursuer-algorithm` kind, and moves after the agents have. The agents see it
+/// like a moving obstacle.
+pub struct Pursuer {
+    pub position: Position,
+    /// Cell it stood on before its last turn, so the agents can tell which way it is
+    /// heading
+    pub previous: Position,
+    algorithm: Box<dyn PathfindingAlgorithm>,
+    pub moves: usize,
+}
+
+impl Pursuer {
+    /// A pursuer starting on `start`, chasing an agent that starts on the grid's start
+    pub fn new(
+        config: &Config,
+        registry: &AlgorithmRegistry,
+        grid: &Grid,
+        start: Position,
+    ) -> Result<Self, ConfigError> {
+        let algorithm = registry.create(
+            &config.pursuer_algorithm,
+            &AlgorithmContext {
+                config,
+                start,
+                goal: grid.start,
+                width: grid.width,
+                height: grid.height,
+            },
+        )?;
+        Ok(Pursuer {
+            position: start,
+            previous: start,
+            algorithm,
+            moves: 0,
+        })
+    }
+
+    /// Take one step towards `target` along a fresh plan around the `blocked` cells
+    /// (obstacles and other agents), telling incremental planners which walls changed;
+    /// waits when there is no way through for now
+    pub fn chase(
+        &mut self,
+        grid: &Grid,
+        target: Position,
+        blocked: &HashSet<Position>,
+        changed_walls: &[Position],
+    ) {
+        self.previous = self.position;
+        self.algorithm
+            .update_environment(grid, blocked, &EnvironmentChanges::walls(changed_walls));
+        let next = self
+            .algorithm
+            .find_path_to_any(grid, self.position, &[target], blocked)
+            .and_then(|path| path.get(1).copied())
+            .filter(|next| !blocked.contains(next));
+        if let Some(next) = next {
+            self.position = next;
+            self.moves += 1;
+        }
+    }
+}
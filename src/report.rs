@@ -0,0 +1,669 @@
+use crate::batch_summary::{self, BatchSummary, Metric, Run, Spread};
+use crate::config::{BatchOptions, Config};
+use std::fmt::Write as _;
+
+/// Most points along the wall density axis of the success rate chart; more distinct
+/// densities are put in bins of equal width
+const DENSITY_POINTS: usize = 20;
+
+/// Colours of the algorithms in the charts, in turn
+const PALETTE: [&str; 10] = [
+    "#1f77b4", "#ff7f0e", "#2ca02c", "#d62728", "#9467bd", "#8c564b", "#e377c2", "#7f7f7f",
+    "#bcbd22", "#17becf",
+];
+
+/// What a report is written as, by the extension of its file
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ReportFormat {
+    /// A self-contained page with inline styles and SVG charts
+    Html,
+    /// Tables only, the charts given as tables too
+    Markdown,
+}
+
+impl ReportFormat {
+    fn of(path: &str) -> Self {
+        let lower = path.to_lowercase();
+        if lower.ends_with(".md") || lower.ends_with(".markdown") {
+            ReportFormat::Markdown
+        } else {
+            ReportFormat::Html
+        }
+    }
+}
+
+/// A table of the report, cells already formatted
+struct Table {
+    headers: Vec<String>,
+    rows: Vec<Vec<String>>,
+}
+
+/// Success rate of every algorithm at each wall density swept
+struct DensityChart {
+    /// Wall densities, in percent of the cells
+    densities: Vec<f64>,
+    /// Each algorithm's success rate in percent at each density, if it ran at it
+    series: Vec<(String, Vec<Option<f64>>)>,
+}
+
+/// Spread of every algorithm's average find_path time per run, in microseconds
+struct TimingChart {
+    /// 5th, 25th, 50th, 75th and 95th percentiles, by algorithm
+    boxes: Vec<(String, [f64; 5])>,
+}
+
+/// Write the summary of a batch to `path` as an HTML page, or as Markdown when it ends
+/// in `.md`: its options, a table of every algorithm's statistics, charts of success
+/// rate against wall density and of planning time, and, when asked for, the
+/// configurations and the paired comparisons.
+pub fn write_report(
+    path: &str,
+    summary: &BatchSummary,
+    config: &Config,
+    options: &BatchOptions,
+) -> Result<(), String> {
+    let mut rng = BatchSummary::rng();
+    let algorithms = summary.algorithms();
+
+    let overview = overview(summary, &algorithms, config, options);
+    let metrics = summary.metrics();
+    let results = results_table(&algorithms, &metrics, &mut rng);
+    let density = density_chart(&algorithms);
+    let timing = timing_chart(&algorithms);
+    let configurations = options
+        .summary_by_configuration
+        .then(|| configurations_table(summary, &algorithms, &metrics));
+    let comparisons = options
+        .significance_test
+        .then(|| comparisons_table(summary, &mut rng));
+
+    let text = match ReportFormat::of(path) {
+        ReportFormat::Html => {
+            let mut page = String::new();
+            page += "<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n";
+            page += "<title>Batch simulation report</title>\n";
+            page += STYLE;
+            page += "</head>\n<body>\n<h1>Batch simulation report</h1>\n";
+            page += &html_table(&overview);
+            page += "<h2>Results by algorithm</h2>\n";
+            page += "<p>Intervals are 95% confidence intervals of the mean; moves, efficiency \
+                     and time are of successful runs.</p>\n";
+            page += &html_table(&results);
+            page += "<h2>Success rate by wall density</h2>\n";
+            page += &density_svg(&density);
+            page += "<h2>Planning time per run</h2>\n";
+            page += "<p>Average find_path time of each run in microseconds: whiskers from the \
+                     5th to the 95th percentile, the box from the 25th to the 75th, and a line \
+                     at the median.</p>\n";
+            page += &timing_svg(&timing);
+            if let Some(table) = &configurations {
+                page += "<h2>Results by configuration</h2>\n";
+                page += &html_table(table);
+            }
+            if let Some(table) = &comparisons {
+                page += "<h2>Paired comparisons</h2>\n";
+                let _ = writeln!(
+                    page,
+                    "<p>{}.</p>",
+                    escape(&batch_summary::comparisons_note())
+                );
+                page += &html_table(table);
+            }
+            page += "</body>\n</html>\n";
+            page
+        }
+        ReportFormat::Markdown => {
+            let mut text = String::from("# Batch simulation report\n\n");
+            text += &markdown_table(&overview);
+            text += "\n## Results by algorithm\n\n";
+            text += "Intervals are 95% confidence intervals of the mean; moves, efficiency and \
+                     time are of successful runs.\n\n";
+            text += &markdown_table(&results);
+            text += "\n## Success rate by wall density\n\n";
+            text += &markdown_table(&density_table(&density));
+            text += "\n## Planning time per run\n\n";
+            text += "Percentiles of the average find_path time of each run, in microseconds.\n\n";
+            text += &markdown_table(&timing_table(&timing));
+            if let Some(table) = &configurations {
+                text += "\n## Results by configuration\n\n";
+                text += &markdown_table(table);
+            }
+            if let Some(table) = &comparisons {
+                text += "\n## Paired comparisons\n\n";
+                let _ = writeln!(text, "{}.\n", batch_summary::comparisons_note());
+                text += &markdown_table(table);
+            }
+            text
+        }
+    };
+    std::fs::write(path, text).map_err(|e| format!("Failed to write report '{}': {}", path, e))
+}
+
+/// The options of the batch and how many results it gave
+fn overview(
+    summary: &BatchSummary,
+    algorithms: &[(&str, Vec<&Run>)],
+    config: &Config,
+    options: &BatchOptions,
+) -> Table {
+    let mut rows = vec![vec!["Algorithm".to_string(), config.algorithm.clone()]];
+    let sizes = options.grid_size_range(config);
+    let grid = match options.sweeps_grid_size(config) {
+        true => format!(
+            "{} to {} in steps of {}",
+            options.min_grid_size.unwrap_or(config.grid_size),
+            sizes.last().unwrap_or(config.grid_size),
+            options.grid_size_step
+        ),
+        false => format!("{}x{}", config.width(), config.height()),
+    };
+    rows.push(vec!["Grid size".to_string(), grid]);
+    rows.push(vec![
+        "Walls".to_string(),
+        format!("{} to {}", options.min_walls, options.max_walls),
+    ]);
+    rows.push(vec![
+        "Obstacles".to_string(),
+        format!("{} to {}", options.min_obstacles, options.max_obstacles),
+    ]);
+    if let Some(sweep) = &options.sweep {
+        rows.push(vec!["Sweep file".to_string(), sweep.clone()]);
+    }
+    rows.push(vec![
+        "Simulations per configuration".to_string(),
+        options.num_simulations.to_string(),
+    ]);
+    if let Some(seed) = config.seed {
+        rows.push(vec!["Seed".to_string(), seed.to_string()]);
+    }
+    rows.push(vec![
+        "Configurations".to_string(),
+        summary.configurations().len().to_string(),
+    ]);
+    rows.push(vec![
+        "Results".to_string(),
+        algorithms
+            .iter()
+            .map(|(_, runs)| runs.len())
+            .sum::<usize>()
+            .to_string(),
+    ]);
+    rows.push(vec!["Output file".to_string(), options.output_file()]);
+    Table {
+        headers: vec!["Option".to_string(), "Value".to_string()],
+        rows,
+    }
+}
+
+/// Success rate and the spread of every metric, by algorithm
+fn results_table(
+    algorithms: &[(&str, Vec<&Run>)],
+    metrics: &[&Metric],
+    rng: &mut rand::rngs::StdRng,
+) -> Table {
+    let mut headers = vec!["Algorithm".to_string(), "Success rate".to_string()];
+    for metric in metrics {
+        headers.push(format!("{} (mean [95% CI])", metric.name));
+        headers.push(format!("{} median / p95 / p99", metric.name));
+    }
+    let rows = algorithms
+        .iter()
+        .map(|(name, runs)| {
+            let successes: Vec<f64> = runs.iter().map(|run| f64::from(run.success)).collect();
+            let (low, high) = batch_summary::confidence_interval(&successes, rng);
+            let mut row = vec![
+                name.to_string(),
+                format!(
+                    "{}/{} ({:.1}%) [{:.1}%, {:.1}%]",
+                    successes.iter().filter(|&&success| success > 0.0).count(),
+                    runs.len(),
+                    batch_summary::mean(&successes) * 100.0,
+                    low * 100.0,
+                    high * 100.0
+                ),
+            ];
+            let successful: Vec<&&Run> = runs.iter().filter(|run| run.success).collect();
+            for metric in metrics {
+                if successful.is_empty() {
+                    row.extend(["-".to_string(), "-".to_string()]);
+                    continue;
+                }
+                let values: Vec<f64> = successful.iter().map(|run| (metric.value)(run)).collect();
+                let spread = Spread::of(&values, rng);
+                row.push(format!(
+                    "{:.p$}{u} [{:.p$}, {:.p$}]",
+                    spread.mean,
+                    spread.low,
+                    spread.high,
+                    p = metric.precision,
+                    u = metric.unit
+                ));
+                row.push(format!(
+                    "{:.p$} / {:.p$} / {:.p$}",
+                    spread.median,
+                    spread.p95,
+                    spread.p99,
+                    p = metric.precision
+                ));
+            }
+            row
+        })
+        .collect();
+    Table { headers, rows }
+}
+
+/// Success rate of every algorithm in every configuration, with the mean of each metric
+fn configurations_table(
+    summary: &BatchSummary,
+    algorithms: &[(&str, Vec<&Run>)],
+    metrics: &[&Metric],
+) -> Table {
+    let mut headers = vec!["Configuration".to_string(), "Algorithm".to_string()];
+    headers.push("Success rate".to_string());
+    headers.extend(metrics.iter().map(|metric| metric.name.to_string()));
+    let mut rows = Vec::new();
+    for (configuration, label) in summary.configurations().iter().enumerate() {
+        for (name, runs) in algorithms {
+            let runs: Vec<&&Run> = runs
+                .iter()
+                .filter(|run| run.configuration == configuration)
+                .collect();
+            if runs.is_empty() {
+                continue;
+            }
+            let successful: Vec<&&&Run> = runs.iter().filter(|run| run.success).collect();
+            let mut row = vec![
+                label.clone(),
+                name.to_string(),
+                format!("{}/{}", successful.len(), runs.len()),
+            ];
+            for metric in metrics {
+                let values: Vec<f64> = successful.iter().map(|run| (metric.value)(run)).collect();
+                row.push(match values.is_empty() {
+                    true => "-".to_string(),
+                    false => format!(
+                        "{:.p$}{}",
+                        batch_summary::mean(&values),
+                        metric.unit,
+                        p = metric.precision
+                    ),
+                });
+            }
+            rows.push(row);
+        }
+    }
+    Table { headers, rows }
+}
+
+/// The paired comparisons of every two algorithms, a row per metric
+fn comparisons_table(summary: &BatchSummary, rng: &mut rand::rngs::StdRng) -> Table {
+    let headers = [
+        "Algorithms",
+        "Paired runs",
+        "Metric",
+        "Difference",
+        "p-value",
+    ]
+    .map(String::from)
+    .to_vec();
+    let mut rows = Vec::new();
+    for comparison in summary.comparisons(rng) {
+        let algorithms = format!("{} vs {}", comparison.first, comparison.second);
+        let pairs = format!(
+            "{} ({} both successful)",
+            comparison.pairs, comparison.both_successful
+        );
+        if comparison.differences.is_empty() {
+            rows.push(vec![
+                algorithms.clone(),
+                pairs.clone(),
+                "-".into(),
+                "-".into(),
+                "-".into(),
+            ]);
+        }
+        for difference in &comparison.differences {
+            rows.push(vec![
+                algorithms.clone(),
+                pairs.clone(),
+                difference.name.to_string(),
+                format!(
+                    "{:+.p$}{}",
+                    difference.mean,
+                    difference.unit,
+                    p = difference.precision + 1
+                ),
+                batch_summary::significance(difference.p),
+            ]);
+        }
+    }
+    Table { headers, rows }
+}
+
+/// Success rates by wall density, the densities binned when there are many
+fn density_chart(algorithms: &[(&str, Vec<&Run>)]) -> DensityChart {
+    let mut densities: Vec<f64> = algorithms
+        .iter()
+        .flat_map(|(_, runs)| runs.iter().map(|run| run.wall_density * 100.0))
+        .collect();
+    densities.sort_by(f64::total_cmp);
+    densities.dedup_by(|a, b| (*a - *b).abs() < 1e-9);
+    let (min, max) = match (densities.first(), densities.last()) {
+        (Some(&min), Some(&max)) => (min, max),
+        _ => (0.0, 0.0),
+    };
+    let binned = densities.len() > DENSITY_POINTS;
+    let width = (max - min) / DENSITY_POINTS as f64;
+    // Index of the point a run counts towards
+    let point = |run: &Run| -> usize {
+        let density = run.wall_density * 100.0;
+        match binned {
+            true => (((density - min) / width) as usize).min(DENSITY_POINTS - 1),
+            false => densities
+                .iter()
+                .position(|&d| (d - density).abs() < 1e-9)
+                .unwrap_or_default(),
+        }
+    };
+    let points = match binned {
+        true => (0..DENSITY_POINTS)
+            .map(|bin| min + width * (bin as f64 + 0.5))
+            .collect(),
+        false => densities.clone(),
+    };
+
+    let series = algorithms
+        .iter()
+        .map(|(name, runs)| {
+            let mut counts = vec![(0, 0); points.len()];
+            for run in runs {
+                let (successes, total) = &mut counts[point(run)];
+                *successes += usize::from(run.success);
+                *total += 1;
+            }
+            let rates = counts
+                .into_iter()
+                .map(|(successes, total)| {
+                    (total > 0).then(|| successes as f64 / total as f64 * 100.0)
+                })
+                .collect();
+            (name.to_string(), rates)
+        })
+        .collect();
+    DensityChart {
+        densities: points,
+        series,
+    }
+}
+
+/// Percentiles of the find_path time of every algorithm's runs that planned at all
+fn timing_chart(algorithms: &[(&str, Vec<&Run>)]) -> TimingChart {
+    let boxes = algorithms
+        .iter()
+        .filter_map(|(name, runs)| {
+            let mut times: Vec<f64> = runs
+                .iter()
+                .map(|run| run.find_path_time_us)
+                .filter(|&time| time > 0.0)
+                .collect();
+            if times.is_empty() {
+                return None;
+            }
+            times.sort_by(f64::total_cmp);
+            let quantiles = [0.05, 0.25, 0.5, 0.75, 0.95]
+                .map(|fraction| batch_summary::percentile(&times, fraction));
+            Some((name.to_string(), quantiles))
+        })
+        .collect();
+    TimingChart { boxes }
+}
+
+/// The success rate chart as a table, a row per density
+fn density_table(chart: &DensityChart) -> Table {
+    let mut headers = vec!["Wall density".to_string()];
+    headers.extend(chart.series.iter().map(|(name, _)| name.clone()));
+    let rows = chart
+        .densities
+        .iter()
+        .enumerate()
+        .map(|(i, density)| {
+            let mut row = vec![format!("{:.1}%", density)];
+            row.extend(chart.series.iter().map(|(_, rates)| match rates[i] {
+                Some(rate) => format!("{:.1}%", rate),
+                None => "-".to_string(),
+            }));
+            row
+        })
+        .collect();
+    Table { headers, rows }
+}
+
+/// The planning time chart as a table, a row per algorithm
+fn timing_table(chart: &TimingChart) -> Table {
+    let headers = ["Algorithm", "p5", "p25", "Median", "p75", "p95"]
+        .map(String::from)
+        .to_vec();
+    let rows = chart
+        .boxes
+        .iter()
+        .map(|(name, quantiles)| {
+            let mut row = vec![name.clone()];
+            row.extend(quantiles.iter().map(|time| format!("{:.1}", time)));
+            row
+        })
+        .collect();
+    Table { headers, rows }
+}
+
+/// Line chart of the success rate against wall density, an SVG element
+fn density_svg(chart: &DensityChart) -> String {
+    let (width, height) = (640.0, 320.0);
+    let (left, right, top, bottom) = (50.0, 20.0, 15.0, 40.0);
+    let (plot_width, plot_height) = (width - left - right, height - top - bottom);
+    let (min, max) = match (chart.densities.first(), chart.densities.last()) {
+        (Some(&min), Some(&max)) if max > min => (min, max),
+        (Some(&density), _) => (density - 1.0, density + 1.0),
+        _ => (0.0, 1.0),
+    };
+    let x = |density: f64| left + (density - min) / (max - min) * plot_width;
+    let y = |rate: f64| top + (100.0 - rate) / 100.0 * plot_height;
+
+    let mut svg = format!(
+        "<svg viewBox=\"0 0 {} {}\" width=\"{}\" height=\"{}\" role=\"img\">\n",
+        width, height, width, height
+    );
+    for rate in [0.0, 25.0, 50.0, 75.0, 100.0] {
+        let _ = writeln!(
+            svg,
+            "<line class=\"grid\" x1=\"{}\" y1=\"{:.1}\" x2=\"{}\" y2=\"{:.1}\"/>\
+             <text x=\"{}\" y=\"{:.1}\" text-anchor=\"end\">{}%</text>",
+            left,
+            y(rate),
+            width - right,
+            y(rate),
+            left - 6.0,
+            y(rate) + 4.0,
+            rate
+        );
+    }
+    for i in 0..=4 {
+        let density = min + (max - min) * i as f64 / 4.0;
+        let _ = writeln!(
+            svg,
+            "<text x=\"{:.1}\" y=\"{}\" text-anchor=\"middle\">{:.1}%</text>",
+            x(density),
+            height - bottom + 16.0,
+            density
+        );
+    }
+    let _ = writeln!(
+        svg,
+        "<text x=\"{}\" y=\"{}\" text-anchor=\"middle\">Wall density (share of cells)</text>",
+        left + plot_width / 2.0,
+        height - 6.0
+    );
+    for (i, (name, rates)) in chart.series.iter().enumerate() {
+        let colour = PALETTE[i % PALETTE.len()];
+        let points: Vec<(f64, f64)> = chart
+            .densities
+            .iter()
+            .zip(rates)
+            .filter_map(|(&density, rate)| rate.map(|rate| (x(density), y(rate))))
+            .collect();
+        let line: Vec<String> = points
+            .iter()
+            .map(|(x, y)| format!("{:.1},{:.1}", x, y))
+            .collect();
+        let _ = writeln!(
+            svg,
+            "<polyline fill=\"none\" stroke=\"{}\" stroke-width=\"2\" points=\"{}\"><title>{}</title></polyline>",
+            colour,
+            line.join(" "),
+            escape(name)
+        );
+        for (x, y) in points {
+            let _ = writeln!(
+                svg,
+                "<circle cx=\"{:.1}\" cy=\"{:.1}\" r=\"3\" fill=\"{}\"/>",
+                x, y, colour
+            );
+        }
+    }
+    svg += "</svg>\n";
+    svg + &legend(chart.series.iter().map(|(name, _)| name.as_str()))
+}
+
+/// Box plots of the planning time of every algorithm, an SVG element
+fn timing_svg(chart: &TimingChart) -> String {
+    if chart.boxes.is_empty() {
+        return "<p>No run called find_path.</p>\n".to_string();
+    }
+    let (width, row) = (640.0, 26.0);
+    let (left, right, top, bottom) = (160.0, 70.0, 10.0, 30.0);
+    let height = top + bottom + row * chart.boxes.len() as f64;
+    let plot_width = width - left - right;
+    let max = chart
+        .boxes
+        .iter()
+        .map(|(_, quantiles)| quantiles[4])
+        .fold(0.0, f64::max)
+        .max(f64::MIN_POSITIVE);
+    let x = |time: f64| left + time / max * plot_width;
+
+    let mut svg = format!(
+        "<svg viewBox=\"0 0 {} {}\" width=\"{}\" height=\"{}\" role=\"img\">\n",
+        width, height, width, height
+    );
+    for i in 0..=4 {
+        let time = max * i as f64 / 4.0;
+        let _ = writeln!(
+            svg,
+            "<line class=\"grid\" x1=\"{0:.1}\" y1=\"{1}\" x2=\"{0:.1}\" y2=\"{2}\"/>\
+             <text x=\"{0:.1}\" y=\"{3}\" text-anchor=\"middle\">{4:.1} µs</text>",
+            x(time),
+            top,
+            height - bottom,
+            height - bottom + 16.0,
+            time
+        );
+    }
+    for (i, (name, [p5, p25, median, p75, p95])) in chart.boxes.iter().enumerate() {
+        let colour = PALETTE[i % PALETTE.len()];
+        let middle = top + row * (i as f64 + 0.5);
+        let _ = writeln!(
+            svg,
+            "<text x=\"{}\" y=\"{:.1}\" text-anchor=\"end\">{}</text>\
+             <line x1=\"{:.1}\" y1=\"{:.1}\" x2=\"{:.1}\" y2=\"{:.1}\" stroke=\"{}\"/>\
+             <rect x=\"{:.1}\" y=\"{:.1}\" width=\"{:.1}\" height=\"{}\" fill=\"{}\" fill-opacity=\"0.4\" stroke=\"{}\"/>\
+             <line x1=\"{:.1}\" y1=\"{:.1}\" x2=\"{:.1}\" y2=\"{:.1}\" stroke=\"{}\" stroke-width=\"2\"/>\
+             <text x=\"{:.1}\" y=\"{:.1}\">{:.1} µs</text>",
+            left - 8.0,
+            middle + 4.0,
+            escape(name),
+            x(*p5),
+            middle,
+            x(*p95),
+            middle,
+            colour,
+            x(*p25),
+            middle - 8.0,
+            (x(*p75) - x(*p25)).max(1.0),
+            16.0,
+            colour,
+            colour,
+            x(*median),
+            middle - 8.0,
+            x(*median),
+            middle + 8.0,
+            colour,
+            width - right + 6.0,
+            middle + 4.0,
+            median
+        );
+    }
+    svg += "</svg>\n";
+    svg
+}
+
+/// The colour of every algorithm in the charts
+fn legend<'a>(names: impl Iterator<Item = &'a str>) -> String {
+    let mut legend = String::from("<p class=\"legend\">");
+    for (i, name) in names.enumerate() {
+        let _ = write!(
+            legend,
+            "<span><i style=\"background:{}\"></i>{}</span>",
+            PALETTE[i % PALETTE.len()],
+            escape(name)
+        );
+    }
+    legend + "</p>\n"
+}
+
+fn html_table(table: &Table) -> String {
+    let mut html = String::from("<table>\n<tr>");
+    for header in &table.headers {
+        let _ = write!(html, "<th>{}</th>", escape(header));
+    }
+    html += "</tr>\n";
+    for row in &table.rows {
+        html += "<tr>";
+        for cell in row {
+            let _ = write!(html, "<td>{}</td>", escape(cell));
+        }
+        html += "</tr>\n";
+    }
+    html + "</table>\n"
+}
+
+fn markdown_table(table: &Table) -> String {
+    let line = |cells: &[String]| {
+        let cells: Vec<String> = cells.iter().map(|cell| cell.replace('|', "\\|")).collect();
+        format!("| {} |\n", cells.join(" | "))
+    };
+    let mut text = line(&table.headers);
+    text += &line(&vec!["---".to_string(); table.headers.len()]);
+    for row in &table.rows {
+        text += &line(row);
+    }
+    text
+}
+
+/// Text with the characters HTML reads as markup escaped
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+const STYLE: &str = "<style>
+body { font-family: system-ui, sans-serif; margin: 2em auto; max-width: 72em; padding: 0 1em; color: #222; }
+table { border-collapse: collapse; margin: 1em 0; font-size: 0.9em; }
+th, td { border: 1px solid #ccc; padding: 0.3em 0.6em; text-align: left; }
+th { background: #f3f3f3; }
+svg { display: block; font-size: 11px; }
+svg .grid { stroke: #e5e5e5; }
+.legend span { margin-right: 1.2em; white-space: nowrap; }
+.legend i { display: inline-block; width: 0.8em; height: 0.8em; margin-right: 0.3em; }
+</style>
+";
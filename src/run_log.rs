@@ -0,0 +1,76 @@
+use crate::grid::Position;
+use crate::observer::SimulationObserver;
+use crate::simulation::{Failure, Simulation, StepOutcome};
+use std::time::Duration;
+use tracing::{debug, trace};
+
+/// Logs a run as `tracing` events: its start and end at `--log-level debug`, and how
+/// every step went at `trace`. The simulation logs its `run`, `step` and `replan`
+/// spans itself; the events are logged inside them, so each names the run's
+/// algorithm and seed and the step it happened on.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RunLog;
+
+/// A cell as `[x, y]`
+fn cell(position: Position) -> [usize; 2] {
+    [position.x, position.y]
+}
+
+impl SimulationObserver for RunLog {
+    fn on_start(&mut self, simulation: &Simulation) {
+        let targets: Vec<[usize; 2]> = simulation.targets().into_iter().map(cell).collect();
+        debug!(
+            start = ?cell(simulation.agent.position),
+            targets = ?targets,
+            optimal_path_length = simulation.optimal_path_length(),
+            "run_start"
+        );
+    }
+
+    fn on_step(&mut self, simulation: &Simulation, outcome: StepOutcome) {
+        match outcome {
+            StepOutcome::Moved { to, replanned, .. } => {
+                trace!(outcome = "moved", position = ?cell(to), replanned, "step")
+            }
+            StepOutcome::Waited { replanned } => trace!(
+                outcome = "waited",
+                position = ?cell(simulation.agent.position),
+                replanned,
+                "step"
+            ),
+            StepOutcome::Finished => trace!(outcome = "finished", "step"),
+            StepOutcome::Failed(failure) => {
+                trace!(outcome = "failed", failure = failure_name(failure), "step")
+            }
+        }
+    }
+
+    fn on_finish(&mut self, simulation: &Simulation) {
+        let statistics = simulation.statistics();
+        let outcome = match simulation.outcome() {
+            Some(StepOutcome::Failed(failure)) => failure_name(failure),
+            Some(StepOutcome::Finished) => "finished",
+            _ => "stopped",
+        };
+        let find_path_time: Duration = simulation.timing_data().find_path_times.iter().sum();
+        debug!(
+            outcome,
+            moves = statistics.total_moves,
+            planner_calls = simulation.planner_calls(),
+            find_path_time_ns = find_path_time.as_nanos() as u64,
+            "run_finish"
+        );
+    }
+}
+
+/// Name of a failure in the logs (and the frames of the web viewer)
+pub(crate) fn failure_name(failure: Failure) -> &'static str {
+    match failure {
+        Failure::NoPath => "no_path",
+        Failure::Stuck => "stuck",
+        Failure::OutOfEnergy => "out_of_energy",
+        Failure::Caught => "caught",
+        Failure::StepLimit => "step_limit",
+        Failure::TimedOut => "timed_out",
+    }
+}
@@ -0,0 +1,411 @@
+use crate::checkpoint::Checkpoint;
+use crate::config::{Cli, Config};
+use crate::grid::Position;
+use crate::moving_obstacle::{MovingObstacle, Trajectory};
+use crate::obstacle_script::{ObstacleScript, ObstacleWave, WaveEntry};
+use crate::simulation::{EnvironmentSetup, WallEvent};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// A fully specified experiment, read from a TOML file: the options of the run under
+/// `[settings]`, by their long names (`vision_radius = 5`), and optionally the
+/// environment to run on under `[environment]`, cells given as `[x, y]` or
+/// `[x, y, floor]`. `--dump-scenario` writes one for any run, and `--checkpoint` one
+/// with a `[checkpoint]` of how far the run got.
+pub struct Scenario {
+    path: String,
+    file: ScenarioFile,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ScenarioFile {
+    #[serde(default)]
+    settings: toml::Table,
+    environment: Option<EnvironmentEntry>,
+    checkpoint: Option<CheckpointEntry>,
+}
+
+/// Everything of an environment the options do not already say
+#[derive(Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct EnvironmentEntry {
+    width: usize,
+    height: usize,
+    #[serde(default = "one_floor")]
+    floors: usize,
+    start: Vec<usize>,
+    goal: Vec<usize>,
+    #[serde(default)]
+    walls: Vec<Vec<usize>>,
+    #[serde(default)]
+    extra_goals: Vec<Vec<usize>>,
+    /// `[x, y, cost]` or `[x, y, floor, cost]`
+    #[serde(default)]
+    terrain: Vec<Vec<usize>>,
+    #[serde(default)]
+    stairs: Vec<Vec<usize>>,
+    #[serde(default)]
+    portals: Vec<[Vec<usize>; 2]>,
+    /// Where the goal is after each step of its wander (empty for a fixed goal)
+    #[serde(default)]
+    goal_timeline: Vec<Vec<usize>>,
+    /// Start and goal of every other agent
+    #[serde(default)]
+    agent_routes: Vec<[Vec<usize>; 2]>,
+    pursuer_start: Option<Vec<usize>>,
+    /// A string, since TOML integers stop short of the largest seeds
+    sensor_seed: Option<String>,
+    #[serde(default)]
+    wall_events: Vec<WallEventEntry>,
+    #[serde(default)]
+    moving_obstacles: Vec<MoverEntry>,
+    /// The obstacle timeline, as in an obstacle script
+    #[serde(default)]
+    obstacles: Vec<WaveEntry>,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct WallEventEntry {
+    step: usize,
+    cell: Vec<usize>,
+    /// Whether a wall appears (true) or disappears (false)
+    wall: bool,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct MoverEntry {
+    trajectory: String,
+    route: Vec<Vec<usize>>,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct CheckpointEntry {
+    step: usize,
+    agent: Vec<usize>,
+    moves: usize,
+    replans: usize,
+    #[serde(default)]
+    companions: Vec<Vec<usize>>,
+    pursuer: Option<Vec<usize>>,
+}
+
+fn one_floor() -> usize {
+    1
+}
+
+impl Scenario {
+    /// Read a scenario file
+    pub fn load(path: &str) -> Result<Self, String> {
+        let text = std::fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read scenario '{}': {}", path, e))?;
+        let file: ScenarioFile =
+            toml::from_str(&text).map_err(|e| format!("Invalid scenario '{}': {}", path, e))?;
+        Ok(Scenario {
+            path: path.to_string(),
+            file,
+        })
+    }
+
+    /// Arguments for the option values of the scenario's settings, a layer of
+    /// `Cli::layered`
+    pub fn settings_args(&self) -> Result<Vec<String>, String> {
+        Cli::table_args(&self.file.settings)
+            .map_err(|e| format!("Invalid settings in scenario '{}': {}", self.path, e))
+    }
+
+    /// How far the run the scenario was written from got, for a checkpoint
+    pub fn checkpoint(&self) -> Result<Option<Checkpoint>, String> {
+        let Some(entry) = &self.file.checkpoint else {
+            return Ok(None);
+        };
+        let cell = |cell: &[usize]| {
+            position(cell).ok_or_else(|| {
+                format!(
+                    "Scenario '{}' has a checkpoint cell that is not [x, y] or [x, y, floor]",
+                    self.path
+                )
+            })
+        };
+        Ok(Some(Checkpoint {
+            step: entry.step,
+            agent: cell(&entry.agent)?,
+            moves: entry.moves,
+            replans: entry.replans,
+            companions: entry
+                .companions
+                .iter()
+                .map(|entry| cell(entry))
+                .collect::<Result<_, _>>()?,
+            pursuer: entry.pursuer.as_deref().map(cell).transpose()?,
+        }))
+    }
+
+    /// The scenario's environment for a run with the given options (which say
+    /// how cells connect and how long obstacles stay), if it has one
+    pub fn environment(&self, config: &Config) -> Result<Option<EnvironmentSetup>, String> {
+        let Some(entry) = &self.file.environment else {
+            return Ok(None);
+        };
+        let invalid = |what: &str| format!("Scenario '{}' has {}", self.path, what);
+        let cell = |cell: &[usize]| {
+            position(cell).ok_or_else(|| invalid("a cell that is not [x, y] or [x, y, floor]"))
+        };
+        let cells = |cells: &[Vec<usize>]| {
+            cells
+                .iter()
+                .map(|entry| cell(entry))
+                .collect::<Result<Vec<_>, _>>()
+        };
+        let pair = |[a, b]: &[Vec<usize>; 2]| Ok::<_, String>((cell(a)?, cell(b)?));
+
+        let terrain = entry
+            .terrain
+            .iter()
+            .map(|cell| match cell[..] {
+                [x, y, cost] => Ok((Position { x, y, z: 0 }, cost as u32)),
+                [x, y, z, cost] => Ok((Position { x, y, z }, cost as u32)),
+                _ => Err(invalid(
+                    "terrain that is not [x, y, cost] or [x, y, floor, cost]",
+                )),
+            })
+            .collect::<Result<HashMap<_, _>, _>>()?;
+        let wall_events = entry
+            .wall_events
+            .iter()
+            .map(|event| {
+                Ok(WallEvent {
+                    step: event.step,
+                    pos: cell(&event.cell)?,
+                    wall: event.wall,
+                })
+            })
+            .collect::<Result<Vec<_>, String>>()?;
+        let moving_obstacles = entry
+            .moving_obstacles
+            .iter()
+            .map(|mover| {
+                let trajectory = Trajectory::from_name(&mover.trajectory)?
+                    .ok_or_else(|| invalid("a moving obstacle with a 'mixed' trajectory"))?;
+                let route = cells(&mover.route)?;
+                if route.is_empty() {
+                    return Err(invalid("a moving obstacle without a route"));
+                }
+                Ok(MovingObstacle { trajectory, route })
+            })
+            .collect::<Result<Vec<_>, String>>()?;
+        let sensor_seed = match &entry.sensor_seed {
+            Some(seed) => seed
+                .parse()
+                .map_err(|_| invalid("a sensor seed that is not a whole number"))?,
+            None => 0,
+        };
+
+        let environment = EnvironmentSetup {
+            width: entry.width,
+            height: entry.height,
+            floors: entry.floors,
+            start: cell(&entry.start)?,
+            goal: cell(&entry.goal)?,
+            walls: cells(&entry.walls)?.into_iter().collect(),
+            obstacle_timeline: Vec::new(),
+            obstacle_cycle_interval: config.obstacle_interval.max(1),
+            obstacle_persistence_cycles: config.obstacle_persistence.max(1),
+            obstacle_waves: ObstacleScript::from_entries(entry.obstacles.clone(), &self.path)?
+                .waves,
+            goal_timeline: cells(&entry.goal_timeline)?,
+            extra_goals: cells(&entry.extra_goals)?,
+            waypoints: Vec::new(),
+            diagonal_movement: config.diagonal_movement,
+            hex_grid: config.hex_grid,
+            wrap_edges: config.wrap_edges,
+            terrain,
+            stairs: cells(&entry.stairs)?.into_iter().collect(),
+            portals: entry.portals.iter().map(pair).collect::<Result<_, _>>()?,
+            wall_events,
+            moving_obstacles,
+            sensor_seed,
+            agent_routes: entry
+                .agent_routes
+                .iter()
+                .map(pair)
+                .collect::<Result<_, _>>()?,
+            pursuer_start: entry.pursuer_start.as_deref().map(cell).transpose()?,
+        };
+        if environment.width == 0 || environment.height == 0 || environment.floors == 0 {
+            return Err(invalid("an empty grid"));
+        }
+        let every_cell = [environment.start, environment.goal]
+            .into_iter()
+            .chain(environment.walls.iter().copied())
+            .chain(environment.extra_goals.iter().copied())
+            .chain(environment.terrain.keys().copied())
+            .chain(environment.stairs.iter().copied())
+            .chain(environment.goal_timeline.iter().copied())
+            .chain(environment.portals.iter().flat_map(|&(a, b)| [a, b]))
+            .chain(environment.agent_routes.iter().flat_map(|&(a, b)| [a, b]))
+            .chain(environment.pursuer_start)
+            .chain(environment.wall_events.iter().map(|event| event.pos))
+            .chain(
+                environment
+                    .moving_obstacles
+                    .iter()
+                    .flat_map(|mover| mover.route.iter().copied()),
+            )
+            .chain(
+                environment
+                    .obstacle_waves
+                    .iter()
+                    .flat_map(|wave| wave.cells.iter().copied()),
+            );
+        for pos in every_cell {
+            environment.check_position("Scenario cell", pos)?;
+        }
+        Ok(Some(environment))
+    }
+
+    /// Write the options of a run and the environment it runs on to a scenario file.
+    /// The random obstacle timeline is written as the obstacles it places, step by
+    /// step, which the file then replays the same way.
+    pub fn save(config: &Config, environment: &EnvironmentSetup, path: &str) -> Result<(), String> {
+        Self::write(config, environment, None, path)
+    }
+
+    /// Write a scenario file like `save`, with a checkpoint of how far a run of it got
+    pub fn save_checkpoint(
+        config: &Config,
+        environment: &EnvironmentSetup,
+        checkpoint: &Checkpoint,
+        path: &str,
+    ) -> Result<(), String> {
+        let entry = CheckpointEntry {
+            step: checkpoint.step,
+            agent: cell_entry(checkpoint.agent),
+            moves: checkpoint.moves,
+            replans: checkpoint.replans,
+            companions: checkpoint
+                .companions
+                .iter()
+                .copied()
+                .map(cell_entry)
+                .collect(),
+            pursuer: checkpoint.pursuer.map(cell_entry),
+        };
+        Self::write(config, environment, Some(entry), path)
+    }
+
+    fn write(
+        config: &Config,
+        environment: &EnvironmentSetup,
+        checkpoint: Option<CheckpointEntry>,
+        path: &str,
+    ) -> Result<(), String> {
+        let entries = |cells: &mut dyn Iterator<Item = Position>| -> Vec<Vec<usize>> {
+            let mut cells: Vec<Position> = cells.collect();
+            cells.sort();
+            cells.into_iter().map(cell_entry).collect()
+        };
+        let pair = |&(a, b): &(Position, Position)| [cell_entry(a), cell_entry(b)];
+
+        let mut terrain: Vec<(Position, u32)> = environment
+            .terrain
+            .iter()
+            .map(|(&pos, &cost)| (pos, cost))
+            .collect();
+        terrain.sort();
+        let timeline_waves = environment
+            .obstacle_timeline
+            .iter()
+            .enumerate()
+            .filter(|(_, cells)| !cells.is_empty())
+            .map(|(cycle, cells)| ObstacleWave {
+                step: (cycle + 1) * environment.obstacle_cycle_interval,
+                duration: environment.obstacle_persistence_cycles,
+                cells: cells.clone(),
+            });
+        let waves: Vec<ObstacleWave> = timeline_waves
+            .chain(environment.obstacle_waves.iter().cloned())
+            .collect();
+
+        let file = ScenarioFile {
+            settings: toml::Table::try_from(config)
+                .map_err(|e| format!("Failed to write scenario settings: {}", e))?,
+            environment: Some(EnvironmentEntry {
+                width: environment.width,
+                height: environment.height,
+                floors: environment.floors,
+                start: cell_entry(environment.start),
+                goal: cell_entry(environment.goal),
+                walls: entries(&mut environment.walls.iter().copied()),
+                extra_goals: environment
+                    .extra_goals
+                    .iter()
+                    .copied()
+                    .map(cell_entry)
+                    .collect(),
+                terrain: terrain
+                    .into_iter()
+                    .map(|(pos, cost)| {
+                        let mut entry = cell_entry(pos);
+                        entry.push(cost as usize);
+                        entry
+                    })
+                    .collect(),
+                stairs: entries(&mut environment.stairs.iter().copied()),
+                portals: environment.portals.iter().map(pair).collect(),
+                goal_timeline: environment
+                    .goal_timeline
+                    .iter()
+                    .copied()
+                    .map(cell_entry)
+                    .collect(),
+                agent_routes: environment.agent_routes.iter().map(pair).collect(),
+                pursuer_start: environment.pursuer_start.map(cell_entry),
+                sensor_seed: Some(environment.sensor_seed.to_string()),
+                wall_events: environment
+                    .wall_events
+                    .iter()
+                    .map(|event| WallEventEntry {
+                        step: event.step,
+                        cell: cell_entry(event.pos),
+                        wall: event.wall,
+                    })
+                    .collect(),
+                moving_obstacles: environment
+                    .moving_obstacles
+                    .iter()
+                    .map(|mover| MoverEntry {
+                        trajectory: mover.trajectory.name().to_string(),
+                        route: mover.route.iter().copied().map(cell_entry).collect(),
+                    })
+                    .collect(),
+                obstacles: waves.iter().map(WaveEntry::new).collect(),
+            }),
+            checkpoint,
+        };
+        let text = toml::to_string_pretty(&file)
+            .map_err(|e| format!("Failed to write scenario '{}': {}", path, e))?;
+        std::fs::write(path, text)
+            .map_err(|e| format!("Failed to write scenario '{}': {}", path, e))
+    }
+}
+
+/// The cell of an `[x, y]` or `[x, y, floor]` entry
+fn position(cell: &[usize]) -> Option<Position> {
+    match *cell {
+        [x, y] => Some(Position { x, y, z: 0 }),
+        [x, y, z] => Some(Position { x, y, z }),
+        _ => None,
+    }
+}
+
+/// The entry of a cell, with its floor only off the ground floor
+fn cell_entry(pos: Position) -> Vec<usize> {
+    match pos.z {
+        0 => vec![pos.x, pos.y],
+        z => vec![pos.x, pos.y, z],
+    }
+}
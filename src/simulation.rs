@@ -1,26 +1,71 @@
 use crate::agent::Agent;
 use crate::algorithms::a_star::AStar;
-use crate::algorithms::common::PathfindingAlgorithm;
-use crate::algorithms::d_star_lite::DStarLite;
-
-use crate::algorithms::hybrid_a_star_d_star::HybridAStarDStar;
+use crate::algorithms::common::{
+    AbortHandle, EnvironmentChanges, ObstacleSchedule, PathfindingAlgorithm, SearchTrace,
+};
+use crate::algorithms::landmarks::{LandmarkHeuristic, LandmarkStrategy};
+use crate::algorithms::registry::{AlgorithmContext, AlgorithmRegistry};
+use crate::algorithms::space_time_a_star::SpaceTimeAStar;
+use crate::algorithms::switching_policy::SwitchingPolicyKind;
+use crate::cbs::{ConflictSearch, Task};
 use crate::config::Config;
-use crate::grid::{Cell, Grid, Position};
-use crate::statistics::{AlgorithmStats, Statistics};
+use crate::cooperative::{Coordination, ReservationTable};
+use crate::error::{ConfigError, PlanningError, SimulationError};
+use crate::grid::{Cell, Grid, Position, MUD_COST, WATER_COST};
+use crate::knowledge::{Knowledge, Memory};
+use crate::map_file::MapLayout;
+use crate::maze::MapStyle;
+#[cfg(feature = "metrics")]
+use crate::metrics_exporter;
+use crate::moving_obstacle::{MovingObstacle, Trajectory};
+use crate::multi_agent::{Companion, Planning, Turn, MAX_STUCK_ATTEMPTS};
+use crate::observer::{Replan, SimulationObserver};
+use crate::obstacle_model::{ObstacleGenerator, ObstacleModel};
+use crate::obstacle_script::{ObstacleScript, ObstacleWave};
+use crate::pursuer::Pursuer;
+use crate::run_log::RunLog;
+use crate::smoothing::smooth_path;
+use crate::statistics::{LegStatistics, PlannerMetrics, Statistics};
+use crate::vision::{SensorNoise, Vision};
+use crate::wall_pattern::WallPattern;
 use rand::{Rng, SeedableRng};
-use std::collections::HashSet;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt;
+use std::num::NonZeroUsize;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{mpsc, Arc};
 use std::thread;
-use std::time::{Duration, Instant};
+use std::time::Duration;
+use tracing::field::Empty;
+use tracing::{debug_span, trace_span, Span};
+use web_time::Instant;
 
 #[derive(Debug, Clone)]
 pub struct ObstacleGroup {
-    positions: HashSet<Position>,
-    cycles_remaining: usize,
+    pub positions: HashSet<Position>,
+    /// Obstacle cycles before the group clears
+    pub cycles_remaining: usize,
+}
+
+/// Where a moving obstacle is along its route
+#[derive(Debug, Clone, Default)]
+struct MoverState {
+    /// Steps of the route taken so far
+    index: usize,
+    /// Cell it occupies, once it has been placed
+    cell: Option<Position>,
+    /// Cell it occupied before its last step (the same cell after a pause)
+    previous: Option<Position>,
 }
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct EnvironmentSetup {
-    pub grid_size: usize,
+    pub width: usize,
+    pub height: usize,
+    pub floors: usize,
     pub start: Position,
     pub goal: Position,
     pub walls: HashSet<Position>,
@@ -28,473 +73,2110 @@ pub struct EnvironmentSetup {
     pub obstacle_timeline: Vec<HashSet<Position>>,
     pub obstacle_cycle_interval: usize,
     pub obstacle_persistence_cycles: usize,
+
+    /// Scripted obstacles from `--obstacle-script`, ordered by step (empty for the
+    /// random timeline)
+    pub obstacle_waves: Vec<ObstacleWave>,
+
+    /// Goal position for each cycle when the goal wanders (empty for a fixed goal)
+    pub goal_timeline: Vec<Position>,
+
+    /// Additional goals in multi-goal mode (empty when there is a single goal)
+    pub extra_goals: Vec<Position>,
+
+    /// Ordered waypoints to visit before the goal (empty outside mission mode)
+    pub waypoints: Vec<Position>,
+
+    /// 8-connected movement with octile costs
+    pub diagonal_movement: bool,
+
+    /// Hexagonal cells in axial coordinates
+    pub hex_grid: bool,
+
+    /// Toroidal grid whose edges wrap around
+    pub wrap_edges: bool,
+
+    /// Terrain cost of each weighted cell; cells not listed cost 1 to enter
+    #[cfg_attr(feature = "serde", serde(with = "crate::grid::cell_map"))]
+    pub terrain: HashMap<Position, u32>,
+
+    /// Stair cells, in pairs at the same spot on adjacent floors
+    pub stairs: HashSet<Position>,
+
+    /// Portal pairs; each end leads to the other in a single move
+    pub portals: Vec<(Position, Position)>,
+
+    /// Permanent wall changes during the run, ordered by step
+    pub wall_events: Vec<WallEvent>,
+
+    /// Obstacles that move along their route for the whole run
+    pub moving_obstacles: Vec<MovingObstacle>,
+
+    /// Seed of the agent's sensor noise, so each algorithm run on this environment
+    /// starts from the same draws
+    pub sensor_seed: u64,
+
+    /// Start and goal of every other agent in a multi-agent run (empty with a single
+    /// agent)
+    pub agent_routes: Vec<(Position, Position)>,
+
+    /// Where the pursuer starts (`None` without `--pursuer`)
+    pub pursuer_start: Option<Position>,
+}
+
+/// A wall appearing on (or disappearing from) a cell for the rest of the run
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct WallEvent {
+    /// Simulation step the change happens at; an event that cannot happen then
+    /// (e.g. the agent stands on the cell) waits for a later step
+    pub step: usize,
+    pub pos: Position,
+    /// Whether a wall appears (true) or disappears (false)
+    pub wall: bool,
 }
 
 impl EnvironmentSetup {
+    /// Environment for the given options: on the `--map-file` map when there is one,
+    /// otherwise with walls in the `--map-style` layout
+    pub fn from_config(config: &Config, seed: Option<u64>) -> Result<Self, SimulationError> {
+        let style = MapStyle::from_name(&config.map_style)?;
+        let pattern = match WallPattern::from_names(&config.wall_pattern)?[..] {
+            [pattern] => pattern,
+            _ => {
+                return Err("Only batch mode can sweep several wall patterns"
+                    .to_string()
+                    .into())
+            }
+        };
+        Trajectory::from_name(&config.obstacle_trajectory)?;
+        ObstacleModel::from_name(&config.obstacle_model)?;
+        if config
+            .arrival_rate
+            .is_some_and(|rate| rate < 0.0 || !rate.is_finite())
+        {
+            return Err("--arrival-rate must be a non-negative number"
+                .to_string()
+                .into());
+        }
+        if config
+            .energy_budget
+            .is_some_and(|budget| budget < 0.0 || budget.is_nan())
+        {
+            return Err("--energy-budget cannot be negative".to_string().into());
+        }
+        if config
+            .simulation_timeout_seconds
+            .is_some_and(|seconds| seconds <= 0.0 || !seconds.is_finite())
+        {
+            return Err("--simulation-timeout-seconds must be above 0"
+                .to_string()
+                .into());
+        }
+        if config
+            .find_path_budget_ms
+            .is_some_and(|ms| ms <= 0.0 || !ms.is_finite())
+        {
+            return Err("--find-path-budget-ms must be above 0".to_string().into());
+        }
+        if config.hotspot_spread < 0.0 || config.hotspot_drift < 0.0 {
+            return Err("--hotspot-spread and --hotspot-drift cannot be negative"
+                .to_string()
+                .into());
+        }
+        if config.agents == 0 {
+            return Err("--agents must be at least 1".to_string().into());
+        }
+        if config.goal_move_interval == 0 {
+            return Err("--goal-move-interval must be at least 1".to_string().into());
+        }
+        if config.moving_goal && !config.goal_route.is_empty() {
+            return Err(
+                "--goal-route replaces the random wander of --moving-goal; use one or the other"
+                    .to_string()
+                    .into(),
+            );
+        }
+        config.validate_grid()?;
+        let layout = MapLayout::from_config(config)?;
+        if layout.is_some() && style != MapStyle::Random {
+            return Err("--map-style cannot be combined with a loaded map"
+                .to_string()
+                .into());
+        }
+        Ok(Self::generate_on(
+            config,
+            seed,
+            layout.as_ref(),
+            style,
+            pattern,
+        ))
+    }
+
+    /// Generate an environment on the walls (and the start and goal, where it marks
+    /// them) of `layout`, with obstacles and the rest as the options say
+    pub fn generate_on_layout(config: &Config, seed: Option<u64>, layout: &MapLayout) -> Self {
+        Self::generate_on(
+            config,
+            seed,
+            Some(layout),
+            MapStyle::Random,
+            WallPattern::Random,
+        )
+    }
+
+    /// Generate an environment with walls in the `--map-style` layout and
+    /// `--wall-pattern` (random ones for unknown names; `from_config` reports those
+    /// as errors)
     pub fn generate(config: &Config, seed: Option<u64>) -> Self {
+        let style = MapStyle::from_name(&config.map_style).unwrap_or(MapStyle::Random);
+        let pattern = WallPattern::from_name(&config.wall_pattern).unwrap_or(WallPattern::Random);
+        Self::generate_on(config, seed, None, style, pattern)
+    }
+
+    /// Generate an environment, taking the walls (and the start and goal, when it has
+    /// scenarios) from `layout` if given, otherwise laying walls out in `style`, with
+    /// `--num-walls` walls placed in `pattern` for the random style
+    fn generate_on(
+        config: &Config,
+        seed: Option<u64>,
+        layout: Option<&MapLayout>,
+        style: MapStyle,
+        pattern: WallPattern,
+    ) -> Self {
         let mut rng = if let Some(seed) = seed {
             rand::rngs::StdRng::seed_from_u64(seed)
         } else {
             rand::rngs::StdRng::from_entropy()
         };
 
-        let start = Position {
-            x: rng.gen_range(0..config.grid_size / 2),
-            y: rng.gen_range(0..config.grid_size / 2),
+        let (width, height, floors, start, goal, mut walls) = match layout {
+            Some(layout) => {
+                let (start, goal) = Self::map_start_and_goal(&mut rng, config, layout);
+                (
+                    layout.width,
+                    layout.height,
+                    1,
+                    start,
+                    goal,
+                    layout.walls.clone(),
+                )
+            }
+            None => {
+                let (width, height, floors) = (config.width(), config.height(), config.floors());
+                let start = Position {
+                    x: rng.gen_range(0..width / 2),
+                    y: rng.gen_range(0..height / 2),
+                    z: 0,
+                };
+                let goal = Position {
+                    x: rng.gen_range(width / 2..width),
+                    y: rng.gen_range(height / 2..height),
+                    z: floors - 1,
+                };
+                let mazes: Option<HashSet<Position>> = (0..floors)
+                    .map(|z| style.generate(&mut rng, width, height, z))
+                    .try_fold(HashSet::new(), |mut walls, floor| {
+                        walls.extend(floor?);
+                        Some(walls)
+                    });
+                match mazes {
+                    // Structured layouts leave the start and goal wherever they land, so
+                    // move them to the closest open cell
+                    Some(walls) => {
+                        let start = Self::nearest_free(start, width, height, &walls);
+                        let goal = Self::nearest_free(goal, width, height, &walls);
+                        (width, height, floors, start, goal, walls)
+                    }
+                    None => {
+                        let walls = pattern.place(
+                            &mut rng,
+                            width,
+                            height,
+                            floors,
+                            config.num_walls,
+                            &[start, goal],
+                        );
+                        (width, height, floors, start, goal, walls)
+                    }
+                }
+            }
         };
-        let goal = Position {
-            x: rng.gen_range(config.grid_size / 2..config.grid_size),
-            y: rng.gen_range(config.grid_size / 2..config.grid_size),
+
+        let obstacle_cycle_interval = config.obstacle_interval.max(1);
+        let obstacle_persistence_cycles = config.obstacle_persistence.max(1);
+
+        let max_cycles = width * height * floors;
+        let num_obstacle_cycles = max_cycles / obstacle_cycle_interval;
+
+        let generator = ObstacleGenerator {
+            model: ObstacleModel::from_name(&config.obstacle_model)
+                .unwrap_or(ObstacleModel::Uniform),
+            group_size: config.num_obstacles,
+            arrival_rate: config.arrival_rate,
+            interval: obstacle_cycle_interval,
+            hotspots: config.hotspots,
+            spread: config.hotspot_spread,
+            drift: config.hotspot_drift,
         };
+        let obstacle_timeline = generator.timeline(
+            &mut rng,
+            (width, height, floors),
+            num_obstacle_cycles,
+            &[start, goal],
+            &walls,
+        );
 
-        let mut walls = HashSet::new();
-        let mut walls_placed = 0;
-        let mut attempts = 0;
-        while walls_placed < config.num_walls && attempts < config.num_walls * 3 {
-            let x = rng.gen_range(0..config.grid_size);
-            let y = rng.gen_range(0..config.grid_size);
-            let pos = Position { x, y };
+        // Generated after the obstacles so a fixed-goal run with the same seed is unchanged
+        let goal_timeline = if config.moving_goal {
+            Self::generate_goal_wander(
+                &mut rng,
+                (width, height),
+                goal,
+                &walls,
+                max_cycles,
+                config.goal_move_interval.max(1),
+            )
+        } else {
+            Vec::new()
+        };
 
-            if pos != start && pos != goal && !walls.contains(&pos) {
-                walls.insert(pos);
-                walls_placed += 1;
-            }
-            attempts += 1;
+        // Also generated last, for the same reason
+        let extra_goals = Self::generate_extra_goals(
+            &mut rng,
+            width,
+            height,
+            floors,
+            config.num_goals.saturating_sub(1),
+            &[start, goal],
+            &walls,
+        );
+
+        // Also generated last, for the same reason
+        let mut terrain = Self::generate_terrain(
+            &mut rng,
+            width,
+            height,
+            floors,
+            config.terrain_density,
+            &walls,
+        );
+
+        // Also generated last, for the same reason; stairs clear any wall or terrain,
+        // except in mazes and dungeons, where a cleared wall would rarely open onto a
+        // corridor, so stairs go on open cells instead
+        let mut reserved = vec![start, goal];
+        reserved.extend_from_slice(&extra_goals);
+        let no_walls = HashSet::new();
+        let stairs = Self::generate_stairs(
+            &mut rng,
+            width,
+            height,
+            floors,
+            config.stairs_per_floor,
+            &reserved,
+            if style == MapStyle::Random || layout.is_some() {
+                &no_walls
+            } else {
+                &walls
+            },
+        );
+        for pos in &stairs {
+            walls.remove(pos);
+            terrain.remove(pos);
         }
 
-        let obstacle_cycle_interval = 5;
-        let obstacle_persistence_cycles = 5;
+        // Also generated last, for the same reason; portals clear any wall or terrain
+        let portals = Self::generate_portals(
+            &mut rng,
+            width,
+            height,
+            floors,
+            config.portals,
+            &reserved,
+            &stairs,
+        );
+        for pos in portals.iter().flat_map(|&(a, b)| [a, b]) {
+            walls.remove(&pos);
+            terrain.remove(&pos);
+        }
 
-        let max_cycles = config.grid_size * config.grid_size;
-        let num_obstacle_cycles = max_cycles / obstacle_cycle_interval;
+        // Also generated last, for the same reason; stairs, portals and terrain keep their cells
+        let taken: HashSet<Position> = reserved
+            .iter()
+            .chain(&stairs)
+            .chain(portals.iter().flat_map(|(a, b)| [a, b]))
+            .chain(terrain.keys())
+            .copied()
+            .collect();
+        let wall_events = Self::generate_wall_events(
+            &mut rng,
+            (width, height, floors),
+            config.wall_events,
+            &walls,
+            &taken,
+        );
 
-        let mut obstacle_timeline = Vec::new();
+        let mut environment = EnvironmentSetup {
+            width,
+            height,
+            floors,
+            start,
+            goal,
+            walls,
+            obstacle_timeline,
+            obstacle_cycle_interval,
+            obstacle_persistence_cycles,
+            obstacle_waves: Vec::new(),
+            goal_timeline,
+            extra_goals,
+            waypoints: Vec::new(),
+            diagonal_movement: config.diagonal_movement,
+            hex_grid: config.hex_grid,
+            wrap_edges: config.wrap_edges,
+            terrain,
+            stairs,
+            portals,
+            wall_events,
+            moving_obstacles: Vec::new(),
+            sensor_seed: 0,
+            agent_routes: Vec::new(),
+            pursuer_start: None,
+        };
 
-        for _cycle in 0..num_obstacle_cycles {
-            let mut obstacle_group = HashSet::new();
-            let mut attempts = 0;
-            let max_attempts = config.num_obstacles * 10;
+        // Also generated last, for the same reason; routes avoid everything placed so far
+        environment.moving_obstacles = MovingObstacle::generate(
+            &mut rng,
+            &environment.create_grid(),
+            config.moving_obstacles,
+            Trajectory::from_name(&config.obstacle_trajectory).unwrap_or(None),
+            max_cycles,
+        );
+
+        // Also drawn last, for the same reason
+        environment.sensor_seed = rng.gen();
+
+        // Also generated last, for the same reason
+        environment.agent_routes = Self::generate_agent_routes(
+            &mut rng,
+            &environment.create_grid(),
+            config.agents.saturating_sub(1),
+        );
+
+        // Also drawn last, for the same reason
+        if config.pursuer {
+            environment.pursuer_start =
+                Self::generate_pursuer_start(&mut rng, &environment.create_grid(), &environment);
+        }
+        environment
+    }
+
+    /// The open cell on the same floor closest to `pos` (`pos` itself if it is open)
+    fn nearest_free(
+        pos: Position,
+        width: usize,
+        height: usize,
+        walls: &HashSet<Position>,
+    ) -> Position {
+        (0..width)
+            .flat_map(|x| (0..height).map(move |y| Position { x, y, ..pos }))
+            .filter(|cell| !walls.contains(cell))
+            .min_by_key(|cell| cell.x.abs_diff(pos.x) + cell.y.abs_diff(pos.y))
+            .unwrap_or(pos)
+    }
+
+    /// Start and goal on a loaded map: the `--scen-index` query, a random query of the
+    /// scenario file, or the cells marked on the map, with random free cells for any
+    /// that are not marked
+    fn map_start_and_goal(
+        rng: &mut rand::rngs::StdRng,
+        config: &Config,
+        layout: &MapLayout,
+    ) -> (Position, Position) {
+        if !layout.scenarios.is_empty() {
+            let index = config
+                .scen_index
+                .unwrap_or_else(|| rng.gen_range(0..layout.scenarios.len()));
+            let scenario = layout.scenarios[index];
+            return (scenario.start, scenario.goal);
+        }
+
+        let mut random_free_cell = |other: Option<Position>| loop {
+            let pos = Position {
+                x: rng.gen_range(0..layout.width),
+                y: rng.gen_range(0..layout.height),
+                z: 0,
+            };
+            if layout.is_free(pos) && Some(pos) != other {
+                return pos;
+            }
+        };
+        let start = layout
+            .start
+            .unwrap_or_else(|| random_free_cell(layout.goal));
+        let goal = layout.goal.unwrap_or_else(|| random_free_cell(Some(start)));
+        (start, goal)
+    }
 
-            while obstacle_group.len() < config.num_obstacles && attempts < max_attempts {
-                let x = rng.gen_range(0..config.grid_size);
-                let y = rng.gen_range(0..config.grid_size);
-                let pos = Position { x, y };
+    /// Random floor for a generated cell; single-floor grids draw nothing, so seeded
+    /// environments are the same as before floors existed
+    fn random_floor(rng: &mut rand::rngs::StdRng, floors: usize) -> usize {
+        if floors > 1 {
+            rng.gen_range(0..floors)
+        } else {
+            0
+        }
+    }
 
-                if pos != start
-                    && pos != goal
-                    && !walls.contains(&pos)
-                    && !obstacle_group.contains(&pos)
+    /// `per_floor` stairwells between each pair of adjacent floors: a stair cell on
+    /// both floors at the same spot, away from the start, the goals and `blocked`
+    fn generate_stairs(
+        rng: &mut rand::rngs::StdRng,
+        width: usize,
+        height: usize,
+        floors: usize,
+        per_floor: usize,
+        reserved: &[Position],
+        blocked: &HashSet<Position>,
+    ) -> HashSet<Position> {
+        let mut stairs = HashSet::new();
+        for z in 0..floors.saturating_sub(1) {
+            let mut placed = 0;
+            let mut attempts = 0;
+            while placed < per_floor && attempts < per_floor * 10 {
+                let (x, y) = (rng.gen_range(0..width), rng.gen_range(0..height));
+                let (lower, upper) = (Position { x, y, z }, Position { x, y, z: z + 1 });
+                if !reserved.contains(&lower)
+                    && !reserved.contains(&upper)
+                    && !blocked.contains(&lower)
+                    && !blocked.contains(&upper)
+                    && !stairs.contains(&lower)
                 {
-                    obstacle_group.insert(pos);
+                    stairs.insert(lower);
+                    stairs.insert(upper);
+                    placed += 1;
                 }
                 attempts += 1;
             }
+        }
+        stairs
+    }
 
-            obstacle_timeline.push(obstacle_group);
+    /// `count` portal pairs joining random cells anywhere on the grid, away from the
+    /// start, the goals and the stairs
+    fn generate_portals(
+        rng: &mut rand::rngs::StdRng,
+        width: usize,
+        height: usize,
+        floors: usize,
+        count: usize,
+        reserved: &[Position],
+        stairs: &HashSet<Position>,
+    ) -> Vec<(Position, Position)> {
+        let mut portals: Vec<(Position, Position)> = Vec::with_capacity(count);
+        let mut attempts = 0;
+        while portals.len() < count && attempts < count * 10 {
+            let mut random_cell = || Position {
+                x: rng.gen_range(0..width),
+                y: rng.gen_range(0..height),
+                z: Self::random_floor(rng, floors),
+            };
+            let (a, b) = (random_cell(), random_cell());
+            let taken = |pos: Position| {
+                reserved.contains(&pos)
+                    || stairs.contains(&pos)
+                    || portals.iter().any(|&(c, d)| pos == c || pos == d)
+            };
+            if a != b && !taken(a) && !taken(b) {
+                portals.push((a, b));
+            }
+            attempts += 1;
         }
+        portals
+    }
 
-        EnvironmentSetup {
-            grid_size: config.grid_size,
-            start,
-            goal,
-            walls,
-            obstacle_timeline,
-            obstacle_cycle_interval,
-            obstacle_persistence_cycles,
+    /// Wall changes at random steps within the first `width + height` steps, each
+    /// adding a wall on a random open cell or removing a random existing wall
+    fn generate_wall_events(
+        rng: &mut rand::rngs::StdRng,
+        (width, height, floors): (usize, usize, usize),
+        count: usize,
+        walls: &HashSet<Position>,
+        taken: &HashSet<Position>,
+    ) -> Vec<WallEvent> {
+        // Sorted so the same seed picks the same walls
+        let mut existing: Vec<Position> = walls.iter().copied().collect();
+        existing.sort_by_key(|pos| (pos.z, pos.y, pos.x));
+
+        let mut events = Vec::with_capacity(count);
+        let mut attempts = 0;
+        while events.len() < count && attempts < count * 10 {
+            attempts += 1;
+            let step = rng.gen_range(1..=width + height);
+            if !existing.is_empty() && rng.gen_bool(0.5) {
+                let pos = existing[rng.gen_range(0..existing.len())];
+                events.push(WallEvent {
+                    step,
+                    pos,
+                    wall: false,
+                });
+                continue;
+            }
+            let pos = Position {
+                x: rng.gen_range(0..width),
+                y: rng.gen_range(0..height),
+                z: Self::random_floor(rng, floors),
+            };
+            if !walls.contains(&pos) && !taken.contains(&pos) {
+                events.push(WallEvent {
+                    step,
+                    pos,
+                    wall: true,
+                });
+            }
+        }
+        events.sort_by_key(|event| event.step);
+        events
+    }
+
+    /// Check that a user-given cell lies on the grid
+    pub(crate) fn check_position(&self, what: &str, pos: Position) -> Result<(), String> {
+        if pos.x >= self.width || pos.y >= self.height {
+            return Err(format!(
+                "{} ({}, {}) is outside the {}x{} grid",
+                what, pos.x, pos.y, self.width, self.height
+            ));
+        }
+        if pos.z >= self.floors {
+            return Err(format!(
+                "{} ({}, {}) is on floor {}, but the grid has {} floor(s)",
+                what, pos.x, pos.y, pos.z, self.floors
+            ));
+        }
+        Ok(())
+    }
+
+    /// Set the mission waypoints, clearing any walls generated on top of them
+    pub fn set_waypoints(&mut self, waypoints: Vec<Position>) -> Result<(), ConfigError> {
+        for waypoint in &waypoints {
+            self.check_position("Waypoint", *waypoint)?;
+            self.walls.remove(waypoint);
+        }
+        self.waypoints = waypoints;
+        Ok(())
+    }
+
+    /// Replace the portals with the given pairs, clearing any walls generated on top
+    /// of them
+    pub fn set_portals(&mut self, portals: Vec<(Position, Position)>) -> Result<(), ConfigError> {
+        let mut ends = HashSet::new();
+        for &(a, b) in &portals {
+            if a == b {
+                return Err(ConfigError::Invalid(format!(
+                    "Portal ({}, {}) is paired with itself",
+                    a.x, a.y
+                )));
+            }
+            for pos in [a, b] {
+                self.check_position("Portal", pos)?;
+                if !ends.insert(pos) {
+                    return Err(ConfigError::Invalid(format!(
+                        "Portal ({}, {}) belongs to more than one pair",
+                        pos.x, pos.y
+                    )));
+                }
+                self.walls.remove(&pos);
+            }
+        }
+        self.portals = portals;
+        Ok(())
+    }
+
+    /// Replace the goal's random wander with a scripted route: it walks the shortest
+    /// way around the walls to each cell of `route` in turn, a cell every `interval`
+    /// cycles, and stays on the last one. Nothing changes for an empty route.
+    pub fn set_goal_route(
+        &mut self,
+        route: Vec<Position>,
+        interval: usize,
+    ) -> Result<(), ConfigError> {
+        for &pos in &route {
+            self.check_position("Goal route cell", pos)?;
+            self.walls.remove(&pos);
+        }
+        if route.is_empty() {
+            return Ok(());
+        }
+        let grid = self.create_grid();
+        let mut timeline = Vec::new();
+        let mut current = self.goal;
+        for target in route {
+            let leg = AStar::new()
+                .find_path(&grid, current, target, &HashSet::new())
+                .ok_or_else(|| {
+                    format!(
+                        "The goal cannot get to route cell ({}, {}) around the walls",
+                        target.x, target.y
+                    )
+                })?;
+            for &pos in &leg[1..] {
+                timeline.extend(std::iter::repeat_n(current, interval.max(1) - 1));
+                timeline.push(pos);
+                current = pos;
+            }
         }
+        self.goal_timeline = timeline;
+        Ok(())
+    }
+
+    /// Replace the random obstacle timeline with a scripted one
+    pub fn set_obstacle_script(&mut self, script: ObstacleScript) -> Result<(), ConfigError> {
+        for wave in &script.waves {
+            for &pos in &wave.cells {
+                self.check_position("Scripted obstacle", pos)?;
+            }
+        }
+        self.obstacle_timeline.clear();
+        self.obstacle_waves = script.waves;
+        Ok(())
+    }
+
+    /// Start and goal for each of `count` more agents: free cells no other agent starts
+    /// or ends on, each goal reachable from its start around the walls
+    fn generate_agent_routes(
+        rng: &mut rand::rngs::StdRng,
+        grid: &Grid,
+        count: usize,
+    ) -> Vec<(Position, Position)> {
+        let mut taken = grid.goals();
+        taken.push(grid.start);
+        let mut routes = Vec::with_capacity(count);
+        let mut attempts = 0;
+        while routes.len() < count && attempts < count * 20 {
+            attempts += 1;
+            let [start, goal] = [(); 2].map(|_| Position {
+                x: rng.gen_range(0..grid.width),
+                y: rng.gen_range(0..grid.height),
+                z: Self::random_floor(rng, grid.floors),
+            });
+            let free = |pos: Position| grid.cell(pos) == Cell::Empty && !taken.contains(&pos);
+            if start == goal || !free(start) || !free(goal) {
+                continue;
+            }
+            if AStar::new()
+                .find_path(grid, start, goal, &HashSet::new())
+                .is_some()
+            {
+                taken.extend([start, goal]);
+                routes.push((start, goal));
+            }
+        }
+        routes
+    }
+
+    /// A free cell well away from the agent's start (a quarter of the way around the
+    /// grid's edge, at least) from which the pursuer can get to it around the walls
+    fn generate_pursuer_start(
+        rng: &mut rand::rngs::StdRng,
+        grid: &Grid,
+        environment: &EnvironmentSetup,
+    ) -> Option<Position> {
+        let start = grid.start;
+        let min_distance = (grid.width + grid.height) / 4;
+        (0..100)
+            .map(|_| Position {
+                x: rng.gen_range(0..grid.width),
+                y: rng.gen_range(0..grid.height),
+                z: Self::random_floor(rng, grid.floors),
+            })
+            .find(|&pos| {
+                grid.cell(pos) == Cell::Empty
+                    && !grid.is_goal(pos)
+                    && pos.x.abs_diff(start.x) + pos.y.abs_diff(start.y) >= min_distance
+                    && !environment
+                        .agent_routes
+                        .iter()
+                        .any(|&(from, to)| pos == from || pos == to)
+                    && AStar::new()
+                        .find_path(grid, pos, start, &HashSet::new())
+                        .is_some()
+            })
+    }
+
+    /// Distinct free cells anywhere on the grid, away from the start and the main goal
+    fn generate_extra_goals(
+        rng: &mut rand::rngs::StdRng,
+        width: usize,
+        height: usize,
+        floors: usize,
+        count: usize,
+        reserved: &[Position],
+        walls: &HashSet<Position>,
+    ) -> Vec<Position> {
+        let mut extra_goals = Vec::with_capacity(count);
+        let mut attempts = 0;
+        while extra_goals.len() < count && attempts < count * 10 {
+            let pos = Position {
+                x: rng.gen_range(0..width),
+                y: rng.gen_range(0..height),
+                z: Self::random_floor(rng, floors),
+            };
+            if !reserved.contains(&pos) && !walls.contains(&pos) && !extra_goals.contains(&pos) {
+                extra_goals.push(pos);
+            }
+            attempts += 1;
+        }
+        extra_goals
+    }
+
+    /// Roughly round patches of mud or water until about `density` of the cells are
+    /// covered. Walls stay walls; patches may overlap, the later one winning.
+    fn generate_terrain(
+        rng: &mut rand::rngs::StdRng,
+        width: usize,
+        height: usize,
+        floors: usize,
+        density: f64,
+        walls: &HashSet<Position>,
+    ) -> HashMap<Position, u32> {
+        let mut terrain = HashMap::new();
+        let target = (density.clamp(0.0, 1.0) * (width * height * floors) as f64).round() as usize;
+        let mut attempts = 0;
+        while terrain.len() < target && attempts < target * 10 {
+            let center = Position {
+                x: rng.gen_range(0..width),
+                y: rng.gen_range(0..height),
+                z: Self::random_floor(rng, floors),
+            };
+            let radius = rng.gen_range(1..=3);
+            let cost = if rng.gen_bool(0.5) {
+                MUD_COST
+            } else {
+                WATER_COST
+            };
+
+            for x in center.x.saturating_sub(radius)..(center.x + radius + 1).min(width) {
+                for y in center.y.saturating_sub(radius)..(center.y + radius + 1).min(height) {
+                    let pos = Position { x, y, ..center };
+                    let (dx, dy) = (x.abs_diff(center.x), y.abs_diff(center.y));
+                    if dx * dx + dy * dy <= radius * radius && !walls.contains(&pos) {
+                        terrain.insert(pos, cost);
+                    }
+                }
+            }
+            attempts += 1;
+        }
+        terrain
+    }
+
+    /// Random walk of the goal, one step (or a pause) per cycle, avoiding walls
+    fn generate_goal_wander(
+        rng: &mut rand::rngs::StdRng,
+        (width, height): (usize, usize),
+        goal: Position,
+        walls: &HashSet<Position>,
+        num_cycles: usize,
+        interval: usize,
+    ) -> Vec<Position> {
+        let mut timeline = Vec::with_capacity(num_cycles);
+        let mut current = goal;
+
+        for cycle in 1..=num_cycles {
+            // Between moves the goal stays put
+            if cycle % interval != 0 {
+                timeline.push(current);
+                continue;
+            }
+            let (x, y) = (current.x as i32, current.y as i32);
+            let options: Vec<Position> = [(0, 0), (0, 1), (0, -1), (1, 0), (-1, 0)]
+                .iter()
+                .map(|(dx, dy)| (x + dx, y + dy))
+                .filter(|&(nx, ny)| nx >= 0 && ny >= 0 && nx < width as i32 && ny < height as i32)
+                .map(|(nx, ny)| Position {
+                    x: nx as usize,
+                    y: ny as usize,
+                    ..current
+                })
+                .filter(|pos| !walls.contains(pos))
+                .collect();
+
+            current = options[rng.gen_range(0..options.len())];
+            timeline.push(current);
+        }
+
+        timeline
     }
 
     pub fn create_grid(&self) -> Grid {
-        let mut cells = vec![vec![Cell::Empty; self.grid_size]; self.grid_size];
+        let mut grid = Grid::empty(self.width, self.height, self.floors);
+        grid.start = self.start;
+        grid.goal = self.goal;
+        grid.extra_goals = self.extra_goals.clone();
+        grid.waypoints = self.waypoints.clone();
+        grid.diagonal = self.diagonal_movement;
+        grid.hex = self.hex_grid;
+        grid.wrap = self.wrap_edges;
 
         for &wall_pos in &self.walls {
-            cells[wall_pos.x][wall_pos.y] = Cell::Wall;
+            grid.set_cell(wall_pos, Cell::Wall);
+        }
+        for (&pos, &cost) in &self.terrain {
+            grid.set_cell(pos, Cell::Terrain(cost));
+        }
+        for &pos in &self.stairs {
+            grid.set_cell(pos, Cell::Stairs);
+        }
+        for &(a, b) in &self.portals {
+            grid.portals.insert(a, b);
+            grid.portals.insert(b, a);
+        }
+        grid
+    }
+
+    /// Every obstacle the timeline (and the obstacle script) will place during a run,
+    /// at the steps `update_obstacles_from_timeline` places it. Cells no obstacle is
+    /// ever placed on are left out; cells an obstacle can only be kept off by where
+    /// the agents happen to be are not.
+    pub fn obstacle_schedule(&self) -> ObstacleSchedule {
+        let placeable = |pos: &&Position| {
+            **pos != self.start
+                && (**pos != self.goal || !self.goal_timeline.is_empty())
+                && !self.extra_goals.contains(pos)
+                && !self.waypoints.contains(pos)
+                && !self.agent_routes.iter().any(|&(_, goal)| goal == **pos)
+                && !self.terrain.contains_key(pos)
+                && !self.stairs.contains(pos)
+        };
+        let mut schedule = ObstacleSchedule::new();
+        // A timeline entry is placed every `obstacle_cycle_interval` steps, the first
+        // one after as many steps (every step for an interval of 0)
+        let interval = self.obstacle_cycle_interval.max(1);
+        for (cycle, cells) in self.obstacle_timeline.iter().enumerate() {
+            schedule.add(
+                cells.iter().filter(placeable).copied(),
+                (cycle + 1) * interval,
+                self.obstacle_persistence_cycles,
+            );
+        }
+        for wave in &self.obstacle_waves {
+            schedule.add(
+                wave.cells.iter().filter(placeable).copied(),
+                wave.step,
+                wave.duration,
+            );
+        }
+        schedule
+    }
+}
+
+/// An algorithm's name and its simulation in a comparison, or why the simulation
+/// could not be set up
+pub type ComparisonRun = (String, Result<Simulation, SimulationError>);
+
+/// What a run's moves are measured against: the optimal path on the grid before
+/// any obstacle appears, and the best run the obstacle timeline allows
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Baseline {
+    pub optimal_path_length: usize,
+    /// Cost of the optimal path, in straight moves
+    pub optimal_path_cost: f64,
+    /// Cost of the best run with every obstacle of the timeline known ahead, in
+    /// straight moves with a wait costing one; 0 without `--dynamic-baseline` or
+    /// when the search for it gave up
+    pub dynamic_optimal_cost: f64,
+}
+
+/// What every run of a comparison shares: the environment, its grid and obstacle
+/// schedule, and the baseline the runs are measured against
+struct Comparison {
+    environment: EnvironmentSetup,
+    grid: Grid,
+    schedule: Arc<ObstacleSchedule>,
+    baseline: Baseline,
+}
+
+impl Comparison {
+    /// The environment with the options' waypoints, goal route, portals and
+    /// obstacle script, failing when its goal cannot be reached. The dynamic
+    /// baseline's search gives up when `abort` is aborted.
+    fn prepare(
+        config: &Config,
+        mut environment: EnvironmentSetup,
+        abort: &AbortHandle,
+    ) -> Result<Self, SimulationError> {
+        let waypoints = Simulation::waypoints(config)?;
+        if !waypoints.is_empty() {
+            environment.set_waypoints(waypoints)?;
+        }
+        environment.set_goal_route(Simulation::goal_route(config)?, config.goal_move_interval)?;
+        if let Some(portals) = Simulation::portal_pairs(config)? {
+            environment.set_portals(portals)?;
+        }
+        if let Some(path) = &config.obstacle_script {
+            environment.set_obstacle_script(ObstacleScript::load(path)?)?;
+        }
+
+        // Create grid for all algorithms to use
+        let grid = environment.create_grid();
+
+        // Calculate optimal path using A* (no obstacles, only walls)
+        let (optimal_path_length, optimal_path_cost) =
+            Simulation::calculate_optimal_path_with_astar(&grid);
+
+        if optimal_path_length == 0 {
+            return Err(Simulation::no_path(&grid).into());
         }
+        // And, once for every run, the best run with the timeline known ahead
+        let schedule = Arc::new(environment.obstacle_schedule());
+        let baseline = Baseline {
+            optimal_path_length,
+            optimal_path_cost,
+            dynamic_optimal_cost: Simulation::dynamic_optimal_cost(config, &grid, &schedule, abort),
+        };
+        Ok(Comparison {
+            environment,
+            grid,
+            schedule,
+            baseline,
+        })
+    }
 
-        Grid {
-            size: self.grid_size,
-            cells,
-            start: self.start,
-            goal: self.goal,
+    fn start(&self) -> Position {
+        self.environment.start
+    }
+
+    fn context<'a>(&self, config: &'a Config) -> AlgorithmContext<'a> {
+        AlgorithmContext {
+            config,
+            start: self.environment.start,
+            goal: self.environment.goal,
+            width: self.grid.width,
+            height: self.grid.height,
         }
     }
+
+    /// A simulation of `algorithm` on a copy of the environment, drawing nothing
+    fn simulation(
+        &self,
+        config: &Config,
+        name: &str,
+        algorithm: Box<dyn PathfindingAlgorithm>,
+        registry: &Arc<AlgorithmRegistry>,
+    ) -> Result<Simulation, SimulationError> {
+        // Create a new config for this algorithm run (no visualization)
+        let mut algorithm_config = config.clone();
+        algorithm_config.no_visualization = true;
+        algorithm_config.algorithm = name.to_string();
+
+        // Create simulation with the shared environment
+        Simulation::new_with_environment_and_algorithm(
+            algorithm_config,
+            self.environment.clone(),
+            algorithm,
+            Arc::clone(registry),
+            self.baseline,
+            Arc::clone(&self.schedule),
+            &self.grid,
+        )
+    }
+
+    /// A simulation of every algorithm in the registry, as `comparison_simulations`
+    fn simulations(
+        &self,
+        config: &Config,
+        registry: &Arc<AlgorithmRegistry>,
+    ) -> Result<Vec<ComparisonRun>, SimulationError> {
+        // Build every algorithm up front so invalid options fail the whole run
+        let context = self.context(config);
+        let algorithms = registry
+            .names()
+            .map(|name| Ok((name.to_string(), registry.create(name, &context)?)))
+            .collect::<Result<Vec<_>, ConfigError>>()?;
+
+        Ok(algorithms
+            .into_iter()
+            .map(|(name, algorithm)| {
+                let simulation = self.simulation(config, &name, algorithm, registry);
+                (name, simulation)
+            })
+            .collect())
+    }
+
+    /// Build the algorithm registered as `name` and run it to the end, aborted
+    /// along with `abort`, or say why its run could not be set up
+    fn run(
+        &self,
+        config: &Config,
+        registry: &Arc<AlgorithmRegistry>,
+        name: &str,
+        abort: &AbortHandle,
+    ) -> Result<AlgorithmResult, String> {
+        let algorithm = registry
+            .create(name, &self.context(config))
+            .map_err(|e| e.to_string())?;
+        let mut simulation = self
+            .simulation(config, name, algorithm, registry)
+            .map_err(|e| e.to_string())?;
+        simulation.set_abort_handle(abort.child());
+        let results = simulation.run();
+        Ok(AlgorithmResult::finished(
+            name.to_string(),
+            &simulation,
+            results,
+        ))
+    }
 }
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct AlgorithmResult {
     pub name: String,
     pub statistics: Statistics,
     pub success: bool,
     pub final_position: Position,
-    pub algorithm_stats: AlgorithmStats,
+    pub planner_metrics: PlannerMetrics,
     pub timing_data: TimingData,
+    /// Time the algorithm's own run took (see `Simulation::run_time`)
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub run_time: Duration,
 }
 
-pub struct AlgorithmRunner {
-    pub name: String,
-    pub create_algorithm: Box<dyn Fn(Position, Position, usize) -> Box<dyn PathfindingAlgorithm>>, // Add usize for grid_size
-}
+impl AlgorithmResult {
+    /// The result of an algorithm's simulation, from what `Simulation::finish` (or
+    /// `run`) gave once the run was over
+    pub fn finished(
+        name: String,
+        simulation: &Simulation,
+        (statistics, planner_metrics, timing_data): (Statistics, PlannerMetrics, TimingData),
+    ) -> Self {
+        AlgorithmResult {
+            name,
+            statistics,
+            planner_metrics,
+            timing_data,
+            success: simulation.is_mission_complete(),
+            final_position: simulation.agent.position,
+            run_time: simulation.run_time(),
+        }
+    }
 
-impl AlgorithmRunner {
-    pub fn new<F>(name: &str, create_fn: F) -> Self
-    where
-        F: Fn(Position, Position, usize) -> Box<dyn PathfindingAlgorithm> + 'static, // Add usize for grid_size
-    {
-        AlgorithmRunner {
-            name: name.to_string(),
-            create_algorithm: Box::new(create_fn),
+    /// The result of an algorithm whose simulation could not be set up, warning
+    /// about it unless quiet
+    pub fn not_started(name: String, config: &Config, start: Position, error: &str) -> Self {
+        if !config.quiet {
+            tracing::warn!(algorithm = %name, error, "simulation_failed");
+        }
+        AlgorithmResult {
+            planner_metrics: PlannerMetrics::new(name.clone(), 0),
+            name,
+            statistics: Statistics::new(config.num_walls, config.num_obstacles, 0),
+            timing_data: TimingData::new(),
+            success: false,
+            final_position: start,
+            run_time: Duration::ZERO,
         }
     }
 }
 
+/// Why a run ended without the agent on its goal
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Failure {
+    /// There was no path to the goal from the start
+    NoPath,
+    /// No plan found a path for more than `MAX_STUCK_ATTEMPTS` steps in a row
+    Stuck,
+    /// The next move or wait would have cost more than was left of `--energy-budget`
+    OutOfEnergy,
+    /// The pursuer caught the agent
+    Caught,
+    /// The run took as many steps as a run may
+    StepLimit,
+    /// The run took longer than `--simulation-timeout-seconds`, or its abort handle
+    /// was aborted
+    TimedOut,
+}
+
+/// What a step of `Simulation::step` did with this run's own agent
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StepOutcome {
+    /// The agent moved to the next cell of its path, after planning a new one if
+    /// `replanned`
+    Moved {
+        from: Position,
+        to: Position,
+        replanned: bool,
+    },
+    /// The agent stayed where it was: no path for now, something in its way, the end
+    /// of a partial path, or on its goal waiting for the other agents
+    Waited { replanned: bool },
+    /// The run is over with the agent on its goal
+    Finished,
+    /// The run is over without it
+    Failed(Failure),
+}
+
+impl StepOutcome {
+    /// Whether the run is over
+    pub fn is_over(self) -> bool {
+        matches!(self, StepOutcome::Finished | StepOutcome::Failed(_))
+    }
+}
+
 pub struct Simulation {
     pub grid: Grid,
     pub agent: Agent,
     algorithm: Box<dyn PathfindingAlgorithm>,
+    registry: Arc<AlgorithmRegistry>,
     config: Config,
     optimal_path_length: usize,
     environment: EnvironmentSetup,
+    /// Every obstacle the environment's timeline will place, for planners that plan
+    /// against the future
+    obstacle_schedule: Arc<ObstacleSchedule>,
     active_obstacle_groups: Vec<ObstacleGroup>,
+    /// Cells of timeline obstacles that have expired and not been covered again
+    expired_obstacles: HashSet<Position>,
     cycles_since_last_obstacle: usize,
     current_obstacle_cycle: usize,
+    current_goal_cycle: usize,
+    current_leg: usize,
+    /// Which of the environment's wall events have happened
+    wall_events_applied: Vec<bool>,
+    movers: Vec<MoverState>,
+    /// The other agents of a multi-agent run, taking their turns after this one's
+    companions: Vec<Companion>,
+    /// Cell the agent stood on before its last move, for the other agents' sightings
+    agent_previous: Position,
+    /// Shared reservations when the agents plan cooperatively
+    reservations: Option<ReservationTable>,
+    /// Conflict-Based Search when the agents follow one joint plan
+    conflict_search: Option<ConflictSearch>,
+    /// The adversary chasing the agent (`--pursuer`)
+    pursuer: Option<Pursuer>,
+    stats: Statistics,
+    timing_data: TimingData,
+    /// Calls made to the planner so far, the initial plan included
+    planner_calls: usize,
+    /// Steps taken so far
+    steps: usize,
+    /// Plans in a row that found no path
+    stuck_attempts: usize,
+    /// Whether the first step has set the run up
+    started: bool,
+    /// How the run ended, once it has
+    outcome: Option<StepOutcome>,
+    /// Told about the run as it goes on, in order
+    observers: Vec<Box<dyn SimulationObserver>>,
+    /// Aborts the planner's searches, ending the run as timed out
+    abort: AbortHandle,
+    /// The planner's own handle, under `abort`, renewed for `--find-path-budget-ms`
+    /// before every replan
+    planning: AbortHandle,
+    /// The `run` span the run's steps and log events are in
+    span: Span,
+    /// Time spent in `step` so far, observers included
+    run_time: Duration,
 }
 
 impl Simulation {
-    pub fn new(config: Config) -> Result<Self, String> {
+    pub fn new(config: Config) -> Result<Self, SimulationError> {
         Self::new_with_environment(config, None)
     }
 
     pub fn new_with_environment(
         config: Config,
         environment: Option<EnvironmentSetup>,
-    ) -> Result<Self, String> {
-        let environment = environment.unwrap_or_else(|| EnvironmentSetup::generate(&config, None));
-        let grid = environment.create_grid();
-        let agent = Agent::new(grid.start);
+    ) -> Result<Self, SimulationError> {
+        Self::new_with_registry(
+            config,
+            environment,
+            Arc::new(AlgorithmRegistry::with_builtins()),
+        )
+    }
 
-        let mut algorithm: Box<dyn PathfindingAlgorithm> = match config.algorithm.as_str() {
-            "a_star" => Box::new(AStar::new()),
-            "d_star_lite" => Box::new(DStarLite::new(grid.start, grid.goal, grid.size)),
-            "hybrid" => Box::new(HybridAStarDStar::new(grid.start, grid.goal, grid.size)),
-            _ => return Err(format!("Unknown algorithm: '{}'", config.algorithm)),
-        };
+    /// Like `new_with_environment`, but looks `config.algorithm` up in the given registry
+    pub fn new_with_registry(
+        config: Config,
+        environment: Option<EnvironmentSetup>,
+        registry: Arc<AlgorithmRegistry>,
+    ) -> Result<Self, SimulationError> {
+        Self::new_with_planner(config, environment, registry, None)
+    }
 
-        // Add this: Initialize grid size for algorithms that need it
-        if config.algorithm.as_str() == "d_star_lite" {
-            if let Some(d_star) = algorithm.as_any_mut().downcast_mut::<DStarLite>() {
-                d_star.ensure_grid_size(grid.size);
-            }
+    /// Like `new_with_registry`, planning with `planner` when given instead of a new
+    /// planner of the `config.algorithm` kind. The registry still builds the planners
+    /// of the other agents and the pursuer.
+    pub(crate) fn new_with_planner(
+        config: Config,
+        environment: Option<EnvironmentSetup>,
+        registry: Arc<AlgorithmRegistry>,
+        planner: Option<Box<dyn PathfindingAlgorithm>>,
+    ) -> Result<Self, SimulationError> {
+        if config.hex_grid && config.diagonal_movement {
+            return Err("--hex-grid and --diagonal-movement cannot be combined"
+                .to_string()
+                .into());
         }
+        let mut environment = match environment {
+            Some(environment) => environment,
+            None => EnvironmentSetup::from_config(&config, config.seed)?,
+        };
+        // Waypoints of the options replace those the environment came with
+        let waypoints = Self::waypoints(&config)?;
+        if !waypoints.is_empty() {
+            environment.set_waypoints(waypoints)?;
+        }
+        environment.set_goal_route(Self::goal_route(&config)?, config.goal_move_interval)?;
+        if let Some(portals) = Self::portal_pairs(&config)? {
+            environment.set_portals(portals)?;
+        }
+        if let Some(path) = &config.obstacle_script {
+            environment.set_obstacle_script(ObstacleScript::load(path)?)?;
+        }
+        let grid = environment.create_grid();
+        let start = grid.start;
+        let agent = Agent::new(start)
+            .with_vision(Vision::from_config(&config)?)
+            .with_knowledge(Knowledge::from_name(&config.knowledge)?, &grid)
+            .with_memory(Memory::from_config(&config)?)
+            .with_noise(SensorNoise::from_config(&config)?, environment.sensor_seed);
+
+        let mut algorithm = match planner {
+            Some(planner) => planner,
+            None => registry.create(
+                &config.algorithm,
+                &AlgorithmContext {
+                    config: &config,
+                    start,
+                    goal: grid.goal,
+                    width: grid.width,
+                    height: grid.height,
+                },
+            )?,
+        };
+        if config.debug_planner {
+            algorithm.trace_searches();
+        }
+        let abort = AbortHandle::new();
+        let planning = abort.child();
+        algorithm.set_abort_handle(planning.clone());
 
-        let optimal_path_length = Self::calculate_optimal_path_with_astar(&grid);
+        let (optimal_path_length, optimal_path_cost) =
+            Self::calculate_optimal_path_with_astar(&grid);
+        let companions = Self::create_companions(&config, &environment, &registry, &grid)?;
+        let (reservations, conflict_search) = Self::coordinators(&config)?;
+        let pursuer = Self::create_pursuer(&config, &environment, &registry, &grid)?;
 
         if optimal_path_length == 0 {
-            return Err(format!(
-                "No valid path exists from start {:?} to goal {:?}! Grid has {} walls.",
-                grid.start,
-                grid.goal,
-                grid.cells
-                    .iter()
-                    .flatten()
-                    .filter(|&cell| *cell == Cell::Wall)
-                    .count()
-            ));
+            return Err(Self::no_path(&grid).into());
         }
+        let obstacle_schedule = Arc::new(environment.obstacle_schedule());
+        let baseline = Baseline {
+            optimal_path_length,
+            optimal_path_cost,
+            dynamic_optimal_cost: Self::dynamic_optimal_cost(
+                &config,
+                &grid,
+                &obstacle_schedule,
+                &abort,
+            ),
+        };
 
+        let stats = Self::initial_statistics(&config, baseline);
+        let observers = Self::default_observers(&config);
+        let span = Self::run_span(&config);
         Ok(Simulation {
             grid,
             agent,
             algorithm,
+            registry,
             config,
             optimal_path_length,
+            obstacle_schedule,
             environment,
             active_obstacle_groups: Vec::new(),
+            expired_obstacles: HashSet::new(),
             cycles_since_last_obstacle: 0,
             current_obstacle_cycle: 0,
+            current_goal_cycle: 0,
+            current_leg: 0,
+            wall_events_applied: Vec::new(),
+            movers: Vec::new(),
+            companions,
+            agent_previous: start,
+            reservations,
+            conflict_search,
+            pursuer,
+            stats,
+            timing_data: TimingData::new(),
+            planner_calls: 0,
+            steps: 0,
+            stuck_attempts: 0,
+            started: false,
+            outcome: None,
+            observers,
+            abort,
+            planning,
+            span,
+            run_time: Duration::ZERO,
         })
     }
 
-    pub fn run(&mut self) -> (Statistics, AlgorithmStats, TimingData) {
-        let mut stats = Statistics::new(
-            self.config.num_walls,
-            self.config.num_obstacles,
-            self.optimal_path_length,
-        );
+    /// Run the simulation to the end, or until an observer asks for it to stop. A run
+    /// that outlasts `--simulation-timeout-seconds`, or whose abort handle is aborted,
+    /// ends as timed out, even in the middle of a search.
+    pub fn run(&mut self) -> (Statistics, PlannerMetrics, TimingData) {
+        if let Some(seconds) = self.config.simulation_timeout_seconds {
+            self.abort
+                .abort_at(Instant::now() + Duration::from_secs_f64(seconds));
+        }
+        while !self.step().is_over() && !self.stop_requested() {
+            if self.abort.is_aborted() && !self.is_mission_complete() {
+                self.time_out();
+                break;
+            }
+        }
+        self.finish()
+    }
 
-        let mut total_iterations = 0;
-        let max_iterations = self.grid.size * self.grid.size * 4; // safe bound that doesn't seem to get hit unless something is broken in an implementation
+    /// Handle that aborts the planner's searches, which ends the run as timed out; a
+    /// clone aborts it from another thread
+    pub fn abort_handle(&self) -> AbortHandle {
+        self.abort.clone()
+    }
 
-        // Track timing data
-        let mut timing_data = TimingData::new();
+    /// Abort the planner's searches with `abort` rather than the run's own handle,
+    /// e.g. a child of a handle that aborts every run of a batch
+    pub fn set_abort_handle(&mut self, abort: AbortHandle) {
+        self.planning = abort.child();
+        self.algorithm.set_abort_handle(self.planning.clone());
+        self.abort = abort;
+    }
 
-        // Track stuck attempts
-        let mut stuck_attempts = 0;
-        const MAX_STUCK_ATTEMPTS: usize = 5;
+    /// Time the agent's planner has for each replan (`--find-path-budget-ms`); the
+    /// initial plan, made before the agent sets off, and cooperative plans have none
+    fn find_path_budget(&self) -> Option<Duration> {
+        self.config
+            .find_path_budget_ms
+            .filter(|_| self.reservations.is_none() && self.conflict_search.is_none())
+            .map(|ms| Duration::from_secs_f64(ms / 1000.0))
+    }
 
-        // Print initial grid only if visualization is enabled
-        if !self.config.no_visualization && !self.config.batch_mode {
-            self.clear_screen();
-            println!("=== PATHFINDING SIMULATION ===");
-            println!(
-                "Algorithm: {} | Step: 0 | Moves: 0 | Active obstacle groups: 0",
-                self.config.algorithm
-            );
-            println!("Optimal path length (A*): {}", self.optimal_path_length);
-            self.grid.print_grid(Some(self.agent.position));
-            thread::sleep(Duration::from_millis(self.config.delay_ms));
-        }
+    /// End the run as timed out
+    fn time_out(&mut self) -> StepOutcome {
+        self.stats.timed_out = true;
+        self.end(StepOutcome::Failed(Failure::TimedOut))
+    }
 
-        // Calculate initial path
-        let initial_path = self.algorithm.find_path(
-            &self.grid,
-            self.agent.position,
-            self.grid.goal,
-            &self.agent.known_obstacles,
-        );
+    /// Take steps until `step` steps are taken or the run is over, without telling the
+    /// observers about them
+    pub fn skip_to(&mut self, step: usize) {
+        let observers = std::mem::take(&mut self.observers);
+        while self.steps < step && !self.step().is_over() {}
+        self.observers = observers;
+    }
 
-        if let Some(path) = initial_path {
-            self.agent.set_path(path);
-        } else {
-            // No initial path found
-            return (stats, self.get_algorithm_stats(), timing_data);
+    /// Whether one of the observers asks for the run to stop
+    fn stop_requested(&self) -> bool {
+        self.observers
+            .iter()
+            .any(|observer| observer.stop_requested())
+    }
+
+    /// The span of a run, naming its algorithm and seed; logged as it closes with the
+    /// time spent in it at `--log-level debug`
+    fn run_span(config: &Config) -> Span {
+        debug_span!("run", algorithm = %config.algorithm, seed = config.seed)
+    }
+
+    /// The observers every simulation starts with: the terminal renderer (or the
+    /// dashboard of `--tui`) in builds with the `terminal` feature, the run log when
+    /// debug events are logged and the metrics counter while metrics are served
+    #[cfg_attr(not(feature = "terminal"), allow(unused_variables))]
+    fn default_observers(config: &Config) -> Vec<Box<dyn SimulationObserver>> {
+        let mut observers: Vec<Box<dyn SimulationObserver>> = Vec::new();
+        #[cfg(feature = "terminal")]
+        {
+            let renderer: Box<dyn SimulationObserver> =
+                match config.tui && !config.no_visualization && !config.batch_mode {
+                    true => Box::new(crate::dashboard::Dashboard::default()),
+                    false => Box::new(crate::terminal_renderer::TerminalRenderer::default()),
+                };
+            observers.push(renderer);
         }
+        if tracing::enabled!(tracing::Level::DEBUG) {
+            observers.push(Box::new(RunLog));
+        }
+        #[cfg(feature = "metrics")]
+        if metrics_exporter::is_serving() {
+            observers.push(Box::new(metrics_exporter::MetricsObserver));
+        }
+        observers
+    }
 
-        while self.agent.position != self.grid.goal && total_iterations < max_iterations {
-            // Update obstacle lifecycle using pre-generated timeline
-            let obstacles_changed = self.update_obstacles_from_timeline();
+    /// Add an observer, told about the run after the ones already added
+    pub fn add_observer(&mut self, observer: Box<dyn SimulationObserver>) {
+        self.observers.push(observer);
+    }
 
-            // Agent observes environment
-            self.agent.observe(&self.grid);
+    /// Remove every observer, the terminal renderer and run log included, to draw the
+    /// run some other way
+    pub fn clear_observers(&mut self) {
+        self.observers.clear();
+    }
 
-            // Check if path needs recalculation
-            let needs_recalc = self.agent.path_needs_recalculation(&self.grid)
-                || self.agent.is_path_blocked(&self.grid)
-                || obstacles_changed;
+    /// Advance the run by one step: the environment changes, the agent looks around,
+    /// plans again if it has to and moves, then the other agents and the pursuer take
+    /// their turns. The first step also places the moving obstacles and makes the
+    /// initial plan. Once the run is over, every step returns how it ended and
+    /// `finish` gives its statistics.
+    pub fn step(&mut self) -> StepOutcome {
+        if let Some(outcome) = self.outcome {
+            return outcome;
+        }
+        let started = Instant::now();
+        let run = self.span.clone();
+        let _run = run.enter();
+        let _step = trace_span!("step", step = self.steps + 1).entered();
+        let outcome = self.advance();
+        self.run_time += started.elapsed();
+        outcome
+    }
+
+    /// Time the run has spent stepping so far, observers included, however many
+    /// other runs share the CPU or the clock with it
+    pub fn run_time(&self) -> Duration {
+        self.run_time
+    }
 
-            if needs_recalc {
-                if !self.config.no_visualization {
-                    println!("Path blocked or environment changed - recalculating...");
+    /// Take the step `step` stands for, inside its span
+    fn advance(&mut self) -> StepOutcome {
+        if !self.started {
+            self.started = true;
+            if !self.start() {
+                if self.abort.is_aborted() {
+                    return self.time_out();
                 }
+                return self.end(StepOutcome::Failed(Failure::NoPath));
+            }
+        }
+        if self.is_mission_complete() && self.companions_done() {
+            return self.end(StepOutcome::Finished);
+        }
+        let step = self.steps + 1;
+
+        // Update obstacle lifecycle using pre-generated timeline
+        let obstacles_changed = self.update_obstacles_from_timeline(step);
+
+        // Add and remove permanent walls whose time has come
+        let wall_changes = self.apply_wall_events(step);
+
+        if obstacles_changed || !wall_changes.is_empty() {
+            self.notify(|observer, simulation| {
+                observer.on_obstacle_change(simulation, &wall_changes)
+            });
+        }
+
+        // Move the goal along its wander route (moving-goal mode only)
+        let goal_moved = self.update_goal_from_timeline();
+
+        // Moving obstacles take their next step
+        self.move_obstacles();
+
+        // In a multi-agent run, an agent that has finished waits on its goal for
+        // the others
+        let finished = self.is_mission_complete();
+        self.agent_previous = self.agent.position;
+
+        // Cooperative agents start the step holding only the cells they stand on
+        if self.reservations.is_some() {
+            let standing: Vec<(Position, bool)> = std::iter::once((self.agent.position, finished))
+                .chain(
+                    self.companions
+                        .iter()
+                        .map(|companion| (companion.agent.position, companion.is_done())),
+                )
+                .collect();
+            if let Some(table) = &mut self.reservations {
+                table.reset(&standing);
+            }
+        }
+
+        // Another agent on the next cell of the path is a collision to avoid, by
+        // replanning or waiting
+        if self
+            .agent
+            .get_next_step()
+            .is_some_and(|next| self.companions.iter().any(|c| c.agent.position == next))
+        {
+            self.stats.agent_collisions += 1;
+        }
 
-                // Notify algorithm of environment changes (for incremental algorithms)
-                self.algorithm
-                    .update_environment(&self.grid, &self.agent.known_obstacles);
+        // Agent observes environment, moving obstacles (and other agents) first
+        let movers_seen =
+            self.agent
+                .observe_movers(&self.grid, &self.sightings(None), self.config.predict_steps);
+        let obstacles_forgotten = self.agent.observe(&self.grid);
+        self.stats.stale_belief_steps += self.agent.stale_beliefs(&self.grid);
+
+        // An exploring agent tells the planners about the walls it has seen
+        // change instead of the ones that really did
+        let changed_walls = self
+            .agent
+            .observe_walls(&self.grid)
+            .unwrap_or_else(|| wall_changes.clone());
+
+        // Agents following a joint plan all get a new one when any of them needs it
+        let joint_requested =
+            self.conflict_search.is_some() && self.companions.iter().any(|c| c.wants_joint_plan);
+        if finished && joint_requested {
+            self.plan_jointly(true);
+        }
 
-                // Recalculate path
-                let find_path_start = Instant::now();
-                let new_path = self.algorithm.find_path(
-                    &self.grid,
+        // Check if path needs recalculation
+        // (a joint plan is only made again once some agent's part stops fitting)
+        let environment_changed =
+            obstacles_changed || !changed_walls.is_empty() || movers_seen || obstacles_forgotten;
+        let needs_recalc = !finished
+            && (self.reservations.is_some()
+                || joint_requested
+                || self.agent.path_needs_recalculation(&self.grid)
+                || self.agent.is_path_blocked(&self.grid)
+                || (environment_changed && self.conflict_search.is_none())
+                || goal_moved);
+
+        if needs_recalc {
+            // Notify algorithm of environment changes (for incremental algorithms)
+            let changes = EnvironmentChanges {
+                walls: changed_walls,
+                obstacles: Some(self.agent.take_obstacle_changes()),
+            };
+            self.algorithm.update_environment(
+                self.agent.planning_grid(&self.grid),
+                &self.agent.known_obstacles,
+                &changes,
+            );
+            self.algorithm
+                .update_obstacle_lifetimes(&self.obstacle_lifetimes(&self.agent.known_obstacles));
+            self.algorithm
+                .update_obstacle_schedule(&self.obstacle_schedule, self.steps);
+
+            // Recalculate path (cooperatively, as the first to plan this step, or
+            // as part of a joint plan for every agent)
+            let replan = debug_span!(
+                "replan",
+                duration_ns = Empty,
+                found_path = Empty,
+                stuck_attempts = Empty,
+                routine = self.reservations.is_some()
+            )
+            .entered();
+            let budget = self.find_path_budget();
+            if let Some(budget) = budget {
+                self.planning.renew(budget);
+            }
+            let find_path_start = Instant::now();
+            let targets = self.current_targets();
+            let new_path = if self.conflict_search.is_some() {
+                self.plan_jointly(false)
+            } else if let Some(table) = &mut self.reservations {
+                table.plan(
+                    self.agent.planning_grid(&self.grid),
                     self.agent.position,
-                    self.grid.goal,
+                    &targets,
                     &self.agent.known_obstacles,
-                );
-                let find_path_duration = find_path_start.elapsed();
-                timing_data.find_path_times.push(find_path_duration);
-
-                if let Some(path) = new_path {
-                    self.agent.set_path(path);
-                    stuck_attempts = 0; // Reset stuck counter
+                    0,
+                )
+            } else {
+                self.planner_calls += 1;
+                self.algorithm.find_path_to_any(
+                    self.agent.planning_grid(&self.grid),
+                    self.agent.position,
+                    &targets,
+                    &self.agent.known_obstacles,
+                )
+            };
+            let find_path_duration = find_path_start.elapsed();
+            self.timing_data.find_path_times.push(find_path_duration);
+            self.planning.reset();
+
+            // A replan that ran out of its budget leaves the agent on its previous
+            // path while that is still open
+            let over_budget = budget.is_some_and(|budget| find_path_duration >= budget);
+            if over_budget {
+                self.stats.budget_overruns += 1;
+            }
+            let kept_path = new_path.is_none()
+                && over_budget
+                && !self.abort.is_aborted()
+                && self.agent.get_next_step().is_some()
+                && !self.agent.is_path_blocked(&self.grid);
+
+            if let Some(path) = &new_path {
+                self.follow_path(path.clone());
+                self.stuck_attempts = 0; // Reset stuck counter
+            } else if !kept_path {
+                // No path found - agent is stuck
+                self.stuck_attempts += 1;
+            }
+            replan.record("duration_ns", find_path_duration.as_nanos() as u64);
+            replan.record("found_path", new_path.is_some() || kept_path);
+            replan.record("stuck_attempts", self.stuck_attempts);
+            replan.exit();
+            let replan = Replan {
+                duration: find_path_duration,
+                stuck_attempts: self.stuck_attempts,
+                routine: self.reservations.is_some(),
+            };
+            self.notify(|observer, simulation| observer.on_replan(simulation, &replan));
 
-                    if !self.config.no_visualization {
-                        println!(
-                            "New path found with {} steps",
-                            self.agent.get_current_path().unwrap().len()
-                        );
-                    }
-                } else {
-                    // No path found - agent is stuck
-                    stuck_attempts += 1;
+            if new_path.is_none() && !kept_path {
+                if self.abort.is_aborted() {
+                    return self.time_out();
+                }
+                if self.stuck_attempts > MAX_STUCK_ATTEMPTS {
+                    return self.end(StepOutcome::Failed(Failure::Stuck));
+                }
+                if !self.spend_energy(1.0) {
+                    return self.end(StepOutcome::Failed(Failure::OutOfEnergy));
+                }
+                self.stats.total_moves += 1; // Count waiting as a move
+            }
+        }
 
-                    if stuck_attempts <= MAX_STUCK_ATTEMPTS {
-                        stats.total_moves += 1; // Count waiting as a move
+        // Give anytime planners the rest of the step to improve the current path
+        // (final leg of single-goal runs only; refinement always targets the main goal)
+        if !needs_recalc
+            && !finished
+            && self.stuck_attempts == 0
+            && self.grid.extra_goals.is_empty()
+            && self.current_leg == self.grid.waypoints.len()
+        {
+            if let Some(path) = self.algorithm.refine_path(
+                self.agent.planning_grid(&self.grid),
+                self.agent.position,
+                self.grid.goal,
+                &self.agent.known_obstacles,
+            ) {
+                self.follow_path(path);
+            }
+        }
 
-                        if !self.config.no_visualization {
-                            println!(
-                                "No path found - waiting... (attempt {}/{})",
-                                stuck_attempts, MAX_STUCK_ATTEMPTS
-                            );
-                        }
-                    } else {
-                        if !self.config.no_visualization {
-                            println!(
-                                "FAILURE: Agent permanently stuck after {} attempts",
-                                MAX_STUCK_ATTEMPTS
-                            );
-                        }
-                        break;
+        // Follow current path (only if we have a valid path and aren't stuck)
+        let mut moved = None;
+        if self.stuck_attempts == 0 && !finished {
+            // Running into an obstacle the agent missed, or finding another agent
+            // on the next cell, costs it a step
+            let bumped = self.agent.bump(&self.grid);
+            let blocked = !bumped
+                && self
+                    .agent
+                    .get_next_step()
+                    .is_some_and(|next| self.companions.iter().any(|c| c.agent.position == next));
+            if bumped || blocked {
+                if !self.spend_energy(1.0) {
+                    return self.end(StepOutcome::Failed(Failure::OutOfEnergy));
+                }
+                self.stats.total_moves += 1;
+            }
+            if let Some(next_pos) = self.agent.get_next_step().filter(|_| !bumped && !blocked) {
+                if !self.spend_energy(self.grid.path_cost(&[self.agent.position, next_pos])) {
+                    return self.end(StepOutcome::Failed(Failure::OutOfEnergy));
+                }
+                moved = Some(self.agent.position);
+                self.agent.move_to(next_pos);
+                self.stats.total_moves += 1;
+                self.advance_legs();
+            } else {
+                // Reached end of path - at the goal, or at the end of a partial
+                // path from a real-time planner
+                if self.is_mission_complete() {
+                    if self.companions_done() {
+                        self.record_makespan(step);
+                        return self.end(StepOutcome::Finished);
                     }
+                } else {
+                    // Force recalculation
+                    self.agent.clear_path();
                 }
             }
+        }
 
-            // Follow current path (only if we have a valid path and aren't stuck)
-            if stuck_attempts == 0 {
-                if let Some(next_pos) = self.agent.get_next_step() {
-                    self.agent.move_to(next_pos);
-                    stats.total_moves += 1;
+        // The other agents take their turns, in order
+        self.step_companions(obstacles_changed, &wall_changes);
+        self.record_makespan(step);
 
-                    if !self.config.no_visualization || !self.config.batch_mode {
-                        self.clear_screen();
-                        println!("=== PATHFINDING SIMULATION ===");
-                        println!(
-                            "Algorithm: {} | Step: {} | Moves: {} | Active obstacle groups: {}",
-                            self.config.algorithm,
-                            total_iterations + 1,
-                            stats.total_moves,
-                            self.active_obstacle_groups.len()
-                        );
+        // Then the pursuer, which ends the run if it catches the agent
+        if self.pursue(&wall_changes) {
+            self.stats.caught = true;
+            return self.end(StepOutcome::Failed(Failure::Caught));
+        }
 
-                        let (path_progress, path_total) = self.agent.get_path_progress();
-                        println!(
-                            "Agent position: ({}, {}) | Path progress: {}/{}",
-                            self.agent.position.x, self.agent.position.y, path_progress, path_total
-                        );
-                        println!(
-                            "Goal position: ({}, {})",
-                            self.grid.goal.x, self.grid.goal.y
-                        );
-                        println!("Original optimal path (A*): {}", self.optimal_path_length);
-                        println!(
-                            "Obstacle cycle: {} | Cycles until next: {}",
-                            self.current_obstacle_cycle,
-                            self.environment.obstacle_cycle_interval
-                                - self.cycles_since_last_obstacle
-                        );
+        self.steps = step;
+        if self.steps >= self.max_steps() {
+            return self.end(if self.is_mission_complete() {
+                StepOutcome::Finished
+            } else {
+                StepOutcome::Failed(Failure::StepLimit)
+            });
+        }
 
-                        // Show timing info
+        let replanned = needs_recalc;
+        let outcome = match moved {
+            Some(from) => StepOutcome::Moved {
+                from,
+                to: self.agent.position,
+                replanned,
+            },
+            None => StepOutcome::Waited { replanned },
+        };
+        self.notify(|observer, simulation| observer.on_step(simulation, outcome));
+        outcome
+    }
 
-                        if !timing_data.find_path_times.is_empty() {
-                            println!(
-                                "Last find_path: {:.2?} | Avg find_path: {:.2?}",
-                                timing_data.find_path_times.last().unwrap(),
-                                timing_data.average_find_path_time()
-                            );
-                        }
+    /// Statistics of the run, telling the observers it is over; meant for once the
+    /// run is over
+    pub fn finish(&mut self) -> (Statistics, PlannerMetrics, TimingData) {
+        let run = self.span.clone();
+        let _run = run.enter();
+        // A run without an initial path never started
+        if self.outcome != Some(StepOutcome::Failed(Failure::NoPath)) {
+            self.stats.agents = self
+                .companions
+                .iter()
+                .map(|companion| companion.stats.clone())
+                .collect();
+            self.stats.cbs_expansions = self
+                .conflict_search
+                .as_ref()
+                .map_or(0, |search| search.expansions);
+
+            // Clean up any remaining obstacles
+            self.clear_all_obstacles();
+
+            self.close_leg(false);
+            self.stats.calculate_efficiency();
+        }
+        self.notify(|observer, simulation| observer.on_finish(simulation));
+        (
+            self.stats.clone(),
+            self.get_algorithm_stats(),
+            self.timing_data.clone(),
+        )
+    }
 
-                        // Show obstacle group info
-                        for (i, group) in self.active_obstacle_groups.iter().enumerate() {
-                            println!(
-                                "Obstacle group {}: {} obstacles, {} cycles remaining",
-                                i + 1,
-                                group.positions.len(),
-                                group.cycles_remaining
-                            );
-                        }
+    /// Set the run up before its first step: place the moving obstacles, open the
+    /// legs and make the initial plan. Returns false when there is no initial path.
+    fn start(&mut self) -> bool {
+        // Moving obstacles start on the first cell of their route
+        self.movers = vec![MoverState::default(); self.environment.moving_obstacles.len()];
+        self.move_obstacles();
+
+        self.notify(|observer, simulation| observer.on_start(simulation));
+
+        // One leg per waypoint, plus the final leg to the goal
+        self.current_leg = 0;
+        self.stats.legs = self
+            .grid
+            .waypoints
+            .iter()
+            .chain(std::iter::once(&self.grid.goal))
+            .map(|&target| LegStatistics {
+                target,
+                moves: 0,
+                replans: 0,
+                reached: false,
+            })
+            .collect();
+        self.advance_legs();
+
+        // Calculate initial path, once an exploring agent has looked around (along
+        // with the other agents' when they follow a joint plan)
+        self.agent.observe_walls(&self.grid);
+        // The planner takes in the obstacles known so far whole; later ones are
+        // passed on as changes
+        self.agent.take_obstacle_changes();
+        self.algorithm
+            .update_obstacle_schedule(&self.obstacle_schedule, self.steps);
+        let _plan = debug_span!("initial_plan").entered();
+        let initial_path = if self.conflict_search.is_some() {
+            self.plan_jointly(false)
+        } else {
+            self.planner_calls += 1;
+            self.algorithm.find_path_to_any(
+                self.agent.planning_grid(&self.grid),
+                self.agent.position,
+                &self.current_targets(),
+                &self.agent.known_obstacles,
+            )
+        };
+        let Some(path) = initial_path else {
+            return false;
+        };
+        self.follow_path(path);
+        self.wall_events_applied = vec![false; self.environment.wall_events.len()];
+        true
+    }
 
-                        // Show next few moves in current path
-                        if let Some(path) = self.agent.get_current_path() {
-                            let (current_idx, _) = self.agent.get_path_progress();
-                            if current_idx + 1 < path.len() {
-                                let next_moves: Vec<_> =
-                                    path.iter().skip(current_idx + 1).take(3).collect();
-                                println!("Next moves: {:?}", next_moves);
-                            }
-                        }
+    /// Record how the run ended and return it
+    fn end(&mut self, outcome: StepOutcome) -> StepOutcome {
+        self.outcome = Some(outcome);
+        self.notify(|observer, simulation| observer.on_step(simulation, outcome));
+        outcome
+    }
 
-                        self.grid.print_grid(Some(self.agent.position));
-                        thread::sleep(Duration::from_millis(self.config.delay_ms));
-                    }
-                } else {
-                    // Reached end of path - should be at goal
-                    if !self.agent.is_at_goal(self.grid.goal) {
-                        if !self.config.no_visualization {
-                            println!("Warning: Reached end of path but not at goal!");
-                        }
-                        // Force recalculation
-                        self.agent.clear_path();
-                    }
-                    break;
-                }
-            }
+    /// Tell every observer about an event
+    fn notify(&mut self, event: impl Fn(&mut dyn SimulationObserver, &Simulation)) {
+        let mut observers = std::mem::take(&mut self.observers);
+        for observer in &mut observers {
+            event(observer.as_mut(), self);
+        }
+        self.observers = observers;
+    }
 
-            total_iterations += 1;
-            if total_iterations >= max_iterations {
-                if !self.config.no_visualization {
-                    println!("Reached max iterations, stopping simulation");
-                }
-                break;
-            }
+    /// Steps a run may take; a safe bound that is only hit when an implementation
+    /// is broken
+    fn max_steps(&self) -> usize {
+        self.grid.cell_count() * 4
+    }
+
+    /// Statistics of a run before its first step
+    fn initial_statistics(config: &Config, baseline: Baseline) -> Statistics {
+        let mut stats = Statistics::new(
+            config.num_walls,
+            config.num_obstacles,
+            baseline.optimal_path_length,
+        );
+        stats.optimal_path_cost = baseline.optimal_path_cost;
+        stats.dynamic_optimal_cost = baseline.dynamic_optimal_cost;
+        stats.energy_budget = config.energy_budget;
+        stats
+    }
+
+    /// Targets for the current leg: the next waypoint, or the goal(s) once all are visited
+    fn current_targets(&self) -> Vec<Position> {
+        match self.grid.waypoints.get(self.current_leg) {
+            Some(&waypoint) => vec![waypoint],
+            None => self.grid.goals(),
         }
+    }
 
-        // Clean up any remaining obstacles
-        self.clear_all_obstacles();
+    /// True once every waypoint has been visited and the agent stands on a goal
+    pub fn is_mission_complete(&self) -> bool {
+        self.current_leg == self.grid.waypoints.len() && self.grid.is_goal(self.agent.position)
+    }
 
-        // Final state
-        if !self.config.no_visualization {
-            self.clear_screen();
-            println!("=== SIMULATION COMPLETE ===");
-            if self.agent.is_at_goal(self.grid.goal) {
-                println!("SUCCESS: Agent reached the goal!");
-            } else {
-                println!("FAILED: Agent did not reach the goal");
-            }
-            println!("Algorithm: {}", self.config.algorithm);
-            println!(
-                "Final position: ({}, {})",
-                self.agent.position.x, self.agent.position.y
-            );
-            println!(
-                "Total steps: {} | Total moves: {}",
-                total_iterations, stats.total_moves
-            );
-            println!("Original optimal path (A*): {}", self.optimal_path_length);
-            println!("Path recalculations: {}", timing_data.find_path_times.len());
+    /// Options of the run
+    pub fn config(&self) -> &Config {
+        &self.config
+    }
 
-            // Show timing summary
-            println!(
-                "Average find_path time: {:.2?}",
-                timing_data.average_find_path_time()
-            );
+    /// Steps taken so far
+    pub fn steps(&self) -> usize {
+        self.steps
+    }
 
-            // Calculate final optimal path
-            let final_optimal_length = Self::calculate_optimal_path_with_astar(&self.grid);
-            println!("Final optimal path (A*): {}", final_optimal_length);
+    /// Statistics of the run so far; `finish` adds the other agents' and the
+    /// efficiency figures
+    pub fn statistics(&self) -> &Statistics {
+        &self.stats
+    }
 
-            self.grid.print_grid(Some(self.agent.position));
-        }
+    /// Time of every replan of the run so far (the initial plan is not timed)
+    pub fn timing_data(&self) -> &TimingData {
+        &self.timing_data
+    }
 
-        stats.calculate_efficiency();
-        (stats, self.get_algorithm_stats(), timing_data)
+    /// What the planner's latest search did, with `--debug-planner`, if the planner
+    /// can show it
+    pub fn search_trace(&self) -> Option<SearchTrace> {
+        self.algorithm
+            .search_trace(self.agent.planning_grid(&self.grid))
     }
 
-    /// Get algorithm statistics based on algorithm type
-    fn get_algorithm_stats(&self) -> AlgorithmStats {
-        let path_calculations = self.get_path_calculation_count();
+    /// Calls made to the planner so far, the initial plan included; plans made
+    /// jointly or through the reservation table of a multi-agent run are not its
+    pub fn planner_calls(&self) -> usize {
+        self.planner_calls
+    }
 
-        match self.config.algorithm.as_str() {
-            "a_star" => AlgorithmStats::AStar(path_calculations),
-            "d_star_lite" => AlgorithmStats::DStarLite(path_calculations),
-            "hybrid" => {
-                let (a_star_calls, d_star_calls) = self.algorithm.get_usage_stats();
-                AlgorithmStats::Hybrid {
-                    a_star_calls,
-                    d_star_calls,
-                }
-            }
-            _ => AlgorithmStats::AStar(path_calculations),
+    /// How the run ended, once it has
+    pub fn outcome(&self) -> Option<StepOutcome> {
+        self.outcome
+    }
+
+    /// Whether the run is over
+    pub fn is_over(&self) -> bool {
+        self.outcome.is_some()
+    }
+
+    /// Cells the agent heads for on the current leg: the next waypoint, or the
+    /// goal(s) once all are visited
+    pub fn targets(&self) -> Vec<Position> {
+        self.current_targets()
+    }
+
+    /// Cells of the other agents, in planning order
+    pub fn companion_positions(&self) -> Vec<Position> {
+        self.companions
+            .iter()
+            .map(|companion| companion.agent.position)
+            .collect()
+    }
+
+    /// How many of the other agents are at their goals
+    pub fn companions_reached(&self) -> usize {
+        self.companions.iter().filter(|c| c.stats.reached).count()
+    }
+
+    /// The pursuer, with `--pursuer`
+    pub fn pursuer(&self) -> Option<&Pursuer> {
+        self.pursuer.as_ref()
+    }
+
+    /// Cells of the moving obstacles placed so far
+    pub fn moving_obstacle_positions(&self) -> Vec<Position> {
+        self.movers.iter().filter_map(|mover| mover.cell).collect()
+    }
+
+    /// Groups of obstacles of the timeline on the grid now
+    pub fn obstacle_groups(&self) -> &[ObstacleGroup] {
+        &self.active_obstacle_groups
+    }
+
+    /// Groups of the obstacle timeline placed so far
+    pub fn obstacle_cycle(&self) -> usize {
+        self.current_obstacle_cycle
+    }
+
+    /// Steps before the next group of the obstacle timeline is due
+    pub fn cycles_until_next_obstacles(&self) -> usize {
+        self.environment.obstacle_cycle_interval - self.cycles_since_last_obstacle
+    }
+
+    /// How many of the environment's wall events have happened, and how many it has
+    pub fn wall_event_progress(&self) -> (usize, usize) {
+        let happened = self
+            .wall_events_applied
+            .iter()
+            .filter(|&&done| done)
+            .count();
+        (happened, self.wall_events_applied.len())
+    }
+
+    /// Length of the optimal path from the start to the goal before the run
+    pub fn optimal_path_length(&self) -> usize {
+        self.optimal_path_length
+    }
+
+    /// Index of the current leg: the waypoints visited so far
+    pub fn current_leg(&self) -> usize {
+        self.current_leg
+    }
+
+    /// Cells of the obstacles of the timeline on the grid now
+    pub fn obstacle_positions(&self) -> Vec<Position> {
+        self.active_obstacle_groups
+            .iter()
+            .flat_map(|group| group.positions.iter().copied())
+            .collect()
+    }
+
+    /// Close every leg whose waypoint the agent is standing on and move on to the next
+    fn advance_legs(&mut self) {
+        while self
+            .grid
+            .waypoints
+            .get(self.current_leg)
+            .is_some_and(|&waypoint| waypoint == self.agent.position)
+        {
+            self.close_leg(true);
+            self.current_leg += 1;
+            // The old path led to the waypoint; plan the next leg from here
+            self.agent.clear_path();
         }
     }
 
-    /// Get total number of path calculations performed
-    fn get_path_calculation_count(&self) -> usize {
-        // This should be tracked by timing_data.find_path_times.len()
-        // but for hybrid algorithms, we need to use their internal counters
-        match self.config.algorithm.as_str() {
-            "hybrid" => {
-                let (a_star_calls, d_star_calls) = self.algorithm.get_usage_stats();
-                a_star_calls + d_star_calls
-            }
-            _ => {
-                // For non-hybrid algorithms, count is the number of find_path calls
-                // This will be properly tracked by timing_data, but we need to access it
-                // For now, return 0 and let the caller use timing_data.find_path_times.len()
-                0
-            }
+    /// Record the moves and replans spent on the current leg
+    fn close_leg(&mut self, reached: bool) {
+        let moves_before: usize = self.stats.legs[..self.current_leg]
+            .iter()
+            .map(|leg| leg.moves)
+            .sum();
+        let replans_before: usize = self.stats.legs[..self.current_leg]
+            .iter()
+            .map(|leg| leg.replans)
+            .sum();
+        let reached = reached || self.is_mission_complete();
+        let (total_moves, total_calls) = (self.stats.total_moves, self.timing_data.total_calls());
+        if let Some(leg) = self.stats.legs.get_mut(self.current_leg) {
+            leg.moves = total_moves - moves_before;
+            leg.replans = total_calls - replans_before;
+            leg.reached = reached;
         }
     }
 
-    /// Update obstacles using the pre-generated timeline
+    /// Spend `cost` of the agent's `--energy-budget` on a move or a wait. Returns
+    /// false, marking the run as out of energy, when too little is left for it.
+    fn spend_energy(&mut self, cost: f64) -> bool {
+        if self
+            .stats
+            .energy_budget
+            .is_some_and(|budget| self.stats.travel_cost + cost > budget + 1e-9)
+        {
+            self.stats.out_of_energy = true;
+            return false;
+        }
+        self.stats.travel_cost += cost;
+        true
+    }
+
+    /// Hand a planned path to the agent, smoothing it first if `--smooth-path` is set
+    fn follow_path(&mut self, path: Vec<Position>) {
+        self.stats.raw_path_length += path.len().saturating_sub(1);
+        let path = if self.config.smooth_path {
+            smooth_path(
+                self.agent.planning_grid(&self.grid),
+                &path,
+                &self.agent.known_obstacles,
+            )
+        } else {
+            path
+        };
+        self.stats.smoothed_path_length += path.len().saturating_sub(1);
+        self.agent.set_path(path);
+    }
+
+    /// Get the planner's metrics from the registered stats adapter
+    fn get_algorithm_stats(&mut self) -> PlannerMetrics {
+        self.registry.stats(
+            &self.config.algorithm,
+            self.algorithm.as_mut(),
+            &self.config,
+            self.planner_calls,
+        )
+    }
+
+    /// Steps each known obstacle from the timeline stays before it expires (0 once it
+    /// has, should the agent still remember it), for planners that can wait for one
+    /// to go away
+    fn obstacle_lifetimes(&self, known_obstacles: &HashSet<Position>) -> HashMap<Position, usize> {
+        let expired = self.expired_obstacles.iter().map(|&pos| (pos, 0));
+        let active = self.active_obstacle_groups.iter().flat_map(|group| {
+            group
+                .positions
+                .iter()
+                .map(|&pos| (pos, group.cycles_remaining))
+        });
+        expired
+            .chain(active)
+            .filter(|(pos, _)| known_obstacles.contains(pos))
+            .collect()
+    }
+
+    /// Update obstacles using the pre-generated timeline (or the scripted one)
     /// Returns true if obstacles changed
-    fn update_obstacles_from_timeline(&mut self) -> bool {
+    fn update_obstacles_from_timeline(&mut self, step: usize) -> bool {
         let mut obstacles_changed = false;
 
         // Increment cycle counter
@@ -507,8 +2189,9 @@ impl Simulation {
             if group.cycles_remaining == 0 {
                 // Remove obstacles from grid
                 for &pos in &group.positions {
-                    self.grid.cells[pos.x][pos.y] = Cell::Empty;
+                    self.grid.set_cell(pos, Cell::Empty);
                 }
+                self.expired_obstacles.extend(&group.positions);
                 expired_groups.push(i);
                 obstacles_changed = true;
             }
@@ -528,17 +2211,303 @@ impl Simulation {
             self.cycles_since_last_obstacle = 0;
         }
 
+        // Scripted obstacles due this step
+        let waves: Vec<ObstacleWave> = self
+            .environment
+            .obstacle_waves
+            .iter()
+            .filter(|wave| wave.step == step)
+            .cloned()
+            .collect();
+        for wave in waves {
+            obstacles_changed =
+                self.place_obstacle_group(&wave.cells, wave.duration) || obstacles_changed;
+        }
+
         obstacles_changed
     }
 
+    /// Move the goal to its next position in the pre-generated wander route
+    /// Returns true if the goal moved
+    fn update_goal_from_timeline(&mut self) -> bool {
+        let Some(&next_goal) = self.environment.goal_timeline.get(self.current_goal_cycle) else {
+            return false;
+        };
+
+        // A goal can't step onto an obstacle; it waits until the cell clears
+        if self.grid.cell(next_goal) == Cell::Obstacle {
+            return false;
+        }
+
+        self.current_goal_cycle += 1;
+        if next_goal == self.grid.goal {
+            return false;
+        }
+        self.grid.goal = next_goal;
+        if let Some(belief) = &mut self.agent.belief {
+            belief.goal = next_goal;
+        }
+        true
+    }
+
+    /// Apply the pending wall events due by `step`, returning the cells that changed.
+    /// An event whose cell is taken (e.g. by the agent, an obstacle or the wandering
+    /// goal's route) stays pending.
+    fn apply_wall_events(&mut self, step: usize) -> Vec<Position> {
+        let mut changed = Vec::new();
+        for (i, event) in self.environment.wall_events.iter().enumerate() {
+            if event.step > step {
+                break;
+            }
+            if self.wall_events_applied[i] {
+                continue;
+            }
+            let possible = if event.wall {
+                self.is_valid_obstacle_position(&event.pos)
+                    && !self.environment.goal_timeline[self.current_goal_cycle..]
+                        .contains(&event.pos)
+            } else {
+                self.grid.is_wall(event.pos)
+            };
+            if possible {
+                let cell = if event.wall { Cell::Wall } else { Cell::Empty };
+                self.grid.set_cell(event.pos, cell);
+                self.wall_events_applied[i] = true;
+                changed.push(event.pos);
+            }
+        }
+        changed
+    }
+
+    /// Move every moving obstacle one step along its route (placing it on the first
+    /// cell at the start of the run). An obstacle whose next cell is taken, e.g. by the
+    /// agent or another obstacle, waits where it is.
+    fn move_obstacles(&mut self) {
+        let pursuer = self.pursuer.as_ref().map(|pursuer| pursuer.position);
+        for (mover, obstacle) in self
+            .movers
+            .iter_mut()
+            .zip(&self.environment.moving_obstacles)
+        {
+            let next = match mover.cell {
+                Some(_) => obstacle.cell(mover.index + 1),
+                None => obstacle.cell(0),
+            };
+            mover.previous = mover.cell;
+            if mover.cell == Some(next) {
+                mover.index += 1;
+                continue;
+            }
+            if self.grid.cell(next) != Cell::Empty
+                || next == self.agent.position
+                || pursuer == Some(next)
+                || self.grid.is_goal(next)
+                || self
+                    .companions
+                    .iter()
+                    .any(|companion| companion.agent.position == next || companion.goal == next)
+            {
+                continue;
+            }
+            if let Some(cell) = mover.cell {
+                self.grid.set_cell(cell, Cell::Empty);
+                mover.index += 1;
+            }
+            self.grid.set_cell(next, Cell::Obstacle);
+            mover.cell = Some(next);
+        }
+    }
+
+    /// Current and previous cell of every placed moving obstacle
+    fn mover_sightings(&self) -> Vec<(Position, Position)> {
+        self.movers
+            .iter()
+            .filter_map(|mover| Some((mover.cell?, mover.previous.or(mover.cell)?)))
+            .collect()
+    }
+
+    /// What the given other agent (or, for `None`, this run's own agent) can see
+    /// moving: the moving obstacles, the pursuer and every other agent, by current and
+    /// previous cell (agents planning cooperatively leave each other to the
+    /// reservations or the joint plan)
+    fn sightings(&self, observer: Option<usize>) -> Vec<(Position, Position)> {
+        let mut sightings = self.mover_sightings();
+        sightings.extend(
+            self.pursuer
+                .as_ref()
+                .map(|pursuer| (pursuer.position, pursuer.previous)),
+        );
+        if self.reservations.is_some() || self.conflict_search.is_some() {
+            return sightings;
+        }
+        if observer.is_some() {
+            sightings.push((self.agent.position, self.agent_previous));
+        }
+        for (i, companion) in self.companions.iter().enumerate() {
+            if observer != Some(i) {
+                sightings.push((companion.agent.position, companion.previous));
+            }
+        }
+        sightings
+    }
+
+    /// Note the step as the makespan the first time every agent is on its goal
+    fn record_makespan(&mut self, step: usize) {
+        if self.stats.makespan.is_none()
+            && self.is_mission_complete()
+            && self
+                .companions
+                .iter()
+                .all(|companion| companion.stats.reached)
+        {
+            self.stats.makespan = Some(step);
+        }
+    }
+
+    /// Whether the pursuer stands on `pos`
+    fn pursuer_at(&self, pos: Position) -> bool {
+        self.pursuer
+            .as_ref()
+            .is_some_and(|pursuer| pursuer.position == pos)
+    }
+
+    /// The pursuer takes its turn after the agents, chasing this run's own agent around
+    /// every obstacle and the other agents, unless the agent has reached its goal.
+    /// Returns whether it has caught the agent (or the agent walked into it).
+    fn pursue(&mut self, changed_walls: &[Position]) -> bool {
+        if self.pursuer.is_none() || self.is_mission_complete() {
+            return false;
+        }
+        let target = self.agent.position;
+        let blocked: HashSet<Position> = self
+            .active_obstacle_groups
+            .iter()
+            .flat_map(|group| group.positions.iter().copied())
+            .chain(self.movers.iter().filter_map(|mover| mover.cell))
+            .chain(
+                self.companions
+                    .iter()
+                    .map(|companion| companion.agent.position),
+            )
+            .collect();
+        let Some(pursuer) = &mut self.pursuer else {
+            return false;
+        };
+        if pursuer.position != target {
+            pursuer.chase(&self.grid, target, &blocked, changed_walls);
+        }
+        pursuer.position == target
+    }
+
+    /// Whether every other agent has stopped (always true with a single agent)
+    fn companions_done(&self) -> bool {
+        self.companions.iter().all(Companion::is_done)
+    }
+
+    /// Give each other agent its turn, after this run's own agent has moved; each
+    /// sees the others where they stand at the start of its turn
+    fn step_companions(&mut self, obstacles_changed: bool, changed_walls: &[Position]) {
+        for i in 0..self.companions.len() {
+            let sightings = self.sightings(Some(i));
+            let occupied: HashSet<Position> = self
+                .companions
+                .iter()
+                .enumerate()
+                .filter(|&(j, _)| j != i)
+                .map(|(_, companion)| companion.agent.position)
+                .chain([self.agent.position])
+                .chain(self.pursuer.as_ref().map(|pursuer| pursuer.position))
+                .collect();
+            let lifetimes = self.obstacle_lifetimes(&self.companions[i].agent.known_obstacles);
+            let turn = Turn {
+                sightings: &sightings,
+                occupied: &occupied,
+                obstacles_changed,
+                changed_walls,
+                lifetimes: &lifetimes,
+            };
+            let planning = match &mut self.reservations {
+                Some(table) => Planning::Reserved(table),
+                None if self.conflict_search.is_some() => Planning::Joint,
+                None => Planning::Own,
+            };
+            self.companions[i].take_turn(&self.grid, &self.config, &turn, planning);
+        }
+    }
+
+    /// Make a joint plan for every agent still on its way with Conflict-Based Search,
+    /// hand the other agents their paths and return this run's own agent's (`None`
+    /// when it has `finished`, or no way to its targets). Agents that have stopped
+    /// are in the way like obstacles.
+    fn plan_jointly(&mut self, finished: bool) -> Option<Vec<Position>> {
+        let mut stopped: HashSet<Position> = self
+            .companions
+            .iter()
+            .filter(|companion| companion.is_done())
+            .map(|companion| companion.agent.position)
+            .collect();
+        if finished {
+            stopped.insert(self.agent.position);
+        }
+        let planners: Vec<usize> = (0..self.companions.len())
+            .filter(|&i| !self.companions[i].is_done())
+            .collect();
+        let mut tasks: Vec<Task> = Vec::new();
+        if !finished {
+            tasks.push(Task {
+                grid: self.agent.planning_grid(&self.grid),
+                start: self.agent.position,
+                goals: self.current_targets(),
+                obstacles: &self.agent.known_obstacles | &stopped,
+            });
+        }
+        for &i in &planners {
+            let agent = &self.companions[i].agent;
+            tasks.push(Task {
+                grid: agent.planning_grid(&self.grid),
+                start: agent.position,
+                goals: vec![self.companions[i].goal],
+                obstacles: &agent.known_obstacles | &stopped,
+            });
+        }
+        let mut paths = match &mut self.conflict_search {
+            Some(search) => search.solve(&tasks),
+            None => return None,
+        }
+        .into_iter();
+
+        let own_path = if finished {
+            None
+        } else {
+            paths.next().flatten()
+        };
+        for (i, path) in planners.into_iter().zip(paths) {
+            self.companions[i].follow(path);
+        }
+        own_path
+    }
+
     /// Place obstacles from the pre-generated timeline
     /// Returns true if obstacles were placed
     fn place_obstacle_group_from_timeline(&mut self) -> bool {
-        let obstacle_positions = &self.environment.obstacle_timeline[self.current_obstacle_cycle];
+        let obstacle_positions =
+            self.environment.obstacle_timeline[self.current_obstacle_cycle].clone();
+        self.place_obstacle_group(
+            &obstacle_positions,
+            self.environment.obstacle_persistence_cycles,
+        )
+    }
 
+    /// Place a group of obstacles that stays for `cycles` cycles
+    /// Returns true if obstacles were placed
+    fn place_obstacle_group(
+        &mut self,
+        obstacle_positions: &HashSet<Position>,
+        cycles: usize,
+    ) -> bool {
         let mut new_group = ObstacleGroup {
             positions: HashSet::new(),
-            cycles_remaining: self.environment.obstacle_persistence_cycles,
+            cycles_remaining: cycles,
         };
 
         // Place obstacles from the timeline
@@ -546,7 +2515,8 @@ impl Simulation {
             // Double-check that position is still valid (not occupied by agent)
             if self.is_valid_obstacle_position(&pos) {
                 new_group.positions.insert(pos);
-                self.grid.cells[pos.x][pos.y] = Cell::Obstacle;
+                self.expired_obstacles.remove(&pos);
+                self.grid.set_cell(pos, Cell::Obstacle);
             }
         }
 
@@ -561,12 +2531,27 @@ impl Simulation {
     /// Check if a position is valid for obstacle placement
     fn is_valid_obstacle_position(&self, pos: &Position) -> bool {
         // Can't place on start, goal, or agent position
-        if *pos == self.grid.start || *pos == self.grid.goal || *pos == self.agent.position {
+        if *pos == self.grid.start
+            || self.grid.is_goal(*pos)
+            || self.grid.waypoints.contains(pos)
+            || *pos == self.agent.position
+        {
             return false;
         }
 
-        // Can't place on walls or existing obstacles
-        if self.grid.cells[pos.x][pos.y] != Cell::Empty {
+        // Nor on another agent, its goal or the pursuer
+        if self
+            .companions
+            .iter()
+            .any(|companion| companion.agent.position == *pos || companion.goal == *pos)
+            || self.pursuer_at(*pos)
+        {
+            return false;
+        }
+
+        // Can't place on walls, terrain or existing obstacles, so terrain costs
+        // never change during a run
+        if self.grid.cell(*pos) != Cell::Empty {
             return false;
         }
 
@@ -577,142 +2562,460 @@ impl Simulation {
     fn clear_all_obstacles(&mut self) {
         for group in &self.active_obstacle_groups {
             for &pos in &group.positions {
-                self.grid.cells[pos.x][pos.y] = Cell::Empty;
+                self.grid.set_cell(pos, Cell::Empty);
             }
         }
         self.active_obstacle_groups.clear();
+        for mover in &mut self.movers {
+            if let Some(cell) = mover.cell.take() {
+                self.grid.set_cell(cell, Cell::Empty);
+            }
+        }
     }
 
-    /// Clear the terminal screen (only used when visualization is enabled)
-    fn clear_screen(&self) {
-        print!("\x1B[2J\x1B[1;1H");
+    /// The error for a grid whose walls cut the goal off from the start
+    fn no_path(grid: &Grid) -> PlanningError {
+        PlanningError::NoPathExists {
+            start: grid.start,
+            goal: grid.goal,
+            walls: (0..grid.cell_count())
+                .filter(|&index| grid.is_wall(grid.position(index)))
+                .count(),
+        }
     }
 
-    /// Calculate optimal path length using A* with current grid state
-    fn calculate_optimal_path_with_astar(grid: &Grid) -> usize {
-        let mut a_star = AStar::new();
-        if let Some(path) = a_star.find_path(grid, grid.start, grid.goal, &HashSet::new()) {
-            path.len().saturating_sub(1)
-        } else {
-            0
+    /// ALT landmark heuristic requested on the command line, if any
+    pub(crate) fn landmark_heuristic(config: &Config) -> Result<Option<LandmarkHeuristic>, String> {
+        let strategy = LandmarkStrategy::from_name(&config.landmark_strategy)?;
+        if config.landmarks == 0 {
+            return Ok(None);
         }
+        Ok(Some(LandmarkHeuristic::new(strategy, config.landmarks)))
     }
 
-    /// Run all algorithms and compare results
-    pub fn run_all_algorithms(config: Config) -> Result<Vec<AlgorithmResult>, String> {
-        // Generate a random seed for this run, but use it consistently across all algorithms
-        let run_seed = rand::random::<u64>();
-        let environment = EnvironmentSetup::generate(&config, Some(run_seed));
-
-        // Define available algorithms
-        let algorithms = [
-            AlgorithmRunner::new("a_star", |_start, _goal, _grid_size| Box::new(AStar::new())),
-            AlgorithmRunner::new("d_star_lite", |start, goal, grid_size| {
-                let mut d_star = DStarLite::new(start, goal, grid_size);
-                d_star.ensure_grid_size(grid_size);
-                Box::new(d_star)
+    /// Mission waypoints from `--waypoints` or `--waypoints-file`, in visiting order
+    fn waypoints(config: &Config) -> Result<Vec<Position>, SimulationError> {
+        let text = match &config.waypoints_file {
+            Some(_) if !config.waypoints.is_empty() => {
+                return Err("Use either --waypoints or --waypoints-file, not both"
+                    .to_string()
+                    .into())
+            }
+            Some(path) => std::fs::read_to_string(path).map_err(|e| {
+                SimulationError::io(format!("Failed to read waypoints file '{}'", path), e)
+            })?,
+            None => config.waypoints.clone(),
+        };
+
+        // "x,y" (or "x,y,floor") entries separated by ';' or newlines; '#' starts a comment
+        Ok(text
+            .lines()
+            .map(|line| line.split('#').next().unwrap_or(""))
+            .flat_map(|line| line.split(';'))
+            .map(str::trim)
+            .filter(|entry| !entry.is_empty())
+            .map(|entry| Self::parse_position(entry, "waypoint"))
+            .collect::<Result<_, String>>()?)
+    }
+
+    /// Portal pairs from `--portal-pairs`, or `None` to keep the generated ones
+    fn portal_pairs(config: &Config) -> Result<Option<Vec<(Position, Position)>>, String> {
+        if config.portal_pairs.trim().is_empty() {
+            return Ok(None);
+        }
+        if config.portals > 0 {
+            return Err("Use either --portals or --portal-pairs, not both".to_string());
+        }
+
+        // "x,y:x,y" entries separated by ';', each cell optionally with ",floor"
+        config
+            .portal_pairs
+            .split(';')
+            .map(str::trim)
+            .filter(|entry| !entry.is_empty())
+            .map(|entry| match entry.split(':').collect::<Vec<_>>()[..] {
+                [a, b] => Ok((
+                    Self::parse_position(a.trim(), "portal")?,
+                    Self::parse_position(b.trim(), "portal")?,
+                )),
+                _ => Err(format!(
+                    "Invalid portal pair '{}' (expected x,y:x,y)",
+                    entry
+                )),
+            })
+            .collect::<Result<Vec<_>, _>>()
+            .map(Some)
+    }
+
+    /// Parse a cell given as "x,y" or "x,y,floor"
+    /// The `--goal-route` cells, in order
+    fn goal_route(config: &Config) -> Result<Vec<Position>, String> {
+        config
+            .goal_route
+            .split(';')
+            .map(str::trim)
+            .filter(|entry| !entry.is_empty())
+            .map(|entry| Self::parse_position(entry, "goal route cell"))
+            .collect()
+    }
+
+    fn parse_position(entry: &str, what: &str) -> Result<Position, String> {
+        let invalid = || format!("Invalid {} '{}' (expected x,y or x,y,floor)", what, entry);
+        let coordinates = entry
+            .split(',')
+            .map(|value| value.trim().parse::<usize>().map_err(|_| invalid()))
+            .collect::<Result<Vec<_>, _>>()?;
+        match coordinates[..] {
+            [x, y] => Ok(Position { x, y, z: 0 }),
+            [x, y, z] => Ok(Position { x, y, z }),
+            _ => Err(invalid()),
+        }
+    }
+
+    /// Hybrid switching policy requested on the command line
+    pub(crate) fn switching_policy(config: &Config) -> Result<SwitchingPolicyKind, String> {
+        match config.switching_policy.as_str() {
+            "threshold" => Ok(SwitchingPolicyKind::Threshold {
+                max_start_jump: config.switch_start_jump,
+                max_obstacle_delta: config.switch_obstacle_delta,
+            }),
+            "time_budget" => Ok(SwitchingPolicyKind::TimeBudget {
+                budget: Duration::from_micros(config.switch_budget_us),
             }),
-            AlgorithmRunner::new("hybrid", |start, goal, grid_size| {
-                Box::new(HybridAStarDStar::new(start, goal, grid_size)) // Pass grid_size
+            "change_rate" => Ok(SwitchingPolicyKind::ChangeRate {
+                max_rate: config.switch_change_rate,
             }),
-        ];
+            _ => Err(format!(
+                "Unknown switching policy: '{}' (expected 'threshold', 'time_budget' or 'change_rate')",
+                config.switching_policy
+            )),
+        }
+    }
 
-        let mut results = Vec::new();
+    /// Calculate the optimal path's length and cost (in straight moves) using A* with the
+    /// current grid state, through every waypoint in order when on a mission
+    /// (0 if any leg is unreachable)
+    pub(crate) fn calculate_optimal_path_with_astar(grid: &Grid) -> (usize, f64) {
+        let mut a_star = AStar::new();
+        let mut from = grid.start;
+        let (mut length, mut cost) = (0, 0.0);
+        for waypoint in &grid.waypoints {
+            match a_star.find_path(grid, from, *waypoint, &HashSet::new()) {
+                Some(path) => {
+                    length += path.len().saturating_sub(1);
+                    cost += grid.path_cost(&path);
+                }
+                None => return (0, 0.0),
+            }
+            from = *waypoint;
+        }
+        if let Some(path) = a_star.find_path_to_any(grid, from, &grid.goals(), &HashSet::new()) {
+            (
+                length + path.len().saturating_sub(1),
+                cost + grid.path_cost(&path),
+            )
+        } else {
+            (0, 0.0)
+        }
+    }
 
-        // Create grid for all algorithms to use
-        let grid = environment.create_grid();
+    /// The dynamic baseline with `--dynamic-baseline`, 0 without it. Its search
+    /// gives up (also with 0) at the oracle's state limit, when `abort` is aborted
+    /// or once `--simulation-timeout-seconds` passes.
+    fn dynamic_optimal_cost(
+        config: &Config,
+        grid: &Grid,
+        schedule: &Arc<ObstacleSchedule>,
+        abort: &AbortHandle,
+    ) -> f64 {
+        if !config.dynamic_baseline {
+            return 0.0;
+        }
+        let abort = abort.child();
+        if let Some(seconds) = config.simulation_timeout_seconds {
+            abort.abort_at(Instant::now() + Duration::from_secs_f64(seconds));
+        }
+        Self::calculate_dynamic_optimal_cost(grid, schedule, abort)
+    }
 
-        // Calculate optimal path using A* (no obstacles, only walls)
-        let optimal_path_length = Self::calculate_optimal_path_with_astar(&grid);
+    /// Cost (in straight moves, a wait counting one) of the best run through every
+    /// waypoint to the goal when every obstacle in `schedule` is known ahead, planned
+    /// leg by leg with space-time A* from step 0 (0 if any leg is unreachable or its
+    /// search gave up)
+    pub(crate) fn calculate_dynamic_optimal_cost(
+        grid: &Grid,
+        schedule: &Arc<ObstacleSchedule>,
+        abort: AbortHandle,
+    ) -> f64 {
+        let mut oracle = SpaceTimeAStar::new();
+        oracle.set_abort_handle(abort);
+        let (mut from, mut step, mut cost) = (grid.start, 0, 0.0);
+        let legs = grid.waypoints.iter().map(|&waypoint| vec![waypoint]);
+        for targets in legs.chain(std::iter::once(grid.goals())) {
+            oracle.update_obstacle_schedule(schedule, step);
+            let Some(path) = oracle.find_path_to_any(grid, from, &targets, &HashSet::new()) else {
+                return 0.0;
+            };
+            step += path.len() - 1;
+            cost += grid.path_cost(&path);
+            from = path[path.len() - 1];
+        }
+        cost
+    }
 
-        if optimal_path_length == 0 {
-            return Err(format!(
-                "No valid path exists from start {:?} to goal {:?}! Grid has {} walls.",
-                grid.start,
-                grid.goal,
-                grid.cells
-                    .iter()
-                    .flatten()
-                    .filter(|&cell| *cell == Cell::Wall)
-                    .count()
-            ));
+    /// The other agents of a multi-agent run, on the environment's routes, each with
+    /// a sensor seeded apart from the others'
+    fn create_companions(
+        config: &Config,
+        environment: &EnvironmentSetup,
+        registry: &AlgorithmRegistry,
+        grid: &Grid,
+    ) -> Result<Vec<Companion>, ConfigError> {
+        if environment.agent_routes.len() + 1 < config.agents {
+            return Err(ConfigError::Invalid(format!(
+                "Only found room for {} of {} agents",
+                environment.agent_routes.len() + 1,
+                config.agents
+            )));
+        }
+        environment
+            .agent_routes
+            .iter()
+            .enumerate()
+            .map(|(i, &(start, goal))| {
+                let optimal_path_cost = AStar::new()
+                    .find_path(grid, start, goal, &HashSet::new())
+                    .map_or(0.0, |path| grid.path_cost(&path));
+                Companion::new(
+                    i + 1,
+                    config,
+                    registry,
+                    grid,
+                    (start, goal),
+                    optimal_path_cost,
+                    environment.sensor_seed.wrapping_add(i as u64 + 1),
+                )
+            })
+            .collect()
+    }
+
+    /// Reservation table or conflict search for the agents to plan through, neither
+    /// when they plan independently
+    fn coordinators(
+        config: &Config,
+    ) -> Result<(Option<ReservationTable>, Option<ConflictSearch>), ConfigError> {
+        Ok(match Coordination::from_config(config)? {
+            Coordination::Independent => (None, None),
+            Coordination::Whca { window } => (Some(ReservationTable::new(window)), None),
+            Coordination::Cbs { node_limit } => (None, Some(ConflictSearch::new(node_limit))),
+        })
+    }
+
+    /// The pursuer on the environment's pursuer start, with `--pursuer`
+    fn create_pursuer(
+        config: &Config,
+        environment: &EnvironmentSetup,
+        registry: &AlgorithmRegistry,
+        grid: &Grid,
+    ) -> Result<Option<Pursuer>, ConfigError> {
+        if !config.pursuer {
+            return Ok(None);
         }
+        let start = environment.pursuer_start.ok_or_else(|| {
+            ConfigError::Invalid("Found no room for the pursuer away from the agent".to_string())
+        })?;
+        Pursuer::new(config, registry, grid, start).map(Some)
+    }
 
-        for algorithm_runner in algorithms.iter() {
-            // Create a new config for this algorithm run (no visualization)
-            let mut algorithm_config = config.clone();
-            algorithm_config.no_visualization = true;
-            algorithm_config.algorithm = algorithm_runner.name.clone();
+    /// Run all algorithms and compare results
+    pub fn run_all_algorithms(config: Config) -> Result<Vec<AlgorithmResult>, SimulationError> {
+        Self::run_all_algorithms_with_registry(config, Arc::new(AlgorithmRegistry::with_builtins()))
+    }
 
-            // Create simulation with the shared environment
-            match Self::new_with_environment_and_algorithm(
-                algorithm_config,
-                environment.clone(),
-                (algorithm_runner.create_algorithm)(environment.start, environment.goal, grid.size),
-                optimal_path_length,
-                &grid,
-            ) {
-                Ok(mut simulation) => {
-                    // Run the simulation
-                    let (statistics, algorithm_stats, timing_data) = simulation.run();
-                    let success = simulation.agent.is_at_goal(simulation.grid.goal);
-                    let final_position = simulation.agent.position;
-
-                    results.push(AlgorithmResult {
-                        name: algorithm_runner.name.clone(),
-                        statistics,
-                        algorithm_stats,
-                        timing_data,
-                        success,
-                        final_position,
-                    });
-                }
-                Err(e) => {
-                    // Handle simulation creation failure
-                    if !config.quiet {
-                        println!(
-                            "Failed to create simulation for {}: {}",
-                            algorithm_runner.name, e
-                        );
-                    }
+    /// Run every algorithm in the registry on the same environment
+    pub fn run_all_algorithms_with_registry(
+        config: Config,
+        registry: Arc<AlgorithmRegistry>,
+    ) -> Result<Vec<AlgorithmResult>, SimulationError> {
+        // Generate a random seed for this run (unless one is given), but use it
+        // consistently across all algorithms
+        let run_seed = config.seed.unwrap_or_else(rand::random::<u64>);
+        Self::run_all_algorithms_with_seed(config, registry, run_seed)
+    }
+
+    /// Run every algorithm in the registry on the environment generated from `run_seed`
+    pub fn run_all_algorithms_with_seed(
+        config: Config,
+        registry: Arc<AlgorithmRegistry>,
+        run_seed: u64,
+    ) -> Result<Vec<AlgorithmResult>, SimulationError> {
+        let environment = EnvironmentSetup::from_config(&config, Some(run_seed))?;
+        Self::run_all_algorithms_on(config, registry, environment)
+    }
+
+    /// Run every algorithm in the registry on the given environment (e.g. a scenario's)
+    pub fn run_all_algorithms_on(
+        config: Config,
+        registry: Arc<AlgorithmRegistry>,
+        environment: EnvironmentSetup,
+    ) -> Result<Vec<AlgorithmResult>, SimulationError> {
+        Self::run_all_algorithms_with_abort(config, registry, environment, &AbortHandle::new())
+    }
+
+    /// `run_all_algorithms_on`, each run aborted (and timed out) along with `abort`.
+    /// The runs share out the CPU cores, each on its own copy of the environment,
+    /// and their results come back in the registry's order however they finish.
+    pub fn run_all_algorithms_with_abort(
+        config: Config,
+        registry: Arc<AlgorithmRegistry>,
+        environment: EnvironmentSetup,
+        abort: &AbortHandle,
+    ) -> Result<Vec<AlgorithmResult>, SimulationError> {
+        let comparison = Comparison::prepare(&config, environment, abort)?;
+        // Build every algorithm up front so invalid options fail the whole run;
+        // each run builds its own again on the thread it runs on
+        let context = comparison.context(&config);
+        for name in registry.names() {
+            registry.create(name, &context)?;
+        }
 
-                    let failed_result = AlgorithmResult {
-                        name: algorithm_runner.name.clone(),
-                        statistics: Statistics::new(config.num_walls, config.num_obstacles, 0),
-                        algorithm_stats: AlgorithmStats::AStar(0),
-                        timing_data: TimingData::new(),
-                        success: false,
-                        final_position: grid.start,
+        let names: Vec<String> = registry.names().map(str::to_string).collect();
+        // Debug logs of runs going at once would be interleaved
+        let workers = match tracing::enabled!(tracing::Level::DEBUG) {
+            true => 1,
+            false => thread::available_parallelism().map_or(1, NonZeroUsize::get),
+        }
+        .min(names.len());
+        let next = AtomicUsize::new(0);
+        let (sender, receiver) = mpsc::channel();
+        thread::scope(|scope| {
+            for _ in 0..workers {
+                let sender = sender.clone();
+                let (comparison, config, registry) = (&comparison, &config, &registry);
+                let (names, next) = (&names, &next);
+                scope.spawn(move || loop {
+                    let index = next.fetch_add(1, Ordering::Relaxed);
+                    let Some(name) = names.get(index) else {
+                        break;
                     };
-                    results.push(failed_result);
-                }
+                    let _ = sender.send((index, comparison.run(config, registry, name, abort)));
+                });
             }
-        }
+        });
+        drop(sender);
+
+        // Setup failures are warned about here, in order too
+        let mut finished: Vec<(usize, Result<AlgorithmResult, String>)> =
+            receiver.into_iter().collect();
+        finished.sort_by_key(|&(index, _)| index);
+        Ok(finished
+            .into_iter()
+            .zip(names)
+            .map(|((_, result), name)| {
+                result.unwrap_or_else(|e| {
+                    AlgorithmResult::not_started(name, &config, comparison.start(), &e)
+                })
+            })
+            .collect())
+    }
+
+    /// `run_all_algorithms_with_abort` on the calling thread, one algorithm after
+    /// the other, for callers that run comparisons on threads of their own
+    pub fn run_all_algorithms_in_turn(
+        config: Config,
+        registry: Arc<AlgorithmRegistry>,
+        environment: EnvironmentSetup,
+        abort: &AbortHandle,
+    ) -> Result<Vec<AlgorithmResult>, SimulationError> {
+        let start = environment.start;
+        let comparison = Comparison::prepare(&config, environment, abort)?;
+        let simulations = comparison.simulations(&config, &registry)?;
+        Ok(simulations
+            .into_iter()
+            .map(|(name, simulation)| match simulation {
+                Ok(mut simulation) => {
+                    simulation.set_abort_handle(abort.child());
+                    let results = simulation.run();
+                    AlgorithmResult::finished(name, &simulation, results)
+                }
+                Err(e) => AlgorithmResult::not_started(name, &config, start, &e.to_string()),
+            })
+            .collect())
+    }
 
-        Ok(results)
+    /// A simulation of every algorithm in the registry on the given environment, in
+    /// the registry's order, each under its name, or why it could not be set up. The
+    /// simulations draw nothing; they are run by `run_all_algorithms_on`, or in
+    /// lockstep by the compare command's `--side-by-side`.
+    pub fn comparison_simulations(
+        config: &Config,
+        registry: Arc<AlgorithmRegistry>,
+        environment: EnvironmentSetup,
+    ) -> Result<Vec<ComparisonRun>, SimulationError> {
+        Comparison::prepare(config, environment, &AbortHandle::new())?
+            .simulations(config, &registry)
     }
 
     /// Create simulation with specific environment and algorithm
     pub fn new_with_environment_and_algorithm(
         config: Config,
         environment: EnvironmentSetup,
-        algorithm: Box<dyn PathfindingAlgorithm>,
-        optimal_path_length: usize,
+        mut algorithm: Box<dyn PathfindingAlgorithm>,
+        registry: Arc<AlgorithmRegistry>,
+        baseline: Baseline,
+        obstacle_schedule: Arc<ObstacleSchedule>,
         grid: &Grid,
-    ) -> Result<Self, String> {
-        let agent = Agent::new(grid.start);
+    ) -> Result<Self, SimulationError> {
+        let abort = AbortHandle::new();
+        let planning = abort.child();
+        algorithm.set_abort_handle(planning.clone());
+        let agent = Agent::new(grid.start)
+            .with_vision(Vision::from_config(&config)?)
+            .with_knowledge(Knowledge::from_name(&config.knowledge)?, grid)
+            .with_memory(Memory::from_config(&config)?)
+            .with_noise(SensorNoise::from_config(&config)?, environment.sensor_seed);
+        let companions = Self::create_companions(&config, &environment, &registry, grid)?;
+        let (reservations, conflict_search) = Self::coordinators(&config)?;
+        let pursuer = Self::create_pursuer(&config, &environment, &registry, grid)?;
         let sim_grid = grid.clone();
+        let stats = Self::initial_statistics(&config, baseline);
 
+        let observers = Self::default_observers(&config);
+        let span = Self::run_span(&config);
         Ok(Simulation {
             grid: sim_grid,
             agent,
             algorithm,
+            registry,
             config,
-            optimal_path_length,
+            optimal_path_length: baseline.optimal_path_length,
+            obstacle_schedule,
             environment,
             active_obstacle_groups: Vec::new(),
+            expired_obstacles: HashSet::new(),
             cycles_since_last_obstacle: 0,
             current_obstacle_cycle: 0,
+            current_goal_cycle: 0,
+            current_leg: 0,
+            wall_events_applied: Vec::new(),
+            movers: Vec::new(),
+            companions,
+            agent_previous: grid.start,
+            reservations,
+            conflict_search,
+            pursuer,
+            stats,
+            timing_data: TimingData::new(),
+            planner_calls: 0,
+            steps: 0,
+            stuck_attempts: 0,
+            started: false,
+            outcome: None,
+            observers,
+            abort,
+            planning,
+            span,
+            run_time: Duration::ZERO,
         })
     }
 
@@ -723,53 +3026,71 @@ impl Simulation {
 
         // Print header
         println!(
-            "{:<15} {:<8} {:<8} {:<8} {:<12} {:<15} {:<15} {:<15} {:<15} {:<20}",
+            "{:<17} {:<8} {:<8} {:<8} {:<12} {:<12} {:<15} {:<15} {:<15} {:<15} {:<10} {:<20}",
             "Algorithm",
             "Success",
             "Moves",
             "Optimal",
             "Efficiency",
-            "Avg Observe",
+            "Dynamic Eff",
             "Avg Find Path",
+            "p99 Find Path",
             "Path Recalcs",
             "Final Position",
+            "Expanded",
             "Algorithm Usage"
         );
-        println!("{}", "-".repeat(140));
+        println!("{}", "-".repeat(166));
 
         // Print results for each algorithm
         for result in results {
             let success_str = if result.success { "✓" } else { "✗" };
             let efficiency_str = format!("{:.3}", result.statistics.route_efficiency);
+            // Runs with no dynamic baseline show a dash too
+            let dynamic_efficiency_str = match result.statistics.dynamic_optimal_cost > 0.0 {
+                true => format!("{:.3}", result.statistics.dynamic_route_efficiency),
+                false => "-".to_string(),
+            };
             let final_pos_str =
                 format!("({},{})", result.final_position.x, result.final_position.y);
 
-            let usage_str = match &result.algorithm_stats {
-                AlgorithmStats::AStar(_) => format!("{} calls", result.timing_data.total_calls()),
-                AlgorithmStats::DStarLite(_) => {
-                    format!("{} calls", result.timing_data.total_calls())
-                }
-                AlgorithmStats::Hybrid {
-                    a_star_calls,
-                    d_star_calls,
-                } => {
-                    format!("A*:{} D*:{}", a_star_calls, d_star_calls)
-                }
+            let metrics = &result.planner_metrics;
+            let mut usage_str = if metrics.delegated_calls.is_empty() {
+                format!("{} calls", metrics.calls)
+            } else {
+                let usage: Vec<String> = metrics
+                    .delegated_calls
+                    .iter()
+                    .map(|(planner, calls)| format!("{}:{}", planner, calls))
+                    .collect();
+                usage.join(" ")
             };
+            if let Some(headline) = metrics.headline() {
+                usage_str.push_str(&format!(" ({})", headline));
+            }
 
             let avg_find_path_str = format!("{:.2?}", result.timing_data.average_find_path_time());
+            let p99_find_path_str = format!("{:.2?}", result.timing_data.percentiles().p99);
             let path_recalcs_str = format!("{}", result.timing_data.total_calls());
+            // Planners that do not count their work show a dash rather than 0
+            let expanded_str = match result.planner_metrics.search.nodes_expanded {
+                0 => "-".to_string(),
+                expanded => expanded.to_string(),
+            };
 
             println!(
-                "{:<15} {:<8} {:<8} {:<8} {:<12} {:<15} {:<15} {:<15} {:<20}",
+                "{:<17} {:<8} {:<8} {:<8} {:<12} {:<12} {:<15} {:<15} {:<15} {:<15} {:<10} {:<20}",
                 result.name,
                 success_str,
                 result.statistics.total_moves,
                 result.statistics.optimal_path_length,
                 efficiency_str,
+                dynamic_efficiency_str,
                 avg_find_path_str,
+                p99_find_path_str,
                 path_recalcs_str,
                 final_pos_str,
+                expanded_str,
                 usage_str
             );
         }
@@ -779,6 +3100,42 @@ impl Simulation {
         println!("=== PERFORMANCE ANALYSIS ===");
 
         let successful_algorithms: Vec<_> = results.iter().filter(|r| r.success).collect();
+        let out_of_energy: Vec<_> = results
+            .iter()
+            .filter(|r| r.statistics.out_of_energy)
+            .map(|r| r.name.as_str())
+            .collect();
+        if !out_of_energy.is_empty() {
+            println!("Ran out of energy: {}", out_of_energy.join(", "));
+        }
+        let timed_out: Vec<_> = results
+            .iter()
+            .filter(|r| r.statistics.timed_out)
+            .map(|r| r.name.as_str())
+            .collect();
+        if !timed_out.is_empty() {
+            println!("Timed out: {}", timed_out.join(", "));
+        }
+        let over_budget: Vec<_> = results
+            .iter()
+            .filter(|r| r.statistics.budget_overruns > 0)
+            .map(|r| format!("{} ({})", r.name, r.statistics.budget_overruns))
+            .collect();
+        if !over_budget.is_empty() {
+            println!(
+                "Replans over the find_path budget: {}",
+                over_budget.join(", ")
+            );
+        }
+        for result in results.iter().filter(|r| !r.statistics.agents.is_empty()) {
+            println!(
+                "{}: {}/{} agents at their goals, {} collisions avoided",
+                result.name,
+                result.statistics.agents_reached(result.success),
+                result.statistics.agents.len() + 1,
+                result.statistics.total_agent_collisions()
+            );
+        }
 
         if !successful_algorithms.is_empty() {
             // Find best performing algorithm by different metrics
@@ -807,6 +3164,11 @@ impl Simulation {
                 .min_by_key(|r| r.timing_data.average_find_path_time())
                 .unwrap();
 
+            let fewest_expansions = successful_algorithms
+                .iter()
+                .filter(|r| r.planner_metrics.search.nodes_expanded > 0)
+                .min_by_key(|r| r.planner_metrics.search.nodes_expanded);
+
             println!(
                 "Best by total moves: {} ({} moves)",
                 best_moves.name, best_moves.statistics.total_moves
@@ -825,6 +3187,12 @@ impl Simulation {
                 fastest_avg_recalc.name,
                 fastest_avg_recalc.timing_data.average_find_path_time()
             );
+            if let Some(fewest_expansions) = fewest_expansions {
+                println!(
+                    "Fewest nodes expanded: {} ({} nodes)",
+                    fewest_expansions.name, fewest_expansions.planner_metrics.search.nodes_expanded
+                );
+            }
 
             // Show path recalculation comparison
             println!();
@@ -848,16 +3216,18 @@ impl Simulation {
             println!();
             println!("=== HYBRID ALGORITHM BREAKDOWN ===");
             for result in results {
-                if let AlgorithmStats::Hybrid {
-                    a_star_calls,
-                    d_star_calls,
-                } = &result.algorithm_stats
-                {
+                let metrics = &result.planner_metrics;
+                if let Some(policy) = metrics.text("switching_policy") {
+                    let a_star_calls = metrics.calls_of("A*");
+                    let d_star_calls = metrics.calls_of("D* Lite");
                     let total_calls = a_star_calls + d_star_calls;
                     if total_calls > 0 {
-                        let a_star_pct = (*a_star_calls as f64 / total_calls as f64) * 100.0;
-                        let d_star_pct = (*d_star_calls as f64 / total_calls as f64) * 100.0;
-                        println!("{}: {} total calls", result.name, total_calls);
+                        let a_star_pct = (a_star_calls as f64 / total_calls as f64) * 100.0;
+                        let d_star_pct = (d_star_calls as f64 / total_calls as f64) * 100.0;
+                        println!(
+                            "{}: {} total calls ({} switching policy)",
+                            result.name, total_calls, policy
+                        );
                         println!("  • A* usage: {} calls ({:.1}%)", a_star_calls, a_star_pct);
                         println!(
                             "  • D* Lite usage: {} calls ({:.1}%)",
@@ -865,27 +3235,192 @@ impl Simulation {
                         );
 
                         // Performance analysis
-                        if *a_star_calls == 1 && *d_star_calls > 0 {
+                        if a_star_calls == 1 && d_star_calls > 0 {
                             println!("  ✓ Optimal hybrid performance: A* used once for initial path, D* Lite handled updates");
-                        } else if *a_star_calls > 1 {
+                        } else if a_star_calls > 1 {
                             println!("  ⚠ Multiple A* calls: {} - indicates significant environment changes", a_star_calls);
-                        } else if *d_star_calls == 0 {
+                        } else if d_star_calls == 0 {
                             println!("  ⚠ Only A* used - no incremental updates occurred");
                         }
                     }
                 }
             }
+
+            // Weighted A* suboptimality versus plain A*
+            let a_star_result = results.iter().find(|r| r.name == "a_star" && r.success);
+            for result in results {
+                if let Some(weight) = result.planner_metrics.number("heuristic_weight") {
+                    println!();
+                    println!("=== WEIGHTED A* ANALYSIS ===");
+                    println!(
+                        "{}: suboptimality bound {:.2}x optimal",
+                        result.name, weight
+                    );
+                    if !result.success {
+                        println!("  ✗ Did not reach the goal");
+                        continue;
+                    }
+
+                    let optimal = result.statistics.optimal_path_length;
+                    let moves = result.statistics.total_moves;
+                    if optimal > 0 {
+                        println!(
+                            "  • Moves vs optimal: {} vs {} ({:.3}x)",
+                            moves,
+                            optimal,
+                            moves as f64 / optimal as f64
+                        );
+                    }
+                    if let Some(a_star) = a_star_result {
+                        let difference = moves as i64 - a_star.statistics.total_moves as i64;
+                        println!(
+                            "  • Moves vs plain A*: {} vs {} ({:+} moves)",
+                            moves, a_star.statistics.total_moves, difference
+                        );
+                        println!(
+                            "  • Avg find_path vs plain A*: {:.2?} vs {:.2?}",
+                            result.timing_data.average_find_path_time(),
+                            a_star.timing_data.average_find_path_time()
+                        );
+                    }
+                }
+            }
+
+            // HPA* abstraction maintenance cost versus planning time saved
+            for result in results {
+                let metrics = &result.planner_metrics;
+                if let (Some(cluster_size), Some(build_time), Some(rebuilds), Some(rebuild_time)) = (
+                    metrics.count("cluster_size"),
+                    metrics.duration("build_time"),
+                    metrics.count("rebuilds"),
+                    metrics.duration("rebuild_time"),
+                ) {
+                    println!();
+                    println!("=== HPA* ANALYSIS ===");
+                    println!(
+                        "{}: {}x{} clusters, abstraction built in {:.2?}",
+                        result.name, cluster_size, cluster_size, build_time
+                    );
+                    if rebuilds > 0 {
+                        println!(
+                            "  • Rebuilds after obstacle changes: {} ({:.2?} total, {:.2?} avg)",
+                            rebuilds,
+                            rebuild_time,
+                            rebuild_time / rebuilds as u32
+                        );
+                    } else {
+                        println!("  • No rebuilds - obstacles never changed the known map");
+                    }
+                    if let Some(a_star) = a_star_result {
+                        println!(
+                            "  • Avg find_path vs plain A*: {:.2?} vs {:.2?}",
+                            result.timing_data.average_find_path_time(),
+                            a_star.timing_data.average_find_path_time()
+                        );
+                    }
+                }
+            }
+
+            // Waypoint missions: moves and replans per leg
+            if results.iter().any(|r| r.statistics.legs.len() > 1) {
+                println!();
+                println!("=== MISSION LEG BREAKDOWN ===");
+                for result in results {
+                    let legs: Vec<String> = result
+                        .statistics
+                        .legs
+                        .iter()
+                        .map(|leg| {
+                            format!(
+                                "{}m/{}r{}",
+                                leg.moves,
+                                leg.replans,
+                                if leg.reached { "" } else { "✗" }
+                            )
+                        })
+                        .collect();
+                    println!("{}: {}", result.name, legs.join(" | "));
+                }
+                println!("(moves/replans per leg, ✗ = leg not completed)");
+            }
+
+            // Path smoothing: planned moves before and after line-of-sight shortcutting
+            if results
+                .iter()
+                .any(|r| r.statistics.smoothed_path_length < r.statistics.raw_path_length)
+            {
+                println!();
+                println!("=== PATH SMOOTHING ANALYSIS ===");
+                for result in results {
+                    println!(
+                        "{}: {} planned moves raw, {} smoothed ({:.1}% shorter)",
+                        result.name,
+                        result.statistics.raw_path_length,
+                        result.statistics.smoothed_path_length,
+                        result.statistics.smoothing_reduction() * 100.0
+                    );
+                }
+            }
         } else {
             println!("No algorithms successfully reached the goal.");
         }
     }
 }
 
+/// Sub-buckets each power of two of the find_path time histogram is split into,
+/// which keeps the width of a bucket within a quarter of its times as in an HDR
+/// histogram
+const HISTOGRAM_SUB_BUCKETS: u64 = 4;
+
+/// Width of the longest bar of a printed histogram, in characters
+const HISTOGRAM_WIDTH: usize = 40;
+
 #[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct TimingData {
     pub find_path_times: Vec<Duration>,
 }
 
+/// Spread of the find_path times of a run: the fastest, median and slowest calls and
+/// the 95th and 99th percentiles, which show the rare slow replans an average hides
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TimingPercentiles {
+    pub min: Duration,
+    pub median: Duration,
+    pub p95: Duration,
+    pub p99: Duration,
+    pub max: Duration,
+}
+
+impl fmt::Display for TimingPercentiles {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "min {:.2?}, median {:.2?}, p95 {:.2?}, p99 {:.2?}, max {:.2?}",
+            self.min, self.median, self.p95, self.p99, self.max
+        )
+    }
+}
+
+/// Histogram bucket of a time in nanoseconds: one per value below
+/// `HISTOGRAM_SUB_BUCKETS`, then `HISTOGRAM_SUB_BUCKETS` per power of two
+fn histogram_bucket(nanos: u64) -> u64 {
+    if nanos < HISTOGRAM_SUB_BUCKETS {
+        return nanos;
+    }
+    let shift = nanos.ilog2() - HISTOGRAM_SUB_BUCKETS.ilog2();
+    HISTOGRAM_SUB_BUCKETS * (shift as u64 + 1) + ((nanos >> shift) - HISTOGRAM_SUB_BUCKETS)
+}
+
+/// Smallest time in nanoseconds of a histogram bucket
+fn histogram_bucket_start(bucket: u64) -> u64 {
+    if bucket < HISTOGRAM_SUB_BUCKETS {
+        return bucket;
+    }
+    let shift = bucket / HISTOGRAM_SUB_BUCKETS - 1;
+    (HISTOGRAM_SUB_BUCKETS + bucket % HISTOGRAM_SUB_BUCKETS) << shift
+}
+
 impl TimingData {
     pub fn new() -> Self {
         Self::default()
@@ -903,4 +3438,66 @@ impl TimingData {
     pub fn total_calls(&self) -> usize {
         self.find_path_times.len()
     }
+
+    /// Spread of the find_path times, by the nearest-rank method (all zero without
+    /// any call)
+    pub fn percentiles(&self) -> TimingPercentiles {
+        let mut sorted = self.find_path_times.clone();
+        sorted.sort_unstable();
+        let Some(&max) = sorted.last() else {
+            return TimingPercentiles::default();
+        };
+        let rank = |fraction: f64| {
+            let rank = (fraction * sorted.len() as f64).ceil() as usize;
+            sorted[rank.clamp(1, sorted.len()) - 1]
+        };
+        TimingPercentiles {
+            min: sorted[0],
+            median: rank(0.5),
+            p95: rank(0.95),
+            p99: rank(0.99),
+            max,
+        }
+    }
+
+    /// The find_path times in log-linear buckets, from the fastest call's bucket to
+    /// the slowest's: the smallest time of each bucket and the calls in it
+    pub fn histogram(&self) -> Vec<(Duration, usize)> {
+        let mut counts: BTreeMap<u64, usize> = BTreeMap::new();
+        for time in &self.find_path_times {
+            *counts
+                .entry(histogram_bucket(time.as_nanos() as u64))
+                .or_default() += 1;
+        }
+        let (Some((&first, _)), Some((&last, _))) =
+            (counts.first_key_value(), counts.last_key_value())
+        else {
+            return Vec::new();
+        };
+        (first..=last)
+            .map(|bucket| {
+                (
+                    Duration::from_nanos(histogram_bucket_start(bucket)),
+                    counts.get(&bucket).copied().unwrap_or(0),
+                )
+            })
+            .collect()
+    }
+
+    /// Print the histogram of the find_path times as bars
+    pub fn print_histogram(&self) {
+        let histogram = self.histogram();
+        let most = histogram.iter().map(|&(_, count)| count).max().unwrap_or(0);
+        for (start, count) in histogram {
+            // Rounded up, so a bucket with any call shows
+            let width = (count * HISTOGRAM_WIDTH).div_ceil(most.max(1));
+            println!(
+                "  >= {:>10} | {:<width$} {}",
+                format!("{:.2?}", start),
+                "█".repeat(width),
+                count,
+                width = HISTOGRAM_WIDTH
+            );
+        }
+    }
 }
@@ -0,0 +1,289 @@
+use crate::algorithms::common::PathfindingAlgorithm;
+use crate::algorithms::registry::AlgorithmRegistry;
+use crate::config::Config;
+use crate::error::SimulationError;
+use crate::grid::{Cell, Grid, Position};
+use crate::knowledge::{Knowledge, Memory};
+use crate::map_file::MapLayout;
+use crate::observer::SimulationObserver;
+use crate::obstacle_model::ObstacleModel;
+use crate::simulation::{EnvironmentSetup, Simulation};
+use crate::vision::VisionShape;
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Where the simulation's environment comes from
+enum World {
+    /// Generated from the options, on a grid of `size` and `walls` walls
+    Generated,
+    /// The walls, terrain, portals, start and goals of a grid, with obstacles
+    /// generated from the options
+    Grid(Box<Grid>),
+    /// An environment made beforehand
+    Environment(Box<EnvironmentSetup>),
+}
+
+/// Sets up a `Simulation` in code, one setting at a time, for uses of the library
+/// that have no command line to parse options from:
+///
+/// ```no_run
+/// use dynamic_pathfinding::prelude::*;
+///
+/// let mut simulation = SimulationBuilder::new()
+///     .size(40, 30)
+///     .walls(150)
+///     .obstacles(8)
+///     .algorithm("d_star_lite")
+///     .vision_radius(4)
+///     .seed(7)
+///     .build()?;
+/// let (statistics, _, _) = simulation.run();
+/// # Ok::<(), SimulationError>(())
+/// ```
+///
+/// Settings left out keep the defaults of the command line. A built simulation
+/// draws nothing: it tells only the observers given to `observer`. `configure`
+/// reaches the options the builder has no method for.
+pub struct SimulationBuilder {
+    config: Config,
+    world: World,
+    planner: Option<Box<dyn PathfindingAlgorithm>>,
+    registry: Option<Arc<AlgorithmRegistry>>,
+    observers: Vec<Box<dyn SimulationObserver>>,
+}
+
+impl Default for SimulationBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SimulationBuilder {
+    pub fn new() -> Self {
+        SimulationBuilder {
+            config: Config {
+                no_visualization: true,
+                quiet: true,
+                ..Config::default()
+            },
+            world: World::Generated,
+            planner: None,
+            registry: None,
+            observers: Vec::new(),
+        }
+    }
+
+    /// Generate a grid `width` cells wide and `height` tall
+    pub fn size(mut self, width: usize, height: usize) -> Self {
+        self.config.grid_width = Some(width);
+        self.config.grid_height = Some(height);
+        self
+    }
+
+    /// Walls placed on a generated grid
+    pub fn walls(mut self, walls: usize) -> Self {
+        self.config.num_walls = walls;
+        self
+    }
+
+    /// Run on the walls, terrain, portals, start, goals and waypoints of `grid`
+    /// instead of a generated grid, taking its movement rules with it. The obstacles
+    /// are still generated.
+    pub fn grid(mut self, grid: Grid) -> Self {
+        self.world = World::Grid(Box::new(grid));
+        self
+    }
+
+    /// Run on an environment made beforehand, such as one from
+    /// `EnvironmentSetup::from_config` or a file
+    pub fn environment(mut self, environment: EnvironmentSetup) -> Self {
+        self.world = World::Environment(Box::new(environment));
+        self
+    }
+
+    /// Plan with a new planner of the algorithm registered as `name`
+    pub fn algorithm(mut self, name: &str) -> Self {
+        self.config.algorithm = name.to_string();
+        self.planner = None;
+        self
+    }
+
+    /// Plan with `planner`, reported under `name`. Planners that keep state between
+    /// calls should be new, made for this grid.
+    pub fn planner(mut self, name: &str, planner: Box<dyn PathfindingAlgorithm>) -> Self {
+        self.config.algorithm = name.to_string();
+        self.planner = Some(planner);
+        self
+    }
+
+    /// Look algorithms up (those of the other agents and the pursuer too) in
+    /// `registry` rather than among the built-in ones only
+    pub fn registry(mut self, registry: Arc<AlgorithmRegistry>) -> Self {
+        self.registry = Some(registry);
+        self
+    }
+
+    /// Cells the agent sees obstacles within
+    pub fn vision_radius(mut self, radius: usize) -> Self {
+        self.config.vision_radius = Some(radius);
+        self
+    }
+
+    /// Which cells within the vision radius the agent sees
+    pub fn vision_shape(mut self, shape: VisionShape) -> Self {
+        self.config.vision_shape = shape.name().to_string();
+        self
+    }
+
+    /// What the agent knows from the start
+    pub fn knowledge(mut self, knowledge: Knowledge) -> Self {
+        self.config.knowledge = knowledge.name().to_string();
+        self
+    }
+
+    /// How the agent forgets obstacles it has seen
+    pub fn memory(mut self, memory: Memory) -> Self {
+        self.config.memory = memory.name().to_string();
+        match memory {
+            Memory::Ttl(steps) => self.config.memory_ttl = steps,
+            Memory::Decay(rate) => self.config.memory_decay = rate,
+            Memory::Forever | Memory::Reobserve => {}
+        }
+        self
+    }
+
+    /// Travel cost the agent can spend before it gives up
+    pub fn energy_budget(mut self, budget: f64) -> Self {
+        self.config.energy_budget = Some(budget);
+        self
+    }
+
+    /// Time the planner has for each replan, after which it gives the search up and
+    /// the agent keeps to its previous path
+    pub fn find_path_budget(mut self, budget: Duration) -> Self {
+        self.config.find_path_budget_ms = Some(budget.as_secs_f64() * 1000.0);
+        self
+    }
+
+    /// 8-connected movement with octile costs
+    pub fn diagonal_movement(mut self, diagonal: bool) -> Self {
+        self.config.diagonal_movement = diagonal;
+        self
+    }
+
+    /// Obstacles in each group of random obstacles
+    pub fn obstacles(mut self, obstacles: usize) -> Self {
+        self.config.num_obstacles = obstacles;
+        self
+    }
+
+    /// Where the groups of random obstacles appear
+    pub fn obstacle_model(mut self, model: ObstacleModel) -> Self {
+        self.config.obstacle_model = model.name().to_string();
+        self
+    }
+
+    /// A new group of obstacles every `interval` steps, each staying `persistence`
+    /// intervals
+    pub fn obstacle_timing(mut self, interval: usize, persistence: usize) -> Self {
+        self.config.obstacle_interval = interval;
+        self.config.obstacle_persistence = persistence;
+        self
+    }
+
+    /// Seed of the generated grid, obstacles and sensor noise, so runs repeat
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.config.seed = Some(seed);
+        self
+    }
+
+    /// Tell `observer` about the run, after the observers added before it
+    pub fn observer(mut self, observer: Box<dyn SimulationObserver>) -> Self {
+        self.observers.push(observer);
+        self
+    }
+
+    /// Change any other option, by its field on `Config`
+    pub fn configure(mut self, change: impl FnOnce(&mut Config)) -> Self {
+        change(&mut self.config);
+        self
+    }
+
+    /// Set the simulation up, failing as `Simulation::new` does for options that do
+    /// not go together and grids without a way to the goal
+    pub fn build(self) -> Result<Simulation, SimulationError> {
+        let SimulationBuilder {
+            mut config,
+            world,
+            planner,
+            registry,
+            observers,
+        } = self;
+        let environment = match world {
+            World::Generated => None,
+            World::Environment(environment) => Some(*environment),
+            World::Grid(grid) => Some(Self::environment_on(&mut config, &grid)?),
+        };
+        let registry = registry.unwrap_or_else(|| Arc::new(AlgorithmRegistry::with_builtins()));
+        let mut simulation = Simulation::new_with_planner(config, environment, registry, planner)?;
+        simulation.clear_observers();
+        for observer in observers {
+            simulation.add_observer(observer);
+        }
+        Ok(simulation)
+    }
+
+    /// The environment of a run on `grid`, with the options set to match it
+    fn environment_on(
+        config: &mut Config,
+        grid: &Grid,
+    ) -> Result<EnvironmentSetup, SimulationError> {
+        if grid.floors > 1 {
+            return Err("Grids given to the builder have a single floor"
+                .to_string()
+                .into());
+        }
+        config.grid_width = Some(grid.width);
+        config.grid_height = Some(grid.height);
+        config.diagonal_movement = grid.diagonal;
+        config.hex_grid = grid.hex;
+        config.wrap_edges = grid.wrap;
+
+        let cells: Vec<(Position, Cell)> = (0..grid.cell_count())
+            .map(|index| grid.position(index))
+            .map(|pos| (pos, grid.cell(pos)))
+            .collect();
+        let layout = MapLayout {
+            width: grid.width,
+            height: grid.height,
+            walls: cells
+                .iter()
+                .filter(|&&(_, cell)| cell == Cell::Wall)
+                .map(|&(pos, _)| pos)
+                .collect::<HashSet<_>>(),
+            scenarios: Vec::new(),
+            start: Some(grid.start),
+            goal: Some(grid.goal),
+        };
+        let mut environment = EnvironmentSetup::generate_on_layout(config, config.seed, &layout);
+        environment.terrain = cells
+            .iter()
+            .filter_map(|&(pos, cell)| match cell {
+                Cell::Terrain(cost) => Some((pos, cost)),
+                _ => None,
+            })
+            .collect::<HashMap<_, _>>();
+        environment.extra_goals = grid.extra_goals.clone();
+        environment.set_waypoints(grid.waypoints.clone())?;
+        // Each pair once, from its lower end
+        let portals = grid
+            .portals
+            .iter()
+            .filter(|(a, b)| a < b)
+            .map(|(&a, &b)| (a, b))
+            .collect();
+        environment.set_portals(portals)?;
+        Ok(environment)
+    }
+}
@@ -0,0 +1,161 @@
+use crate::grid::{Grid, Position};
+use std::collections::HashSet;
+
+/// Line-of-sight path smoothing, applied to planner output before the agent follows it.
+///
+/// Starting from the first cell, the path is scanned forward for the farthest
+/// waypoint that can be reached in a straight line, and the detour in between is
+/// replaced by that line. Lines are rasterized into grid moves (4-connected,
+/// 8-connected with diagonal movement, or 6-connected on hex grids) that cost no
+/// more than any other route between their ends, so the result is still a valid
+/// grid path and never more expensive than the original; detours around
+/// since-removed obstacles get cut, and staircases become straighter. On weighted
+/// grids a line is only taken if it costs no more than the stretch of path it
+/// replaces, so shortcuts never wade through mud the planner went around.
+///
+/// Only walls and known obstacles block a line, the same information planners get.
+/// Lines stay on one floor; stairs and waits are always kept as planned.
+pub fn smooth_path(grid: &Grid, path: &[Position], obstacles: &HashSet<Position>) -> Vec<Position> {
+    let Some(&first) = path.first() else {
+        return Vec::new();
+    };
+
+    // Cost of the original path up to each waypoint
+    let mut cost_to = Vec::with_capacity(path.len());
+    cost_to.push(0);
+    for step in path.windows(2) {
+        cost_to.push(cost_to[cost_to.len() - 1] + grid.move_cost(step[0], step[1]));
+    }
+
+    let mut smoothed = vec![first];
+    let mut anchor = 0;
+    while anchor + 1 < path.len() {
+        // Farthest waypoint visible from the anchor at no extra cost; the next one always is
+        let mut target = anchor + 1;
+        let mut shortcut = None;
+        for candidate in anchor + 2..path.len() {
+            // Planned waits are kept as they are
+            if path[anchor..=candidate]
+                .windows(2)
+                .any(|step| step[0] == step[1])
+            {
+                break;
+            }
+            match line_of_sight(grid, path[anchor], path[candidate], obstacles) {
+                Some(line) => {
+                    if line_cost(grid, path[anchor], &line) <= cost_to[candidate] - cost_to[anchor]
+                    {
+                        target = candidate;
+                        shortcut = Some(line);
+                    }
+                }
+                None => break,
+            }
+        }
+
+        match shortcut {
+            Some(line) => smoothed.extend(line),
+            None => smoothed.push(path[target]),
+        }
+        anchor = target;
+    }
+    smoothed
+}
+
+/// Cost of following `line` from `from`
+fn line_cost(grid: &Grid, from: Position, line: &[Position]) -> u32 {
+    std::iter::once(&from)
+        .chain(line)
+        .zip(line)
+        .map(|(&a, &b)| grid.move_cost(a, b))
+        .sum()
+}
+
+/// Cells on the straight line from `from` to `to` (excluding `from`), or `None` if
+/// any move along it is blocked. Lines are 4-connected, or 8-connected with diagonal
+/// movement, so without terrain they cost exactly the Manhattan or octile distance.
+/// Cells on different floors are never in sight of each other; on a toroidal grid
+/// the line takes the short way round.
+fn line_of_sight(
+    grid: &Grid,
+    from: Position,
+    to: Position,
+    obstacles: &HashSet<Position>,
+) -> Option<Vec<Position>> {
+    if from.z != to.z {
+        return None;
+    }
+    if grid.hex {
+        return hex_line_of_sight(grid, from, to, obstacles);
+    }
+    // On a toroidal grid the line may leave one edge and come back in at the other
+    let (dx, dy) = grid.metric().offset(from, to);
+    let nx = dx.unsigned_abs() as usize;
+    let ny = dy.unsigned_abs() as usize;
+    let steps = if grid.diagonal { nx.max(ny) } else { nx + ny };
+
+    let mut line = Vec::with_capacity(steps);
+    let mut previous = from;
+    let (mut ix, mut iy) = (0, 0);
+    for step in 1..=steps {
+        if grid.diagonal {
+            // One cell along the longer axis, the other axis rounded to the nearest cell
+            ix = (2 * step * nx + steps) / (2 * steps);
+            iy = (2 * step * ny + steps) / (2 * steps);
+        } else if iy == ny || (ix < nx && (1 + 2 * ix) * ny <= (1 + 2 * iy) * nx) {
+            // Step along whichever axis the line crosses next (x on ties)
+            ix += 1;
+        } else {
+            iy += 1;
+        }
+
+        let pos = grid.offset(from, dx.signum() * ix as i32, dy.signum() * iy as i32)?;
+        if !grid.can_move(previous, pos, |cell| obstacles.contains(&cell)) {
+            return None;
+        }
+        line.push(pos);
+        previous = pos;
+    }
+    Some(line)
+}
+
+/// Hex grid version of `line_of_sight`: the line is sampled once per move in cube
+/// coordinates and each sample rounded to the nearest cell, which yields a chain of
+/// neighbors exactly as long as the hex distance.
+fn hex_line_of_sight(
+    grid: &Grid,
+    from: Position,
+    to: Position,
+    obstacles: &HashSet<Position>,
+) -> Option<Vec<Position>> {
+    let metric = grid.metric();
+    let steps = metric.distance(from, to) as usize;
+    let (dq, dr) = metric.offset(from, to);
+    let (dq, dr) = (dq as f64, dr as f64);
+
+    let mut line = Vec::with_capacity(steps);
+    let mut previous = from;
+    for step in 1..=steps {
+        let t = step as f64 / steps as f64;
+        // Offsets from `from`, nudged off the edges between cells so ties round consistently
+        let q = dq * t + 1e-6;
+        let r = dr * t + 2e-6;
+        let s = -q - r;
+        let (mut rq, mut rr, rs) = (q.round(), r.round(), s.round());
+        // Rounding each coordinate may break q + r + s = 0; fix the one that moved most
+        let (eq, er, es) = ((rq - q).abs(), (rr - r).abs(), (rs - s).abs());
+        if eq > er && eq > es {
+            rq = -rr - rs;
+        } else if er > es {
+            rr = -rq - rs;
+        }
+
+        let pos = grid.offset(from, rq as i32, rr as i32)?;
+        if !grid.can_move(previous, pos, |cell| obstacles.contains(&cell)) {
+            return None;
+        }
+        line.push(pos);
+        previous = pos;
+    }
+    Some(line)
+}
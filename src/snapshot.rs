@@ -0,0 +1,392 @@
+use crate::gif_recorder::{fill, Layout, GRID_LINE};
+use crate::grid::{Cell, Grid, GridView, Position};
+use crate::observer::{Replan, SimulationObserver};
+use crate::say;
+use crate::simulation::{Simulation, StepOutcome};
+use image::{ImageFormat, Rgba, RgbaImage};
+use std::fmt::Write as _;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+
+/// Color of the agent's trajectory
+const TRAJECTORY: Rgba<u8> = Rgba([30, 90, 200, 255]);
+
+/// Color of the obstacle history's shading, stronger where obstacles stayed longer
+const OBSTACLE_SHADE: Rgba<u8> = Rgba([200, 30, 30, 255]);
+
+/// Color of the marks of replans that found a path, and of those that found none
+const REPLAN: Rgba<u8> = Rgba([235, 120, 0, 255]);
+const REPLAN_FAILED: Rgba<u8> = Rgba([200, 0, 0, 255]);
+
+/// The kinds of picture a snapshot can be, by the extension of its file
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Format {
+    Svg,
+    Png,
+}
+
+/// Saves a picture of a run once it is over: the final grid, the agent's whole
+/// trajectory, a ring where it replanned (red when no path was found) and the cells
+/// obstacles held at some point shaded by how many steps they held them. Written as
+/// SVG or PNG, by the file's extension.
+pub struct SnapshotExporter {
+    path: String,
+    format: Format,
+    file: Option<File>,
+    layout: Layout,
+    trail: Vec<Position>,
+    /// Where the agent replanned, and whether the plan found a path
+    replans: Vec<(Position, bool)>,
+    /// Steps each cell, by index, held an obstacle
+    obstacle_steps: Vec<u32>,
+}
+
+impl SnapshotExporter {
+    /// Save the snapshot to `path`, a `.svg` or `.png` file, each cell `cell_size`
+    /// pixels wide
+    pub fn new(path: &str, cell_size: u32) -> Result<Self, String> {
+        let extension = path
+            .rsplit_once('.')
+            .map(|(_, extension)| extension.to_lowercase());
+        let format = match extension.as_deref() {
+            Some("svg") => Format::Svg,
+            Some("png") => Format::Png,
+            _ => {
+                return Err(format!(
+                    "Cannot tell the format of the snapshot {}: use a .svg or .png file",
+                    path
+                ))
+            }
+        };
+        if cell_size == 0 {
+            return Err("--snapshot-cell-size must be at least 1".to_string());
+        }
+        let file =
+            File::create(path).map_err(|e| format!("Cannot save a snapshot to {}: {}", path, e))?;
+        Ok(SnapshotExporter {
+            path: path.to_string(),
+            format,
+            file: Some(file),
+            layout: Layout { cell_size },
+            trail: Vec::new(),
+            replans: Vec::new(),
+            obstacle_steps: Vec::new(),
+        })
+    }
+
+    /// Count the cells holding obstacles now
+    fn count_obstacles(&mut self, grid: &Grid) {
+        self.obstacle_steps.resize(grid.cell_count(), 0);
+        for (index, steps) in self.obstacle_steps.iter_mut().enumerate() {
+            if grid.cell(grid.position(index)) == Cell::Obstacle {
+                *steps += 1;
+            }
+        }
+    }
+
+    /// Opacity of the shading of the cells obstacles held, by index, from faint for
+    /// a step to strong for the longest-held cell
+    fn shading(&self) -> Vec<(usize, f32)> {
+        let most = self
+            .obstacle_steps
+            .iter()
+            .copied()
+            .max()
+            .unwrap_or(0)
+            .max(1) as f32;
+        self.obstacle_steps
+            .iter()
+            .enumerate()
+            .filter(|&(_, &steps)| steps > 0)
+            .map(|(index, &steps)| (index, 0.15 + 0.5 * steps as f32 / most))
+            .collect()
+    }
+
+    /// The trajectory as runs of cells next to each other on the same floor; a
+    /// portal or the stairs start a new one
+    fn strokes(&self) -> Vec<&[Position]> {
+        let mut strokes = Vec::new();
+        let mut start = 0;
+        for i in 1..=self.trail.len() {
+            let joined = i < self.trail.len() && {
+                let (a, b) = (self.trail[i - 1], self.trail[i]);
+                a.z == b.z && a.x.abs_diff(b.x) <= 1 && a.y.abs_diff(b.y) <= 1
+            };
+            if !joined {
+                strokes.push(&self.trail[start..i]);
+                start = i;
+            }
+        }
+        strokes
+    }
+
+    /// The final grid without the agent, so the start and goals show beneath the
+    /// trajectory
+    fn view(&self) -> GridView<'_> {
+        GridView {
+            agent: None,
+            path: &[],
+            visited: &[],
+            seen: None,
+            window: None,
+            marks: None,
+            color: false,
+        }
+    }
+
+    fn render_svg(&self, simulation: &Simulation) -> String {
+        let grid = &simulation.grid;
+        let layout = self.layout;
+        let (width, height) = layout.size(grid);
+        let size = layout.cell_size as f32;
+        let mut out = String::new();
+        let _ = writeln!(
+            out,
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{w}\" height=\"{h}\" viewBox=\"0 0 {w} {h}\">",
+            w = width,
+            h = height
+        );
+        let _ = writeln!(out, "<title>{}</title>", caption(simulation));
+        let _ = writeln!(
+            out,
+            "<rect width=\"{}\" height=\"{}\" fill=\"{}\"/>",
+            width,
+            height,
+            hex(GRID_LINE)
+        );
+        let view = self.view();
+        let none = Default::default();
+        let inner = layout.inner();
+        for index in 0..grid.cell_count() {
+            let pos = grid.position(index);
+            let (left, top) = layout.corner(grid, pos);
+            let (glyph, _) = grid.glyph(pos, &view, (&none, &none));
+            let _ = writeln!(
+                out,
+                "<rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" fill=\"{}\"/>",
+                left,
+                top,
+                inner,
+                inner,
+                hex(fill(glyph))
+            );
+        }
+        for (index, opacity) in self.shading() {
+            let (left, top) = layout.corner(grid, grid.position(index));
+            let _ = writeln!(
+                out,
+                "<rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" fill=\"{}\" fill-opacity=\"{:.2}\"/>",
+                left,
+                top,
+                inner,
+                inner,
+                hex(OBSTACLE_SHADE),
+                opacity
+            );
+        }
+        for stroke in self.strokes() {
+            let points: Vec<String> = stroke
+                .iter()
+                .map(|&pos| {
+                    let (x, y) = layout.center(grid, pos);
+                    format!("{},{}", x, y)
+                })
+                .collect();
+            let _ = writeln!(
+                out,
+                "<polyline points=\"{}\" fill=\"none\" stroke=\"{}\" stroke-width=\"{}\" stroke-linecap=\"round\" stroke-linejoin=\"round\"/>",
+                points.join(" "),
+                hex(TRAJECTORY),
+                (size / 4.0).max(1.0)
+            );
+        }
+        for &(pos, found) in &self.replans {
+            let (x, y) = layout.center(grid, pos);
+            let _ = writeln!(
+                out,
+                "<circle cx=\"{}\" cy=\"{}\" r=\"{}\" fill=\"none\" stroke=\"{}\" stroke-width=\"{}\"/>",
+                x,
+                y,
+                size * 0.4,
+                hex(if found { REPLAN } else { REPLAN_FAILED }),
+                (size / 8.0).max(1.0)
+            );
+        }
+        let (x, y) = layout.center(grid, simulation.agent.position);
+        let _ = writeln!(
+            out,
+            "<circle cx=\"{}\" cy=\"{}\" r=\"{}\" fill=\"{}\"/>",
+            x,
+            y,
+            size * 0.3,
+            hex(fill('A'))
+        );
+        out.push_str("</svg>\n");
+        out
+    }
+
+    fn render_png(&self, simulation: &Simulation) -> RgbaImage {
+        let grid = &simulation.grid;
+        let layout = self.layout;
+        let size = layout.cell_size as f32;
+        let mut image = layout.draw(grid, &self.view());
+        let inner = layout.inner();
+        for (index, opacity) in self.shading() {
+            let (left, top) = layout.corner(grid, grid.position(index));
+            for y in top..top + inner {
+                for x in left..left + inner {
+                    blend(&mut image, x, y, OBSTACLE_SHADE, opacity);
+                }
+            }
+        }
+        let width = (size / 4.0).max(1.0);
+        for stroke in self.strokes() {
+            let centers: Vec<(f32, f32)> =
+                stroke.iter().map(|&pos| layout.center(grid, pos)).collect();
+            for pair in centers.windows(2) {
+                line(&mut image, pair[0], pair[1], width / 2.0, TRAJECTORY);
+            }
+            if let [center] = centers[..] {
+                disc(&mut image, center, width / 2.0, TRAJECTORY);
+            }
+        }
+        for &(pos, found) in &self.replans {
+            let color = if found { REPLAN } else { REPLAN_FAILED };
+            ring(
+                &mut image,
+                layout.center(grid, pos),
+                size * 0.4,
+                (size / 8.0).max(1.0),
+                color,
+            );
+        }
+        disc(
+            &mut image,
+            layout.center(grid, simulation.agent.position),
+            size * 0.3,
+            fill('A'),
+        );
+        image
+    }
+
+    fn save(&mut self, simulation: &Simulation) -> Result<(), String> {
+        let Some(file) = self.file.take() else {
+            return Ok(());
+        };
+        let mut writer = BufWriter::new(file);
+        let written = match self.format {
+            Format::Svg => writer
+                .write_all(self.render_svg(simulation).as_bytes())
+                .map_err(|e| e.to_string()),
+            Format::Png => self
+                .render_png(simulation)
+                .write_to(&mut writer, ImageFormat::Png)
+                .map_err(|e| e.to_string()),
+        };
+        written
+            .and_then(|()| writer.flush().map_err(|e| e.to_string()))
+            .map_err(|e| format!("Cannot save a snapshot to {}: {}", self.path, e))
+    }
+}
+
+/// What the run was and how it went, for the picture's title
+fn caption(simulation: &Simulation) -> String {
+    let config = simulation.config();
+    let outcome = match simulation.outcome() {
+        Some(StepOutcome::Finished) => "reached the goal".to_string(),
+        Some(StepOutcome::Failed(failure)) => format!("failed ({:?})", failure),
+        _ => "stopped".to_string(),
+    };
+    let seed = config
+        .seed
+        .map_or(String::new(), |seed| format!(", seed {}", seed));
+    format!(
+        "{}{}: {} after {} moves and {} replans",
+        config.algorithm,
+        seed,
+        outcome,
+        simulation.statistics().total_moves,
+        simulation.timing_data().total_calls()
+    )
+}
+
+/// A color as SVG writes it
+fn hex(Rgba([r, g, b, _]): Rgba<u8>) -> String {
+    format!("#{:02x}{:02x}{:02x}", r, g, b)
+}
+
+/// Mix `color` into the pixel at (x, y), `alpha` of the way
+fn blend(image: &mut RgbaImage, x: u32, y: u32, color: Rgba<u8>, alpha: f32) {
+    if x >= image.width() || y >= image.height() {
+        return;
+    }
+    let pixel = image.get_pixel_mut(x, y);
+    for channel in 0..3 {
+        let mixed = pixel[channel] as f32 * (1.0 - alpha) + color[channel] as f32 * alpha;
+        pixel[channel] = mixed.round() as u8;
+    }
+}
+
+/// Fill the pixels within `radius` of `center`
+fn disc(image: &mut RgbaImage, center: (f32, f32), radius: f32, color: Rgba<u8>) {
+    ring(image, center, radius, radius, color);
+}
+
+/// Fill the pixels between `radius - width` and `radius` from `center`
+fn ring(image: &mut RgbaImage, (cx, cy): (f32, f32), radius: f32, width: f32, color: Rgba<u8>) {
+    let (left, top) = (
+        (cx - radius).floor().max(0.0) as u32,
+        (cy - radius).floor().max(0.0) as u32,
+    );
+    let (right, bottom) = ((cx + radius).ceil() as u32, (cy + radius).ceil() as u32);
+    for y in top..=bottom {
+        for x in left..=right {
+            let distance = ((x as f32 + 0.5 - cx).powi(2) + (y as f32 + 0.5 - cy).powi(2)).sqrt();
+            if distance <= radius && distance >= radius - width {
+                blend(image, x, y, color, 1.0);
+            }
+        }
+    }
+}
+
+/// Draw a line `half_width` either side of the one from `from` to `to`, with round
+/// ends
+fn line(image: &mut RgbaImage, from: (f32, f32), to: (f32, f32), half_width: f32, color: Rgba<u8>) {
+    let length = ((to.0 - from.0).powi(2) + (to.1 - from.1).powi(2)).sqrt();
+    let samples = (length * 2.0).ceil().max(1.0) as usize;
+    for i in 0..=samples {
+        let t = i as f32 / samples as f32;
+        let point = (from.0 + (to.0 - from.0) * t, from.1 + (to.1 - from.1) * t);
+        disc(image, point, half_width.max(0.5), color);
+    }
+}
+
+impl SimulationObserver for SnapshotExporter {
+    fn on_start(&mut self, simulation: &Simulation) {
+        self.trail = vec![simulation.agent.position];
+        self.replans.clear();
+        self.obstacle_steps.clear();
+        self.count_obstacles(&simulation.grid);
+    }
+
+    fn on_replan(&mut self, simulation: &Simulation, replan: &Replan) {
+        if !replan.routine {
+            self.replans
+                .push((simulation.agent.position, replan.stuck_attempts == 0));
+        }
+    }
+
+    fn on_step(&mut self, simulation: &Simulation, outcome: StepOutcome) {
+        if let StepOutcome::Moved { to, .. } = outcome {
+            self.trail.push(to);
+        }
+        self.count_obstacles(&simulation.grid);
+    }
+
+    fn on_finish(&mut self, simulation: &Simulation) {
+        match self.save(simulation) {
+            Ok(()) => say!("Saved a snapshot of the run to {}", self.path),
+            Err(e) => tracing::error!("{}", e),
+        }
+    }
+}
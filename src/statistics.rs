@@ -1,70 +1,267 @@
+use crate::algorithms::common::SearchMetrics;
+use crate::grid::Position;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
 use std::fmt;
+use std::time::Duration;
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Statistics {
     pub total_moves: usize,
     pub num_obstacles: usize,
     pub num_walls: usize,
     pub route_efficiency: f64,
     pub optimal_path_length: usize,
+    /// Cost of every move made, in straight moves (equal to `total_moves` without diagonal movement or terrain)
+    pub travel_cost: f64,
+    /// Cost of the optimal path, in straight moves (equal to `optimal_path_length` without diagonal movement or terrain)
+    pub optimal_path_cost: f64,
+    /// Cost of the best run the obstacle timeline allows, known ahead, in straight
+    /// moves with a wait costing one (equal to `optimal_path_cost` when no obstacle
+    /// gets in the way); 0 when it was not measured (see `--dynamic-baseline`)
+    pub dynamic_optimal_cost: f64,
+    /// Travel cost over `dynamic_optimal_cost`, where `route_efficiency` compares
+    /// with the optimal path before any obstacle appears (0 when not measured)
+    pub dynamic_route_efficiency: f64,
+    /// Total moves over every path the planner returned, before smoothing
+    pub raw_path_length: usize,
+    /// Total moves over the same paths after `--smooth-path` (equal to the raw total when disabled)
+    pub smoothed_path_length: usize,
+    /// Per-leg breakdown of a waypoint mission (a single leg without waypoints)
+    pub legs: Vec<LegStatistics>,
+    /// Known obstacles that were already gone, summed over every step (one obstacle
+    /// believed in for three steps after it vanished counts 3)
+    pub stale_belief_steps: usize,
+    /// Travel cost the agent may spend before it has to give up (`--energy-budget`)
+    pub energy_budget: Option<f64>,
+    /// The run ended because the next move would have cost more energy than was left
+    pub out_of_energy: bool,
+    /// Times another agent stood on the next cell of the agent's path, a collision it
+    /// had to avoid by replanning or waiting
+    pub agent_collisions: usize,
+    /// The other agents of a multi-agent run, in order (empty with a single agent)
+    pub agents: Vec<AgentStatistics>,
+    /// Steps until the last agent reached its goal (`None` if any never did)
+    pub makespan: Option<usize>,
+    /// Constraint tree nodes Conflict-Based Search expanded over every joint plan
+    pub cbs_expansions: usize,
+    /// The run ended because the pursuer caught the agent
+    pub caught: bool,
+    /// The run was stopped for taking longer than `--simulation-timeout-seconds`
+    pub timed_out: bool,
+    /// Replans that used up all of `--find-path-budget-ms`, most of them given up
+    /// before they found a path
+    pub budget_overruns: usize,
 }
 
+/// How one of the other agents of a multi-agent run fared.
 #[derive(Debug, Clone)]
-pub enum AlgorithmStats {
-    AStar(usize),
-    DStarLite(usize),
-    Hybrid { a_star_calls: usize, d_star_calls: usize },
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct AgentStatistics {
+    pub start: Position,
+    pub goal: Position,
+    pub moves: usize,
+    /// Cost of every move made, in straight moves
+    pub travel_cost: f64,
+    /// Cost of its optimal path on the empty grid, in straight moves
+    pub optimal_path_cost: f64,
+    pub replans: usize,
+    /// Times another agent stood on the next cell of its path
+    pub agent_collisions: usize,
+    pub reached: bool,
+    pub out_of_energy: bool,
+    /// It stopped trying after finding no path several steps in a row
+    pub gave_up: bool,
 }
 
-impl AlgorithmStats {
-    pub fn total_calls(&self) -> usize {
+/// Moves and replans spent on one leg of a mission, from one waypoint to the next.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct LegStatistics {
+    pub target: Position,
+    pub moves: usize,
+    pub replans: usize,
+    pub reached: bool,
+}
+
+/// A value of one of a planner's own metrics
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum MetricValue {
+    Count(usize),
+    Number(f64),
+    Duration(Duration),
+    Text(String),
+}
+
+impl fmt::Display for MetricValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
-            AlgorithmStats::AStar(calls) => *calls,
-            AlgorithmStats::DStarLite(calls) => *calls,
-            AlgorithmStats::Hybrid { a_star_calls, d_star_calls } => a_star_calls + d_star_calls,
+            MetricValue::Count(count) => write!(f, "{}", count),
+            MetricValue::Number(number) => write!(f, "{:.2}", number),
+            MetricValue::Duration(duration) => write!(f, "{:.2?}", duration),
+            MetricValue::Text(text) => write!(f, "{}", text),
+        }
+    }
+}
+
+/// What a planner did over a run: the calls it answered, the work of its searches
+/// and whatever else it reports about itself.
+///
+/// Built by the stats adapters of the `AlgorithmRegistry`; a planner adds metrics of
+/// its own with `with`, so a new one needs no type of its own.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct PlannerMetrics {
+    /// Name of the planner in the heading of its statistics, e.g. "D* Lite"
+    pub planner: String,
+    pub calls: usize,
+    /// Calls answered by each planner a combined planner switches between, by name
+    /// (e.g. "A*" and "D* Lite" for the hybrid planner)
+    pub delegated_calls: Vec<(String, usize)>,
+    /// Nodes expanded and generated and open list work over every search
+    pub search: SearchMetrics,
+    /// Share of the calls answered by repairing earlier work rather than planning
+    /// from scratch, for planners that keep it
+    pub reuse_ratio: Option<f64>,
+    /// The planner's own metrics by snake_case key, in the order they are printed
+    pub custom: Vec<(String, MetricValue)>,
+}
+
+impl PlannerMetrics {
+    pub fn new(planner: impl Into<String>, calls: usize) -> Self {
+        PlannerMetrics {
+            planner: planner.into(),
+            calls,
+            delegated_calls: Vec::new(),
+            search: SearchMetrics::default(),
+            reuse_ratio: None,
+            custom: Vec::new(),
+        }
+    }
+
+    /// Adds the calls one of the planners of a combined planner answered
+    pub fn with_delegated(mut self, planner: impl Into<String>, calls: usize) -> Self {
+        self.delegated_calls.push((planner.into(), calls));
+        self
+    }
+
+    pub fn with_reuse_ratio(mut self, reuse_ratio: f64) -> Self {
+        self.reuse_ratio = Some(reuse_ratio);
+        self
+    }
+
+    /// Adds a metric of the planner's own
+    pub fn with(mut self, key: &str, value: MetricValue) -> Self {
+        self.custom.push((key.to_string(), value));
+        self
+    }
+
+    /// Calls answered by the named planner: the calls delegated to it, or all of them
+    /// when it is this planner
+    pub fn calls_of(&self, planner: &str) -> usize {
+        match self
+            .delegated_calls
+            .iter()
+            .find(|(name, _)| name == planner)
+        {
+            Some(&(_, calls)) => calls,
+            None if self.planner == planner => self.calls,
+            None => 0,
+        }
+    }
+
+    pub fn get(&self, key: &str) -> Option<&MetricValue> {
+        self.custom
+            .iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, value)| value)
+    }
+
+    pub fn count(&self, key: &str) -> Option<usize> {
+        match self.get(key)? {
+            MetricValue::Count(count) => Some(*count),
+            _ => None,
+        }
+    }
+
+    pub fn number(&self, key: &str) -> Option<f64> {
+        match self.get(key)? {
+            MetricValue::Number(number) => Some(*number),
+            _ => None,
         }
     }
+
+    pub fn duration(&self, key: &str) -> Option<Duration> {
+        match self.get(key)? {
+            MetricValue::Duration(duration) => Some(*duration),
+            _ => None,
+        }
+    }
+
+    pub fn text(&self, key: &str) -> Option<&str> {
+        match self.get(key)? {
+            MetricValue::Text(text) => Some(text),
+            _ => None,
+        }
+    }
+
+    /// The first of the planner's own metrics as `key=value`, the one a table of
+    /// several planners shows
+    pub fn headline(&self) -> Option<String> {
+        self.custom
+            .first()
+            .map(|(key, value)| format!("{}={}", key, value))
+    }
 }
 
-impl fmt::Display for AlgorithmStats {
+/// "heuristic_weight" as "Heuristic weight"
+fn label(key: &str) -> String {
+    let words = key.replace('_', " ");
+    let mut chars = words.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => words,
+    }
+}
+
+impl fmt::Display for PlannerMetrics {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        match self {
-            AlgorithmStats::AStar(calls) => {
-                writeln!(f, "A* Algorithm Statistics:")?;
-                writeln!(f, "Total pathfinding calls: {}", calls)?;
-            }
-            AlgorithmStats::DStarLite(calls) => {
-                writeln!(f, "D* Lite Algorithm Statistics:")?;
-                writeln!(f, "Total pathfinding calls: {}", calls)?;
-            }
-            AlgorithmStats::Hybrid { a_star_calls, d_star_calls } => {
-                let total = a_star_calls + d_star_calls;
-                let a_star_percentage = if total > 0 {
-                    (*a_star_calls as f64 / total as f64) * 100.0
-                } else {
-                    0.0
-                };
-                let d_star_percentage = if total > 0 {
-                    (*d_star_calls as f64 / total as f64) * 100.0
-                } else {
-                    0.0
-                };
-
-                writeln!(f, "Hybrid A*/D* Algorithm Statistics:")?;
-                writeln!(f, "Total pathfinding calls: {}", total)?;
-                writeln!(f, "A* usage: {} calls ({:.1}%)", a_star_calls, a_star_percentage)?;
-                writeln!(f, "D* Lite usage: {} calls ({:.1}%)", d_star_calls, d_star_percentage)?;
-                
-                if total > 0 {
-                    if *a_star_calls == 1 && *d_star_calls > 0 {
-                        writeln!(f, "✓ Optimal hybrid performance: A* used once for initial path, D* Lite handled all updates")?;
-                    } else if *a_star_calls > 1 {
-                        writeln!(f, "⚠ Multiple A* calls detected - may indicate significant environment changes")?;
-                    } else if *d_star_calls == 0 {
-                        writeln!(f, "⚠ Only A* was used - no incremental updates occurred")?;
-                    }
-                }
+        writeln!(f, "{} Algorithm Statistics:", self.planner)?;
+        writeln!(f, "Total pathfinding calls: {}", self.calls)?;
+        for (key, value) in &self.custom {
+            writeln!(f, "{}: {}", label(key), value)?;
+        }
+        // Shares of the delegated searches, which one call may make more than one of
+        let delegated: usize = self.delegated_calls.iter().map(|(_, calls)| calls).sum();
+        for (planner, calls) in &self.delegated_calls {
+            let percentage = if delegated > 0 {
+                (*calls as f64 / delegated as f64) * 100.0
+            } else {
+                0.0
+            };
+            writeln!(f, "{} usage: {} calls ({:.1}%)", planner, calls, percentage)?;
+        }
+        if let Some(reuse_ratio) = self.reuse_ratio {
+            writeln!(f, "Calls reusing earlier work: {:.1}%", reuse_ratio * 100.0)?;
+        }
+        if self.search.nodes_expanded > 0 {
+            write!(
+                f,
+                "Search effort: {} nodes expanded, {} generated",
+                self.search.nodes_expanded, self.search.nodes_generated
+            )?;
+            // Searches whose open list is out of sight leave these at 0
+            if self.search.heap_operations > 0 {
+                write!(
+                    f,
+                    ", open list up to {}, {} heap operations",
+                    self.search.max_open_list, self.search.heap_operations
+                )?;
             }
+            writeln!(f)?;
         }
         Ok(())
     }
@@ -78,35 +275,198 @@ impl Statistics {
             num_walls,
             route_efficiency: 0.0,
             optimal_path_length,
+            travel_cost: 0.0,
+            optimal_path_cost: optimal_path_length as f64,
+            dynamic_optimal_cost: 0.0,
+            dynamic_route_efficiency: 0.0,
+            raw_path_length: 0,
+            smoothed_path_length: 0,
+            legs: Vec::new(),
+            stale_belief_steps: 0,
+            energy_budget: None,
+            out_of_energy: false,
+            agent_collisions: 0,
+            agents: Vec::new(),
+            makespan: None,
+            cbs_expansions: 0,
+            caught: false,
+            timed_out: false,
+            budget_overruns: 0,
         }
     }
 
-    pub fn calculate_efficiency(&mut self) {
-        if self.total_moves > 0 && self.optimal_path_length > 0 {
-            self.route_efficiency = self.total_moves as f64 / self.optimal_path_length as f64;
+    /// Agents that reached their goals, counting this run's own agent if `reached`
+    pub fn agents_reached(&self, reached: bool) -> usize {
+        usize::from(reached) + self.agents.iter().filter(|agent| agent.reached).count()
+    }
+
+    /// Travel cost summed over every agent (the sum-of-costs of multi-agent planning)
+    pub fn sum_of_costs(&self) -> f64 {
+        self.travel_cost
+            + self
+                .agents
+                .iter()
+                .map(|agent| agent.travel_cost)
+                .sum::<f64>()
+    }
+
+    /// Collisions avoided, summed over every agent
+    pub fn total_agent_collisions(&self) -> usize {
+        self.agent_collisions
+            + self
+                .agents
+                .iter()
+                .map(|agent| agent.agent_collisions)
+                .sum::<usize>()
+    }
+
+    /// Fraction of planned moves removed by path smoothing
+    pub fn smoothing_reduction(&self) -> f64 {
+        if self.raw_path_length > 0 {
+            1.0 - self.smoothed_path_length as f64 / self.raw_path_length as f64
         } else {
-            self.route_efficiency = 0.0;
+            0.0
         }
     }
+
+    /// Travel cost over optimal path cost (moves over optimal moves on a 4-connected
+    /// grid), against the static and the dynamic optimum
+    pub fn calculate_efficiency(&mut self) {
+        let efficiency = |optimal: f64| match self.travel_cost > 0.0 && optimal > 0.0 {
+            true => self.travel_cost / optimal,
+            false => 0.0,
+        };
+        self.route_efficiency = efficiency(self.optimal_path_cost);
+        self.dynamic_route_efficiency = efficiency(self.dynamic_optimal_cost);
+    }
 }
 
 impl fmt::Display for Statistics {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         writeln!(f, "Total Moves: {}", self.total_moves)?;
         writeln!(f, "Optimal Path Length: {}", self.optimal_path_length)?;
+        if self.travel_cost != self.total_moves as f64
+            || self.optimal_path_cost != self.optimal_path_length as f64
+        {
+            writeln!(
+                f,
+                "Travel Cost: {:.1} (optimal {:.1}, in straight moves)",
+                self.travel_cost, self.optimal_path_cost
+            )?;
+        }
         writeln!(f, "Number of Walls: {}", self.num_walls)?;
         writeln!(f, "Number of Obstacles: {}", self.num_obstacles)?;
         writeln!(f, "Route Efficiency: {:.3}", self.route_efficiency)?;
-        
+        if self.dynamic_optimal_cost > 0.0 {
+            writeln!(
+                f,
+                "Dynamic Route Efficiency: {:.3} (best run with the obstacles known ahead costs {:.1})",
+                self.dynamic_route_efficiency, self.dynamic_optimal_cost
+            )?;
+        }
+        if self.smoothed_path_length < self.raw_path_length {
+            writeln!(
+                f,
+                "Planned Path Length: {} raw, {} smoothed ({:.1}% shorter)",
+                self.raw_path_length,
+                self.smoothed_path_length,
+                self.smoothing_reduction() * 100.0
+            )?;
+        } else {
+            writeln!(f, "Planned Path Length: {}", self.raw_path_length)?;
+        }
+        if let Some(budget) = self.energy_budget {
+            writeln!(
+                f,
+                "Energy Spent: {:.1} of {:.1}{}",
+                self.travel_cost,
+                budget,
+                if self.out_of_energy { " (ran out)" } else { "" }
+            )?;
+        }
+        if self.caught {
+            writeln!(f, "Caught by the pursuer")?;
+        }
+        if self.budget_overruns > 0 {
+            writeln!(f, "find_path Budget Overruns: {}", self.budget_overruns)?;
+        }
+        if self.stale_belief_steps > 0 {
+            writeln!(
+                f,
+                "Stale Obstacle Beliefs: {} obstacle-steps",
+                self.stale_belief_steps
+            )?;
+        }
+        if !self.agents.is_empty() {
+            writeln!(f, "Collisions Avoided: {}", self.agent_collisions)?;
+            writeln!(f, "Other Agents:")?;
+            for (i, agent) in self.agents.iter().enumerate() {
+                writeln!(
+                    f,
+                    "  Agent {} ({}, {}) -> ({}, {}): {} moves, cost {:.1} (optimal {:.1}), {} replans, {} collisions avoided{}",
+                    i + 2,
+                    agent.start.x,
+                    agent.start.y,
+                    agent.goal.x,
+                    agent.goal.y,
+                    agent.moves,
+                    agent.travel_cost,
+                    agent.optimal_path_cost,
+                    agent.replans,
+                    agent.agent_collisions,
+                    if agent.reached {
+                        ""
+                    } else if agent.out_of_energy {
+                        " (ran out of energy)"
+                    } else if agent.gave_up {
+                        " (stuck)"
+                    } else {
+                        " (not reached)"
+                    }
+                )?;
+            }
+            writeln!(
+                f,
+                "All Agents: {:.1} total travel cost, {} collisions avoided",
+                self.sum_of_costs(),
+                self.total_agent_collisions()
+            )?;
+            match self.makespan {
+                Some(makespan) => writeln!(f, "Makespan: {} steps", makespan)?,
+                None => writeln!(f, "Makespan: not every agent reached its goal")?,
+            }
+            if self.cbs_expansions > 0 {
+                writeln!(f, "CBS Nodes Expanded: {}", self.cbs_expansions)?;
+            }
+        }
+        if self.legs.len() > 1 {
+            writeln!(f, "Mission Legs:")?;
+            for (i, leg) in self.legs.iter().enumerate() {
+                writeln!(
+                    f,
+                    "  Leg {} -> ({}, {}): {} moves, {} replans{}",
+                    i + 1,
+                    leg.target.x,
+                    leg.target.y,
+                    leg.moves,
+                    leg.replans,
+                    if leg.reached { "" } else { " (not reached)" }
+                )?;
+            }
+        }
+
         if self.route_efficiency > 0.0 {
             let efficiency_percentage = (self.route_efficiency * 100.0).min(100.0);
             writeln!(f, "Efficiency Percentage: {:.1}%", efficiency_percentage)?;
-            
+
             if self.route_efficiency < 1.0 {
-                writeln!(f, "Note: Efficiency > 100% (took fewer moves than pre-obstacle optimal)")?;
+                writeln!(
+                    f,
+                    "Note: Efficiency > 100% (took fewer moves than pre-obstacle optimal)"
+                )?;
             }
         }
-        
+
         Ok(())
     }
 }
@@ -0,0 +1,193 @@
+use crate::config::Config;
+use clap::CommandFactory;
+
+/// Options a batch sweeps beyond its own ranges, read from a `--sweep` file: a TOML
+/// table of options by long name, each given a list of values
+/// (`algorithm = ["a_star", "d_star_lite"]`), a range
+/// (`vision_radius = { min = 3, max = 9, step = 2 }`) or a single value. The batch
+/// runs every combination of the values.
+#[derive(Debug, Clone, Default)]
+pub struct SweepSpec {
+    /// Each swept option and its values, by name
+    parameters: Vec<(String, Vec<toml::Value>)>,
+}
+
+/// Options that pick what a batch runs rather than how a simulation goes
+const NOT_SWEPT: [&str; 3] = ["seed", "config", "scenario"];
+
+impl SweepSpec {
+    /// Read a sweep file
+    pub fn load(path: &str) -> Result<Self, String> {
+        let text = std::fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read sweep file '{}': {}", path, e))?;
+        let table: toml::Table =
+            toml::from_str(&text).map_err(|e| format!("Invalid sweep file '{}': {}", path, e))?;
+        Self::from_table(&table).map_err(|e| format!("Invalid sweep file '{}': {}", path, e))
+    }
+
+    /// The sweep of a TOML table of options and their values
+    pub fn from_table(table: &toml::Table) -> Result<Self, String> {
+        let command = Config::command();
+        let mut parameters = Vec::with_capacity(table.len());
+        for (name, values) in table {
+            let long = name.replace('_', "-");
+            if NOT_SWEPT.contains(&name.as_str())
+                || !command
+                    .get_arguments()
+                    .any(|arg| arg.get_long() == Some(long.as_str()))
+            {
+                return Err(format!("'{}' is not an option that can be swept", name));
+            }
+            let values = match values {
+                toml::Value::Array(values) => values.clone(),
+                toml::Value::Table(range) => Self::range(range)
+                    .map_err(|e| format!("Invalid range for '{}': {}", name, e))?,
+                value => vec![value.clone()],
+            };
+            if values.is_empty() {
+                return Err(format!("'{}' has no values to sweep", name));
+            }
+            if let Some(value) = values.iter().find(|value| {
+                matches!(
+                    value,
+                    toml::Value::Array(_) | toml::Value::Table(_) | toml::Value::Datetime(_)
+                )
+            }) {
+                return Err(format!("'{}' is not a single value of '{}'", value, name));
+            }
+            parameters.push((name.clone(), values));
+        }
+        Ok(SweepSpec { parameters })
+    }
+
+    /// The values of a `{ min, max, step }` range, integers unless any of them is
+    /// fractional; the step defaults to 1
+    fn range(range: &toml::Table) -> Result<Vec<toml::Value>, String> {
+        if let Some(key) = range
+            .keys()
+            .find(|key| !["min", "max", "step"].contains(&key.as_str()))
+        {
+            return Err(format!("unknown key '{}'", key));
+        }
+        let bound = |key: &str| match range.get(key) {
+            None if key == "step" => Ok(toml::Value::Integer(1)),
+            None => Err(format!("'{}' is missing", key)),
+            Some(value @ (toml::Value::Integer(_) | toml::Value::Float(_))) => Ok(value.clone()),
+            Some(_) => Err(format!("'{}' must be a number", key)),
+        };
+        let (min, max, step) = (bound("min")?, bound("max")?, bound("step")?);
+        if let (toml::Value::Integer(min), toml::Value::Integer(max), toml::Value::Integer(step)) =
+            (&min, &max, &step)
+        {
+            if *step <= 0 {
+                return Err("'step' must be above 0".to_string());
+            }
+            return Ok((*min..=*max)
+                .step_by(*step as usize)
+                .map(toml::Value::Integer)
+                .collect());
+        }
+        let number =
+            |value: &toml::Value| value.as_float().or(value.as_integer().map(|n| n as f64));
+        let (min, max, step) = (
+            number(&min).unwrap_or_default(),
+            number(&max).unwrap_or_default(),
+            number(&step).unwrap_or_default(),
+        );
+        if step <= 0.0 {
+            return Err("'step' must be above 0".to_string());
+        }
+        // Counted rather than added up, so the last value is not lost to rounding
+        let steps = ((max - min) / step + 1e-9).floor();
+        if steps < 0.0 {
+            return Ok(Vec::new());
+        }
+        Ok((0..=steps as usize)
+            .map(|i| toml::Value::Float(((min + i as f64 * step) * 1e9).round() / 1e9))
+            .collect())
+    }
+
+    /// Whether the file sweeps no options
+    pub fn is_empty(&self) -> bool {
+        self.parameters.is_empty()
+    }
+
+    /// Names of the swept options
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.parameters.iter().map(|(name, _)| name.as_str())
+    }
+
+    /// Name and values of the swept option at `index`
+    pub fn parameter(&self, index: usize) -> (&str, &[toml::Value]) {
+        let (name, values) = &self.parameters[index];
+        (name, values)
+    }
+
+    /// Whether the option is swept
+    pub fn sweeps(&self, name: &str) -> bool {
+        self.names().any(|swept| swept == name)
+    }
+
+    /// Number of combinations of the values, 1 when nothing is swept
+    pub fn combinations(&self) -> usize {
+        self.parameters
+            .iter()
+            .map(|(_, values)| values.len())
+            .product()
+    }
+
+    /// Values of every swept option in a combination, the last option's changing
+    /// fastest from one combination to the next
+    fn values(&self, combination: usize) -> Vec<(&str, &toml::Value)> {
+        let mut rest = combination;
+        let mut values: Vec<_> = self
+            .parameters
+            .iter()
+            .rev()
+            .map(|(name, values)| {
+                let value = &values[rest % values.len()];
+                rest /= values.len();
+                (name.as_str(), value)
+            })
+            .collect();
+        values.reverse();
+        values
+    }
+
+    /// Values of every swept option in a combination
+    pub fn combination(&self, combination: usize) -> Vec<toml::Value> {
+        self.values(combination)
+            .into_iter()
+            .map(|(_, value)| value.clone())
+            .collect()
+    }
+
+    /// Values of every swept option in a combination, as they read in the CSV
+    pub fn labels(&self, combination: usize) -> Vec<String> {
+        self.values(combination)
+            .into_iter()
+            .map(|(_, value)| Self::label(value))
+            .collect()
+    }
+
+    /// A swept value as it reads in the CSV
+    pub fn label(value: &toml::Value) -> String {
+        match value {
+            toml::Value::String(text) => text.clone(),
+            value => value.to_string(),
+        }
+    }
+
+    /// The options of a run with the swept options of a combination set over them
+    pub fn apply(&self, config: &Config, combination: usize) -> Result<Config, String> {
+        if self.is_empty() {
+            return Ok(config.clone());
+        }
+        config.with_settings(
+            self.values(combination)
+                .into_iter()
+                .map(|(name, value)| (name.to_string(), value.clone()))
+                .collect(),
+        )
+    }
+}
@@ -0,0 +1,781 @@
+use crate::algorithms::common::SearchTrace;
+use crate::grid::{Cell, Grid, GridView, Position, Window};
+use crate::multi_agent::MAX_STUCK_ATTEMPTS;
+use crate::observer::{Replan, SimulationObserver, Viewport};
+use crate::simulation::{Failure, Simulation, StepOutcome};
+use crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers};
+use crossterm::style::{Color, Print};
+use crossterm::terminal::{self, Clear, ClearType};
+use crossterm::{cursor, execute, queue};
+use std::collections::{HashMap, HashSet};
+use std::fmt::Write as _;
+use std::io::{self, IsTerminal, Write};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Widest minimap drawn beside a viewport, in characters
+const MINIMAP_WIDTH: usize = 40;
+
+/// Fewest columns and rows of cells a viewport shows, however small the terminal
+const MIN_VIEWPORT: usize = 8;
+
+/// Draws the run in the terminal: the grid and the state of the run after every move
+/// of the agent, pausing `--delay-ms` after each frame, what it plans, and the final
+/// screen. Draws nothing with visualization off in batch runs.
+///
+/// While a frame is shown it reads the keyboard, when there is one: space pauses and
+/// resumes the run, n shows the next frame and pauses again, + and - double and halve
+/// the delay, and q (or Ctrl+C) stops the run.
+#[derive(Debug, Clone, Default)]
+pub struct TerminalRenderer {
+    paused: bool,
+    /// Delay set with + and -, in place of `--delay-ms`
+    delay_ms: Option<u64>,
+    quit: bool,
+    /// Cells the agent has stood on, in the order it came to them
+    trail: Vec<Position>,
+    /// Cells the agent has had in view, kept with `--show-belief`
+    seen: HashSet<Position>,
+}
+
+/// Raw mode of the terminal, for reading single keys, until dropped
+pub(crate) struct RawMode;
+
+impl RawMode {
+    pub(crate) fn enable() -> io::Result<Self> {
+        terminal::enable_raw_mode()?;
+        Ok(RawMode)
+    }
+}
+
+impl Drop for RawMode {
+    fn drop(&mut self) {
+        let _ = terminal::disable_raw_mode();
+    }
+}
+
+/// A key the renderer acts on
+pub(crate) enum Control {
+    Pause,
+    Next,
+    Slower,
+    Faster,
+    Quit,
+}
+
+/// Wait up to `timeout` (for good with `None`) for a key the renderer acts on
+pub(crate) fn read_control(timeout: Option<Duration>) -> io::Result<Option<Control>> {
+    let deadline = timeout.map(|timeout| Instant::now() + timeout);
+    loop {
+        if let Some(deadline) = deadline {
+            if !event::poll(deadline.saturating_duration_since(Instant::now()))? {
+                return Ok(None);
+            }
+        }
+        let Event::Key(key) = event::read()? else {
+            continue;
+        };
+        if key.kind != KeyEventKind::Press {
+            continue;
+        }
+        let control = match key.code {
+            KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => Control::Quit,
+            KeyCode::Char(' ') => Control::Pause,
+            KeyCode::Char('n') => Control::Next,
+            KeyCode::Char('+') | KeyCode::Char('=') => Control::Slower,
+            KeyCode::Char('-') => Control::Faster,
+            KeyCode::Char('q') | KeyCode::Esc => Control::Quit,
+            _ => continue,
+        };
+        return Ok(Some(control));
+    }
+}
+
+impl TerminalRenderer {
+    /// Whether frames are drawn in place with the cursor, rather than printed one
+    /// after the other to a file or pipe
+    fn in_place(&self) -> bool {
+        io::stdout().is_terminal()
+    }
+
+    /// What the grid shows: the agent, the rest of its planned path, the cells it has
+    /// visited, and colors unless turned off or the frames do not go to a terminal
+    fn view<'a>(&'a self, simulation: &'a Simulation) -> GridView<'a> {
+        let (progress, _) = simulation.agent.get_path_progress();
+        GridView {
+            agent: Some(simulation.agent.position),
+            path: simulation
+                .agent
+                .get_current_path()
+                .map_or(&[], |path| &path[progress.min(path.len())..]),
+            visited: &self.trail,
+            seen: None,
+            marks: None,
+            window: None,
+            color: !simulation.config().no_color
+                && self.in_place()
+                && std::env::var_os("NO_COLOR").is_none(),
+        }
+    }
+
+    /// The grid as the agent believes it to be: the map it plans on, with only the
+    /// obstacles it knows of
+    fn belief(simulation: &Simulation) -> Grid {
+        let agent = &simulation.agent;
+        let mut belief = agent.planning_grid(&simulation.grid).clone();
+        belief.replace_cells(Cell::Obstacle, Cell::Empty);
+        for &pos in &agent.known_obstacles {
+            if belief.cell(pos) == Cell::Empty {
+                belief.set_cell(pos, Cell::Obstacle);
+            }
+        }
+        belief
+    }
+
+    /// The grid of the frame, under its first `above` lines, beside the agent's belief
+    /// with `--show-belief` and the planner's latest search with `--debug-planner`,
+    /// under a shared legend. A grid larger than the viewport shows the part around
+    /// the agent, with a minimap of the whole grid beside it when drawn alone;
+    /// `--overview` shrinks the whole grid.
+    fn grids(&self, simulation: &Simulation, above: usize) -> String {
+        let config = simulation.config();
+        let grid = &simulation.grid;
+        let mut view = self.view(simulation);
+        let mut out = String::new();
+        if let Some(scale) = config.overview {
+            out.push_str(&grid.legend(&view));
+            let _ = writeln!(
+                out,
+                "Overview: each character stands for {}x{} cells",
+                scale, scale
+            );
+            out.push_str(&grid.render_overview(&view, scale));
+            return out;
+        }
+
+        let seen = config.show_belief.then_some(&self.seen);
+        out.push_str(&grid.legend(&GridView { seen, ..view }));
+        let trace = match config.debug_planner {
+            true => simulation.search_trace(),
+            false => None,
+        };
+        if config.debug_planner {
+            out.push_str(&search_summary(simulation, trace.as_ref()));
+        }
+        let marks = trace.as_ref().map(search_marks);
+
+        let grids = 1 + config.show_belief as usize + marks.is_some() as usize;
+        let mut minimap = None;
+        if let Some((window, scale)) = self.window(simulation, above + out.lines().count(), grids) {
+            view.window = Some(window);
+            minimap = (grids == 1).then(|| grid.render_overview(&view, scale));
+        }
+        let cells = grid.render_cells(&view);
+        if grids == 1 && minimap.is_none() {
+            out.push_str(&cells);
+            return out;
+        }
+        let title = match view.window {
+            _ if config.show_belief => "Real world".to_string(),
+            Some(window) => format!(
+                "Columns {}-{}, rows {}-{} of {}x{}",
+                window.left,
+                window.columns().end - 1,
+                window.top,
+                window.rows().end - 1,
+                grid.width,
+                grid.height
+            ),
+            None => "Grid".to_string(),
+        };
+        let mut columns = vec![(title, cells)];
+        if config.show_belief {
+            columns.push((
+                "Agent's belief".to_string(),
+                Self::belief(simulation).render_cells(&GridView { seen, ..view }),
+            ));
+        }
+        if let Some(marks) = &marks {
+            // Drawn on the grid the planner searched
+            columns.push((
+                "Planner's latest search".to_string(),
+                simulation
+                    .agent
+                    .planning_grid(grid)
+                    .render_cells(&GridView {
+                        path: &[],
+                        visited: &[],
+                        marks: Some(marks),
+                        ..view
+                    }),
+            ));
+        }
+        if let Some(minimap) = minimap {
+            columns.push(("Minimap".to_string(), minimap));
+        }
+        out.push_str(&side_by_side(&columns));
+        out
+    }
+
+    /// The part of the grid around the agent that the `--viewport` has room for, with
+    /// `grids` grids side by side, and how many times the minimap beside a lone grid
+    /// shrinks it; `None` when the whole grid is drawn
+    fn window(
+        &self,
+        simulation: &Simulation,
+        above: usize,
+        grids: usize,
+    ) -> Option<(Window, usize)> {
+        let config = simulation.config();
+        let grid = &simulation.grid;
+        let (width, height) = match config.viewport {
+            Viewport::Full => return None,
+            Viewport::Size(width, height) => (width, height),
+            Viewport::Auto if !self.in_place() => return None,
+            Viewport::Auto => {
+                let (columns, rows) = terminal::size().ok()?;
+                // Each floor has its number, a header and a blank line, under a title,
+                // and the replan messages below the frame need a few lines
+                let height =
+                    ((rows as usize).saturating_sub(above + 4) / grid.floors).saturating_sub(3);
+                // Each grid has its row numbers, and three spaces between it and the
+                // next grid or the minimap
+                let label = (grid.height - 1).to_string().len().max(2) + 1;
+                let mut room = (columns as usize).saturating_sub((label + 3) * grids);
+                if grids == 1 {
+                    room = room.saturating_sub(grid.width.div_ceil(minimap_scale(grid, height)));
+                }
+                room /= grids;
+                if grid.hex {
+                    room = room.saturating_sub(height);
+                }
+                // Each cell takes two columns
+                (room / 2, height)
+            }
+        };
+        let window = Window::around(
+            simulation.agent.position,
+            width.max(MIN_VIEWPORT),
+            height.max(MIN_VIEWPORT),
+            grid,
+        );
+        window
+            .is_cropped(grid)
+            .then(|| (window, minimap_scale(grid, window.height)))
+    }
+
+    /// Add the cells in the agent's view to the ones it has seen
+    fn look(&mut self, simulation: &Simulation) {
+        if simulation.config().show_belief {
+            self.seen
+                .extend(simulation.agent.visible_cells(&simulation.grid));
+        }
+    }
+
+    /// Show a frame for the delay, or for as long as the run is paused, acting on the
+    /// keys pressed meanwhile. Just waits out the delay without a keyboard.
+    fn pause(&mut self, simulation: &Simulation) {
+        let delay_ms = self.delay_ms.unwrap_or(simulation.config().delay_ms);
+        let raw_mode = match io::stdin().is_terminal() {
+            true => RawMode::enable().ok(),
+            false => None,
+        };
+        if raw_mode.is_none() {
+            thread::sleep(Duration::from_millis(delay_ms));
+            return;
+        }
+        let deadline = Instant::now() + Duration::from_millis(delay_ms);
+        if self.paused {
+            self.status("Paused - space resumes, n shows the next step, q quits");
+        }
+        loop {
+            let timeout = match self.paused {
+                true => None,
+                false => Some(deadline.saturating_duration_since(Instant::now())),
+            };
+            let control = match read_control(timeout) {
+                Ok(Some(control)) => control,
+                // Out of time, or no keys to read after all
+                Ok(None) | Err(_) => break,
+            };
+            match control {
+                Control::Pause if self.paused => {
+                    self.paused = false;
+                    break;
+                }
+                Control::Pause => {
+                    self.paused = true;
+                    self.status("Paused - space resumes, n shows the next step, q quits");
+                }
+                Control::Next => {
+                    self.paused = true;
+                    break;
+                }
+                Control::Slower | Control::Faster => {
+                    let delay_ms = self.delay_ms.unwrap_or(simulation.config().delay_ms);
+                    let delay_ms = match control {
+                        Control::Slower => (delay_ms * 2).max(10),
+                        _ => delay_ms / 2,
+                    };
+                    self.delay_ms = Some(delay_ms);
+                    self.status(&format!("Delay: {}ms", delay_ms));
+                }
+                Control::Quit => {
+                    self.quit = true;
+                    self.paused = false;
+                    break;
+                }
+            }
+        }
+    }
+
+    /// Print a line under the frame while the terminal is in raw mode
+    fn status(&self, message: &str) {
+        print!("{}\r\n", message);
+        let _ = io::stdout().flush();
+    }
+
+    /// The grid and the state of the run after the agent's move
+    fn step_frame(&self, simulation: &Simulation) -> String {
+        let agent = &simulation.agent;
+        let grid = &simulation.grid;
+        let timing_data = simulation.timing_data();
+        let mut frame = String::new();
+        let _ = writeln!(frame, "=== PATHFINDING SIMULATION ===");
+        let _ = writeln!(
+            frame,
+            "Algorithm: {} | Step: {} | Moves: {} | Active obstacle groups: {}",
+            simulation.config().algorithm,
+            simulation.steps(),
+            simulation.statistics().total_moves,
+            simulation.obstacle_groups().len()
+        );
+
+        let (path_progress, path_total) = agent.get_path_progress();
+        let _ = writeln!(
+            frame,
+            "Agent position: ({}, {}) | Path progress: {}/{}",
+            agent.position.x, agent.position.y, path_progress, path_total
+        );
+        if grid.extra_goals.is_empty() {
+            let _ = writeln!(frame, "Goal position: ({}, {})", grid.goal.x, grid.goal.y);
+        } else {
+            let _ = writeln!(frame, "Goal positions (any one): {:?}", grid.goals());
+        }
+        if !grid.waypoints.is_empty() {
+            let _ = writeln!(
+                frame,
+                "Mission leg: {}/{} | Heading for: {:?}",
+                simulation.current_leg() + 1,
+                grid.waypoints.len() + 1,
+                simulation.targets()
+            );
+        }
+        let _ = writeln!(
+            frame,
+            "Original optimal path (A*): {}",
+            simulation.optimal_path_length()
+        );
+        let _ = writeln!(
+            frame,
+            "Obstacle cycle: {} | Cycles until next: {}",
+            simulation.obstacle_cycle(),
+            simulation.cycles_until_next_obstacles()
+        );
+
+        // Show timing info
+
+        if let Some(last) = timing_data.find_path_times.last() {
+            let _ = writeln!(
+                frame,
+                "Last find_path: {:.2?} | Avg find_path: {:.2?}",
+                last,
+                timing_data.average_find_path_time()
+            );
+        }
+
+        let (wall_events_happened, wall_events) = simulation.wall_event_progress();
+        if wall_events > 0 {
+            let _ = writeln!(
+                frame,
+                "Wall events: {}/{} happened",
+                wall_events_happened, wall_events
+            );
+        }
+
+        let companions = simulation.companion_positions();
+        if !companions.is_empty() {
+            let _ = writeln!(
+                frame,
+                "Other agents: {:?} ({}/{} at their goals)",
+                companions
+                    .iter()
+                    .map(|pos| (pos.x, pos.y))
+                    .collect::<Vec<_>>(),
+                simulation.companions_reached(),
+                companions.len()
+            );
+        }
+
+        if let Some(pursuer) = simulation.pursuer() {
+            let _ = writeln!(
+                frame,
+                "Pursuer: ({}, {}) | {} moves",
+                pursuer.position.x, pursuer.position.y, pursuer.moves
+            );
+        }
+
+        let movers = simulation.moving_obstacle_positions();
+        if !movers.is_empty() {
+            let in_view = agent.visible_cells(grid);
+            let _ = writeln!(
+                frame,
+                "Moving obstacles: {} ({} in view)",
+                movers.len(),
+                movers.iter().filter(|cell| in_view.contains(cell)).count()
+            );
+        }
+
+        // Show obstacle group info
+        for (i, group) in simulation.obstacle_groups().iter().enumerate() {
+            let _ = writeln!(
+                frame,
+                "Obstacle group {}: {} obstacles, {} cycles remaining",
+                i + 1,
+                group.positions.len(),
+                group.cycles_remaining
+            );
+        }
+
+        // Show next few moves in current path
+        if let Some(path) = agent.get_current_path() {
+            let (current_idx, _) = agent.get_path_progress();
+            if current_idx + 1 < path.len() {
+                let next_moves: Vec<_> = path.iter().skip(current_idx + 1).take(3).collect();
+                let _ = writeln!(frame, "Next moves: {:?}", next_moves);
+            }
+        }
+
+        frame.push_str(&self.grids(simulation, frame.lines().count()));
+        frame
+    }
+}
+
+/// How many times a minimap shrinks `grid` each way to be at most `MINIMAP_WIDTH`
+/// characters wide and `rows` high
+fn minimap_scale(grid: &Grid, rows: usize) -> usize {
+    grid.width
+        .div_ceil(MINIMAP_WIDTH)
+        .max(grid.height.div_ceil(rows.max(1)))
+        .max(2)
+}
+
+/// Show a frame over the one before: each line is written over the old one and the
+/// rest of the screen cleared, so nothing flickers, in one write to the terminal. A
+/// frame taller than the terminal scrolls it, so the screen is cleared first. Frames
+/// that do not go to a terminal are printed one after the other.
+pub(crate) fn draw_frame(frame: &str) {
+    let mut stdout = io::stdout().lock();
+    if !stdout.is_terminal() {
+        let _ = write!(stdout, "{}", frame);
+        return;
+    }
+    let rows = terminal::size().map_or(u16::MAX, |(_, rows)| rows);
+    if frame.lines().count() >= rows as usize {
+        let _ = queue!(stdout, Clear(ClearType::All), Clear(ClearType::Purge));
+    }
+    let _ = queue!(stdout, cursor::MoveTo(0, 0));
+    for line in frame.lines() {
+        let _ = queue!(
+            stdout,
+            Print(line),
+            Clear(ClearType::UntilNewLine),
+            Print("\r\n")
+        );
+    }
+    let _ = queue!(stdout, Clear(ClearType::FromCursorDown));
+    let _ = stdout.flush();
+}
+
+/// Blocks of lines in columns, each under its title
+pub(crate) fn side_by_side(columns: &[(String, String)]) -> String {
+    let columns: Vec<Vec<&str>> = columns
+        .iter()
+        .map(|(title, block)| [title.as_str()].into_iter().chain(block.lines()).collect())
+        .collect();
+    let widths: Vec<usize> = columns
+        .iter()
+        .map(|lines| {
+            lines
+                .iter()
+                .map(|line| visible_width(line))
+                .max()
+                .unwrap_or(0)
+        })
+        .collect();
+    let rows = columns.iter().map(Vec::len).max().unwrap_or(0);
+    let mut out = String::new();
+    for i in 0..rows {
+        for (j, lines) in columns.iter().enumerate() {
+            let line = lines.get(i).copied().unwrap_or("");
+            out.push_str(line);
+            // The last column is not padded
+            if j + 1 < columns.len() {
+                out.push_str(&" ".repeat(widths[j] - visible_width(line) + 3));
+            }
+        }
+        out.push('\n');
+    }
+    out
+}
+
+/// Colors of the cells a search expanded, from the first expanded to the last
+const SEARCH_HEAT: [Color; 5] = [
+    Color::DarkBlue,
+    Color::Blue,
+    Color::Cyan,
+    Color::Green,
+    Color::Yellow,
+];
+
+/// What `--debug-planner` draws of a search: the cells it expanded as `x`, colored
+/// from blue (first) to yellow (last), A*'s open list as `o`, D* Lite's inconsistent
+/// cells (g ≠ rhs) as `!` and the cells whose costs it kept from earlier searches as
+/// `+`
+fn search_marks(trace: &SearchTrace) -> HashMap<Position, (char, Color)> {
+    let mut marks: HashMap<Position, (char, Color)> = trace
+        .costs
+        .keys()
+        .map(|&pos| (pos, ('+', Color::DarkGreen)))
+        .collect();
+    marks.extend(trace.open.iter().map(|&pos| (pos, ('o', Color::Magenta))));
+    for (i, &pos) in trace.expanded.iter().enumerate() {
+        let heat = SEARCH_HEAT[i * SEARCH_HEAT.len() / trace.expanded.len()];
+        marks.insert(pos, ('x', heat));
+    }
+    for (&pos, &(g, rhs)) in &trace.costs {
+        if g != rhs {
+            marks.insert(pos, ('!', Color::Red));
+        }
+    }
+    marks
+}
+
+/// The legend of `search_marks` and what the latest search did, or why there is
+/// nothing to draw
+fn search_summary(simulation: &Simulation, trace: Option<&SearchTrace>) -> String {
+    let Some(trace) = trace else {
+        return format!(
+            "The {} planner cannot show its searches; --debug-planner draws those of a_star and d_star_lite\n",
+            simulation.config().algorithm
+        );
+    };
+    let mut out = String::from(
+        "Search: x=Expanded (blue first, yellow last), o=Open, !=Inconsistent (g != rhs), +=Cost kept from an earlier search\n",
+    );
+    let _ = write!(
+        out,
+        "Latest search: {} expanded, {} open",
+        trace.expanded.len(),
+        trace.open.len()
+    );
+    if !trace.costs.is_empty() {
+        let expanded: HashSet<&Position> = trace.expanded.iter().collect();
+        let _ = write!(
+            out,
+            ", {} inconsistent, {} costs kept from earlier searches",
+            trace.costs.values().filter(|(g, rhs)| g != rhs).count(),
+            trace
+                .costs
+                .keys()
+                .filter(|pos| !expanded.contains(pos))
+                .count()
+        );
+    }
+    out.push('\n');
+    out
+}
+
+/// Characters of a line that take up space on the screen, leaving out the color
+/// escapes
+pub(crate) fn visible_width(line: &str) -> usize {
+    let mut width = 0;
+    let mut chars = line.chars();
+    while let Some(c) = chars.next() {
+        if c == '\u{1b}' {
+            // An escape runs up to its final letter
+            chars.by_ref().find(|c| c.is_ascii_alphabetic());
+        } else {
+            width += 1;
+        }
+    }
+    width
+}
+
+impl SimulationObserver for TerminalRenderer {
+    fn on_start(&mut self, simulation: &Simulation) {
+        self.trail = vec![simulation.agent.position];
+        self.seen.clear();
+        self.look(simulation);
+        let config = simulation.config();
+        if config.no_visualization || config.batch_mode {
+            return;
+        }
+        if self.in_place() {
+            let _ = execute!(io::stdout(), Clear(ClearType::All), cursor::Hide);
+        }
+        let mut frame = String::new();
+        let _ = writeln!(frame, "=== PATHFINDING SIMULATION ===");
+        let _ = writeln!(
+            frame,
+            "Algorithm: {} | Step: 0 | Moves: 0 | Active obstacle groups: 0",
+            config.algorithm
+        );
+        let _ = writeln!(
+            frame,
+            "Optimal path length (A*): {}",
+            simulation.optimal_path_length()
+        );
+        frame.push_str(&self.grids(simulation, frame.lines().count()));
+        draw_frame(&frame);
+        self.pause(simulation);
+    }
+
+    fn on_replan(&mut self, simulation: &Simulation, replan: &Replan) {
+        if simulation.config().no_visualization {
+            return;
+        }
+        if !replan.routine {
+            println!("Path blocked or environment changed - recalculating...");
+        }
+        match replan.stuck_attempts {
+            0 => println!(
+                "New path found with {} steps",
+                simulation.agent.get_current_path().map_or(0, Vec::len)
+            ),
+            attempts if attempts <= MAX_STUCK_ATTEMPTS => println!(
+                "No path found - waiting... (attempt {}/{})",
+                attempts, MAX_STUCK_ATTEMPTS
+            ),
+            _ => {}
+        }
+    }
+
+    fn on_step(&mut self, simulation: &Simulation, outcome: StepOutcome) {
+        if let StepOutcome::Moved { to, .. } = outcome {
+            self.trail.push(to);
+        }
+        self.look(simulation);
+        let config = simulation.config();
+        match outcome {
+            StepOutcome::Moved { .. } if !config.no_visualization && !config.batch_mode => {
+                draw_frame(&self.step_frame(simulation));
+                self.pause(simulation);
+            }
+            _ if config.no_visualization => {}
+            StepOutcome::Failed(Failure::Stuck) => println!(
+                "FAILURE: Agent permanently stuck after {} attempts",
+                MAX_STUCK_ATTEMPTS
+            ),
+            StepOutcome::Failed(Failure::Caught) => {
+                println!("FAILURE: The pursuer caught the agent")
+            }
+            StepOutcome::Failed(Failure::StepLimit) => {
+                println!("Reached max iterations, stopping simulation")
+            }
+            StepOutcome::Failed(Failure::TimedOut) => {
+                println!("Reached the simulation timeout, stopping simulation")
+            }
+            _ => {}
+        }
+    }
+
+    fn on_finish(&mut self, simulation: &Simulation) {
+        // A run without an initial path never started
+        if simulation.config().no_visualization
+            || simulation.outcome() == Some(StepOutcome::Failed(Failure::NoPath))
+        {
+            return;
+        }
+        let stats = simulation.statistics();
+        let timing_data = simulation.timing_data();
+        let agent = &simulation.agent;
+        let mut frame = String::new();
+        let _ = writeln!(frame, "=== SIMULATION COMPLETE ===");
+        if simulation.is_mission_complete() {
+            let _ = writeln!(frame, "SUCCESS: Agent reached the goal!");
+        } else if !simulation.is_over() {
+            let _ = writeln!(frame, "STOPPED: The run was stopped before it ended");
+        } else if stats.out_of_energy {
+            let _ = writeln!(
+                frame,
+                "FAILED: Agent ran out of energy before reaching the goal"
+            );
+        } else if stats.timed_out {
+            let _ = writeln!(
+                frame,
+                "FAILED: The run timed out before the agent reached the goal"
+            );
+        } else if stats.caught {
+            let _ = writeln!(
+                frame,
+                "FAILED: The pursuer caught the agent before it reached the goal"
+            );
+        } else {
+            let _ = writeln!(frame, "FAILED: Agent did not reach the goal");
+        }
+        let companions = simulation.companion_positions().len();
+        if companions > 0 {
+            let _ = writeln!(
+                frame,
+                "Agents at their goals: {}/{}",
+                stats.agents_reached(simulation.is_mission_complete()),
+                companions + 1
+            );
+        }
+        let _ = writeln!(frame, "Algorithm: {}", simulation.config().algorithm);
+        let _ = writeln!(
+            frame,
+            "Final position: ({}, {})",
+            agent.position.x, agent.position.y
+        );
+        let _ = writeln!(
+            frame,
+            "Total steps: {} | Total moves: {}",
+            simulation.steps(),
+            stats.total_moves
+        );
+        let _ = writeln!(
+            frame,
+            "Original optimal path (A*): {}",
+            simulation.optimal_path_length()
+        );
+        let _ = writeln!(
+            frame,
+            "Path recalculations: {}",
+            timing_data.find_path_times.len()
+        );
+
+        // Show timing summary
+        let _ = writeln!(
+            frame,
+            "Average find_path time: {:.2?}",
+            timing_data.average_find_path_time()
+        );
+
+        // Calculate final optimal path
+        let (final_optimal_length, _) =
+            Simulation::calculate_optimal_path_with_astar(&simulation.grid);
+        let _ = writeln!(frame, "Final optimal path (A*): {}", final_optimal_length);
+
+        frame.push_str(&self.grids(simulation, frame.lines().count()));
+        draw_frame(&frame);
+        if self.in_place() {
+            let _ = execute!(io::stdout(), cursor::Show);
+        }
+    }
+
+    fn stop_requested(&self) -> bool {
+        self.quit
+    }
+}
@@ -0,0 +1,141 @@
+use crate::config::Config;
+use crate::grid::{Grid, MetricKind, Position};
+
+/// Which cells within the vision radius the agent sees.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VisionShape {
+    /// Every cell at most `radius` columns and rows away (on a hex grid, at most
+    /// `radius` moves away).
+    Square,
+    /// Every cell whose center is at most `radius` cell widths away.
+    Circle,
+    /// Like `Circle`, but walls hide the cells behind them.
+    LineOfSight,
+}
+
+impl VisionShape {
+    /// Parses a vision shape name as given on the command line.
+    pub fn from_name(name: &str) -> Result<Self, String> {
+        match name {
+            "square" => Ok(VisionShape::Square),
+            "circle" => Ok(VisionShape::Circle),
+            "line_of_sight" => Ok(VisionShape::LineOfSight),
+            _ => Err(format!(
+                "Unknown vision shape: '{}' (expected 'square', 'circle' or 'line_of_sight')",
+                name
+            )),
+        }
+    }
+
+    /// Name of the vision shape as given on the command line.
+    pub fn name(self) -> &'static str {
+        match self {
+            VisionShape::Square => "square",
+            VisionShape::Circle => "circle",
+            VisionShape::LineOfSight => "line_of_sight",
+        }
+    }
+}
+
+/// How far around itself the agent sees obstacles.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Vision {
+    pub radius: usize,
+    pub shape: VisionShape,
+}
+
+impl Vision {
+    /// Vision for `--vision-radius` and `--vision-shape`, or `None` without a radius,
+    /// when the agent only sees the cells it can step to next
+    pub fn from_config(config: &Config) -> Result<Option<Self>, String> {
+        let shape = VisionShape::from_name(&config.vision_shape)?;
+        Ok(config.vision_radius.map(|radius| Vision { radius, shape }))
+    }
+
+    /// Cells seen from `pos`: those in range on its own floor, plus the stairs and
+    /// portal exits it can step to, which are never hidden
+    pub fn visible_cells(&self, grid: &Grid, pos: Position) -> Vec<Position> {
+        let metric = grid.metric();
+        let radius = self.radius as i32;
+        let mut visible = Vec::new();
+        for dx in -radius..=radius {
+            for dy in -radius..=radius {
+                let Some(cell) = grid.offset(pos, dx, dy) else {
+                    continue;
+                };
+                let in_range = match self.shape {
+                    VisionShape::Square if metric.kind == MetricKind::Hex => {
+                        metric.distance(pos, cell) <= self.radius as u32
+                    }
+                    VisionShape::Square => true,
+                    VisionShape::Circle | VisionShape::LineOfSight => {
+                        metric.euclidean(pos, cell) <= self.radius as f64
+                    }
+                };
+                if in_range
+                    && cell != pos
+                    && !visible.contains(&cell)
+                    && (self.shape != VisionShape::LineOfSight || in_sight(grid, pos, dx, dy))
+                {
+                    visible.push(cell);
+                }
+            }
+        }
+        for link in grid.links(&pos) {
+            if !visible.contains(&link) {
+                visible.push(link);
+            }
+        }
+        visible
+    }
+}
+
+/// Whether the cell `dx` columns and `dy` rows away can be seen from `pos`: no wall
+/// on the cells a straight line between their centers passes over, the two ends
+/// aside. The line is sampled twice per cell, which catches every cell it crosses
+/// by more than a sliver.
+fn in_sight(grid: &Grid, pos: Position, dx: i32, dy: i32) -> bool {
+    let samples = 2 * dx.abs().max(dy.abs());
+    (1..samples).all(|i| {
+        let t = i as f64 / samples as f64;
+        let (x, y) = (
+            (dx as f64 * t).round() as i32,
+            (dy as f64 * t).round() as i32,
+        );
+        (x, y) == (0, 0)
+            || (x, y) == (dx, dy)
+            || grid
+                .offset(pos, x, y)
+                .is_some_and(|cell| !grid.is_wall(cell))
+    })
+}
+
+/// Chances of a wrong reading each time the agent looks at a cell in view.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SensorNoise {
+    /// Chance of not noticing an obstacle that is there
+    pub miss_rate: f64,
+    /// Chance of seeing an obstacle on a free cell that is not a wall, a goal or a
+    /// waypoint
+    pub false_alarm_rate: f64,
+}
+
+impl SensorNoise {
+    /// Noise for `--miss-rate` and `--false-alarm-rate`, or `None` when both are 0
+    pub fn from_config(config: &Config) -> Result<Option<Self>, String> {
+        for (option, rate) in [
+            ("--miss-rate", config.miss_rate),
+            ("--false-alarm-rate", config.false_alarm_rate),
+        ] {
+            if !(0.0..=1.0).contains(&rate) {
+                return Err(format!("{} must be between 0 and 1", option));
+            }
+        }
+        Ok(
+            (config.miss_rate > 0.0 || config.false_alarm_rate > 0.0).then_some(SensorNoise {
+                miss_rate: config.miss_rate,
+                false_alarm_rate: config.false_alarm_rate,
+            }),
+        )
+    }
+}
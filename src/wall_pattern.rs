@@ -0,0 +1,188 @@
+use crate::grid::Position;
+use rand::Rng;
+use std::collections::HashSet;
+
+/// Where the `--num-walls` walls of a random-style map are placed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WallPattern {
+    /// Every wall on a uniformly random cell.
+    Random,
+    /// Compact blobs of 3 to 8 walls, each grown from a random cell.
+    Clustered,
+    /// Straight horizontal or vertical segments, like the walls of a building.
+    Corridors,
+    /// Mostly near the edges of the grid, leaving its middle fairly open.
+    Border,
+}
+
+impl WallPattern {
+    /// Parses a wall pattern name as given on the command line.
+    pub fn from_name(name: &str) -> Result<Self, String> {
+        match name {
+            "random" => Ok(WallPattern::Random),
+            "clustered" => Ok(WallPattern::Clustered),
+            "corridors" => Ok(WallPattern::Corridors),
+            "border" => Ok(WallPattern::Border),
+            _ => Err(format!(
+                "Unknown wall pattern: '{}' (expected 'random', 'clustered', 'corridors' or 'border')",
+                name
+            )),
+        }
+    }
+
+    /// Parses a comma-separated list of wall pattern names.
+    pub fn from_names(names: &str) -> Result<Vec<Self>, String> {
+        names
+            .split(',')
+            .map(|name| Self::from_name(name.trim()))
+            .collect()
+    }
+
+    /// Name of the wall pattern as given on the command line.
+    pub fn name(self) -> &'static str {
+        match self {
+            WallPattern::Random => "random",
+            WallPattern::Clustered => "clustered",
+            WallPattern::Corridors => "corridors",
+            WallPattern::Border => "border",
+        }
+    }
+
+    /// Place up to `count` walls on cells other than the `reserved` ones. Each floor
+    /// is picked at random per wall (or per blob or segment).
+    pub fn place<R: Rng>(
+        self,
+        rng: &mut R,
+        width: usize,
+        height: usize,
+        floors: usize,
+        count: usize,
+        reserved: &[Position],
+    ) -> HashSet<Position> {
+        let mut walls = HashSet::new();
+        let mut attempts = 0;
+        let max_attempts = match self {
+            WallPattern::Random => count * 3,
+            _ => count * 10,
+        };
+
+        while walls.len() < count && attempts < max_attempts {
+            attempts += 1;
+            let shape = match self {
+                WallPattern::Random => {
+                    let (x, y) = (rng.gen_range(0..width), rng.gen_range(0..height));
+                    let z = random_floor(rng, floors);
+                    vec![Position { x, y, z }]
+                }
+                WallPattern::Clustered => {
+                    let z = random_floor(rng, floors);
+                    blob(rng, width, height, z)
+                }
+                WallPattern::Corridors => {
+                    let z = random_floor(rng, floors);
+                    segment(rng, width, height, z)
+                }
+                WallPattern::Border => {
+                    let z = random_floor(rng, floors);
+                    vec![near_border(rng, width, height, z)]
+                }
+            };
+
+            for pos in shape {
+                if walls.len() < count && !reserved.contains(&pos) {
+                    walls.insert(pos);
+                }
+            }
+        }
+        walls
+    }
+}
+
+/// Random floor for a wall; single-floor grids draw nothing, so seeded environments
+/// are the same as before floors existed.
+fn random_floor<R: Rng>(rng: &mut R, floors: usize) -> usize {
+    if floors > 1 {
+        rng.gen_range(0..floors)
+    } else {
+        0
+    }
+}
+
+/// A blob grown from a random cell by repeatedly adding a neighbour of a cell
+/// already in it.
+fn blob<R: Rng>(rng: &mut R, width: usize, height: usize, z: usize) -> Vec<Position> {
+    let size = rng.gen_range(3..=8);
+    let mut cells = vec![Position {
+        x: rng.gen_range(0..width),
+        y: rng.gen_range(0..height),
+        z,
+    }];
+    for _ in 0..size * 4 {
+        if cells.len() >= size {
+            break;
+        }
+        let from = cells[rng.gen_range(0..cells.len())];
+        let (dx, dy) = [(1, 0), (-1, 0), (0, 1), (0, -1)][rng.gen_range(0..4)];
+        let (x, y) = (from.x as i32 + dx, from.y as i32 + dy);
+        if x >= 0 && y >= 0 && (x as usize) < width && (y as usize) < height {
+            let pos = Position {
+                x: x as usize,
+                y: y as usize,
+                z,
+            };
+            if !cells.contains(&pos) {
+                cells.push(pos);
+            }
+        }
+    }
+    cells
+}
+
+/// A horizontal or vertical segment between 4 cells and half the grid long.
+fn segment<R: Rng>(rng: &mut R, width: usize, height: usize, z: usize) -> Vec<Position> {
+    let (x, y) = (rng.gen_range(0..width), rng.gen_range(0..height));
+    if rng.gen_bool(0.5) {
+        let length = rng.gen_range(4..=(width / 2).max(4));
+        (x..(x + length).min(width))
+            .map(|x| Position { x, y, z })
+            .collect()
+    } else {
+        let length = rng.gen_range(4..=(height / 2).max(4));
+        (y..(y + length).min(height))
+            .map(|y| Position { x, y, z })
+            .collect()
+    }
+}
+
+/// A cell in the outer fifth of the grid four times out of five, otherwise anywhere.
+fn near_border<R: Rng>(rng: &mut R, width: usize, height: usize, z: usize) -> Position {
+    let (x, y) = (rng.gen_range(0..width), rng.gen_range(0..height));
+    if !rng.gen_bool(0.8) {
+        return Position { x, y, z };
+    }
+    let band = |side: usize| (side / 5).max(1);
+    // Snap one coordinate into the band along the nearest edge
+    if rng.gen_bool(0.5) {
+        let depth = rng.gen_range(0..band(width));
+        Position {
+            x: if x < width / 2 {
+                depth
+            } else {
+                width - 1 - depth
+            },
+            y,
+            z,
+        }
+    } else {
+        let depth = rng.gen_range(0..band(height));
+        Position {
+            x,
+            y: if y < height / 2 {
+                depth
+            } else {
+                height - 1 - depth
+            },
+            z,
+        }
+    }
+}
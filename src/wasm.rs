@@ -0,0 +1,86 @@
+use crate::config::{Cli, Config};
+use crate::frames::{grid_frame, state_frame};
+use crate::grid::Position;
+use crate::run_log::failure_name;
+use crate::simulation::{Simulation, StepOutcome};
+use clap::Parser;
+use wasm_bindgen::prelude::*;
+
+/// A run for JavaScript, in builds with the `wasm` feature: a page steps it at its own
+/// pace and draws the frames it returns.
+///
+/// ```js
+/// const run = new WasmSimulation(JSON.stringify({ grid_size: 40, num_walls: 300 }));
+/// while (!run.is_over()) { run.step(); draw(JSON.parse(run.grid_state())); }
+/// ```
+#[wasm_bindgen]
+pub struct WasmSimulation {
+    simulation: Simulation,
+    /// Cells the agent has stood on, in the order it came to them
+    trail: Vec<Position>,
+}
+
+#[wasm_bindgen]
+impl WasmSimulation {
+    /// A run with the options of a JSON object by long name (`{"grid_size": 40}`),
+    /// the rest at their defaults. Without a `seed`, each run is a new one.
+    #[wasm_bindgen(constructor)]
+    pub fn new(options: &str) -> Result<WasmSimulation, JsError> {
+        let settings: toml::Table = match options.trim() {
+            "" => toml::Table::new(),
+            options => serde_json::from_str(options)
+                .map_err(|e| JsError::new(&format!("Invalid options: {}", e)))?,
+        };
+        let mut command_line = vec![env!("CARGO_PKG_NAME").to_string()];
+        command_line.extend(Cli::table_args(&settings).map_err(|e| JsError::new(&e))?);
+        let mut config = Config::try_parse_from(command_line)
+            .map_err(|e| JsError::new(&format!("Invalid options: {}", e)))?;
+        config.seed.get_or_insert_with(rand::random);
+        config.no_visualization = true;
+        config.quiet = true;
+        let mut simulation = Simulation::new(config)?;
+        // The page draws the run itself
+        simulation.clear_observers();
+        let trail = vec![simulation.agent.position];
+        Ok(WasmSimulation { simulation, trail })
+    }
+
+    /// Take one step, and say how it went: `moved`, `waited`, `finished` or a failure
+    /// such as `stuck`. A run that is over stays over.
+    pub fn step(&mut self) -> String {
+        let outcome = self.simulation.step();
+        if let StepOutcome::Moved { to, .. } = outcome {
+            self.trail.push(to);
+        }
+        match outcome {
+            StepOutcome::Moved { .. } => "moved".to_string(),
+            StepOutcome::Waited { .. } => "waited".to_string(),
+            StepOutcome::Finished => "finished".to_string(),
+            StepOutcome::Failed(failure) => failure_name(failure).to_string(),
+        }
+    }
+
+    /// The grid and the state of the run as one JSON object: the fields of the
+    /// web viewer's `grid` and `state` frames, with the whole trail
+    pub fn grid_state(&self) -> String {
+        let mut frame = grid_frame(&self.simulation);
+        if let (Some(frame), serde_json::Value::Object(state)) = (
+            frame.as_object_mut(),
+            state_frame(&self.simulation, &self.trail, false),
+        ) {
+            frame.extend(state);
+            frame.remove("type");
+        }
+        frame.to_string()
+    }
+
+    /// Whether the run is over
+    pub fn is_over(&self) -> bool {
+        self.simulation.is_over()
+    }
+
+    /// Steps taken
+    pub fn steps(&self) -> usize {
+        self.simulation.steps()
+    }
+}
@@ -0,0 +1,217 @@
+use crate::frames::{grid_frame, state_frame};
+use crate::grid::Position;
+use crate::observer::SimulationObserver;
+use crate::simulation::{Simulation, StepOutcome};
+use serde_json::Value;
+use std::io::{self, Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread;
+use std::time::Duration;
+use tungstenite::{Message, WebSocket};
+
+/// The page that draws the run on a canvas, served at `/`
+const VIEWER_PAGE: &str = include_str!("web_viewer.html");
+
+/// Longest request head read before deciding what a connection asks for
+const MAX_HEAD: usize = 8192;
+
+/// Times a connection's request head is looked at before giving up on it
+const HEAD_ATTEMPTS: usize = 200;
+
+/// The browsers watching a run, and the frames a browser that connects mid-run is
+/// sent first
+#[derive(Default)]
+struct Viewers {
+    sockets: Vec<WebSocket<TcpStream>>,
+    /// The latest `grid` frame
+    grid: Option<String>,
+    /// The latest `state` frame, with the whole trail so far
+    state: Option<String>,
+}
+
+/// Serves the run to browsers (`--serve`): the page at `/` connects to the
+/// WebSocket at `/ws` and draws every frame it is sent on a canvas, scaled to the
+/// window, so grids far larger than a terminal stay watchable.
+///
+/// The frames are those of `frames`: a `grid` frame at the start, and again whenever
+/// walls change, and a `state` frame at the start and after every step, with the
+/// cells added to the agent's trail since the last one.
+///
+/// The run waits for a browser before its first step. Without a terminal view to
+/// set the pace, each step is shown for `--delay-ms`.
+pub struct WebViewer {
+    address: SocketAddr,
+    viewers: Arc<(Mutex<Viewers>, Condvar)>,
+    /// Cells the agent has stood on, in the order it came to them
+    trail: Vec<Position>,
+    /// How much of the trail the browsers have been sent
+    sent: usize,
+}
+
+impl WebViewer {
+    /// Serve the page and the frames of the run at `address`, from threads of their
+    /// own
+    pub fn serve(address: &str) -> Result<Self, String> {
+        let listener = TcpListener::bind(address)
+            .map_err(|e| format!("Cannot serve the viewer at {}: {}", address, e))?;
+        let local = listener
+            .local_addr()
+            .map_err(|e| format!("Cannot serve the viewer at {}: {}", address, e))?;
+        let viewers = Arc::new((Mutex::new(Viewers::default()), Condvar::new()));
+        let shared = Arc::clone(&viewers);
+        thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                let viewers = Arc::clone(&shared);
+                // A client that goes away mid-answer only loses its own answer
+                thread::spawn(move || answer(stream, &viewers));
+            }
+        });
+        Ok(WebViewer {
+            address: local,
+            viewers,
+            trail: Vec::new(),
+            sent: 0,
+        })
+    }
+
+    /// Address bound, which has the port picked for port 0
+    pub fn address(&self) -> SocketAddr {
+        self.address
+    }
+
+    /// Wait until a browser is watching
+    fn wait_for_viewer(&self) {
+        let (viewers, connected) = &*self.viewers;
+        let Ok(viewers) = viewers.lock() else {
+            return;
+        };
+        drop(connected.wait_while(viewers, |viewers| viewers.sockets.is_empty()));
+    }
+
+    /// Send a frame to every browser, keeping it as the latest of its kind for
+    /// browsers that connect later (`catch_up` in its place, if given)
+    fn broadcast(&self, frame: &Value, catch_up: Option<&Value>) {
+        let text = frame.to_string();
+        let (viewers, _) = &*self.viewers;
+        let Ok(mut viewers) = viewers.lock() else {
+            return;
+        };
+        let latest = catch_up.map_or_else(|| text.clone(), Value::to_string);
+        match frame["type"].as_str() {
+            Some("grid") => viewers.grid = Some(latest),
+            _ => viewers.state = Some(latest),
+        }
+        // Browsers that went away are dropped
+        viewers
+            .sockets
+            .retain_mut(|socket| socket.send(Message::Text(text.clone())).is_ok());
+    }
+
+    /// Send the grid as it is now
+    fn send_grid(&self, simulation: &Simulation) {
+        self.broadcast(&grid_frame(simulation), None);
+    }
+
+    /// Send the state of the run, with the cells the trail gained since the last one;
+    /// a run `stopped` without an outcome was stopped before it was over
+    fn send_state(&mut self, simulation: &Simulation, stopped: bool) {
+        let frame = state_frame(simulation, &self.trail[self.sent..], stopped);
+        let catch_up = state_frame(simulation, &self.trail, stopped);
+        self.broadcast(&frame, Some(&catch_up));
+        self.sent = self.trail.len();
+    }
+}
+
+/// Answer a connection: the page for GET /, a WebSocket of the run's frames for an
+/// upgrade, 404 for anything else
+fn answer(mut stream: TcpStream, viewers: &(Mutex<Viewers>, Condvar)) -> io::Result<()> {
+    let head = request_head(&stream)?;
+    if head.to_ascii_lowercase().contains("upgrade: websocket") {
+        let mut socket = tungstenite::accept(stream).map_err(io::Error::other)?;
+        let (viewers, connected) = viewers;
+        let mut viewers = viewers.lock().map_err(|_| io::Error::other("poisoned"))?;
+        // Browsers joining mid-run are brought up to date first
+        for frame in [&viewers.grid, &viewers.state].into_iter().flatten() {
+            socket
+                .send(Message::Text(frame.clone()))
+                .map_err(io::Error::other)?;
+        }
+        viewers.sockets.push(socket);
+        connected.notify_all();
+        return Ok(());
+    }
+
+    // The request is read before answering, so closing the connection does not
+    // reset it
+    stream.read_exact(&mut vec![0; head.len()])?;
+    let path = head.split_whitespace().nth(1).unwrap_or("");
+    let (status, content_type, body) = match path.split('?').next().unwrap_or("") {
+        "/" | "/index.html" => ("200 OK", "text/html; charset=utf-8", VIEWER_PAGE),
+        _ => (
+            "404 Not Found",
+            "text/plain; charset=utf-8",
+            "The viewer is served at /\n",
+        ),
+    };
+    write!(
+        stream,
+        "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        content_type,
+        body.len(),
+        body
+    )
+}
+
+/// The head of the request on a connection, up to its blank line, without reading
+/// it off the connection
+fn request_head(stream: &TcpStream) -> io::Result<String> {
+    let mut buffer = vec![0; MAX_HEAD];
+    for _ in 0..HEAD_ATTEMPTS {
+        let read = stream.peek(&mut buffer)?;
+        let head = String::from_utf8_lossy(&buffer[..read]);
+        if let Some(end) = head.find("\r\n\r\n") {
+            return Ok(head[..end + 4].to_string());
+        }
+        if read == 0 || read == MAX_HEAD {
+            break;
+        }
+        thread::sleep(Duration::from_millis(5));
+    }
+    Err(io::Error::new(
+        io::ErrorKind::InvalidData,
+        "no complete request head",
+    ))
+}
+
+impl SimulationObserver for WebViewer {
+    fn on_start(&mut self, simulation: &Simulation) {
+        self.trail = vec![simulation.agent.position];
+        self.sent = 0;
+        self.send_grid(simulation);
+        self.send_state(simulation, false);
+        self.wait_for_viewer();
+    }
+
+    fn on_obstacle_change(&mut self, simulation: &Simulation, changed_walls: &[Position]) {
+        if !changed_walls.is_empty() {
+            self.send_grid(simulation);
+        }
+    }
+
+    fn on_step(&mut self, simulation: &Simulation, outcome: StepOutcome) {
+        if let StepOutcome::Moved { to, .. } = outcome {
+            self.trail.push(to);
+        }
+        self.send_state(simulation, false);
+        let config = simulation.config();
+        if config.no_visualization && !outcome.is_over() {
+            thread::sleep(Duration::from_millis(config.delay_ms));
+        }
+    }
+
+    fn on_finish(&mut self, simulation: &Simulation) {
+        self.send_state(simulation, true);
+    }
+}
@@ -23,9 +23,6 @@ CSV_FILES=()
 mkdir -p "$OUTPUT_DIR"
 cd "$OUTPUT_DIR" || exit
 
-# Initialize CSV header file
-echo "simulation_id,algorithm,grid_size,num_walls,num_obstacles,success,total_moves,optimal_path_length,route_efficiency,execution_time_ms,a_star_calls,d_star_calls,average_find_path_time_ns,total_pathfinding_calls" > "$FINAL_CSV"
-
 # Logging function
 log() {
     echo "[$(date '+%Y-%m-%d %H:%M:%S')] $*" | tee -a "$LOG_FILE"
@@ -98,6 +95,9 @@ cleanup_and_combine() {
     if [[ ${#CSV_FILES[@]} -gt 0 ]]; then
         log "Combining CSV files into $FINAL_CSV"
 
+        # Every batch writes the same columns, so take the header from the first file
+        head -n 1 "${CSV_FILES[0]}" > "$FINAL_CSV"
+
         # Add data from all files (skip headers)
         for file in "${CSV_FILES[@]}"; do
             if [[ -f "$file" ]]; then
@@ -161,96 +161,96 @@ update_progress 1 "60x60 Grids with Medium and High Obstacle Scaling"
 
 # 60x60 grid (3,600 squares) - Medium obstacles: 10-20% = 360-720
 run_test "Grid_60x60_Medium_Obstacles" "phase1_60x60_med.csv" \
-    --batch-mode --algorithm all --grid-size 60 \
+    batch --algorithm all --grid-size 60 \
     --min-walls 180 --max-walls 360 --min-obstacles 360 --max-obstacles 720 \
-    --num-simulations 40 --timeout-seconds 1800 --output-file "phase1_60x60_med.csv" --quiet --no-visualization
+    --num-simulations 40 --timeout-seconds 1800 --output-file "phase1_60x60_med.csv" --quiet
 
 # 60x60 grid (3,600 squares) - High obstacles: 40-50% = 1,440-1,800
 run_test "Grid_60x60_High_Obstacles" "phase1_60x60_high.csv" \
-    --batch-mode --algorithm all --grid-size 60 \
+    batch --algorithm all --grid-size 60 \
     --min-walls 360 --max-walls 720 --min-obstacles 1440 --max-obstacles 1800 \
-    --num-simulations 40 --timeout-seconds 1800 --output-file "phase1_60x60_high.csv" --quiet --no-visualization
+    --num-simulations 40 --timeout-seconds 1800 --output-file "phase1_60x60_high.csv" --quiet
 
 update_progress 2 "Large Grids with Exponential Obstacle Scaling"
 
 # 120x120 grid (14,400 squares) - Low obstacles: 5-10% = 720-1,440
 run_test "Grid_120x120_Low_Obstacles" "phase2_120x120_low.csv" \
-    --batch-mode --algorithm all --grid-size 120 \
+    batch --algorithm all --grid-size 120 \
     --min-walls 360 --max-walls 720 --min-obstacles 720 --max-obstacles 1440 \
-    --num-simulations 50 --timeout-seconds 3600 --output-file "phase2_120x120_low.csv" --quiet --no-visualization
+    --num-simulations 50 --timeout-seconds 3600 --output-file "phase2_120x120_low.csv" --quiet
 
 # 120x120 grid (14,400 squares) - Medium obstacles: 10-20% = 1,440-2,880
 run_test "Grid_120x120_Medium_Obstacles" "phase2_120x120_med.csv" \
-    --batch-mode --algorithm all --grid-size 120 \
+    batch --algorithm all --grid-size 120 \
     --min-walls 720 --max-walls 1440 --min-obstacles 1440 --max-obstacles 2880 \
-    --num-simulations 50 --timeout-seconds 3600 --output-file "phase2_120x120_med.csv" --quiet --no-visualization
+    --num-simulations 50 --timeout-seconds 3600 --output-file "phase2_120x120_med.csv" --quiet
 
 # 120x120 grid (14,400 squares) - High obstacles: 20-30% = 2,880-4,320
 run_test "Grid_120x120_High_Obstacles" "phase2_120x120_high.csv" \
-    --batch-mode --algorithm all --grid-size 120 \
+    batch --algorithm all --grid-size 120 \
     --min-walls 1440 --max-walls 2160 --min-obstacles 2880 --max-obstacles 4320 \
-    --num-simulations 50 --timeout-seconds 3600 --output-file "phase2_120x120_high.csv" --quiet --no-visualization
+    --num-simulations 50 --timeout-seconds 3600 --output-file "phase2_120x120_high.csv" --quiet
 
 # 120x120 grid (14,400 squares) - Very high obstacles: 30-40% = 4,320-5,760
 run_test "Grid_120x120_Very_High_Obstacles" "phase2_120x120_veryhigh.csv" \
-    --batch-mode --algorithm all --grid-size 120 \
+    batch --algorithm all --grid-size 120 \
     --min-walls 2160 --max-walls 2880 --min-obstacles 4320 --max-obstacles 5760 \
-    --num-simulations 40 --timeout-seconds 3600 --output-file "phase2_120x120_veryhigh.csv" --quiet --no-visualization
+    --num-simulations 40 --timeout-seconds 3600 --output-file "phase2_120x120_veryhigh.csv" --quiet
 
 # PHASE 3: Massive Grids (180x180 to 240x240) - ~5 hours
 update_progress 3 "Massive Grids with Exponential Obstacle Scaling"
 
 # 180x180 grid (32,400 squares) - Moderate obstacles: 10-20% = 3,240-6,480
 run_test "Grid_180x180_Moderate_Obstacles" "phase3_180x180_mod.csv" \
-    --batch-mode --algorithm all --grid-size 180 \
+    batch --algorithm all --grid-size 180 \
     --min-walls 1620 --max-walls 3240 --min-obstacles 3240 --max-obstacles 6480 \
-    --num-simulations 40 --timeout-seconds 5400 --output-file "phase3_180x180_mod.csv" --quiet --no-visualization
+    --num-simulations 40 --timeout-seconds 5400 --output-file "phase3_180x180_mod.csv" --quiet
 
 # 200x200 grid (40,000 squares) - High obstacles: 30-40% = 12,000-16,000
 run_test "Grid_200x200_High_Obstacles" "phase3_200x200_high.csv" \
-    --batch-mode --algorithm all --grid-size 200 \
+    batch --algorithm all --grid-size 200 \
     --min-walls 2000 --max-walls 4000 --min-obstacles 12000 --max-obstacles 16000 \
-    --num-simulations 32 --timeout-seconds 5400 --output-file "phase3_200x200_high.csv" --quiet --no-visualization
+    --num-simulations 32 --timeout-seconds 5400 --output-file "phase3_200x200_high.csv" --quiet
 
 # 240x240 grid (57,600 squares) - Very high obstacles: 50-60% = 28,800-34,560
 run_test "Grid_240x240_Very_High_Obstacles" "phase3_240x240_veryhigh.csv" \
-    --batch-mode --algorithm all --grid-size 240 \
+    batch --algorithm all --grid-size 240 \
     --min-walls 5760 --max-walls 11520 --min-obstacles 28800 --max-obstacles 34560 \
-    --num-simulations 24 --timeout-seconds 5400 --output-file "phase3_240x240_veryhigh.csv" --quiet --no-visualization
+    --num-simulations 24 --timeout-seconds 5400 --output-file "phase3_240x240_veryhigh.csv" --quiet
 
 # PHASE 4: Extreme Stress Tests - ~1 hour
 update_progress 4 "Extreme Stress Tests with Ultra-High Density"
 
 # 150x150 grid (22,500 squares) - Extreme density: 50-60% = 11,250-13,500
 run_test "Stress_150x150_Extreme_Density" "phase4_stress_150x150.csv" \
-    --batch-mode --algorithm all --grid-size 150 \
+    batch --algorithm all --grid-size 150 \
     --min-walls 2250 --max-walls 3375 --min-obstacles 11250 --max-obstacles 13500 \
-    --num-simulations 60 --timeout-seconds 3600 --output-file "phase4_stress_150x150.csv" --quiet --no-visualization
+    --num-simulations 60 --timeout-seconds 3600 --output-file "phase4_stress_150x150.csv" --quiet
 
 # 180x180 grid (32,400 squares) - Extreme density: 50-60% = 16,200-19,440
 run_test "Stress_180x180_Extreme_Density" "phase4_stress_180x180.csv" \
-    --batch-mode --algorithm all --grid-size 180 \
+    batch --algorithm all --grid-size 180 \
     --min-walls 3240 --max-walls 4860 --min-obstacles 16200 --max-obstacles 19440 \
-    --num-simulations 40 --timeout-seconds 5400 --output-file "phase4_stress_180x180.csv" --quiet --no-visualization
+    --num-simulations 40 --timeout-seconds 5400 --output-file "phase4_stress_180x180.csv" --quiet
 
 # 220x220 grid (48,400 squares) - Ultra extreme density: 65% = 31,460 obstacles
 run_test "Stress_220x220_Ultra_Extreme" "phase4_stress_220x220.csv" \
-    --batch-mode --algorithm all --grid-size 220 \
+    batch --algorithm all --grid-size 220 \
     --min-walls 4840 --max-walls 7260 --min-obstacles 31460 --max-obstacles 31460 \
-    --num-simulations 30 --timeout-seconds 5400 --output-file "phase4_stress_220x220.csv" --quiet --no-visualization
+    --num-simulations 30 --timeout-seconds 5400 --output-file "phase4_stress_220x220.csv" --quiet
 
 # Algorithm-specific ultra-dense tests
 # D* Lite specific test - 160x160 (25,600 squares) - 70% density = 17,920 obstacles
 run_test "Stress_DStar_Ultra_Dense" "phase4_stress_dstar_ultra.csv" \
-    --batch-mode --algorithm d_star_lite --grid-size 160 \
+    batch --algorithm d_star_lite --grid-size 160 \
     --min-walls 2560 --max-walls 3840 --min-obstacles 17920 --max-obstacles 17920 \
-    --num-simulations 80 --timeout-seconds 3600 --output-file "phase4_stress_dstar_ultra.csv" --quiet --no-visualization
+    --num-simulations 80 --timeout-seconds 3600 --output-file "phase4_stress_dstar_ultra.csv" --quiet
 
 # A* specific test - 160x160 (25,600 squares) - 70% density = 17,920 obstacles
 run_test "Stress_AStar_Ultra_Dense" "phase4_stress_astar_ultra.csv" \
-    --batch-mode --algorithm a_star --grid-size 160 \
+    batch --algorithm a_star --grid-size 160 \
     --min-walls 2560 --max-walls 3840 --min-obstacles 17920 --max-obstacles 17920 \
-    --num-simulations 80 --timeout-seconds 3600 --output-file "phase4_stress_astar_ultra.csv" --quiet --no-visualization
+    --num-simulations 80 --timeout-seconds 3600 --output-file "phase4_stress_astar_ultra.csv" --quiet
 
 
 log "=== ALL PHASES COMPLETED ==="
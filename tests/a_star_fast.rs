@@ -0,0 +1,59 @@
+use dynamic_pathfinding::algorithms::a_star::AStar;
+use dynamic_pathfinding::algorithms::a_star_fast::AStarFast;
+use dynamic_pathfinding::algorithms::common::PathfindingAlgorithm;
+use dynamic_pathfinding::grid::{Cell, Grid, Position};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::collections::HashSet;
+
+/// A 4-connected `size` x `size` grid with `walls` walls scattered by `seed`, the
+/// start in the top-left quarter and the goal in the bottom-right one
+fn walled_grid(size: usize, walls: usize, seed: u64) -> Grid {
+    let mut rng = StdRng::seed_from_u64(seed);
+    let mut grid = Grid::empty(size, size, 1);
+    let mut corner = |range: std::ops::Range<usize>| Position {
+        x: rng.gen_range(range.clone()),
+        y: rng.gen_range(range),
+        z: 0,
+    };
+    grid.start = corner(0..size / 2);
+    grid.goal = corner(size / 2..size);
+    for _ in 0..walls {
+        let pos = Position {
+            x: rng.gen_range(0..size),
+            y: rng.gen_range(0..size),
+            z: 0,
+        };
+        if pos != grid.start && pos != grid.goal {
+            grid.set_cell(pos, Cell::Wall);
+        }
+    }
+    grid
+}
+
+#[test]
+fn expands_about_as_many_cells_as_a_star() {
+    // Random walls leave wide plateaus of equal f for the ties to spread over
+    for walls in [25_000, 50_000, 75_000] {
+        for seed in 0..3 {
+            let grid = walled_grid(500, walls, seed);
+            let obstacles = HashSet::new();
+            let mut a_star = AStar::new();
+            let mut fast = AStarFast::new();
+            let expected = a_star.find_path(&grid, grid.start, grid.goal, &obstacles);
+            let path = fast.find_path(&grid, grid.start, grid.goal, &obstacles);
+            assert_eq!(
+                path.map(|path| grid.path_cost(&path)),
+                expected.map(|path| grid.path_cost(&path))
+            );
+            let (fast, a_star) = (
+                fast.search_metrics().nodes_expanded,
+                a_star.search_metrics().nodes_expanded,
+            );
+            assert!(
+                fast <= 2 * a_star,
+                "{walls} walls, seed {seed}: a_star_fast expanded {fast} cells, a_star {a_star}"
+            );
+        }
+    }
+}
@@ -0,0 +1,92 @@
+use clap::Parser;
+use dynamic_pathfinding::batch_simulation::BatchSimulation;
+use dynamic_pathfinding::config::{Cli, Command};
+use std::path::{Path, PathBuf};
+
+/// Path of a scratch output file for a test, removed if it is left from a run before
+fn output_file(name: &str) -> PathBuf {
+    let path = std::env::temp_dir().join(format!(
+        "dynamic_pathfinding_{}_{}",
+        std::process::id(),
+        name
+    ));
+    let _ = std::fs::remove_file(&path);
+    path
+}
+
+/// A small seeded batch of two configurations, written to `path` in `format`, picking
+/// up the file there with `resume`
+fn batch(path: &Path, format: &str, resume: bool) -> BatchSimulation {
+    let path = path.to_str().expect("temporary path is not UTF-8");
+    let mut args = vec![
+        "dynamic_pathfinding",
+        "batch",
+        "--grid-size",
+        "12",
+        "--algorithm",
+        "a_star",
+        "--seed",
+        "7",
+        "--num-simulations",
+        "4",
+        "--min-walls",
+        "5",
+        "--max-walls",
+        "6",
+        "--min-obstacles",
+        "3",
+        "--max-obstacles",
+        "3",
+        "--output-format",
+        format,
+        "--output-file",
+        path,
+        "--quiet",
+    ];
+    if resume {
+        args.push("--resume");
+    }
+    let Command::Batch(args) = Cli::parse_from(args).command else {
+        unreachable!("not a batch command");
+    };
+    BatchSimulation::new(args.config, args.options)
+}
+
+/// Run the batch through, then again from its file cut off partway: the resumed batch
+/// keeps the results it found and writes the rest, and its summary counts both
+fn check_resume(format: &str) {
+    let path = output_file(&format!("resume.{}", format));
+    let mut whole = batch(&path, format, false);
+    whole.run().expect("batch failed");
+    let written = std::fs::read_to_string(&path).expect("no output file");
+    let totals = whole.summary().totals();
+    assert_eq!(totals.len(), 1);
+    assert_eq!(totals[0].1, 8, "two configurations of four simulations");
+
+    // Interrupted partway through the sixth result
+    let lines: Vec<&str> = written.split_inclusive('\n').collect();
+    let header = usize::from(format == "csv");
+    let mut cut: String = lines[..header + 5].concat();
+    cut += &lines[header + 5][..10];
+    std::fs::write(&path, cut).expect("cannot write output file");
+
+    let mut resumed = batch(&path, format, true);
+    resumed.run().expect("resumed batch failed");
+    let rewritten = std::fs::read_to_string(&path).expect("no output file");
+    let relines: Vec<&str> = rewritten.split_inclusive('\n').collect();
+    assert_eq!(relines.len(), lines.len());
+    // The last simulation found is run again, in case it was cut off too
+    assert_eq!(relines[..header + 4], lines[..header + 4]);
+    assert_eq!(resumed.summary().totals(), totals);
+    let _ = std::fs::remove_file(&path);
+}
+
+#[test]
+fn resumed_csv_batch_summarizes_every_result() {
+    check_resume("csv");
+}
+
+#[test]
+fn resumed_jsonl_batch_summarizes_every_result() {
+    check_resume("jsonl");
+}
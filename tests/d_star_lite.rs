@@ -0,0 +1,172 @@
+use dynamic_pathfinding::algorithms::a_star::AStar;
+use dynamic_pathfinding::algorithms::common::{EnvironmentChanges, PathfindingAlgorithm};
+use dynamic_pathfinding::algorithms::d_star_lite::DStarLite;
+use dynamic_pathfinding::grid::{Cell, Grid, Position};
+use std::collections::HashSet;
+
+const SIZES: [usize; 7] = [10, 25, 50, 51, 100, 250, 500];
+
+/// A `width` x `height` grid with a wall down every fourth column, each with a gap
+/// at alternating ends, so paths wind across the whole grid
+fn winding_grid(width: usize, height: usize) -> Grid {
+    let mut grid = Grid::empty(width, height, 1);
+    for x in (2..width - 1).step_by(4) {
+        let gap = if x % 8 == 2 { height - 1 } else { 0 };
+        for y in (0..height).filter(|&y| y != gap) {
+            grid.set_cell(Position { x, y, z: 0 }, Cell::Wall);
+        }
+    }
+    grid.goal = Position {
+        x: width - 1,
+        y: height - 1,
+        z: 0,
+    };
+    grid
+}
+
+/// Check `path` is a way from the grid's start to its goal as cheap as A*'s
+fn assert_optimal(grid: &Grid, obstacles: &HashSet<Position>, path: Option<Vec<Position>>) {
+    let path = path.expect("D* Lite found no path");
+    let a_star = AStar::new()
+        .find_path(grid, grid.start, grid.goal, obstacles)
+        .expect("A* found no path");
+    assert_eq!(path.first(), Some(&grid.start));
+    assert_eq!(path.last(), Some(&grid.goal));
+    for step in path.windows(2) {
+        assert!(grid.can_move(step[0], step[1], |pos| obstacles.contains(&pos)));
+    }
+    assert_eq!(grid.path_cost(&path), grid.path_cost(&a_star));
+}
+
+#[test]
+fn plans_on_every_size() {
+    for size in SIZES {
+        let grid = winding_grid(size, size);
+        let obstacles = HashSet::new();
+        let mut d_star = DStarLite::new(grid.start, grid.goal, size, size);
+        let path = d_star.find_path(&grid, grid.start, grid.goal, &obstacles);
+        assert_optimal(&grid, &obstacles, path);
+    }
+}
+
+#[test]
+fn sizes_itself_on_first_use() {
+    for size in SIZES {
+        let grid = winding_grid(size, size);
+        let obstacles = HashSet::new();
+        // Sized for a grid far smaller than the one it is given
+        let mut d_star = DStarLite::new(grid.start, grid.goal, 2, 2);
+        let path = d_star.find_path(&grid, grid.start, grid.goal, &obstacles);
+        assert_optimal(&grid, &obstacles, path);
+    }
+}
+
+#[test]
+fn replans_after_obstacles_change() {
+    for size in SIZES {
+        let grid = winding_grid(size, size);
+        let mut obstacles = HashSet::new();
+        let mut d_star = DStarLite::new(grid.start, grid.goal, size, size);
+        let path = d_star
+            .find_path(&grid, grid.start, grid.goal, &obstacles)
+            .expect("D* Lite found no path");
+
+        // Block the middle of the path and move the start a few steps along it
+        let blocked = path[path.len() / 2];
+        obstacles.insert(blocked);
+        d_star.update_environment(&grid, &obstacles, &EnvironmentChanges::default());
+        let mut moved = grid.clone();
+        moved.start = path[3.min(path.len() / 2 - 1)];
+        let path = d_star.find_path(&moved, moved.start, moved.goal, &obstacles);
+        assert_optimal(&moved, &obstacles, path);
+    }
+}
+
+#[test]
+fn moves_between_grids_of_any_size() {
+    // The goal stays put, so only the grid tells the planner to start over
+    let goal = Position { x: 9, y: 9, z: 0 };
+    let mut d_star = DStarLite::new(goal, goal, 10, 10);
+    let obstacles = HashSet::new();
+    // Growing, shrinking and back
+    let shapes = [(10, 10), (500, 500), (50, 50), (10, 10), (51, 51)];
+    for (width, height) in shapes {
+        let mut grid = winding_grid(width, height);
+        grid.goal = goal;
+        let path = d_star.find_path(&grid, grid.start, goal, &obstacles);
+        assert_optimal(&grid, &obstacles, path);
+    }
+}
+
+#[test]
+fn starts_over_on_a_grid_of_another_shape() {
+    // The same number of cells, start and goal, but rows of another width
+    let goal = Position { x: 9, y: 9, z: 0 };
+    let mut wide = winding_grid(40, 10);
+    let mut tall = winding_grid(10, 40);
+    wide.goal = goal;
+    tall.goal = goal;
+    let obstacles = HashSet::new();
+    let mut d_star = DStarLite::new(wide.start, goal, 40, 10);
+    for grid in [&wide, &tall, &wide] {
+        let path = d_star.find_path(grid, grid.start, goal, &obstacles);
+        assert_optimal(grid, &obstacles, path);
+    }
+}
+
+#[test]
+fn starts_over_on_a_grid_with_other_walls() {
+    // The same shape, start and goal, but the gaps in the walls at the other ends
+    let winding = winding_grid(30, 30);
+    let mut flipped = Grid::empty(30, 30, 1);
+    flipped.goal = winding.goal;
+    for x in (2..29).step_by(4) {
+        let gap = if x % 8 == 2 { 0 } else { 29 };
+        for y in (0..30).filter(|&y| y != gap) {
+            flipped.set_cell(Position { x, y, z: 0 }, Cell::Wall);
+        }
+    }
+    let mut open = Grid::empty(30, 30, 1);
+    open.goal = winding.goal;
+    let obstacles = HashSet::new();
+    let mut d_star = DStarLite::new(winding.start, winding.goal, 30, 30);
+    for grid in [&winding, &flipped, &open, &winding] {
+        let path = d_star.find_path(grid, grid.start, grid.goal, &obstacles);
+        assert_optimal(grid, &obstacles, path);
+    }
+}
+
+#[test]
+fn repairs_its_search_around_a_reported_wall() {
+    let mut grid = Grid::empty(200, 200, 1);
+    grid.goal = Position {
+        x: 199,
+        y: 199,
+        z: 0,
+    };
+    let obstacles = HashSet::new();
+    let mut d_star = DStarLite::new(grid.start, grid.goal, 200, 200);
+    let path = d_star
+        .find_path(&grid, grid.start, grid.goal, &obstacles)
+        .expect("D* Lite found no path");
+
+    // A wall across the path near the goal, reported as the grid's walls changing
+    let wall = path[path.len() - 3];
+    grid.set_cell(wall, Cell::Wall);
+    d_star.update_environment(&grid, &obstacles, &EnvironmentChanges::walls(&[wall]));
+    let before = d_star.search_metrics().nodes_expanded;
+    let path = d_star.find_path(&grid, grid.start, grid.goal, &obstacles);
+    let repaired = d_star.search_metrics().nodes_expanded - before;
+    assert_optimal(&grid, &obstacles, path);
+
+    let mut fresh = DStarLite::new(grid.start, grid.goal, 200, 200);
+    let path = fresh.find_path(&grid, grid.start, grid.goal, &obstacles);
+    assert_optimal(&grid, &obstacles, path);
+    let searched = fresh.search_metrics().nodes_expanded;
+    assert!(
+        repaired * 20 < searched,
+        "repairing expanded {} nodes, a fresh search {}",
+        repaired,
+        searched
+    );
+}
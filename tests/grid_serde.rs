@@ -0,0 +1,31 @@
+#![cfg(feature = "serde")]
+
+use dynamic_pathfinding::grid::{Cell, Grid, Position};
+
+#[test]
+fn grid_round_trips_through_json() {
+    let mut grid = Grid::empty(4, 3, 2);
+    let wall = Position { x: 3, y: 2, z: 1 };
+    grid.set_cell(wall, Cell::Wall);
+    let json = serde_json::to_string(&grid).expect("grid did not serialize");
+    let read: Grid = serde_json::from_str(&json).expect("grid did not deserialize");
+    assert!(read.cells() == grid.cells());
+    assert!(read.is_wall(wall));
+}
+
+#[test]
+fn grid_with_cells_missing_or_left_over_is_refused() {
+    let grid = Grid::empty(4, 3, 2);
+    let mut json: serde_json::Value = serde_json::to_value(&grid).expect("grid did not serialize");
+    for count in [23, 25] {
+        json["cells"] = serde_json::to_value(vec![Cell::Empty; count]).unwrap();
+        let error = serde_json::from_value::<Grid>(json.clone())
+            .err()
+            .expect("the cells do not fit the grid");
+        assert!(
+            error.to_string().contains("4x3 cells on 2 floors"),
+            "{}",
+            error
+        );
+    }
+}
@@ -0,0 +1,27 @@
+use dynamic_pathfinding::algorithms::common::ObstacleLifetimes;
+use dynamic_pathfinding::grid::{Grid, Position};
+use std::collections::HashMap;
+
+fn at(x: usize, y: usize) -> Position {
+    Position { x, y, z: 0 }
+}
+
+#[test]
+fn waits_for_nothing_on_an_empty_path() {
+    let grid = Grid::empty(5, 5, 1);
+    let mut lifetimes = ObstacleLifetimes::default();
+    lifetimes.update(&HashMap::from([(at(1, 0), 2)]));
+    assert!(lifetimes.wait_for(&grid, &[]).is_empty());
+}
+
+#[test]
+fn waits_in_place_until_an_obstacle_in_the_way_is_gone() {
+    let grid = Grid::empty(5, 5, 1);
+    let mut lifetimes = ObstacleLifetimes::default();
+    lifetimes.update(&HashMap::from([(at(1, 0), 2)]));
+    let path = [at(0, 0), at(1, 0), at(2, 0)];
+    assert_eq!(
+        lifetimes.wait_for(&grid, &path),
+        [at(0, 0), at(0, 0), at(0, 0), at(1, 0), at(2, 0)]
+    );
+}
@@ -0,0 +1,77 @@
+use dynamic_pathfinding::algorithms::common::{
+    AbortHandle, ObstacleSchedule, PathfindingAlgorithm,
+};
+use dynamic_pathfinding::algorithms::space_time_a_star::SpaceTimeAStar;
+use dynamic_pathfinding::grid::{Grid, Position};
+use std::collections::HashSet;
+use std::sync::Arc;
+
+fn cell(x: usize, y: usize) -> Position {
+    Position { x, y, z: 0 }
+}
+
+#[test]
+fn waits_for_a_scheduled_obstacle_to_go() {
+    // A one-cell-wide corridor, blocked in the middle for the first four steps
+    let mut grid = Grid::empty(5, 1, 1);
+    grid.goal = cell(4, 0);
+    let mut schedule = ObstacleSchedule::new();
+    schedule.add([cell(2, 0)], 0, 4);
+    let mut oracle = SpaceTimeAStar::new();
+    oracle.update_obstacle_schedule(&Arc::new(schedule), 0);
+
+    let path = oracle
+        .find_path(&grid, grid.start, grid.goal, &HashSet::new())
+        .expect("no path past the obstacle");
+    assert_eq!(path.len(), 7);
+    assert_eq!(path.last(), Some(&grid.goal));
+    assert!(path.iter().position(|&pos| pos == cell(2, 0)) >= Some(4));
+}
+
+#[test]
+fn schedules_overlapping_stays_as_one() {
+    let mut schedule = ObstacleSchedule::new();
+    schedule.add([cell(1, 1)], 0, 5);
+    schedule.add([cell(1, 1)], 3, 5);
+    schedule.add([cell(1, 1)], 10, 1);
+    let blocked: Vec<usize> = (0..12)
+        .filter(|&step| schedule.is_blocked(cell(1, 1), step))
+        .collect();
+    assert_eq!(blocked, [0, 1, 2, 3, 4, 5, 6, 7, 10]);
+    assert_eq!(schedule.end(), 11);
+}
+
+#[test]
+fn gives_up_on_a_long_timeline_within_its_state_limit() {
+    // The goal stays blocked for a million steps, far more states than the limit
+    let mut grid = Grid::empty(200, 200, 1);
+    grid.goal = cell(199, 199);
+    let mut schedule = ObstacleSchedule::new();
+    schedule.add([grid.goal], 0, 1_000_000);
+    let state_limit = 50_000;
+    let mut oracle = SpaceTimeAStar::new().with_state_limit(state_limit);
+    oracle.update_obstacle_schedule(&Arc::new(schedule), 0);
+
+    let path = oracle.find_path(&grid, grid.start, grid.goal, &HashSet::new());
+    assert_eq!(path, None);
+    let metrics = oracle.search_metrics();
+    assert!(metrics.nodes_expanded <= state_limit);
+    assert!(metrics.max_open_list <= state_limit);
+}
+
+#[test]
+fn gives_up_when_aborted() {
+    let mut grid = Grid::empty(200, 200, 1);
+    grid.goal = cell(199, 199);
+    let mut schedule = ObstacleSchedule::new();
+    schedule.add([grid.goal], 0, 1_000_000);
+    let abort = AbortHandle::new();
+    abort.abort();
+    let mut oracle = SpaceTimeAStar::new();
+    oracle.set_abort_handle(abort);
+    oracle.update_obstacle_schedule(&Arc::new(schedule), 0);
+
+    let path = oracle.find_path(&grid, grid.start, grid.goal, &HashSet::new());
+    assert_eq!(path, None);
+    assert_eq!(oracle.search_metrics().nodes_expanded, 0);
+}